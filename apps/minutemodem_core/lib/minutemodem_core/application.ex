@@ -3,6 +3,12 @@ defmodule MinuteModemCore.Application do
 
   @impl true
   def start(_type, _args) do
+    with :ok <- self_test() do
+      start_supervisor()
+    end
+  end
+
+  defp start_supervisor do
     # Start pg scope for pubsub
     :pg.start_link(:minutemodem_pg)
 
@@ -38,4 +44,17 @@ defmodule MinuteModemCore.Application do
       name: MinuteModemCore.Supervisor
     )
   end
+
+  # Refuse to start on a phy_modem build that computes garbage. Set
+  # config :minutemodem_core, nif_self_test: false to skip the check.
+  defp self_test do
+    if Application.get_env(:minutemodem_core, :nif_self_test, true) do
+      case MinuteModemCore.DSP.PhyModem.self_test() do
+        {:ok, _report} -> :ok
+        {:error, failures} -> {:error, {:nif_self_test_failed, failures}}
+      end
+    else
+      :ok
+    end
+  end
 end
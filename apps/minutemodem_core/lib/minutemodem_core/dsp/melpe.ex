@@ -18,6 +18,50 @@ defmodule MinuteModemCore.DSP.Melpe do
 
       dec = MinuteModemCore.DSP.Melpe.decoder_new()
       audio = MinuteModemCore.DSP.Melpe.decode(dec, bitstream)
+
+  ## Binary PCM
+
+  `encode_binary/2` and `decode_binary/2` take and return a superframe
+  as a binary of 16-bit signed little-endian PCM (1080 bytes) and the
+  bitstream as a 6-byte binary, which saves building and decoding a
+  540-element list per superframe. A sample `s` encodes as `s / 32768`
+  would in `encode/2`, to the same bitstream; decoded audio is rounded
+  and saturated to 16 bits.
+
+      bitstream = MinuteModemCore.DSP.Melpe.encode_binary(enc, pcm_1080_bytes)
+      pcm = MinuteModemCore.DSP.Melpe.decode_binary(dec, bitstream)
+
+  ## Warm start
+
+  A fresh decoder's first superframe starts with ~6 ms of silence and a
+  ramp while its synthesis filter fills, which is a large part of a short
+  PTT transmission. `decoder_prime/2` decodes a superframe without
+  returning audio; priming with the first superframe and then decoding it
+  as usual makes the first returned superframe as clean as the steady-state
+  ones. `encoder_prime/2` likewise runs PCM history (any length) through
+  the encoder's analysis so it does not start from rest.
+
+      :ok = MinuteModemCore.DSP.Melpe.decoder_prime(dec, first_bitstream)
+      audio = MinuteModemCore.DSP.Melpe.decode(dec, first_bitstream)
+
+  ## Panics
+
+  A call that panics in the codec returns `{:error, {:panic, message}}`
+  (message cut to 256 bytes). The encoder or decoder is reset by the next
+  call on it, which then proceeds, so there is no need to recreate it.
+
+  ## Census
+
+  `census/0` counts the encoders and decoders alive, created and dropped
+  since the library loaded, and the bytes the live ones hold:
+
+      %{encoders: %{live: 2, created: 40, dropped: 38, bytes: _},
+        decoders: %{live: 2, created: 40, dropped: 38, bytes: _}} =
+        MinuteModemCore.DSP.Melpe.census()
+
+  A resource is dropped once the VM collects it, so `live` lags the
+  processes that held it until they've been garbage collected. Over a
+  long run `live` and `bytes` should settle rather than climb.
   """
 
   use Rustler,
@@ -30,6 +74,8 @@ defmodule MinuteModemCore.DSP.Melpe do
 
   def encoder_new(), do: :erlang.nif_error(:nif_not_loaded)
   def encode(_encoder, _samples), do: :erlang.nif_error(:nif_not_loaded)
+  def encode_binary(_encoder, _pcm), do: :erlang.nif_error(:nif_not_loaded)
+  def encoder_prime(_encoder, _history), do: :erlang.nif_error(:nif_not_loaded)
   def encoder_reset(_encoder), do: :erlang.nif_error(:nif_not_loaded)
 
   # ============================================================================
@@ -38,6 +84,8 @@ defmodule MinuteModemCore.DSP.Melpe do
 
   def decoder_new(), do: :erlang.nif_error(:nif_not_loaded)
   def decode(_decoder, _bitstream), do: :erlang.nif_error(:nif_not_loaded)
+  def decode_binary(_decoder, _bitstream), do: :erlang.nif_error(:nif_not_loaded)
+  def decoder_prime(_decoder, _bitstream), do: :erlang.nif_error(:nif_not_loaded)
   def decoder_reset(_decoder), do: :erlang.nif_error(:nif_not_loaded)
 
   # ============================================================================
@@ -45,4 +93,5 @@ defmodule MinuteModemCore.DSP.Melpe do
   # ============================================================================
 
   def codec_info(), do: :erlang.nif_error(:nif_not_loaded)
+  def census(), do: :erlang.nif_error(:nif_not_loaded)
 end
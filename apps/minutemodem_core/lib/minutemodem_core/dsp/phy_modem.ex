@@ -1,6 +1,568 @@
 defmodule MinuteModemCore.DSP.PhyModem do
   @moduledoc """
   Software-defined HF modem for MIL-STD-188-110D.
+
+  ## Errors
+
+  Every NIF reports failure as `{:error, reason}`, where `reason` is one of:
+
+    * `{:invalid_argument, which}` - `which` names the offending argument
+      or option, e.g. `:sample_rate`, `:carrier_freq`, `:ff_taps`, `:mu`,
+      `:dfe_config`, `:iq`, `:known_symbols`, `:rx_filter`, `:length`,
+      `:persistence`, `:colormap`, `:oversample`, `:pulse`, `:bt`, `:gap`,
+      `:symbol`, `:corrections`, `:hold_symbols`, `:evm_ceiling`, `:sps`,
+      `:alpha`, `:span`, `:impulses`, `:modulation`, `:training`, `:eot`,
+      `:polynomial`, `:stage_timing`, `:timing_tracking`, `:symbol_map`,
+      `:rotation_deg`, `:ramp_ms`, `:hops`, `:format`, `:scatter_points`
+    * `{:out_of_range, which, max}` - a number that sizes an allocation
+      is above `max`: `:sample_rate` (192_000), `:samples_per_symbol`
+      (sample rate over symbol rate, 512), `:ff_taps` and `:fb_taps`
+      (256), `:n` of `unified_mod_pull_samples/2` (480_000),
+      `:average_symbols` (4096), the pulse shaper's `:sps` (64) and
+      `:span` (32), the scope's `:width` and `:height` (2048), the
+      `:count` of `prbs_symbols/4` (8_388_608), `:scatter_points` (65_536)
+    * `:unsupported_constellation` - modulation atom not recognised
+    * `{:panic, message}` - the call panicked, which is a bug; `message`
+      is the panic message cut to 256 bytes. Calls on a resource carry on
+      after one: the next call resets it (as the resource's reset
+      function would) and proceeds
+    * `{:incompatible_state, :no_equalizer}` - equalizer call on a
+      demodulator created without one
+    * `{:incompatible_state, :no_stage_timing}` - stage timings asked of a
+      demodulator not timing its stages
+    * `{:incompatible_state, :no_probe_tracking}` - probe metrics drained
+      from a demodulator not tracking probes
+    * `{:numeric_fault, :equalizer_diverged}` - equalizer MSE went non-finite
+    * `:alloc_failed` - the result binary could not be allocated
+
+  Calls that used to return `:ok` unconditionally (the reset functions,
+  `unified_demod_set_training/2`, the equalizer toggles and
+  `constellation_scope_clear/1`) return these errors too.
+
+  ## Input level warnings
+
+  The demodulators take i16 PCM. A nonzero block peaking below 64 counts
+  is most likely ±1.0 float audio rounded into i16; it is still
+  demodulated, but `{:input_warning, :suspicious_input_level, demodulator,
+  peak}` goes to the pid registered with `set_warning_logger/1`.
+  `demod_set_input_warnings/2` and `unified_demod_set_input_warnings/2`
+  turn this off per demodulator.
+
+  ## Pulse shapes
+
+  `unified_mod_new/3` and `unified_demod_new/3` take an opts map (or
+  keyword list) selecting the pulse shape; both ends must agree.
+
+    * `pulse: :rrc` - root raised cosine at both ends (default)
+    * `pulse: :rc` - full raised cosine at the transmitter, no matched
+      filter at the receiver
+    * `pulse: :gaussian` - Gaussian pulse with `bt:` (0.1..1.0, default
+      0.3), matched at the receiver; not Nyquist, so expect ISI
+
+  ## Burst ramps
+
+  With `ramp_ms:` (0..100, default 0) in the `unified_mod_new/3` opts,
+  the modulator shapes each burst's first and last samples with a
+  raised-cosine amplitude ramp instead of keying at full amplitude.
+  `unified_mod_end_burst/1` emits the queued symbols and the pulse tail
+  and ramps it down; `unified_mod_flush/1` ramps down its padding. Either
+  ends the burst, as does `unified_mod_reset/1`, and the next sample
+  starts a new ramp up. `unified_mod_ramp_samples/1` gives the samples
+  each ramp covers: `ramp_ms` rounded, and at most the pulse tail the
+  burst ends emit (12 symbols for RRC). Only the first and last
+  `ceil(ramp_samples / samples_per_symbol)` symbols of a burst see the
+  ramps at the receiver.
+
+  ## Constellations
+
+  `symbol_to_iq/2`, `iq_to_symbol/3`, `constellation_points/1`,
+  `canonical_symbol/2`, `bits_per_symbol/1` and `order/1` are stateless
+  and need no resource: they expose the same mappings and slicer the
+  modem uses, for property tests and test-data generators. Symbols run
+  from 0 to `order - 1`.
+
+  The 32-QAM and 64-QAM tables give some points to two symbols, as in
+  MIL-STD-188-110D, so `constellation_points/1` can list a point twice
+  and `iq_to_symbol/3` on a duplicate's point returns the lowest symbol
+  with it. `canonical_symbol/2` gives that symbol, so for every symbol
+
+      {i, q} = symbol_to_iq(c, s)
+      iq_to_symbol(c, i, q) == canonical_symbol(c, s)
+
+  A symbol is its bit pattern, most significant bit first, duplicates
+  included. A decision on a duplicated point can't tell the bits its
+  symbols disagree on: `constellation_bit_map/1` lists each symbol's
+  point, canonical symbol, `bits` and that `ambiguous_bits` mask.
+  `bit_llrs(c, i, q, noise_var)` gives the exact log-likelihood ratio
+  ln(P(0) / P(1)) of each bit of a received point, most significant
+  first, for complex noise of variance `noise_var`; a duplicated point
+  counts for each of its symbols, so every bit's LLR is defined.
+  `bit_llrs_block(c, iq, noise_var)` does the same for an I/Q binary of
+  points and returns an LLR_F32_V1 binary, point by point.
+
+  ## Wire format
+
+  I/Q, symbol and LLR binaries carry a 12-byte header naming the format,
+  element width and count ahead of a little-endian payload:
+  IQ_F32_INTERLEAVED_V1, SYMBOLS_PACKED_V1 and LLR_F32_V1, decoded and
+  built by `MinuteModemCore.DSP.Wire`. Every I/Q binary below, in or out,
+  is IQ_F32_INTERLEAVED_V1; channel_physics' derotated output is too.
+  Until callers have moved over, I/Q input without a header is still
+  taken as the bare payload it used to be (interleaved f64-native for
+  the equalizer NIFs that take `iq`); a NIF built without the
+  `legacy-wire` feature rejects it.
+
+  ## Soft decisions as binaries
+
+  `unified_demod_symbols(demod, samples, binary: true)` returns
+  `%{symbols: binary, confidences: binary, iq: binary}`: the symbols as
+  SYMBOLS_PACKED_V1 at the constellation's bits per symbol, a bare f64-le
+  slicer confidence per symbol, and as IQ_F32_INTERLEAVED_V1 the point
+  each was sliced from (the equalizer's output, with one), aligned
+  through EOT truncation. The three are sub-binaries
+  of one allocation, far smaller than the lists `confidence: true`
+  returns and cheap to send between processes.
+
+  A sub-binary keeps its whole allocation alive: garbage collecting the
+  `iq` doesn't free it while the `symbols` are still held, and never
+  disturbs their bytes. Use `:binary.copy/1` on a field kept long after
+  the rest.
+
+  ## Externally clocked input
+
+  `unified_demod_at(demod, start_sample_index, samples)` (and `/4` with
+  opts) takes the capture pipeline's index of the block's first sample
+  and returns `{symbols, discontinuity}`. The demodulator counts the
+  samples it has demodulated since creation or reset. A matching index
+  gives `:none` and behaves like `unified_demod_symbols/2`. Otherwise:
+
+    * `{:gap, n}` - `n` samples were lost. Joining the blocks would slip
+      every later symbol (and the symbol timing, unless `n` is a whole
+      number of symbols) for good. With `gap: :zero_fill` (default) the
+      gap is demodulated as silence: it comes out as meaningless symbols,
+      and the symbols after it are good within the pulse span (6 symbols
+      for RRC). With `gap: :reacquire` the gap emits no symbols and the
+      timing is acquired afresh; symbols are good after twice the span.
+      Gaps over a second are always handled as `:reacquire`.
+    * `{:overlap, n}` - the block starts `n` samples before the count;
+      those samples are dropped.
+
+  ## Frequency pre-correction
+
+  `unified_demod_with_correction(demod, samples, corrections)` takes a
+  known carrier offset, e.g. from a Doppler or reference drift track, as
+  `[{sample_offset, freq_hz}]` breakpoints within the block. Offsets must
+  strictly increase and fall inside the block. The offset is linearly
+  interpolated between breakpoints, held before the first and after the
+  last, and added to the mixing NCO ahead of the PLL, which then only
+  tracks the residual.
+
+  ## Short bursts
+
+  A burst of a few dozen symbols, such as a 141D link-quality probe, is
+  mostly over before the streaming path's timing acquisition and PLL
+  have settled. `unified_demod_short_burst(demod, samples, known_prefix,
+  opts)` takes the whole burst with the symbols it starts with and
+  searches every start sample (so every timing phase), a grid of carrier
+  offsets, and the carrier phase and gain for the best match to the
+  prefix, then slices the rest of the burst with those held fixed. It
+  returns `%{symbols:, confidences:, start_sample:, freq_offset_hz:,
+  phase:, gain:, correlation:}`: the symbols after the prefix, and the
+  hypothesis found, `start_sample` being where the first prefix symbol's
+  strobe falls and `correlation` 1.0 for a perfect match. Options:
+
+    * `max_freq_offset_hz:` - offset searched either side (default 20.0)
+    * `freq_step_hz:` - grid spacing (default 2.0), refined between points
+    * `symbols:` - symbols in the burst, prefix included, at most 64
+      (default: as many as the samples hold)
+
+  The demodulator's state is left untouched, so the same samples can
+  still go through the streaming path. At 8 dB SNR and 10 Hz off, a
+  32-symbol QPSK burst with a 16-symbol prefix decodes cleanly this way
+  where the streaming path gets little of it.
+
+  ## Time-bounded demodulation
+
+  `unified_demod_step(demod, samples, budget_us)` demodulates for about
+  `budget_us` microseconds and returns `{consumed_samples, symbols, done}`,
+  for a caller sharing a core with an audio deadline. It checks the clock
+  every 256 samples (500 while acquiring timing) and always takes at
+  least that many, so a step can overrun its budget by one such window.
+  Until `done`, call it again with the samples not yet consumed: the
+  steps continue one call, and their symbols together are exactly those
+  of `unified_demod_symbols/2` over the whole input. Any other demodulate
+  call or a reset abandons an unfinished step.
+
+  ## Chunked input
+
+  Audio arriving in blocks (20 ms from the radio, say) can go to
+  `unified_demod_symbols/2` block by block: the filter warm-up, PLL,
+  timing and training carry across calls, so the symbols of all the
+  calls are exactly those of one call over the whole input, wherever it
+  was split. Timing is acquired once there are 500 samples; until then
+  the samples are held and the calls return nothing. At the end of a
+  burst shorter than that, `unified_demod_flush/1` demodulates what is
+  held, acquiring on what there is.
+
+  ## End of transmission
+
+  `unified_demod_enable_eot(demod, opts)` watches the short-term matched
+  filter power and EVM of the symbols the slicing calls emit. When both
+  collapse (power `power_drop_db` below its in-burst level, EVM above
+  `evm_ceiling`) for `hold_symbols` symbols, `unified_demod_signal_quality/1`
+  reports `eot_detected: true` and `eot_symbol`, the index (counted from
+  reset) of the first symbol past the end. A fade shorter than
+  `hold_symbols` doesn't trigger it, so set that above the longest fade
+  the link should ride through. With `truncate: true` no symbols from
+  `eot_symbol` on are emitted once it is declared, though those already
+  returned by earlier calls can't be taken back. Detection holds until
+  `unified_demod_reset/1`.
+
+  ## Timing tracking
+
+  The demodulator acquires the symbol timing once and then holds that
+  sample phase, which is fine until the two ends' sample clocks differ:
+  at 100 ppm the strobe walks a whole sample every 2500 symbols. With
+  `timing_tracking: true` in the `unified_demod_new/3` opts, or after
+  `unified_demod_enable_timing_tracking/1`, a Gardner timing error
+  detector on the mid-symbol and on-symbol matched filter samples steers
+  a proportional-integral loop that moves a fractional strobe, with the
+  samples linearly interpolated there. It absorbs clock offsets to beyond
+  ±100 ppm. `unified_demod_timing_offset_ppm/1` returns the loop's offset
+  estimate (positive when the receiver's clock is fast, nil with no
+  loop), and `unified_demod_disable_timing_tracking/1` goes back to the
+  held phase. `unified_demod_reset/1` clears the loop but keeps it on.
+
+  ## Stage timing
+
+  With `stage_timing: true` in the `unified_demod_new/3` opts, or after
+  `unified_demod_enable_stage_timing/1`, the demodulator times its stages
+  on every demodulate call. `unified_demod_stage_timings/1` returns
+  `%{last: times, cumulative: times, calls: n}`, where `times` maps
+  `:mix` (input scaling, IF filter model and mixing), `:filter` (matched
+  filter), `:pll` (timing and carrier recovery), `:equalizer`, `:slicer`
+  (slicing without an equalizer, and EOT detection) and `:total` (the
+  whole call) to microseconds. The stages add up to a little under
+  `:total`. Mixing, filtering and the PLL run interleaved per sample, so
+  their split comes from timing a sample of the samples. Timing costs a few
+  percent of each call and changes no output.
+  `unified_demod_disable_stage_timing/1` stops it and drops the timings.
+
+  ## Symbol maps
+
+  To interoperate with an implementation that numbers constellation
+  points differently, `unified_mod_set_symbol_map(modulator, map, opts)`
+  makes the modulator take input symbols in that numbering and
+  `unified_demod_set_symbol_map(demodulator, map, opts)` makes the
+  demodulator emit (and take training symbols) in it. `map` is a list
+  giving the other numbering's symbol for each of our symbols of
+  the current constellation, a permutation, or `nil`. For PSK,
+  `rotation_deg:` says the other numbering's symbol s sits that many
+  degrees counterclockwise of ours, in whole symbol steps; it applies
+  before `map`. A half-step offset (e.g. 22.5 degrees for 8PSK) needs no
+  map: carrier recovery absorbs it. `nil` with no rotation returns to the
+  native numbering. The slicer and equalizer always work on the native
+  constellation, and a map is kept for its constellation only: maps set
+  for one constellation pass other constellations' symbols through.
+
+  ## Frequency hopping
+
+  `unified_mod_set_hop_schedule(modulator, hops)` and
+  `unified_demod_set_hop_schedule(demodulator, hops)` take a list of
+  `{sample_offset, carrier_hz}` (offsets strictly increasing, carriers
+  between 0 and half the sample rate, as floats). Offsets count from the
+  next sample produced or demodulated; from each one on, the carrier is
+  that frequency. The oscillators keep their phase across a hop and the
+  filters and PLL carry on, so a burst hopped on the same schedule at
+  both ends (the receiver's offsets shifted by the path delay)
+  demodulates as if the carrier had stayed put. Keep the signal's
+  sidebands (1620 Hz each side at 2400 Bd) clear of 0 Hz and Nyquist.
+  The carrier stays on the last hop until `unified_mod_reset/1` or
+  `unified_demod_reset/1`, which drop the schedule and return to the
+  configured carrier; set a schedule per burst.
+
+  ## QAM gain reference
+
+  A fade or AGC step scales a QAM constellation and the slicer maps outer
+  points inward. `unified_demod_set_gain_reference(demodulator,
+  {period, offset, probe})` tracks the received gain from the known
+  8-PSK probes: `probe` is the probe's symbol list, and one starts every
+  `period` symbols from output symbol `offset` (counted from reset, like
+  `eot_symbol`). At each probe's end the estimate is refreshed, and every
+  symbol is divided by its magnitude ahead of the equalizer, so a step is
+  corrected from the end of the next probe on. The carrier phase is left
+  to the PLL. `unified_demod_signal_quality/1` reports the estimate as
+  `gain_db` and `gain_phase_deg` (the PLL's residual at the probe), nil
+  while the reference is off. `nil` turns it off; `unified_demod_reset/1`
+  starts the estimate over.
+
+  ## Probe tracking
+
+  Over a long transmission a sound-card clock a few tens of ppm off walks
+  the symbol strobes off the eye. `unified_demod_set_probe_tracking(demodulator,
+  {period, offset, probe})`, with the schedule as for the gain reference,
+  correlates each known probe at the strobe and a sample either side.
+  The correlation peak gives the residual timing error and the on-strobe
+  correlation the residual carrier phase; both are fed back into the
+  timing loop and the PLL, weighted by the square of the normalized
+  correlation so that faded probes count for little (nothing below 0.5).
+  The Gardner detector stays off unless timing tracking was also enabled.
+  `unified_demod_drain_probe_metrics/1` returns
+  `%{metrics: [...], clock_skew_ppm: ppm}`, where each metric is `%{symbol:,
+  correlation:, timing_error:, phase_error:, weight:}` (timing in symbols,
+  phase in radians) and the skew is fitted over every probe since reset,
+  positive when the receiver's clock is fast and nil before two probes.
+  `nil` turns tracking off; `unified_demod_reset/1` starts the estimate
+  and the log over.
+
+  ## Sideband detection
+
+  A transmitter on one sideband heard by a receiver on the other (LSB
+  against USB) delivers the burst with its spectrum mirrored about the
+  carrier, every 8-PSK point reflected in the I axis, which no carrier
+  phase undoes. `unified_demod_set_sideband_detection(demodulator, true)`
+  stretches each burst's acquisition window over the next probe of the
+  probe tracking or gain reference schedule and correlates its probes
+  with the received points both as they are and conjugated; if the
+  conjugate wins, the burst is demodulated conjugated. The decode report
+  gives the verdict as `acquisition.sideband_inverted` (nil with detection
+  off or no schedule) and warns `:inverted_sideband`. The verdict lasts
+  until `unified_demod_reset/1`; `false` turns detection off.
+
+  ## Clipped input
+
+  An ADC overloaded by a static crash leaves runs of samples at full
+  scale, which the receive filter smears over the symbols around them.
+  The demodulator counts samples at the i16 rails as clipped; both
+  `unified_demod_signal_quality/1` and the decode report give the burst's
+  `clip_fraction`. `unified_demod_set_clip_handling(demodulator, opts)`
+  takes `threshold:` (1.0), the fraction of full scale from which a
+  sample counts as clipped, for audio that clipped before it became i16
+  (a float capture limited at its own level), and `declip:` (false),
+  which replaces each clipped run with a cubic through the two good
+  samples either side of it before demodulation. Interpolation needs the
+  burst well oversampled: at 48 kHz it recovers bursts that 1% clipping
+  otherwise ruins, while at 9600 Hz it can do more harm than good. A run
+  still open at the end of a demodulate call is left clipped.
+
+  ## Baseband capture
+
+  For post-mortems of failed bursts,
+  `unified_demod_enable_capture(demodulator, decimation, max_samples)`
+  records the matched filter output (full-rate complex baseband, ahead of
+  timing selection and the equalizer) every `decimation` samples into a
+  buffer of at most `max_samples` points, allocated up front; points
+  past it are dropped and counted. Every demodulate call adds to the
+  recording. At the end of a burst call `unified_demod_keep_capture/1`
+  if it failed, which stops recording so later bursts leave it alone, or
+  `unified_demod_discard_capture/1` if it didn't, which starts afresh.
+  `unified_demod_drain_capture/1` returns `%{iq: binary, decimation:,
+  start_sample:, sps:, sample_rate:, carrier_freq:, dropped:,
+  provenance:}`, `iq` an I/Q binary, `start_sample` the input sample index
+  (counted from reset) of its first point and `provenance` as
+  `provenance/0`, and starts afresh.
+  `unified_demod_reset/1` drops a recording not kept.
+
+  ## Equalizer taps and scatter
+
+  For live plots, `unified_demod_eq_taps/1` returns the equalizer's
+  coefficients as `%{ff: feedforward, fb: feedback}`, each a list of
+  `{re, im}` (`{:error, {:incompatible_state, :no_equalizer}}` without
+  one; a fractionally-spaced equalizer's feedforward is T/2-spaced), and
+  `unified_demod_scatter/1` the last points the slicer saw as `{i, q}`,
+  oldest first: the equalizer's output, or the matched filter's without
+  one. The demodulator keeps 256 of them in a ring as it slices, so a
+  fetch copies only those; `scatter_points:` in the `unified_demod_new/3`
+  opts or `unified_demod_set_scatter_points(demodulator, n)` sets how
+  many (0 for none, at most 65536). `unified_demod_reset/1` empties the
+  ring but keeps its length.
+
+  ## Decode reports
+
+  `unified_demod_decode_report(demodulator)` snapshots the burst the
+  demodulator is in, from its last timing acquisition (the first call
+  after reset, or the block after a `:reacquire` gap), as one nested map:
+
+    * `acquisition:` - `%{start_sample:, timing_phase:, coarse_freq_hz:,
+      sideband_inverted:}`, the PLL frequency at the end of the
+      acquisition window and the sideband detection verdict (nil until
+      acquired)
+    * `samples:` - input samples since
+    * `clip_fraction:` - the fraction of them that were clipped
+    * `pll:` - `%{updates:, mean_abs_error:, max_abs_error:, freq_hz:}`,
+      phase errors in radians
+    * `equalizer:` - `%{mode_timeline: [{symbol, :cma | :dd}], mse_curve:
+      [{symbol, mse}], mse_stride:, mse:, taps_digest:}` (nil without
+      one); the curve keeps at most 128 points, doubling `mse_stride`
+      whenever it fills, and `taps_digest` is a 64-bit hash of the final
+      taps
+    * `symbols:` - `%{iq:, sliced:, trained:, emitted:}`
+    * `confidence_histogram:` - 10 bins of slicer confidence over 0..1
+    * `snr_db:`, `evm_percent:` - sliced points against their decisions
+      (after the equalizer if there is one; nil before any)
+    * `eot_symbol:` - as in `unified_demod_signal_quality/1`
+    * `warnings:` - any of `:suspicious_input_level`, `:clipped_input`,
+      `:pll_at_limit`, `:equalizer_not_converged`, `:inverted_sideband`
+    * `fingerprint:` - as `unified_demod_config_fingerprint/1`
+    * `provenance:` - as `provenance/0`
+
+  The histogram and quality figures leave out the filter warm-up at the
+  start of the burst. `unified_demod_decode_report(demodulator, :cbor)`
+  returns the same report as a CBOR binary (RFC 8949) for archival, with
+  atoms as text and tuples as arrays. Take the report before
+  `unified_demod_reset/1`, which empties it.
+
+  ## Waveform presets
+
+  `waveform_preset(name)` describes a 110D data rate and interleaver at
+  3 kHz, named `:m110d_<bps>_<interleaver>` (e.g. `:m110d_2400_short`,
+  75 to 7200 bps): the data constellation, U and K, the 8-PSK mini-probe
+  (and its boundary-marked form), the demodulator's `probe_schedule`,
+  `dfe` settings and `pll_bandwidth_hz`, plus the code rate, constraint
+  length and interleaver geometry for the Elixir codec and interleaver.
+  `ser_budget` is the raw symbol error rate the pair holds to on a CCIR
+  moderate channel at `ser_snr_db`. `unified_mod_for_waveform(name,
+  sample_rate)` and `unified_demod_for_waveform(name, sample_rate)` build
+  the matched pair: send the probes through
+  `unified_mod_modulate_mixed/2` as `:psk8`, and start (or reset) the
+  demodulator at the initial mini-probe, from which it trains its
+  equalizer on every probe. An unknown name fails with
+  `{:invalid_argument, :waveform}`.
+
+  ## Walsh-16 correlation
+
+  `walsh_correlate(iq)` correlates descrambled symbol-rate I/Q (a list of
+  `{i, q}` or an I/Q binary, as for `dfe_train/3`)
+  against the Deep WALE Walsh-16 bank (Table G-IX, see
+  `MinuteModemCore.ALE.Waveform.Walsh.walsh_16/1`), one 64-symbol dwell
+  at a time, and returns `%{best:, metric:, metrics:}` per whole dwell.
+  The metrics are noncoherent, |correlation|² over 64 × the dwell's
+  energy: 1 for a clean dwell of the sequence, 0 for the others, and at
+  most 1 between all 16, so they can go straight to a soft decoder.
+  `walsh_correlator_new/0` and `walsh_correlator_push/2` do the same for
+  I/Q arriving in blocks of any length, holding an unfinished dwell for
+  the next push; `walsh_correlator_reset/1` drops it.
+
+  ## 75 bps Walsh mode
+
+  `unified_mod_walsh(modulator, dibits)` sends each dibit (0-3) as its
+  32-chip Walsh sequence on 8-PSK: 0000, 0404, 0044 or 0440, eight times
+  over, whatever the modulator's constellation. On the receive side
+  `unified_demod_walsh(demodulator, samples)` correlates the matched
+  filter's I/Q against the four sequences 32 chips at a time, keeping
+  the soft I/Q that slicing the chips first would throw away (about
+  2 dB), and returns `%{dibit:, magnitudes:}` per completed symbol, the
+  magnitudes by dibit and 1.0 for a clean symbol of its own. Symbols
+  count from the first chip after creation or reset; once preamble sync
+  has found the boundary, `unified_demod_align_walsh(demodulator, chips)`
+  starts the next symbol `chips` chips on.
+
+  ## PRBS test patterns
+
+  `prbs_symbols(modulation, count, seed, polynomial)` returns the first
+  `count` symbols of an ITU-T O.150 PRBS as a binary, one symbol index
+  per byte, for BER tests and modulation-quality measurements.
+  `polynomial` is `:prbs9`, `:prbs15` or `:prbs23`; `seed`'s low bits
+  are the starting register, with 0 meaning all ones (from which
+  `:prbs9` is the usual FF 83 DF 17 ... PN9 stream). Each symbol takes
+  the modulation's bits per symbol, first bit most significant, so the
+  same seed gives both ends the same symbols. The inversion O.150
+  applies to PRBS15 and PRBS23 is left out.
+
+  When the bits per symbol divide the register length, the symbols are
+  correlated and the modulated spectrum ripples: use `:prbs23` rather
+  than `:prbs15` for 8-PSK and 32-QAM, and rather than `:prbs9` for
+  8-PSK, where the spectrum matters.
+
+  ## Pulse shaper
+
+  `pulse_shaper_new(sps, alpha, span)` is the modulator's root raised
+  cosine transmit filter on its own, for baseband generated outside the
+  modem. `pulse_shaper_process/2` takes symbol impulses as an I/Q binary
+  and returns `sps` shaped samples per impulse in the same format; the
+  filter history carries across calls, and `pulse_shaper_flush/1` returns
+  the `2 * span * sps` samples of tail. The modem's own filter is
+  `sps = sample_rate / 2400`, `alpha = 0.35`, `span = 6`.
+
+  ## Determinism digest
+
+  `demod_digest(opts, samples)` builds a fresh unified demodulator from
+  `opts` (map or keyword list), runs it over `samples` and returns the
+  32-byte BLAKE3 of the symbols it decided, the matched filter I/Q they
+  were sliced from and the state it ended in. Options:
+  `modulation:` (required), `sample_rate:` (9600), `pulse:`/`bt:` as
+  above, `equalizer:` a `dfe_new/2` config map, `training:` symbols
+  (needs `equalizer:`), `rx_filter:` as `unified_demod_set_rx_filter/2`
+  and `eot:` the options of `unified_demod_enable_eot/2`.
+
+  The receive chain has no randomness, so equal options and samples give
+  an equal digest on every call and scheduler. Across platforms it holds
+  only where the float arithmetic does: sin/cos/atan2/exp come from the
+  platform libm and can differ in the last bit, which changes the digest.
+  The reference digest in the crate's tests is from x86_64 Linux (glibc).
+
+  `unified_demod_export_state/1` returns the canonical state encoding the
+  digest covers, as a binary; two demodulators with equal exports give
+  identical output from then on.
+
+  ## Introspection
+
+  `describe(resource)` takes any modulator or demodulator resource and
+  returns its effective configuration and resource usage as a map, for
+  monitoring: `resource:` (`:modulator`, `:demodulator`,
+  `:unified_modulator` or `:unified_demodulator`), `constellation:` (the
+  one currently selected), `sample_rate:`, `symbol_rate:`,
+  `carrier_freq:`, `sps:`, `pulse:`, `rrc_alpha:`, `span:`,
+  `filter_len:` (taps), `memory_bytes:` (an estimate of the heap memory
+  the resource holds: taps, filter history, queues and scratch buffers),
+  `fingerprint:` (as `unified_mod_config_fingerprint/1`) and
+  `symbols_modulated:` or `symbols_demodulated:`, counted since creation
+  (reset doesn't clear them). A unified demodulator also reports `eq:`
+  (its `DFEConfig` fields and `mode:`, nil without an equalizer),
+  `eq_taps:`, `pll:` (`alpha:`, `beta:`, `freq_offset_hz:`), `gain_db:`
+  (the gain reference, nil unless enabled), `samples_consumed:` and which
+  optional stages are on. A modulator counts a symbol as its impulse
+  enters the pulse shaping filter, so `unified_mod_push_symbols/2`
+  symbols count once pulled, and flush padding counts.
+
+  ## Resource census
+
+  `census/0` counts every kind of resource this library makes, for
+  catching leaks over long runs. It returns a map from kind
+  (`:modulators`, `:demodulators`, `:unified_modulators`,
+  `:unified_demodulators`, `:equalizers`, `:walsh_correlators`,
+  `:pulse_shapers`, `:constellation_scopes`, `:capture_buffers`) to
+  `%{live: n, created: n, dropped: n, bytes: n}`. `bytes` is the
+  `memory_bytes:` of the live resources as their last call returned;
+  capture buffers are counted both on their own and in their
+  demodulator's bytes. A resource is dropped once the VM collects it, so
+  `live` lags the processes that held it until they've been garbage
+  collected. Over a soak run `live` and `bytes` should settle rather than
+  climb.
+
+  ## Self test
+
+  `self_test/0` checks the loaded library computes what it should: the
+  constellation tables against embedded fingerprints, the RRC filter
+  against the Nyquist criterion and a short 8-PSK loopback. It returns
+  `{:ok, %{elapsed_us: n, checks: %{name => summary}}}` or
+  `{:error, [{name, detail}]}`, and runs at application start (see
+  `MinuteModemCore.Application`). The summary map also holds
+  `provenance:`, naming the build the checks passed on.
+
+  ## Provenance
+
+  `provenance/0` returns the library's "license plate", a one-line string
+  naming the code that built it, which decode reports, capture drains and
+  self-test summaries also carry:
+
+      "phy_modem/0.1.0 git=v0.4-12-g3f2a9c1-dirty profile=release features=nif,legacy-wire tables=9d1c04e2b7a35f60"
+
+  `git` is `git describe --always --dirty --tags` at build time (`unknown`
+  without git), and `tables` a hash of the constellation points and pulse
+  shapes as compiled, so a locally edited table changes it even where the
+  describe only says `-dirty`. It's the same string for the life of the
+  VM.
   """
 
   use Rustler,
@@ -37,6 +599,25 @@ defmodule MinuteModemCore.DSP.PhyModem do
   def demod_demodulate(_demodulator, _samples), do: :erlang.nif_error(:nif_not_loaded)
   def demod_reset(_demodulator), do: :erlang.nif_error(:nif_not_loaded)
 
+  def demod_set_input_warnings(_demodulator, _enabled),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def set_warning_logger(_pid), do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # Constellations (stateless)
+  # ============================================================================
+
+  def symbol_to_iq(_constellation, _symbol), do: :erlang.nif_error(:nif_not_loaded)
+  def iq_to_symbol(_constellation, _i, _q), do: :erlang.nif_error(:nif_not_loaded)
+  def constellation_points(_constellation), do: :erlang.nif_error(:nif_not_loaded)
+  def canonical_symbol(_constellation, _symbol), do: :erlang.nif_error(:nif_not_loaded)
+  def constellation_bit_map(_constellation), do: :erlang.nif_error(:nif_not_loaded)
+  def bit_llrs(_constellation, _i, _q, _noise_var), do: :erlang.nif_error(:nif_not_loaded)
+  def bit_llrs_block(_constellation, _iq, _noise_var), do: :erlang.nif_error(:nif_not_loaded)
+  def bits_per_symbol(_constellation), do: :erlang.nif_error(:nif_not_loaded)
+  def order(_constellation), do: :erlang.nif_error(:nif_not_loaded)
+
   # ============================================================================
   # Unified Modulator (runtime constellation switching)
   # ============================================================================
@@ -44,21 +625,48 @@ defmodule MinuteModemCore.DSP.PhyModem do
   def unified_mod_new(_constellation, _sample_rate),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def unified_mod_new(_constellation, _sample_rate, _opts),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def unified_mod_modulate(_modulator, _symbols),
     do: :erlang.nif_error(:nif_not_loaded)
 
   def unified_mod_modulate_mixed(_modulator, _symbols),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def unified_mod_walsh(_modulator, _dibits),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def unified_mod_set_constellation(_modulator, _constellation),
     do: :erlang.nif_error(:nif_not_loaded)
 
   def unified_mod_get_constellation(_modulator),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def unified_mod_set_symbol_map(_modulator, _map, _opts \\ []),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_mod_set_hop_schedule(_modulator, _hops),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def unified_mod_flush(_modulator),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def unified_mod_end_burst(_modulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_mod_ramp_samples(_modulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_mod_push_symbols(_modulator, _symbols),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_mod_pull_samples(_modulator, _n),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_mod_queue_depth(_modulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def unified_mod_reset(_modulator),
     do: :erlang.nif_error(:nif_not_loaded)
 
@@ -69,23 +677,131 @@ defmodule MinuteModemCore.DSP.PhyModem do
   def unified_demod_new(_constellation, _sample_rate),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def unified_demod_new(_constellation, _sample_rate, _opts),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def unified_demod_iq(_demodulator, _samples),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def unified_demod_walsh(_demodulator, _samples),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_align_walsh(_demodulator, _chips),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_mf_output(_demodulator, _samples, _oversample, _apply_pll \\ false),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def unified_demod_symbols(_demodulator, _samples),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def unified_demod_symbols(_demodulator, _samples, _opts),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_at(_demodulator, _start_sample_index, _samples),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_at(_demodulator, _start_sample_index, _samples, _opts),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_with_correction(_demodulator, _samples, _corrections),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_short_burst(_demodulator, _samples, _known_prefix, _opts \\ []),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_step(_demodulator, _samples, _budget_us),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_flush(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_signal_quality(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_decode_report(_demodulator, _format \\ :map),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_enable_eot(_demodulator, _opts \\ []),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_disable_eot(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_enable_timing_tracking(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_disable_timing_tracking(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_timing_offset_ppm(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_enable_stage_timing(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_disable_stage_timing(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_stage_timings(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def unified_demod_set_constellation(_demodulator, _constellation),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def unified_demod_set_symbol_map(_demodulator, _map, _opts \\ []),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_set_hop_schedule(_demodulator, _hops),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_set_gain_reference(_demodulator, _schedule),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_set_probe_tracking(_demodulator, _schedule),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_drain_probe_metrics(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_set_sideband_detection(_demodulator, _enabled),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_set_clip_handling(_demodulator, _opts),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_enable_capture(_demodulator, _decimation, _max_samples),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_disable_capture(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_keep_capture(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_discard_capture(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_drain_capture(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_set_scatter_points(_demodulator, _points),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_scatter(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def unified_demod_reset(_demodulator),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def unified_demod_set_input_warnings(_demodulator, _enabled),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   # ============================================================================
   # Equalizer Functions
   # ============================================================================
 
-  def unified_demod_new_with_eq(_constellation, _sample_rate, _ff_taps, _fb_taps, _mu),
+  def unified_demod_new_with_eq(_constellation, _sample_rate, _ff_taps, _fb_taps, _mu, _adaptation \\ nil),
     do: :erlang.nif_error(:nif_not_loaded)
 
   def unified_demod_new_hf(_constellation, _sample_rate),
@@ -106,12 +822,150 @@ defmodule MinuteModemCore.DSP.PhyModem do
   def unified_demod_enable_eq(_demodulator, _ff_taps, _fb_taps, _mu),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def unified_demod_enable_fse(_demodulator, _ff_taps, _fb_taps, _mu),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def unified_demod_disable_eq(_demodulator),
     do: :erlang.nif_error(:nif_not_loaded)
 
   def unified_demod_eq_mode(_demodulator),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def unified_demod_eq_taps(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # Standalone DFE (offline re-equalization)
+  # ============================================================================
+
+  def dfe_new(_constellation, _config),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def dfe_train(_dfe, _iq, _known_symbols),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def dfe_equalize(_dfe, _iq, _opts \\ []),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def dfe_stats(_dfe),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def dfe_reset(_dfe),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # RX IF Filter Model
+  # ============================================================================
+
+  def unified_demod_set_rx_filter(_demodulator, _filter),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # Modulator/Demodulator Compatibility
+  # ============================================================================
+
+  def unified_mod_config_fingerprint(_modulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_config_fingerprint(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def check_compatibility(_modulator, _demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # Resource Introspection
+  # ============================================================================
+
+  def describe(_resource), do: :erlang.nif_error(:nif_not_loaded)
+  def census(), do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # Probe Sequences
+  # ============================================================================
+
+  def probe_symbols(_kind, _opts \\ []),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # Waveform Presets
+  # ============================================================================
+
+  def waveform_preset(_name), do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_mod_for_waveform(_name, _sample_rate),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_for_waveform(_name, _sample_rate),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # Walsh-16 Correlation
+  # ============================================================================
+
+  def walsh_correlate(_iq), do: :erlang.nif_error(:nif_not_loaded)
+  def walsh_correlator_new(), do: :erlang.nif_error(:nif_not_loaded)
+  def walsh_correlator_push(_correlator, _iq), do: :erlang.nif_error(:nif_not_loaded)
+  def walsh_correlator_reset(_correlator), do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # PRBS Test Patterns
+  # ============================================================================
+
+  def prbs_symbols(_modulation, _count, _seed, _polynomial),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # Pulse Shaper
+  # ============================================================================
+
+  def pulse_shaper_new(_sps, _alpha, _span),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def pulse_shaper_process(_shaper, _impulses),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def pulse_shaper_flush(_shaper),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def pulse_shaper_reset(_shaper),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # Determinism Digest
+  # ============================================================================
+
+  def demod_digest(_opts, _samples),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def unified_demod_export_state(_demodulator),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # Self Test
+  # ============================================================================
+
+  def self_test(), do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # Provenance
+  # ============================================================================
+
+  def provenance(), do: :erlang.nif_error(:nif_not_loaded)
+
+  # ============================================================================
+  # Constellation Scope (LiveView display)
+  # ============================================================================
+
+  def constellation_scope_new(_width, _height, _full_scale \\ nil),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def render_constellation(_scope, _iq, _persistence, _colormap, _constellation \\ nil),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def constellation_scope_clear(_scope),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   # ============================================================================
   # Convenience wrapper
   # ============================================================================
@@ -120,7 +974,8 @@ defmodule MinuteModemCore.DSP.PhyModem do
     ff_taps = Keyword.get(opts, :ff_taps, 15)
     fb_taps = Keyword.get(opts, :fb_taps, 7)
     mu = Keyword.get(opts, :mu, 0.03)
+    adaptation = Keyword.get(opts, :adaptation)
 
-    unified_demod_new_with_eq(constellation, sample_rate, ff_taps, fb_taps, mu)
+    unified_demod_new_with_eq(constellation, sample_rate, ff_taps, fb_taps, mu, adaptation)
   end
 end
@@ -0,0 +1,95 @@
+defmodule MinuteModemCore.DSP.Wire do
+  @moduledoc """
+  The versioned binary formats the NIFs use for I/Q, symbols and LLRs.
+
+  Every binary starts with a 12-byte header,
+
+      <<"MMWF", version = 1, format, element, 0, count::little-32>>
+
+  followed by exactly the payload `count` implies:
+
+    * format 1, element 32 - IQ_F32_INTERLEAVED_V1: `count` pairs of
+      f32-le I, Q
+    * format 2, element 1..8 - SYMBOLS_PACKED_V1: `count` symbols of
+      `element` bits, most significant bit first, zero padded to a byte
+    * format 3, element 32 - LLR_F32_V1: `count` f32-le bit LLRs,
+      ln(P(0) / P(1))
+
+  The definition lives in the Rust `minutemodem_dsp::wire` module, shared
+  by phy_modem and channel_physics. While phy_modem is built with its
+  `legacy-wire` feature (the default) its I/Q inputs also take a bare
+  payload with no header.
+  """
+
+  import Bitwise
+
+  @magic "MMWF"
+  @version 1
+
+  @type decoded ::
+          {:iq, [{float(), float()}]}
+          | {:symbols, 1..8, [non_neg_integer()]}
+          | {:llrs, [float()]}
+
+  @doc """
+  Decode a framed binary.
+
+  Errors name what is wrong, as the Rust side does: `:too_short`,
+  `:bad_magic`, `:unsupported_version`, `:unknown_format`,
+  `:bad_element`, `:bad_reserved` or `:length_mismatch`.
+  """
+  @spec decode(binary()) :: {:ok, decoded()} | {:error, atom()}
+  def decode(<<@magic, @version, format, element, reserved, count::little-32, payload::binary>>) do
+    cond do
+      format not in 1..3 -> {:error, :unknown_format}
+      not valid_element?(format, element) -> {:error, :bad_element}
+      reserved != 0 -> {:error, :bad_reserved}
+      true -> decode_payload(format, element, count, payload)
+    end
+  end
+
+  def decode(binary) when byte_size(binary) < 12, do: {:error, :too_short}
+  def decode(<<@magic, _::binary>>), do: {:error, :unsupported_version}
+  def decode(_binary), do: {:error, :bad_magic}
+
+  defp valid_element?(2, bits), do: bits in 1..8
+  defp valid_element?(_format, element), do: element == 32
+
+  defp decode_payload(1, 32, count, payload) when byte_size(payload) == 8 * count,
+    do: {:ok, {:iq, for(<<i::float-little-32, q::float-little-32 <- payload>>, do: {i, q})}}
+
+  defp decode_payload(2, bits, count, payload) when byte_size(payload) == div(count * bits + 7, 8) do
+    <<packed::bitstring-size(count * bits), _pad::bitstring>> = payload
+    {:ok, {:symbols, bits, for(<<s::size(bits) <- packed>>, do: s)}}
+  end
+
+  defp decode_payload(3, 32, count, payload) when byte_size(payload) == 4 * count,
+    do: {:ok, {:llrs, for(<<x::float-little-32 <- payload>>, do: x)}}
+
+  defp decode_payload(_format, _element, _count, _payload), do: {:error, :length_mismatch}
+
+  @doc "IQ_F32_INTERLEAVED_V1 of `{i, q}` pairs"
+  @spec encode_iq([{number(), number()}]) :: binary()
+  def encode_iq(points) do
+    payload = for {i, q} <- points, into: <<>>, do: <<i::float-little-32, q::float-little-32>>
+    header(1, 32, length(points)) <> payload
+  end
+
+  @doc "SYMBOLS_PACKED_V1 of symbols of `bits` bits each"
+  @spec encode_symbols([non_neg_integer()], 1..8) :: binary()
+  def encode_symbols(symbols, bits) when bits in 1..8 do
+    true = Enum.all?(symbols, &(&1 >>> bits == 0))
+    packed = for s <- symbols, into: <<>>, do: <<s::size(bits)>>
+    pad = rem(8 - rem(bit_size(packed), 8), 8)
+    header(2, bits, length(symbols)) <> <<packed::bitstring, 0::size(pad)>>
+  end
+
+  @doc "LLR_F32_V1 of bit LLRs"
+  @spec encode_llrs([number()]) :: binary()
+  def encode_llrs(llrs) do
+    header(3, 32, length(llrs)) <> for(x <- llrs, into: <<>>, do: <<x::float-little-32>>)
+  end
+
+  defp header(format, element, count),
+    do: <<@magic, @version, format, element, 0, count::little-32>>
+end
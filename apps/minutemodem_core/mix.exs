@@ -39,7 +39,10 @@ defmodule MinuteModemCore.MixProject do
       {:gen_state_machine, "~> 3.0"},
 
       # Rustler for Rust NIFs
-      {:rustler, "~> 0.37"}
+      {:rustler, "~> 0.37"},
+
+      # Property tests
+      {:stream_data, "~> 1.1", only: :test}
     ]
   end
 end
@@ -1,7 +1,9 @@
 // lib.rs
+use minutemodem_dsp::limits::check_max;
 use rustler::{Binary, Env, NifResult, OwnedBinary};
 
 mod fft;
+mod limits;
 mod window;
 
 #[rustler::nif]
@@ -11,6 +13,7 @@ fn compute_fft_db(
     window: &str,            // "hann", "hamming", "none"
 ) -> NifResult<OwnedBinary> {
     // Returns f32-le dB magnitude bins (fft_size/2)
+    check_max("fft_size", fft_size as u64, limits::MAX_FFT_SIZE as u64).map_err(limits::range_error)?;
     fft::compute_db(audio.as_slice(), fft_size, window)
 }
 
@@ -0,0 +1,23 @@
+//! Upper bounds on NIF arguments that size allocations
+
+use minutemodem_dsp::limits::OutOfRange;
+use rustler::{Atom, Encoder, Env, Term};
+
+/// Largest fft_size compute_fft_db accepts
+pub const MAX_FFT_SIZE: usize = 1 << 16;
+
+/// `{:out_of_range, param, max}`: a number that sizes an allocation was
+/// above its maximum
+struct RangeError(OutOfRange);
+
+impl Encoder for RangeError {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        let atom = |name: &str| Atom::from_str(env, name).expect("short ASCII atom names");
+        (atom("out_of_range"), atom(self.0.param), self.0.max).encode(env)
+    }
+}
+
+/// The `{:error, {:out_of_range, param, max}}` a NIF returns for `e`
+pub fn range_error(e: OutOfRange) -> rustler::Error {
+    rustler::Error::Term(Box::new(RangeError(e)))
+}
@@ -1,13 +1,99 @@
 use melpe_codec::core_types::{SUPERFRAME_BYTES_600, SUPERFRAME_SAMPLES};
 use melpe_codec::decoder::Decoder;
 use melpe_codec::encoder::Encoder;
-use rustler::{Env, NifResult, ResourceArc, Term};
-use std::sync::Mutex;
+use minutemodem_dsp::census::{Census, Tally};
+use minutemodem_dsp::convert::{self, I16_FULL_SCALE};
+use minutemodem_dsp::guard;
+use rustler::{Atom, Binary, Env, NifMap, NifResult, OwnedBinary, ResourceArc, Term};
+use std::sync::{Mutex, MutexGuard};
 
 // ── Resource wrappers (Mutex for BEAM scheduler safety) ─────────────────────
 
-pub struct EncoderResource(Mutex<Encoder>);
-pub struct DecoderResource(Mutex<Decoder>);
+pub struct EncoderResource {
+    codec: Mutex<Encoder>,
+    /// Counts the resource out of ENCODERS when the VM collects it
+    _tally: Tally,
+}
+
+pub struct DecoderResource {
+    codec: Mutex<Decoder>,
+    /// Counts the resource out of DECODERS when the VM collects it
+    _tally: Tally,
+}
+
+// ── Census (see minutemodem_dsp::census) ────────────────────────────────────
+//
+// The codecs keep all their state inline, so a resource's bytes are its
+// codec's size and never change.
+
+static ENCODERS: Census = Census::new("encoders");
+static DECODERS: Census = Census::new("decoders");
+
+impl EncoderResource {
+    fn new() -> Self {
+        EncoderResource {
+            codec: Mutex::new(Encoder::new()),
+            _tally: Tally::new(&ENCODERS, std::mem::size_of::<Encoder>()),
+        }
+    }
+}
+
+impl DecoderResource {
+    fn new() -> Self {
+        DecoderResource {
+            codec: Mutex::new(Decoder::new()),
+            _tally: Tally::new(&DECODERS, std::mem::size_of::<Decoder>()),
+        }
+    }
+}
+
+// ── Panic recovery ──────────────────────────────────────────────────────────
+//
+// A NIF that panics with a codec locked returns {:error, {:panic, message}}
+// and leaves the lock poisoned. The next lock() resets the codec and clears
+// the poison, so the resource carries on instead of failing for good.
+
+/// Codec state a panic may have left half-updated
+trait Recover {
+    fn recover(&mut self);
+}
+
+impl Recover for Encoder {
+    fn recover(&mut self) {
+        self.reset();
+    }
+}
+
+impl Recover for Decoder {
+    fn recover(&mut self) {
+        self.reset();
+    }
+}
+
+/// Lock a codec, resetting it if an earlier call panicked with it held
+fn lock<T: Recover>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        let mut codec = poisoned.into_inner();
+        codec.recover();
+        mutex.clear_poison();
+        codec
+    })
+}
+
+/// A caught panic's message, encoded as {:panic, message}
+struct Panicked(String);
+
+impl rustler::Encoder for Panicked {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        let panic = Atom::from_str(env, "panic").expect("a short ASCII atom");
+        (panic, self.0.as_str()).encode(env)
+    }
+}
+
+/// Run a NIF body, turning a panic into {:error, {:panic, message}}
+fn guarded<T>(body: impl FnOnce() -> NifResult<T>) -> NifResult<T> {
+    guard::catch(body).unwrap_or_else(|message| Err(rustler::Error::Term(Box::new(Panicked(message)))))
+}
 
 fn load(env: Env, _info: Term) -> bool {
     rustler::resource!(EncoderResource, env);
@@ -19,84 +105,198 @@ fn load(env: Env, _info: Term) -> bool {
 
 #[rustler::nif]
 fn encoder_new() -> ResourceArc<EncoderResource> {
-    ResourceArc::new(EncoderResource(Mutex::new(Encoder::new())))
+    ResourceArc::new(EncoderResource::new())
 }
 
-/// 540 f64 samples → 6-byte binary
-#[rustler::nif]
-fn encode(encoder: ResourceArc<EncoderResource>, samples: Vec<f64>) -> NifResult<Vec<u8>> {
-    let mut enc = encoder
-        .0
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock_poisoned")))?;
+// ── Binary PCM ──────────────────────────────────────────────────────────────
+//
+// encode_binary/decode_binary take and return a superframe as one binary
+// of 16-bit signed little-endian PCM instead of a 540-element list, which
+// at many channels costs more to decode term by term than the codec does
+// to run. Samples are scaled as convert::i16_to_f64, so a list of
+// sample / 32768 encodes to the same bitstream.
+
+/// Bytes of one superframe of 16-bit PCM
+const SUPERFRAME_PCM_BYTES: usize = SUPERFRAME_SAMPLES * 2;
 
+/// A superframe of ±1.0 full-scale samples as encoder input
+fn samples_to_input(samples: &[f64]) -> Result<[f32; SUPERFRAME_SAMPLES], String> {
     if samples.len() != SUPERFRAME_SAMPLES {
-        return Err(rustler::Error::Term(Box::new(format!(
-            "expected {} samples, got {}",
-            SUPERFRAME_SAMPLES,
-            samples.len()
-        ))));
+        return Err(format!("expected {} samples, got {}", SUPERFRAME_SAMPLES, samples.len()));
+    }
+    let mut input = [0.0f32; SUPERFRAME_SAMPLES];
+    for (d, &s) in input.iter_mut().zip(samples) {
+        *d = s as f32;
     }
+    Ok(input)
+}
 
+/// A superframe of s16le PCM as encoder input
+fn pcm_to_input(pcm: &[u8]) -> Result<[f32; SUPERFRAME_SAMPLES], String> {
+    if pcm.len() != SUPERFRAME_PCM_BYTES {
+        return Err(format!("expected {} bytes, got {}", SUPERFRAME_PCM_BYTES, pcm.len()));
+    }
     let mut input = [0.0f32; SUPERFRAME_SAMPLES];
-    for (i, &s) in samples.iter().enumerate() {
-        input[i] = s as f32;
+    for (d, c) in input.iter_mut().zip(pcm.chunks_exact(2)) {
+        *d = convert::i16_to_f64(i16::from_le_bytes([c[0], c[1]])) as f32;
     }
+    Ok(input)
+}
+
+/// Decoder output as s16le PCM, rounded and saturated
+fn output_to_pcm(output: &[f32], pcm: &mut [u8]) {
+    for (c, &s) in pcm.chunks_exact_mut(2).zip(output) {
+        let v = convert::clamp_i16((s as f64 * I16_FULL_SCALE).round());
+        c.copy_from_slice(&v.to_le_bytes());
+    }
+}
+
+/// A 6-byte superframe as decoder input
+fn bitstream_to_input(bitstream: &[u8]) -> Result<[u8; SUPERFRAME_BYTES_600], String> {
+    bitstream
+        .try_into()
+        .map_err(|_| format!("expected {} bytes, got {}", SUPERFRAME_BYTES_600, bitstream.len()))
+}
+
+/// A binary the VM owns holding `bytes`
+fn owned_binary<'a>(env: Env<'a>, bytes: &[u8]) -> NifResult<Binary<'a>> {
+    let mut owned = OwnedBinary::new(bytes.len())
+        .ok_or_else(|| rustler::Error::Term(Box::new("binary_alloc_failed")))?;
+    owned.as_mut_slice().copy_from_slice(bytes);
+    Ok(owned.release(env))
+}
+
+/// 540 f64 samples → 6-byte binary
+#[rustler::nif]
+fn encode(encoder: ResourceArc<EncoderResource>, samples: Vec<f64>) -> NifResult<Vec<u8>> {
+    guarded(|| {
+        let mut enc = lock(&encoder.codec);
+
+        let input = samples_to_input(&samples).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        let mut bitstream = [0u8; SUPERFRAME_BYTES_600];
+        enc.encode(&input, &mut bitstream);
+        Ok(bitstream.to_vec())
+    })
+}
+
+/// 1080-byte s16le PCM binary → 6-byte binary
+#[rustler::nif]
+fn encode_binary<'a>(env: Env<'a>, encoder: ResourceArc<EncoderResource>, pcm: Binary) -> NifResult<Binary<'a>> {
+    guarded(|| {
+        let mut enc = lock(&encoder.codec);
+
+        let input = pcm_to_input(pcm.as_slice()).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        let mut bitstream = [0u8; SUPERFRAME_BYTES_600];
+        enc.encode(&input, &mut bitstream);
+        owned_binary(env, &bitstream)
+    })
+}
+
+/// PCM history (any length, oldest first) → analysis state, no bitstream
+///
+/// Runs the history through the encoder as whole superframes, zero-padded
+/// in front, so the voicing filter bank has settled by the first real
+/// superframe instead of ringing up from rest.
+#[rustler::nif]
+fn encoder_prime(encoder: ResourceArc<EncoderResource>, history: Vec<f64>) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        let mut enc = lock(&encoder.codec);
+
+        prime_encoder(&mut enc, &history);
+        Ok(rustler::types::atom::ok())
+    })
+}
+
+fn prime_encoder(enc: &mut Encoder, history: &[f64]) {
+    let pad = (SUPERFRAME_SAMPLES - history.len() % SUPERFRAME_SAMPLES) % SUPERFRAME_SAMPLES;
+    let padded: Vec<f32> = std::iter::repeat_n(0.0, pad)
+        .chain(history.iter().map(|&s| s as f32))
+        .collect();
 
-    let mut bitstream = [0u8; SUPERFRAME_BYTES_600];
-    enc.encode(&input, &mut bitstream);
-    Ok(bitstream.to_vec())
+    let mut discard = [0u8; SUPERFRAME_BYTES_600];
+    for chunk in padded.chunks_exact(SUPERFRAME_SAMPLES) {
+        let mut input = [0.0f32; SUPERFRAME_SAMPLES];
+        input.copy_from_slice(chunk);
+        enc.encode(&input, &mut discard);
+    }
 }
 
 #[rustler::nif]
 fn encoder_reset(encoder: ResourceArc<EncoderResource>) -> NifResult<rustler::Atom> {
-    encoder
-        .0
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock_poisoned")))?
-        .reset();
-    Ok(rustler::types::atom::ok())
+    guarded(|| {
+        lock(&encoder.codec).reset();
+        Ok(rustler::types::atom::ok())
+    })
 }
 
 // ── Decoder NIFs ────────────────────────────────────────────────────────────
 
 #[rustler::nif]
 fn decoder_new() -> ResourceArc<DecoderResource> {
-    ResourceArc::new(DecoderResource(Mutex::new(Decoder::new())))
+    ResourceArc::new(DecoderResource::new())
 }
 
 /// 6-byte binary → 540 f64 samples
 #[rustler::nif]
 fn decode(decoder: ResourceArc<DecoderResource>, bitstream: Vec<u8>) -> NifResult<Vec<f64>> {
-    let mut dec = decoder
-        .0
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock_poisoned")))?;
+    guarded(|| {
+        let mut dec = lock(&decoder.codec);
 
-    if bitstream.len() != SUPERFRAME_BYTES_600 {
-        return Err(rustler::Error::Term(Box::new(format!(
-            "expected {} bytes, got {}",
-            SUPERFRAME_BYTES_600,
-            bitstream.len()
-        ))));
-    }
+        let bs = bitstream_to_input(&bitstream).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        let mut output = [0.0f32; SUPERFRAME_SAMPLES];
+        dec.decode(&bs, &mut output);
+        Ok(output.iter().map(|&s| s as f64).collect())
+    })
+}
+
+/// 6-byte binary → 1080-byte s16le PCM binary
+#[rustler::nif]
+fn decode_binary<'a>(env: Env<'a>, decoder: ResourceArc<DecoderResource>, bitstream: Binary) -> NifResult<Binary<'a>> {
+    guarded(|| {
+        let mut dec = lock(&decoder.codec);
 
-    let mut bs = [0u8; SUPERFRAME_BYTES_600];
-    bs.copy_from_slice(&bitstream);
+        let bs = bitstream_to_input(bitstream.as_slice()).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        let mut output = [0.0f32; SUPERFRAME_SAMPLES];
+        dec.decode(&bs, &mut output);
+        let mut pcm = [0u8; SUPERFRAME_PCM_BYTES];
+        output_to_pcm(&output, &mut pcm);
+        owned_binary(env, &pcm)
+    })
+}
+
+/// 6-byte binary → synthesis state, no audio
+///
+/// Decodes the superframe and discards the output, so the synthesis
+/// filter, LSF interpolation and excitation phase have settled. Priming
+/// with the first superframe of a transmission and then decoding it as
+/// usual avoids the rough start of a decoder fresh from decoder_new.
+#[rustler::nif]
+fn decoder_prime(decoder: ResourceArc<DecoderResource>, bitstream: Vec<u8>) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        let mut dec = lock(&decoder.codec);
+
+        let bs = bitstream_to_input(&bitstream).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        prime_decoder(&mut dec, &bs);
+        Ok(rustler::types::atom::ok())
+    })
+}
 
-    let mut output = [0.0f32; SUPERFRAME_SAMPLES];
-    dec.decode(&bs, &mut output);
-    Ok(output.iter().map(|&s| s as f64).collect())
+fn prime_decoder(dec: &mut Decoder, bitstream: &[u8; SUPERFRAME_BYTES_600]) {
+    let mut discard = [0.0f32; SUPERFRAME_SAMPLES];
+    dec.decode(bitstream, &mut discard);
 }
 
 #[rustler::nif]
 fn decoder_reset(decoder: ResourceArc<DecoderResource>) -> NifResult<rustler::Atom> {
-    decoder
-        .0
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock_poisoned")))?
-        .reset();
-    Ok(rustler::types::atom::ok())
+    guarded(|| {
+        lock(&decoder.codec).reset();
+        Ok(rustler::types::atom::ok())
+    })
 }
 
 // ── Info ────────────────────────────────────────────────────────────────────
@@ -113,4 +313,264 @@ fn codec_info() -> Vec<(String, usize)> {
     ]
 }
 
+/// One kind's entry in census/0
+#[derive(NifMap)]
+struct CensusMap {
+    live: u64,
+    created: u64,
+    dropped: u64,
+    bytes: u64,
+}
+
+#[derive(NifMap)]
+struct CodecCensusMap {
+    encoders: CensusMap,
+    decoders: CensusMap,
+}
+
+fn census_map(census: &Census) -> CensusMap {
+    let c = census.count();
+    CensusMap { live: c.live, created: c.created, dropped: c.dropped, bytes: c.bytes }
+}
+
+/// Live encoders and decoders, how many have come and gone, and their bytes
+#[rustler::nif]
+fn census() -> CodecCensusMap {
+    CodecCensusMap { encoders: census_map(&ENCODERS), decoders: census_map(&DECODERS) }
+}
+
 rustler::init!("Elixir.MinuteModemCore.DSP.Melpe", load = load);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    const FS: f64 = 8000.0;
+    const BLOCK: usize = 45;
+
+    /// Stationary synthetic vowel: 133 Hz pulse train through three formants
+    fn vowel(n: usize) -> Vec<f64> {
+        let formants = [(700.0, 110.0), (1220.0, 120.0), (2600.0, 160.0)];
+        let mut x: Vec<f64> = (0..n).map(|i| if i % 60 == 0 { 1.0 } else { 0.0 }).collect();
+        for &(f, bw) in &formants {
+            let r = (-PI * bw / FS).exp();
+            let a1 = 2.0 * r * (2.0 * PI * f / FS).cos();
+            let a2 = -r * r;
+            let (mut y1, mut y2) = (0.0, 0.0);
+            for s in x.iter_mut() {
+                let y = *s + a1 * y1 + a2 * y2;
+                y2 = y1;
+                y1 = y;
+                *s = y;
+            }
+        }
+        let peak = x.iter().fold(0.0f64, |m, &s| m.max(s.abs()));
+        x.iter().map(|s| 0.5 * s / peak).collect()
+    }
+
+    /// Hann-windowed power in 500 Hz bands, in dB
+    fn band_spectrum(x: &[f32]) -> Vec<f64> {
+        let n = x.len();
+        (0..8)
+            .map(|b| {
+                let power: f64 = (0..4)
+                    .map(|k| {
+                        let f = 62.5 + 125.0 * (4 * b + k) as f64;
+                        let (mut re, mut im) = (0.0, 0.0);
+                        for (i, &s) in x.iter().enumerate() {
+                            let w = 0.5 - 0.5 * (2.0 * PI * i as f64 / n as f64).cos();
+                            let phase = 2.0 * PI * f * i as f64 / FS;
+                            re += w * s as f64 * phase.cos();
+                            im -= w * s as f64 * phase.sin();
+                        }
+                        re * re + im * im
+                    })
+                    .sum();
+                10.0 * (power + 1e-12).log10()
+            })
+            .collect()
+    }
+
+    /// Decode `speech` superframe by superframe, optionally primed first
+    fn decode_speech(speech: &[f64], history: Option<&[f64]>) -> Vec<f32> {
+        let mut enc = Encoder::new();
+        let mut dec = Decoder::new();
+        if let Some(history) = history {
+            prime_encoder(&mut enc, history);
+        }
+
+        let mut decoded = Vec::with_capacity(speech.len());
+        for (k, chunk) in speech.chunks_exact(SUPERFRAME_SAMPLES).enumerate() {
+            let mut input = [0.0f32; SUPERFRAME_SAMPLES];
+            for (d, &s) in input.iter_mut().zip(chunk) {
+                *d = s as f32;
+            }
+            let mut bs = [0u8; SUPERFRAME_BYTES_600];
+            enc.encode(&input, &mut bs);
+            if k == 0 && history.is_some() {
+                prime_decoder(&mut dec, &bs);
+            }
+            let mut out = [0.0f32; SUPERFRAME_SAMPLES];
+            dec.decode(&bs, &mut out);
+            decoded.extend_from_slice(&out);
+        }
+        decoded
+    }
+
+    /// Worst spectral distortion (RMS dB over bands, level included) of any
+    /// BLOCK-sample block in each superframe, against the average spectrum of
+    /// the superframes from `settled` on
+    fn superframe_distortion(decoded: &[f32], settled: usize) -> Vec<f64> {
+        let spectra: Vec<Vec<f64>> = decoded.chunks_exact(BLOCK).map(band_spectrum).collect();
+        let steady = &spectra[settled * SUPERFRAME_SAMPLES / BLOCK..];
+        let reference: Vec<f64> = (0..8)
+            .map(|b| steady.iter().map(|s| s[b]).sum::<f64>() / steady.len() as f64)
+            .collect();
+
+        spectra
+            .chunks_exact(SUPERFRAME_SAMPLES / BLOCK)
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .map(|s| {
+                        let sq: f64 = s.iter().zip(&reference).map(|(a, b)| (a - b).powi(2)).sum();
+                        (sq / 8.0).sqrt()
+                    })
+                    .fold(0.0, f64::max)
+            })
+            .collect()
+    }
+
+    /// A cold decoder's first 45 samples are silent and the next few ms
+    /// ramp up, so its first superframe's worst block measures ~113 dB
+    /// against 17-30 dB for settled ones. Primed (with a superframe of
+    /// history and the first bitstream) it measures ~24 dB.
+    #[test]
+    fn test_priming_smooths_first_superframe() {
+        let signal = vowel(SUPERFRAME_SAMPLES * 14);
+        let (history, speech) = signal.split_at(SUPERFRAME_SAMPLES * 2);
+        let settled = 4;
+
+        let gap = |history: Option<&[f64]>| {
+            let sd = superframe_distortion(&decode_speech(speech, history), settled);
+            let steady = &sd[settled..];
+            let worst = steady.iter().cloned().fold(0.0, f64::max);
+            (sd[0] - steady.iter().sum::<f64>() / steady.len() as f64, sd[0], worst)
+        };
+        let (cold_gap, cold_first, _) = gap(None);
+        let (warm_gap, warm_first, steady_worst) = gap(Some(history));
+
+        assert!(cold_first > steady_worst + 20.0, "cold {} vs steady {}", cold_first, steady_worst);
+        assert!(warm_first <= steady_worst, "primed {} vs steady {}", warm_first, steady_worst);
+        assert!(warm_gap < cold_gap / 4.0, "gap {} primed vs {} cold", warm_gap, cold_gap);
+    }
+
+    #[test]
+    fn test_encoder_prime_pads_partial_superframes() {
+        let signal = vowel(SUPERFRAME_SAMPLES * 3);
+        let (history, speech) = signal.split_at(SUPERFRAME_SAMPLES * 2);
+
+        let mut padded = vec![0.0; SUPERFRAME_SAMPLES - 100];
+        padded.extend_from_slice(&history[history.len() - 100..]);
+        let mut whole = Encoder::new();
+        let mut partial = Encoder::new();
+        prime_encoder(&mut whole, &padded);
+        prime_encoder(&mut partial, &history[history.len() - 100..]);
+
+        let mut input = [0.0f32; SUPERFRAME_SAMPLES];
+        for (d, &s) in input.iter_mut().zip(speech) {
+            *d = s as f32;
+        }
+        let (mut a, mut b) = ([0u8; SUPERFRAME_BYTES_600], [0u8; SUPERFRAME_BYTES_600]);
+        whole.encode(&input, &mut a);
+        partial.encode(&input, &mut b);
+        assert_eq!(a, b);
+        assert!(partial.is_initialized());
+    }
+
+    /// A panic mid-encode (the test-only hook) is returned as an error and
+    /// the next call gets a reset encoder
+    #[test]
+    fn test_encoder_recovers_from_panic() {
+        let mutex = Mutex::new(Encoder::new());
+        let speech = vowel(SUPERFRAME_SAMPLES * 2);
+        let encode_with = |enc: &mut Encoder, chunk: &[f64]| {
+            let mut input = [0.0f32; SUPERFRAME_SAMPLES];
+            for (d, &s) in input.iter_mut().zip(chunk) {
+                *d = s as f32;
+            }
+            let mut bs = [0u8; SUPERFRAME_BYTES_600];
+            enc.encode(&input, &mut bs);
+            bs
+        };
+
+        let result = guarded::<()>(|| {
+            let mut enc = lock(&mutex);
+            encode_with(&mut enc, &speech[..SUPERFRAME_SAMPLES]);
+            panic!("superframe index {} out of range", 3);
+        });
+        assert!(matches!(result, Err(rustler::Error::Term(_))));
+        assert!(mutex.is_poisoned());
+
+        let recovered = guarded(|| Ok(encode_with(&mut lock(&mutex), &speech[SUPERFRAME_SAMPLES..]))).ok();
+        assert!(!mutex.is_poisoned());
+        let fresh = encode_with(&mut Encoder::new(), &speech[SUPERFRAME_SAMPLES..]);
+        assert_eq!(recovered, Some(fresh));
+    }
+
+    #[test]
+    fn test_binary_and_list_paths_encode_alike() {
+        let speech = vowel(SUPERFRAME_SAMPLES * 4);
+        let pcm: Vec<i16> = speech.iter().map(|&s| convert::f64_to_i16(s)).collect();
+        let bytes: Vec<u8> = pcm.iter().flat_map(|s| s.to_le_bytes()).collect();
+        let list: Vec<f64> = pcm.iter().map(|&s| s as f64 / 32768.0).collect();
+
+        let (mut from_list, mut from_binary) = (Encoder::new(), Encoder::new());
+        let (mut list_dec, mut binary_dec) = (Decoder::new(), Decoder::new());
+        for (samples, pcm) in list.chunks(SUPERFRAME_SAMPLES).zip(bytes.chunks(SUPERFRAME_PCM_BYTES)) {
+            let (mut a, mut b) = ([0u8; SUPERFRAME_BYTES_600], [0u8; SUPERFRAME_BYTES_600]);
+            from_list.encode(&samples_to_input(samples).unwrap(), &mut a);
+            from_binary.encode(&pcm_to_input(pcm).unwrap(), &mut b);
+            assert_eq!(a, b);
+
+            // The decoded PCM is the list output, rounded to 16 bits
+            let (mut x, mut y) = ([0.0f32; SUPERFRAME_SAMPLES], [0.0f32; SUPERFRAME_SAMPLES]);
+            list_dec.decode(&bitstream_to_input(&a).unwrap(), &mut x);
+            binary_dec.decode(&bitstream_to_input(&b).unwrap(), &mut y);
+            let mut out = [0u8; SUPERFRAME_PCM_BYTES];
+            output_to_pcm(&y, &mut out);
+            for (c, &s) in out.chunks_exact(2).zip(&x) {
+                let v = i16::from_le_bytes([c[0], c[1]]) as f64 / 32768.0;
+                assert!((v - s as f64).abs() <= 0.5 / 32768.0 || s.abs() >= 1.0, "{} vs {}", v, s);
+            }
+        }
+    }
+
+    #[test]
+    fn test_binary_lengths_checked() {
+        assert_eq!(pcm_to_input(&[0u8; 1078]).unwrap_err(), "expected 1080 bytes, got 1078");
+        assert_eq!(samples_to_input(&[0.0; 541]).unwrap_err(), "expected 540 samples, got 541");
+        assert!(bitstream_to_input(&[0u8; 7]).is_err());
+
+        // Full scale saturates rather than wrapping
+        let mut out = [0u8; 4];
+        output_to_pcm(&[1.5, -1.5], &mut out);
+        assert_eq!(out, [0xff, 0x7f, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn test_census_returns_to_zero() {
+        let codecs: Vec<(EncoderResource, DecoderResource)> =
+            (0..2000).map(|_| (EncoderResource::new(), DecoderResource::new())).collect();
+        assert_eq!(ENCODERS.this_thread().live, 2000);
+        assert_eq!(DECODERS.this_thread().bytes, 2000 * std::mem::size_of::<Decoder>() as i64);
+
+        drop(codecs);
+        for census in [&ENCODERS, &DECODERS] {
+            assert_eq!(census.this_thread().live, 0);
+            assert_eq!(census.this_thread().bytes, 0);
+            assert!(census.count().dropped >= 2000);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Live resource counts, for spotting leaks on long runs
+//!
+//! Every NIF resource holds a Tally, which counts it into its kind's
+//! Census when it's created and out again when the VM collects it, along
+//! with the buffer bytes it last reported. Over a soak run `live` and
+//! `bytes` should come back to where they started once the owning
+//! processes are gone; `created` and `dropped` only ever climb.
+//!
+//! The counts are process-wide. Each thread also keeps its own share
+//! (Census::this_thread) so that tests running in parallel don't see each
+//! other's resources, as with the allocation audit. A resource dropped on
+//! another thread than the one that created it leaves both threads' shares
+//! off; the process-wide counts are always right.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering::Relaxed};
+
+/// One kind's counts at a moment
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CensusCount {
+    pub live: u64,
+    pub created: u64,
+    pub dropped: u64,
+    /// Buffer bytes held by the live ones, as they last reported
+    pub bytes: u64,
+}
+
+/// This thread's share of a kind (signed: see the module docs)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThreadCount {
+    pub live: i64,
+    pub bytes: i64,
+}
+
+/// Counters for one kind of resource
+pub struct Census {
+    name: &'static str,
+    created: AtomicU64,
+    dropped: AtomicU64,
+    bytes: AtomicU64,
+}
+
+thread_local! {
+    // (census address, share); a handful of kinds, so a scan is fine
+    static THREAD: RefCell<Vec<(usize, ThreadCount)>> = const { RefCell::new(Vec::new()) };
+}
+
+impl Census {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            created: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// The kind, as census NIFs key it (`"unified_demodulators"`, ...)
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn count(&self) -> CensusCount {
+        // dropped before created: the other way round, a resource created
+        // and dropped between the loads would show as -1 live
+        let dropped = self.dropped.load(Relaxed);
+        let created = self.created.load(Relaxed);
+        CensusCount {
+            live: created.saturating_sub(dropped),
+            created,
+            dropped,
+            bytes: self.bytes.load(Relaxed),
+        }
+    }
+
+    /// What this thread has created and not dropped
+    pub fn this_thread(&self) -> ThreadCount {
+        let key = self.key();
+        THREAD
+            .try_with(|shares| {
+                shares.borrow().iter().find(|(k, _)| *k == key).map_or(ThreadCount::default(), |&(_, c)| c)
+            })
+            .unwrap_or_default()
+    }
+
+    fn key(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// Add `bytes` and then take away `released`, so that the total never
+    /// dips below what's really held
+    fn shift(&self, bytes: usize, released: usize) {
+        self.bytes.fetch_add(bytes as u64, Relaxed);
+        self.bytes.fetch_sub(released as u64, Relaxed);
+        self.share(0, bytes as i64 - released as i64);
+    }
+
+    fn share(&self, live: i64, bytes: i64) {
+        let key = self.key();
+        // try_with: the thread-local may already be gone during thread exit
+        let _ = THREAD.try_with(|shares| {
+            let mut shares = shares.borrow_mut();
+            let i = match shares.iter().position(|(k, _)| *k == key) {
+                Some(i) => i,
+                None => {
+                    shares.push((key, ThreadCount::default()));
+                    shares.len() - 1
+                }
+            };
+            shares[i].1.live += live;
+            shares[i].1.bytes += bytes;
+        });
+    }
+}
+
+/// One resource's place in its kind's census; counts it out when dropped
+pub struct Tally {
+    census: &'static Census,
+    bytes: AtomicUsize,
+}
+
+impl Tally {
+    /// Count a new resource holding `bytes` of buffers into `census`
+    pub fn new(census: &'static Census, bytes: usize) -> Self {
+        census.created.fetch_add(1, Relaxed);
+        census.share(1, 0);
+        census.shift(bytes, 0);
+        Self { census, bytes: AtomicUsize::new(bytes) }
+    }
+
+    /// The resource's buffers now hold `bytes`
+    pub fn set_bytes(&self, bytes: usize) {
+        let old = self.bytes.swap(bytes, Relaxed);
+        if old != bytes {
+            self.census.shift(bytes, old);
+        }
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Relaxed)
+    }
+}
+
+impl Drop for Tally {
+    fn drop(&mut self) {
+        self.census.shift(0, self.bytes());
+        self.census.share(-1, 0);
+        self.census.dropped.fetch_add(1, Relaxed);
+    }
+}
+
+impl std::fmt::Debug for Tally {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tally").field("census", &self.census.name).field("bytes", &self.bytes()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static WIDGETS: Census = Census::new("widgets");
+
+    #[test]
+    fn test_tallies_count_in_and_out() {
+        let before = WIDGETS.count();
+        let tallies: Vec<Tally> = (0..5000).map(|i| Tally::new(&WIDGETS, i)).collect();
+        let held: usize = (0..5000).sum();
+        assert_eq!(WIDGETS.this_thread(), ThreadCount { live: 5000, bytes: held as i64 });
+        assert_eq!(WIDGETS.count().created, before.created + 5000);
+
+        tallies[0].set_bytes(1 << 20);
+        tallies[1].set_bytes(0);
+        assert_eq!(WIDGETS.this_thread().bytes, (held + (1 << 20) - 1) as i64);
+
+        drop(tallies);
+        assert_eq!(WIDGETS.this_thread(), ThreadCount::default());
+        let after = WIDGETS.count();
+        assert_eq!(after.dropped, before.dropped + 5000);
+        assert_eq!(after.live, before.live);
+        assert_eq!(after.bytes, before.bytes);
+    }
+
+    #[test]
+    fn test_threads_see_their_own_share() {
+        let mine = Tally::new(&WIDGETS, 10);
+        std::thread::spawn(|| {
+            assert_eq!(WIDGETS.this_thread(), ThreadCount::default());
+            let theirs = Tally::new(&WIDGETS, 7);
+            assert_eq!(WIDGETS.this_thread(), ThreadCount { live: 1, bytes: 7 });
+            drop(theirs);
+        })
+        .join()
+        .unwrap();
+        assert_eq!(WIDGETS.this_thread(), ThreadCount { live: 1, bytes: 10 });
+        drop(mine);
+    }
+}
@@ -0,0 +1,168 @@
+//! Minimal complex number type
+//!
+//! Just enough arithmetic for equalizers, mixers and fading taps without
+//! pulling in num-complex.
+
+/// Complex number with f64 parts
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    #[inline]
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    #[inline]
+    pub fn zero() -> Self {
+        Self { re: 0.0, im: 0.0 }
+    }
+
+    /// Unit phasor e^{jθ} scaled by `mag`
+    #[inline]
+    pub fn from_polar(mag: f64, theta: f64) -> Self {
+        let (s, c) = theta.sin_cos();
+        Self { re: mag * c, im: mag * s }
+    }
+
+    #[inline]
+    pub fn conj(self) -> Self {
+        Self { re: self.re, im: -self.im }
+    }
+
+    #[inline]
+    pub fn mag_sq(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    #[inline]
+    pub fn mag(self) -> f64 {
+        self.mag_sq().sqrt()
+    }
+
+    /// Argument in radians, (-π, π]
+    #[inline]
+    pub fn phase(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+}
+
+impl std::ops::Add for Complex {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self { re: self.re + rhs.re, im: self.im + rhs.im }
+    }
+}
+
+impl std::ops::Sub for Complex {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self { re: self.re - rhs.re, im: self.im - rhs.im }
+    }
+}
+
+impl std::ops::Mul for Complex {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            re: self.re * rhs.re - self.im * rhs.im,
+            im: self.re * rhs.im + self.im * rhs.re,
+        }
+    }
+}
+
+impl std::ops::Mul<f64> for Complex {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: f64) -> Self {
+        Self { re: self.re * rhs, im: self.im * rhs }
+    }
+}
+
+impl std::ops::Neg for Complex {
+    type Output = Self;
+    #[inline]
+    fn neg(self) -> Self {
+        Self { re: -self.re, im: -self.im }
+    }
+}
+
+impl std::ops::AddAssign for Complex {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        self.re += rhs.re;
+        self.im += rhs.im;
+    }
+}
+
+impl std::ops::SubAssign for Complex {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        self.re -= rhs.re;
+        self.im -= rhs.im;
+    }
+}
+
+impl std::iter::Sum for Complex {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Complex::zero(), |acc, x| acc + x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Complex::new(1.0, 2.0);
+        let b = Complex::new(3.0, -1.0);
+
+        assert_eq!(a + b, Complex::new(4.0, 1.0));
+        assert_eq!(a - b, Complex::new(-2.0, 3.0));
+        // (1 + 2j)(3 - j) = 3 - j + 6j + 2 = 5 + 5j
+        assert_eq!(a * b, Complex::new(5.0, 5.0));
+        assert_eq!(a * 2.0, Complex::new(2.0, 4.0));
+        assert_eq!(-a, Complex::new(-1.0, -2.0));
+    }
+
+    #[test]
+    fn test_assign_ops() {
+        let mut a = Complex::new(1.0, 1.0);
+        a += Complex::new(2.0, -3.0);
+        assert_eq!(a, Complex::new(3.0, -2.0));
+        a -= Complex::new(3.0, -2.0);
+        assert_eq!(a, Complex::zero());
+    }
+
+    #[test]
+    fn test_conj_and_magnitude() {
+        let a = Complex::new(3.0, 4.0);
+        assert_eq!(a.conj(), Complex::new(3.0, -4.0));
+        assert_eq!(a.mag_sq(), 25.0);
+        assert_eq!(a.mag(), 5.0);
+        // z · conj(z) is real |z|²
+        assert_eq!(a * a.conj(), Complex::new(25.0, 0.0));
+    }
+
+    #[test]
+    fn test_polar_roundtrip() {
+        let z = Complex::from_polar(2.0, PI / 3.0);
+        assert!((z.mag() - 2.0).abs() < 1e-12);
+        assert!((z.phase() - PI / 3.0).abs() < 1e-12);
+        assert!((Complex::new(-1.0, 0.0).phase() - PI).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_sum() {
+        let total: Complex = (1..=4).map(|k| Complex::new(k as f64, -(k as f64))).sum();
+        assert_eq!(total, Complex::new(10.0, -10.0));
+    }
+}
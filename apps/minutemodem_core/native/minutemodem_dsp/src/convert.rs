@@ -0,0 +1,366 @@
+//! Sample format conversions
+//!
+//! Audio crosses the NIF boundary as i16 PCM or as packed f32/f64
+//! binaries. These helpers are the one place that knows the scaling and
+//! byte layout.
+//!
+//! Byte conversions take a fast path when the binary happens to be
+//! suitably aligned and in native byte order: the payload is reinterpreted
+//! in place and copied with a single memcpy. Unaligned input falls back to
+//! per-element decoding with identical results. Binaries in the other
+//! byte order are swapped an element at a time, which compiles to a bswap
+//! per element.
+
+/// i16 full scale: -32768 maps to -1.0
+pub const I16_FULL_SCALE: f64 = 32768.0;
+
+/// Convert an i16 PCM sample to ±1.0 full scale
+#[inline]
+pub fn i16_to_f64(x: i16) -> f64 {
+    x as f64 / I16_FULL_SCALE
+}
+
+/// Clamp a value already scaled to PCM units into i16 range (truncating)
+#[inline]
+pub fn clamp_i16(val: f64) -> i16 {
+    if val >= 32767.0 {
+        32767
+    } else if val <= -32768.0 {
+        -32768
+    } else {
+        val as i16
+    }
+}
+
+/// Convert a ±1.0 full-scale sample to saturated i16 PCM
+#[inline]
+pub fn f64_to_i16(x: f64) -> i16 {
+    clamp_i16(x * I16_FULL_SCALE)
+}
+
+/// Convert a block of i16 PCM to ±1.0 full scale
+pub fn i16s_to_f64(samples: &[i16]) -> Vec<f64> {
+    samples.iter().map(|&s| i16_to_f64(s)).collect()
+}
+
+/// Signed 24-bit full scale: -8388608 maps to -1.0
+pub const I24_FULL_SCALE: f64 = 8_388_608.0;
+
+/// Unpack packed 3-byte little-endian signed 24-bit PCM to ±1.0 full scale
+///
+/// None if the length isn't a multiple of 3.
+pub fn s24le_to_f64s(bytes: &[u8]) -> Option<Vec<f64>> {
+    if !bytes.len().is_multiple_of(3) {
+        return None;
+    }
+    Some(
+        bytes
+            .chunks_exact(3)
+            .map(|c| {
+                // Place in the top 24 bits, then arithmetic shift sign-extends
+                let v = i32::from_le_bytes([0, c[0], c[1], c[2]]) >> 8;
+                v as f64 / I24_FULL_SCALE
+            })
+            .collect(),
+    )
+}
+
+/// Pack ±1.0 full-scale samples as 3-byte little-endian signed 24-bit PCM
+///
+/// Rounds to nearest and saturates at the 24-bit limits.
+///
+/// # Panics
+/// If `out` is shorter than `samples.len() * 3` bytes.
+pub fn f64s_to_s24le(samples: &[f64], out: &mut [u8]) {
+    for (chunk, &s) in out[..samples.len() * 3].chunks_exact_mut(3).zip(samples) {
+        let v = (s * I24_FULL_SCALE).round().clamp(-I24_FULL_SCALE, I24_FULL_SCALE - 1.0) as i32;
+        chunk.copy_from_slice(&v.to_le_bytes()[..3]);
+    }
+}
+
+macro_rules! float_bytes {
+    (
+        $t:ty, $size:expr,
+        $cast:ident, $from_ne:ident, $from_le:ident, $from_be:ident,
+        $to_ne:ident, $to_le:ident, $to_be:ident
+    ) => {
+        /// Reinterpret a native-endian binary in place, if it is aligned
+        /// and a whole number of elements
+        pub fn $cast(bytes: &[u8]) -> Option<&[$t]> {
+            if !bytes.len().is_multiple_of($size) {
+                return None;
+            }
+            // SAFETY: every bit pattern is a valid float; align_to only
+            // returns a non-empty middle for properly aligned memory
+            let (prefix, body, suffix) = unsafe { bytes.align_to::<$t>() };
+            if prefix.is_empty() && suffix.is_empty() {
+                Some(body)
+            } else {
+                None
+            }
+        }
+
+        /// Decode a native-endian binary; None if the length isn't a
+        /// whole number of elements
+        pub fn $from_ne(bytes: &[u8]) -> Option<Vec<$t>> {
+            if !bytes.len().is_multiple_of($size) {
+                return None;
+            }
+            if let Some(body) = $cast(bytes) {
+                return Some(body.to_vec());
+            }
+            Some(
+                bytes
+                    .chunks_exact($size)
+                    .map(|c| <$t>::from_ne_bytes(c.try_into().unwrap()))
+                    .collect(),
+            )
+        }
+
+        /// Decode a little-endian binary; None if the length isn't a
+        /// whole number of elements
+        pub fn $from_le(bytes: &[u8]) -> Option<Vec<$t>> {
+            if cfg!(target_endian = "little") {
+                return $from_ne(bytes);
+            }
+            if !bytes.len().is_multiple_of($size) {
+                return None;
+            }
+            Some(
+                bytes
+                    .chunks_exact($size)
+                    .map(|c| <$t>::from_le_bytes(c.try_into().unwrap()))
+                    .collect(),
+            )
+        }
+
+        /// Decode a big-endian binary; None if the length isn't a whole
+        /// number of elements
+        pub fn $from_be(bytes: &[u8]) -> Option<Vec<$t>> {
+            if cfg!(target_endian = "big") {
+                return $from_ne(bytes);
+            }
+            if !bytes.len().is_multiple_of($size) {
+                return None;
+            }
+            Some(
+                bytes
+                    .chunks_exact($size)
+                    .map(|c| <$t>::from_be_bytes(c.try_into().unwrap()))
+                    .collect(),
+            )
+        }
+
+        /// Encode samples native-endian into `out`
+        ///
+        /// # Panics
+        /// If `out` is shorter than `samples.len()` elements.
+        pub fn $to_ne(samples: &[$t], out: &mut [u8]) {
+            let out = &mut out[..samples.len() * $size];
+            // SAFETY: as above, floats accept any bit pattern
+            let (prefix, body, suffix) = unsafe { out.align_to_mut::<$t>() };
+            if prefix.is_empty() && suffix.is_empty() {
+                body.copy_from_slice(samples);
+                return;
+            }
+            for (chunk, s) in out.chunks_exact_mut($size).zip(samples) {
+                chunk.copy_from_slice(&s.to_ne_bytes());
+            }
+        }
+
+        /// Encode samples little-endian into `out`
+        ///
+        /// # Panics
+        /// If `out` is shorter than `samples.len()` elements.
+        pub fn $to_le(samples: &[$t], out: &mut [u8]) {
+            if cfg!(target_endian = "little") {
+                return $to_ne(samples, out);
+            }
+            for (chunk, s) in out.chunks_exact_mut($size).zip(samples) {
+                chunk.copy_from_slice(&s.to_le_bytes());
+            }
+        }
+
+        /// Encode samples big-endian into `out`
+        ///
+        /// # Panics
+        /// If `out` is shorter than `samples.len()` elements.
+        pub fn $to_be(samples: &[$t], out: &mut [u8]) {
+            if cfg!(target_endian = "big") {
+                return $to_ne(samples, out);
+            }
+            let out = &mut out[..samples.len() * $size];
+            for (chunk, s) in out.chunks_exact_mut($size).zip(samples) {
+                chunk.copy_from_slice(&s.to_be_bytes());
+            }
+        }
+    };
+}
+
+float_bytes!(
+    f32, 4, cast_f32, f32s_from_ne_bytes, f32s_from_le_bytes, f32s_from_be_bytes,
+    f32s_to_ne_bytes, f32s_to_le_bytes, f32s_to_be_bytes
+);
+float_bytes!(
+    f64, 8, cast_f64, f64s_from_ne_bytes, f64s_from_le_bytes, f64s_from_be_bytes,
+    f64s_to_ne_bytes, f64s_to_le_bytes, f64s_to_be_bytes
+);
+
+/// Decode interleaved native-endian f64 I/Q (I0 Q0 I1 Q1 ...) into pairs
+///
+/// None unless the length is a whole number of 16-byte pairs.
+pub fn iq_from_ne_bytes(bytes: &[u8]) -> Option<Vec<(f64, f64)>> {
+    if !bytes.len().is_multiple_of(16) {
+        return None;
+    }
+    let flat = f64s_from_ne_bytes(bytes)?;
+    Some(flat.chunks_exact(2).map(|c| (c[0], c[1])).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_i16_scaling() {
+        assert_eq!(i16_to_f64(0), 0.0);
+        assert_eq!(i16_to_f64(-32768), -1.0);
+        assert_eq!(i16_to_f64(16384), 0.5);
+        assert_eq!(i16s_to_f64(&[16384, -16384]), vec![0.5, -0.5]);
+    }
+
+    #[test]
+    fn test_clamp_i16() {
+        assert_eq!(clamp_i16(1000.9), 1000);
+        assert_eq!(clamp_i16(-1000.9), -1000);
+        assert_eq!(clamp_i16(40000.0), 32767);
+        assert_eq!(clamp_i16(-40000.0), -32768);
+    }
+
+    #[test]
+    fn test_f64_to_i16_saturates() {
+        assert_eq!(f64_to_i16(0.5), 16384);
+        assert_eq!(f64_to_i16(1.5), 32767);
+        assert_eq!(f64_to_i16(-1.5), -32768);
+    }
+
+    #[test]
+    fn test_s24le_unpack_fixtures() {
+        let bytes = [
+            0x00, 0x00, 0x00, // 0
+            0x01, 0x00, 0x00, // +1 LSB
+            0xff, 0xff, 0xff, // -1 LSB
+            0xff, 0xff, 0x7f, // +full scale
+            0x00, 0x00, 0x80, // -full scale
+            0x00, 0x00, 0x40, // +0.5
+            0x56, 0x34, 0x12, // 0x123456
+        ];
+        let got = s24le_to_f64s(&bytes).unwrap();
+        let lsb = 1.0 / I24_FULL_SCALE;
+        assert_eq!(
+            got,
+            vec![0.0, lsb, -lsb, 1.0 - lsb, -1.0, 0.5, 0x123456 as f64 * lsb]
+        );
+        assert!(s24le_to_f64s(&[0u8; 4]).is_none());
+    }
+
+    #[test]
+    fn test_s24le_pack_fixtures_and_roundtrip() {
+        let lsb = 1.0 / I24_FULL_SCALE;
+        let samples = [0.0, lsb, -lsb, -1.0, 0.5, 2.0, -2.0, 0.4 * lsb, -0.6 * lsb];
+        let mut out = vec![0u8; samples.len() * 3];
+        f64s_to_s24le(&samples, &mut out);
+        assert_eq!(
+            out,
+            vec![
+                0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0xff, 0xff, 0xff, // 0, +1, -1
+                0x00, 0x00, 0x80, 0x00, 0x00, 0x40, // -1.0, 0.5
+                0xff, 0xff, 0x7f, 0x00, 0x00, 0x80, // saturated
+                0x00, 0x00, 0x00, 0xff, 0xff, 0xff, // rounded to nearest
+            ]
+        );
+
+        // Every 24-bit code survives unpack → pack
+        let codes: Vec<u8> = (0..=255u8).flat_map(|b| [b, b.wrapping_mul(7), b ^ 0x80]).collect();
+        let mut repacked = vec![0u8; codes.len()];
+        f64s_to_s24le(&s24le_to_f64s(&codes).unwrap(), &mut repacked);
+        assert_eq!(codes, repacked);
+    }
+
+    #[test]
+    fn test_iq_pairs_from_bytes() {
+        let flat = [0.5, -0.25, 1.0, 0.0];
+        let mut buf = vec![0u8; 32];
+        f64s_to_ne_bytes(&flat, &mut buf);
+        assert_eq!(iq_from_ne_bytes(&buf).unwrap(), vec![(0.5, -0.25), (1.0, 0.0)]);
+        // A lone I without its Q is rejected
+        assert!(iq_from_ne_bytes(&buf[..24]).is_none());
+        assert!(iq_from_ne_bytes(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_f32_roundtrip_aligned_and_unaligned() {
+        let samples = [0.0f32, 1.5, -2.25, f32::MIN_POSITIVE, 1e30];
+
+        // Over-allocate so we can test both alignments of the same data
+        let mut storage = vec![0u8; samples.len() * 4 + 1];
+        for offset in [0usize, 1] {
+            let buf = &mut storage[offset..offset + samples.len() * 4];
+            f32s_to_ne_bytes(&samples, buf);
+            assert_eq!(f32s_from_ne_bytes(buf).unwrap(), samples);
+
+            f32s_to_le_bytes(&samples, buf);
+            assert_eq!(f32s_from_le_bytes(buf).unwrap(), samples);
+        }
+    }
+
+    #[test]
+    fn test_f32_le_layout() {
+        let mut buf = [0u8; 4];
+        f32s_to_le_bytes(&[1.0], &mut buf);
+        assert_eq!(buf, 1.0f32.to_le_bytes());
+        assert_eq!(f32s_from_le_bytes(&0.5f32.to_le_bytes()).unwrap(), vec![0.5]);
+    }
+
+    #[test]
+    fn test_f32_be_layout() {
+        let mut buf = [0u8; 8];
+        f32s_to_be_bytes(&[1.0, -0.5], &mut buf);
+        assert_eq!(buf, [0x3f, 0x80, 0x00, 0x00, 0xbf, 0x00, 0x00, 0x00]);
+        assert_eq!(f32s_from_be_bytes(&buf).unwrap(), vec![1.0, -0.5]);
+        // Unaligned input decodes the same
+        let shifted = [&[0u8][..], &buf].concat();
+        assert_eq!(f32s_from_be_bytes(&shifted[1..]).unwrap(), vec![1.0, -0.5]);
+        assert!(f32s_from_be_bytes(&buf[..7]).is_none());
+    }
+
+    #[test]
+    fn test_f64_roundtrip() {
+        let samples = [0.1f64, -7.0, 1e-300];
+        let mut buf = vec![0u8; 24];
+        f64s_to_ne_bytes(&samples, &mut buf);
+        assert_eq!(f64s_from_ne_bytes(&buf).unwrap(), samples);
+        f64s_to_le_bytes(&samples, &mut buf);
+        assert_eq!(f64s_from_le_bytes(&buf).unwrap(), samples);
+        f64s_to_be_bytes(&samples, &mut buf);
+        assert_eq!(f64s_from_be_bytes(&buf).unwrap(), samples);
+    }
+
+    #[test]
+    fn test_bad_length_rejected() {
+        assert!(f32s_from_ne_bytes(&[0u8; 6]).is_none());
+        assert!(f32s_from_le_bytes(&[0u8; 3]).is_none());
+        assert!(f64s_from_ne_bytes(&[0u8; 12]).is_none());
+        assert!(cast_f32(&[0u8; 5]).is_none());
+        assert_eq!(f32s_from_ne_bytes(&[]).unwrap(), Vec::<f32>::new());
+    }
+
+    #[test]
+    fn test_cast_only_when_aligned() {
+        let storage = [0f32; 4];
+        // SAFETY: viewing initialized f32 storage as bytes
+        let bytes = unsafe { std::slice::from_raw_parts(storage.as_ptr() as *const u8, 16) };
+        assert_eq!(cast_f32(bytes).unwrap().len(), 4);
+        assert!(cast_f32(&bytes[1..13]).is_none());
+    }
+}
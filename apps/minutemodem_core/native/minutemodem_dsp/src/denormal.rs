@@ -0,0 +1,66 @@
+//! Flush-to-zero for decaying state
+//!
+//! A recursive state left to decay (an IIR filter after its input goes
+//! quiet, an exponential average with nothing left to average, leaky
+//! equalizer taps) eventually sinks below the smallest normal float.
+//! Arithmetic on those subnormals takes a slow microcode path on many x86
+//! and some ARM cores, up to ~100x per operation, which showed up as
+//! periodic latency spikes in long soak runs on silent input.
+//!
+//! Rather than switch the FPU into FTZ/DAZ mode for the whole process
+//! (which a NIF shares with the BEAM and every other NIF), the update
+//! paths that can decay clamp their state here. DENORMAL_FLOOR is far
+//! below anything audible or meaningful in the modem, and far above the
+//! subnormal range of either float width.
+
+/// Magnitudes below this are flushed to zero
+pub const DENORMAL_FLOOR: f64 = 1e-30;
+
+/// `x`, or 0.0 if its magnitude is below DENORMAL_FLOOR
+#[inline]
+pub fn flush_denormal(x: f64) -> f64 {
+    if x.abs() < DENORMAL_FLOOR {
+        0.0
+    } else {
+        x
+    }
+}
+
+/// f32 variant of flush_denormal()
+#[inline]
+pub fn flush_denormal_f32(x: f32) -> f32 {
+    if x.abs() < DENORMAL_FLOOR as f32 {
+        0.0
+    } else {
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flushes_only_below_floor() {
+        assert_eq!(flush_denormal(1e-31), 0.0);
+        assert_eq!(flush_denormal(-1e-31), 0.0);
+        assert_eq!(flush_denormal(f64::MIN_POSITIVE / 4.0), 0.0);
+        assert_eq!(flush_denormal(1e-29), 1e-29);
+        assert_eq!(flush_denormal(-0.5), -0.5);
+        assert!(flush_denormal(f64::NAN).is_nan());
+        assert_eq!(flush_denormal_f32(f32::MIN_POSITIVE / 4.0), 0.0);
+        assert_eq!(flush_denormal_f32(1e-29), 1e-29);
+    }
+
+    #[test]
+    fn test_decay_stops_at_zero() {
+        // A pole at 0.5 reaches the subnormals within ~1030 steps
+        let (mut raw, mut flushed) = (1.0_f64, 1.0_f64);
+        for _ in 0..1050 {
+            raw *= 0.5;
+            flushed = flush_denormal(flushed * 0.5);
+        }
+        assert!(raw.is_subnormal());
+        assert_eq!(flushed, 0.0);
+    }
+}
@@ -0,0 +1,316 @@
+//! FIR filter design
+//!
+//! Window functions, windowed-sinc low-pass and pulse shape (root raised
+//! cosine, raised cosine, Gaussian) coefficient generation. All designs are symmetric (linear phase) with
+//! an odd number of taps, so the group delay is exactly (len - 1) / 2.
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+/// Distinct RRC designs rrc_coefficients_shared() keeps; past this they
+/// are computed afresh each time
+const RRC_CACHE_CAP: usize = 64;
+
+/// Shared RRC designs by (sps, alpha bits, span)
+type RrcCache = Mutex<HashMap<(usize, u64, usize), Arc<[f64]>>>;
+
+/// Window function for FIR design and spectral analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    Rectangular,
+    Hann,
+    Hamming,
+}
+
+/// Symmetric window of length `len`
+pub fn window(kind: Window, len: usize) -> Vec<f64> {
+    if len <= 1 {
+        return vec![1.0; len];
+    }
+
+    let denom = (len - 1) as f64;
+    (0..len)
+        .map(|i| {
+            let c = (2.0 * PI * i as f64 / denom).cos();
+            match kind {
+                Window::Rectangular => 1.0,
+                Window::Hann => 0.5 - 0.5 * c,
+                Window::Hamming => 0.54 - 0.46 * c,
+            }
+        })
+        .collect()
+}
+
+/// Hamming-windowed sinc low-pass, normalized for unity DC gain
+///
+/// An even `num_taps` is bumped to the next odd length (type 1 linear
+/// phase).
+pub fn windowed_sinc_lowpass(cutoff_hz: f64, sample_rate: f64, num_taps: usize) -> Vec<f64> {
+    let num_taps = if num_taps.is_multiple_of(2) { num_taps + 1 } else { num_taps };
+    let center = (num_taps - 1) / 2;
+
+    // Normalized cutoff frequency (0 to 0.5)
+    let fc = cutoff_hz / sample_rate;
+    let win = window(Window::Hamming, num_taps);
+
+    let mut coeffs: Vec<f64> = (0..num_taps)
+        .map(|i| {
+            let n = i as f64 - center as f64;
+            let sinc = if n.abs() < 1e-10 {
+                2.0 * fc
+            } else {
+                (2.0 * PI * fc * n).sin() / (PI * n)
+            };
+            sinc * win[i]
+        })
+        .collect();
+
+    let sum: f64 = coeffs.iter().sum();
+    for c in &mut coeffs {
+        *c /= sum;
+    }
+
+    coeffs
+}
+
+/// Root raised cosine impulse response at `t` symbol periods
+///
+/// Handles the t = 0 and t = ±1/(4α) singularities explicitly.
+pub fn rrc_sample(t: f64, alpha: f64) -> f64 {
+    if t.abs() < 1e-10 {
+        1.0 + alpha * (4.0 / PI - 1.0)
+    } else if (t.abs() - 1.0 / (4.0 * alpha)).abs() < 1e-10 {
+        let term1 = (1.0 + 2.0 / PI) * (PI / (4.0 * alpha)).sin();
+        let term2 = (1.0 - 2.0 / PI) * (PI / (4.0 * alpha)).cos();
+        alpha / 2.0_f64.sqrt() * (term1 + term2)
+    } else {
+        let num = (PI * t * (1.0 - alpha)).sin()
+            + 4.0 * alpha * t * (PI * t * (1.0 + alpha)).cos();
+        let den = PI * t * (1.0 - (4.0 * alpha * t).powi(2));
+        num / den
+    }
+}
+
+/// Root raised cosine filter, normalized to unit energy
+///
+/// # Arguments
+/// * `sps` - Samples per symbol
+/// * `alpha` - Roll-off factor (excess bandwidth)
+/// * `span` - Filter span in symbols (each side of center)
+pub fn rrc_coefficients(sps: usize, alpha: f64, span: usize) -> Vec<f64> {
+    let len = 2 * span * sps + 1;
+
+    let mut coeffs: Vec<f64> = (0..len)
+        .map(|i| {
+            let t = (i as f64 - (len - 1) as f64 / 2.0) / sps as f64;
+            rrc_sample(t, alpha)
+        })
+        .collect();
+
+    let norm = coeffs.iter().map(|x| x * x).sum::<f64>().sqrt();
+    for c in &mut coeffs {
+        *c /= norm;
+    }
+
+    coeffs
+}
+
+/// rrc_coefficients(), designed once per (sps, alpha, span) and shared
+///
+/// Modulators and pulse shapers built with the same parameters hold the
+/// same taps, so their spectra match exactly.
+pub fn rrc_coefficients_shared(sps: usize, alpha: f64, span: usize) -> Arc<[f64]> {
+    static CACHE: OnceLock<RrcCache> = OnceLock::new();
+    let mut cache = CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    let key = (sps, alpha.to_bits(), span);
+    if let Some(coeffs) = cache.get(&key) {
+        return coeffs.clone();
+    }
+    let coeffs: Arc<[f64]> = rrc_coefficients(sps, alpha, span).into();
+    if cache.len() < RRC_CACHE_CAP {
+        cache.insert(key, coeffs.clone());
+    }
+    coeffs
+}
+
+/// Raised cosine impulse response at `t` symbol periods (1.0 at t = 0)
+///
+/// Handles the t = ±1/(2α) singularities explicitly.
+pub fn rc_sample(t: f64, alpha: f64) -> f64 {
+    let sinc = |x: f64| if x.abs() < 1e-10 { 1.0 } else { (PI * x).sin() / (PI * x) };
+    if alpha > 0.0 && (t.abs() - 1.0 / (2.0 * alpha)).abs() < 1e-10 {
+        PI / 4.0 * sinc(1.0 / (2.0 * alpha))
+    } else {
+        sinc(t) * (PI * alpha * t).cos() / (1.0 - (2.0 * alpha * t).powi(2))
+    }
+}
+
+/// Raised cosine filter, normalized to unit energy
+///
+/// Nyquist on its own, for a transmitter whose receiver has no matched
+/// filter. Same arguments and length as rrc_coefficients.
+pub fn rc_coefficients(sps: usize, alpha: f64, span: usize) -> Vec<f64> {
+    sampled_pulse(sps, span, |t| rc_sample(t, alpha))
+}
+
+/// Gaussian pulse with bandwidth-time product `bt`, normalized to unit
+/// energy
+///
+/// The -3 dB bandwidth is `bt` times the symbol rate (as in GMSK).
+pub fn gaussian_coefficients(sps: usize, bt: f64, span: usize) -> Vec<f64> {
+    let k = 2.0 * (PI * bt).powi(2) / std::f64::consts::LN_2;
+    sampled_pulse(sps, span, |t| (-k * t * t).exp())
+}
+
+/// `pulse` (in symbol periods) sampled over ±span symbols, unit energy
+fn sampled_pulse(sps: usize, span: usize, pulse: impl Fn(f64) -> f64) -> Vec<f64> {
+    let len = 2 * span * sps + 1;
+    let mut coeffs: Vec<f64> = (0..len)
+        .map(|i| pulse((i as f64 - (len - 1) as f64 / 2.0) / sps as f64))
+        .collect();
+
+    let norm = coeffs.iter().map(|x| x * x).sum::<f64>().sqrt();
+    for c in &mut coeffs {
+        *c /= norm;
+    }
+
+    coeffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_shapes() {
+        let hamming = window(Window::Hamming, 5);
+        assert!((hamming[0] - 0.08).abs() < 1e-12);
+        assert!((hamming[2] - 1.0).abs() < 1e-12);
+        assert!((hamming[4] - 0.08).abs() < 1e-12);
+
+        let hann = window(Window::Hann, 5);
+        assert!(hann[0].abs() < 1e-12);
+        assert!((hann[1] - 0.5).abs() < 1e-12);
+        assert!((hann[2] - 1.0).abs() < 1e-12);
+
+        assert_eq!(window(Window::Rectangular, 3), vec![1.0; 3]);
+        assert_eq!(window(Window::Hann, 1), vec![1.0]);
+        assert!(window(Window::Hann, 0).is_empty());
+    }
+
+    #[test]
+    fn test_lowpass_unity_dc_and_symmetry() {
+        let h = windowed_sinc_lowpass(2800.0, 9600.0, 31);
+        assert_eq!(h.len(), 31);
+        assert!((h.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        for i in 0..15 {
+            assert!((h[i] - h[30 - i]).abs() < 1e-15);
+        }
+        // Center tap is the peak
+        assert!(h.iter().all(|&c| c <= h[15]));
+    }
+
+    #[test]
+    fn test_lowpass_even_taps_bumped_to_odd() {
+        assert_eq!(windowed_sinc_lowpass(1000.0, 8000.0, 30).len(), 31);
+    }
+
+    #[test]
+    fn test_lowpass_stopband() {
+        let h = windowed_sinc_lowpass(1000.0, 9600.0, 63);
+        // Gain at 3 kHz via direct DTFT
+        let w = 2.0 * PI * 3000.0 / 9600.0;
+        let (re, im) = h.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, &c)| {
+            (re + c * (w * n as f64).cos(), im - c * (w * n as f64).sin())
+        });
+        let gain_db = 10.0 * (re * re + im * im).log10();
+        assert!(gain_db < -40.0, "stopband gain {:.1} dB", gain_db);
+    }
+
+    #[test]
+    fn test_rrc_length_energy_symmetry() {
+        let h = rrc_coefficients(4, 0.35, 6);
+        assert_eq!(h.len(), 49);
+        assert!((h.iter().map(|x| x * x).sum::<f64>() - 1.0).abs() < 1e-12);
+        for i in 0..24 {
+            assert!((h[i] - h[48 - i]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_rrc_shared_is_cached_design() {
+        let a = rrc_coefficients_shared(4, 0.35, 6);
+        let b = rrc_coefficients_shared(4, 0.35, 6);
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(a[..], rrc_coefficients(4, 0.35, 6)[..]);
+        assert!(!Arc::ptr_eq(&a, &rrc_coefficients_shared(4, 0.25, 6)));
+    }
+
+    #[test]
+    fn test_rrc_singularity_is_continuous() {
+        // t = 1/(4α) hits the special-case branch; neighbors use the general one
+        let alpha = 0.25;
+        let t0 = 1.0 / (4.0 * alpha);
+        let at = rrc_sample(t0, alpha);
+        let near = rrc_sample(t0 + 1e-6, alpha);
+        assert!((at - near).abs() < 1e-4, "{} vs {}", at, near);
+    }
+
+    #[test]
+    fn test_rrc_cascade_is_nyquist() {
+        // RRC ⊛ RRC = raised cosine: zero crossings at nonzero symbol times
+        let sps = 8;
+        let h = rrc_coefficients(sps, 0.35, 8);
+        let rc: Vec<f64> = (0..2 * h.len() - 1)
+            .map(|k| {
+                (0..h.len())
+                    .filter(|&n| k >= n && k - n < h.len())
+                    .map(|n| h[n] * h[k - n])
+                    .sum()
+            })
+            .collect();
+        let center = h.len() - 1;
+        for m in 1..6 {
+            let isi = rc[center + m * sps] / rc[center];
+            assert!(isi.abs() < 0.01, "ISI at {} symbols: {}", m, isi);
+        }
+    }
+
+    #[test]
+    fn test_rc_is_nyquist_on_its_own() {
+        let sps = 8;
+        let h = rc_coefficients(sps, 0.35, 6);
+        assert_eq!(h.len(), 97);
+        assert!((h.iter().map(|x| x * x).sum::<f64>() - 1.0).abs() < 1e-12);
+        let center = h.len() / 2;
+        for m in 1..=6 {
+            assert!((h[center + m * sps] / h[center]).abs() < 1e-12, "ISI at {} symbols", m);
+        }
+        // Singular points are continuous
+        let t0 = 1.0 / (2.0 * 0.25);
+        assert!((rc_sample(t0, 0.25) - rc_sample(t0 + 1e-6, 0.25)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_gaussian_3db_bandwidth() {
+        // |H(f)| at f = BT (in symbol-rate units) is -3 dB
+        let sps = 16;
+        let bt = 0.3;
+        let h = gaussian_coefficients(sps, bt, 4);
+        assert!((h.iter().map(|x| x * x).sum::<f64>() - 1.0).abs() < 1e-12);
+        let gain = |f: f64| {
+            let w = 2.0 * PI * f / sps as f64;
+            let (re, im) = h.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, &c)| {
+                (re + c * (w * n as f64).cos(), im - c * (w * n as f64).sin())
+            });
+            (re * re + im * im).sqrt()
+        };
+        let db = 20.0 * (gain(bt) / gain(0.0)).log10();
+        assert!((db + 3.01).abs() < 0.05, "{:.2} dB at BT", db);
+    }
+}
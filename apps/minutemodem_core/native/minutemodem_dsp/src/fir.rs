@@ -0,0 +1,119 @@
+//! Ring-buffer FIR filter
+//!
+//! Sample-at-a-time direct-form FIR. The history is a circular buffer so
+//! each sample costs one write plus the dot product, with no shifting.
+
+/// Direct-form FIR with circular history
+#[derive(Debug, Clone)]
+pub struct RingFir {
+    coeffs: Vec<f64>,
+    history: Vec<f64>,
+    write_idx: usize,
+}
+
+impl RingFir {
+    /// Create a filter from its impulse response
+    ///
+    /// # Panics
+    /// If `coeffs` is empty.
+    pub fn new(coeffs: Vec<f64>) -> Self {
+        assert!(!coeffs.is_empty(), "FIR needs at least one tap");
+        let len = coeffs.len();
+        Self {
+            coeffs,
+            history: vec![0.0; len],
+            write_idx: 0,
+        }
+    }
+
+    /// Process one sample through the filter
+    #[inline]
+    pub fn process(&mut self, x: f64) -> f64 {
+        self.history[self.write_idx] = x;
+
+        // coeffs[0] multiplies the newest sample
+        let len = self.coeffs.len();
+        let mut sum = 0.0;
+        for i in 0..len {
+            let hist_idx = (self.write_idx + len - i) % len;
+            sum += self.history[hist_idx] * self.coeffs[i];
+        }
+
+        self.write_idx = (self.write_idx + 1) % len;
+
+        sum
+    }
+
+    /// Clear the history
+    pub fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.write_idx = 0;
+    }
+
+    /// Number of taps
+    pub fn len(&self) -> usize {
+        self.coeffs.len()
+    }
+
+    /// Always false; a filter has at least one tap
+    pub fn is_empty(&self) -> bool {
+        self.coeffs.is_empty()
+    }
+
+    /// Group delay in samples, assuming a symmetric (linear-phase) design
+    pub fn group_delay(&self) -> usize {
+        (self.coeffs.len() - 1) / 2
+    }
+
+    pub fn coeffs(&self) -> &[f64] {
+        &self.coeffs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_impulse_response_is_coeffs() {
+        let coeffs = vec![0.5, -0.25, 0.125, 1.0];
+        let mut fir = RingFir::new(coeffs.clone());
+        let out: Vec<f64> = (0..6).map(|n| fir.process(if n == 0 { 1.0 } else { 0.0 })).collect();
+        assert_eq!(&out[..4], &coeffs[..]);
+        assert_eq!(&out[4..], &[0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_matches_direct_convolution() {
+        let coeffs = vec![0.1, 0.2, 0.4, 0.2, 0.1];
+        let input: Vec<f64> = (0..50).map(|n| ((n * 13) % 7) as f64 - 3.0).collect();
+
+        let mut fir = RingFir::new(coeffs.clone());
+        for (n, &x) in input.iter().enumerate() {
+            let y = fir.process(x);
+            let expected: f64 = (0..coeffs.len())
+                .filter(|&k| k <= n)
+                .map(|k| coeffs[k] * input[n - k])
+                .sum();
+            assert!((y - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_reset_and_group_delay() {
+        let mut fir = RingFir::new(vec![1.0; 7]);
+        assert_eq!(fir.len(), 7);
+        assert_eq!(fir.group_delay(), 3);
+
+        let first: Vec<f64> = (0..10).map(|n| fir.process(n as f64)).collect();
+        fir.reset();
+        let again: Vec<f64> = (0..10).map(|n| fir.process(n as f64)).collect();
+        assert_eq!(first, again);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_rejected() {
+        RingFir::new(Vec::new());
+    }
+}
@@ -0,0 +1,61 @@
+//! Panics caught at the NIF boundary
+//!
+//! Every NIF crate runs its NIF bodies through catch() and returns the
+//! message as `{:error, {:panic, message}}` rather than letting the panic
+//! reach the VM. Building that error term needs rustler, so it stays in
+//! each crate; what's caught and how the message is cut is shared here.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Longest panic message returned, in bytes
+pub const MAX_PANIC_MESSAGE: usize = 256;
+
+/// A panic payload's message, cut to MAX_PANIC_MESSAGE bytes
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload");
+    let mut end = message.len().min(MAX_PANIC_MESSAGE);
+    while !message.is_char_boundary(end) {
+        end -= 1;
+    }
+    message[..end].to_string()
+}
+
+/// Run `body`, returning the message of any panic instead of unwinding
+/// out of it
+pub fn catch<T>(body: impl FnOnce() -> T) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(body)).map_err(|payload| panic_message(payload.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_message_is_truncated() {
+        let short: Box<dyn Any + Send> = Box::new("equalizer index 17");
+        assert_eq!(panic_message(short.as_ref()), "equalizer index 17");
+
+        let long: Box<dyn Any + Send> = Box::new("é".repeat(MAX_PANIC_MESSAGE));
+        let message = panic_message(long.as_ref());
+        assert_eq!(message.len(), MAX_PANIC_MESSAGE);
+        assert!(message.chars().all(|c| c == 'é'));
+
+        let formatted: Box<dyn Any + Send> = Box::new(format!("superframe index {}", 3));
+        assert_eq!(panic_message(formatted.as_ref()), "superframe index 3");
+
+        let other: Box<dyn Any + Send> = Box::new(42u8);
+        assert_eq!(panic_message(other.as_ref()), "non-string panic payload");
+    }
+
+    #[test]
+    fn test_catch() {
+        assert_eq!(catch(|| 7), Ok(7));
+        let caught = catch(|| -> u32 { panic!("block of {} samples", 480) });
+        assert_eq!(caught, Err("block of 480 samples".to_string()));
+    }
+}
@@ -0,0 +1,76 @@
+//! Input level sanity checks
+//!
+//! Two scaling mistakes are easy to make at the NIF boundary and hard to
+//! spot from the symptoms:
+//! - ±1.0 floats rounded into an i16 API arrive as near-silence (a few
+//!   counts), and the demodulator finds nothing
+//! - i16-range values handed to a float API sit far above full scale, and
+//!   everything downstream clips
+//!
+//! These checks look at an evenly spaced subset of the block (at most
+//! LEVEL_CHECK_POINTS samples), so they cost the same however long the
+//! block is. They can miss a short peak; they're a hint, not a guarantee.
+
+/// Most samples of a block the checks look at
+pub const LEVEL_CHECK_POINTS: usize = 256;
+
+/// An i16 block whose peak is below this many counts looks like ±1.0 data
+pub const I16_QUIET_COUNTS: u16 = 64;
+
+/// A float block whose peak is above this looks like i16-scaled data
+pub const FLOAT_LOUD: f64 = 8.0;
+
+/// Every n-th sample, so that at most LEVEL_CHECK_POINTS are visited
+fn subset<T: Copy>(samples: &[T]) -> impl Iterator<Item = T> + '_ {
+    let stride = samples.len().div_ceil(LEVEL_CHECK_POINTS).max(1);
+    samples.iter().step_by(stride).copied()
+}
+
+/// Sampled peak of an i16 block, if it's suspiciously quiet
+///
+/// Digital silence (every sampled value zero) is normal idle input and
+/// isn't reported.
+pub fn quiet_i16_peak(samples: &[i16]) -> Option<u16> {
+    let peak = subset(samples).map(i16::unsigned_abs).max()?;
+    (peak > 0 && peak < I16_QUIET_COUNTS).then_some(peak)
+}
+
+/// Sampled peak of a float block, if it's suspiciously loud
+pub fn loud_float_peak<T: Copy + Into<f64>>(samples: &[T]) -> Option<f64> {
+    let peak = subset(samples).map(|x| x.into().abs()).fold(0.0, f64::max);
+    (peak > FLOAT_LOUD).then_some(peak)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float_data_in_i16_api() {
+        // A ±1.0 tone rounded into i16
+        let rounded: Vec<i16> = (0..4800).map(|n| (n as f64 * 0.3).sin().round() as i16).collect();
+        assert_eq!(quiet_i16_peak(&rounded), Some(1));
+
+        let tone: Vec<i16> = (0..4800).map(|n| ((n as f64 * 0.3).sin() * 12000.0) as i16).collect();
+        assert_eq!(quiet_i16_peak(&tone), None);
+        assert_eq!(quiet_i16_peak(&[0; 4800]), None);
+        assert_eq!(quiet_i16_peak(&[]), None);
+    }
+
+    #[test]
+    fn test_i16_data_in_float_api() {
+        let scaled: Vec<f32> = (0..4800).map(|n| (n as f32 * 0.3).sin() * 16000.0).collect();
+        assert!(loud_float_peak(&scaled).unwrap() > 15_000.0);
+
+        let tone: Vec<f64> = (0..4800).map(|n| (n as f64 * 0.3).sin() * 0.9).collect();
+        assert_eq!(loud_float_peak(&tone), None);
+        // Some headroom past full scale is fine
+        assert_eq!(loud_float_peak(&[4.0f32, -7.5]), None);
+    }
+
+    #[test]
+    fn test_subset_is_bounded() {
+        assert!(subset(&vec![0u8; 1_000_000]).count() <= LEVEL_CHECK_POINTS);
+        assert_eq!(subset(&[0u8; 10]).count(), 10);
+    }
+}
@@ -0,0 +1,40 @@
+//! Shared DSP primitives for the MinuteModem NIF crates
+//!
+//! Plain Rust with no rustler dependency, so phy_modem and channel_physics
+//! build against one copy of:
+//! - `Complex` arithmetic
+//! - Sample/byte conversions (i16, f32, f64)
+//! - Window, windowed-sinc and pulse shape (RRC, RC, Gaussian) design
+//! - A ring-buffer FIR filter
+//! - Trig-free oscillators for carriers at a quarter or half the sample rate
+//! - Input level sanity checks
+//! - The bounds check on arguments that size allocations
+//! - Flush-to-zero for state that decays toward the subnormals
+//! - The versioned wire format for I/Q, symbol and LLR binaries
+//! - Live resource counts for the census NIFs
+//! - Provenance strings for exported reports and captures, and the build
+//!   script half that records the git describe behind them
+//! - Panic catching and message truncation for the NIF boundary
+
+pub mod census;
+pub mod complex;
+pub mod convert;
+pub mod denormal;
+pub mod design;
+pub mod fir;
+pub mod guard;
+pub mod level;
+pub mod limits;
+pub mod lo;
+pub mod provenance;
+pub mod wire;
+
+pub use complex::Complex;
+pub use denormal::{flush_denormal, flush_denormal_f32, DENORMAL_FLOOR};
+pub use design::{
+    gaussian_coefficients, rc_coefficients, rc_sample, rrc_coefficients, rrc_coefficients_shared,
+    rrc_sample, window, windowed_sinc_lowpass, Window,
+};
+pub use fir::RingFir;
+pub use lo::{QuadrantLo, TrivialLo};
+pub use provenance::Provenance;
@@ -0,0 +1,36 @@
+//! Upper bounds on arguments that size allocations
+//!
+//! Each NIF crate keeps its own maxima and checks a caller's number
+//! against them before allocating anything sized by it. The failure is
+//! returned to Elixir as `{:error, {:out_of_range, param, max}}`; building
+//! that term needs rustler, so the Encoder stays in each crate.
+
+/// A number that sizes an allocation was above its maximum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfRange {
+    pub param: &'static str,
+    pub max: u64,
+}
+
+/// OutOfRange if `value` is above `max`
+pub fn check_max(param: &'static str, value: u64, max: u64) -> Result<(), OutOfRange> {
+    if value > max {
+        return Err(OutOfRange { param, max });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_max() {
+        assert_eq!(check_max("fft_size", 1 << 16, 1 << 16), Ok(()));
+        assert_eq!(check_max("fft_size", 0, 1 << 16), Ok(()));
+        assert_eq!(
+            check_max("fft_size", (1 << 16) + 1, 1 << 16),
+            Err(OutOfRange { param: "fft_size", max: 1 << 16 })
+        );
+    }
+}
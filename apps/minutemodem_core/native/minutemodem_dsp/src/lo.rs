@@ -0,0 +1,192 @@
+//! Local oscillator fast path for trivial carriers
+//!
+//! A carrier at exactly a quarter or half of the sample rate only visits
+//! the phases 0, π/2, π, 3π/2 (or 0, π), where cos and sin are 1, 0 or -1.
+//! Mixing with it needs no trig at all, and its phase can be kept as a
+//! whole count of quarter turns instead of an accumulating f64.
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::Complex;
+
+/// Samples between re-anchoring a rotated phasor from its phase
+const REANCHOR_INTERVAL: u32 = 64;
+
+/// A carrier that advances a whole number of quarter turns per sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrivialLo {
+    /// carrier = sample_rate / 4
+    Quarter,
+    /// carrier = sample_rate / 2
+    Half,
+}
+
+impl TrivialLo {
+    /// The fast path for `carrier_hz` at `sample_rate`, if it has one
+    pub fn detect(carrier_hz: f64, sample_rate: f64) -> Option<Self> {
+        if carrier_hz * 4.0 == sample_rate {
+            Some(Self::Quarter)
+        } else if carrier_hz * 2.0 == sample_rate {
+            Some(Self::Half)
+        } else {
+            None
+        }
+    }
+
+    /// Quarter turns per sample
+    #[inline]
+    pub fn step(self) -> u8 {
+        match self {
+            Self::Quarter => 1,
+            Self::Half => 2,
+        }
+    }
+}
+
+/// (cos, sin) of `quadrant` quarter turns, taken mod 4
+#[inline]
+pub fn quadrant_cos_sin(quadrant: u8) -> (f64, f64) {
+    match quadrant & 3 {
+        0 => (1.0, 0.0),
+        1 => (0.0, 1.0),
+        2 => (-1.0, 0.0),
+        _ => (0.0, -1.0),
+    }
+}
+
+/// `z` turned by `quadrant` quarter turns; exact, as it only swaps and
+/// negates parts
+#[inline]
+pub fn rotate_quadrants(z: Complex, quadrant: u8) -> Complex {
+    match quadrant & 3 {
+        0 => z,
+        1 => Complex::new(-z.im, z.re),
+        2 => Complex::new(-z.re, -z.im),
+        _ => Complex::new(z.im, -z.re),
+    }
+}
+
+/// Oscillator at a trivial carrier plus a small residual frequency
+///
+/// The carrier is a count of quarter turns. The residual (a PLL's
+/// correction, say) is a phasor turned by a fixed step each sample and
+/// re-anchored from its phase when the residual changes and every
+/// REANCHOR_INTERVAL samples, so there's a sin_cos every few dozen
+/// samples rather than one per sample, and the result stays within
+/// ~1e-14 of mixing at the accumulated phase.
+#[derive(Debug, Clone)]
+pub struct QuadrantLo {
+    lo: TrivialLo,
+    quadrant: u8,
+    /// Phase beyond the quarter turns, radians, within about ±π/4
+    residual: f64,
+    residual_inc: f64,
+    phasor: Complex,
+    turn: Complex,
+    since_anchor: u32,
+}
+
+impl QuadrantLo {
+    /// Oscillator starting at `phase` radians, advancing the carrier's
+    /// quarter turns plus `residual_inc` radians per sample
+    pub fn new(lo: TrivialLo, phase: f64, residual_inc: f64) -> Self {
+        let mut osc = Self {
+            lo,
+            quadrant: 0,
+            residual: phase,
+            residual_inc,
+            phasor: Complex::new(1.0, 0.0),
+            turn: Complex::new(1.0, 0.0),
+            since_anchor: 0,
+        };
+        osc.anchor();
+        osc
+    }
+
+    /// (cos, sin) at the current phase
+    #[inline]
+    pub fn cos_sin(&self) -> (f64, f64) {
+        let z = rotate_quadrants(self.phasor, self.quadrant);
+        (z.re, z.im)
+    }
+
+    /// Step one sample with `residual_inc` radians on top of the carrier
+    #[inline]
+    pub fn advance(&mut self, residual_inc: f64) {
+        self.quadrant = (self.quadrant + self.lo.step()) & 3;
+        self.residual += residual_inc;
+        self.since_anchor += 1;
+        if residual_inc != self.residual_inc || self.since_anchor >= REANCHOR_INTERVAL {
+            self.residual_inc = residual_inc;
+            self.anchor();
+        } else if self.residual_inc != 0.0 {
+            self.phasor = self.phasor * self.turn;
+        }
+    }
+
+    /// Current phase, radians, [0, 2π)
+    pub fn phase(&self) -> f64 {
+        let phase = (self.quadrant as f64 * FRAC_PI_2 + self.residual).rem_euclid(2.0 * PI);
+        if phase < 2.0 * PI {
+            phase
+        } else {
+            0.0
+        }
+    }
+
+    /// Fold whole quarter turns of the residual into the count and
+    /// recompute the phasors from the phases
+    fn anchor(&mut self) {
+        let turns = (self.residual / FRAC_PI_2).round();
+        self.residual -= turns * FRAC_PI_2;
+        self.quadrant = (self.quadrant as i64 + turns as i64).rem_euclid(4) as u8;
+        self.phasor = Complex::from_polar(1.0, self.residual);
+        self.turn = Complex::from_polar(1.0, self.residual_inc);
+        self.since_anchor = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(TrivialLo::detect(2400.0, 9600.0), Some(TrivialLo::Quarter));
+        assert_eq!(TrivialLo::detect(12000.0, 48000.0), Some(TrivialLo::Quarter));
+        assert_eq!(TrivialLo::detect(4800.0, 9600.0), Some(TrivialLo::Half));
+        assert_eq!(TrivialLo::detect(1800.0, 9600.0), None);
+        assert_eq!(TrivialLo::detect(2400.5, 9600.0), None);
+    }
+
+    #[test]
+    fn test_rotate_quadrants_matches_trig() {
+        let z = Complex::new(0.3, -0.8);
+        for quadrant in 0..8 {
+            let (c, s) = quadrant_cos_sin(quadrant);
+            let expected = z * Complex::new(c, s);
+            assert_eq!(rotate_quadrants(z, quadrant), expected);
+            let angle = quadrant as f64 * FRAC_PI_2;
+            assert!((c - angle.cos()).abs() < 1e-15 && (s - angle.sin()).abs() < 1e-15);
+        }
+    }
+
+    #[test]
+    fn test_quadrant_lo_tracks_accumulated_phase() {
+        for (lo, carrier_inc) in [(TrivialLo::Quarter, FRAC_PI_2), (TrivialLo::Half, PI)] {
+            let mut osc = QuadrantLo::new(lo, 1.0, 0.0);
+            let mut phase = 1.0f64;
+            for n in 0..5000 {
+                // A residual that changes now and then, as a PLL's does
+                let residual_inc = if n < 1000 { 0.0 } else { 1e-3 * ((n / 40) % 5) as f64 - 2e-3 };
+                let (c, s) = osc.cos_sin();
+                assert!((c - phase.cos()).abs() < 1e-12, "cos at {n}");
+                assert!((s - phase.sin()).abs() < 1e-12, "sin at {n}");
+                osc.advance(residual_inc);
+                phase = (phase + carrier_inc + residual_inc).rem_euclid(2.0 * PI);
+            }
+            let diff = (osc.phase() - phase).rem_euclid(2.0 * PI);
+            assert!(diff.min(2.0 * PI - diff) < 1e-12);
+        }
+    }
+}
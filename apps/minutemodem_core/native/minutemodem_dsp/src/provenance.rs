@@ -0,0 +1,142 @@
+//! Provenance strings for exported artifacts
+//!
+//! Every report, capture and export a NIF crate hands out carries a
+//! one-line "license plate" naming the code that produced it:
+//!
+//! ```text
+//! phy_modem/0.1.0 git=v0.4-12-g3f2a9c1-dirty profile=release features=nif,legacy-wire tables=9d1c04e2b7a35f60
+//! ```
+//!
+//! The crate name and version come from Cargo, the `git describe` and
+//! build profile from the crate's build script (via record_build()), and
+//! the features from `cfg!`. `tables` is an FNV-1a hash over the bit
+//! patterns of the algorithm tables the crate names (constellations,
+//! pulse shapes, presets), taken from the tables as compiled, so a table
+//! edited in a working tree changes the plate even where the describe
+//! can't say how (`-dirty` covers any edit at all).
+//!
+//! Each crate builds its plate once and hands out the same `&'static str`
+//! for the life of the process.
+
+use std::path::Path;
+use std::process::Command;
+
+/// FNV-1a 64-bit offset basis and prime
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Builder for a crate's provenance string
+#[derive(Debug, Clone)]
+pub struct Provenance {
+    name: &'static str,
+    version: &'static str,
+    git: &'static str,
+    profile: &'static str,
+    features: Vec<&'static str>,
+    tables: u64,
+}
+
+impl Provenance {
+    /// `git` and `profile` as the build script recorded them; empty
+    /// strings (no build script, or git unavailable) read as "unknown"
+    pub fn new(name: &'static str, version: &'static str, git: &'static str, profile: &'static str) -> Self {
+        let known = |s: &'static str| if s.is_empty() { "unknown" } else { s };
+        Self {
+            name,
+            version,
+            git: known(git),
+            profile: known(profile),
+            features: Vec::new(),
+            tables: FNV_OFFSET,
+        }
+    }
+
+    /// List `name` among the features if `enabled`
+    pub fn feature(mut self, name: &'static str, enabled: bool) -> Self {
+        if enabled {
+            self.features.push(name);
+        }
+        self
+    }
+
+    /// Hash a table into the plate: its name, length and values' bits
+    pub fn table(mut self, name: &str, values: &[f64]) -> Self {
+        let bytes = name
+            .bytes()
+            .chain((values.len() as u64).to_le_bytes())
+            .chain(values.iter().flat_map(|v| v.to_bits().to_le_bytes()));
+        self.tables = bytes.fold(self.tables, |h, b| (h ^ b as u64).wrapping_mul(FNV_PRIME));
+        self
+    }
+
+    pub fn build(&self) -> String {
+        let features = if self.features.is_empty() { "none".to_string() } else { self.features.join(",") };
+        format!(
+            "{}/{} git={} profile={} features={} tables={:016x}",
+            self.name, self.version, self.git, self.profile, features, self.tables
+        )
+    }
+}
+
+/// stdout of `git args`, trimmed, if git ran and succeeded
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Set MINUTEMODEM_GIT_DESCRIBE and MINUTEMODEM_BUILD_PROFILE for the
+/// crate being built
+///
+/// Call from the crate's build script, with minutemodem_dsp as a build
+/// dependency; the crate reads both back with `env!` for Provenance::new.
+pub fn record_build() {
+    let describe = git(&["describe", "--always", "--dirty", "--tags"]).unwrap_or_default();
+    println!("cargo:rustc-env=MINUTEMODEM_GIT_DESCRIBE={describe}");
+    println!("cargo:rustc-env=MINUTEMODEM_BUILD_PROFILE={}", std::env::var("PROFILE").unwrap_or_default());
+
+    // Describe again after a commit, checkout or edit to the sources
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src");
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        for name in ["HEAD", "index", "refs"] {
+            let path = Path::new(&git_dir).join(name);
+            if path.exists() {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plate(table: &[f64]) -> String {
+        Provenance::new("crate", "1.0.0", "v1-2-gabc", "release")
+            .feature("nif", true)
+            .feature("capi", false)
+            .table("points", table)
+            .build()
+    }
+
+    #[test]
+    fn test_plate_names_the_build() {
+        let plate = plate(&[1.0, -1.0]);
+        assert!(plate.starts_with("crate/1.0.0 git=v1-2-gabc profile=release features=nif tables="), "{plate}");
+        assert_eq!(Provenance::new("c", "0", "", "").build(), format!("c/0 git=unknown profile=unknown features=none tables={FNV_OFFSET:016x}"));
+    }
+
+    #[test]
+    fn test_any_table_change_changes_the_hash() {
+        let base = plate(&[1.0, -1.0]);
+        assert_eq!(plate(&[1.0, -1.0]), base);
+        assert_ne!(plate(&[1.0, -1.0 + f64::EPSILON]), base);
+        assert_ne!(plate(&[1.0, -1.0, 0.0]), base);
+        // A table's name is part of it, so moving values between tables counts
+        let split = Provenance::new("crate", "1.0.0", "v1-2-gabc", "release")
+            .feature("nif", true)
+            .table("point", &[1.0, -1.0])
+            .build();
+        assert_ne!(split, base);
+    }
+}
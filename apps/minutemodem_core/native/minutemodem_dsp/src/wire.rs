@@ -0,0 +1,521 @@
+//! Versioned binary formats for I/Q, symbols and LLRs
+//!
+//! Binaries that cross between the NIF crates and Elixir carry a 12-byte
+//! header, so a reader can tell what it has been handed instead of
+//! guessing from the length:
+//!
+//! | Offset | Size | Field                                         |
+//! |--------|------|-----------------------------------------------|
+//! | 0      | 4    | Magic, `"MMWF"`                               |
+//! | 4      | 1    | Version, 1                                    |
+//! | 5      | 1    | Format: 1 I/Q, 2 symbols, 3 LLRs              |
+//! | 6      | 1    | Element: bits per element (see below)         |
+//! | 7      | 1    | Reserved, 0                                   |
+//! | 8      | 4    | Count of elements, u32 little-endian          |
+//!
+//! and the payload follows, exactly as long as the count implies:
+//!
+//! - `IQ_F32_INTERLEAVED_V1` (format 1, element 32): I0 Q0 I1 Q1 ... as
+//!   f32-le; the count is of I/Q pairs, 8 bytes each.
+//! - `SYMBOLS_PACKED_V1` (format 2, element 1..=8 bits per symbol):
+//!   symbols packed most significant bit first, the last byte padded
+//!   with zero bits.
+//! - `LLR_F32_V1` (format 3, element 32): one f32-le per bit, ln(P(0) /
+//!   P(1)).
+//!
+//! Payloads are little-endian whatever the host, so a binary written on
+//! one machine reads the same on another: IQ exports and capture drains
+//! pass between federated nodes of either byte order unchanged, with no
+//! order to agree on out of band (unlike process_block's native-endian
+//! samples). In Elixir:
+//!
+//! ```text
+//! <<"MMWF", 1, 1, 32, 0, count::little-32, iq::binary>>
+//! for <<i::float-little-32, q::float-little-32 <- iq>>, do: {i, q}
+//! ```
+//!
+//! ## Legacy input
+//!
+//! Before the header, these binaries were bare payloads. During the
+//! transition a reader given `Legacy::Accept` takes a binary that doesn't
+//! start with the magic as a bare payload in the same element format (a
+//! byte per symbol, for symbols). A bare I/Q payload whose first I happens
+//! to encode as `"MMWF"` (13_779.33) is misread as a header and
+//! rejected; no real sample sits there.
+
+/// First four bytes of every framed binary
+pub const MAGIC: [u8; 4] = *b"MMWF";
+/// Header version written, and the only one read
+pub const VERSION: u8 = 1;
+/// Bytes before the payload
+pub const HEADER_LEN: usize = 12;
+
+const FORMAT_IQ_F32: u8 = 1;
+const FORMAT_SYMBOLS: u8 = 2;
+const FORMAT_LLR_F32: u8 = 3;
+
+/// What a binary holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// IQ_F32_INTERLEAVED_V1
+    IqF32,
+    /// SYMBOLS_PACKED_V1, `bits` per symbol (1..=8)
+    Symbols { bits: u8 },
+    /// LLR_F32_V1
+    LlrF32,
+}
+
+impl Format {
+    fn id(&self) -> u8 {
+        match self {
+            Format::IqF32 => FORMAT_IQ_F32,
+            Format::Symbols { .. } => FORMAT_SYMBOLS,
+            Format::LlrF32 => FORMAT_LLR_F32,
+        }
+    }
+
+    fn element(&self) -> u8 {
+        match self {
+            Format::Symbols { bits } => *bits,
+            Format::IqF32 | Format::LlrF32 => 32,
+        }
+    }
+
+    fn from_header(id: u8, element: u8) -> Result<Self, WireError> {
+        let format = match id {
+            FORMAT_IQ_F32 => Format::IqF32,
+            FORMAT_SYMBOLS => Format::Symbols { bits: element },
+            FORMAT_LLR_F32 => Format::LlrF32,
+            _ => return Err(WireError::UnknownFormat),
+        };
+        let valid = match format {
+            Format::Symbols { bits } => (1..=8).contains(&bits),
+            Format::IqF32 | Format::LlrF32 => element == 32,
+        };
+        if !valid {
+            return Err(WireError::BadElement);
+        }
+        Ok(format)
+    }
+
+    /// Payload bytes for `count` elements
+    pub fn payload_len(&self, count: usize) -> usize {
+        match self {
+            Format::IqF32 => 8 * count,
+            Format::Symbols { bits } => (count * *bits as usize).div_ceil(8),
+            Format::LlrF32 => 4 * count,
+        }
+    }
+
+    /// Header and payload bytes for `count` elements
+    pub fn frame_len(&self, count: usize) -> usize {
+        HEADER_LEN + self.payload_len(count)
+    }
+}
+
+/// Why a binary was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireError {
+    /// Shorter than a header, and not accepted as a bare payload
+    TooShort,
+    /// Doesn't start with MAGIC, and not accepted as a bare payload
+    BadMagic,
+    UnsupportedVersion,
+    UnknownFormat,
+    /// A different format from the one asked for
+    WrongFormat,
+    /// Element width invalid for the format, or a symbol too wide for it
+    BadElement,
+    /// Reserved byte not zero
+    BadReserved,
+    /// Payload not the length the count implies
+    LengthMismatch,
+}
+
+impl WireError {
+    /// The error as a snake_case reason, for NIF errors
+    pub fn name(&self) -> &'static str {
+        match self {
+            WireError::TooShort => "too_short",
+            WireError::BadMagic => "bad_magic",
+            WireError::UnsupportedVersion => "unsupported_version",
+            WireError::UnknownFormat => "unknown_format",
+            WireError::WrongFormat => "wrong_format",
+            WireError::BadElement => "bad_element",
+            WireError::BadReserved => "bad_reserved",
+            WireError::LengthMismatch => "length_mismatch",
+        }
+    }
+}
+
+/// Whether a reader takes headerless binaries (see the module docs)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Legacy {
+    Accept,
+    Reject,
+}
+
+/// A parsed header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub format: Format,
+    pub count: usize,
+}
+
+impl Header {
+    /// The header's bytes
+    ///
+    /// # Panics
+    /// If the count doesn't fit in a u32.
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let count = u32::try_from(self.count).expect("wire count exceeds u32");
+        let mut out = [0; HEADER_LEN];
+        out[..4].copy_from_slice(&MAGIC);
+        out[4] = VERSION;
+        out[5] = self.format.id();
+        out[6] = self.format.element();
+        out[8..].copy_from_slice(&count.to_le_bytes());
+        out
+    }
+
+    /// Parse and check the header at the start of `bytes`
+    ///
+    /// The payload isn't looked at; see `split`.
+    pub fn parse(bytes: &[u8]) -> Result<Self, WireError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(WireError::TooShort);
+        }
+        if !is_framed(bytes) {
+            return Err(WireError::BadMagic);
+        }
+        if bytes[4] != VERSION {
+            return Err(WireError::UnsupportedVersion);
+        }
+        let format = Format::from_header(bytes[5], bytes[6])?;
+        if bytes[7] != 0 {
+            return Err(WireError::BadReserved);
+        }
+        let count = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize;
+        Ok(Header { format, count })
+    }
+
+    /// Parse the header and return it with the payload, checked to be
+    /// exactly as long as the count implies
+    pub fn split(bytes: &[u8]) -> Result<(Self, &[u8]), WireError> {
+        let header = Self::parse(bytes)?;
+        let payload = &bytes[HEADER_LEN..];
+        if payload.len() != header.format.payload_len(header.count) {
+            return Err(WireError::LengthMismatch);
+        }
+        Ok((header, payload))
+    }
+}
+
+/// Whether `bytes` starts with the magic, i.e. claims to be framed
+pub fn is_framed(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// The payload of a framed binary in format `want`, or the whole binary
+/// as a bare payload if legacy input is accepted
+fn payload(bytes: &[u8], legacy: Legacy, want: Format) -> Result<&[u8], WireError> {
+    if !is_framed(bytes) && legacy == Legacy::Accept {
+        return Ok(bytes);
+    }
+    let (header, payload) = Header::split(bytes)?;
+    if header.format != want {
+        return Err(WireError::WrongFormat);
+    }
+    Ok(payload)
+}
+
+fn f32s_le(payload: &[u8]) -> impl Iterator<Item = f64> + '_ {
+    payload
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]) as f64)
+}
+
+/// Write IQ_F32_INTERLEAVED_V1 into `out`
+///
+/// # Panics
+/// If `out` isn't exactly `Format::IqF32.frame_len(iq.len())` long.
+pub fn write_iq_f32(iq: &[(f64, f64)], out: &mut [u8]) {
+    assert_eq!(out.len(), Format::IqF32.frame_len(iq.len()));
+    let header = Header { format: Format::IqF32, count: iq.len() };
+    out[..HEADER_LEN].copy_from_slice(&header.to_bytes());
+    for (chunk, &(i, q)) in out[HEADER_LEN..].chunks_exact_mut(8).zip(iq) {
+        chunk[..4].copy_from_slice(&(i as f32).to_le_bytes());
+        chunk[4..].copy_from_slice(&(q as f32).to_le_bytes());
+    }
+}
+
+/// IQ_F32_INTERLEAVED_V1 of the points
+pub fn encode_iq_f32(iq: &[(f64, f64)]) -> Vec<u8> {
+    let mut out = vec![0; Format::IqF32.frame_len(iq.len())];
+    write_iq_f32(iq, &mut out);
+    out
+}
+
+/// Read IQ_F32_INTERLEAVED_V1 (or, if accepted, bare interleaved f32-le)
+pub fn decode_iq_f32(bytes: &[u8], legacy: Legacy) -> Result<Vec<(f64, f64)>, WireError> {
+    let payload = payload(bytes, legacy, Format::IqF32)?;
+    if !payload.len().is_multiple_of(8) {
+        return Err(WireError::LengthMismatch);
+    }
+    let flat: Vec<f64> = f32s_le(payload).collect();
+    Ok(flat.chunks_exact(2).map(|c| (c[0], c[1])).collect())
+}
+
+/// Check symbols fit in `bits` each, and `bits` is 1..=8
+pub fn check_symbols(symbols: &[u8], bits: u8) -> Result<(), WireError> {
+    if !(1..=8).contains(&bits) || symbols.iter().any(|&s| (s as u32) >> bits != 0) {
+        return Err(WireError::BadElement);
+    }
+    Ok(())
+}
+
+/// Write SYMBOLS_PACKED_V1 into `out`
+///
+/// The symbols must already pass `check_symbols`.
+///
+/// # Panics
+/// If `out` isn't exactly `Format::Symbols { bits }.frame_len(symbols.len())`
+/// long.
+pub fn write_symbols(symbols: &[u8], bits: u8, out: &mut [u8]) {
+    let format = Format::Symbols { bits };
+    assert_eq!(out.len(), format.frame_len(symbols.len()));
+    debug_assert_eq!(check_symbols(symbols, bits), Ok(()));
+    let header = Header { format, count: symbols.len() };
+    out[..HEADER_LEN].copy_from_slice(&header.to_bytes());
+
+    let payload = &mut out[HEADER_LEN..];
+    payload.fill(0);
+    let mut bit = 0;
+    for &symbol in symbols {
+        for b in (0..bits).rev() {
+            if (symbol >> b) & 1 == 1 {
+                payload[bit / 8] |= 0x80 >> (bit % 8);
+            }
+            bit += 1;
+        }
+    }
+}
+
+/// SYMBOLS_PACKED_V1 of the symbols, `bits` each
+pub fn encode_symbols(symbols: &[u8], bits: u8) -> Result<Vec<u8>, WireError> {
+    check_symbols(symbols, bits)?;
+    let mut out = vec![0; Format::Symbols { bits }.frame_len(symbols.len())];
+    write_symbols(symbols, bits, &mut out);
+    Ok(out)
+}
+
+/// Read SYMBOLS_PACKED_V1 (or, if accepted, a bare byte per symbol),
+/// returning the symbols and their width in bits (8 for bare input)
+pub fn decode_symbols(bytes: &[u8], legacy: Legacy) -> Result<(Vec<u8>, u8), WireError> {
+    if !is_framed(bytes) && legacy == Legacy::Accept {
+        return Ok((bytes.to_vec(), 8));
+    }
+    let (header, payload) = Header::split(bytes)?;
+    let Format::Symbols { bits } = header.format else {
+        return Err(WireError::WrongFormat);
+    };
+    let mut symbols = Vec::with_capacity(header.count);
+    let mut bit = 0;
+    for _ in 0..header.count {
+        let mut symbol = 0;
+        for _ in 0..bits {
+            symbol = (symbol << 1) | (payload[bit / 8] >> (7 - bit % 8)) & 1;
+            bit += 1;
+        }
+        symbols.push(symbol);
+    }
+    Ok((symbols, bits))
+}
+
+/// Write LLR_F32_V1 into `out`
+///
+/// # Panics
+/// If `out` isn't exactly `Format::LlrF32.frame_len(llrs.len())` long.
+pub fn write_llrs_f32(llrs: &[f64], out: &mut [u8]) {
+    assert_eq!(out.len(), Format::LlrF32.frame_len(llrs.len()));
+    let header = Header { format: Format::LlrF32, count: llrs.len() };
+    out[..HEADER_LEN].copy_from_slice(&header.to_bytes());
+    for (chunk, &llr) in out[HEADER_LEN..].chunks_exact_mut(4).zip(llrs) {
+        chunk.copy_from_slice(&(llr as f32).to_le_bytes());
+    }
+}
+
+/// LLR_F32_V1 of the LLRs
+pub fn encode_llrs_f32(llrs: &[f64]) -> Vec<u8> {
+    let mut out = vec![0; Format::LlrF32.frame_len(llrs.len())];
+    write_llrs_f32(llrs, &mut out);
+    out
+}
+
+/// Read LLR_F32_V1 (or, if accepted, bare f32-le)
+pub fn decode_llrs_f32(bytes: &[u8], legacy: Legacy) -> Result<Vec<f64>, WireError> {
+    let payload = payload(bytes, legacy, Format::LlrF32)?;
+    if !payload.len().is_multiple_of(4) {
+        return Err(WireError::LengthMismatch);
+    }
+    Ok(f32s_le(payload).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift64*, for reproducible arbitrary inputs
+    struct Arbitrary(u64);
+
+    impl Arbitrary {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        /// A float exactly representable as f32, over a wide range
+        fn f32_value(&mut self) -> f64 {
+            let x = f32::from_bits(self.next() as u32);
+            if x.is_finite() {
+                x as f64
+            } else {
+                0.0
+            }
+        }
+
+        fn len(&mut self) -> usize {
+            (self.next() % 70) as usize
+        }
+    }
+
+    #[test]
+    fn test_iq_round_trip() {
+        let mut rng = Arbitrary(1);
+        for _ in 0..500 {
+            let iq: Vec<(f64, f64)> = (0..rng.len()).map(|_| (rng.f32_value(), rng.f32_value())).collect();
+            let bytes = encode_iq_f32(&iq);
+            assert_eq!(bytes.len(), HEADER_LEN + 8 * iq.len());
+            assert_eq!(Header::parse(&bytes), Ok(Header { format: Format::IqF32, count: iq.len() }));
+            assert_eq!(decode_iq_f32(&bytes, Legacy::Reject), Ok(iq.clone()));
+            assert_eq!(decode_iq_f32(&bytes, Legacy::Accept), Ok(iq));
+        }
+    }
+
+    #[test]
+    fn test_symbols_round_trip() {
+        let mut rng = Arbitrary(2);
+        for _ in 0..500 {
+            let bits = (rng.next() % 8) as u8 + 1;
+            let symbols: Vec<u8> = (0..rng.len()).map(|_| (rng.next() as u32 >> (32 - bits)) as u8).collect();
+            let bytes = encode_symbols(&symbols, bits).unwrap();
+            assert_eq!(bytes.len(), HEADER_LEN + (symbols.len() * bits as usize).div_ceil(8));
+            assert_eq!(decode_symbols(&bytes, Legacy::Reject), Ok((symbols, bits)));
+        }
+    }
+
+    #[test]
+    fn test_symbols_pack_msb_first() {
+        let bytes = encode_symbols(&[0b101, 0b011, 0b110], 3).unwrap();
+        assert_eq!(&bytes[..8], b"MMWF\x01\x02\x03\x00");
+        assert_eq!(&bytes[HEADER_LEN..], &[0b1010_1111, 0b0000_0000]);
+        assert_eq!(encode_symbols(&[4], 2), Err(WireError::BadElement));
+        assert_eq!(encode_symbols(&[0], 9), Err(WireError::BadElement));
+    }
+
+    #[test]
+    fn test_llr_round_trip() {
+        let mut rng = Arbitrary(3);
+        for _ in 0..500 {
+            let llrs: Vec<f64> = (0..rng.len()).map(|_| rng.f32_value()).collect();
+            let bytes = encode_llrs_f32(&llrs);
+            assert_eq!(bytes.len(), HEADER_LEN + 4 * llrs.len());
+            assert_eq!(decode_llrs_f32(&bytes, Legacy::Reject), Ok(llrs));
+        }
+    }
+
+    #[test]
+    fn test_iq_header_bytes() {
+        let bytes = encode_iq_f32(&[(1.0, -0.5)]);
+        assert_eq!(&bytes[..HEADER_LEN], b"MMWF\x01\x01\x20\x00\x01\x00\x00\x00");
+        assert_eq!(&bytes[HEADER_LEN..], [1.0f32.to_le_bytes(), (-0.5f32).to_le_bytes()].concat());
+    }
+
+    #[test]
+    fn test_iq_payload_order_is_fixed() {
+        // As a big-endian host would have framed it, byte for byte
+        let mut bytes = b"MMWF\x01\x01\x20\x00\x01\x00\x00\x00".to_vec();
+        bytes.extend([0x00, 0x00, 0x80, 0x3f, 0x00, 0x00, 0x00, 0xbf]);
+        assert_eq!(decode_iq_f32(&bytes, Legacy::Reject), Ok(vec![(1.0, -0.5)]));
+        assert_eq!(encode_iq_f32(&[(1.0, -0.5)]), bytes);
+    }
+
+    #[test]
+    fn test_corrupted_headers_rejected() {
+        let mut rng = Arbitrary(4);
+        let iq: Vec<(f64, f64)> = (0..9).map(|_| (rng.f32_value(), rng.f32_value())).collect();
+        let good = encode_iq_f32(&iq);
+
+        // Any change to any header byte is caught, whether or not bare
+        // payloads are accepted
+        for pos in 0..HEADER_LEN {
+            for flip in 1..=255u8 {
+                let mut bad = good.clone();
+                bad[pos] ^= flip;
+                let decoded = decode_iq_f32(&bad, Legacy::Reject);
+                assert!(decoded.is_err(), "byte {} ^ {:#x} accepted", pos, flip);
+                if pos >= 4 {
+                    assert_eq!(decode_iq_f32(&bad, Legacy::Accept), decoded);
+                }
+            }
+        }
+
+        let mut bad = good.clone();
+        bad[4] = 2;
+        assert_eq!(Header::parse(&bad), Err(WireError::UnsupportedVersion));
+        let mut bad = good.clone();
+        bad[5] = 9;
+        assert_eq!(Header::parse(&bad), Err(WireError::UnknownFormat));
+        let mut bad = good.clone();
+        bad[6] = 16;
+        assert_eq!(Header::parse(&bad), Err(WireError::BadElement));
+        let mut bad = good.clone();
+        bad[7] = 1;
+        assert_eq!(Header::parse(&bad), Err(WireError::BadReserved));
+        let mut bad = good.clone();
+        bad[8] = 10;
+        assert_eq!(decode_iq_f32(&bad, Legacy::Reject), Err(WireError::LengthMismatch));
+        assert_eq!(decode_iq_f32(&good[..good.len() - 1], Legacy::Reject), Err(WireError::LengthMismatch));
+        assert_eq!(decode_iq_f32(&good[..7], Legacy::Reject), Err(WireError::TooShort));
+        assert_eq!(decode_iq_f32(&good[..7], Legacy::Accept), Err(WireError::TooShort));
+    }
+
+    #[test]
+    fn test_wrong_format_rejected() {
+        let llrs = encode_llrs_f32(&[1.0, 2.0]);
+        let symbols = encode_symbols(&[1, 2, 3], 2).unwrap();
+        let iq = encode_iq_f32(&[(1.0, 2.0)]);
+        assert_eq!(decode_iq_f32(&llrs, Legacy::Accept), Err(WireError::WrongFormat));
+        assert_eq!(decode_llrs_f32(&iq, Legacy::Accept), Err(WireError::WrongFormat));
+        assert_eq!(decode_symbols(&iq, Legacy::Accept), Err(WireError::WrongFormat));
+        assert_eq!(decode_iq_f32(&symbols, Legacy::Reject), Err(WireError::WrongFormat));
+        // Symbol widths can't disagree with the element byte
+        let mut bad = symbols.clone();
+        bad[5] = FORMAT_IQ_F32;
+        assert_eq!(Header::parse(&bad), Err(WireError::BadElement));
+    }
+
+    #[test]
+    fn test_legacy_payloads() {
+        let bare: Vec<u8> = [1.0f32, -0.5, 0.25, 2.0].iter().flat_map(|x| x.to_le_bytes()).collect();
+        assert_eq!(decode_iq_f32(&bare, Legacy::Accept), Ok(vec![(1.0, -0.5), (0.25, 2.0)]));
+        assert_eq!(decode_iq_f32(&bare, Legacy::Reject), Err(WireError::BadMagic));
+        assert_eq!(decode_iq_f32(&bare[..12], Legacy::Accept), Err(WireError::LengthMismatch));
+        assert_eq!(decode_llrs_f32(&bare, Legacy::Accept), Ok(vec![1.0, -0.5, 0.25, 2.0]));
+        assert_eq!(decode_symbols(&[3, 200, 0], Legacy::Accept), Ok((vec![3, 200, 0], 8)));
+        assert_eq!(decode_symbols(&[3, 200, 0], Legacy::Reject), Err(WireError::TooShort));
+        assert_eq!(decode_iq_f32(&[], Legacy::Accept), Ok(vec![]));
+    }
+}
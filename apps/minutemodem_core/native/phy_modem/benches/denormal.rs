@@ -0,0 +1,55 @@
+//! Silence-after-burst benchmarks
+//!
+//! A demodulator with the SSB IF filter model and the HF equalizer,
+//! running on digital silence. "after_burst" has just decoded a burst, so
+//! its filter and equalizer state is decaying through the range where,
+//! unflushed, it would turn subnormal (the soak-test latency spikes);
+//! "idle" has only ever seen zeros. With the state flushed to zero the two
+//! should time the same.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use phy_modem::*;
+use phy_modem::filters::RxFilterPreset;
+
+const SAMPLE_RATE: u32 = 9600;
+
+fn demodulator() -> UnifiedDemodulator {
+    let mut demod = UnifiedDemodulator::with_hf_equalizer(ConstellationType::Psk8, SAMPLE_RATE, 2400, 1800.0);
+    demod.set_rx_filter_preset(RxFilterPreset::Ssb2k7);
+    demod
+}
+
+fn burst() -> Vec<i16> {
+    let symbols: Vec<u8> = (0..2400).map(|i| ((i * 3 + i / 5) % 8) as u8).collect();
+    let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, SAMPLE_RATE, 2400, 1800.0);
+    let mut samples = modulator.modulate(&symbols);
+    samples.extend(modulator.end_burst());
+    samples
+}
+
+fn benchmark_silence(c: &mut Criterion) {
+    let burst = burst();
+    let silence = vec![0i16; SAMPLE_RATE as usize];
+
+    // 1 s of silence, starting 2 s after the burst: deep in the decay
+    c.bench_function("unified_demod_silence_1s_after_burst", |b| {
+        b.iter_batched(
+            || {
+                let mut demod = demodulator();
+                demod.demodulate(&burst);
+                demod.demodulate(&silence);
+                demod.demodulate(&silence);
+                demod
+            },
+            |mut demod| black_box(demod.demodulate(&silence)),
+            BatchSize::SmallInput,
+        )
+    });
+
+    c.bench_function("unified_demod_silence_1s_idle", |b| {
+        b.iter_batched(demodulator, |mut demod| black_box(demod.demodulate(&silence)), BatchSize::SmallInput)
+    });
+}
+
+criterion_group!(benches, benchmark_silence);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+//! Quarter-rate mixing benchmarks
+//!
+//! The same 48 kHz 8-PSK burst demodulated with the carrier at fs/4
+//! (trig-free mixing) and just off it (general path).
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use phy_modem::*;
+
+const SAMPLE_RATE: u32 = 48000;
+
+fn burst(carrier: f64) -> Vec<i16> {
+    let symbols: Vec<u8> = (0..2400).map(|i| ((i * 3 + i / 5) % 8) as u8).collect();
+    let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, SAMPLE_RATE, 2400, carrier);
+    let mut samples = modulator.modulate(&symbols);
+    samples.extend(modulator.flush());
+    samples
+}
+
+fn benchmark_unified_demod_mixing(c: &mut Criterion) {
+    for (name, carrier) in [("quarter_rate_12000hz", 12000.0), ("general_11999hz", 11999.0)] {
+        let samples = burst(carrier);
+        let mut demodulator = UnifiedDemodulator::new(ConstellationType::Psk8, SAMPLE_RATE, 2400, carrier);
+
+        c.bench_function(&format!("unified_demod_48k_1s_{}", name), |b| {
+            b.iter(|| {
+                demodulator.reset();
+                black_box(demodulator.demodulate(&samples))
+            })
+        });
+    }
+}
+
+fn benchmark_nco(c: &mut Criterion) {
+    for (name, carrier) in [("quarter_rate_12000hz", 12000.0), ("general_11999hz", 11999.0)] {
+        let mut nco = Nco::new(carrier, SAMPLE_RATE);
+
+        c.bench_function(&format!("nco_48k_1s_{}", name), |b| {
+            b.iter(|| {
+                let mut acc = 0.0;
+                for _ in 0..SAMPLE_RATE {
+                    acc += nco.next().0;
+                }
+                black_box(acc)
+            })
+        });
+    }
+}
+
+criterion_group!(benches, benchmark_unified_demod_mixing, benchmark_nco);
+criterion_main!(benches);
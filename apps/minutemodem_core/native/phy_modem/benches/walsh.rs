@@ -0,0 +1,33 @@
+//! Walsh-16 correlation benchmarks
+//!
+//! One second of 2400 Bd dwells (37 of them) through the bank, sequence
+//! by sequence and with the fast Walsh-Hadamard transform.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use phy_modem::walsh::{self, WALSH_DWELL};
+
+fn dwells() -> Vec<(f64, f64)> {
+    (0..2400 / WALSH_DWELL)
+        .flat_map(|n| walsh::walsh_sequence(n % 16))
+        .enumerate()
+        .map(|(s, sym)| {
+            let angle = sym as f64 * std::f64::consts::FRAC_PI_4 + s as f64 * 1e-3;
+            (angle.cos(), angle.sin())
+        })
+        .collect()
+}
+
+fn benchmark_walsh_correlate(c: &mut Criterion) {
+    let iq = dwells();
+
+    c.bench_function("walsh16_1s_naive", |b| {
+        b.iter(|| {
+            let dwells: Vec<_> = iq.chunks_exact(WALSH_DWELL).map(walsh::correlate_dwell_naive).collect();
+            black_box(dwells)
+        })
+    });
+    c.bench_function("walsh16_1s_fwht", |b| b.iter(|| black_box(walsh::correlate(&iq))));
+}
+
+criterion_group!(benches, benchmark_walsh_correlate);
+criterion_main!(benches);
@@ -0,0 +1,6 @@
+//! Records the build's `git describe` and profile for the provenance
+//! string (see src/provenance.rs)
+
+fn main() {
+    minutemodem_dsp::provenance::record_build();
+}
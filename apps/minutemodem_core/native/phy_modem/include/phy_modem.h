@@ -0,0 +1,165 @@
+#ifndef PHY_MODEM_H
+#define PHY_MODEM_H
+
+/* Generated by cbindgen from src/capi.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Bumped whenever a signature or struct layout in this module changes
+ */
+#define PM_ABI_VERSION 1
+
+#define PM_OK 0
+
+/**
+ * A required pointer was NULL
+ */
+#define PM_ERR_NULL -1
+
+/**
+ * An option is out of range
+ */
+#define PM_ERR_INVALID_ARG -2
+
+/**
+ * The output buffer is smaller than pm_demod_max_symbols()
+ */
+#define PM_ERR_BUFFER_TOO_SMALL -3
+
+/**
+ * The library panicked; the handle must be freed and not used again
+ */
+#define PM_ERR_PANIC -4
+
+#define PM_CONSTELLATION_BPSK 0
+
+#define PM_CONSTELLATION_QPSK 1
+
+#define PM_CONSTELLATION_PSK8 2
+
+#define PM_CONSTELLATION_QAM16 3
+
+#define PM_CONSTELLATION_QAM32 4
+
+#define PM_CONSTELLATION_QAM64 5
+
+/**
+ * Opaque demodulator handle
+ */
+typedef struct PmDemodulator PmDemodulator;
+
+/**
+ * Demodulator options; start from pm_demod_options_default()
+ */
+typedef struct PmDemodOptions {
+  /**
+   * One of PM_CONSTELLATION_*
+   */
+  uint32_t constellation;
+  uint32_t sample_rate;
+  uint32_t symbol_rate;
+  double carrier_freq_hz;
+  /**
+   * Enable the DFE with its HF skywave preset
+   */
+  bool hf_equalizer;
+} PmDemodOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Library version, e.g. "0.1.0" (static, NUL-terminated)
+ */
+const char *pm_version(void);
+
+/**
+ * PM_ABI_VERSION of the library actually linked
+ */
+uint32_t pm_abi_version(void);
+
+/**
+ * Defaults used by the NIF: 8-PSK, 9600 Hz, 2400 baud, 1800 Hz carrier,
+ * no equalizer
+ */
+struct PmDemodOptions pm_demod_options_default(void);
+
+/**
+ * Create a demodulator; on success `*out` holds the new handle
+ *
+ * # Safety
+ * `options` must point to a valid PmDemodOptions and `out` to writable
+ * storage for a pointer.
+ */
+int32_t pm_demod_new(const struct PmDemodOptions *options, struct PmDemodulator **out);
+
+/**
+ * Free a demodulator; NULL is ignored
+ *
+ * # Safety
+ * `demod` must come from pm_demod_new() and not be used afterwards.
+ */
+void pm_demod_free(struct PmDemodulator *demod);
+
+/**
+ * Output capacity that pm_demod_symbols() needs for `num_samples` input
+ * samples, with any held for timing acquisition ahead of them (0 for a
+ * NULL handle)
+ *
+ * # Safety
+ * `demod` must be NULL or a live handle.
+ */
+uintptr_t pm_demod_max_symbols(const struct PmDemodulator *demod, uintptr_t num_samples);
+
+/**
+ * Demodulate 16-bit samples to symbol indices
+ *
+ * State carries over between calls, as with the NIF. `out_capacity`
+ * must be at least pm_demod_max_symbols(demod, num_samples); the number
+ * of symbols written goes to `*out_len`.
+ *
+ * # Safety
+ * `samples` must point to `num_samples` readable values (it may be NULL
+ * if `num_samples` is 0), `out` to `out_capacity` writable bytes and
+ * `out_len` to a writable size_t.
+ */
+int32_t pm_demod_symbols(struct PmDemodulator *demod,
+                         const int16_t *samples,
+                         uintptr_t num_samples,
+                         uint8_t *out,
+                         uintptr_t out_capacity,
+                         uintptr_t *out_len);
+
+/**
+ * Demodulate the samples held for timing acquisition (see
+ * UnifiedDemodulator::flush)
+ *
+ * `out_capacity` must be at least pm_demod_max_symbols(demod, 0).
+ *
+ * # Safety
+ * `out` must point to `out_capacity` writable bytes and `out_len` to a
+ * writable size_t.
+ */
+int32_t pm_demod_flush(struct PmDemodulator *demod,
+                       uint8_t *out,
+                       uintptr_t out_capacity,
+                       uintptr_t *out_len);
+
+/**
+ * Return the demodulator to idle (see UnifiedDemodulator::reset_to_idle)
+ *
+ * # Safety
+ * `demod` must be NULL or a live handle.
+ */
+int32_t pm_demod_reset(struct PmDemodulator *demod);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PHY_MODEM_H */
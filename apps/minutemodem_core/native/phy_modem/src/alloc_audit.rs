@@ -0,0 +1,165 @@
+//! Allocation audit (feature `alloc-audit`)
+//!
+//! Installs a counting global allocator so tests can report the transient
+//! heap use of the big entry points, for sizing the embedded gateway build:
+//!
+//! ```text
+//! cargo test --release --features alloc-audit alloc_audit -- --nocapture
+//! ```
+//!
+//! Counts are per thread, so tests running in parallel don't see each
+//! other's allocations. Only for audits; the NIF build leaves it off.
+//!
+//! Budget: demodulating a 3 s burst at 48 kHz (with DFE and IF filter)
+//! peaks under DEMOD_BUDGET_BYTES, result included. The receive chain
+//! works in DEMOD_WINDOW-sample windows, so that peak is the returned
+//! symbols and confidences plus a fixed scratch, not a copy of the burst.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Peak transient bytes allowed for demodulating a 3 s burst
+pub const DEMOD_BUDGET_BYTES: usize = 256 * 1024;
+
+struct CountingAlloc;
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+thread_local! {
+    // Signed: a block freed on another thread than it was allocated on
+    // would otherwise underflow
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+    static COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+fn track(delta: isize) {
+    // try_with: the thread-locals may already be gone during thread exit
+    let _ = LIVE.try_with(|live| {
+        let now = live.get() + delta;
+        live.set(now);
+        if delta > 0 {
+            let _ = PEAK.try_with(|peak| peak.set(peak.get().max(now)));
+            let _ = COUNT.try_with(|count| count.set(count.get() + 1));
+        }
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            track(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        track(-(layout.size() as isize));
+    }
+}
+
+/// Heap use of one measured call
+#[derive(Debug, Clone, Copy)]
+pub struct AllocReport {
+    /// Most bytes live at once above the level before the call, including
+    /// the returned value
+    pub peak_bytes: usize,
+    /// Number of allocations (a reallocation counts as one)
+    pub allocations: usize,
+}
+
+/// Run `f` on this thread and report its transient heap use
+pub fn measure<R>(f: impl FnOnce() -> R) -> (R, AllocReport) {
+    let base = LIVE.with(Cell::get);
+    PEAK.with(|peak| peak.set(base));
+    COUNT.with(|count| count.set(0));
+
+    let result = f();
+
+    let report = AllocReport {
+        peak_bytes: (PEAK.with(Cell::get) - base).max(0) as usize,
+        allocations: COUNT.with(Cell::get),
+    };
+    (result, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modem::DEMOD_WINDOW;
+    use crate::{ConstellationType, UnifiedDemodulator, UnifiedModulator};
+    use crate::filters::RxFilterPreset;
+
+    const SYMBOL_RATE: u32 = 2400;
+    const SECONDS: u32 = 3;
+
+    fn burst(sample_rate: u32) -> (Vec<u8>, Vec<i16>) {
+        let symbols: Vec<u8> = (0..SYMBOL_RATE * SECONDS).map(|k| (k * 3 % 8) as u8).collect();
+        let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, sample_rate, SYMBOL_RATE, 1800.0);
+        let samples = modulator.modulate(&symbols);
+        (symbols, samples)
+    }
+
+    fn report(name: &str, r: AllocReport) {
+        println!("{:<40} peak {:>9} B  allocs {:>4}", name, r.peak_bytes, r.allocations);
+    }
+
+    #[test]
+    fn test_measure_counts_this_thread() {
+        let (v, r) = measure(|| vec![0u8; 10_000]);
+        assert_eq!(r.peak_bytes, 10_000);
+        assert_eq!(r.allocations, 1);
+        drop(v);
+
+        let (_, r) = measure(|| drop(vec![0u64; 1000]));
+        assert_eq!(r.peak_bytes, 8000);
+    }
+
+    #[test]
+    fn test_demodulate_3s_within_budget() {
+        for sample_rate in [9600, 48_000] {
+            let (_, samples) = burst(sample_rate);
+
+            let mut demod = UnifiedDemodulator::with_hf_equalizer(ConstellationType::Psk8, sample_rate, SYMBOL_RATE, 1800.0);
+            demod.set_rx_filter_preset(RxFilterPreset::Ssb2k7);
+            let (_, r) = measure(|| demod.demodulate(&samples));
+            report(&format!("demodulate 3 s @ {} Hz", sample_rate), r);
+            assert!(r.peak_bytes < DEMOD_BUDGET_BYTES, "{} Hz: {} B", sample_rate, r.peak_bytes);
+
+            // Scratch is kept for the next call: a second burst allocates
+            // only its result
+            let (symbols, r) = measure(|| demod.demodulate_with_confidence(&samples));
+            report(&format!("demodulate (warm) 3 s @ {} Hz", sample_rate), r);
+            let result_bytes = symbols.0.capacity() + symbols.1.capacity() * 8;
+            assert_eq!(r.peak_bytes, result_bytes);
+        }
+    }
+
+    #[test]
+    fn test_demodulate_iq_is_window_bounded() {
+        // Apart from the result, a 3 s call costs no more than one window
+        let (_, samples) = burst(48_000);
+        let mut demod = UnifiedDemodulator::new(ConstellationType::Psk8, 48_000, SYMBOL_RATE, 1800.0);
+        let (iq, r) = measure(|| demod.demodulate_iq(&samples));
+        report("demodulate_iq 3 s @ 48000 Hz", r);
+
+        let result_bytes = iq.capacity() * 16;
+        let window_bytes = DEMOD_WINDOW * 8 + (DEMOD_WINDOW / 20 + 1) * 16;
+        assert!(r.peak_bytes <= result_bytes + window_bytes + 4096, "{} B", r.peak_bytes);
+        assert!(r.peak_bytes < samples.len() * 8, "{} B: burst-sized", r.peak_bytes);
+    }
+
+    #[test]
+    fn test_modulate_3s() {
+        // Output only: 2 bytes per sample
+        let (symbols, samples) = burst(48_000);
+        let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, 48_000, SYMBOL_RATE, 1800.0);
+        let (out, r) = measure(|| modulator.modulate(&symbols));
+        report("modulate 3 s @ 48000 Hz", r);
+        assert_eq!(out, samples);
+        assert_eq!(r.peak_bytes, out.capacity() * 2);
+    }
+}
@@ -0,0 +1,309 @@
+//! C ABI (feature `capi`)
+//!
+//! A small extern "C" surface over UnifiedDemodulator so C and C++ test
+//! tools can link the same implementation the NIF ships. The header is
+//! include/phy_modem.h, generated by cbindgen (see cbindgen.toml and
+//! scripts/capi_smoke.sh).
+//!
+//! Handles are opaque pointers owned by the caller: every `pm_*_new` must be
+//! paired with the matching `pm_*_free`. Functions return PM_OK or a
+//! negative PM_ERR_* code, and never unwind into C: a panic is caught and
+//! reported as PM_ERR_PANIC. A handle must not be used from two threads at
+//! once.
+
+use std::ffi::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::modem::{ConstellationType, UnifiedDemodulator};
+
+/// Bumped whenever a signature or struct layout in this module changes
+pub const PM_ABI_VERSION: u32 = 1;
+
+pub const PM_OK: i32 = 0;
+/// A required pointer was NULL
+pub const PM_ERR_NULL: i32 = -1;
+/// An option is out of range
+pub const PM_ERR_INVALID_ARG: i32 = -2;
+/// The output buffer is smaller than pm_demod_max_symbols()
+pub const PM_ERR_BUFFER_TOO_SMALL: i32 = -3;
+/// The library panicked; the handle must be freed and not used again
+pub const PM_ERR_PANIC: i32 = -4;
+
+pub const PM_CONSTELLATION_BPSK: u32 = 0;
+pub const PM_CONSTELLATION_QPSK: u32 = 1;
+pub const PM_CONSTELLATION_PSK8: u32 = 2;
+pub const PM_CONSTELLATION_QAM16: u32 = 3;
+pub const PM_CONSTELLATION_QAM32: u32 = 4;
+pub const PM_CONSTELLATION_QAM64: u32 = 5;
+
+/// Opaque demodulator handle
+pub struct PmDemodulator {
+    inner: UnifiedDemodulator,
+}
+
+/// Demodulator options; start from pm_demod_options_default()
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PmDemodOptions {
+    /// One of PM_CONSTELLATION_*
+    pub constellation: u32,
+    pub sample_rate: u32,
+    pub symbol_rate: u32,
+    pub carrier_freq_hz: f64,
+    /// Enable the DFE with its HF skywave preset
+    pub hf_equalizer: bool,
+}
+
+fn constellation_from_u32(value: u32) -> Option<ConstellationType> {
+    match value {
+        PM_CONSTELLATION_BPSK => Some(ConstellationType::Bpsk),
+        PM_CONSTELLATION_QPSK => Some(ConstellationType::Qpsk),
+        PM_CONSTELLATION_PSK8 => Some(ConstellationType::Psk8),
+        PM_CONSTELLATION_QAM16 => Some(ConstellationType::Qam16),
+        PM_CONSTELLATION_QAM32 => Some(ConstellationType::Qam32),
+        PM_CONSTELLATION_QAM64 => Some(ConstellationType::Qam64),
+        _ => None,
+    }
+}
+
+/// Run `f`, turning a panic into PM_ERR_PANIC
+fn guard(f: impl FnOnce() -> i32) -> i32 {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(PM_ERR_PANIC)
+}
+
+/// Library version, e.g. "0.1.0" (static, NUL-terminated)
+#[no_mangle]
+pub extern "C" fn pm_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// PM_ABI_VERSION of the library actually linked
+#[no_mangle]
+pub extern "C" fn pm_abi_version() -> u32 {
+    PM_ABI_VERSION
+}
+
+/// Defaults used by the NIF: 8-PSK, 9600 Hz, 2400 baud, 1800 Hz carrier,
+/// no equalizer
+#[no_mangle]
+pub extern "C" fn pm_demod_options_default() -> PmDemodOptions {
+    PmDemodOptions {
+        constellation: PM_CONSTELLATION_PSK8,
+        sample_rate: 9600,
+        symbol_rate: 2400,
+        carrier_freq_hz: 1800.0,
+        hf_equalizer: false,
+    }
+}
+
+/// Create a demodulator; on success `*out` holds the new handle
+///
+/// # Safety
+/// `options` must point to a valid PmDemodOptions and `out` to writable
+/// storage for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn pm_demod_new(
+    options: *const PmDemodOptions,
+    out: *mut *mut PmDemodulator,
+) -> i32 {
+    guard(|| {
+        if options.is_null() || out.is_null() {
+            return PM_ERR_NULL;
+        }
+        let options = *options;
+        let Some(constellation) = constellation_from_u32(options.constellation) else {
+            return PM_ERR_INVALID_ARG;
+        };
+        if options.symbol_rate == 0
+            || options.sample_rate / options.symbol_rate < 2
+            || !(options.carrier_freq_hz.is_finite() && options.carrier_freq_hz > 0.0)
+        {
+            return PM_ERR_INVALID_ARG;
+        }
+
+        let inner = if options.hf_equalizer {
+            UnifiedDemodulator::with_hf_equalizer(
+                constellation,
+                options.sample_rate,
+                options.symbol_rate,
+                options.carrier_freq_hz,
+            )
+        } else {
+            UnifiedDemodulator::new(constellation, options.sample_rate, options.symbol_rate, options.carrier_freq_hz)
+        };
+        *out = Box::into_raw(Box::new(PmDemodulator { inner }));
+        PM_OK
+    })
+}
+
+/// Free a demodulator; NULL is ignored
+///
+/// # Safety
+/// `demod` must come from pm_demod_new() and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn pm_demod_free(demod: *mut PmDemodulator) {
+    if !demod.is_null() {
+        // Dropping only frees buffers; a panic here would mean a bug in
+        // the allocator, so there is nothing sensible to report
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(demod))));
+    }
+}
+
+/// Output capacity that pm_demod_symbols() needs for `num_samples` input
+/// samples, with any held for timing acquisition ahead of them (0 for a
+/// NULL handle)
+///
+/// # Safety
+/// `demod` must be NULL or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn pm_demod_max_symbols(demod: *const PmDemodulator, num_samples: usize) -> usize {
+    match demod.as_ref() {
+        Some(demod) => (demod.inner.samples_held() + num_samples) / demod.inner.sps() + 1,
+        None => 0,
+    }
+}
+
+/// Demodulate 16-bit samples to symbol indices
+///
+/// State carries over between calls, as with the NIF. `out_capacity`
+/// must be at least pm_demod_max_symbols(demod, num_samples); the number
+/// of symbols written goes to `*out_len`.
+///
+/// # Safety
+/// `samples` must point to `num_samples` readable values (it may be NULL
+/// if `num_samples` is 0), `out` to `out_capacity` writable bytes and
+/// `out_len` to a writable size_t.
+#[no_mangle]
+pub unsafe extern "C" fn pm_demod_symbols(
+    demod: *mut PmDemodulator,
+    samples: *const i16,
+    num_samples: usize,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> i32 {
+    guard(|| {
+        let Some(demod) = demod.as_mut() else {
+            return PM_ERR_NULL;
+        };
+        if out.is_null() || out_len.is_null() || (samples.is_null() && num_samples > 0) {
+            return PM_ERR_NULL;
+        }
+        if out_capacity < (demod.inner.samples_held() + num_samples) / demod.inner.sps() + 1 {
+            return PM_ERR_BUFFER_TOO_SMALL;
+        }
+
+        let samples = if num_samples == 0 { &[][..] } else { std::slice::from_raw_parts(samples, num_samples) };
+        let symbols = demod.inner.demodulate(samples);
+        std::ptr::copy_nonoverlapping(symbols.as_ptr(), out, symbols.len());
+        *out_len = symbols.len();
+        PM_OK
+    })
+}
+
+/// Demodulate the samples held for timing acquisition (see
+/// UnifiedDemodulator::flush)
+///
+/// `out_capacity` must be at least pm_demod_max_symbols(demod, 0).
+///
+/// # Safety
+/// `out` must point to `out_capacity` writable bytes and `out_len` to a
+/// writable size_t.
+#[no_mangle]
+pub unsafe extern "C" fn pm_demod_flush(
+    demod: *mut PmDemodulator,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> i32 {
+    guard(|| {
+        let Some(demod) = demod.as_mut() else {
+            return PM_ERR_NULL;
+        };
+        if out.is_null() || out_len.is_null() {
+            return PM_ERR_NULL;
+        }
+        if out_capacity < demod.inner.samples_held() / demod.inner.sps() + 1 {
+            return PM_ERR_BUFFER_TOO_SMALL;
+        }
+
+        let symbols = demod.inner.flush();
+        std::ptr::copy_nonoverlapping(symbols.as_ptr(), out, symbols.len());
+        *out_len = symbols.len();
+        PM_OK
+    })
+}
+
+/// Return the demodulator to idle (see UnifiedDemodulator::reset_to_idle)
+///
+/// # Safety
+/// `demod` must be NULL or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn pm_demod_reset(demod: *mut PmDemodulator) -> i32 {
+    guard(|| match demod.as_mut() {
+        Some(demod) => {
+            demod.inner.reset_to_idle();
+            PM_OK
+        }
+        None => PM_ERR_NULL,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modem::UnifiedModulator;
+    use std::ffi::CStr;
+    use std::ptr;
+
+    fn new_demod(options: &PmDemodOptions) -> Result<*mut PmDemodulator, i32> {
+        let mut handle = ptr::null_mut();
+        match unsafe { pm_demod_new(options, &mut handle) } {
+            PM_OK => Ok(handle),
+            err => Err(err),
+        }
+    }
+
+    #[test]
+    fn test_versions() {
+        let version = unsafe { CStr::from_ptr(pm_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+        assert_eq!(pm_abi_version(), PM_ABI_VERSION);
+    }
+
+    #[test]
+    fn test_demod_matches_rust_api() {
+        let symbols: Vec<u8> = (0..400).map(|i| (i * 5 % 8) as u8).collect();
+        let samples = UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0).modulate(&symbols);
+        let expected = UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0).demodulate(&samples);
+
+        let demod = new_demod(&pm_demod_options_default()).unwrap();
+        let capacity = unsafe { pm_demod_max_symbols(demod, samples.len()) };
+        let mut out = vec![0u8; capacity];
+        let mut len = 0;
+        let rc = unsafe { pm_demod_symbols(demod, samples.as_ptr(), samples.len(), out.as_mut_ptr(), out.len(), &mut len) };
+        unsafe { pm_demod_free(demod) };
+
+        assert_eq!(rc, PM_OK);
+        assert_eq!(&out[..len], &expected[..]);
+    }
+
+    #[test]
+    fn test_demod_rejects_bad_arguments() {
+        let bad_constellation = PmDemodOptions { constellation: 99, ..pm_demod_options_default() };
+        assert_eq!(new_demod(&bad_constellation).unwrap_err(), PM_ERR_INVALID_ARG);
+        let bad_rate = PmDemodOptions { symbol_rate: 0, ..pm_demod_options_default() };
+        assert_eq!(new_demod(&bad_rate).unwrap_err(), PM_ERR_INVALID_ARG);
+        assert_eq!(unsafe { pm_demod_new(ptr::null(), &mut ptr::null_mut()) }, PM_ERR_NULL);
+
+        let demod = new_demod(&pm_demod_options_default()).unwrap();
+        let samples = [0i16; 400];
+        let mut out = [0u8; 10];
+        let mut len = 0;
+        let rc = unsafe { pm_demod_symbols(demod, samples.as_ptr(), samples.len(), out.as_mut_ptr(), out.len(), &mut len) };
+        assert_eq!(rc, PM_ERR_BUFFER_TOO_SMALL);
+        assert_eq!(unsafe { pm_demod_symbols(ptr::null_mut(), samples.as_ptr(), 0, out.as_mut_ptr(), 10, &mut len) }, PM_ERR_NULL);
+        unsafe { pm_demod_free(demod) };
+        unsafe { pm_demod_free(ptr::null_mut()) };
+    }
+}
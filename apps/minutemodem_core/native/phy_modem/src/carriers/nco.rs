@@ -1,9 +1,11 @@
 //! Numerically Controlled Oscillator (NCO)
 //!
 //! Generates carrier signal for modulation and demodulation.
-//! Phase-continuous, frequency-adjustable.
+//! Phase-continuous, frequency-adjustable. A carrier at exactly a quarter
+//! or half of the sample rate skips the trig (see minutemodem_dsp::lo).
 
 use crate::traits::Carrier;
+use minutemodem_dsp::{QuadrantLo, TrivialLo};
 use std::f64::consts::PI;
 
 /// Numerically Controlled Oscillator
@@ -13,6 +15,8 @@ pub struct Nco {
     phase_inc: f64,
     freq_hz: f64,
     sample_rate: f64,
+    /// Quarter/half-rate fast path, which then keeps the phase
+    trivial: Option<QuadrantLo>,
 }
 
 impl Nco {
@@ -28,6 +32,7 @@ impl Nco {
             phase_inc: 2.0 * PI * freq_hz / sample_rate_f,
             freq_hz,
             sample_rate: sample_rate_f,
+            trivial: TrivialLo::detect(freq_hz, sample_rate_f).map(|lo| QuadrantLo::new(lo, 0.0, 0.0)),
         }
     }
 
@@ -39,6 +44,12 @@ impl Nco {
 
 impl Carrier for Nco {
     fn next(&mut self) -> (f64, f64) {
+        if let Some(lo) = &mut self.trivial {
+            let cos_sin = lo.cos_sin();
+            lo.advance(0.0);
+            return cos_sin;
+        }
+        
         let (sin, cos) = self.phase.sin_cos();
         self.phase += self.phase_inc;
         
@@ -52,10 +63,11 @@ impl Carrier for Nco {
 
     fn reset(&mut self) {
         self.phase = 0.0;
+        self.trivial = TrivialLo::detect(self.freq_hz, self.sample_rate).map(|lo| QuadrantLo::new(lo, 0.0, 0.0));
     }
 
     fn phase(&self) -> f64 {
-        self.phase
+        self.trivial.as_ref().map_or(self.phase, QuadrantLo::phase)
     }
 
     fn frequency(&self) -> f64 {
@@ -63,8 +75,10 @@ impl Carrier for Nco {
     }
 
     fn set_frequency(&mut self, freq_hz: f64) {
+        self.phase = self.phase();
         self.freq_hz = freq_hz;
         self.phase_inc = 2.0 * PI * freq_hz / self.sample_rate;
+        self.trivial = TrivialLo::detect(freq_hz, self.sample_rate).map(|lo| QuadrantLo::new(lo, self.phase, 0.0));
     }
 }
 
@@ -107,6 +121,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_quarter_rate_matches_general_path() {
+        for (freq, sample_rate) in [(2400.0, 9600), (12000.0, 48000), (4800.0, 9600)] {
+            let mut fast = Nco::new(freq, sample_rate);
+            assert!(fast.trivial.is_some());
+            let mut general = fast.clone();
+            general.trivial = None;
+            
+            for n in 0..10000 {
+                let (fc, fs) = fast.next();
+                let (gc, gs) = general.next();
+                assert!((fc - gc).abs() < 1e-12 && (fs - gs).abs() < 1e-12, "{freq} Hz sample {n}");
+            }
+            let diff = (fast.phase() - general.phase()).abs();
+            assert!(diff.min(2.0 * PI - diff) < 1e-12);
+        }
+        
+        // Retuning carries the phase across paths
+        let mut nco = Nco::new(1800.0, 9600);
+        for _ in 0..7 {
+            nco.next();
+        }
+        let phase = nco.phase();
+        nco.set_frequency(2400.0);
+        assert!(nco.trivial.is_some());
+        assert!((nco.phase() - phase).abs() < 1e-12);
+        nco.set_frequency(1800.0);
+        assert!(nco.trivial.is_none());
+        assert!((nco.phase() - phase).abs() < 1e-12);
+    }
+
     #[test]
     fn test_nco_phase_wrapping() {
         let mut nco = Nco::new(1800.0, 8000);
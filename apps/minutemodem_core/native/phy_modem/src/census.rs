@@ -0,0 +1,31 @@
+//! This crate's resource kinds (see minutemodem_dsp::census)
+//!
+//! Generic and legacy modulators count as `modulators`. Baseband capture
+//! buffers are counted on their own as well as in their demodulator's
+//! bytes: they're the largest thing a demodulator holds, and come and go
+//! without it.
+
+pub use minutemodem_dsp::census::{Census, CensusCount, Tally, ThreadCount};
+
+pub static MODULATORS: Census = Census::new("modulators");
+pub static DEMODULATORS: Census = Census::new("demodulators");
+pub static UNIFIED_MODULATORS: Census = Census::new("unified_modulators");
+pub static UNIFIED_DEMODULATORS: Census = Census::new("unified_demodulators");
+pub static EQUALIZERS: Census = Census::new("equalizers");
+pub static WALSH_CORRELATORS: Census = Census::new("walsh_correlators");
+pub static PULSE_SHAPERS: Census = Census::new("pulse_shapers");
+pub static CONSTELLATION_SCOPES: Census = Census::new("constellation_scopes");
+pub static CAPTURE_BUFFERS: Census = Census::new("capture_buffers");
+
+/// Every kind, in census/0 order
+pub static KINDS: [&Census; 9] = [
+    &MODULATORS,
+    &DEMODULATORS,
+    &UNIFIED_MODULATORS,
+    &UNIFIED_DEMODULATORS,
+    &EQUALIZERS,
+    &WALSH_CORRELATORS,
+    &PULSE_SHAPERS,
+    &CONSTELLATION_SCOPES,
+    &CAPTURE_BUFFERS,
+];
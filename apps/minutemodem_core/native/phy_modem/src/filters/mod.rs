@@ -0,0 +1,8 @@
+//! General-purpose IIR filters
+//!
+//! Currently only cascaded biquads, used to model the receiver's
+//! IF/roofing filter ahead of the demodulator.
+
+mod biquad;
+
+pub use biquad::{Biquad, BiquadCascade, RxFilterPreset};
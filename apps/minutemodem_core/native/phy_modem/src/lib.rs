@@ -1,36 +1,60 @@
 //! PHY Modem - Trait-based waveform engine for HF modems
 //!
 //! This crate provides a unified PHY layer for MIL-STD-188-110D and 188-141D
-//! waveforms. All protocol logic (scrambling, Walsh, interleaving, FEC) lives
-//! in Elixir. Rust only handles symbol ↔ sample conversion.
+//! waveforms. Protocol logic (scrambling, interleaving, FEC) lives in Elixir;
+//! Rust handles symbol ↔ sample conversion, plus the Walsh-16 correlation
+//! bank that is too hot per dwell to run in the BEAM and the 75 bps Walsh
+//! mode, whose chips are correlated on their soft I/Q.
 
+#[cfg(feature = "nif")]
 use rustler::{Env, Term};
 
+pub mod census;
 pub mod traits;
 pub mod constellations;
 pub mod pulse_shapes;
 pub mod carriers;
+pub mod filters;
 pub mod timing;
 pub mod modem;
+pub mod scope;
+pub mod probes;
+pub mod waveform;
+pub mod prbs;
+pub mod provenance;
+pub mod self_test;
+pub mod walsh;
+pub mod wire;
+#[cfg(feature = "alloc-audit")]
+pub mod alloc_audit;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "nif")]
 pub mod nif;
 mod utils;
 
 // Re-export core types for convenience
 pub use traits::{Constellation, PulseShape, Carrier, SymbolTiming};
 pub use constellations::{Bpsk, Qpsk, Psk8, Qam16, Qam32, Qam64};
-pub use pulse_shapes::RootRaisedCosine;
+pub use pulse_shapes::{PulseShaper, RootRaisedCosine};
 pub use carriers::Nco;
 pub use timing::FixedTiming;
 pub use modem::{Modulator, Demodulator, UnifiedModulator, UnifiedDemodulator, ConstellationType, DFEConfig};
 
+#[cfg(feature = "nif")]
 fn on_load(env: Env, _info: Term) -> bool {
     let _ = rustler::resource!(nif::ModulatorResource, env);
     let _ = rustler::resource!(nif::DemodulatorResource, env);
     let _ = rustler::resource!(nif::UnifiedModulatorResource, env);
     let _ = rustler::resource!(nif::UnifiedDemodulatorResource, env);
+    let _ = rustler::resource!(nif::ConstellationScopeResource, env);
+    let _ = rustler::resource!(nif::PulseShaperResource, env);
+    let _ = rustler::resource!(nif::DFEResource, env);
+    let _ = rustler::resource!(nif::WalshCorrelatorResource, env);
     true
 }
 
+#[cfg(feature = "nif")]
 rustler::init!(
     "Elixir.MinuteModemCore.DSP.PhyModem",
     [
@@ -49,23 +73,81 @@ rustler::init!(
         // Generic demodulator
         nif::demod_new,
         nif::demod_demodulate,
+        nif::demod_set_input_warnings,
         nif::demod_reset,
         
         // Unified modulator
         nif::unified_mod_new,
+        nif::unified_mod_new_opts,
         nif::unified_mod_modulate,
         nif::unified_mod_modulate_mixed,
+        nif::unified_mod_walsh,
         nif::unified_mod_set_constellation,
         nif::unified_mod_get_constellation,
+        nif::unified_mod_set_symbol_map,
+        nif::unified_mod_set_hop_schedule,
         nif::unified_mod_flush,
+        nif::unified_mod_end_burst,
+        nif::unified_mod_ramp_samples,
+        nif::unified_mod_push_symbols,
+        nif::unified_mod_pull_samples,
+        nif::unified_mod_queue_depth,
         nif::unified_mod_reset,
         
         // Unified demodulator
         nif::unified_demod_new,
+        nif::unified_demod_new_opts,
         nif::unified_demod_iq,
+        nif::unified_demod_walsh,
+        nif::unified_demod_align_walsh,
+        nif::unified_demod_mf_output,
         nif::unified_demod_symbols,
+        nif::unified_demod_symbols_opts,
+        nif::unified_demod_at,
+        nif::unified_demod_at_opts,
+        nif::unified_demod_with_correction,
+        nif::unified_demod_short_burst,
+        nif::unified_demod_step,
+        nif::unified_demod_flush,
+        nif::unified_demod_signal_quality,
+        nif::unified_demod_decode_report,
+        nif::unified_demod_enable_eot,
+        nif::unified_demod_disable_eot,
+        nif::unified_demod_enable_timing_tracking,
+        nif::unified_demod_disable_timing_tracking,
+        nif::unified_demod_timing_offset_ppm,
+        nif::unified_demod_enable_stage_timing,
+        nif::unified_demod_disable_stage_timing,
+        nif::unified_demod_stage_timings,
         nif::unified_demod_set_constellation,
+        nif::unified_demod_set_symbol_map,
+        nif::unified_demod_set_hop_schedule,
+        nif::unified_demod_set_gain_reference,
+        nif::unified_demod_set_probe_tracking,
+        nif::unified_demod_drain_probe_metrics,
+        nif::unified_demod_set_sideband_detection,
+        nif::unified_demod_set_clip_handling,
+        nif::unified_demod_enable_capture,
+        nif::unified_demod_disable_capture,
+        nif::unified_demod_keep_capture,
+        nif::unified_demod_discard_capture,
+        nif::unified_demod_drain_capture,
+        nif::unified_demod_set_scatter_points,
+        nif::unified_demod_scatter,
         nif::unified_demod_reset,
+        nif::unified_demod_set_input_warnings,
+        nif::set_warning_logger,
+        
+        // Constellation functions (stateless)
+        nif::symbol_to_iq,
+        nif::iq_to_symbol,
+        nif::constellation_points,
+        nif::canonical_symbol,
+        nif::constellation_bit_map,
+        nif::bit_llrs,
+        nif::bit_llrs_block,
+        nif::bits_per_symbol,
+        nif::order,
         
         // Equalizer functions
         nif::unified_demod_new_with_eq,
@@ -75,8 +157,69 @@ rustler::init!(
         nif::unified_demod_mse,
         nif::unified_demod_has_eq,
         nif::unified_demod_enable_eq,
+        nif::unified_demod_enable_fse,
         nif::unified_demod_disable_eq,
         nif::unified_demod_eq_mode,
+        nif::unified_demod_eq_taps,
+        
+        // Standalone DFE
+        nif::dfe_new,
+        nif::dfe_train,
+        nif::dfe_equalize,
+        nif::dfe_stats,
+        nif::dfe_reset,
+        
+        // RX IF filter model
+        nif::unified_demod_set_rx_filter,
+        
+        // Modulator/demodulator compatibility
+        nif::unified_mod_config_fingerprint,
+        nif::unified_demod_config_fingerprint,
+        nif::check_compatibility,
+        
+        // Resource introspection
+        nif::describe,
+        
+        // Probe sequences
+        nif::probe_symbols,
+        
+        // Waveform presets
+        nif::waveform_preset,
+        nif::unified_mod_for_waveform,
+        nif::unified_demod_for_waveform,
+        
+        // Walsh-16 correlation
+        nif::walsh_correlate,
+        nif::walsh_correlator_new,
+        nif::walsh_correlator_push,
+        nif::walsh_correlator_reset,
+        
+        // PRBS test patterns
+        nif::prbs_symbols,
+        
+        // Pulse shaper
+        nif::pulse_shaper_new,
+        nif::pulse_shaper_process,
+        nif::pulse_shaper_flush,
+        nif::pulse_shaper_reset,
+        
+        // Determinism digest
+        nif::demod_digest,
+        nif::unified_demod_export_state,
+        
+        // Self test
+        nif::self_test,
+        
+        // Provenance
+        nif::provenance,
+        
+        // Constellation scope
+        nif::constellation_scope_new,
+        nif::render_constellation,
+        nif::constellation_scope_clear,
+        
+        // Resource census
+        nif::census,
     ],
     load = on_load
 );
\ No newline at end of file
@@ -33,6 +33,7 @@ where
     timing: T,
     i_history: Vec<f64>,
     q_history: Vec<f64>,
+    symbols_demodulated: u64,
 }
 
 impl<C, P, K, T> Demodulator<C, P, K, T>
@@ -52,6 +53,7 @@ where
             timing,
             i_history: vec![0.0; filter_len],
             q_history: vec![0.0; filter_len],
+            symbols_demodulated: 0,
         }
     }
 
@@ -148,6 +150,7 @@ where
         let filtered_iq = self.demodulate_to_baseband(samples);
         let timing_offset = self.find_timing_phase(&filtered_iq);
         let iq = self.decimate_iq(&filtered_iq, timing_offset);
+        self.symbols_demodulated += iq.len() as u64;
 
         SoftIQ { iq, timing_offset }
     }
@@ -165,6 +168,7 @@ where
 
         let filtered_iq = self.demodulate_to_baseband(samples);
         let iq = self.decimate_iq(&filtered_iq, timing_offset);
+        self.symbols_demodulated += iq.len() as u64;
 
         SoftIQ { iq, timing_offset }
     }
@@ -228,6 +232,26 @@ where
     pub fn timing(&self) -> &T {
         &self.timing
     }
+
+    /// Get reference to pulse shape
+    pub fn pulse(&self) -> &P {
+        &self.pulse
+    }
+
+    /// Get reference to carrier
+    pub fn carrier(&self) -> &K {
+        &self.carrier
+    }
+
+    /// Symbols demodulated since creation (reset() doesn't clear it)
+    pub fn symbols_demodulated(&self) -> u64 {
+        self.symbols_demodulated
+    }
+
+    /// Estimate of the heap memory held: filter taps and I/Q history
+    pub fn memory_bytes(&self) -> usize {
+        3 * self.pulse.filter_len() * std::mem::size_of::<f64>()
+    }
 }
 
 #[cfg(test)]
@@ -312,6 +336,20 @@ mod tests {
             "Timing offset {} should be < {}", soft.timing_offset, sps);
     }
 
+    #[test]
+    fn test_symbols_demodulated() {
+        let mut modulator = make_modulator();
+        let mut demodulator = make_demodulator();
+        let samples = modulator.modulate(&[0, 1, 2, 3, 4, 5, 6, 7]);
+
+        let first = demodulator.demodulate(&samples);
+        let second = demodulator.demodulate_with_timing(&samples, 0);
+        assert_eq!(demodulator.symbols_demodulated(), (first.len() + second.len()) as u64);
+
+        demodulator.reset();
+        assert_eq!(demodulator.symbols_demodulated(), 16);
+    }
+
     #[test]
     fn test_demodulator_reset() {
         let mut demod = make_demodulator();
@@ -0,0 +1,122 @@
+//! Determinism digest of a demodulator run
+//!
+//! demod_digest() runs a demodulator over a block and hashes (BLAKE3)
+//! everything it decided: the symbols, the matched filter I/Q they were
+//! sliced from, and the state it ended in (export_state()). The receive
+//! chain has no randomness, so the same configuration and input give the
+//! same digest on every call and every thread.
+//!
+//! Across platforms the digest holds where the float arithmetic does.
+//! Basic operations are IEEE 754 everywhere and Rust never contracts them
+//! into FMAs, but sin/cos/atan2/exp come from the platform libm and can
+//! differ in the last bit, and one differing bit changes the digest. The
+//! golden digest in the tests is from x86_64 Linux (glibc); check a new
+//! platform against it before relying on digests taken there.
+
+use super::state::StateWriter;
+use super::UnifiedDemodulator;
+
+/// Demodulate `samples` and digest the decisions, I/Q and final state
+///
+/// Leaves `demod` as demodulate() would.
+pub fn demod_digest(demod: &mut UnifiedDemodulator, samples: &[i16]) -> [u8; 32] {
+    let (symbols, iq) = demod.demodulate_traced(samples);
+    let mut w = StateWriter::new(b"MMDD");
+    w.bytes(&symbols);
+    w.seq(iq.iter(), |w, &(i, q)| {
+        w.f64(i);
+        w.f64(q);
+    });
+    w.bytes(&demod.export_state());
+    blake3::hash(&w.finish()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::RxFilterPreset;
+    use crate::modem::{ConstellationType, DFEConfig, EotConfig, UnifiedModulator};
+
+    /// demod_digest() of the reference vector on x86_64 Linux
+    const GOLDEN_DIGEST: &str = "3709f9b2ed8f6c97fce8b109cf98fc480146a218b00467f1d6bec37db76a01c3";
+
+    /// Every optional stage on: IF filter, equalizer, EOT, timing tracking
+    fn untrained_demodulator() -> UnifiedDemodulator {
+        let mut demod =
+            UnifiedDemodulator::with_equalizer(ConstellationType::Psk8, 9600, 2400, 1800.0, DFEConfig::hf_skywave());
+        demod.set_rx_filter_preset(RxFilterPreset::Ssb2k7);
+        demod.set_eot_detector(Some(EotConfig::default()));
+        demod.enable_timing_tracking();
+        demod
+    }
+
+    /// untrained_demodulator() with a training sequence
+    fn reference_demodulator() -> UnifiedDemodulator {
+        let mut demod = untrained_demodulator();
+        demod.set_training_symbols(vec![0, 1, 2, 3, 4, 5, 6, 7].repeat(4));
+        demod
+    }
+
+    /// 600 pseudo-random 8-PSK symbols, then 0.2 s of silence
+    fn reference_samples() -> Vec<i16> {
+        let mut x = 0x1956u32;
+        let symbols: Vec<u8> = (0..600)
+            .map(|_| {
+                x = x.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (x >> 16) as u8 & 7
+            })
+            .collect();
+        let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0);
+        let mut samples = modulator.modulate(&symbols);
+        samples.extend(modulator.flush());
+        samples.extend(std::iter::repeat_n(0, 1920));
+        samples
+    }
+
+    fn reference_digest() -> String {
+        blake3::Hash::from_bytes(demod_digest(&mut reference_demodulator(), &reference_samples())).to_hex().to_string()
+    }
+
+    #[test]
+    fn test_digest_repeats() {
+        let first = reference_digest();
+        for _ in 0..3 {
+            assert_eq!(reference_digest(), first);
+        }
+    }
+
+    #[test]
+    fn test_digest_matches_across_threads() {
+        let first = reference_digest();
+        let threads: Vec<_> = (0..4).map(|_| std::thread::spawn(reference_digest)).collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), first);
+        }
+    }
+
+    #[test]
+    fn test_digest_matches_golden() {
+        assert_eq!(reference_digest(), GOLDEN_DIGEST);
+    }
+
+    #[test]
+    fn test_export_state_tracks_changes() {
+        let fresh = reference_demodulator().export_state();
+        assert!(reference_demodulator().export_state() == fresh);
+
+        let mut demod = reference_demodulator();
+        let samples = reference_samples();
+        demod.demodulate(&samples);
+        let after = demod.export_state();
+        assert!(after != fresh);
+
+        // demodulate_traced() leaves the same state as demodulate()
+        let mut traced = reference_demodulator();
+        traced.demodulate_traced(&samples);
+        assert!(traced.export_state() == after);
+
+        // reset() drops the training sequence along with the rest
+        demod.reset();
+        assert!(demod.export_state() == untrained_demodulator().export_state());
+    }
+}
@@ -9,7 +9,17 @@
 mod modulator;
 mod demodulator;
 mod unified;
+mod state;
+mod digest;
+mod report;
+mod declip;
+mod short_burst;
 
 pub use modulator::Modulator;
 pub use demodulator::Demodulator;
-pub use unified::{UnifiedModulator, UnifiedDemodulator, ConstellationType, DFEConfig, DFE, Complex, EqMode};
\ No newline at end of file
+pub use digest::demod_digest;
+pub use declip::ClipConfig;
+pub use short_burst::{ShortBurst, ShortBurstConfig, SHORT_BURST_MAX_SYMBOLS};
+pub use report::{BurstReport, BurstWarning, Acquisition, PllSummary, EqSummary, SymbolCounts, CONFIDENCE_BINS};
+pub use state::STATE_VERSION;
+pub use unified::{UnifiedModulator, UnifiedDemodulator, ConstellationType, Adaptation, DFEConfig, DFE, Complex, EqMode, ModemConfig, ConfigMismatch, CaptureData, ConfidenceStats, Pulse, GapPolicy, Discontinuity, FreqCorrection, EotConfig, ProbeMetric, StageTimes, StageTimings, SymbolMap, HopSchedule, ProbeSchedule, DEMOD_WINDOW, PROBE_LOG_LEN, PROBE_TRACKING_MIN_CORRELATION, GAUSSIAN_BT_RANGE, PLL_BANDWIDTH_HZ, RAMP_MS_RANGE};
\ No newline at end of file
@@ -26,6 +26,7 @@ where
     i_history: Vec<f64>,
     q_history: Vec<f64>,
     output_scale: f64,
+    symbols_modulated: u64,
 }
 
 impl<C, P, K, T> Modulator<C, P, K, T>
@@ -55,6 +56,7 @@ where
             // RX: /32768 * 2.0 * RRC_gain
             // Empirically calibrated for I/Q unity at symbol centers
             output_scale: 32768.0,
+            symbols_modulated: 0,
         }
     }
 
@@ -74,6 +76,7 @@ where
         let sps = self.timing.samples_per_symbol();
         let impulse_offset = self.timing.impulse_offset();
         let mut output = Vec::with_capacity(symbols.len() * sps);
+        self.symbols_modulated += symbols.len() as u64;
 
         for &sym in symbols {
             // Map symbol to I/Q
@@ -142,6 +145,27 @@ where
     pub fn timing(&self) -> &T {
         &self.timing
     }
+
+    /// Get reference to pulse shape
+    pub fn pulse(&self) -> &P {
+        &self.pulse
+    }
+
+    /// Get reference to carrier
+    pub fn carrier(&self) -> &K {
+        &self.carrier
+    }
+
+    /// Symbols modulated since creation, flush() padding included (reset()
+    /// doesn't clear it)
+    pub fn symbols_modulated(&self) -> u64 {
+        self.symbols_modulated
+    }
+
+    /// Estimate of the heap memory held: filter taps and I/Q history
+    pub fn memory_bytes(&self) -> usize {
+        3 * self.pulse.filter_len() * std::mem::size_of::<f64>()
+    }
 }
 
 #[cfg(test)]
@@ -195,6 +219,19 @@ mod tests {
         assert_eq!(samples1, samples2);
     }
 
+    #[test]
+    fn test_symbols_modulated() {
+        let mut mod_ = make_test_modulator();
+        mod_.modulate(&[0, 1, 2, 3]);
+        assert_eq!(mod_.symbols_modulated(), 4);
+
+        mod_.flush();
+        assert_eq!(mod_.symbols_modulated(), 4 + 2 * 6);
+
+        mod_.reset();
+        assert_eq!(mod_.symbols_modulated(), 16);
+    }
+
     #[test]
     fn test_modulator_bounded_output() {
         let mut mod_ = make_test_modulator();
@@ -0,0 +1,554 @@
+//! Per-burst decode reports
+//!
+//! UnifiedDemodulator keeps running statistics of the burst it is in and
+//! snapshots them with decode_report(), so a test harness gets the
+//! acquisition, PLL, equalizer and slicer view of one burst in one piece
+//! instead of joining separate stats by timestamp. A burst starts at each
+//! timing acquisition: the first demodulate call after creation or
+//! reset_to_idle(), and the block after a GapPolicy::Reacquire gap.
+//! reset_to_idle() empties the report, so take it before resetting.
+//!
+//! Everything is accumulated in place, in fixed-size state: the MSE curve
+//! halves its resolution when full rather than grow, and the mode
+//! timeline keeps its first MODE_TIMELINE_LEN changes.
+//!
+//! BurstReport::to_cbor() encodes a report for archival through its
+//! serde Serialize impl with ciborium, with the same keys and nesting as
+//! the NIF's map: atoms become text strings, nil null, tuples arrays.
+
+use minutemodem_dsp::level;
+use serde::Serialize;
+
+use super::unified::EqMode;
+
+/// Confidence histogram bins, evenly over 0..=1
+pub const CONFIDENCE_BINS: usize = 10;
+
+/// Most points on the equalizer MSE curve
+pub const MSE_CURVE_POINTS: usize = 128;
+
+/// Symbols between MSE curve points until the curve first fills
+pub const MSE_CURVE_STRIDE: u64 = 16;
+
+/// Most equalizer mode changes kept per burst
+pub const MODE_TIMELINE_LEN: usize = 16;
+
+/// Something about the burst worth a look
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BurstWarning {
+    /// Nonzero input peaking below minutemodem_dsp::level::I16_QUIET_COUNTS:
+    /// probably ±1.0 float audio rounded into i16
+    SuspiciousInputLevel,
+    /// Input samples at the clip threshold (i16 full scale unless set)
+    ClippedInput,
+    /// The PLL's frequency correction hit its ±50 Hz clamp
+    PllAtLimit,
+    /// The equalizer sliced symbols without leaving CMA
+    EqualizerNotConverged,
+    /// Sideband detection found the burst's sidebands swapped
+    InvertedSideband,
+}
+
+impl BurstWarning {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SuspiciousInputLevel => "suspicious_input_level",
+            Self::ClippedInput => "clipped_input",
+            Self::PllAtLimit => "pll_at_limit",
+            Self::EqualizerNotConverged => "equalizer_not_converged",
+            Self::InvertedSideband => "inverted_sideband",
+        }
+    }
+}
+
+/// Where and how the burst was acquired
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Acquisition {
+    /// Input sample index (as samples_consumed()) of the acquisition window
+    pub start_sample: u64,
+    /// Sample offset within a symbol picked as the symbol centre
+    pub timing_phase: usize,
+    /// PLL frequency correction at the end of the acquisition window, Hz
+    pub coarse_freq_hz: f64,
+    /// Whether sideband detection found the sidebands swapped; None with
+    /// it off or no whole probe in the acquisition window
+    pub sideband_inverted: Option<bool>,
+}
+
+/// Carrier PLL over the burst
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PllSummary {
+    /// Symbols the loop updated on
+    pub updates: u64,
+    /// Mean and largest |phase error| at the updates, radians
+    pub mean_abs_error: f64,
+    pub max_abs_error: f64,
+    /// Frequency correction now, Hz
+    pub freq_hz: f64,
+}
+
+/// Equalizer over the burst
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EqSummary {
+    /// (burst symbol index, mode) at the first equalized symbol and at
+    /// each change after it
+    pub mode_timeline: Vec<(u64, EqMode)>,
+    /// (burst symbol index, running MSE), every `mse_stride` symbols
+    pub mse_curve: Vec<(u64, f64)>,
+    pub mse_stride: u64,
+    pub mse: f64,
+    /// FNV-1a of the feedforward then feedback taps' bit patterns, to
+    /// tell whether two bursts ended on the same taps
+    pub taps_digest: u64,
+}
+
+/// Symbols of the burst, by how far they got
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct SymbolCounts {
+    /// Symbol-rate I/Q points out of the receive chain
+    pub iq: u64,
+    /// Points sliced into decisions (the slicing demodulate calls)
+    pub sliced: u64,
+    /// Sliced against known training symbols
+    pub trained: u64,
+    /// Decisions returned, after any EOT truncation
+    pub emitted: u64,
+}
+
+/// Snapshot of one burst (see UnifiedDemodulator::decode_report)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BurstReport {
+    /// None until the burst has been acquired
+    pub acquisition: Option<Acquisition>,
+    /// Input samples since the burst started
+    pub samples: u64,
+    /// Fraction of them at or above the clip threshold
+    pub clip_fraction: f64,
+    pub pll: PllSummary,
+    /// None without an equalizer
+    pub equalizer: Option<EqSummary>,
+    pub symbols: SymbolCounts,
+    /// Slicer confidence of the sliced symbols past the filter warm-up,
+    /// bin k counting k/10 up to (k+1)/10 (the last bin includes 1)
+    pub confidence_histogram: [u64; CONFIDENCE_BINS],
+    /// Sliced points against their decisions, past the filter warm-up:
+    /// decision power over error power, and RMS error over RMS decision
+    /// (None until there are any)
+    pub snr_db: Option<f64>,
+    pub evm_percent: Option<f64>,
+    pub eot_symbol: Option<u64>,
+    pub warnings: Vec<BurstWarning>,
+    pub fingerprint: u64,
+    /// The code that decoded it (see provenance)
+    pub provenance: &'static str,
+}
+
+/// FNV-1a over the bit patterns of `taps`
+pub(super) fn taps_digest(taps: impl IntoIterator<Item = (f64, f64)>) -> u64 {
+    taps.into_iter()
+        .flat_map(|(re, im)| [re.to_bits(), im.to_bits()])
+        .flat_map(u64::to_le_bytes)
+        .fold(0xcbf2_9ce4_8422_2325_u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Running statistics of the current burst
+#[derive(Debug, Clone)]
+pub(super) struct BurstStats {
+    acquisition: Option<Acquisition>,
+    samples: u64,
+    input_peak: u16,
+    clipped: u64,
+    pll_updates: u64,
+    pll_error_sum: f64,
+    pll_error_max: f64,
+    pll_at_limit: bool,
+    symbols: SymbolCounts,
+    /// Sliced symbols of filter warm-up, left out of the quality figures
+    warmup_symbols: u64,
+    histogram: [u64; CONFIDENCE_BINS],
+    decision_power: f64,
+    error_power: f64,
+    measured: u64,
+    equalized: u64,
+    mode_timeline: Vec<(u64, EqMode)>,
+    mse_curve: Vec<(u64, f64)>,
+    mse_stride: u64,
+}
+
+impl BurstStats {
+    pub fn new() -> Self {
+        Self::with_buffers(Vec::with_capacity(MODE_TIMELINE_LEN), Vec::with_capacity(MSE_CURVE_POINTS))
+    }
+
+    /// An empty burst recording into these (cleared) buffers
+    fn with_buffers(mode_timeline: Vec<(u64, EqMode)>, mse_curve: Vec<(u64, f64)>) -> Self {
+        Self {
+            acquisition: None,
+            samples: 0,
+            input_peak: 0,
+            clipped: 0,
+            pll_updates: 0,
+            pll_error_sum: 0.0,
+            pll_error_max: 0.0,
+            pll_at_limit: false,
+            symbols: SymbolCounts::default(),
+            warmup_symbols: 0,
+            histogram: [0; CONFIDENCE_BINS],
+            decision_power: 0.0,
+            error_power: 0.0,
+            measured: 0,
+            equalized: 0,
+            mode_timeline,
+            mse_curve,
+            mse_stride: MSE_CURVE_STRIDE,
+        }
+    }
+
+    /// Forget the burst, keeping the buffers
+    pub fn clear(&mut self) {
+        let mut mode_timeline = std::mem::take(&mut self.mode_timeline);
+        let mut mse_curve = std::mem::take(&mut self.mse_curve);
+        mode_timeline.clear();
+        mse_curve.clear();
+        *self = Self::with_buffers(mode_timeline, mse_curve);
+    }
+
+    /// Start a new burst acquired at `start_sample` on `timing_phase`,
+    /// its first `warmup_symbols` sliced symbols being filter warm-up
+    pub fn start(&mut self, start_sample: u64, timing_phase: usize, warmup_symbols: u64) {
+        self.clear();
+        self.acquisition = Some(Acquisition { start_sample, timing_phase, coarse_freq_hz: 0.0, sideband_inverted: None });
+        self.warmup_symbols = warmup_symbols;
+    }
+
+    /// The PLL's frequency as the acquisition window ends
+    pub fn set_coarse_freq(&mut self, hz: f64) {
+        if let Some(acquisition) = &mut self.acquisition {
+            acquisition.coarse_freq_hz = hz;
+        }
+    }
+
+    /// What sideband detection made of the acquisition window
+    pub fn set_sideband(&mut self, inverted: Option<bool>) {
+        if let Some(acquisition) = &mut self.acquisition {
+            acquisition.sideband_inverted = inverted;
+        }
+    }
+
+    /// Input samples as they arrive, counted clipped from `clip_level`
+    pub fn input(&mut self, samples: &[i16], clip_level: u16) {
+        self.samples += samples.len() as u64;
+        for &s in samples {
+            self.input_peak = self.input_peak.max(s.unsigned_abs());
+            self.clipped += (s.unsigned_abs() >= clip_level) as u64;
+        }
+    }
+
+    pub fn clip_fraction(&self) -> f64 {
+        if self.samples > 0 { self.clipped as f64 / self.samples as f64 } else { 0.0 }
+    }
+
+    /// A PLL update on `error` radians, the correction at its clamp or not
+    #[inline]
+    pub fn pll_update(&mut self, error: f64, at_limit: bool) {
+        self.pll_updates += 1;
+        self.pll_error_sum += error.abs();
+        self.pll_error_max = self.pll_error_max.max(error.abs());
+        self.pll_at_limit |= at_limit;
+    }
+
+    pub fn iq_points(&mut self, n: usize) {
+        self.symbols.iq += n as u64;
+    }
+
+    /// A decision on `point`, sliced to `decided` with `confidence`
+    #[inline]
+    pub fn sliced(&mut self, point: (f64, f64), decided: (f64, f64), confidence: f64, trained: bool) {
+        if self.symbols.sliced >= self.warmup_symbols {
+            let bin = ((confidence * CONFIDENCE_BINS as f64) as usize).min(CONFIDENCE_BINS - 1);
+            self.histogram[bin] += 1;
+            let (ei, eq) = (point.0 - decided.0, point.1 - decided.1);
+            self.error_power += ei * ei + eq * eq;
+            self.decision_power += decided.0 * decided.0 + decided.1 * decided.1;
+            self.measured += 1;
+        }
+        self.symbols.sliced += 1;
+        self.symbols.trained += trained as u64;
+    }
+
+    /// The equalizer's mode and running MSE after the symbol just sliced
+    #[inline]
+    pub fn equalized(&mut self, mode: EqMode, mse: f64) {
+        let n = self.equalized;
+        self.equalized += 1;
+        if self.mode_timeline.last().is_none_or(|&(_, last)| last != mode)
+            && self.mode_timeline.len() < MODE_TIMELINE_LEN
+        {
+            self.mode_timeline.push((n, mode));
+        }
+        if !n.is_multiple_of(self.mse_stride) {
+            return;
+        }
+        if self.mse_curve.len() == MSE_CURVE_POINTS {
+            let stride = 2 * self.mse_stride;
+            self.mse_curve.retain(|&(k, _)| k.is_multiple_of(stride));
+            self.mse_stride = stride;
+            if !n.is_multiple_of(stride) {
+                return;
+            }
+        }
+        self.mse_curve.push((n, mse));
+    }
+
+    /// Decisions a slicing call returned
+    pub fn emitted(&mut self, n: usize) {
+        self.symbols.emitted += n as u64;
+    }
+
+    /// Bytes held by the buffers
+    pub fn memory_bytes(&self) -> usize {
+        self.mode_timeline.capacity() * std::mem::size_of::<(u64, EqMode)>()
+            + self.mse_curve.capacity() * std::mem::size_of::<(u64, f64)>()
+    }
+
+    /// The report, given what only the demodulator knows: the PLL's
+    /// frequency, the equalizer's mode, MSE and taps digest, the EOT
+    /// symbol and the config fingerprint
+    pub fn report(
+        &self,
+        freq_hz: f64,
+        equalizer: Option<(EqMode, f64, u64)>,
+        eot_symbol: Option<u64>,
+        fingerprint: u64,
+    ) -> BurstReport {
+        let mut warnings = Vec::new();
+        if self.input_peak > 0 && self.input_peak < level::I16_QUIET_COUNTS {
+            warnings.push(BurstWarning::SuspiciousInputLevel);
+        }
+        if self.clipped > 0 {
+            warnings.push(BurstWarning::ClippedInput);
+        }
+        if self.pll_at_limit {
+            warnings.push(BurstWarning::PllAtLimit);
+        }
+        if equalizer.is_some_and(|(mode, ..)| mode == EqMode::CMA) && self.equalized > 0 {
+            warnings.push(BurstWarning::EqualizerNotConverged);
+        }
+        if self.acquisition.is_some_and(|a| a.sideband_inverted == Some(true)) {
+            warnings.push(BurstWarning::InvertedSideband);
+        }
+
+        let measured = self.measured > 0 && self.decision_power > 0.0;
+        BurstReport {
+            acquisition: self.acquisition,
+            samples: self.samples,
+            clip_fraction: self.clip_fraction(),
+            pll: PllSummary {
+                updates: self.pll_updates,
+                mean_abs_error: if self.pll_updates > 0 { self.pll_error_sum / self.pll_updates as f64 } else { 0.0 },
+                max_abs_error: self.pll_error_max,
+                freq_hz,
+            },
+            equalizer: equalizer.map(|(_, mse, taps_digest)| EqSummary {
+                mode_timeline: self.mode_timeline.clone(),
+                mse_curve: self.mse_curve.clone(),
+                mse_stride: self.mse_stride,
+                mse,
+                taps_digest,
+            }),
+            symbols: self.symbols,
+            confidence_histogram: self.histogram,
+            snr_db: measured.then(|| 10.0 * (self.decision_power / self.error_power).log10()),
+            evm_percent: measured.then(|| 100.0 * (self.error_power / self.decision_power).sqrt()),
+            eot_symbol,
+            warnings,
+            fingerprint,
+            provenance: crate::provenance::provenance(),
+        }
+    }
+}
+
+impl BurstReport {
+    /// The report as CBOR (see the module docs)
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(self, &mut bytes).expect("writing to a Vec can't fail");
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modem::{ConstellationType, DFEConfig, GapPolicy, UnifiedDemodulator, UnifiedModulator};
+
+    /// `n` pseudo-random 8-PSK symbols, modulated and flushed
+    fn burst(n: usize, seed: u32) -> Vec<i16> {
+        let mut x = seed;
+        let symbols: Vec<u8> = (0..n)
+            .map(|_| {
+                x = x.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (x >> 16) as u8 & 7
+            })
+            .collect();
+        let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0);
+        let mut samples = modulator.modulate(&symbols);
+        samples.extend(modulator.flush());
+        samples
+    }
+
+    fn trained_demodulator() -> UnifiedDemodulator {
+        let mut demod =
+            UnifiedDemodulator::with_equalizer(ConstellationType::Psk8, 9600, 2400, 1800.0, DFEConfig::hf_skywave());
+        demod.set_training_symbols(vec![0; 32]);
+        demod
+    }
+
+    #[test]
+    fn test_report_of_clean_burst() {
+        let samples = burst(600, 7);
+        let mut demod = UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0);
+        let symbols = demod.demodulate(&samples);
+        let report = demod.decode_report();
+
+        let acquisition = report.acquisition.unwrap();
+        assert_eq!(acquisition.start_sample, 0);
+        assert!(acquisition.timing_phase < 4);
+        assert_eq!(report.samples, samples.len() as u64);
+
+        assert_eq!(report.symbols.sliced, report.symbols.iq);
+        assert_eq!(report.symbols.emitted, symbols.len() as u64);
+        assert_eq!(report.symbols.trained, 0);
+        // The filter warm-up (2 * span symbols) is left out
+        let histogram_total: u64 = report.confidence_histogram.iter().sum();
+        assert_eq!(histogram_total, report.symbols.sliced - 12);
+
+        assert!(report.snr_db.unwrap() > 30.0, "{:?}", report.snr_db);
+        assert!(report.evm_percent.unwrap() < 3.0, "{:?}", report.evm_percent);
+        assert!(report.pll.updates > 0 && report.pll.max_abs_error >= report.pll.mean_abs_error);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert_eq!(report.equalizer, None);
+        assert_eq!(report.eot_symbol, None);
+        assert_eq!(report.fingerprint, demod.config_fingerprint());
+    }
+
+    #[test]
+    fn test_equalizer_summary() {
+        let mut demod = trained_demodulator();
+        demod.demodulate(&burst(600, 7));
+        let report = demod.decode_report();
+        assert_eq!(report.symbols.trained, 32);
+
+        let eq = report.equalizer.unwrap();
+        // Training takes it straight to decision-directed
+        assert_eq!(eq.mode_timeline, [(0, EqMode::DD)]);
+        assert_eq!(eq.mse_stride, MSE_CURVE_STRIDE);
+        assert!(eq.mse_curve.iter().enumerate().all(|(k, &(n, _))| n == k as u64 * MSE_CURVE_STRIDE));
+        assert_eq!(eq.mse, demod.equalizer_mse().unwrap());
+
+        // Same taps, same digest
+        let mut again = trained_demodulator();
+        again.demodulate(&burst(600, 7));
+        assert_eq!(again.decode_report().equalizer.unwrap().taps_digest, eq.taps_digest);
+    }
+
+    #[test]
+    fn test_successive_bursts_are_independent() {
+        let mut demod = trained_demodulator();
+        demod.demodulate(&burst(400, 1));
+        let first = demod.decode_report();
+
+        demod.reset_to_idle();
+        let empty = demod.decode_report();
+        assert_eq!(empty.acquisition, None);
+        assert_eq!((empty.samples, empty.symbols), (0, SymbolCounts::default()));
+        assert!(empty.equalizer.unwrap().mse_curve.is_empty());
+
+        // A different burst in between leaves no trace
+        demod.set_training_symbols(vec![0; 32]);
+        demod.demodulate(&burst(900, 2));
+        assert_ne!(demod.decode_report(), first);
+        demod.reset_to_idle();
+        demod.set_training_symbols(vec![0; 32]);
+        demod.demodulate(&burst(400, 1));
+        assert_eq!(demod.decode_report(), first);
+    }
+
+    #[test]
+    fn test_reacquisition_starts_a_burst() {
+        let mut demod = UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0);
+        let samples = burst(300, 3);
+        demod.demodulate(&samples);
+        let start = samples.len() as u64 + 4801;
+        demod.demodulate_at(start, &samples, GapPolicy::Reacquire);
+
+        let report = demod.decode_report();
+        assert_eq!(report.acquisition.unwrap().start_sample, start);
+        assert_eq!(report.samples, samples.len() as u64);
+        assert_eq!(report.equalizer, None);
+    }
+
+    #[test]
+    fn test_input_level_warnings() {
+        let quiet: Vec<i16> = burst(200, 4).iter().map(|&s| s / 1000).collect();
+        let mut demod = UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0);
+        demod.demodulate(&quiet);
+        assert!(demod.decode_report().warnings.contains(&BurstWarning::SuspiciousInputLevel));
+
+        let loud: Vec<i16> = burst(200, 4).iter().map(|&s| s.saturating_mul(8)).collect();
+        demod.reset_to_idle();
+        demod.demodulate(&loud);
+        assert!(demod.decode_report().warnings.contains(&BurstWarning::ClippedInput));
+    }
+
+    #[test]
+    fn test_mse_curve_stays_bounded() {
+        let mut demod = trained_demodulator();
+        for k in 0..6 {
+            demod.demodulate(&burst(1000, 10 + k));
+        }
+        let eq = demod.decode_report().equalizer.unwrap();
+        assert!(eq.mse_curve.len() <= MSE_CURVE_POINTS);
+        assert!(eq.mse_stride > MSE_CURVE_STRIDE);
+        assert!(eq.mse_curve.iter().all(|&(n, _)| n.is_multiple_of(eq.mse_stride)));
+        assert!(eq.mse_curve.windows(2).all(|w| w[1].0 - w[0].0 == eq.mse_stride));
+    }
+
+    #[test]
+    fn test_cbor_layout() {
+        let mut demod = trained_demodulator();
+        demod.demodulate(&burst(200, 5));
+        let report = demod.decode_report();
+        let bytes = report.to_cbor();
+
+        // A map of 13, starting "acquisition": {"start_sample": 0, ...
+        let mut head = vec![0xad, 0x6b];
+        head.extend_from_slice(b"acquisition");
+        head.extend_from_slice(&[0xa4, 0x6c]);
+        head.extend_from_slice(b"start_sample");
+        head.push(0x00);
+        assert!(bytes.starts_with(&head));
+
+        // ... and decodes back to the map's keys and values
+        let value: ciborium::Value = ciborium::from_reader(bytes.as_slice()).unwrap();
+        let map = value.as_map().unwrap();
+        let keys: Vec<&str> = map.iter().map(|(k, _)| k.as_text().unwrap()).collect();
+        assert_eq!(
+            keys,
+            [
+                "acquisition", "samples", "clip_fraction", "pll", "equalizer", "symbols",
+                "confidence_histogram", "snr_db", "evm_percent", "eot_symbol", "warnings",
+                "fingerprint", "provenance",
+            ]
+        );
+        let get = |key: &str| &map.iter().find(|(k, _)| k.as_text() == Some(key)).unwrap().1;
+        assert_eq!(get("fingerprint"), &ciborium::Value::from(report.fingerprint));
+        assert_eq!(get("provenance").as_text(), Some(report.provenance));
+        assert_eq!(get("pll").as_map().unwrap()[3].1.as_float(), Some(report.pll.freq_hz));
+        let timeline = get("equalizer").as_map().unwrap()[0].1.as_array().unwrap();
+        let (n, mode) = report.equalizer.as_ref().unwrap().mode_timeline[0];
+        assert_eq!(timeline[0], ciborium::Value::Array(vec![n.into(), format!("{mode:?}").to_lowercase().into()]));
+        assert_eq!(report.provenance, crate::provenance::provenance());
+    }
+}
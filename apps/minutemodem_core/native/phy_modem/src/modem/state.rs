@@ -0,0 +1,95 @@
+//! Canonical byte encoding of modem state
+//!
+//! UnifiedDemodulator::export_state() writes every field that affects
+//! later output in a fixed order: integers little-endian (usize as u64),
+//! floats by bit pattern (so -0.0 and each NaN stay distinct), options as
+//! a 0/1 tag then the value, sequences as a u64 length then the elements.
+//! Scratch buffers are left out, their contents never being read before
+//! they're overwritten.
+//!
+//! The encoding starts with a 4-byte tag and STATE_VERSION; any change to
+//! what's written or its order bumps the version.
+
+/// Version of the encoding written after the tag
+pub const STATE_VERSION: u32 = 9;
+
+/// Appends fields to an export_state() buffer
+pub(crate) struct StateWriter {
+    buf: Vec<u8>,
+    /// Subnormal f64 fields written so far (see minutemodem_dsp::denormal)
+    #[cfg(test)]
+    subnormals: usize,
+}
+
+impl StateWriter {
+    pub fn new(tag: &[u8; 4]) -> Self {
+        let mut w = Self {
+            buf: Vec::with_capacity(1024),
+            #[cfg(test)]
+            subnormals: 0,
+        };
+        w.buf.extend_from_slice(tag);
+        w.u32(STATE_VERSION);
+        w
+    }
+
+    pub fn u8(&mut self, x: u8) {
+        self.buf.push(x);
+    }
+
+    pub fn bool(&mut self, x: bool) {
+        self.u8(x as u8);
+    }
+
+    pub fn u32(&mut self, x: u32) {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, x: u64) {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+    }
+
+    pub fn usize(&mut self, x: usize) {
+        self.u64(x as u64);
+    }
+
+    pub fn f64(&mut self, x: f64) {
+        #[cfg(test)]
+        {
+            self.subnormals += x.is_subnormal() as usize;
+        }
+        self.u64(x.to_bits());
+    }
+
+    pub fn option<T>(&mut self, x: Option<T>, write: impl FnOnce(&mut Self, T)) {
+        self.bool(x.is_some());
+        if let Some(x) = x {
+            write(self, x);
+        }
+    }
+
+    pub fn seq<T>(&mut self, items: impl ExactSizeIterator<Item = T>, mut write: impl FnMut(&mut Self, T)) {
+        self.usize(items.len());
+        for x in items {
+            write(self, x);
+        }
+    }
+
+    pub fn f64s(&mut self, xs: &[f64]) {
+        self.seq(xs.iter(), |w, &x| w.f64(x));
+    }
+
+    pub fn bytes(&mut self, xs: &[u8]) {
+        self.usize(xs.len());
+        self.buf.extend_from_slice(xs);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+
+    #[cfg(test)]
+    pub fn subnormals(&self) -> usize {
+        self.subnormals
+    }
+}
@@ -465,10 +465,7 @@ mod loopback_tests {
         );
         
         // Generate BPSK sequence (symbols 0 and 4 only)
-        let bpsk_sequence: Vec<u8> = vec![
-            0, 4, 0, 0, 4, 0, 4, 4, 0, 0, 4, 4, 4, 0, 0, 4,
-            4, 4, 0, 4, 0, 0, 0, 4, 0, 4, 0, 4, 4, 0, 4, 0,
-        ];
+        let bpsk_sequence: Vec<u8> = crate::probes::capture_probe(32).unwrap();
         
         // Long preamble
         let mut symbols = vec![0u8; 50];
@@ -614,12 +611,7 @@ mod ale_tests {
     const SYMBOL_RATE: u32 = 2400;
     const CARRIER_FREQ: f64 = 1800.0;
     
-    /// The ALE capture probe sequence (first 32 symbols)
-    const CAPTURE_PROBE: [u8; 32] = [
-        0, 4, 0, 0, 4, 0, 4, 4, 0, 0, 4, 4, 4, 0, 0, 4,
-        4, 4, 0, 4, 0, 0, 0, 4, 0, 4, 0, 4, 4, 0, 4, 0,
-    ];
-    
+
     /// Test that we can recover the capture probe
     #[test]
     fn test_capture_probe_recovery() {
@@ -630,11 +622,14 @@ mod ale_tests {
             ConstellationType::Psk8, SAMPLE_RATE, SYMBOL_RATE, CARRIER_FREQ
         );
         
+        // The ALE capture probe sequence (first 32 symbols)
+        let capture_probe = crate::probes::capture_probe(32).unwrap();
+        
         // Build sequence: preamble + probe + probe + probe
         let mut symbols = vec![0u8; 50];  // Preamble for lock
-        symbols.extend_from_slice(&CAPTURE_PROBE);
-        symbols.extend_from_slice(&CAPTURE_PROBE);
-        symbols.extend_from_slice(&CAPTURE_PROBE);
+        symbols.extend_from_slice(&capture_probe);
+        symbols.extend_from_slice(&capture_probe);
+        symbols.extend_from_slice(&capture_probe);
         
         let mut samples = mod_.modulate(&symbols);
         samples.extend(mod_.flush());
@@ -648,7 +643,7 @@ mod ale_tests {
             let rx_section = &recovered[probe_start..probe_start + 32];
             
             // BPSK correlation
-            let corr: i32 = CAPTURE_PROBE.iter().zip(rx_section)
+            let corr: i32 = capture_probe.iter().zip(rx_section)
                 .map(|(&t, &r)| {
                     let t_sign = if t < 4 { 1 } else { -1 };
                     let r_sign = if r < 4 { 1 } else { -1 };
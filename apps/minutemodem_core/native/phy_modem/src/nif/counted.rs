@@ -0,0 +1,121 @@
+//! Locking that keeps the resource census current
+//!
+//! Every resource holds a Tally in its kind's census (see crate::census).
+//! Locking one through its state() returns a Counted guard that reports
+//! the buffer bytes back as the call lets go, so the census follows
+//! buffers that grow with use: scratch sized by the largest input so far,
+//! the push_symbols() queue, the probe log.
+
+use rustler::NifMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard};
+
+use super::error::{lock, Recover};
+use super::{DemodulatorTrait, ModulatorTrait};
+use crate::census::{CensusCount, Tally};
+use crate::modem::{UnifiedDemodulator, UnifiedModulator, DFE};
+use crate::pulse_shapes::PulseShaper;
+use crate::scope::ConstellationScope;
+use crate::walsh::WalshCorrelator;
+
+/// Resource state whose buffers the census counts
+pub trait Measured {
+    fn memory_bytes(&self) -> usize;
+}
+
+impl Measured for Box<dyn ModulatorTrait> {
+    fn memory_bytes(&self) -> usize {
+        self.describe().memory_bytes
+    }
+}
+
+impl Measured for Box<dyn DemodulatorTrait> {
+    fn memory_bytes(&self) -> usize {
+        self.describe().memory_bytes
+    }
+}
+
+impl Measured for UnifiedModulator {
+    fn memory_bytes(&self) -> usize {
+        UnifiedModulator::memory_bytes(self)
+    }
+}
+
+impl Measured for UnifiedDemodulator {
+    fn memory_bytes(&self) -> usize {
+        UnifiedDemodulator::memory_bytes(self)
+    }
+}
+
+impl Measured for DFE {
+    fn memory_bytes(&self) -> usize {
+        DFE::memory_bytes(self)
+    }
+}
+
+impl Measured for WalshCorrelator {
+    fn memory_bytes(&self) -> usize {
+        WalshCorrelator::memory_bytes(self)
+    }
+}
+
+impl Measured for PulseShaper {
+    fn memory_bytes(&self) -> usize {
+        PulseShaper::memory_bytes(self)
+    }
+}
+
+impl Measured for ConstellationScope {
+    fn memory_bytes(&self) -> usize {
+        ConstellationScope::memory_bytes(self)
+    }
+}
+
+/// A locked resource that brings its tally up to date when released
+pub struct Counted<'a, T: Measured> {
+    state: MutexGuard<'a, T>,
+    tally: &'a Tally,
+}
+
+impl<'a, T: Measured + Recover> Counted<'a, T> {
+    /// lock() `inner`, reporting to `tally` on release
+    pub fn lock(inner: &'a Mutex<T>, tally: &'a Tally) -> Self {
+        Self { state: lock(inner), tally }
+    }
+}
+
+impl<T: Measured> Deref for Counted<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.state
+    }
+}
+
+impl<T: Measured> DerefMut for Counted<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.state
+    }
+}
+
+impl<T: Measured> Drop for Counted<'_, T> {
+    fn drop(&mut self) {
+        self.tally.set_bytes(self.state.memory_bytes());
+    }
+}
+
+/// One kind's entry in census/0 (see super::census)
+#[derive(NifMap)]
+pub struct CensusMap {
+    pub live: u64,
+    pub created: u64,
+    pub dropped: u64,
+    /// Buffer bytes the live ones held as their last call returned
+    pub bytes: u64,
+}
+
+impl From<CensusCount> for CensusMap {
+    fn from(c: CensusCount) -> Self {
+        CensusMap { live: c.live, created: c.created, dropped: c.dropped, bytes: c.bytes }
+    }
+}
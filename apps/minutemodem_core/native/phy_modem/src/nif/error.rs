@@ -0,0 +1,179 @@
+//! Error type shared by every NIF in this crate
+//!
+//! A NIF that fails returns `{:error, reason}`, where reason is one of:
+//!
+//! | Variant                       | Reason term                      |
+//! |-------------------------------|----------------------------------|
+//! | `InvalidArgument(which)`      | `{:invalid_argument, which}`     |
+//! | `OutOfRange(which, max)`      | `{:out_of_range, which, max}`    |
+//! | `UnsupportedConstellation`    | `:unsupported_constellation`     |
+//! | `IncompatibleState(detail)`   | `{:incompatible_state, detail}`  |
+//! | `NumericFault(detail)`        | `{:numeric_fault, detail}`       |
+//! | `AllocFailed`                 | `:alloc_failed`                  |
+//!
+//! `which` names the offending argument or option (`:sample_rate`,
+//! `:dfe_config`, ...); `detail` is an atom describing what went wrong.
+//!
+//! A NIF that touches a resource runs inside guarded(), so a panic comes
+//! back as `{:error, {:panic, message}}` instead of raising. The panic
+//! poisons the resource's lock on the way out; the next lock() clears the
+//! poison and puts the resource back to its just-reset state (see
+//! Recover) rather than failing every call from then on.
+
+use minutemodem_dsp::guard;
+use minutemodem_dsp::limits::OutOfRange;
+use rustler::{Atom, Encoder, Env, NifResult, Term};
+use std::sync::{Mutex, MutexGuard};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhyError {
+    /// An argument or option was out of range or malformed
+    InvalidArgument(&'static str),
+    /// A number that sizes an allocation was above its maximum (see limits)
+    OutOfRange(&'static str, u64),
+    /// Not one of :bpsk, :qpsk, :psk8, :qam16, :qam32, :qam64
+    UnsupportedConstellation,
+    /// The call doesn't apply to the resource as configured
+    IncompatibleState(&'static str),
+    /// The DSP state went non-finite
+    NumericFault(&'static str),
+    /// The VM couldn't allocate the result binary
+    AllocFailed,
+}
+
+impl PhyError {
+    /// Reason atom and, for the tuple forms, the detail atom
+    pub fn parts(self) -> (&'static str, Option<&'static str>) {
+        match self {
+            PhyError::InvalidArgument(which) => ("invalid_argument", Some(which)),
+            PhyError::OutOfRange(which, _) => ("out_of_range", Some(which)),
+            PhyError::UnsupportedConstellation => ("unsupported_constellation", None),
+            PhyError::IncompatibleState(detail) => ("incompatible_state", Some(detail)),
+            PhyError::NumericFault(detail) => ("numeric_fault", Some(detail)),
+            PhyError::AllocFailed => ("alloc_failed", None),
+        }
+    }
+}
+
+impl Encoder for PhyError {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        let atom = |name: &str| {
+            Atom::from_str(env, name)
+                .expect("error atoms are short ASCII names")
+                .encode(env)
+        };
+        match (self.parts(), self) {
+            ((reason, Some(which)), PhyError::OutOfRange(_, max)) => (atom(reason), atom(which), *max).encode(env),
+            ((reason, None), _) => atom(reason),
+            ((reason, Some(detail)), _) => (atom(reason), atom(detail)).encode(env),
+        }
+    }
+}
+
+impl From<OutOfRange> for PhyError {
+    fn from(e: OutOfRange) -> Self {
+        PhyError::OutOfRange(e.param, e.max)
+    }
+}
+
+impl From<PhyError> for rustler::Error {
+    fn from(e: PhyError) -> Self {
+        rustler::Error::Term(Box::new(e))
+    }
+}
+
+/// State a resource can be put back into after a panic left it half
+/// updated
+pub trait Recover {
+    fn recover(&mut self);
+}
+
+/// Lock a resource, recovering it if an earlier call panicked with it held
+pub fn lock<T: Recover>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        let mut state = poisoned.into_inner();
+        state.recover();
+        mutex.clear_poison();
+        state
+    })
+}
+
+/// A panic caught by guarded(), encoded as `{:panic, message}` (the
+/// message cut as by minutemodem_dsp::guard)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Panicked(pub String);
+
+impl Encoder for Panicked {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        let panic = Atom::from_str(env, "panic").expect("error atoms are short ASCII names");
+        (panic, self.0.as_str()).encode(env)
+    }
+}
+
+/// Run a NIF body, turning a panic into `{:error, {:panic, message}}`
+pub fn guarded<T>(body: impl FnOnce() -> NifResult<T>) -> NifResult<T> {
+    guard::catch(body).unwrap_or_else(|message| Err(rustler::Error::Term(Box::new(Panicked(message)))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reason_shapes() {
+        assert_eq!(
+            PhyError::InvalidArgument("sample_rate").parts(),
+            ("invalid_argument", Some("sample_rate"))
+        );
+        assert_eq!(PhyError::OutOfRange("ff_taps", 256).parts(), ("out_of_range", Some("ff_taps")));
+        assert_eq!(PhyError::UnsupportedConstellation.parts(), ("unsupported_constellation", None));
+        assert_eq!(
+            PhyError::IncompatibleState("no_equalizer").parts(),
+            ("incompatible_state", Some("no_equalizer"))
+        );
+        assert_eq!(
+            PhyError::NumericFault("equalizer_diverged").parts(),
+            ("numeric_fault", Some("equalizer_diverged"))
+        );
+        assert_eq!(PhyError::AllocFailed.parts(), ("alloc_failed", None));
+    }
+
+    /// Counts recoveries; the test-only hook panics while it's locked
+    #[derive(Default)]
+    struct Counter {
+        value: u32,
+        recoveries: u32,
+    }
+
+    impl Recover for Counter {
+        fn recover(&mut self) {
+            self.value = 0;
+            self.recoveries += 1;
+        }
+    }
+
+    fn bump(mutex: &Mutex<Counter>, fail: bool) -> NifResult<u32> {
+        guarded(|| {
+            let mut state = lock(mutex);
+            state.value += 1;
+            if fail {
+                panic!("index out of bounds: the len is 4 but the index is {}", state.value);
+            }
+            Ok(state.value)
+        })
+    }
+
+    #[test]
+    fn test_panic_is_caught_and_lock_recovers() {
+        let mutex = Mutex::new(Counter::default());
+        assert_eq!(bump(&mutex, false).ok(), Some(1));
+
+        assert!(bump(&mutex, true).is_err());
+        assert!(mutex.is_poisoned());
+
+        // The next call gets a reset resource, and the poison is gone
+        assert_eq!(bump(&mutex, false).ok(), Some(1));
+        assert!(!mutex.is_poisoned());
+        assert_eq!(lock(&mutex).recoveries, 1);
+    }
+}
@@ -0,0 +1,71 @@
+//! Input level warnings
+//!
+//! The demodulator NIFs take i16 PCM. A block that is nonzero but peaks
+//! below a few dozen counts is almost always ±1.0 float audio rounded
+//! into i16 (see minutemodem_dsp::level). The call still succeeds, but
+//!
+//!   {:input_warning, :suspicious_input_level, demodulator, peak_counts}
+//!
+//! goes to the pid registered with set_warning_logger/1. Nothing is sent
+//! while no logger is registered, or for a demodulator whose warnings were
+//! turned off.
+
+use minutemodem_dsp::level;
+use rustler::{Encoder, Env, LocalPid};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use super::{input_warning, suspicious_input_level};
+
+static LOGGER: Mutex<Option<LocalPid>> = Mutex::new(None);
+
+/// Where warnings go from now on (None stops them)
+pub fn set_logger(pid: Option<LocalPid>) {
+    *LOGGER.lock().unwrap_or_else(|e| e.into_inner()) = pid;
+}
+
+fn logger() -> Option<LocalPid> {
+    *LOGGER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Peak of `samples` if they look mis-scaled and warnings are on
+fn quiet_peak(enabled: &AtomicBool, samples: &[i16]) -> Option<u16> {
+    if !enabled.load(Ordering::Relaxed) {
+        return None;
+    }
+    level::quiet_i16_peak(samples)
+}
+
+/// Warn the logger about `resource`'s input, if it looks mis-scaled
+pub fn check_i16(env: Env, enabled: &AtomicBool, resource: &impl Encoder, samples: &[i16]) {
+    let Some(pid) = logger() else {
+        return;
+    };
+    if let Some(peak) = quiet_peak(enabled, samples) {
+        // A dead logger just misses the message
+        let _ = env.send(&pid, (input_warning(), suspicious_input_level(), resource, peak));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float_scaled_input_is_flagged_unless_suppressed() {
+        let rounded: Vec<i16> = (0..4800).map(|n| (n as f64 * 0.3).sin().round() as i16).collect();
+        let enabled = AtomicBool::new(true);
+        assert_eq!(quiet_peak(&enabled, &rounded), Some(1));
+
+        enabled.store(false, Ordering::Relaxed);
+        assert_eq!(quiet_peak(&enabled, &rounded), None);
+    }
+
+    #[test]
+    fn test_proper_pcm_is_not_flagged() {
+        let enabled = AtomicBool::new(true);
+        let tone: Vec<i16> = (0..4800).map(|n| ((n as f64 * 0.3).sin() * 8000.0) as i16).collect();
+        assert_eq!(quiet_peak(&enabled, &tone), None);
+        assert_eq!(quiet_peak(&enabled, &[0; 4800]), None);
+    }
+}
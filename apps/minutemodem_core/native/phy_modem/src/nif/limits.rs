@@ -0,0 +1,134 @@
+//! Upper bounds on NIF arguments that size allocations
+//!
+//! Anything a NIF allocates in proportion to a number the caller passes
+//! (filter taps, samples per symbol, image size, samples to generate) is
+//! checked against a maximum here before the allocation (with
+//! minutemodem_dsp::limits::check_max, whose OutOfRange converts into
+//! PhyError::OutOfRange), so a stray 10_000_000 comes back as `{:error, {:out_of_range, param, max}}`
+//! instead of gigabytes allocated on a scheduler thread.
+//!
+//! Binaries and lists the caller passes in aren't bounded: the VM already
+//! holds them, and the work done on them is proportional to their size.
+
+/// Highest sample rate, Hz, of every NIF that takes one
+pub const MAX_SAMPLE_RATE: u32 = 192_000;
+
+/// Most samples per symbol (sample_rate / symbol_rate). The RRC filters
+/// are 12 * sps + 1 taps and the demodulator windows scale with it too.
+pub const MAX_SAMPLES_PER_SYMBOL: u32 = 512;
+
+/// Most equalizer taps, feedforward and feedback each. The HF preset
+/// uses 21 feedforward.
+pub const MAX_EQ_TAPS: usize = 256;
+
+/// Most samples one unified_mod_pull_samples call returns (10 s at 48 kHz)
+pub const MAX_PULL_SAMPLES: usize = 480_000;
+
+/// Most points a unified demodulator's baseband capture holds (8 MiB of
+/// f32 I/Q; 22 s at 48 kHz, decimation 1)
+pub const MAX_CAPTURE_SAMPLES: usize = 1 << 20;
+
+/// Most sliced points a unified demodulator keeps for its scatter (1 MiB)
+pub const MAX_SCATTER_POINTS: usize = 1 << 16;
+
+/// Most symbols in the end-of-transmission detector's short averages
+pub const MAX_EOT_AVERAGE_SYMBOLS: usize = 4096;
+
+/// Most symbols one prbs_symbols call returns (a full PRBS23 period
+/// fits)
+pub const MAX_PRBS_SYMBOLS: usize = 1 << 23;
+
+/// Most samples per impulse pulse_shaper_new accepts
+pub const MAX_SHAPER_SPS: usize = 64;
+
+/// Widest filter span (symbols each side) pulse_shaper_new accepts
+pub const MAX_SHAPER_SPAN: usize = 32;
+
+/// Largest constellation scope image edge, pixels
+pub const MAX_SCOPE_DIM: usize = 2048;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nif::PhyError;
+    use crate::modem::{ConstellationType, UnifiedModulator};
+    use crate::modem::UnifiedDemodulator;
+    use crate::nif::{
+        check_rates, constellation_scope, enable_capture, eq_demodulator, pull_samples, pulse_shaper, set_scatter_points,
+    };
+
+    #[test]
+    fn test_oversized_arguments_are_rejected() {
+        let max_rate = MAX_SAMPLE_RATE as u64;
+        assert_eq!(check_rates(u32::MAX, 2400, 1800.0), Err(PhyError::OutOfRange("sample_rate", max_rate)));
+        assert_eq!(
+            check_rates(96_000, 75, 1800.0),
+            Err(PhyError::OutOfRange("samples_per_symbol", MAX_SAMPLES_PER_SYMBOL as u64))
+        );
+        assert_eq!(check_rates(MAX_SAMPLE_RATE, 2400, 1800.0), Ok(()));
+
+        for (ff, fb, param) in [(10_000_000, 5, "ff_taps"), (15, 10_000_000, "fb_taps")] {
+            let err = eq_demodulator(ConstellationType::Psk8, 9600, ff, fb, 0.01).err();
+            assert_eq!(err, Some(PhyError::OutOfRange(param, MAX_EQ_TAPS as u64)));
+        }
+        let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0);
+        assert_eq!(
+            pull_samples(&mut modulator, MAX_PULL_SAMPLES + 1).err(),
+            Some(PhyError::OutOfRange("n", MAX_PULL_SAMPLES as u64))
+        );
+        let mut demod = UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0);
+        assert_eq!(
+            enable_capture(&mut demod, 4, MAX_CAPTURE_SAMPLES + 1).err(),
+            Some(PhyError::OutOfRange("max_samples", MAX_CAPTURE_SAMPLES as u64))
+        );
+        assert_eq!(enable_capture(&mut demod, 0, 100).err(), Some(PhyError::InvalidArgument("decimation")));
+        assert!(!demod.has_capture());
+        assert_eq!(
+            set_scatter_points(&mut demod, MAX_SCATTER_POINTS + 1).err(),
+            Some(PhyError::OutOfRange("scatter_points", MAX_SCATTER_POINTS as u64))
+        );
+        assert_eq!(set_scatter_points(&mut demod, MAX_SCATTER_POINTS), Ok(()));
+        assert_eq!(
+            pulse_shaper(1 << 30, 0.35, 6).err(),
+            Some(PhyError::OutOfRange("sps", MAX_SHAPER_SPS as u64))
+        );
+        assert_eq!(
+            pulse_shaper(8, 0.35, 1 << 30).err(),
+            Some(PhyError::OutOfRange("span", MAX_SHAPER_SPAN as u64))
+        );
+        assert_eq!(
+            constellation_scope(64, 1 << 30, 1.5).err(),
+            Some(PhyError::OutOfRange("height", MAX_SCOPE_DIM as u64))
+        );
+    }
+
+    /// Each rejected call allocates next to nothing: the check comes
+    /// before anything sized by the argument
+    #[cfg(feature = "alloc-audit")]
+    #[test]
+    fn test_rejection_allocates_nothing() {
+        use crate::alloc_audit::measure;
+
+        const SMALL: usize = 1024;
+        let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0);
+
+        let checks: [(&str, fn() -> bool); 7] = [
+            ("sample_rate", || check_rates(u32::MAX, 2400, 1800.0).is_err()),
+            ("samples_per_symbol", || check_rates(96_000, 1, 1800.0).is_err()),
+            ("ff_taps", || eq_demodulator(ConstellationType::Psk8, 9600, 10_000_000, 5, 0.01).is_err()),
+            ("fb_taps", || eq_demodulator(ConstellationType::Psk8, 9600, 15, 10_000_000, 0.01).is_err()),
+            ("sps", || pulse_shaper(1 << 30, 0.35, 6).is_err()),
+            ("span", || pulse_shaper(8, 0.35, 1 << 30).is_err()),
+            ("width", || constellation_scope(1 << 30, 64, 1.5).is_err()),
+        ];
+        for (name, check) in checks {
+            let (rejected, r) = measure(check);
+            assert!(rejected, "{} accepted", name);
+            assert!(r.peak_bytes < SMALL, "{}: {} B", name, r.peak_bytes);
+        }
+
+        let (pulled, r) = measure(|| pull_samples(&mut modulator, usize::MAX / 2));
+        assert!(pulled.is_err());
+        assert!(r.peak_bytes < SMALL, "n: {} B", r.peak_bytes);
+    }
+}
@@ -2,17 +2,78 @@
 //!
 //! Provides Rustler NIFs that expose the modulator and demodulator.
 //! Modulation type is selected at construction time via atom matching.
+//!
+//! Every NIF fails the same way: `{:error, reason}` with reason one of
+//!
+//! * `{:invalid_argument, which}` - `which` names the bad argument or option:
+//!   `:sample_rate`, `:symbol_rate`, `:carrier_freq`, `:ff_taps`, `:mu`,
+//!   `:dfe_config`, `:dfe_preset`, `:iq`, `:known_symbols`, `:rx_filter`,
+//!   `:probe_kind`, `:length`, `:boundary_marker`, `:dimensions`,
+//!   `:full_scale`, `:persistence`, `:colormap`, `:polynomial`,
+//!   `:stage_timing`, `:timing_tracking`, `:symbol_map`, `:rotation_deg`,
+//!   `:ramp_ms`, `:hops`, `:resource`, `:decimation`, `:waveform`,
+//!   `:noise_var`, `:scatter_points`
+//! * `{:out_of_range, param, max}` - a number that sizes an allocation
+//!   (`:sample_rate`, `:samples_per_symbol`, `:ff_taps`, `:fb_taps`, `:n`,
+//!   `:average_symbols`, `:sps`, `:span`, `:width`, `:height`, `:count`,
+//!   `:max_samples`, `:scatter_points`) is above
+//!   `max` (see limits)
+//! * `:unsupported_constellation` - modulation atom not recognised
+//! * `{:panic, message}` - the call panicked (a bug); `message` is the
+//!   panic message, cut to 256 bytes. The resource is reset by the next
+//!   call that uses it (see error::Recover)
+//! * `{:incompatible_state, :no_equalizer}` - equalizer call on a
+//!   demodulator built without one
+//! * `{:incompatible_state, :no_stage_timing}` - stage timings asked of a
+//!   demodulator not timing its stages
+//! * `{:incompatible_state, :no_capture}` - capture drained from a
+//!   demodulator not capturing
+//! * `{:incompatible_state, :no_probe_tracking}` - probe metrics drained
+//!   from a demodulator not tracking probes
+//! * `{:numeric_fault, :equalizer_diverged}` - equalizer MSE went non-finite
+//! * `:alloc_failed` - the result binary couldn't be allocated
+//!
+//! Arguments of the wrong Erlang type are still rejected by Rustler with
+//! `ArgumentError` before the NIF runs. See [`PhyError`].
+//!
+//! Demodulator NIFs also sanity-check the level of their i16 input and
+//! report suspicious blocks to a logger pid without failing (see
+//! input_level).
 
-use rustler::{Atom, NifResult, ResourceArc};
+use rustler::{Atom, Binary, Encoder, Env, LocalPid, NifMap, NifResult, OwnedBinary, ResourceArc, Term};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 
 use crate::carriers::Nco;
+use crate::census::{self as kinds, Tally};
+use crate::filters::{Biquad, BiquadCascade, RxFilterPreset};
 use crate::constellations::*;
-use crate::modem::{Demodulator, Modulator, UnifiedModulator, UnifiedDemodulator, ConstellationType, DFEConfig};
-use crate::pulse_shapes::RootRaisedCosine;
+use crate::modem::{ClipConfig, ShortBurst, ShortBurstConfig, ConfigMismatch, ModemConfig, Demodulator, Modulator, UnifiedModulator, UnifiedDemodulator, ConstellationType, Adaptation, DFEConfig, DFE, Discontinuity, EotConfig, EqMode, FreqCorrection, GapPolicy, HopSchedule, ProbeMetric, ProbeSchedule, Pulse, StageTimes, SymbolMap, GAUSSIAN_BT_RANGE, RAMP_MS_RANGE};
+use crate::modem;
+use crate::prbs::{self, PrbsPolynomial};
+use crate::probes;
+use crate::pulse_shapes::{PulseShaper, RootRaisedCosine, DEFAULT_ALPHA};
+use crate::scope::{evm_rms, Colormap, ConstellationScope};
 use crate::timing::FixedTiming;
+use crate::walsh::{self, Walsh75Symbol, WalshCorrelator, WalshDwell};
+use crate::waveform::{self, WaveformPreset};
+use crate::wire;
 use crate::traits::{Carrier, Constellation, PulseShape, SymbolTiming};
 
+mod arena;
+mod counted;
+mod error;
+mod input_level;
+mod limits;
+pub use error::PhyError;
+use counted::{CensusMap, Counted};
+use error::{guarded, Recover};
+use minutemodem_dsp::limits::check_max;
+use limits::{
+    MAX_CAPTURE_SAMPLES, MAX_EOT_AVERAGE_SYMBOLS, MAX_EQ_TAPS, MAX_PRBS_SYMBOLS, MAX_PULL_SAMPLES, MAX_SAMPLES_PER_SYMBOL,
+    MAX_SAMPLE_RATE, MAX_SCATTER_POINTS, MAX_SCOPE_DIM, MAX_SHAPER_SPAN, MAX_SHAPER_SPS,
+};
+
 // Atoms for modulation types
 rustler::atoms! {
     ok,
@@ -28,9 +89,107 @@ rustler::atoms! {
     // Equalizer modes
     cma,
     dd,
+    // Equalizer adaptation
+    lms,
+    rls,
+    // DFE config keys and presets
+    preset,
+    ff_taps,
+    fb_taps,
+    mu,
+    mu_cma,
+    leakage,
+    update_threshold,
+    cma_to_dd_threshold,
+    cma_min_symbols,
+    fractional,
+    adaptation,
+    lambda,
+    hf_skywave,
+    ground_wave,
+    fast_acquisition,
+    // RX IF filter presets
+    ssb_2k7,
+    ssb_3k,
+    // Demodulator options
+    confidence,
+    binary,
+    iq,
+    // Sample-timed demodulation: gap policies and discontinuities
+    zero_fill,
+    reacquire,
+    gap,
+    overlap,
+    // Pulse shapes and options
+    pulse,
+    rrc,
+    rc,
+    gaussian,
+    bt,
+    // Config mismatch fields
+    mismatch,
+    sample_rate,
+    symbol_rate,
+    carrier_freq,
+    rrc_alpha,
+    constellation,
+    // Probe kinds and options
+    capture,
+    fast_wale,
+    mini_probe,
+    length,
+    boundary_marker,
+    // PRBS polynomials
+    prbs9,
+    prbs15,
+    prbs23,
+    // Scope colormaps
+    gray,
+    green,
+    heat,
+    // Input level warnings
+    input_warning,
+    suspicious_input_level,
+    // End-of-transmission detector options
+    average_symbols,
+    hold_symbols,
+    power_drop_db,
+    evm_ceiling,
+    truncate,
+    // Clip handling options
+    threshold,
+    declip,
+    // Short-burst acquisition options
+    max_freq_offset_hz,
+    freq_step_hz,
+    symbols,
+    // Stage timing option
+    stage_timing,
+    // Timing tracking option
+    timing_tracking,
+    // Scatter length option
+    scatter_points,
+    // Symbol map option
+    rotation_deg,
+    // Burst ramp option
+    ramp_ms,
+    // Determinism digest options
+    modulation,
+    equalizer,
+    training,
+    rx_filter,
+    eot,
+    // Resource kinds (describe)
+    modulator,
+    demodulator,
+    unified_modulator,
+    unified_demodulator,
+    // Decode report formats
+    map,
+    cbor,
 }
 
-fn atom_to_constellation(atom: Atom) -> Result<ConstellationType, &'static str> {
+fn atom_to_constellation(atom: Atom) -> Result<ConstellationType, PhyError> {
     if atom == bpsk() {
         Ok(ConstellationType::Bpsk)
     } else if atom == qpsk() {
@@ -44,8 +203,25 @@ fn atom_to_constellation(atom: Atom) -> Result<ConstellationType, &'static str>
     } else if atom == qam64() {
         Ok(ConstellationType::Qam64)
     } else {
-        Err("unsupported modulation type")
+        Err(PhyError::UnsupportedConstellation)
+    }
+}
+
+/// Rates the modem can run at: at least 2 samples per symbol, carrier
+/// below Nyquist, and neither the rate nor sps so high the filters get huge
+fn check_rates(sample_rate: u32, symbol_rate: u32, carrier_freq: f64) -> Result<(), PhyError> {
+    check_max("sample_rate", sample_rate.into(), MAX_SAMPLE_RATE.into())?;
+    if symbol_rate == 0 {
+        return Err(PhyError::InvalidArgument("symbol_rate"));
     }
+    if sample_rate / symbol_rate < 2 {
+        return Err(PhyError::InvalidArgument("sample_rate"));
+    }
+    check_max("samples_per_symbol", (sample_rate / symbol_rate).into(), MAX_SAMPLES_PER_SYMBOL.into())?;
+    if !(carrier_freq.is_finite() && carrier_freq >= 0.0 && carrier_freq < sample_rate as f64 / 2.0) {
+        return Err(PhyError::InvalidArgument("carrier_freq"));
+    }
+    Ok(())
 }
 
 fn constellation_to_atom(ct: ConstellationType) -> Atom {
@@ -59,6 +235,56 @@ fn constellation_to_atom(ct: ConstellationType) -> Atom {
     }
 }
 
+/// Look up `key` in an options map or keyword list
+fn get_opt<'a>(opts: Term<'a>, key: Atom) -> Result<Option<Term<'a>>, PhyError> {
+    if opts.is_map() {
+        return Ok(opts.map_get(key).ok());
+    }
+    let list: Vec<(Atom, Term)> = opts.decode().map_err(|_| PhyError::InvalidArgument("opts"))?;
+    Ok(list.into_iter().find(|(k, _)| *k == key).map(|(_, v)| v))
+}
+
+/// Gaussian BT when the options give none
+const DEFAULT_GAUSSIAN_BT: f64 = 0.3;
+
+/// Decode the pulse shape from constructor options (map or keyword list)
+///
+/// `pulse:` is :rrc (default), :rc or :gaussian; `bt:` sets the Gaussian
+/// bandwidth-time product (default 0.3, within GAUSSIAN_BT_RANGE).
+fn decode_pulse(opts: Term) -> Result<Pulse, PhyError> {
+    let get = |key: Atom| get_opt(opts, key);
+
+    let kind = match get(pulse())? {
+        None => rrc(),
+        Some(term) => term.decode::<Atom>().map_err(|_| PhyError::InvalidArgument("pulse"))?,
+    };
+    if kind == rrc() {
+        Ok(Pulse::Rrc)
+    } else if kind == rc() {
+        Ok(Pulse::Rc)
+    } else if kind == gaussian() {
+        let bt = match get(bt())? {
+            None => DEFAULT_GAUSSIAN_BT,
+            Some(term) => term.decode::<f64>().map_err(|_| PhyError::InvalidArgument("bt"))?,
+        };
+        if !GAUSSIAN_BT_RANGE.contains(&bt) {
+            return Err(PhyError::InvalidArgument("bt"));
+        }
+        Ok(Pulse::Gaussian { bt })
+    } else {
+        Err(PhyError::InvalidArgument("pulse"))
+    }
+}
+
+/// :rrc, :rc or {:gaussian, bt}
+fn pulse_term(env: Env<'_>, p: Pulse) -> Term<'_> {
+    match p {
+        Pulse::Rrc => rrc().encode(env),
+        Pulse::Rc => rc().encode(env),
+        Pulse::Gaussian { bt } => (gaussian(), bt).encode(env),
+    }
+}
+
 // ============================================================================
 // Type-erased wrappers for NIF resources
 // ============================================================================
@@ -68,12 +294,55 @@ pub trait ModulatorTrait: Send + Sync {
     fn modulate(&mut self, symbols: &[u8]) -> Vec<i16>;
     fn flush(&mut self) -> Vec<i16>;
     fn reset(&mut self);
+    fn describe(&self) -> GenericDescription;
 }
 
 /// Trait object wrapper for demodulators
 pub trait DemodulatorTrait: Send + Sync {
     fn demodulate(&mut self, samples: &[i16]) -> Vec<u8>;
     fn reset(&mut self);
+    fn describe(&self) -> GenericDescription;
+}
+
+/// Configuration and usage of a generic modulator or demodulator, for
+/// describe/1
+pub struct GenericDescription {
+    pub config: ModemConfig,
+    pub sps: usize,
+    pub span: usize,
+    pub filter_len: usize,
+    pub memory_bytes: usize,
+    /// Symbols modulated or demodulated since creation
+    pub symbols: u64,
+}
+
+impl GenericDescription {
+    /// The factories only build RRC with the default roll-off, over one of
+    /// the six ConstellationType constellations
+    fn new<C, P, K, T>(constellation: &C, pulse: &P, carrier: &K, timing: &T, memory_bytes: usize, symbols: u64) -> Self
+    where
+        C: Constellation,
+        P: PulseShape,
+        K: Carrier,
+        T: SymbolTiming,
+    {
+        Self {
+            config: ModemConfig {
+                sample_rate: timing.sample_rate(),
+                symbol_rate: timing.symbol_rate(),
+                carrier_freq: carrier.frequency(),
+                rrc_alpha: DEFAULT_ALPHA,
+                pulse: Pulse::Rrc,
+                constellation: ConstellationType::from_order(constellation.order())
+                    .expect("generic modems use a ConstellationType constellation"),
+            },
+            sps: timing.samples_per_symbol(),
+            span: pulse.span_symbols(),
+            filter_len: pulse.filter_len(),
+            memory_bytes,
+            symbols,
+        }
+    }
 }
 
 // Implement trait for concrete modulator types
@@ -95,6 +364,17 @@ where
     fn reset(&mut self) {
         Modulator::reset(self)
     }
+
+    fn describe(&self) -> GenericDescription {
+        GenericDescription::new(
+            self.constellation(),
+            self.pulse(),
+            self.carrier(),
+            self.timing(),
+            self.memory_bytes(),
+            self.symbols_modulated(),
+        )
+    }
 }
 
 // Implement trait for concrete demodulator types
@@ -112,29 +392,122 @@ where
     fn reset(&mut self) {
         Demodulator::reset(self)
     }
+
+    fn describe(&self) -> GenericDescription {
+        GenericDescription::new(
+            self.constellation(),
+            self.pulse(),
+            self.carrier(),
+            self.timing(),
+            self.memory_bytes(),
+            self.symbols_demodulated(),
+        )
+    }
 }
 
 /// NIF resource wrapper for modulator
 pub struct ModulatorResource {
     pub inner: Mutex<Box<dyn ModulatorTrait>>,
+    pub tally: Tally,
 }
 
 /// NIF resource wrapper for demodulator
 pub struct DemodulatorResource {
     pub inner: Mutex<Box<dyn DemodulatorTrait>>,
+    /// Send input level warnings (see input_level)
+    pub input_warnings: AtomicBool,
+    pub tally: Tally,
+}
+
+impl ModulatorResource {
+    pub fn new(modulator: Box<dyn ModulatorTrait>) -> Self {
+        let tally = Tally::new(&kinds::MODULATORS, modulator.describe().memory_bytes);
+        Self { inner: Mutex::new(modulator), tally }
+    }
+
+    /// Lock the modulator (see counted::Counted)
+    pub fn state(&self) -> Counted<'_, Box<dyn ModulatorTrait>> {
+        Counted::lock(&self.inner, &self.tally)
+    }
+}
+
+impl DemodulatorResource {
+    pub fn new(demodulator: Box<dyn DemodulatorTrait>) -> Self {
+        let tally = Tally::new(&kinds::DEMODULATORS, demodulator.describe().memory_bytes);
+        Self { inner: Mutex::new(demodulator), input_warnings: AtomicBool::new(true), tally }
+    }
+
+    /// Lock the demodulator (see counted::Counted)
+    pub fn state(&self) -> Counted<'_, Box<dyn DemodulatorTrait>> {
+        Counted::lock(&self.inner, &self.tally)
+    }
+}
+
+impl Recover for Box<dyn ModulatorTrait> {
+    fn recover(&mut self) {
+        self.reset();
+    }
+}
+
+impl Recover for Box<dyn DemodulatorTrait> {
+    fn recover(&mut self) {
+        self.reset();
+    }
 }
 
 // ============================================================================
 // Factory functions - match once, construct specialized type
 // ============================================================================
 
+/// As check_rates, and FixedTiming also needs a whole number of samples per
+/// symbol
+fn check_fixed_rates(sample_rate: u32, symbol_rate: u32, carrier_freq: f64) -> Result<(), PhyError> {
+    check_rates(sample_rate, symbol_rate, carrier_freq)?;
+    if !sample_rate.is_multiple_of(symbol_rate) {
+        return Err(PhyError::InvalidArgument("sample_rate"));
+    }
+    Ok(())
+}
+
+/// Equalizer tap counts: at least one feedforward, at most MAX_EQ_TAPS each
+fn check_eq_taps(ff_taps: usize, fb_taps: usize) -> Result<(), PhyError> {
+    if ff_taps == 0 {
+        return Err(PhyError::InvalidArgument("ff_taps"));
+    }
+    check_max("ff_taps", ff_taps as u64, MAX_EQ_TAPS as u64)?;
+    check_max("fb_taps", fb_taps as u64, MAX_EQ_TAPS as u64)?;
+    Ok(())
+}
+
+/// Equalizer config for the positional (ff_taps, fb_taps, mu) NIFs
+fn eq_config(ff_taps: usize, fb_taps: usize, mu: f64) -> Result<DFEConfig, PhyError> {
+    check_eq_taps(ff_taps, fb_taps)?;
+    if !(mu.is_finite() && mu > 0.0) {
+        return Err(PhyError::InvalidArgument("mu"));
+    }
+    Ok(DFEConfig {
+        ff_taps,
+        fb_taps,
+        mu,
+        mu_cma: mu / 6.0,  // CMA uses smaller step size
+        leakage: 0.9999,
+        update_threshold: 0.1,
+        cma_to_dd_threshold: 0.3,
+        cma_min_symbols: 50,
+        fractional: false,
+        adaptation: Adaptation::Lms,
+        lambda: 0.99,
+    })
+}
+
 /// Build a modulator for the given modulation type
 fn build_modulator(
     modulation: Atom,
     sample_rate: u32,
     symbol_rate: u32,
     carrier_freq: f64,
-) -> Result<Box<dyn ModulatorTrait>, &'static str> {
+) -> Result<Box<dyn ModulatorTrait>, PhyError> {
+    check_fixed_rates(sample_rate, symbol_rate, carrier_freq)?;
     let timing = FixedTiming::new(sample_rate, symbol_rate);
     let sps = timing.samples_per_symbol();
     let pulse = RootRaisedCosine::default_for_sps(sps);
@@ -153,7 +526,7 @@ fn build_modulator(
     } else if modulation == qam64() {
         Ok(Box::new(Modulator::new(Qam64, pulse, carrier, timing)))
     } else {
-        Err("unsupported modulation type")
+        Err(PhyError::UnsupportedConstellation)
     }
 }
 
@@ -163,7 +536,8 @@ fn build_demodulator(
     sample_rate: u32,
     symbol_rate: u32,
     carrier_freq: f64,
-) -> Result<Box<dyn DemodulatorTrait>, &'static str> {
+) -> Result<Box<dyn DemodulatorTrait>, PhyError> {
+    check_fixed_rates(sample_rate, symbol_rate, carrier_freq)?;
     let timing = FixedTiming::new(sample_rate, symbol_rate);
     let sps = timing.samples_per_symbol();
     let pulse = RootRaisedCosine::default_for_sps(sps);
@@ -182,7 +556,7 @@ fn build_demodulator(
     } else if modulation == qam64() {
         Ok(Box::new(Demodulator::new(Qam64, pulse, carrier, timing)))
     } else {
-        Err("unsupported modulation type")
+        Err(PhyError::UnsupportedConstellation)
     }
 }
 
@@ -207,12 +581,9 @@ pub fn mod_new(
     let symbol_rate = symbol_rate.unwrap_or(2400);
     let carrier_freq = carrier_freq.unwrap_or(1800.0);
 
-    let modulator = build_modulator(modulation, sample_rate, symbol_rate, carrier_freq)
-        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    let modulator = build_modulator(modulation, sample_rate, symbol_rate, carrier_freq)?;
 
-    Ok(ResourceArc::new(ModulatorResource {
-        inner: Mutex::new(modulator),
-    }))
+    Ok(ResourceArc::new(ModulatorResource::new(modulator)))
 }
 
 /// Modulate symbols to audio samples
@@ -221,32 +592,31 @@ pub fn mod_modulate(
     modulator: ResourceArc<ModulatorResource>,
     symbols: Vec<u8>,
 ) -> NifResult<Vec<i16>> {
-    let mut state = modulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
+    guarded(|| {
+        let mut state = modulator.state();
 
-    Ok(state.modulate(&symbols))
+        Ok(state.modulate(&symbols))
+    })
 }
 
 /// Flush modulator filter tail
 #[rustler::nif]
 pub fn mod_flush(modulator: ResourceArc<ModulatorResource>) -> NifResult<Vec<i16>> {
-    let mut state = modulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
+    guarded(|| {
+        let mut state = modulator.state();
 
-    Ok(state.flush())
+        Ok(state.flush())
+    })
 }
 
 /// Reset modulator state
 #[rustler::nif]
-pub fn mod_reset(modulator: ResourceArc<ModulatorResource>) -> Atom {
-    if let Ok(mut state) = modulator.inner.lock() {
+pub fn mod_reset(modulator: ResourceArc<ModulatorResource>) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = modulator.state();
         state.reset();
-    }
-    ok()
+        Ok(ok())
+    })
 }
 
 // ============================================================================
@@ -264,35 +634,41 @@ pub fn demod_new(
     let symbol_rate = symbol_rate.unwrap_or(2400);
     let carrier_freq = carrier_freq.unwrap_or(1800.0);
 
-    let demodulator = build_demodulator(modulation, sample_rate, symbol_rate, carrier_freq)
-        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    let demodulator = build_demodulator(modulation, sample_rate, symbol_rate, carrier_freq)?;
 
-    Ok(ResourceArc::new(DemodulatorResource {
-        inner: Mutex::new(demodulator),
-    }))
+    Ok(ResourceArc::new(DemodulatorResource::new(demodulator)))
 }
 
 /// Demodulate audio samples to symbols
 #[rustler::nif]
 pub fn demod_demodulate(
+    env: Env,
     demodulator: ResourceArc<DemodulatorResource>,
     samples: Vec<i16>,
 ) -> NifResult<Vec<u8>> {
-    let mut state = demodulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
+    guarded(|| {
+        input_level::check_i16(env, &demodulator.input_warnings, &demodulator, &samples);
+        let mut state = demodulator.state();
+
+        Ok(state.demodulate(&samples))
+    })
+}
 
-    Ok(state.demodulate(&samples))
+/// Turn this demodulator's input level warnings on or off
+#[rustler::nif]
+pub fn demod_set_input_warnings(demodulator: ResourceArc<DemodulatorResource>, enabled: bool) -> NifResult<Atom> {
+    demodulator.input_warnings.store(enabled, Ordering::Relaxed);
+    Ok(ok())
 }
 
 /// Reset demodulator state
 #[rustler::nif]
-pub fn demod_reset(demodulator: ResourceArc<DemodulatorResource>) -> Atom {
-    if let Ok(mut state) = demodulator.inner.lock() {
+pub fn demod_reset(demodulator: ResourceArc<DemodulatorResource>) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
         state.reset();
-    }
-    ok()
+        Ok(ok())
+    })
 }
 
 // ============================================================================
@@ -302,12 +678,9 @@ pub fn demod_reset(demodulator: ResourceArc<DemodulatorResource>) -> Atom {
 /// Legacy: Create 8-PSK modulator (for backwards compatibility)
 #[rustler::nif]
 pub fn new(sample_rate: u32) -> NifResult<ResourceArc<ModulatorResource>> {
-    let modulator = build_modulator(psk8(), sample_rate, 2400, 1800.0)
-        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    let modulator = build_modulator(psk8(), sample_rate, 2400, 1800.0)?;
 
-    Ok(ResourceArc::new(ModulatorResource {
-        inner: Mutex::new(modulator),
-    }))
+    Ok(ResourceArc::new(ModulatorResource::new(modulator)))
 }
 
 /// Legacy: Modulate (for backwards compatibility)
@@ -316,32 +689,31 @@ pub fn modulate(
     modulator: ResourceArc<ModulatorResource>,
     symbols: Vec<u8>,
 ) -> NifResult<Vec<i16>> {
-    let mut state = modulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
+    guarded(|| {
+        let mut state = modulator.state();
 
-    Ok(state.modulate(&symbols))
+        Ok(state.modulate(&symbols))
+    })
 }
 
 /// Legacy: Flush (for backwards compatibility)
 #[rustler::nif]
 pub fn flush(modulator: ResourceArc<ModulatorResource>) -> NifResult<Vec<i16>> {
-    let mut state = modulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
+    guarded(|| {
+        let mut state = modulator.state();
 
-    Ok(state.flush())
+        Ok(state.flush())
+    })
 }
 
 /// Legacy: Reset (for backwards compatibility)
 #[rustler::nif]
-pub fn reset(modulator: ResourceArc<ModulatorResource>) -> Atom {
-    if let Ok(mut state) = modulator.inner.lock() {
+pub fn reset(modulator: ResourceArc<ModulatorResource>) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = modulator.state();
         state.reset();
-    }
-    ok()
+        Ok(ok())
+    })
 }
 
 // ============================================================================
@@ -351,11 +723,51 @@ pub fn reset(modulator: ResourceArc<ModulatorResource>) -> Atom {
 /// Resource wrapper for unified modulator
 pub struct UnifiedModulatorResource {
     pub inner: Mutex<UnifiedModulator>,
+    pub tally: Tally,
+}
+
+impl UnifiedModulatorResource {
+    pub fn new(modulator: UnifiedModulator) -> Self {
+        let tally = Tally::new(&kinds::UNIFIED_MODULATORS, modulator.memory_bytes());
+        Self { inner: Mutex::new(modulator), tally }
+    }
+
+    /// Lock the modulator (see counted::Counted)
+    pub fn state(&self) -> Counted<'_, UnifiedModulator> {
+        Counted::lock(&self.inner, &self.tally)
+    }
+}
+
+impl Recover for UnifiedModulator {
+    fn recover(&mut self) {
+        self.reset();
+    }
 }
 
 /// Resource wrapper for unified demodulator  
 pub struct UnifiedDemodulatorResource {
     pub inner: Mutex<UnifiedDemodulator>,
+    /// Send input level warnings (see input_level)
+    pub input_warnings: AtomicBool,
+    pub tally: Tally,
+}
+
+impl UnifiedDemodulatorResource {
+    pub fn new(demodulator: UnifiedDemodulator) -> Self {
+        let tally = Tally::new(&kinds::UNIFIED_DEMODULATORS, demodulator.memory_bytes());
+        Self { inner: Mutex::new(demodulator), input_warnings: AtomicBool::new(true), tally }
+    }
+
+    /// Lock the demodulator (see counted::Counted)
+    pub fn state(&self) -> Counted<'_, UnifiedDemodulator> {
+        Counted::lock(&self.inner, &self.tally)
+    }
+}
+
+impl Recover for UnifiedDemodulator {
+    fn recover(&mut self) {
+        self.reset();
+    }
 }
 
 /// Create a unified modulator with runtime constellation switching
@@ -367,14 +779,48 @@ pub fn unified_mod_new(
     let symbol_rate = 2400;
     let carrier_freq = 1800.0;
     
-    let constellation = atom_to_constellation(modulation)
-        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    let constellation = atom_to_constellation(modulation)?;
+    check_rates(sample_rate, symbol_rate, carrier_freq)?;
     
     let modulator = UnifiedModulator::new(constellation, sample_rate, symbol_rate, carrier_freq);
     
-    Ok(ResourceArc::new(UnifiedModulatorResource {
-        inner: Mutex::new(modulator),
-    }))
+    Ok(ResourceArc::new(UnifiedModulatorResource::new(modulator)))
+}
+
+/// Create a unified modulator, with options
+///
+/// Options (map or keyword list):
+/// * `pulse:` - :rrc (default), :rc or :gaussian (see decode_pulse)
+/// * `bt:` - Gaussian bandwidth-time product, default 0.3
+/// * `ramp_ms:` - raised-cosine burst edge ramps, default 0 (hard edges),
+///   within RAMP_MS_RANGE (see UnifiedModulator::set_ramp_ms)
+#[rustler::nif(name = "unified_mod_new")]
+pub fn unified_mod_new_opts(
+    modulation: Atom,
+    sample_rate: u32,
+    opts: Term,
+) -> NifResult<ResourceArc<UnifiedModulatorResource>> {
+    let symbol_rate = 2400;
+    let carrier_freq = 1800.0;
+    
+    let constellation = atom_to_constellation(modulation)?;
+    check_rates(sample_rate, symbol_rate, carrier_freq)?;
+    let pulse = decode_pulse(opts)?;
+    let ramp_ms = match get_opt(opts, ramp_ms())? {
+        None => 0.0,
+        Some(term) => term
+            .decode::<f64>()
+            .or_else(|_| term.decode::<i64>().map(|ms| ms as f64))
+            .map_err(|_| PhyError::InvalidArgument("ramp_ms"))?,
+    };
+    if !RAMP_MS_RANGE.contains(&ramp_ms) {
+        return Err(PhyError::InvalidArgument("ramp_ms").into());
+    }
+    
+    let mut modulator = UnifiedModulator::with_pulse(constellation, sample_rate, symbol_rate, carrier_freq, pulse);
+    modulator.set_ramp_ms(ramp_ms);
+    
+    Ok(ResourceArc::new(UnifiedModulatorResource::new(modulator)))
 }
 
 /// Modulate symbols using current constellation
@@ -383,12 +829,11 @@ pub fn unified_mod_modulate(
     modulator: ResourceArc<UnifiedModulatorResource>,
     symbols: Vec<u8>,
 ) -> NifResult<Vec<i16>> {
-    let mut state = modulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
+    guarded(|| {
+        let mut state = modulator.state();
     
-    Ok(state.modulate(&symbols))
+        Ok(state.modulate(&symbols))
+    })
 }
 
 /// Modulate with per-symbol constellation
@@ -398,22 +843,35 @@ pub fn unified_mod_modulate_mixed(
     modulator: ResourceArc<UnifiedModulatorResource>,
     symbols: Vec<(u8, Atom)>,
 ) -> NifResult<Vec<i16>> {
-    let mut state = modulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
-    
-    // Convert atoms to ConstellationType
-    let mixed: Result<Vec<_>, _> = symbols
-        .into_iter()
-        .map(|(sym, atom)| {
-            atom_to_constellation(atom).map(|ct| (sym, ct))
-        })
-        .collect();
+    guarded(|| {
+        let mut state = modulator.state();
+    
+        // Convert atoms to ConstellationType
+        let mixed: Result<Vec<_>, _> = symbols
+            .into_iter()
+            .map(|(sym, atom)| {
+                atom_to_constellation(atom).map(|ct| (sym, ct))
+            })
+            .collect();
+    
+        let mixed = mixed?;
     
-    let mixed = mixed.map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        Ok(state.modulate_mixed(&mixed))
+    })
+}
+
+/// Modulate 75 bps dibits, each spread to its 32-chip Walsh sequence on
+/// 8-PSK (see UnifiedModulator::modulate_walsh)
+#[rustler::nif]
+pub fn unified_mod_walsh(
+    modulator: ResourceArc<UnifiedModulatorResource>,
+    dibits: Vec<u8>,
+) -> NifResult<Vec<i16>> {
+    guarded(|| {
+        let mut state = modulator.state();
     
-    Ok(state.modulate_mixed(&mixed))
+        Ok(state.modulate_walsh(&dibits))
+    })
 }
 
 /// Switch constellation without resetting filter state
@@ -422,16 +880,14 @@ pub fn unified_mod_set_constellation(
     modulator: ResourceArc<UnifiedModulatorResource>,
     modulation: Atom,
 ) -> NifResult<Atom> {
-    let constellation = atom_to_constellation(modulation)
-        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    guarded(|| {
+        let constellation = atom_to_constellation(modulation)?;
     
-    let mut state = modulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
+        let mut state = modulator.state();
     
-    state.set_constellation(constellation);
-    Ok(ok())
+        state.set_constellation(constellation);
+        Ok(ok())
+    })
 }
 
 /// Get current constellation
@@ -439,12 +895,79 @@ pub fn unified_mod_set_constellation(
 pub fn unified_mod_get_constellation(
     modulator: ResourceArc<UnifiedModulatorResource>,
 ) -> NifResult<Atom> {
-    let state = modulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
+    guarded(|| {
+        let state = modulator.state();
     
-    Ok(constellation_to_atom(state.constellation()))
+        Ok(constellation_to_atom(state.constellation()))
+    })
+}
+
+/// A symbol map for `constellation` from a permutation (nil for none)
+/// and the `rotation_deg:` option, rotation first; None if neither
+fn decode_symbol_map(
+    constellation: ConstellationType,
+    map: Option<Vec<u8>>,
+    opts: Vec<(Atom, Term)>,
+) -> Result<Option<SymbolMap>, PhyError> {
+    let mut rotation = None;
+    for (key, value) in opts {
+        if key == rotation_deg() {
+            let degrees = value
+                .decode::<f64>()
+                .or_else(|_| value.decode::<i64>().map(|d| d as f64))
+                .map_err(|_| PhyError::InvalidArgument("rotation_deg"))?;
+            rotation = Some(SymbolMap::rotation(constellation, degrees).ok_or(PhyError::InvalidArgument("rotation_deg"))?);
+        } else {
+            return Err(PhyError::InvalidArgument("opts"));
+        }
+    }
+    let map = map
+        .map(|map| SymbolMap::new(constellation, map).ok_or(PhyError::InvalidArgument("symbol_map")))
+        .transpose()?;
+    Ok(match (rotation, map) {
+        (Some(rotation), Some(map)) => rotation.then(&map),
+        (rotation, map) => rotation.or(map),
+    })
+}
+
+/// Take input symbols in another vendor's numbering
+///
+/// `map` lists the other numbering's symbol for each native symbol of
+/// the current constellation (a permutation), or nil. Options:
+/// * `rotation_deg:` - PSK only: the other numbering's symbol s sits this
+///   many degrees counterclockwise of ours, a whole number of symbol
+///   steps; applied before `map`
+///
+/// nil and no rotation go back to the native numbering.
+#[rustler::nif]
+pub fn unified_mod_set_symbol_map(
+    modulator: ResourceArc<UnifiedModulatorResource>,
+    map: Option<Vec<u8>>,
+    opts: Vec<(Atom, Term)>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = modulator.state();
+        let map = decode_symbol_map(state.constellation(), map, opts)?;
+        state.set_symbol_map(map);
+        Ok(ok())
+    })
+}
+
+/// Hop the carrier on a schedule of {sample_offset, carrier_hz}, offsets
+/// strictly increasing and counted from the next output sample, carriers
+/// below Nyquist; [] keeps the carrier where it is (see
+/// UnifiedModulator::set_hop_schedule)
+#[rustler::nif]
+pub fn unified_mod_set_hop_schedule(
+    modulator: ResourceArc<UnifiedModulatorResource>,
+    hops: Vec<(u64, f64)>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = modulator.state();
+        let schedule = HopSchedule::new(hops, state.config().sample_rate).ok_or(PhyError::InvalidArgument("hops"))?;
+        state.set_hop_schedule(schedule);
+        Ok(ok())
+    })
 }
 
 /// Flush modulator filter tail
@@ -452,256 +975,3055 @@ pub fn unified_mod_get_constellation(
 pub fn unified_mod_flush(
     modulator: ResourceArc<UnifiedModulatorResource>,
 ) -> NifResult<Vec<i16>> {
-    let mut state = modulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
+    guarded(|| {
+        let mut state = modulator.state();
     
-    Ok(state.flush())
+        Ok(state.flush())
+    })
 }
 
-/// Reset modulator state
+/// Emit queued symbols and the pulse tail, ramped down if ramps are on,
+/// ending the burst
 #[rustler::nif]
-pub fn unified_mod_reset(modulator: ResourceArc<UnifiedModulatorResource>) -> Atom {
-    if let Ok(mut state) = modulator.inner.lock() {
-        state.reset();
-    }
-    ok()
+pub fn unified_mod_end_burst(
+    modulator: ResourceArc<UnifiedModulatorResource>,
+) -> NifResult<Vec<i16>> {
+    guarded(|| {
+        let mut state = modulator.state();
+    
+        Ok(state.end_burst())
+    })
 }
 
-/// Create a unified demodulator
+/// Samples each burst edge ramp covers (0 = hard edges)
 #[rustler::nif]
-pub fn unified_demod_new(
-    modulation: Atom,
-    sample_rate: u32,
-) -> NifResult<ResourceArc<UnifiedDemodulatorResource>> {
-    let symbol_rate = 2400;
-    let carrier_freq = 1800.0;
-    
-    let constellation = atom_to_constellation(modulation)
-        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
-    
-    let demodulator = UnifiedDemodulator::new(constellation, sample_rate, symbol_rate, carrier_freq);
+pub fn unified_mod_ramp_samples(
+    modulator: ResourceArc<UnifiedModulatorResource>,
+) -> NifResult<usize> {
+    guarded(|| {
+        let state = modulator.state();
     
-    Ok(ResourceArc::new(UnifiedDemodulatorResource {
-        inner: Mutex::new(demodulator),
-    }))
+        Ok(state.ramp_samples())
+    })
 }
 
-/// Demodulate to I/Q pairs
+/// Queue symbols for unified_mod_pull_samples, using the current constellation
 #[rustler::nif]
-pub fn unified_demod_iq(
-    demodulator: ResourceArc<UnifiedDemodulatorResource>,
-    samples: Vec<i16>,
-) -> NifResult<Vec<(f64, f64)>> {
-    let mut state = demodulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
+pub fn unified_mod_push_symbols(
+    modulator: ResourceArc<UnifiedModulatorResource>,
+    symbols: Vec<u8>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = modulator.state();
     
-    Ok(state.demodulate_iq(&samples))
+        state.push_symbols(&symbols);
+        Ok(ok())
+    })
 }
 
-/// Demodulate to symbols
+/// Pull n samples, n bounded by MAX_PULL_SAMPLES
+fn pull_samples(modulator: &mut UnifiedModulator, n: usize) -> Result<(Vec<i16>, usize), PhyError> {
+    check_max("n", n as u64, MAX_PULL_SAMPLES as u64)?;
+    Ok(modulator.pull_samples(n))
+}
+
+/// Pull exactly n samples from the symbol queue
+///
+/// Returns {samples, underrun}: underrun counts the samples generated after
+/// the queue ran dry (0 if it didn't). Those carry the ringing-out filter
+/// tail, then silence.
 #[rustler::nif]
-pub fn unified_demod_symbols(
-    demodulator: ResourceArc<UnifiedDemodulatorResource>,
-    samples: Vec<i16>,
-) -> NifResult<Vec<u8>> {
-    let mut state = demodulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
+pub fn unified_mod_pull_samples(
+    modulator: ResourceArc<UnifiedModulatorResource>,
+    n: usize,
+) -> NifResult<(Vec<i16>, usize)> {
+    guarded(|| {
+        let mut state = modulator.state();
     
-    Ok(state.demodulate(&samples))
+        Ok(pull_samples(&mut state, n)?)
+    })
 }
 
-/// Switch demodulator constellation
+/// Symbols queued and not yet fully pulled
 #[rustler::nif]
-pub fn unified_demod_set_constellation(
-    demodulator: ResourceArc<UnifiedDemodulatorResource>,
-    modulation: Atom,
-) -> NifResult<Atom> {
-    let constellation = atom_to_constellation(modulation)
-        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
-    
-    let mut state = demodulator
-        .inner
-        .lock()
-        .map_err(|_| rustler::Error::Term(Box::new("lock poisoned")))?;
+pub fn unified_mod_queue_depth(
+    modulator: ResourceArc<UnifiedModulatorResource>,
+) -> NifResult<usize> {
+    guarded(|| {
+        let state = modulator.state();
     
-    state.set_constellation(constellation);
-    Ok(ok())
+        Ok(state.queued_symbols())
+    })
 }
 
-/// Reset demodulator state
+/// Return modulator to idle (see UnifiedModulator::reset_to_idle)
 #[rustler::nif]
-pub fn unified_demod_reset(demodulator: ResourceArc<UnifiedDemodulatorResource>) -> Atom {
-    if let Ok(mut state) = demodulator.inner.lock() {
+pub fn unified_mod_reset(modulator: ResourceArc<UnifiedModulatorResource>) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = modulator.state();
         state.reset();
-    }
-    ok()
+        Ok(ok())
+    })
 }
 
-// ============================================================================
-// Equalizer NIFs
-// ============================================================================
-
-/// Create a unified demodulator with DFE equalizer enabled
+/// Create a unified demodulator
 #[rustler::nif]
-pub fn unified_demod_new_with_eq(
+pub fn unified_demod_new(
     modulation: Atom,
     sample_rate: u32,
-    ff_taps: usize,
-    fb_taps: usize,
-    mu: f64,
 ) -> NifResult<ResourceArc<UnifiedDemodulatorResource>> {
     let symbol_rate = 2400;
     let carrier_freq = 1800.0;
     
-    let constellation = atom_to_constellation(modulation)
-        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
-    
-    let config = DFEConfig {
-        ff_taps,
-        fb_taps,
-        mu,
-        mu_cma: mu / 6.0,
-        leakage: 0.9999,
-        update_threshold: 0.1,
-        cma_to_dd_threshold: 0.3,
-        cma_min_symbols: 50,
-    };
+    let constellation = atom_to_constellation(modulation)?;
+    check_rates(sample_rate, symbol_rate, carrier_freq)?;
     
-    let demodulator = UnifiedDemodulator::with_equalizer(
-        constellation, sample_rate, symbol_rate, carrier_freq, config
-    );
+    let demodulator = UnifiedDemodulator::new(constellation, sample_rate, symbol_rate, carrier_freq);
     
-    Ok(ResourceArc::new(UnifiedDemodulatorResource {
-        inner: Mutex::new(demodulator),
-    }))
+    Ok(ResourceArc::new(UnifiedDemodulatorResource::new(demodulator)))
 }
 
-/// Create demodulator with default HF skywave equalizer settings
-#[rustler::nif]
-pub fn unified_demod_new_hf(
+/// Create a unified demodulator, with options
+///
+/// Takes the same options as unified_mod_new/3; the pulse shape must
+/// match the transmitter's. `stage_timing: true` also turns on stage
+/// timing (see unified_demod_stage_timings), `timing_tracking: true`
+/// the Gardner timing loop (see unified_demod_enable_timing_tracking), and
+/// `scatter_points: n` keeps n sliced points (see unified_demod_scatter).
+#[rustler::nif(name = "unified_demod_new")]
+pub fn unified_demod_new_opts(
     modulation: Atom,
     sample_rate: u32,
+    opts: Term,
 ) -> NifResult<ResourceArc<UnifiedDemodulatorResource>> {
     let symbol_rate = 2400;
     let carrier_freq = 1800.0;
     
-    let constellation = atom_to_constellation(modulation)
-        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    let constellation = atom_to_constellation(modulation)?;
+    check_rates(sample_rate, symbol_rate, carrier_freq)?;
+    let pulse = decode_pulse(opts)?;
+    let timed = match get_opt(opts, stage_timing())? {
+        None => false,
+        Some(term) => term.decode::<bool>().map_err(|_| PhyError::InvalidArgument("stage_timing"))?,
+    };
+    let tracked = match get_opt(opts, timing_tracking())? {
+        None => false,
+        Some(term) => term.decode::<bool>().map_err(|_| PhyError::InvalidArgument("timing_tracking"))?,
+    };
+    let scatter = match get_opt(opts, scatter_points())? {
+        None => None,
+        Some(term) => Some(term.decode::<usize>().map_err(|_| PhyError::InvalidArgument("scatter_points"))?),
+    };
     
-    let demodulator = UnifiedDemodulator::with_hf_equalizer(
-        constellation, sample_rate, symbol_rate, carrier_freq
-    );
+    let mut demodulator = UnifiedDemodulator::with_pulse(constellation, sample_rate, symbol_rate, carrier_freq, pulse);
+    if timed {
+        demodulator.enable_stage_timing();
+    }
+    if tracked {
+        demodulator.enable_timing_tracking();
+    }
+    if let Some(points) = scatter {
+        set_scatter_points(&mut demodulator, points)?;
+    }
     
-    Ok(ResourceArc::new(UnifiedDemodulatorResource {
-        inner: Mutex::new(demodulator),
-    }))
+    Ok(ResourceArc::new(UnifiedDemodulatorResource::new(demodulator)))
 }
 
-/// Set training symbols for equalizer acquisition
+/// Demodulate to I/Q pairs
 #[rustler::nif]
-pub fn unified_demod_set_training(
+pub fn unified_demod_iq(
+    env: Env,
     demodulator: ResourceArc<UnifiedDemodulatorResource>,
-    symbols: Vec<u8>,
-) -> Atom {
-    if let Ok(mut state) = demodulator.inner.lock() {
-        state.set_training_symbols(symbols);
-    }
-    ok()
+    samples: Vec<i16>,
+) -> NifResult<Vec<(f64, f64)>> {
+    guarded(|| {
+        input_level::check_i16(env, &demodulator.input_warnings, &demodulator, &samples);
+        let mut state = demodulator.state();
+    
+        Ok(state.demodulate_iq(&samples))
+    })
 }
 
-/// Reset equalizer state
+/// Demodulate a 75 bps Walsh transmission; returns a Walsh75SymbolMap per
+/// completed symbol (see UnifiedDemodulator::demodulate_walsh)
 #[rustler::nif]
-pub fn unified_demod_reset_eq(
+pub fn unified_demod_walsh(
+    env: Env,
     demodulator: ResourceArc<UnifiedDemodulatorResource>,
-) -> Atom {
-    if let Ok(mut state) = demodulator.inner.lock() {
-        state.reset_equalizer();
-    }
-    ok()
+    samples: Vec<i16>,
+) -> NifResult<Vec<Walsh75SymbolMap>> {
+    guarded(|| {
+        input_level::check_i16(env, &demodulator.input_warnings, &demodulator, &samples);
+        let mut state = demodulator.state();
+    
+        Ok(state.demodulate_walsh(&samples).into_iter().map(Walsh75SymbolMap::from).collect())
+    })
 }
 
-/// Get current mean squared error
+/// Start the next 75 bps symbol `chips` chips on, dropping any unfinished
+/// one (see UnifiedDemodulator::align_walsh)
 #[rustler::nif]
-pub fn unified_demod_mse(
+pub fn unified_demod_align_walsh(
     demodulator: ResourceArc<UnifiedDemodulatorResource>,
-) -> f64 {
-    demodulator
-        .inner
-        .lock()
-        .map(|state| state.equalizer_mse().unwrap_or(0.0))
-        .unwrap_or(0.0)
+    chips: usize,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+    
+        state.align_walsh(chips);
+        Ok(ok())
+    })
 }
 
-/// Check if equalizer is enabled
-#[rustler::nif]
-pub fn unified_demod_has_eq(
-    demodulator: ResourceArc<UnifiedDemodulatorResource>,
-) -> bool {
-    demodulator
-        .inner
-        .lock()
-        .map(|state| state.has_equalizer())
-        .unwrap_or(false)
-}
+/// Oversampling factors unified_demod_mf_output accepts
+const MF_OVERSAMPLE: [usize; 3] = [1, 2, 4];
 
-/// Enable equalizer on existing demodulator
-#[rustler::nif]
-pub fn unified_demod_enable_eq(
-    demodulator: ResourceArc<UnifiedDemodulatorResource>,
-    ff_taps: usize,
-    fb_taps: usize,
-    mu: f64,
-) -> Atom {
-    if let Ok(mut state) = demodulator.inner.lock() {
-        let config = DFEConfig {
-            ff_taps,
-            fb_taps,
-            mu,
-            mu_cma: mu / 6.0,  // CMA uses smaller step size
-            leakage: 0.9999,
-            update_threshold: 0.1,
-            cma_to_dd_threshold: 0.3,
-            cma_min_symbols: 50,
-        };
-        state.enable_equalizer(config);
+/// Check an MF oversampling factor against the demodulator's sps
+fn check_oversample(oversample: usize, sps: usize) -> Result<(), PhyError> {
+    if !MF_OVERSAMPLE.contains(&oversample) || !sps.is_multiple_of(oversample) {
+        return Err(PhyError::InvalidArgument("oversample"));
     }
-    ok()
+    Ok(())
 }
 
-/// Disable equalizer
+/// Decode an I/Q binary (see wire::read_iq); `arg` names the argument
+/// in the error
+fn iq_from_f32_bytes(bytes: &[u8], arg: &'static str) -> Result<Vec<(f64, f64)>, PhyError> {
+    wire::read_iq(bytes).map_err(|_| PhyError::InvalidArgument(arg))
+}
+
+/// I/Q pairs as an IQ_F32_INTERLEAVED_V1 binary
+fn iq_binary<'a>(env: Env<'a>, iq: &[(f64, f64)]) -> NifResult<Binary<'a>> {
+    let mut owned = OwnedBinary::new(wire::Format::IqF32.frame_len(iq.len()))
+        .ok_or(PhyError::AllocFailed)?;
+    wire::write_iq_f32(iq, owned.as_mut_slice());
+    Ok(owned.release(env))
+}
+
+/// Matched-filter output for external timing recovery
+///
+/// # Arguments
+/// * `oversample` - Samples per symbol out: 1, 2 or 4 (must divide sps)
+/// * `apply_pll` - Mix down with the PLL's current phase and frequency
+///   correction instead of the nominal carrier
+///
+/// Returns IQ_F32_INTERLEAVED_V1 (see wire) with no timing selection
+/// (see UnifiedDemodulator::matched_filter_output). The demodulator's
+/// state is left untouched.
 #[rustler::nif]
-pub fn unified_demod_disable_eq(
+pub fn unified_demod_mf_output<'a>(
+    env: Env<'a>,
     demodulator: ResourceArc<UnifiedDemodulatorResource>,
-) -> Atom {
-    if let Ok(mut state) = demodulator.inner.lock() {
-        state.disable_equalizer();
-    }
-    ok()
+    samples: Vec<i16>,
+    oversample: usize,
+    apply_pll: bool,
+) -> NifResult<Binary<'a>> {
+    guarded(|| {
+        input_level::check_i16(env, &demodulator.input_warnings, &demodulator, &samples);
+        let state = demodulator.state();
+        check_oversample(oversample, state.sps())?;
+    
+        let iq = state.matched_filter_output(&samples, oversample, apply_pll);
+        drop(state);
+    
+        iq_binary(env, &iq)
+    })
 }
 
-/// Get equalizer mode (:cma or :dd)
+/// Demodulate to symbols
 #[rustler::nif]
-pub fn unified_demod_eq_mode(
+pub fn unified_demod_symbols(
+    env: Env,
     demodulator: ResourceArc<UnifiedDemodulatorResource>,
-) -> Atom {
-    demodulator
-        .inner
-        .lock()
-        .map(|state| {
-            if let Some(mode) = state.equalizer_mode() {
-                match mode {
-                    crate::modem::EqMode::CMA => cma(),
-                    crate::modem::EqMode::DD => dd(),
-                }
+    samples: Vec<i16>,
+) -> NifResult<Vec<u8>> {
+    guarded(|| {
+        input_level::check_i16(env, &demodulator.input_warnings, &demodulator, &samples);
+        let mut state = demodulator.state();
+    
+        Ok(state.demodulate(&samples))
+    })
+}
+
+/// Soft decisions as binaries, all sub-binaries of one arena (see arena)
+#[derive(NifMap)]
+pub struct SoftDecisionMap<'a> {
+    /// SYMBOLS_PACKED_V1 at the constellation's bits per symbol
+    pub symbols: Binary<'a>,
+    /// f64-le slicer confidence per symbol, bare
+    pub confidences: Binary<'a>,
+    /// IQ_F32_INTERLEAVED_V1 of the point each symbol was sliced from
+    pub iq: Binary<'a>,
+}
+
+/// Demodulate to symbols, with options
+///
+/// Options (keyword list):
+/// * `confidence: true` - return {symbols, confidences}, where each
+///   confidence is the slicer margin in 0.0 (ambiguous) ..= 1.0
+/// * `binary: true` - return a SoftDecisionMap instead: the symbols,
+///   their confidences and sliced points as binaries sharing one
+///   allocation (see UnifiedDemodulator::demodulate_soft)
+#[rustler::nif(name = "unified_demod_symbols")]
+pub fn unified_demod_symbols_opts<'a>(
+    env: Env<'a>,
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    samples: Vec<i16>,
+    opts: Vec<(Atom, Term<'a>)>,
+) -> NifResult<Term<'a>> {
+    guarded(|| {
+        let flag = |name: Atom| {
+            opts.iter().any(|(key, value)| *key == name && value.decode::<bool>().unwrap_or(false))
+        };
+        let (want_confidence, want_binary) = (flag(confidence()), flag(binary()));
+    
+        input_level::check_i16(env, &demodulator.input_warnings, &demodulator, &samples);
+        let mut state = demodulator.state();
+    
+        if want_binary {
+            let (symbols, confidences, points) = state.demodulate_soft(&samples);
+            let bits = state.constellation().bits_per_symbol() as u8;
+            drop(state);
+            let [symbols, confidences, iq] = arena::binaries(
+                env,
+                [
+                    arena::Field::Symbols(&symbols, bits),
+                    arena::Field::F64sLe(&confidences),
+                    arena::Field::Iq(&points),
+                ],
+            )?;
+            Ok(SoftDecisionMap { symbols, confidences, iq }.encode(env))
+        } else if want_confidence {
+            Ok(state.demodulate_with_confidence(&samples).encode(env))
+        } else {
+            Ok(state.demodulate(&samples).encode(env))
+        }
+    })
+}
+
+/// :none, {:gap, n_samples} or {:overlap, n_samples}
+fn discontinuity_term(env: Env<'_>, discontinuity: Discontinuity) -> Term<'_> {
+    match discontinuity {
+        Discontinuity::None => none().encode(env),
+        Discontinuity::Gap(n) => (gap(), n).encode(env),
+        Discontinuity::Overlap(n) => (overlap(), n).encode(env),
+    }
+}
+
+/// Demodulate samples the caller says start at start_sample_index
+///
+/// Returns {symbols, discontinuity}. A gap is zero-filled (see
+/// UnifiedDemodulator::demodulate_at); an overlap is cut from the front.
+#[rustler::nif]
+pub fn unified_demod_at<'a>(
+    env: Env<'a>,
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    start_sample_index: u64,
+    samples: Vec<i16>,
+) -> NifResult<(Vec<u8>, Term<'a>)> {
+    guarded(|| {
+        input_level::check_i16(env, &demodulator.input_warnings, &demodulator, &samples);
+        let mut state = demodulator.state();
+    
+        let (symbols, discontinuity) = state.demodulate_at(start_sample_index, &samples, GapPolicy::ZeroFill);
+        Ok((symbols, discontinuity_term(env, discontinuity)))
+    })
+}
+
+/// Demodulate samples the caller says start at start_sample_index, with
+/// options
+///
+/// Options (keyword list):
+/// * `gap: :zero_fill | :reacquire` - how to bridge a gap (default
+///   :zero_fill, see GapPolicy)
+#[rustler::nif(name = "unified_demod_at")]
+pub fn unified_demod_at_opts<'a>(
+    env: Env<'a>,
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    start_sample_index: u64,
+    samples: Vec<i16>,
+    opts: Vec<(Atom, Term<'a>)>,
+) -> NifResult<(Vec<u8>, Term<'a>)> {
+    guarded(|| {
+        let policy = match opts.iter().find(|(key, _)| *key == gap()) {
+            None => GapPolicy::ZeroFill,
+            Some((_, value)) => match value.decode::<Atom>() {
+                Ok(a) if a == zero_fill() => GapPolicy::ZeroFill,
+                Ok(a) if a == reacquire() => GapPolicy::Reacquire,
+                _ => return Err(PhyError::InvalidArgument("gap").into()),
+            },
+        };
+    
+        input_level::check_i16(env, &demodulator.input_warnings, &demodulator, &samples);
+        let mut state = demodulator.state();
+    
+        let (symbols, discontinuity) = state.demodulate_at(start_sample_index, &samples, policy);
+        Ok((symbols, discontinuity_term(env, discontinuity)))
+    })
+}
+
+/// Demodulate samples with an external frequency pre-correction
+///
+/// `corrections` is a list of {sample_offset, freq_hz} breakpoints within
+/// this block, offsets strictly increasing; the correction is linearly
+/// interpolated between them and held outside, and is added to the mixing
+/// NCO ahead of the PLL.
+#[rustler::nif]
+pub fn unified_demod_with_correction(
+    env: Env,
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    samples: Vec<i16>,
+    corrections: Vec<(u64, f64)>,
+) -> NifResult<Vec<u8>> {
+    guarded(|| {
+        let breakpoints = corrections
+            .into_iter()
+            .map(|(offset, hz)| (usize::try_from(offset).unwrap_or(usize::MAX), hz))
+            .collect();
+        let correction = FreqCorrection::new(breakpoints, samples.len())
+            .ok_or(PhyError::InvalidArgument("corrections"))?;
+    
+        input_level::check_i16(env, &demodulator.input_warnings, &demodulator, &samples);
+        let mut state = demodulator.state();
+    
+        Ok(state.demodulate_with_correction(&samples, &correction))
+    })
+}
+
+/// A short burst's decode and the hypothesis it was decoded on
+#[derive(NifMap)]
+pub struct ShortBurstMap {
+    /// Symbols after the known prefix
+    pub symbols: Vec<u8>,
+    pub confidences: Vec<f64>,
+    pub start_sample: usize,
+    pub freq_offset_hz: f64,
+    /// Radians, at the first sample
+    pub phase: f64,
+    pub gain: f64,
+    pub correlation: f64,
+}
+
+impl From<ShortBurst> for ShortBurstMap {
+    fn from(b: ShortBurst) -> Self {
+        ShortBurstMap {
+            symbols: b.symbols,
+            confidences: b.confidences,
+            start_sample: b.start_sample,
+            freq_offset_hz: b.freq_offset_hz,
+            phase: b.phase,
+            gain: b.gain,
+            correlation: b.correlation,
+        }
+    }
+}
+
+/// Demodulate a whole short burst that starts with `known_prefix`, by a
+/// joint search over timing, carrier frequency and phase (see
+/// modem::short_burst)
+///
+/// Options (keyword list, see ShortBurstConfig), defaults for any not
+/// given:
+/// * `max_freq_offset_hz:` - carrier offset searched either side (20.0)
+/// * `freq_step_hz:` - frequency grid spacing (2.0)
+/// * `symbols:` - symbols in the burst, prefix included (all that fit)
+///
+/// The demodulator's state is left untouched.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn unified_demod_short_burst(
+    env: Env,
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    samples: Vec<i16>,
+    known_prefix: Vec<u8>,
+    opts: Vec<(Atom, Term)>,
+) -> NifResult<ShortBurstMap> {
+    guarded(|| {
+        let mut config = ShortBurstConfig::default();
+        for (key, value) in opts {
+            if key == max_freq_offset_hz() {
+                config.max_freq_offset_hz = value.decode().map_err(|_| PhyError::InvalidArgument("max_freq_offset_hz"))?;
+            } else if key == freq_step_hz() {
+                config.freq_step_hz = value.decode().map_err(|_| PhyError::InvalidArgument("freq_step_hz"))?;
+            } else if key == symbols() {
+                config.symbols = Some(value.decode().map_err(|_| PhyError::InvalidArgument("symbols"))?);
+            } else {
+                return Err(PhyError::InvalidArgument("opts").into());
+            }
+        }
+    
+        input_level::check_i16(env, &demodulator.input_warnings, &demodulator, &samples);
+        let state = demodulator.state();
+        let burst = state
+            .demodulate_short_burst(&samples, &known_prefix, &config)
+            .map_err(PhyError::InvalidArgument)?;
+        Ok(burst.into())
+    })
+}
+
+/// Demodulate for at most about `budget_us` microseconds
+///
+/// Returns {consumed_samples, symbols, done}; until `done`, pass
+/// samples[consumed_samples..] to the next call to carry on (see
+/// UnifiedDemodulator::demodulate_step).
+#[rustler::nif]
+pub fn unified_demod_step(
+    env: Env,
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    samples: Vec<i16>,
+    budget_us: u64,
+) -> NifResult<(usize, Vec<u8>, bool)> {
+    guarded(|| {
+        input_level::check_i16(env, &demodulator.input_warnings, &demodulator, &samples);
+        let mut state = demodulator.state();
+    
+        let step = state.demodulate_step(&samples, std::time::Duration::from_micros(budget_us));
+        Ok((step.consumed, step.symbols, step.done))
+    })
+}
+
+/// Demodulate the samples held for timing acquisition (see
+/// UnifiedDemodulator::flush)
+#[rustler::nif]
+pub fn unified_demod_flush(demodulator: ResourceArc<UnifiedDemodulatorResource>) -> NifResult<Vec<u8>> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        Ok(state.flush())
+    })
+}
+
+/// Turn this demodulator's input level warnings on or off
+#[rustler::nif]
+pub fn unified_demod_set_input_warnings(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    enabled: bool,
+) -> NifResult<Atom> {
+    demodulator.input_warnings.store(enabled, Ordering::Relaxed);
+    Ok(ok())
+}
+
+/// Register the pid input level warnings are sent to (nil stops them)
+///
+/// Messages are {:input_warning, :suspicious_input_level, demodulator,
+/// peak_counts}; see input_level.
+#[rustler::nif]
+pub fn set_warning_logger(pid: Option<LocalPid>) -> NifResult<Atom> {
+    input_level::set_logger(pid);
+    Ok(ok())
+}
+
+/// Link quality summary for rate adaptation
+#[derive(NifMap)]
+pub struct SignalQualityMap {
+    /// Mean slicer confidence over the recent window (nil before any symbols)
+    pub confidence_mean: Option<f64>,
+    /// 10th-percentile slicer confidence over the recent window
+    pub confidence_p10: Option<f64>,
+    /// Equalizer MSE (nil without an equalizer)
+    pub eq_mse: Option<f64>,
+    /// Whether the EOT detector has declared the end of transmission
+    pub eot_detected: bool,
+    /// Symbol index where the transmission ended (nil until detected)
+    pub eot_symbol: Option<u64>,
+    /// Gain reference magnitude at the last probe, dB (nil unless enabled)
+    pub gain_db: Option<f64>,
+    /// Gain reference phase at the last probe, degrees: the PLL's residual
+    pub gain_phase_deg: Option<f64>,
+    /// Fraction of the burst's input samples that were clipped
+    pub clip_fraction: f64,
+}
+
+/// Report demodulator signal quality
+///
+/// Confidence covers the last CONFIDENCE_WINDOW demodulated symbols.
+#[rustler::nif]
+pub fn unified_demod_signal_quality(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<SignalQualityMap> {
+    guarded(|| {
+        let state = demodulator.state();
+    
+        let stats = state.confidence_stats();
+        Ok(SignalQualityMap {
+            confidence_mean: stats.map(|s| s.mean),
+            confidence_p10: stats.map(|s| s.p10),
+            eq_mse: state.equalizer_mse(),
+            eot_detected: state.eot_symbol().is_some(),
+            eot_symbol: state.eot_symbol(),
+            gain_db: state.reference_gain().map(|g| 20.0 * g.mag().log10()),
+            gain_phase_deg: state.reference_gain().map(|g| g.im.atan2(g.re).to_degrees()),
+            clip_fraction: state.clip_fraction(),
+        })
+    })
+}
+
+/// Acquisition, in a decode report
+#[derive(NifMap)]
+pub struct AcquisitionReportMap {
+    /// Input sample index (counted from reset) of the acquisition window
+    pub start_sample: u64,
+    pub timing_phase: usize,
+    /// PLL frequency correction at the end of the acquisition window, Hz
+    pub coarse_freq_hz: f64,
+    /// nil unless sideband detection decided
+    pub sideband_inverted: Option<bool>,
+}
+
+/// Carrier PLL over the burst, in a decode report
+#[derive(NifMap)]
+pub struct PllReportMap {
+    pub updates: u64,
+    /// |phase error| at the updates, radians
+    pub mean_abs_error: f64,
+    pub max_abs_error: f64,
+    pub freq_hz: f64,
+}
+
+/// Equalizer over the burst, in a decode report
+#[derive(NifMap)]
+pub struct EqReportMap {
+    /// [{symbol, :cma | :dd}] at the first symbol and each change
+    pub mode_timeline: Vec<(u64, Atom)>,
+    /// [{symbol, mse}] every mse_stride symbols
+    pub mse_curve: Vec<(u64, f64)>,
+    pub mse_stride: u64,
+    pub mse: f64,
+    pub taps_digest: u64,
+}
+
+/// Symbol counts, in a decode report
+#[derive(NifMap)]
+pub struct SymbolCountsMap {
+    pub iq: u64,
+    pub sliced: u64,
+    pub trained: u64,
+    pub emitted: u64,
+}
+
+/// Everything about the current burst (see modem::report)
+#[derive(NifMap)]
+pub struct DecodeReportMap {
+    /// nil until the burst has been acquired
+    pub acquisition: Option<AcquisitionReportMap>,
+    pub samples: u64,
+    pub clip_fraction: f64,
+    pub pll: PllReportMap,
+    /// nil without an equalizer
+    pub equalizer: Option<EqReportMap>,
+    pub symbols: SymbolCountsMap,
+    pub confidence_histogram: Vec<u64>,
+    pub snr_db: Option<f64>,
+    pub evm_percent: Option<f64>,
+    pub eot_symbol: Option<u64>,
+    pub warnings: Vec<Atom>,
+    pub fingerprint: u64,
+    pub provenance: String,
+}
+
+/// Report on the current burst, as a map (`:map`) or CBOR (`:cbor`)
+///
+/// The burst runs from the last timing acquisition; reset empties it.
+#[rustler::nif]
+pub fn unified_demod_decode_report<'a>(
+    env: Env<'a>,
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    format: Atom,
+) -> NifResult<Term<'a>> {
+    guarded(|| {
+        if format != map() && format != cbor() {
+            return Err(PhyError::InvalidArgument("format").into());
+        }
+        let report = demodulator.state().decode_report();
+        if format == cbor() {
+            return Ok(bytes_binary(env, &report.to_cbor())?.encode(env));
+        }
+    
+        Ok(DecodeReportMap {
+            acquisition: report.acquisition.map(|a| AcquisitionReportMap {
+                start_sample: a.start_sample,
+                timing_phase: a.timing_phase,
+                coarse_freq_hz: a.coarse_freq_hz,
+                sideband_inverted: a.sideband_inverted,
+            }),
+            samples: report.samples,
+            clip_fraction: report.clip_fraction,
+            pll: PllReportMap {
+                updates: report.pll.updates,
+                mean_abs_error: report.pll.mean_abs_error,
+                max_abs_error: report.pll.max_abs_error,
+                freq_hz: report.pll.freq_hz,
+            },
+            equalizer: report.equalizer.map(|eq| EqReportMap {
+                mode_timeline: eq.mode_timeline.iter().map(|&(n, mode)| (n, eq_mode_to_atom(mode))).collect(),
+                mse_curve: eq.mse_curve,
+                mse_stride: eq.mse_stride,
+                mse: eq.mse,
+                taps_digest: eq.taps_digest,
+            }),
+            symbols: SymbolCountsMap {
+                iq: report.symbols.iq,
+                sliced: report.symbols.sliced,
+                trained: report.symbols.trained,
+                emitted: report.symbols.emitted,
+            },
+            confidence_histogram: report.confidence_histogram.to_vec(),
+            snr_db: report.snr_db,
+            evm_percent: report.evm_percent,
+            eot_symbol: report.eot_symbol,
+            warnings: report
+                .warnings
+                .iter()
+                .map(|w| Atom::from_str(env, w.name()))
+                .collect::<NifResult<_>>()?,
+            fingerprint: report.fingerprint,
+            provenance: report.provenance.to_string(),
+        }
+        .encode(env))
+    })
+}
+
+/// EotConfig from keyword options, defaults for any not given
+fn decode_eot_config(opts: Vec<(Atom, Term)>) -> Result<EotConfig, PhyError> {
+    let count = |value: Term, name| {
+        value.decode::<usize>().ok().filter(|&n| n > 0).ok_or(PhyError::InvalidArgument(name))
+    };
+    let positive = |value: Term, name| {
+        value
+            .decode::<f64>()
+            .ok()
+            .filter(|&x| x.is_finite() && x > 0.0)
+            .ok_or(PhyError::InvalidArgument(name))
+    };
+    
+    let mut config = EotConfig::default();
+    for (key, value) in opts {
+        if key == average_symbols() {
+            config.average_symbols = count(value, "average_symbols")?;
+            check_max("average_symbols", config.average_symbols as u64, MAX_EOT_AVERAGE_SYMBOLS as u64)?;
+        } else if key == hold_symbols() {
+            config.hold_symbols = count(value, "hold_symbols")?;
+        } else if key == power_drop_db() {
+            config.power_drop_db = positive(value, "power_drop_db")?;
+        } else if key == evm_ceiling() {
+            config.evm_ceiling = positive(value, "evm_ceiling")?;
+        } else if key == truncate() {
+            config.truncate = value.decode().map_err(|_| PhyError::InvalidArgument("truncate"))?;
+        } else {
+            return Err(PhyError::InvalidArgument("opts"));
+        }
+    }
+    Ok(config)
+}
+
+/// Turn on the end-of-transmission detector
+///
+/// Options (keyword list, see EotConfig):
+/// * `average_symbols:` - symbols in the short power/EVM averages (8)
+/// * `hold_symbols:` - symbols the collapse must last; fades shorter than
+///   this don't trigger EOT (96)
+/// * `power_drop_db:` - drop below the in-burst power that counts (10.0)
+/// * `evm_ceiling:` - mean squared EVM above which quality counts as
+///   collapsed (0.5)
+/// * `truncate:` - emit no symbols from the collapse on (false)
+#[rustler::nif]
+pub fn unified_demod_enable_eot(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    opts: Vec<(Atom, Term)>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let config = decode_eot_config(opts)?;
+        let mut state = demodulator.state();
+        state.set_eot_detector(Some(config));
+        Ok(ok())
+    })
+}
+
+/// Turn off the end-of-transmission detector
+#[rustler::nif]
+pub fn unified_demod_disable_eot(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        state.set_eot_detector(None);
+        Ok(ok())
+    })
+}
+
+/// Track symbol timing with the Gardner loop (see
+/// UnifiedDemodulator::enable_timing_tracking)
+#[rustler::nif]
+pub fn unified_demod_enable_timing_tracking(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        state.enable_timing_tracking();
+        Ok(ok())
+    })
+}
+
+/// Back to the acquired timing phase (see
+/// UnifiedDemodulator::disable_timing_tracking)
+#[rustler::nif]
+pub fn unified_demod_disable_timing_tracking(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        state.disable_timing_tracking();
+        Ok(ok())
+    })
+}
+
+/// The timing loop's clock offset estimate, ppm, positive when the
+/// receiver's clock is fast (nil without a timing loop)
+#[rustler::nif]
+pub fn unified_demod_timing_offset_ppm(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Option<f64>> {
+    guarded(|| {
+        let state = demodulator.state();
+        Ok(state.timing_offset_ppm())
+    })
+}
+
+/// Wall time per receive chain stage, microseconds
+#[derive(NifMap)]
+pub struct StageTimesMap {
+    pub mix: f64,
+    pub filter: f64,
+    pub pll: f64,
+    pub equalizer: f64,
+    pub slicer: f64,
+    pub total: f64,
+}
+
+impl From<StageTimes> for StageTimesMap {
+    fn from(t: StageTimes) -> Self {
+        let us = |d: std::time::Duration| d.as_secs_f64() * 1e6;
+        Self {
+            mix: us(t.mix),
+            filter: us(t.filter),
+            pll: us(t.pll),
+            equalizer: us(t.equalizer),
+            slicer: us(t.slicer),
+            total: us(t.total),
+        }
+    }
+}
+
+/// Stage times of the last demodulate call and cumulative
+#[derive(NifMap)]
+pub struct StageTimingsMap {
+    pub last: StageTimesMap,
+    pub cumulative: StageTimesMap,
+    /// Demodulate calls timed
+    pub calls: u64,
+}
+
+/// Start timing the receive chain's stages (see
+/// UnifiedDemodulator::enable_stage_timing)
+#[rustler::nif]
+pub fn unified_demod_enable_stage_timing(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        state.enable_stage_timing();
+        Ok(ok())
+    })
+}
+
+/// Stop timing stages and drop the timings
+#[rustler::nif]
+pub fn unified_demod_disable_stage_timing(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        state.disable_stage_timing();
+        Ok(ok())
+    })
+}
+
+/// Per-stage wall time of the last demodulate call and since stage timing
+/// was enabled, in microseconds
+#[rustler::nif]
+pub fn unified_demod_stage_timings(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<StageTimingsMap> {
+    guarded(|| {
+        let state = demodulator.state();
+        let timings = state.stage_timings().ok_or(PhyError::IncompatibleState("no_stage_timing"))?;
+        Ok(StageTimingsMap {
+            last: timings.last.into(),
+            cumulative: timings.cumulative.into(),
+            calls: timings.calls,
+        })
+    })
+}
+
+/// Emit symbols, and take training symbols, in another vendor's
+/// numbering (see unified_mod_set_symbol_map for `map` and options)
+///
+/// The slicer and equalizer still decide on the native constellation.
+#[rustler::nif]
+pub fn unified_demod_set_symbol_map(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    map: Option<Vec<u8>>,
+    opts: Vec<(Atom, Term)>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        let map = decode_symbol_map(state.constellation(), map, opts)?;
+        state.set_symbol_map(map);
+        Ok(ok())
+    })
+}
+
+/// Hop the mixing carrier on a schedule, counted from the next sample
+/// demodulated (see unified_mod_set_hop_schedule)
+#[rustler::nif]
+pub fn unified_demod_set_hop_schedule(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    hops: Vec<(u64, f64)>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        let schedule = HopSchedule::new(hops, state.sample_rate()).ok_or(PhyError::InvalidArgument("hops"))?;
+        state.set_hop_schedule(schedule);
+        Ok(ok())
+    })
+}
+
+/// Track a QAM gain reference from 8-PSK probes of `probe` symbols,
+/// one every `period` symbols from output symbol `offset`, or (nil) stop
+/// (see UnifiedDemodulator::set_gain_reference)
+#[rustler::nif]
+pub fn unified_demod_set_gain_reference(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    schedule: Option<(u64, u64, Vec<u8>)>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        let schedule = schedule
+            .map(|(period, offset, probe)| ProbeSchedule::new(period, offset, probe).ok_or(PhyError::InvalidArgument("schedule")))
+            .transpose()?;
+        state.set_gain_reference(schedule);
+        Ok(ok())
+    })
+}
+
+/// Steer symbol timing and the carrier phase from known probes of `probe`
+/// symbols, one every `period` symbols from output symbol `offset`, or
+/// (nil) stop (see UnifiedDemodulator::set_probe_tracking)
+#[rustler::nif]
+pub fn unified_demod_set_probe_tracking(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    schedule: Option<(u64, u64, Vec<u8>)>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        let schedule = schedule
+            .map(|(period, offset, probe)| ProbeSchedule::new(period, offset, probe).ok_or(PhyError::InvalidArgument("schedule")))
+            .transpose()?;
+        state.set_probe_tracking(schedule);
+        Ok(ok())
+    })
+}
+
+/// Try each burst conjugated too at acquisition, or stop (see
+/// UnifiedDemodulator::set_sideband_detection)
+#[rustler::nif]
+pub fn unified_demod_set_sideband_detection(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    enabled: bool,
+) -> NifResult<Atom> {
+    guarded(|| {
+        demodulator.state().set_sideband_detection(enabled);
+        Ok(ok())
+    })
+}
+
+/// Set how clipped input is counted and whether it's declipped
+///
+/// Options (keyword list, see ClipConfig), defaults for any not given:
+/// * `threshold:` - fraction of full scale at or above which a sample
+///   counts as clipped, in (0, 1] (1.0, the i16 rails)
+/// * `declip:` - replace clipped runs with a cubic across the gap (false)
+#[rustler::nif]
+pub fn unified_demod_set_clip_handling(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    opts: Vec<(Atom, Term)>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut config = ClipConfig::default();
+        for (key, value) in opts {
+            if key == threshold() {
+                config.threshold = value.decode().map_err(|_| PhyError::InvalidArgument("threshold"))?;
+            } else if key == declip() {
+                config.declip = value.decode().map_err(|_| PhyError::InvalidArgument("declip"))?;
             } else {
-                none()
+                return Err(PhyError::InvalidArgument("opts").into());
             }
+        }
+        if !config.is_valid() {
+            return Err(PhyError::InvalidArgument("threshold").into());
+        }
+        demodulator.state().set_clip_handling(config);
+        Ok(ok())
+    })
+}
+
+/// One probe's entry in unified_demod_drain_probe_metrics
+#[derive(NifMap)]
+pub struct ProbeMetricMap {
+    pub symbol: u64,
+    pub correlation: f64,
+    /// Symbols, positive when the strobes are early
+    pub timing_error: f64,
+    /// Radians, within 45°
+    pub phase_error: f64,
+    pub weight: f64,
+}
+
+impl From<ProbeMetric> for ProbeMetricMap {
+    fn from(m: ProbeMetric) -> Self {
+        ProbeMetricMap {
+            symbol: m.symbol,
+            correlation: m.correlation,
+            timing_error: m.timing_error,
+            phase_error: m.phase_error,
+            weight: m.weight,
+        }
+    }
+}
+
+/// Probe tracking's log, drained, and its clock skew estimate
+#[derive(NifMap)]
+pub struct ProbeMetricsMap {
+    pub metrics: Vec<ProbeMetricMap>,
+    /// Positive when the receiver's clock is fast, ppm (nil before two probes)
+    pub clock_skew_ppm: Option<f64>,
+}
+
+/// Take the per-probe metrics logged since the last drain
+#[rustler::nif]
+pub fn unified_demod_drain_probe_metrics(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<ProbeMetricsMap> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        if !state.has_probe_tracking() {
+            return Err(PhyError::IncompatibleState("no_probe_tracking").into());
+        }
+        Ok(ProbeMetricsMap {
+            metrics: state.drain_probe_metrics().into_iter().map(Into::into).collect(),
+            clock_skew_ppm: state.probe_clock_skew_ppm(),
+        })
+    })
+}
+
+/// Baseband capture drained by unified_demod_drain_capture
+#[derive(NifMap)]
+pub struct CaptureMap<'a> {
+    /// IQ_F32_INTERLEAVED_V1
+    pub iq: Binary<'a>,
+    pub decimation: usize,
+    pub start_sample: u64,
+    pub sps: usize,
+    pub sample_rate: u32,
+    pub carrier_freq: f64,
+    pub dropped: u64,
+    pub provenance: String,
+}
+
+fn enable_capture(demodulator: &mut UnifiedDemodulator, decimation: usize, max_samples: usize) -> Result<(), PhyError> {
+    if decimation == 0 {
+        return Err(PhyError::InvalidArgument("decimation"));
+    }
+    check_max("max_samples", max_samples as u64, MAX_CAPTURE_SAMPLES as u64)?;
+    demodulator.enable_capture(decimation, max_samples);
+    Ok(())
+}
+
+/// Capture the matched filter output every `decimation` samples, up to
+/// `max_samples` points (see UnifiedDemodulator::enable_capture)
+#[rustler::nif]
+pub fn unified_demod_enable_capture(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    decimation: usize,
+    max_samples: usize,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        enable_capture(&mut state, decimation, max_samples)?;
+        Ok(ok())
+    })
+}
+
+/// Stop capturing and free the buffer
+#[rustler::nif]
+pub fn unified_demod_disable_capture(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        state.disable_capture();
+        Ok(ok())
+    })
+}
+
+/// Hold the capture so far for unified_demod_drain_capture
+#[rustler::nif]
+pub fn unified_demod_keep_capture(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        state.keep_capture();
+        Ok(ok())
+    })
+}
+
+/// Drop the capture so far and start afresh
+#[rustler::nif]
+pub fn unified_demod_discard_capture(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        state.discard_capture();
+        Ok(ok())
+    })
+}
+
+/// Take the capture, as IQ_F32_INTERLEAVED_V1 with its metadata, and
+/// start afresh
+#[rustler::nif]
+pub fn unified_demod_drain_capture<'a>(
+    env: Env<'a>,
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<CaptureMap<'a>> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        let capture = state.drain_capture().ok_or(PhyError::IncompatibleState("no_capture"))?;
+        drop(state);
+    
+        let iq: Vec<(f64, f64)> = capture.iq.iter().map(|&(i, q)| (i as f64, q as f64)).collect();
+        Ok(CaptureMap {
+            iq: iq_binary(env, &iq)?,
+            decimation: capture.decimation,
+            start_sample: capture.start_sample,
+            sps: capture.sps,
+            sample_rate: capture.sample_rate,
+            carrier_freq: capture.carrier_freq,
+            dropped: capture.dropped,
+            provenance: capture.provenance.to_string(),
         })
-        .unwrap_or(none())
-}
\ No newline at end of file
+    })
+}
+
+fn set_scatter_points(demodulator: &mut UnifiedDemodulator, points: usize) -> Result<(), PhyError> {
+    check_max("scatter_points", points as u64, MAX_SCATTER_POINTS as u64)?;
+    demodulator.set_scatter_points(points);
+    Ok(())
+}
+
+/// Keep the last `points` sliced points for unified_demod_scatter (see
+/// UnifiedDemodulator::set_scatter_points)
+#[rustler::nif]
+pub fn unified_demod_set_scatter_points(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    points: usize,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        set_scatter_points(&mut state, points)?;
+        Ok(ok())
+    })
+}
+
+/// The last sliced points as {i, q}, oldest first (see
+/// UnifiedDemodulator::scatter)
+#[rustler::nif]
+pub fn unified_demod_scatter(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Vec<(f64, f64)>> {
+    guarded(|| {
+        Ok(demodulator.state().scatter())
+    })
+}
+
+/// Switch demodulator constellation
+#[rustler::nif]
+pub fn unified_demod_set_constellation(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    modulation: Atom,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let constellation = atom_to_constellation(modulation)?;
+    
+        let mut state = demodulator.state();
+    
+        state.set_constellation(constellation);
+        Ok(ok())
+    })
+}
+
+/// Return demodulator to idle (see UnifiedDemodulator::reset_to_idle)
+#[rustler::nif]
+pub fn unified_demod_reset(demodulator: ResourceArc<UnifiedDemodulatorResource>) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        state.reset();
+        Ok(ok())
+    })
+}
+
+// ============================================================================
+// Constellation NIFs (stateless)
+// ============================================================================
+
+/// Check a symbol against the constellation's order
+fn check_symbol(constellation: ConstellationType, symbol: u32) -> Result<u8, PhyError> {
+    if symbol as usize >= constellation.order() {
+        return Err(PhyError::InvalidArgument("symbol"));
+    }
+    Ok(symbol as u8)
+}
+
+/// Map a symbol to its constellation point
+#[rustler::nif]
+pub fn symbol_to_iq(modulation: Atom, symbol: u32) -> NifResult<(f64, f64)> {
+    let constellation = atom_to_constellation(modulation)?;
+    let symbol = check_symbol(constellation, symbol)?;
+    Ok(constellation.symbol_to_iq(symbol))
+}
+
+/// Hard-decision slice of one I/Q point, as the demodulators slice
+#[rustler::nif]
+pub fn iq_to_symbol(modulation: Atom, i: f64, q: f64) -> NifResult<u8> {
+    let constellation = atom_to_constellation(modulation)?;
+    Ok(constellation.iq_to_symbol(i, q))
+}
+
+/// All points, indexed by symbol (QAM32/QAM64 repeat some)
+#[rustler::nif]
+pub fn constellation_points(modulation: Atom) -> NifResult<Vec<(f64, f64)>> {
+    Ok(atom_to_constellation(modulation)?.points())
+}
+
+/// The symbol iq_to_symbol returns for this symbol's point
+#[rustler::nif]
+pub fn canonical_symbol(modulation: Atom, symbol: u32) -> NifResult<u8> {
+    let constellation = atom_to_constellation(modulation)?;
+    let symbol = check_symbol(constellation, symbol)?;
+    Ok(constellation.canonical_symbol(symbol))
+}
+
+/// One symbol of constellation_bit_map/1
+#[derive(NifMap)]
+pub struct SymbolBitsMap {
+    pub symbol: u8,
+    pub i: f64,
+    pub q: f64,
+    /// The symbol iq_to_symbol returns for this point
+    pub canonical: u8,
+    /// Most significant first
+    pub bits: Vec<u8>,
+    /// Mask of the bits the symbols sharing this point disagree on
+    pub ambiguous_bits: u8,
+}
+
+/// Every symbol's point, canonical symbol and bits, for debugging the
+/// duplicated QAM points
+#[rustler::nif]
+pub fn constellation_bit_map(modulation: Atom) -> NifResult<Vec<SymbolBitsMap>> {
+    let constellation = atom_to_constellation(modulation)?;
+    Ok((0..constellation.order() as u8)
+        .map(|symbol| {
+            let (i, q) = constellation.symbol_to_iq(symbol);
+            SymbolBitsMap {
+                symbol,
+                i,
+                q,
+                canonical: constellation.canonical_symbol(symbol),
+                bits: constellation.symbol_to_bits(symbol),
+                ambiguous_bits: constellation.ambiguous_bits(symbol),
+            }
+        })
+        .collect())
+}
+
+/// Bit LLRs ln(P(0) / P(1)) of one received point, most significant
+/// first (see ConstellationType::bit_llrs)
+#[rustler::nif]
+pub fn bit_llrs(modulation: Atom, i: f64, q: f64, noise_var: f64) -> NifResult<Vec<f64>> {
+    let constellation = atom_to_constellation(modulation)?;
+    if !(noise_var.is_finite() && noise_var > 0.0) {
+        return Err(PhyError::InvalidArgument("noise_var").into());
+    }
+    Ok(constellation.bit_llrs(i, q, noise_var))
+}
+
+/// Bit LLRs of a block of received points, as LLR_F32_V1
+///
+/// `iq` is an I/Q binary (see wire::read_iq); the result holds
+/// bits_per_symbol LLRs per point, point by point, each most significant
+/// first as bit_llrs gives them.
+#[rustler::nif]
+pub fn bit_llrs_block<'a>(env: Env<'a>, modulation: Atom, iq: Binary, noise_var: f64) -> NifResult<Binary<'a>> {
+    guarded(|| {
+        let constellation = atom_to_constellation(modulation)?;
+        let points = iq_from_f32_bytes(iq.as_slice(), "iq")?;
+        if !(noise_var.is_finite() && noise_var > 0.0) {
+            return Err(PhyError::InvalidArgument("noise_var").into());
+        }
+        let llrs: Vec<f64> = points
+            .iter()
+            .flat_map(|&(i, q)| constellation.bit_llrs(i, q, noise_var))
+            .collect();
+        let mut owned = OwnedBinary::new(wire::Format::LlrF32.frame_len(llrs.len()))
+            .ok_or(PhyError::AllocFailed)?;
+        wire::write_llrs_f32(&llrs, owned.as_mut_slice());
+        Ok(owned.release(env))
+    })
+}
+
+#[rustler::nif]
+pub fn bits_per_symbol(modulation: Atom) -> NifResult<usize> {
+    Ok(atom_to_constellation(modulation)?.bits_per_symbol())
+}
+
+#[rustler::nif]
+pub fn order(modulation: Atom) -> NifResult<usize> {
+    Ok(atom_to_constellation(modulation)?.order())
+}
+
+// ============================================================================
+// Equalizer NIFs
+// ============================================================================
+
+/// Demodulator with a DFE at the fixed 2400 baud, 1800 Hz, checked first
+fn eq_demodulator(
+    constellation: ConstellationType,
+    sample_rate: u32,
+    ff_taps: usize,
+    fb_taps: usize,
+    mu: f64,
+    adaptation: Adaptation,
+) -> Result<UnifiedDemodulator, PhyError> {
+    let symbol_rate = 2400;
+    let carrier_freq = 1800.0;
+    
+    check_rates(sample_rate, symbol_rate, carrier_freq)?;
+    let config = DFEConfig { adaptation, ..eq_config(ff_taps, fb_taps, mu)? };
+    
+    Ok(UnifiedDemodulator::with_equalizer(
+        constellation, sample_rate, symbol_rate, carrier_freq, config
+    ))
+}
+
+/// Create a unified demodulator with DFE equalizer enabled
+///
+/// `adaptation` is :lms (the default, also for nil) or :rls, for which
+/// `mu` is only the CMA step's base.
+#[rustler::nif]
+pub fn unified_demod_new_with_eq(
+    modulation: Atom,
+    sample_rate: u32,
+    ff_taps: usize,
+    fb_taps: usize,
+    mu: f64,
+    adaptation: Option<Atom>,
+) -> NifResult<ResourceArc<UnifiedDemodulatorResource>> {
+    let constellation = atom_to_constellation(modulation)?;
+    let adaptation = adaptation.map(atom_to_adaptation).transpose()?.unwrap_or_default();
+    let demodulator = eq_demodulator(constellation, sample_rate, ff_taps, fb_taps, mu, adaptation)?;
+    
+    Ok(ResourceArc::new(UnifiedDemodulatorResource::new(demodulator)))
+}
+
+/// Create demodulator with default HF skywave equalizer settings
+#[rustler::nif]
+pub fn unified_demod_new_hf(
+    modulation: Atom,
+    sample_rate: u32,
+) -> NifResult<ResourceArc<UnifiedDemodulatorResource>> {
+    let symbol_rate = 2400;
+    let carrier_freq = 1800.0;
+    
+    let constellation = atom_to_constellation(modulation)?;
+    check_rates(sample_rate, symbol_rate, carrier_freq)?;
+    
+    let demodulator = UnifiedDemodulator::with_hf_equalizer(
+        constellation, sample_rate, symbol_rate, carrier_freq
+    );
+    
+    Ok(ResourceArc::new(UnifiedDemodulatorResource::new(demodulator)))
+}
+
+/// Set training symbols for equalizer acquisition
+#[rustler::nif]
+pub fn unified_demod_set_training(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    symbols: Vec<u8>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        if !state.has_equalizer() {
+            return Err(PhyError::IncompatibleState("no_equalizer").into());
+        }
+        state.set_training_symbols(symbols);
+        Ok(ok())
+    })
+}
+
+/// Reset equalizer state
+#[rustler::nif]
+pub fn unified_demod_reset_eq(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        state.reset_equalizer();
+        Ok(ok())
+    })
+}
+
+/// Fail a non-finite equalizer MSE rather than hand NaN to Elixir
+fn check_mse(mse: f64) -> Result<f64, PhyError> {
+    if mse.is_finite() {
+        Ok(mse)
+    } else {
+        Err(PhyError::NumericFault("equalizer_diverged"))
+    }
+}
+
+/// Get current mean squared error (0.0 without an equalizer)
+#[rustler::nif]
+pub fn unified_demod_mse(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<f64> {
+    guarded(|| {
+        let state = demodulator.state();
+        Ok(check_mse(state.equalizer_mse().unwrap_or(0.0))?)
+    })
+}
+
+/// Check if equalizer is enabled
+#[rustler::nif]
+pub fn unified_demod_has_eq(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<bool> {
+    guarded(|| {
+        Ok(demodulator.state().has_equalizer())
+    })
+}
+
+/// Enable equalizer on existing demodulator
+#[rustler::nif]
+pub fn unified_demod_enable_eq(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    ff_taps: usize,
+    fb_taps: usize,
+    mu: f64,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let config = eq_config(ff_taps, fb_taps, mu)?;
+        let mut state = demodulator.state();
+        state.enable_equalizer(config);
+        Ok(ok())
+    })
+}
+
+/// Enable a fractionally-spaced equalizer on existing demodulator: as
+/// unified_demod_enable_eq, with ff_taps T/2-spaced pairs of feedforward
+/// taps fed the mid-symbol point as well as the strobe
+#[rustler::nif]
+pub fn unified_demod_enable_fse(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    ff_taps: usize,
+    fb_taps: usize,
+    mu: f64,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let config = DFEConfig { fractional: true, ..eq_config(ff_taps, fb_taps, mu)? };
+        let mut state = demodulator.state();
+        state.enable_equalizer(config);
+        Ok(ok())
+    })
+}
+
+/// Disable equalizer
+#[rustler::nif]
+pub fn unified_demod_disable_eq(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+        state.disable_equalizer();
+        Ok(ok())
+    })
+}
+
+/// Get equalizer mode (:cma or :dd, :none without an equalizer)
+#[rustler::nif]
+pub fn unified_demod_eq_mode(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let state = demodulator.state();
+        Ok(state.equalizer_mode().map(eq_mode_to_atom).unwrap_or(none()))
+    })
+}
+
+/// Equalizer coefficients as {re, im}; a fractionally-spaced
+/// equalizer's feedforward is T/2-spaced
+#[derive(NifMap)]
+pub struct EqTapsMap {
+    pub ff: Vec<(f64, f64)>,
+    pub fb: Vec<(f64, f64)>,
+}
+
+/// Get the equalizer's feedforward and feedback coefficients
+#[rustler::nif]
+pub fn unified_demod_eq_taps(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<EqTapsMap> {
+    guarded(|| {
+        let state = demodulator.state();
+        let eq = state.equalizer().ok_or(PhyError::IncompatibleState("no_equalizer"))?;
+        Ok(EqTapsMap { ff: eq.ff_coefficients(), fb: eq.fb_coefficients() })
+    })
+}
+
+// ============================================================================
+// Standalone DFE (offline re-equalization of captured symbol-rate I/Q)
+// ============================================================================
+
+/// Resource wrapper for a standalone equalizer
+pub struct DFEResource {
+    pub inner: Mutex<DFE>,
+    pub tally: Tally,
+}
+
+impl DFEResource {
+    pub fn new(dfe: DFE) -> Self {
+        let tally = Tally::new(&kinds::EQUALIZERS, dfe.memory_bytes());
+        Self { inner: Mutex::new(dfe), tally }
+    }
+
+    /// Lock the equalizer (see counted::Counted)
+    pub fn state(&self) -> Counted<'_, DFE> {
+        Counted::lock(&self.inner, &self.tally)
+    }
+}
+
+impl Recover for DFE {
+    fn recover(&mut self) {
+        self.reset();
+    }
+}
+
+/// Equalizer state summary
+#[derive(NifMap)]
+pub struct DFEStatsMap {
+    pub mode: Atom,
+    pub mse: f64,
+    pub cma_cost: f64,
+    pub symbols_processed: u64,
+}
+
+fn eq_mode_to_atom(mode: EqMode) -> Atom {
+    match mode {
+        EqMode::CMA => cma(),
+        EqMode::DD => dd(),
+    }
+}
+
+fn atom_to_adaptation(adaptation: Atom) -> Result<Adaptation, PhyError> {
+    if adaptation == lms() {
+        Ok(Adaptation::Lms)
+    } else if adaptation == rls() {
+        Ok(Adaptation::Rls)
+    } else {
+        Err(PhyError::InvalidArgument("adaptation"))
+    }
+}
+
+fn adaptation_to_atom(adaptation: Adaptation) -> Atom {
+    match adaptation {
+        Adaptation::Lms => lms(),
+        Adaptation::Rls => rls(),
+    }
+}
+
+/// Build a DFEConfig from a map
+///
+/// `preset:` (:hf_skywave, :ground_wave or :fast_acquisition) picks the
+/// starting point, defaulting to DFEConfig::default(); any DFEConfig
+/// field given in the map overrides it, `fractional:` as a boolean and
+/// `adaptation:` as :lms or :rls.
+fn decode_dfe_config(map: Term) -> Result<DFEConfig, PhyError> {
+    let get = |key: Atom| map.map_get(key).ok();
+    
+    let mut config = match get(preset()) {
+        None => DFEConfig::default(),
+        Some(term) => {
+            let name: Atom = term.decode().map_err(|_| PhyError::InvalidArgument("dfe_preset"))?;
+            if name == hf_skywave() {
+                DFEConfig::hf_skywave()
+            } else if name == ground_wave() {
+                DFEConfig::ground_wave()
+            } else if name == fast_acquisition() {
+                DFEConfig::fast_acquisition()
+            } else {
+                return Err(PhyError::InvalidArgument("dfe_preset"));
+            }
+        }
+    };
+    
+    let usize_field = |key: Atom, field: &mut usize| -> Result<(), PhyError> {
+        if let Some(term) = get(key) {
+            *field = term.decode().map_err(|_| PhyError::InvalidArgument("dfe_config"))?;
+        }
+        Ok(())
+    };
+    usize_field(ff_taps(), &mut config.ff_taps)?;
+    usize_field(fb_taps(), &mut config.fb_taps)?;
+    usize_field(cma_min_symbols(), &mut config.cma_min_symbols)?;
+    
+    let f64_field = |key: Atom, field: &mut f64| -> Result<(), PhyError> {
+        if let Some(term) = get(key) {
+            *field = term.decode().map_err(|_| PhyError::InvalidArgument("dfe_config"))?;
+        }
+        Ok(())
+    };
+    f64_field(mu(), &mut config.mu)?;
+    f64_field(mu_cma(), &mut config.mu_cma)?;
+    f64_field(leakage(), &mut config.leakage)?;
+    f64_field(update_threshold(), &mut config.update_threshold)?;
+    f64_field(cma_to_dd_threshold(), &mut config.cma_to_dd_threshold)?;
+    f64_field(lambda(), &mut config.lambda)?;
+    if let Some(term) = get(fractional()) {
+        config.fractional = term.decode().map_err(|_| PhyError::InvalidArgument("dfe_config"))?;
+    }
+    if let Some(term) = get(adaptation()) {
+        let name: Atom = term.decode().map_err(|_| PhyError::InvalidArgument("adaptation"))?;
+        config.adaptation = atom_to_adaptation(name)?;
+    }
+    if !(config.lambda > 0.0 && config.lambda <= 1.0) {
+        return Err(PhyError::InvalidArgument("lambda"));
+    }
+    
+    check_eq_taps(config.ff_taps, config.fb_taps)?;
+    Ok(config)
+}
+
+/// Decode symbol-rate I/Q: a list of {i, q} tuples (as returned by
+/// unified_demod_iq) or an IQ_F32_INTERLEAVED_V1 binary. While the
+/// `legacy-wire` feature is on, a headerless binary is taken as the
+/// interleaved native-endian f64 I, Q these NIFs read before.
+fn decode_iq(term: Term) -> Result<Vec<(f64, f64)>, PhyError> {
+    if let Ok(binary) = term.decode::<Binary>() {
+        let bytes = binary.as_slice();
+        if !wire::is_framed(bytes) && wire::LEGACY == wire::Legacy::Accept {
+            return minutemodem_dsp::convert::iq_from_ne_bytes(bytes).ok_or(PhyError::InvalidArgument("iq"));
+        }
+        return wire::decode_iq_f32(bytes, wire::Legacy::Reject).map_err(|_| PhyError::InvalidArgument("iq"));
+    }
+    term.decode().map_err(|_| PhyError::InvalidArgument("iq"))
+}
+
+/// Create a standalone DFE
+///
+/// # Arguments
+/// * `modulation` - Constellation the decisions are sliced against
+/// * `config` - Map of DFEConfig overrides (see decode_dfe_config)
+#[rustler::nif]
+pub fn dfe_new(modulation: Atom, config: Term) -> NifResult<ResourceArc<DFEResource>> {
+    let constellation = atom_to_constellation(modulation)?;
+    let config = decode_dfe_config(config)?;
+    // The batch NIFs take one point per symbol
+    if config.fractional {
+        return Err(PhyError::InvalidArgument("fractional").into());
+    }
+    
+    Ok(ResourceArc::new(DFEResource::new(DFE::new(config, constellation))))
+}
+
+/// Train on captured I/Q with the symbols known to have been sent
+///
+/// Returns the decisions made while training.
+#[rustler::nif]
+pub fn dfe_train(
+    dfe: ResourceArc<DFEResource>,
+    iq: Term,
+    known_symbols: Vec<u8>,
+) -> NifResult<Vec<u8>> {
+    guarded(|| {
+        let iq = decode_iq(iq)?;
+        if iq.len() != known_symbols.len() {
+            return Err(PhyError::InvalidArgument("known_symbols").into());
+        }
+    
+        let mut state = dfe.state();
+    
+        Ok(state.train_batch(&iq, &known_symbols))
+    })
+}
+
+/// Equalize captured I/Q in decision-directed (or blind CMA) mode
+///
+/// Options (keyword list):
+/// * `iq: true` - return {symbols, equalized_iq} instead of just symbols
+#[rustler::nif]
+pub fn dfe_equalize<'a>(
+    env: Env<'a>,
+    dfe: ResourceArc<DFEResource>,
+    iq_in: Term<'a>,
+    opts: Vec<(Atom, Term<'a>)>,
+) -> NifResult<Term<'a>> {
+    guarded(|| {
+        let want_iq = opts
+            .iter()
+            .any(|(key, value)| *key == iq() && value.decode::<bool>().unwrap_or(false));
+        let samples = decode_iq(iq_in)?;
+    
+        let mut state = dfe.state();
+    
+        let (symbols, equalized) = state.equalize_batch(&samples);
+        if want_iq {
+            Ok((symbols, equalized).encode(env))
+        } else {
+            Ok(symbols.encode(env))
+        }
+    })
+}
+
+/// Get equalizer mode, MSE, CMA cost and symbol count
+#[rustler::nif]
+pub fn dfe_stats(dfe: ResourceArc<DFEResource>) -> NifResult<DFEStatsMap> {
+    guarded(|| {
+        let state = dfe.state();
+    
+        Ok(DFEStatsMap {
+            mode: eq_mode_to_atom(state.mode()),
+            mse: state.mse(),
+            cma_cost: state.cma_cost(),
+            symbols_processed: state.symbols_processed(),
+        })
+    })
+}
+
+/// Reset coefficients, history and statistics (config is kept)
+#[rustler::nif]
+pub fn dfe_reset(dfe: ResourceArc<DFEResource>) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = dfe.state();
+    
+        state.reset();
+        Ok(ok())
+    })
+}
+
+// ============================================================================
+// RX IF Filter Model
+// ============================================================================
+
+/// Decode an IF filter spec: nil, a preset atom, or a list of
+/// {b0, b1, b2, a1, a2} biquad sections (a0 normalized to 1)
+fn decode_rx_filter(spec: Term, sample_rate: u32) -> Result<Option<BiquadCascade>, PhyError> {
+    if let Ok(atom) = spec.decode::<Atom>() {
+        return if atom == rustler::types::atom::nil() {
+            Ok(None)
+        } else if atom == ssb_2k7() {
+            Ok(Some(RxFilterPreset::Ssb2k7.design(sample_rate)))
+        } else if atom == ssb_3k() {
+            Ok(Some(RxFilterPreset::Ssb3k.design(sample_rate)))
+        } else {
+            Err(PhyError::InvalidArgument("rx_filter"))
+        };
+    }
+
+    let coeffs: Vec<(f64, f64, f64, f64, f64)> = spec
+        .decode()
+        .map_err(|_| PhyError::InvalidArgument("rx_filter"))?;
+    biquad_cascade(&coeffs).map(Some)
+}
+
+/// Build a cascade from {b0, b1, b2, a1, a2} sections, rejecting an empty
+/// list and any section with a pole on or outside the unit circle
+fn biquad_cascade(coeffs: &[(f64, f64, f64, f64, f64)]) -> Result<BiquadCascade, PhyError> {
+    if coeffs.is_empty() {
+        return Err(PhyError::InvalidArgument("rx_filter"));
+    }
+
+    let mut sections = Vec::with_capacity(coeffs.len());
+    for &(b0, b1, b2, a1, a2) in coeffs {
+        // Stability triangle: both poles inside the unit circle
+        if !(a2.abs() < 1.0 && a1.abs() < 1.0 + a2) {
+            return Err(PhyError::InvalidArgument("rx_filter"));
+        }
+        sections.push(Biquad::new(b0, b1, b2, a1, a2));
+    }
+    Ok(BiquadCascade::new(sections))
+}
+
+/// Set the receiver IF filter model applied before mixing
+///
+/// # Arguments
+/// * `filter` - nil (off), :ssb_2k7, :ssb_3k, or a list of
+///   {b0, b1, b2, a1, a2} biquad sections
+#[rustler::nif]
+pub fn unified_demod_set_rx_filter(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+    filter: Term,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = demodulator.state();
+
+        let cascade = decode_rx_filter(filter, state.sample_rate())?;
+        state.set_rx_filter(cascade);
+        Ok(ok())
+    })
+}
+
+// ============================================================================
+// Modulator/Demodulator Compatibility
+// ============================================================================
+
+/// Fingerprint of the modulator's sample rate, symbol rate, carrier, RRC
+/// alpha and current constellation
+#[rustler::nif]
+pub fn unified_mod_config_fingerprint(
+    modulator: ResourceArc<UnifiedModulatorResource>,
+) -> NifResult<u64> {
+    guarded(|| {
+        let state = modulator.state();
+    
+        Ok(state.config_fingerprint())
+    })
+}
+
+/// Fingerprint of the demodulator config (comparable with the modulator's)
+#[rustler::nif]
+pub fn unified_demod_config_fingerprint(
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<u64> {
+    guarded(|| {
+        let state = demodulator.state();
+    
+        Ok(state.config_fingerprint())
+    })
+}
+
+/// Compare a modulator and demodulator config
+///
+/// Returns :ok, or {:mismatch, [{field, mod_value, demod_value}]} listing
+/// every field that differs.
+#[rustler::nif]
+pub fn check_compatibility<'a>(
+    env: Env<'a>,
+    modulator: ResourceArc<UnifiedModulatorResource>,
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Term<'a>> {
+    guarded(|| {
+        let tx = modulator.state()
+            .config();
+        let rx = demodulator.state()
+            .config();
+    
+        let diff = tx.diff(&rx);
+        if diff.is_empty() {
+            return Ok(ok().encode(env));
+        }
+    
+        let fields: Vec<Term> = diff
+            .into_iter()
+            .map(|m| match m {
+                ConfigMismatch::SampleRate(a, b) => (sample_rate(), a, b).encode(env),
+                ConfigMismatch::SymbolRate(a, b) => (symbol_rate(), a, b).encode(env),
+                ConfigMismatch::CarrierFreq(a, b) => (carrier_freq(), a, b).encode(env),
+                ConfigMismatch::RrcAlpha(a, b) => (rrc_alpha(), a, b).encode(env),
+                ConfigMismatch::Pulse(a, b) => (pulse(), pulse_term(env, a), pulse_term(env, b)).encode(env),
+                ConfigMismatch::Constellation(a, b) => {
+                    (constellation(), constellation_to_atom(a), constellation_to_atom(b)).encode(env)
+                }
+            })
+            .collect();
+    
+        Ok((mismatch(), fields).encode(env))
+    })
+}
+
+// ============================================================================
+// Resource Introspection
+// ============================================================================
+
+/// describe/1 of a generic modulator (mod_new)
+#[derive(NifMap)]
+pub struct ModulatorDescriptionMap<'a> {
+    pub resource: Atom,
+    pub constellation: Atom,
+    pub sample_rate: u32,
+    pub symbol_rate: u32,
+    pub carrier_freq: f64,
+    pub sps: usize,
+    pub pulse: Term<'a>,
+    pub rrc_alpha: f64,
+    pub span: usize,
+    /// Pulse shaping filter taps
+    pub filter_len: usize,
+    /// Estimate of the heap memory held (taps and filter history)
+    pub memory_bytes: usize,
+    pub symbols_modulated: u64,
+    pub fingerprint: u64,
+}
+
+/// describe/1 of a generic demodulator (demod_new)
+#[derive(NifMap)]
+pub struct DemodulatorDescriptionMap<'a> {
+    pub resource: Atom,
+    pub constellation: Atom,
+    pub sample_rate: u32,
+    pub symbol_rate: u32,
+    pub carrier_freq: f64,
+    pub sps: usize,
+    pub pulse: Term<'a>,
+    pub rrc_alpha: f64,
+    pub span: usize,
+    /// Matched filter taps
+    pub filter_len: usize,
+    /// Estimate of the heap memory held (taps and filter history)
+    pub memory_bytes: usize,
+    pub input_warnings: bool,
+    pub symbols_demodulated: u64,
+    pub fingerprint: u64,
+}
+
+/// describe/1 of a unified modulator
+#[derive(NifMap)]
+pub struct UnifiedModulatorDescriptionMap<'a> {
+    pub resource: Atom,
+    /// The currently selected constellation
+    pub constellation: Atom,
+    pub sample_rate: u32,
+    pub symbol_rate: u32,
+    pub carrier_freq: f64,
+    pub sps: usize,
+    pub pulse: Term<'a>,
+    pub rrc_alpha: f64,
+    pub span: usize,
+    /// Pulse shaping filter taps
+    pub filter_len: usize,
+    pub ramp_samples: usize,
+    pub symbol_map: bool,
+    pub hop_schedule: bool,
+    /// Symbols pushed and not yet pulled
+    pub queued_symbols: usize,
+    /// Estimate of the heap memory held (see UnifiedModulator::memory_bytes)
+    pub memory_bytes: usize,
+    pub symbols_modulated: u64,
+    pub fingerprint: u64,
+}
+
+/// Equalizer configuration and mode, in describe/1 of a unified demodulator
+#[derive(NifMap)]
+pub struct EqDescriptionMap {
+    pub mode: Atom,
+    pub ff_taps: usize,
+    pub fb_taps: usize,
+    pub mu: f64,
+    pub mu_cma: f64,
+    pub leakage: f64,
+    pub update_threshold: f64,
+    pub cma_to_dd_threshold: f64,
+    pub cma_min_symbols: usize,
+    /// T/2-spaced feedforward taps
+    pub fractional: bool,
+    /// :lms or :rls
+    pub adaptation: Atom,
+    /// RLS forgetting factor
+    pub lambda: f64,
+}
+
+/// Carrier PLL, in describe/1 of a unified demodulator
+#[derive(NifMap)]
+pub struct PllDescriptionMap {
+    /// Proportional gain, per symbol
+    pub alpha: f64,
+    /// Integral gain, per symbol (0 = proportional only)
+    pub beta: f64,
+    /// Current frequency correction, Hz
+    pub freq_offset_hz: f64,
+}
+
+/// describe/1 of a unified demodulator
+#[derive(NifMap)]
+pub struct UnifiedDemodulatorDescriptionMap<'a> {
+    pub resource: Atom,
+    /// The currently selected constellation
+    pub constellation: Atom,
+    pub sample_rate: u32,
+    pub symbol_rate: u32,
+    pub carrier_freq: f64,
+    pub sps: usize,
+    pub pulse: Term<'a>,
+    pub rrc_alpha: f64,
+    pub span: usize,
+    /// Receive filter taps
+    pub filter_len: usize,
+    /// nil without an equalizer
+    pub eq: Option<EqDescriptionMap>,
+    /// Feedforward plus feedback taps (0 without an equalizer)
+    pub eq_taps: usize,
+    pub pll: PllDescriptionMap,
+    /// Gain reference magnitude at the last probe, dB (nil unless enabled):
+    /// the demodulator's only gain control
+    pub gain_db: Option<f64>,
+    pub rx_filter: bool,
+    pub timing_tracking: bool,
+    pub probe_tracking: bool,
+    pub sideband_detection: bool,
+    /// Clip threshold, fraction of full scale
+    pub clip_threshold: f64,
+    pub declip: bool,
+    pub stage_timing: bool,
+    pub eot: bool,
+    pub capture: bool,
+    pub symbol_map: bool,
+    pub hop_schedule: bool,
+    pub input_warnings: bool,
+    /// Estimate of the heap memory held (see UnifiedDemodulator::memory_bytes)
+    pub memory_bytes: usize,
+    pub samples_consumed: u64,
+    pub symbols_demodulated: u64,
+    pub fingerprint: u64,
+}
+
+fn describe_modulator<'a>(env: Env<'a>, resource: &ModulatorResource) -> NifResult<Term<'a>> {
+    let d = resource.state().describe();
+    Ok(ModulatorDescriptionMap {
+        resource: modulator(),
+        constellation: constellation_to_atom(d.config.constellation),
+        sample_rate: d.config.sample_rate,
+        symbol_rate: d.config.symbol_rate,
+        carrier_freq: d.config.carrier_freq,
+        sps: d.sps,
+        pulse: pulse_term(env, d.config.pulse),
+        rrc_alpha: d.config.rrc_alpha,
+        span: d.span,
+        filter_len: d.filter_len,
+        memory_bytes: d.memory_bytes,
+        symbols_modulated: d.symbols,
+        fingerprint: d.config.fingerprint(),
+    }
+    .encode(env))
+}
+
+fn describe_demodulator<'a>(env: Env<'a>, resource: &DemodulatorResource) -> NifResult<Term<'a>> {
+    let d = resource.state().describe();
+    Ok(DemodulatorDescriptionMap {
+        resource: demodulator(),
+        constellation: constellation_to_atom(d.config.constellation),
+        sample_rate: d.config.sample_rate,
+        symbol_rate: d.config.symbol_rate,
+        carrier_freq: d.config.carrier_freq,
+        sps: d.sps,
+        pulse: pulse_term(env, d.config.pulse),
+        rrc_alpha: d.config.rrc_alpha,
+        span: d.span,
+        filter_len: d.filter_len,
+        memory_bytes: d.memory_bytes,
+        input_warnings: resource.input_warnings.load(Ordering::Relaxed),
+        symbols_demodulated: d.symbols,
+        fingerprint: d.config.fingerprint(),
+    }
+    .encode(env))
+}
+
+fn describe_unified_modulator<'a>(env: Env<'a>, resource: &UnifiedModulatorResource) -> NifResult<Term<'a>> {
+    let state = resource.state();
+    let config = state.config();
+    Ok(UnifiedModulatorDescriptionMap {
+        resource: unified_modulator(),
+        constellation: constellation_to_atom(config.constellation),
+        sample_rate: config.sample_rate,
+        symbol_rate: config.symbol_rate,
+        carrier_freq: config.carrier_freq,
+        sps: state.sps(),
+        pulse: pulse_term(env, config.pulse),
+        rrc_alpha: config.rrc_alpha,
+        span: config.pulse.span(),
+        filter_len: state.filter_len(),
+        ramp_samples: state.ramp_samples(),
+        symbol_map: state.symbol_map().is_some(),
+        hop_schedule: state.hop_schedule().is_some(),
+        queued_symbols: state.queued_symbols(),
+        memory_bytes: state.memory_bytes(),
+        symbols_modulated: state.symbols_modulated(),
+        fingerprint: config.fingerprint(),
+    }
+    .encode(env))
+}
+
+fn describe_unified_demodulator<'a>(env: Env<'a>, resource: &UnifiedDemodulatorResource) -> NifResult<Term<'a>> {
+    let state = resource.state();
+    let config = state.config();
+    let eq = state.equalizer_config().zip(state.equalizer_mode()).map(|(c, mode)| EqDescriptionMap {
+        mode: eq_mode_to_atom(mode),
+        ff_taps: c.ff_taps,
+        fb_taps: c.fb_taps,
+        mu: c.mu,
+        mu_cma: c.mu_cma,
+        leakage: c.leakage,
+        update_threshold: c.update_threshold,
+        cma_to_dd_threshold: c.cma_to_dd_threshold,
+        cma_min_symbols: c.cma_min_symbols,
+        fractional: c.fractional,
+        adaptation: adaptation_to_atom(c.adaptation),
+        lambda: c.lambda,
+    });
+    let (alpha, beta) = state.pll_gains();
+    Ok(UnifiedDemodulatorDescriptionMap {
+        resource: unified_demodulator(),
+        constellation: constellation_to_atom(config.constellation),
+        sample_rate: config.sample_rate,
+        symbol_rate: config.symbol_rate,
+        carrier_freq: config.carrier_freq,
+        sps: state.sps(),
+        pulse: pulse_term(env, config.pulse),
+        rrc_alpha: config.rrc_alpha,
+        span: config.pulse.span(),
+        filter_len: state.filter_len(),
+        eq_taps: eq.as_ref().map_or(0, |eq| eq.ff_taps + eq.fb_taps),
+        eq,
+        pll: PllDescriptionMap { alpha, beta, freq_offset_hz: state.pll_freq_hz() },
+        gain_db: state.reference_gain().map(|g| 20.0 * g.mag().log10()),
+        rx_filter: state.has_rx_filter(),
+        timing_tracking: state.has_timing_tracking(),
+        probe_tracking: state.has_probe_tracking(),
+        sideband_detection: state.has_sideband_detection(),
+        clip_threshold: state.clip_handling().threshold,
+        declip: state.clip_handling().declip,
+        stage_timing: state.stage_timings().is_some(),
+        eot: state.has_eot_detector(),
+        capture: state.has_capture(),
+        symbol_map: state.symbol_map().is_some(),
+        hop_schedule: state.hop_schedule().is_some(),
+        input_warnings: resource.input_warnings.load(Ordering::Relaxed),
+        memory_bytes: state.memory_bytes(),
+        samples_consumed: state.samples_consumed(),
+        symbols_demodulated: state.symbols_demodulated(),
+        fingerprint: config.fingerprint(),
+    }
+    .encode(env))
+}
+
+/// Effective configuration and resource usage of any modulator or
+/// demodulator resource, for monitoring
+///
+/// A map with `resource:` (:modulator, :demodulator, :unified_modulator
+/// or :unified_demodulator), the configuration (constellation, rates,
+/// carrier, pulse, alpha and span; for a unified demodulator also the
+/// equalizer, PLL and gain reference), filter and equalizer tap counts,
+/// an estimate of the heap memory held, the symbols modulated or
+/// demodulated since creation (reset doesn't clear them) and the config
+/// fingerprint (see unified_mod_config_fingerprint). Fails with
+/// `{:invalid_argument, :resource}` for anything else.
+#[rustler::nif]
+pub fn describe<'a>(env: Env<'a>, resource: Term<'a>) -> NifResult<Term<'a>> {
+    if let Ok(r) = resource.decode::<ResourceArc<ModulatorResource>>() {
+        describe_modulator(env, &r)
+    } else if let Ok(r) = resource.decode::<ResourceArc<DemodulatorResource>>() {
+        describe_demodulator(env, &r)
+    } else if let Ok(r) = resource.decode::<ResourceArc<UnifiedModulatorResource>>() {
+        describe_unified_modulator(env, &r)
+    } else if let Ok(r) = resource.decode::<ResourceArc<UnifiedDemodulatorResource>>() {
+        describe_unified_demodulator(env, &r)
+    } else {
+        Err(PhyError::InvalidArgument("resource").into())
+    }
+}
+
+// ============================================================================
+// Determinism Digest
+// ============================================================================
+
+/// Encode bytes as a fresh binary
+fn bytes_binary<'a>(env: Env<'a>, bytes: &[u8]) -> NifResult<Binary<'a>> {
+    let mut owned = OwnedBinary::new(bytes.len())
+        .ok_or(PhyError::AllocFailed)?;
+    owned.as_mut_slice().copy_from_slice(bytes);
+    Ok(owned.release(env))
+}
+
+/// Build a fresh demodulator from demod_digest options (map or keyword
+/// list)
+///
+/// `modulation:` (required), `sample_rate:` (9600), `pulse:` and `bt:` as
+/// unified_demod_new/3, `equalizer:` a DFE config map (see
+/// decode_dfe_config), `training:` symbols (needs `equalizer:`),
+/// `rx_filter:` as unified_demod_set_rx_filter/2 and `eot:` the options
+/// of unified_demod_enable_eot/2.
+fn digest_demodulator(opts: Term) -> Result<UnifiedDemodulator, PhyError> {
+    let symbol_rate = 2400;
+    let carrier_freq = 1800.0;
+    
+    let modulation = get_opt(opts, modulation())?.ok_or(PhyError::InvalidArgument("modulation"))?;
+    let constellation = atom_to_constellation(modulation.decode().map_err(|_| PhyError::InvalidArgument("modulation"))?)?;
+    let sample_rate = match get_opt(opts, sample_rate())? {
+        None => 9600,
+        Some(term) => term.decode().map_err(|_| PhyError::InvalidArgument("sample_rate"))?,
+    };
+    check_rates(sample_rate, symbol_rate, carrier_freq)?;
+    let pulse = decode_pulse(opts)?;
+    
+    let mut demod = UnifiedDemodulator::with_pulse(constellation, sample_rate, symbol_rate, carrier_freq, pulse);
+    if let Some(term) = get_opt(opts, equalizer())? {
+        demod.enable_equalizer(decode_dfe_config(term)?);
+    }
+    if let Some(term) = get_opt(opts, training())? {
+        if !demod.has_equalizer() {
+            return Err(PhyError::IncompatibleState("no_equalizer"));
+        }
+        demod.set_training_symbols(term.decode().map_err(|_| PhyError::InvalidArgument("training"))?);
+    }
+    if let Some(term) = get_opt(opts, rx_filter())? {
+        demod.set_rx_filter(decode_rx_filter(term, sample_rate)?);
+    }
+    if let Some(term) = get_opt(opts, eot())? {
+        let eot_opts = term.decode().map_err(|_| PhyError::InvalidArgument("eot"))?;
+        demod.set_eot_detector(Some(decode_eot_config(eot_opts)?));
+    }
+    Ok(demod)
+}
+
+/// Digest a fresh demodulator's run over `samples` (see modem::digest)
+///
+/// Returns the 32-byte BLAKE3 of the symbols, the matched filter I/Q and
+/// the final state. Equal options and samples give an equal digest on
+/// every call and scheduler.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn demod_digest<'a>(env: Env<'a>, opts: Term<'a>, samples: Vec<i16>) -> NifResult<Binary<'a>> {
+    let mut demod = digest_demodulator(opts)?;
+    bytes_binary(env, &modem::demod_digest(&mut demod, &samples))
+}
+
+/// The demodulator's state, canonically encoded (see modem::state)
+#[rustler::nif]
+pub fn unified_demod_export_state<'a>(
+    env: Env<'a>,
+    demodulator: ResourceArc<UnifiedDemodulatorResource>,
+) -> NifResult<Binary<'a>> {
+    guarded(|| {
+        let state = demodulator.state();
+        let bytes = state.export_state();
+        drop(state);
+    
+        bytes_binary(env, &bytes)
+    })
+}
+
+// ============================================================================
+// Self Test
+// ============================================================================
+
+/// Run the built-in sanity check (see self_test)
+///
+/// Returns `{:ok, %{elapsed_us: n, checks: %{name => summary}}}`, or
+/// `{:error, [{name, detail}]}` listing the checks that failed.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn self_test(env: Env<'_>) -> NifResult<Term<'_>> {
+    let report = crate::self_test::run();
+    let name = |name: &str| Atom::from_str(env, name);
+
+    if !report.passed() {
+        let failures = report
+            .failures()
+            .into_iter()
+            .map(|(check, detail)| Ok((name(check)?, detail)))
+            .collect::<NifResult<Vec<_>>>()?;
+        return Ok((error(), failures).encode(env));
+    }
+
+    let mut checks = Term::map_new(env);
+    for check in &report.checks {
+        let summary = check.result.as_deref().unwrap_or_default();
+        checks = checks.map_put(name(check.name)?, summary)?;
+    }
+    let summary = Term::map_from_pairs(
+        env,
+        &[
+            (name("elapsed_us")?.encode(env), (report.elapsed.as_micros() as u64).encode(env)),
+            (name("checks")?.encode(env), checks),
+            (name("provenance")?.encode(env), crate::provenance::provenance().encode(env)),
+        ],
+    )?;
+    Ok((ok(), summary).encode(env))
+}
+
+// ============================================================================
+// Provenance
+// ============================================================================
+
+/// The library's provenance string (see crate::provenance)
+#[rustler::nif]
+pub fn provenance() -> &'static str {
+    crate::provenance::provenance()
+}
+
+// ============================================================================
+// Probe Sequences
+// ============================================================================
+
+/// Generate a known-symbol probe sequence as 8-PSK symbol indices
+///
+/// Kinds and options (keyword list):
+/// * `:capture` - WALE capture probe; `length:` (default 96, at most 96)
+/// * `:fast_wale` - Fast WALE 32-symbol known-data probe
+/// * `:mini_probe` - 110D mini-probe; `length:` (required, a Table D-XXI
+///   length) and `boundary_marker:` (default false)
+#[rustler::nif]
+pub fn probe_symbols<'a>(kind: Atom, opts: Vec<(Atom, Term<'a>)>) -> NifResult<Vec<u8>> {
+    let opt = |name: Atom| opts.iter().find(|(key, _)| *key == name).map(|(_, value)| *value);
+    let length_opt = opt(length())
+        .map(|t| t.decode::<usize>())
+        .transpose()
+        .map_err(|_| PhyError::InvalidArgument("length"))?;
+    let boundary = opt(boundary_marker())
+        .map(|t| t.decode::<bool>())
+        .transpose()
+        .map_err(|_| PhyError::InvalidArgument("boundary_marker"))?
+        .unwrap_or(false);
+
+    let symbols = if kind == capture() {
+        probes::capture_probe(length_opt.unwrap_or(probes::CAPTURE_PROBE.len()))
+    } else if kind == fast_wale() {
+        Some(probes::fast_wale_probe())
+    } else if kind == mini_probe() {
+        let length = length_opt.ok_or(PhyError::InvalidArgument("length"))?;
+        probes::mini_probe(length, boundary)
+    } else {
+        return Err(PhyError::InvalidArgument("probe_kind").into());
+    };
+
+    Ok(symbols.ok_or(PhyError::InvalidArgument("length"))?)
+}
+
+// ============================================================================
+// Waveform Presets
+// ============================================================================
+
+/// A preset's equalizer settings, in waveform_preset/1; takes the keys
+/// unified_demod_enable_eq accepts
+#[derive(NifMap)]
+pub struct DfeConfigMap {
+    pub ff_taps: usize,
+    pub fb_taps: usize,
+    pub mu: f64,
+    pub mu_cma: f64,
+    pub leakage: f64,
+    pub update_threshold: f64,
+    pub cma_to_dd_threshold: f64,
+    pub cma_min_symbols: usize,
+    pub fractional: bool,
+    pub adaptation: Atom,
+    pub lambda: f64,
+}
+
+/// waveform_preset/1 (see waveform::WaveformPreset)
+#[derive(NifMap)]
+pub struct WaveformPresetMap {
+    pub name: Atom,
+    pub wid: u8,
+    pub data_rate_bps: u32,
+    pub constellation: Atom,
+    /// Always :psk8
+    pub probe_constellation: Atom,
+    pub symbol_rate: u32,
+    pub carrier_freq: f64,
+    /// U
+    pub data_symbols: usize,
+    /// K
+    pub probe_symbols: usize,
+    pub probe: Vec<u8>,
+    /// The interleaver-boundary probe
+    pub boundary_probe: Vec<u8>,
+    /// {period, offset, probe}, as unified_demod_set_gain_reference takes
+    pub probe_schedule: (u64, u64, Vec<u8>),
+    pub dfe: DfeConfigMap,
+    pub pll_bandwidth_hz: f64,
+    /// {k, n}
+    pub code_rate: (u32, u32),
+    pub constraint_length: u32,
+    pub interleaver: Atom,
+    pub interleaver_frames: usize,
+    pub interleaver_coded_bits: usize,
+    pub interleaver_input_bits: usize,
+    pub interleaver_increment: usize,
+    /// Raw SER the pair holds to on CCIR moderate at ser_snr_db
+    pub ser_budget: f64,
+    pub ser_snr_db: f64,
+}
+
+/// Look a preset up by its atom name
+fn decode_waveform(env: Env, name: Atom) -> Result<WaveformPreset, PhyError> {
+    let name = name.to_term(env).atom_to_string().map_err(|_| PhyError::InvalidArgument("waveform"))?;
+    WaveformPreset::from_name(&name).ok_or(PhyError::InvalidArgument("waveform"))
+}
+
+/// Everything modem-relevant about a 110D preset, e.g.
+/// :m110d_2400_short, FEC and interleaver geometry included
+#[rustler::nif]
+pub fn waveform_preset(env: Env, name: Atom) -> NifResult<WaveformPresetMap> {
+    let preset = decode_waveform(env, name)?;
+    let schedule = preset.probe_schedule();
+    let dfe = preset.dfe_config();
+    let budget = preset.ser_budget();
+    let atom = |s: &str| Atom::from_str(env, s);
+    Ok(WaveformPresetMap {
+        name,
+        wid: preset.wid,
+        data_rate_bps: preset.data_rate_bps,
+        constellation: constellation_to_atom(preset.constellation),
+        probe_constellation: psk8(),
+        symbol_rate: waveform::SYMBOL_RATE,
+        carrier_freq: waveform::CARRIER_FREQ,
+        data_symbols: preset.data_symbols,
+        probe_symbols: preset.probe_symbols,
+        probe: preset.probe(false),
+        boundary_probe: preset.probe(true),
+        probe_schedule: (schedule.period(), schedule.offset(), preset.probe(false)),
+        dfe: DfeConfigMap {
+            ff_taps: dfe.ff_taps,
+            fb_taps: dfe.fb_taps,
+            mu: dfe.mu,
+            mu_cma: dfe.mu_cma,
+            leakage: dfe.leakage,
+            update_threshold: dfe.update_threshold,
+            cma_to_dd_threshold: dfe.cma_to_dd_threshold,
+            cma_min_symbols: dfe.cma_min_symbols,
+            fractional: dfe.fractional,
+            adaptation: adaptation_to_atom(dfe.adaptation),
+            lambda: dfe.lambda,
+        },
+        pll_bandwidth_hz: preset.pll_bandwidth_hz(),
+        code_rate: preset.code_rate,
+        constraint_length: waveform::CONSTRAINT_LENGTH,
+        interleaver: atom(preset.interleaver.name())?,
+        interleaver_frames: preset.geometry.frames,
+        interleaver_coded_bits: preset.geometry.coded_bits,
+        interleaver_input_bits: preset.geometry.input_bits,
+        interleaver_increment: preset.geometry.increment,
+        ser_budget: budget.ser,
+        ser_snr_db: budget.snr_db,
+    })
+}
+
+/// Modulator for a preset (see WaveformPreset::modulator); probes go
+/// through unified_mod_modulate_mixed as :psk8
+#[rustler::nif]
+pub fn unified_mod_for_waveform(env: Env, name: Atom, sample_rate: u32) -> NifResult<ResourceArc<UnifiedModulatorResource>> {
+    let preset = decode_waveform(env, name)?;
+    check_rates(sample_rate, waveform::SYMBOL_RATE, waveform::CARRIER_FREQ)?;
+    
+    Ok(ResourceArc::new(UnifiedModulatorResource::new(preset.modulator(sample_rate))))
+}
+
+/// Demodulator for a preset, its equalizer trained on every mini-probe
+/// (see WaveformPreset::demodulator); start it at the initial probe
+#[rustler::nif]
+pub fn unified_demod_for_waveform(env: Env, name: Atom, sample_rate: u32) -> NifResult<ResourceArc<UnifiedDemodulatorResource>> {
+    let preset = decode_waveform(env, name)?;
+    check_rates(sample_rate, waveform::SYMBOL_RATE, waveform::CARRIER_FREQ)?;
+    
+    Ok(ResourceArc::new(UnifiedDemodulatorResource::new(preset.demodulator(sample_rate))))
+}
+
+// ============================================================================
+// Walsh-16 Correlation
+// ============================================================================
+
+/// Stateful Walsh-16 correlator resource
+pub struct WalshCorrelatorResource {
+    pub inner: Mutex<WalshCorrelator>,
+    pub tally: Tally,
+}
+
+impl WalshCorrelatorResource {
+    pub fn new(correlator: WalshCorrelator) -> Self {
+        let tally = Tally::new(&kinds::WALSH_CORRELATORS, correlator.memory_bytes());
+        Self { inner: Mutex::new(correlator), tally }
+    }
+
+    /// Lock the correlator (see counted::Counted)
+    pub fn state(&self) -> Counted<'_, WalshCorrelator> {
+        Counted::lock(&self.inner, &self.tally)
+    }
+}
+
+impl Recover for WalshCorrelator {
+    fn recover(&mut self) {
+        self.reset();
+    }
+}
+
+/// One dwell's correlation against the Walsh-16 bank
+#[derive(NifMap)]
+pub struct WalshDwellMap {
+    pub best: usize,
+    pub metric: f64,
+    pub metrics: Vec<f64>,
+}
+
+impl From<WalshDwell> for WalshDwellMap {
+    fn from(dwell: WalshDwell) -> Self {
+        Self { best: dwell.best, metric: dwell.metric, metrics: dwell.metrics.to_vec() }
+    }
+}
+
+/// One 75 bps symbol's correlation against the four Walsh sequences
+#[derive(NifMap)]
+pub struct Walsh75SymbolMap {
+    pub dibit: u8,
+    /// By dibit, 1.0 for a clean symbol of its own
+    pub magnitudes: Vec<f64>,
+}
+
+impl From<Walsh75Symbol> for Walsh75SymbolMap {
+    fn from(symbol: Walsh75Symbol) -> Self {
+        Self { dibit: symbol.dibit, magnitudes: symbol.magnitudes.to_vec() }
+    }
+}
+
+/// Correlate descrambled symbol-rate I/Q against the 141D Walsh-16 bank,
+/// one 64-symbol dwell at a time from the start (see walsh)
+///
+/// `iq` takes the same forms as dfe_train; a partial dwell at the end is
+/// ignored.
+#[rustler::nif]
+pub fn walsh_correlate(iq: Term) -> NifResult<Vec<WalshDwellMap>> {
+    let iq = decode_iq(iq)?;
+
+    Ok(walsh::correlate(&iq).into_iter().map(WalshDwellMap::from).collect())
+}
+
+/// Create a correlator for I/Q arriving in blocks that needn't line up
+/// with dwells
+#[rustler::nif]
+pub fn walsh_correlator_new() -> ResourceArc<WalshCorrelatorResource> {
+    ResourceArc::new(WalshCorrelatorResource::new(WalshCorrelator::new()))
+}
+
+/// Push the next I/Q; returns the dwells it completes
+#[rustler::nif]
+pub fn walsh_correlator_push(
+    correlator: ResourceArc<WalshCorrelatorResource>,
+    iq: Term,
+) -> NifResult<Vec<WalshDwellMap>> {
+    guarded(|| {
+        let iq = decode_iq(iq)?;
+        let mut state = correlator.state();
+
+        Ok(state.push(&iq).into_iter().map(WalshDwellMap::from).collect())
+    })
+}
+
+/// Drop an unfinished dwell; the next push starts a new one
+#[rustler::nif]
+pub fn walsh_correlator_reset(correlator: ResourceArc<WalshCorrelatorResource>) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = correlator.state();
+
+        state.reset();
+        Ok(ok())
+    })
+}
+
+// ============================================================================
+// PRBS Test Patterns
+// ============================================================================
+
+/// The first `count` symbols of a PRBS as a binary, one symbol index per
+/// byte, taking `modulation`'s bits per symbol from the sequence first bit
+/// most significant
+///
+/// `polynomial` is `:prbs9`, `:prbs15` or `:prbs23`; `seed` picks the
+/// starting register (0 is all ones). See prbs.
+#[rustler::nif]
+pub fn prbs_symbols<'a>(
+    env: Env<'a>,
+    modulation: Atom,
+    count: usize,
+    seed: u64,
+    polynomial: Atom,
+) -> NifResult<Binary<'a>> {
+    let constellation = atom_to_constellation(modulation)?;
+    let polynomial = if polynomial == prbs9() {
+        PrbsPolynomial::Prbs9
+    } else if polynomial == prbs15() {
+        PrbsPolynomial::Prbs15
+    } else if polynomial == prbs23() {
+        PrbsPolynomial::Prbs23
+    } else {
+        return Err(PhyError::InvalidArgument("polynomial").into());
+    };
+    check_max("count", count as u64, MAX_PRBS_SYMBOLS as u64)?;
+
+    bytes_binary(env, &prbs::prbs_symbols(constellation, count, seed, polynomial))
+}
+
+// ============================================================================
+// Pulse Shaper (externally generated baseband)
+// ============================================================================
+
+/// Resource wrapper for a standalone pulse shaper
+pub struct PulseShaperResource {
+    pub inner: Mutex<PulseShaper>,
+    pub tally: Tally,
+}
+
+impl PulseShaperResource {
+    pub fn new(shaper: PulseShaper) -> Self {
+        let tally = Tally::new(&kinds::PULSE_SHAPERS, shaper.memory_bytes());
+        Self { inner: Mutex::new(shaper), tally }
+    }
+
+    /// Lock the shaper (see counted::Counted)
+    pub fn state(&self) -> Counted<'_, PulseShaper> {
+        Counted::lock(&self.inner, &self.tally)
+    }
+}
+
+impl Recover for PulseShaper {
+    fn recover(&mut self) {
+        self.reset();
+    }
+}
+
+/// RRC shaper, parameters checked before the taps are designed
+fn pulse_shaper(sps: usize, alpha: f64, span: usize) -> Result<PulseShaper, PhyError> {
+    if sps == 0 {
+        return Err(PhyError::InvalidArgument("sps"));
+    }
+    check_max("sps", sps as u64, MAX_SHAPER_SPS as u64)?;
+    if !(0.0..=1.0).contains(&alpha) {
+        return Err(PhyError::InvalidArgument("alpha"));
+    }
+    if span == 0 {
+        return Err(PhyError::InvalidArgument("span"));
+    }
+    check_max("span", span as u64, MAX_SHAPER_SPAN as u64)?;
+    Ok(PulseShaper::rrc(sps, alpha, span))
+}
+
+/// Create a root raised cosine pulse shaper
+///
+/// Uses the same taps as a unified modulator of the same design (the
+/// modem's own is sps = sample_rate / 2400, alpha = 0.35, span = 6).
+///
+/// # Arguments
+/// * `sps` - Output samples per impulse (1..=64)
+/// * `alpha` - Roll-off, 0.0..=1.0
+/// * `span` - Filter span in symbols each side (1..=32)
+#[rustler::nif]
+pub fn pulse_shaper_new(sps: usize, alpha: f64, span: usize) -> NifResult<ResourceArc<PulseShaperResource>> {
+    Ok(ResourceArc::new(PulseShaperResource::new(pulse_shaper(sps, alpha, span)?)))
+}
+
+/// Upsample impulses by sps and filter them
+///
+/// `impulses` and the result are I/Q binaries (see wire::read_iq); sps
+/// samples come out per impulse. The filter history carries across calls.
+#[rustler::nif]
+pub fn pulse_shaper_process<'a>(
+    env: Env<'a>,
+    shaper: ResourceArc<PulseShaperResource>,
+    impulses: Binary,
+) -> NifResult<Binary<'a>> {
+    guarded(|| {
+        let impulses = iq_from_f32_bytes(impulses.as_slice(), "impulses")?;
+        let shaped = shaper.state().process(&impulses);
+        iq_binary(env, &shaped)
+    })
+}
+
+/// Clock out the filter tail (2 * span * sps samples, an I/Q binary)
+#[rustler::nif]
+pub fn pulse_shaper_flush<'a>(env: Env<'a>, shaper: ResourceArc<PulseShaperResource>) -> NifResult<Binary<'a>> {
+    guarded(|| {
+        let tail = shaper.state().flush();
+        iq_binary(env, &tail)
+    })
+}
+
+/// Clear the filter history
+#[rustler::nif]
+pub fn pulse_shaper_reset(shaper: ResourceArc<PulseShaperResource>) -> NifResult<Atom> {
+    guarded(|| {
+        shaper.state().reset();
+        Ok(ok())
+    })
+}
+
+// ============================================================================
+// Constellation Scope (LiveView display)
+// ============================================================================
+
+/// Resource wrapper for constellation scope (holds the persistence frame)
+pub struct ConstellationScopeResource {
+    pub inner: Mutex<ConstellationScope>,
+    pub tally: Tally,
+}
+
+impl ConstellationScopeResource {
+    pub fn new(scope: ConstellationScope) -> Self {
+        let tally = Tally::new(&kinds::CONSTELLATION_SCOPES, scope.memory_bytes());
+        Self { inner: Mutex::new(scope), tally }
+    }
+
+    /// Lock the scope (see counted::Counted)
+    pub fn state(&self) -> Counted<'_, ConstellationScope> {
+        Counted::lock(&self.inner, &self.tally)
+    }
+}
+
+impl Recover for ConstellationScope {
+    fn recover(&mut self) {
+        self.clear();
+    }
+}
+
+/// Per-frame summary returned alongside the rendered image
+#[derive(NifMap)]
+pub struct ScopeStatsMap {
+    pub points: usize,
+    pub out_of_range: usize,
+    pub peak: f64,
+    pub evm_rms: Option<f64>,
+    pub evm_db: Option<f64>,
+}
+
+fn atom_to_colormap(atom: Atom) -> Result<Colormap, PhyError> {
+    if atom == gray() {
+        Ok(Colormap::Gray)
+    } else if atom == green() {
+        Ok(Colormap::Green)
+    } else if atom == heat() {
+        Ok(Colormap::Heat)
+    } else {
+        Err(PhyError::InvalidArgument("colormap"))
+    }
+}
+
+fn check_scope_params(width: usize, height: usize, full_scale: f64) -> Result<(), PhyError> {
+    if width == 0 || height == 0 {
+        return Err(PhyError::InvalidArgument("dimensions"));
+    }
+    check_max("width", width as u64, MAX_SCOPE_DIM as u64)?;
+    check_max("height", height as u64, MAX_SCOPE_DIM as u64)?;
+    if !(full_scale > 0.0 && full_scale.is_finite()) {
+        return Err(PhyError::InvalidArgument("full_scale"));
+    }
+    Ok(())
+}
+
+/// Scope with checked dimensions
+fn constellation_scope(width: usize, height: usize, full_scale: f64) -> Result<ConstellationScope, PhyError> {
+    check_scope_params(width, height, full_scale)?;
+    Ok(ConstellationScope::with_full_scale(width, height, full_scale))
+}
+
+
+/// Create a constellation scope
+///
+/// # Arguments
+/// * `width`, `height` - Image size in pixels (1..=2048)
+/// * `full_scale` - I/Q magnitude mapped to the image edges (default 1.5)
+#[rustler::nif]
+pub fn constellation_scope_new(
+    width: usize,
+    height: usize,
+    full_scale: Option<f64>,
+) -> NifResult<ResourceArc<ConstellationScopeResource>> {
+    let full_scale = full_scale.unwrap_or(crate::scope::DEFAULT_FULL_SCALE);
+    Ok(ResourceArc::new(ConstellationScopeResource::new(constellation_scope(width, height, full_scale)?)))
+}
+
+/// Rasterize I/Q points into the scope and render the blended frame
+///
+/// # Arguments
+/// * `iq` - I/Q binary (see wire::read_iq)
+/// * `persistence` - Decay applied to the previous frame, 0.0..=1.0
+/// * `colormap` - :gray (1 byte/pixel) or :green / :heat (RGBA)
+/// * `constellation` - Constellation atom for EVM, or nil to skip
+///
+/// Returns `{:ok, image, stats}` with the image row-major, top row first.
+#[rustler::nif]
+pub fn render_constellation<'a>(
+    env: Env<'a>,
+    scope: ResourceArc<ConstellationScopeResource>,
+    iq: Binary,
+    persistence: f64,
+    colormap: Atom,
+    constellation: Option<Atom>,
+) -> NifResult<(Atom, Binary<'a>, ScopeStatsMap)> {
+    guarded(|| {
+        let points = iq_from_f32_bytes(iq.as_slice(), "iq")?;
+        if !(0.0..=1.0).contains(&persistence) {
+            return Err(PhyError::InvalidArgument("persistence").into());
+        }
+
+        let colormap = atom_to_colormap(colormap)?;
+        let constellation = constellation
+            .map(atom_to_constellation)
+            .transpose()?;
+
+        let mut state = scope.state();
+
+        let stats = state.accumulate(&points, persistence as f32);
+        let image = state.render(colormap);
+        let evm = constellation.and_then(|ct| evm_rms(&points, ct));
+
+        let mut owned = OwnedBinary::new(image.len())
+            .ok_or(PhyError::AllocFailed)?;
+        owned.as_mut_slice().copy_from_slice(&image);
+
+        Ok((
+            ok(),
+            owned.release(env),
+            ScopeStatsMap {
+                points: stats.points,
+                out_of_range: stats.out_of_range,
+                peak: stats.peak as f64,
+                evm_rms: evm,
+                evm_db: evm.map(|e| 20.0 * e.max(1e-12).log10()),
+            },
+        ))
+    })
+}
+
+/// Clear the scope's persistence buffer
+#[rustler::nif]
+pub fn constellation_scope_clear(scope: ResourceArc<ConstellationScopeResource>) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = scope.state();
+        state.clear();
+        Ok(ok())
+    })
+}
+
+// ============================================================================
+// Resource census (leak checks on long runs)
+// ============================================================================
+
+/// Every resource kind's counts, keyed by kind (see crate::census)
+#[rustler::nif]
+pub fn census(env: Env) -> NifResult<Term> {
+    let mut map = Term::map_new(env);
+    for kind in kinds::KINDS {
+        let key = Atom::from_str(env, kind.name())?;
+        map = map.map_put(key, CensusMap::from(kind.count()).encode(env))?;
+    }
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A few thousand of each kind, made and dropped on this thread
+    #[test]
+    fn test_census_returns_to_zero() {
+        const N: usize = 2000;
+        let generic = || {
+            let timing = FixedTiming::new(9600, 2400);
+            let pulse = RootRaisedCosine::default_for_sps(timing.samples_per_symbol());
+            let carrier = Nco::new(1800.0, 9600);
+            let m: Box<dyn ModulatorTrait> = Box::new(Modulator::new(Psk8, pulse.clone(), carrier.clone(), timing));
+            let d: Box<dyn DemodulatorTrait> = Box::new(Demodulator::new(Psk8, pulse, carrier, timing));
+            (ModulatorResource::new(m), DemodulatorResource::new(d))
+        };
+        let resources: Vec<_> = (0..N)
+            .map(|_| {
+                (
+                    generic(),
+                    UnifiedModulatorResource::new(UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0)),
+                    UnifiedDemodulatorResource::new(UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0)),
+                    DFEResource::new(DFE::new(DFEConfig::default(), ConstellationType::Psk8)),
+                    WalshCorrelatorResource::new(WalshCorrelator::new()),
+                    PulseShaperResource::new(pulse_shaper(4, 0.35, 6).unwrap()),
+                    ConstellationScopeResource::new(constellation_scope(64, 64, 1.5).unwrap()),
+                )
+            })
+            .collect();
+        for kind in &kinds::KINDS[..8] {
+            let count = kind.this_thread();
+            assert_eq!(count.live, N as i64, "{}", kind.name());
+            assert!(count.bytes > 0, "{}", kind.name());
+        }
+
+        drop(resources);
+        for kind in kinds::KINDS {
+            assert_eq!(kind.this_thread(), kinds::ThreadCount::default(), "{}", kind.name());
+        }
+    }
+
+    #[test]
+    fn test_census_follows_buffers_grown_by_use() {
+        let modulator = UnifiedModulatorResource::new(UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0));
+        let idle = modulator.tally.bytes();
+        modulator.state().push_symbols(&[0; 10_000]);
+        assert!(modulator.tally.bytes() >= idle + 10_000 * std::mem::size_of::<(f64, f64)>());
+        assert_eq!(kinds::UNIFIED_MODULATORS.this_thread().bytes, modulator.tally.bytes() as i64);
+
+        // The queue is let go at the end of the burst
+        modulator.state().end_burst();
+        assert_eq!(modulator.tally.bytes(), idle);
+    }
+
+    /// Enable, record, drain, disable, a thousand times over: nothing
+    /// outlives disable_capture
+    #[test]
+    fn test_capture_cycles_dont_leak() {
+        let demodulator = UnifiedDemodulatorResource::new(UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0));
+        let samples = vec![1000i16; 600];
+        demodulator.state().demodulate(&samples);
+        let idle = demodulator.tally.bytes();
+        let buffer = 4096 * std::mem::size_of::<(f32, f32)>();
+
+        for _ in 0..1000 {
+            enable_capture(&mut demodulator.state(), 2, 4096).unwrap();
+            assert_eq!(kinds::CAPTURE_BUFFERS.this_thread(), kinds::ThreadCount { live: 1, bytes: buffer as i64 });
+            assert_eq!(demodulator.tally.bytes(), idle + buffer);
+
+            let mut state = demodulator.state();
+            state.demodulate(&samples);
+            state.keep_capture();
+            assert_eq!(state.drain_capture().unwrap().iq.len(), samples.len() / 2);
+            state.demodulate(&samples);
+            state.discard_capture();
+            drop(state);
+            // Draining and discarding reuse the buffer
+            assert_eq!(demodulator.tally.bytes(), idle + buffer);
+
+            demodulator.state().disable_capture();
+            assert_eq!(kinds::CAPTURE_BUFFERS.this_thread(), kinds::ThreadCount::default());
+            assert_eq!(demodulator.tally.bytes(), idle);
+        }
+
+        // Dropping a demodulator mid-capture frees its buffer too
+        enable_capture(&mut demodulator.state(), 1, 4096).unwrap();
+        drop(demodulator);
+        assert_eq!(kinds::CAPTURE_BUFFERS.this_thread(), kinds::ThreadCount::default());
+        assert_eq!(kinds::UNIFIED_DEMODULATORS.this_thread(), kinds::ThreadCount::default());
+    }
+
+    /// unified_demod_export_state's path: exporting holds nothing on the
+    /// resource, however often it's done (there's no import to pair it
+    /// with; a snapshot is only ever read back by comparing exports)
+    #[test]
+    fn test_state_exports_dont_leak() {
+        let demodulator = UnifiedDemodulatorResource::new(UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0));
+        enable_capture(&mut demodulator.state(), 1, 1024).unwrap();
+        demodulator.state().demodulate(&vec![1000i16; 960]);
+        let held = demodulator.tally.bytes();
+
+        let first = demodulator.state().export_state();
+        for _ in 0..5000 {
+            let snapshot = demodulator.state().export_state();
+            assert_eq!(snapshot.len(), first.len());
+        }
+        assert_eq!(demodulator.tally.bytes(), held);
+        assert_eq!(kinds::CAPTURE_BUFFERS.this_thread().live, 1);
+
+        drop(demodulator);
+        assert_eq!(kinds::UNIFIED_DEMODULATORS.this_thread(), kinds::ThreadCount::default());
+        assert_eq!(kinds::CAPTURE_BUFFERS.this_thread(), kinds::ThreadCount::default());
+    }
+
+    #[test]
+    fn test_rate_errors() {
+        assert_eq!(check_rates(9600, 0, 1800.0), Err(PhyError::InvalidArgument("symbol_rate")));
+        assert_eq!(check_rates(2400, 2400, 1800.0), Err(PhyError::InvalidArgument("sample_rate")));
+        assert_eq!(check_rates(9600, 2400, 4800.0), Err(PhyError::InvalidArgument("carrier_freq")));
+        assert_eq!(check_rates(9600, 2400, f64::NAN), Err(PhyError::InvalidArgument("carrier_freq")));
+        assert_eq!(check_rates(8000, 2400, 1800.0), Ok(()));
+
+        // FixedTiming would assert on this one
+        assert_eq!(check_fixed_rates(8000, 2400, 1800.0), Err(PhyError::InvalidArgument("sample_rate")));
+        assert_eq!(check_fixed_rates(9600, 2400, 1800.0), Ok(()));
+    }
+
+    #[test]
+    fn test_eq_config_errors() {
+        assert_eq!(eq_config(0, 5, 0.01).err(), Some(PhyError::InvalidArgument("ff_taps")));
+        assert_eq!(eq_config(11, 5, 0.0).err(), Some(PhyError::InvalidArgument("mu")));
+        assert_eq!(eq_config(11, 5, f64::INFINITY).err(), Some(PhyError::InvalidArgument("mu")));
+        assert!(eq_config(11, 5, 0.01).is_ok());
+    }
+
+    #[test]
+    fn test_diverged_mse_is_a_numeric_fault() {
+        assert_eq!(check_mse(0.05), Ok(0.05));
+        assert_eq!(check_mse(f64::NAN), Err(PhyError::NumericFault("equalizer_diverged")));
+    }
+
+    #[test]
+    fn test_rx_filter_errors() {
+        assert_eq!(biquad_cascade(&[]).err(), Some(PhyError::InvalidArgument("rx_filter")));
+        // Pole at z = 1.1
+        let unstable = [(1.0, 0.0, 0.0, -1.1, 0.0)];
+        assert_eq!(biquad_cascade(&unstable).err(), Some(PhyError::InvalidArgument("rx_filter")));
+        assert!(biquad_cascade(&[(1.0, 0.0, 0.0, -0.5, 0.0)]).is_ok());
+    }
+
+    #[test]
+    fn test_scope_errors() {
+        assert_eq!(check_scope_params(0, 64, 1.5), Err(PhyError::InvalidArgument("dimensions")));
+        assert_eq!(
+            check_scope_params(64, MAX_SCOPE_DIM + 1, 1.5),
+            Err(PhyError::OutOfRange("height", MAX_SCOPE_DIM as u64))
+        );
+        assert_eq!(check_scope_params(64, 64, 0.0), Err(PhyError::InvalidArgument("full_scale")));
+        assert_eq!(check_scope_params(64, 64, 1.5), Ok(()));
+
+        assert_eq!(iq_from_f32_bytes(&[0; 12], "iq"), Err(PhyError::InvalidArgument("iq")));
+        let one = [1.0f32.to_le_bytes(), (-0.5f32).to_le_bytes()].concat();
+        let bare = iq_from_f32_bytes(&one, "iq");
+        if wire::LEGACY == wire::Legacy::Accept {
+            assert_eq!(bare, Ok(vec![(1.0, -0.5)]));
+        } else {
+            assert_eq!(bare, Err(PhyError::InvalidArgument("iq")));
+        }
+    }
+
+    #[test]
+    fn test_pulse_shaper_errors() {
+        assert!(pulse_shaper(8, 0.35, 6).is_ok());
+        assert_eq!(pulse_shaper(0, 0.35, 6).err(), Some(PhyError::InvalidArgument("sps")));
+        assert_eq!(pulse_shaper(MAX_SHAPER_SPS + 1, 0.35, 6).err(), Some(PhyError::OutOfRange("sps", MAX_SHAPER_SPS as u64)));
+        assert_eq!(pulse_shaper(8, 1.5, 6).err(), Some(PhyError::InvalidArgument("alpha")));
+        assert_eq!(pulse_shaper(8, f64::NAN, 6).err(), Some(PhyError::InvalidArgument("alpha")));
+        assert_eq!(pulse_shaper(8, 0.35, 0).err(), Some(PhyError::InvalidArgument("span")));
+        assert_eq!(iq_from_f32_bytes(&[0; 4], "impulses"), Err(PhyError::InvalidArgument("impulses")));
+    }
+
+    #[test]
+    fn test_mf_output_args() {
+        assert_eq!(check_oversample(2, 4), Ok(()));
+        assert_eq!(check_oversample(4, 20), Ok(()));
+        assert_eq!(check_oversample(3, 6), Err(PhyError::InvalidArgument("oversample")));
+        assert_eq!(check_oversample(0, 4), Err(PhyError::InvalidArgument("oversample")));
+        // 8000 Hz: sps 3
+        assert_eq!(check_oversample(2, 3), Err(PhyError::InvalidArgument("oversample")));
+
+        let bytes = wire::encode_iq_f32(&[(1.0, -0.5), (0.25, 2.0)]);
+        assert_eq!(iq_from_f32_bytes(&bytes, "iq"), Ok(vec![(1.0, -0.5), (0.25, 2.0)]));
+        assert_eq!(iq_from_f32_bytes(&bytes[..bytes.len() - 8], "iq"), Err(PhyError::InvalidArgument("iq")));
+    }
+}
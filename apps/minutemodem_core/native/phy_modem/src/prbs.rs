@@ -0,0 +1,221 @@
+//! Pseudo-random binary sequence (PRBS) symbol source
+//!
+//! BER tests need a symbol stream both ends can regenerate from a few
+//! parameters and whose statistics are known: every bit pattern up to the
+//! register length turns up equally often, so the modulated spectrum is
+//! the pulse's own. The generators are the ITU-T O.150 polynomials as
+//! Fibonacci LFSRs whose last stage is the output, so the first `n` bits
+//! out are the starting state, and from the all-ones state PRBS9 is the
+//! familiar FF 83 DF 17 ... PN9 stream. O.150's inversion of the PRBS15
+//! and PRBS23 outputs is not applied.
+//!
+//! Symbols take the next bits_per_symbol() bits, first bit most
+//! significant, as the constellation's symbol index: the same bit mapping
+//! the modem uses everywhere else.
+//!
+//! A trinomial x^n + x^m + 1 makes bit k + n the XOR of bits k and
+//! k + n - m. When bits_per_symbol() divides n, that ties each symbol to
+//! the one n / bits_per_symbol() before it, and the modulated spectrum
+//! ripples by several dB: avoid PRBS15 for 8-PSK and 32-QAM (3 and 5 bits)
+//! and PRBS9 for 8-PSK where spectral shape matters.
+
+use crate::modem::ConstellationType;
+
+/// LFSR feedback polynomial
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrbsPolynomial {
+    /// x^9 + x^5 + 1, period 511
+    Prbs9,
+    /// x^15 + x^14 + 1, period 32767
+    Prbs15,
+    /// x^23 + x^18 + 1, period 8388607
+    Prbs23,
+}
+
+impl PrbsPolynomial {
+    /// Register length
+    pub fn degree(&self) -> u32 {
+        match self {
+            Self::Prbs9 => 9,
+            Self::Prbs15 => 15,
+            Self::Prbs23 => 23,
+        }
+    }
+
+    /// The polynomial's middle term
+    fn tap(&self) -> u32 {
+        match self {
+            Self::Prbs9 => 5,
+            Self::Prbs15 => 14,
+            Self::Prbs23 => 18,
+        }
+    }
+
+    /// Bits before the sequence repeats
+    pub fn period(&self) -> u64 {
+        (1 << self.degree()) - 1
+    }
+}
+
+/// PRBS bit and symbol generator
+///
+/// Symbol k of a seed is the same however the stream is chunked.
+#[derive(Debug, Clone)]
+pub struct Prbs {
+    polynomial: PrbsPolynomial,
+    state: u32,
+}
+
+impl Prbs {
+    /// Start from `seed`'s low degree() bits; zero, which would lock the
+    /// register, starts from all ones (the O.150 starting state)
+    pub fn new(polynomial: PrbsPolynomial, seed: u64) -> Self {
+        let mask = polynomial.period() as u32;
+        let state = match seed as u32 & mask {
+            0 => mask,
+            state => state,
+        };
+        Self { polynomial, state }
+    }
+
+    pub fn next_bit(&mut self) -> u8 {
+        let n = self.polynomial.degree();
+        let out = (self.state >> (n - 1)) & 1;
+        let feedback = out ^ ((self.state >> (self.polynomial.tap() - 1)) & 1);
+        self.state = ((self.state << 1) | feedback) & self.polynomial.period() as u32;
+        out as u8
+    }
+
+    /// The next `bits` bits as an index, first bit most significant
+    pub fn next_symbol(&mut self, bits: usize) -> u8 {
+        (0..bits).fold(0, |symbol, _| (symbol << 1) | self.next_bit())
+    }
+}
+
+/// The first `count` symbols of the `polynomial` PRBS from `seed`
+pub fn prbs_symbols(constellation: ConstellationType, count: usize, seed: u64, polynomial: PrbsPolynomial) -> Vec<u8> {
+    let bits = constellation.bits_per_symbol();
+    let mut prbs = Prbs::new(polynomial, seed);
+    (0..count).map(|_| prbs.next_symbol(bits)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modem::UnifiedModulator;
+    use std::f64::consts::PI;
+
+    const ALL: [PrbsPolynomial; 3] = [PrbsPolynomial::Prbs9, PrbsPolynomial::Prbs15, PrbsPolynomial::Prbs23];
+
+    fn first_bytes(polynomial: PrbsPolynomial) -> Vec<u8> {
+        let mut prbs = Prbs::new(polynomial, 0);
+        (0..8).map(|_| prbs.next_symbol(8)).collect()
+    }
+
+    #[test]
+    fn test_known_answers() {
+        // The published PN9 whitening sequence
+        assert_eq!(first_bytes(PrbsPolynomial::Prbs9), [0xff, 0x83, 0xdf, 0x17, 0x32, 0x09, 0x4e, 0xd1]);
+        assert_eq!(first_bytes(PrbsPolynomial::Prbs15), [0xff, 0xfe, 0x00, 0x04, 0x00, 0x18, 0x00, 0x50]);
+        assert_eq!(first_bytes(PrbsPolynomial::Prbs23), [0xff, 0xff, 0xfe, 0x00, 0x00, 0x7c, 0x00, 0x1f]);
+    }
+
+    #[test]
+    fn test_follows_polynomial_recurrence() {
+        for polynomial in ALL {
+            let (n, m) = (polynomial.degree() as usize, polynomial.tap() as usize);
+            let mut prbs = Prbs::new(polynomial, 0x1234_5678);
+            let bits: Vec<u8> = (0..4 * n).map(|_| prbs.next_bit()).collect();
+            for k in n..bits.len() {
+                assert_eq!(bits[k], bits[k - n] ^ bits[k - m], "{:?} bit {}", polynomial, k);
+            }
+        }
+    }
+
+    #[test]
+    fn test_maximal_length() {
+        for polynomial in [PrbsPolynomial::Prbs9, PrbsPolynomial::Prbs15] {
+            let period = polynomial.period() as usize;
+            let mut prbs = Prbs::new(polynomial, 1);
+            let bits: Vec<u8> = (0..2 * period).map(|_| prbs.next_bit()).collect();
+            assert_eq!(bits[..period], bits[period..]);
+            // An m-sequence has one more one than zeros
+            let ones = bits[..period].iter().filter(|&&b| b == 1).count();
+            assert_eq!(ones, period.div_ceil(2));
+            // ...and repeats nowhere sooner
+            assert!((1..period).all(|p| bits[p..p + 64] != bits[..64]));
+        }
+    }
+
+    #[test]
+    fn test_symbols_take_bits_msb_first() {
+        let mut prbs = Prbs::new(PrbsPolynomial::Prbs9, 0);
+        let bits: Vec<u8> = (0..36).map(|_| prbs.next_bit()).collect();
+        let symbols = prbs_symbols(ConstellationType::Psk8, 12, 0, PrbsPolynomial::Prbs9);
+        for (k, &symbol) in symbols.iter().enumerate() {
+            assert_eq!(symbol, bits[3 * k] << 2 | bits[3 * k + 1] << 1 | bits[3 * k + 2]);
+        }
+        assert_eq!(symbols, [7, 7, 7, 0, 1, 7, 3, 7, 0, 5, 6, 3]);
+
+        // Chunking doesn't matter, and every symbol is in range
+        let mut prbs = Prbs::new(PrbsPolynomial::Prbs15, 99);
+        let chunked: Vec<u8> = (0..1000).map(|_| prbs.next_symbol(5)).collect();
+        assert_eq!(chunked, prbs_symbols(ConstellationType::Qam32, 1000, 99, PrbsPolynomial::Prbs15));
+        assert!(chunked.iter().all(|&s| s < 32));
+    }
+
+    #[test]
+    fn test_seed_selects_start_state() {
+        let a = prbs_symbols(ConstellationType::Qpsk, 100, 5, PrbsPolynomial::Prbs23);
+        assert_eq!(a, prbs_symbols(ConstellationType::Qpsk, 100, 5, PrbsPolynomial::Prbs23));
+        assert_ne!(a, prbs_symbols(ConstellationType::Qpsk, 100, 6, PrbsPolynomial::Prbs23));
+        // Zero can't start the register; it means all ones
+        assert_eq!(
+            prbs_symbols(ConstellationType::Bpsk, 50, 0, PrbsPolynomial::Prbs9),
+            prbs_symbols(ConstellationType::Bpsk, 50, 511, PrbsPolynomial::Prbs9)
+        );
+    }
+
+    /// Welch power spectrum (Hann, 256-sample segments) at `freq` Hz
+    fn psd_at(samples: &[f64], freq: f64, sample_rate: f64) -> f64 {
+        let segments = samples.chunks_exact(256);
+        let count = segments.len() as f64;
+        segments
+            .map(|segment| {
+                let (re, im) = segment.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, &x)| {
+                    let w = 0.5 - 0.5 * (2.0 * PI * n as f64 / 256.0).cos();
+                    let phase = 2.0 * PI * freq * n as f64 / sample_rate;
+                    (re + w * x * phase.cos(), im - w * x * phase.sin())
+                });
+                re * re + im * im
+            })
+            .sum::<f64>()
+            / count
+    }
+
+    /// Power at 1300..2300 Hz of `symbols` modulated at 2400 baud on an
+    /// 1800 Hz carrier, dB from the mean
+    fn passband_ripple_db(constellation: ConstellationType, symbols: &[u8]) -> Vec<f64> {
+        let mut modulator = UnifiedModulator::new(constellation, 9600, 2400, 1800.0);
+        let samples: Vec<f64> = modulator.modulate(symbols).into_iter().map(f64::from).collect();
+        let levels: Vec<f64> = (0..=20).map(|k| psd_at(&samples, 1300.0 + 50.0 * k as f64, 9600.0)).collect();
+        let mean = levels.iter().sum::<f64>() / levels.len() as f64;
+        levels.iter().map(|level| 10.0 * (level / mean).log10()).collect()
+    }
+
+    #[test]
+    fn test_modulated_spectrum_is_flat() {
+        // Across the RRC passband (1800 ± 780 Hz, less the Welch window's
+        // smearing at the edges) the spectrum of PRBS data is the pulse's,
+        // flat within the estimate's scatter
+        for (constellation, polynomial) in [
+            (ConstellationType::Psk8, PrbsPolynomial::Prbs23),
+            (ConstellationType::Qpsk, PrbsPolynomial::Prbs15),
+        ] {
+            let symbols = prbs_symbols(constellation, 20_000, 1, polynomial);
+            for (k, db) in passband_ripple_db(constellation, &symbols).iter().enumerate() {
+                assert!(db.abs() < 1.0, "{:?}: {} Hz at {:.2} dB from the mean", polynomial, 1300 + 50 * k, db);
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Known-symbol (probe) sequences
+//!
+//! Generates the 188-110D / 188-141D probe sequences as 8-PSK symbol
+//! indices (0-7, 45° per step), ready for `set_training_symbols` or
+//! `modulate`, so tests and Elixir code don't carry hand-typed copies.
+//!
+//! - Mini-probes (110D Appendix D): a base sequence from Table D-XXI,
+//!   cyclically extended to the probe length and optionally started at the
+//!   table's cyclic shift to mark an interleaver boundary. The 16-symbol
+//!   base is a Frank–Heimiller sequence and is generated; the binary bases
+//!   (13, 19, 25, 36) have no closed form and are tabulated.
+//! - Fast WALE probe (141D G.5.1.8.3.1): the 16-symbol Frank sequence,
+//!   sent twice.
+//! - WALE capture probe (141D Appendix G): 96 BPSK chips. No short LFSR
+//!   produces it (its linear complexity is 48 over the 96 chips), so it is
+//!   tabulated here once.
+//!
+//! Mini-probe bases whose phases are not multiples of 45° (the 7-phase
+//! 49-symbol Frank base) or that are not tabulated yet return None.
+
+/// 141D Appendix G WALE capture probe (BPSK chips as 8-PSK symbols 0/4)
+pub const CAPTURE_PROBE: [u8; 96] = [
+    0, 4, 0, 0, 4, 0, 4, 4, 0, 0, 4, 4, 4, 0, 0, 4,
+    4, 4, 0, 4, 0, 0, 0, 4, 0, 4, 0, 4, 4, 0, 4, 0,
+    0, 0, 0, 4, 4, 4, 4, 0, 0, 4, 0, 4, 0, 4, 4, 4,
+    0, 4, 4, 0, 0, 0, 4, 0, 4, 4, 4, 0, 4, 0, 0, 4,
+    4, 0, 4, 4, 0, 4, 0, 4, 0, 0, 0, 4, 4, 0, 0, 4,
+    0, 4, 0, 0, 4, 4, 0, 4, 4, 0, 4, 0, 4, 4, 0, 0,
+];
+
+/// Binary mini-probe bases (110D Appendix D), chip 1 = -1 (symbol 4)
+const BASE_13: [u8; 13] = [0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0];
+const BASE_19: [u8; 19] = [0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 0, 1, 1, 1, 0, 0, 1, 0];
+const BASE_25: [u8; 25] = [
+    0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 0, 0, 1, 0, 1, 1, 1, 0, 1, 1, 0, 0, 0, 1,
+];
+const BASE_36: [u8; 36] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 1, 1, 0, 0, 1, 1,
+    1, 0, 0, 1, 0, 1, 1, 0, 1, 1, 0, 0, 0, 0, 1, 0, 1, 0,
+];
+
+/// Table D-XXI: probe length → (base length, boundary cyclic shift)
+const MINI_PROBE_PARAMS: [(usize, usize, usize); 20] = [
+    (24, 13, 6),
+    (32, 16, 8),
+    (36, 19, 9),
+    (48, 25, 12),
+    (68, 36, 18),
+    (72, 36, 18),
+    (96, 49, 24),
+    (128, 64, 32),
+    (144, 81, 40),
+    (160, 81, 40),
+    (180, 100, 50),
+    (192, 100, 50),
+    (216, 121, 60),
+    (224, 121, 60),
+    (240, 121, 60),
+    (272, 144, 72),
+    (320, 169, 85),
+    (384, 196, 98),
+    (512, 256, 128),
+    (576, 289, 145),
+];
+
+/// The first `len` chips of the WALE capture probe (None past 96)
+///
+/// The 32-chip prefix is what the demodulator tests train on.
+pub fn capture_probe(len: usize) -> Option<Vec<u8>> {
+    CAPTURE_PROBE.get(..len).map(<[u8]>::to_vec)
+}
+
+/// Frank–Heimiller sequence of length n², as 8-PSK symbols
+///
+/// Element (i, k) has phase 2π·i·k/n, or its negative with `conjugate`.
+/// None unless n divides 8, i.e. unless every phase is on the 8-PSK grid.
+pub fn frank(n: usize, conjugate: bool) -> Option<Vec<u8>> {
+    if n == 0 || 8 % n != 0 {
+        return None;
+    }
+    let step = 8 / n;
+    Some(
+        (0..n)
+            .flat_map(|i| (0..n).map(move |k| i * k % n))
+            .map(|p| {
+                let sym = (p * step) as u8;
+                if conjugate { (8 - sym) % 8 } else { sym }
+            })
+            .collect(),
+    )
+}
+
+/// Fast WALE known-data probe (141D G.5.1.8.3.1): Frank-16, sent twice
+pub fn fast_wale_probe() -> Vec<u8> {
+    let base = frank(4, false).expect("n = 4 is on the 8-PSK grid");
+    base.repeat(2)
+}
+
+/// 110D mini-probe of `length` symbols
+///
+/// With `boundary_marker`, the cyclic extension starts at the base
+/// sequence's Table D-XXI shift instead of its first symbol. None for a
+/// length not in Table D-XXI or a base not representable/tabulated here.
+pub fn mini_probe(length: usize, boundary_marker: bool) -> Option<Vec<u8>> {
+    let &(_, base_len, shift) = MINI_PROBE_PARAMS.iter().find(|(len, _, _)| *len == length)?;
+    let base = mini_probe_base(base_len)?;
+    let start = if boundary_marker { shift } else { 0 };
+    Some(base.iter().cycle().skip(start).take(length).copied().collect())
+}
+
+fn mini_probe_base(base_len: usize) -> Option<Vec<u8>> {
+    let binary = |chips: &[u8]| chips.iter().map(|&c| c * 4).collect();
+    match base_len {
+        13 => Some(binary(&BASE_13)),
+        16 => frank(4, true),
+        19 => Some(binary(&BASE_19)),
+        25 => Some(binary(&BASE_25)),
+        36 => Some(binary(&BASE_36)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_probe_prefix() {
+        // The 32-chip prefix previously hard-coded in the demodulator tests
+        let expected: [u8; 32] = [
+            0, 4, 0, 0, 4, 0, 4, 4, 0, 0, 4, 4, 4, 0, 0, 4,
+            4, 4, 0, 4, 0, 0, 0, 4, 0, 4, 0, 4, 4, 0, 4, 0,
+        ];
+        assert_eq!(capture_probe(32).unwrap(), expected);
+        assert_eq!(capture_probe(96).unwrap(), CAPTURE_PROBE);
+        assert!(capture_probe(97).is_none());
+    }
+
+    #[test]
+    fn test_fast_wale_probe_known_answer() {
+        // 141D G.5.1.8.3.1
+        let base = [0, 0, 0, 0, 0, 2, 4, 6, 0, 4, 0, 4, 0, 6, 4, 2];
+        let probe = fast_wale_probe();
+        assert_eq!(probe.len(), 32);
+        assert_eq!(&probe[..16], base);
+        assert_eq!(&probe[16..], base);
+    }
+
+    #[test]
+    fn test_mini_probe_32_known_answer() {
+        // 16-symbol base: 1 1 1 1 / 1 -j -1 j / 1 -1 1 -1 / 1 j -1 -j
+        let base = [0, 0, 0, 0, 0, 6, 4, 2, 0, 4, 0, 4, 0, 2, 4, 6];
+        let probe = mini_probe(32, false).unwrap();
+        assert_eq!(&probe[..16], base);
+        assert_eq!(&probe[16..], base);
+
+        // Boundary marker: start 8 symbols into the base
+        let marked = mini_probe(32, true).unwrap();
+        assert_eq!(&marked[..8], &base[8..]);
+        assert_eq!(&marked[8..24], base);
+    }
+
+    #[test]
+    fn test_mini_probe_24_known_answer() {
+        // 13-symbol base: + + + + + - - + + - - + +
+        let expected = [
+            0, 0, 0, 0, 0, 4, 4, 0, 0, 4, 4, 0, 0,
+            0, 0, 0, 0, 0, 4, 4, 0, 0, 4, 4,
+        ];
+        assert_eq!(mini_probe(24, false).unwrap(), expected);
+        assert_eq!(mini_probe(24, true).unwrap()[0], 4);
+    }
+
+    #[test]
+    fn test_mini_probe_lengths() {
+        for (len, _, _) in MINI_PROBE_PARAMS {
+            if let Some(probe) = mini_probe(len, false) {
+                assert_eq!(probe.len(), len);
+                assert!(probe.iter().all(|&s| s < 8));
+            }
+        }
+        for len in [24, 32, 36, 48, 68, 72] {
+            assert!(mini_probe(len, false).is_some(), "length {}", len);
+        }
+        // 7-phase base is off the 8-PSK grid; 25 is not in Table D-XXI
+        assert!(mini_probe(96, false).is_none());
+        assert!(mini_probe(25, false).is_none());
+    }
+
+    #[test]
+    fn test_frank_properties() {
+        assert!(frank(3, false).is_none());
+        assert_eq!(frank(2, false).unwrap(), [0, 0, 0, 4]);
+
+        // Perfect periodic autocorrelation: zero at every nonzero shift
+        let seq = frank(8, false).unwrap();
+        let n = seq.len();
+        let phase = |s: u8| std::f64::consts::FRAC_PI_4 * s as f64;
+        for shift in 1..n {
+            let (re, im) = (0..n).fold((0.0, 0.0), |(re, im), k| {
+                let d = phase(seq[k]) - phase(seq[(k + shift) % n]);
+                (re + d.cos(), im + d.sin())
+            });
+            assert!(re.hypot(im) < 1e-9, "shift {}: {}", shift, re.hypot(im));
+        }
+    }
+}
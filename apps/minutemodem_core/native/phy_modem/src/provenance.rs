@@ -0,0 +1,75 @@
+//! This crate's provenance string (see minutemodem_dsp::provenance)
+//!
+//! Carried by every decode report (map and CBOR) and capture drain, and
+//! returned by the provenance NIF. The tables hashed are the constellation
+//! points and the RC/RRC pulses at 4 samples per symbol, the ones an edit
+//! would silently change every artifact through.
+
+use std::sync::OnceLock;
+
+use minutemodem_dsp::Provenance;
+
+use crate::modem::{ConstellationType, Pulse};
+
+/// Samples per symbol the pulse tables are hashed at (9600 Hz, 2400 Bd)
+const TABLE_SPS: usize = 4;
+
+/// The algorithm tables the plate hashes, by name
+fn tables() -> Vec<(String, Vec<f64>)> {
+    let mut tables: Vec<(String, Vec<f64>)> = [2, 4, 8, 16, 32, 64]
+        .into_iter()
+        .filter_map(ConstellationType::from_order)
+        .map(|c| {
+            let points = c.points().into_iter().flat_map(|(i, q)| [i, q]).collect();
+            (format!("constellation/{}", c.order()), points)
+        })
+        .collect();
+    tables.push(("pulse/rrc".into(), Pulse::Rrc.tx_coeffs(TABLE_SPS)));
+    tables.push(("pulse/rc".into(), Pulse::Rc.tx_coeffs(TABLE_SPS)));
+    tables
+}
+
+/// The plate with `tables` hashed in
+fn plate(tables: &[(String, Vec<f64>)]) -> String {
+    let build = Provenance::new(
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        env!("MINUTEMODEM_GIT_DESCRIBE"),
+        env!("MINUTEMODEM_BUILD_PROFILE"),
+    )
+    .feature("nif", cfg!(feature = "nif"))
+    .feature("legacy-wire", cfg!(feature = "legacy-wire"))
+    .feature("capi", cfg!(feature = "capi"))
+    .feature("alloc-audit", cfg!(feature = "alloc-audit"));
+    tables.iter().fold(build, |p, (name, values)| p.table(name, values)).build()
+}
+
+/// The provenance string, built on first use
+pub fn provenance() -> &'static str {
+    static PLATE: OnceLock<String> = OnceLock::new();
+    PLATE.get_or_init(|| plate(&tables()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plate_is_stable_within_a_process() {
+        let plate = provenance();
+        assert!(plate.starts_with(concat!("phy_modem/", env!("CARGO_PKG_VERSION"), " git=")), "{plate}");
+        assert!(plate.contains(" tables="));
+        assert_eq!(provenance(), plate);
+        assert_eq!(super::plate(&tables()), plate);
+    }
+
+    #[test]
+    fn test_table_edit_changes_plate() {
+        let mut edited = tables();
+        edited[2].1[3] += 1e-9;
+        assert_ne!(plate(&edited), provenance());
+        let mut edited = tables();
+        edited.last_mut().unwrap().1.pop();
+        assert_ne!(plate(&edited), provenance());
+    }
+}
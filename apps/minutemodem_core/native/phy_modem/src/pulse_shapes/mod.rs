@@ -1,11 +1,14 @@
 //! Pulse shaping filter implementations
 //!
 //! Currently only Root Raised Cosine (RRC), which is used by
-//! both 188-110D and 188-141D with α=0.35.
+//! both 188-110D and 188-141D with α=0.35, plus the complex shaping
+//! filter the unified modulator runs it in.
 
 mod rrc;
+mod shaper;
 
 pub use rrc::RootRaisedCosine;
+pub use shaper::PulseShaper;
 
 /// Default roll-off factor for HF modems
 pub const DEFAULT_ALPHA: f64 = 0.35;
@@ -4,7 +4,6 @@
 //! (TX then RX), produces zero ISI at symbol centers.
 
 use crate::traits::PulseShape;
-use std::f64::consts::PI;
 
 /// Root Raised Cosine filter
 #[derive(Debug, Clone)]
@@ -52,45 +51,9 @@ impl PulseShape for RootRaisedCosine {
 
 /// Generate RRC filter coefficients
 ///
-/// Implements the standard RRC impulse response formula with
-/// proper handling of the singularity points.
+/// Thin wrapper over the shared design in `minutemodem_dsp`.
 fn generate_rrc_coefficients(samples_per_symbol: usize, alpha: f64, span: usize) -> Vec<f64> {
-    let filter_len = 2 * span * samples_per_symbol + 1;
-    let mut coeffs = Vec::with_capacity(filter_len);
-
-    let t_symbol = 1.0; // Normalized symbol period
-
-    for i in 0..filter_len {
-        // t is time in symbol periods, centered at 0
-        let t = (i as f64 - (filter_len - 1) as f64 / 2.0) / samples_per_symbol as f64;
-
-        let h = if t.abs() < 1e-10 {
-            // t = 0 (center tap)
-            (1.0 + alpha * (4.0 / PI - 1.0)) / t_symbol
-        } else if (t.abs() - t_symbol / (4.0 * alpha)).abs() < 1e-10 {
-            // t = ±T/(4α) (singularity points)
-            let term1 = (1.0 + 2.0 / PI) * (PI * alpha / 4.0).sin();
-            let term2 = (1.0 - 2.0 / PI) * (PI * alpha / 4.0).cos();
-            alpha / (t_symbol * 2.0_f64.sqrt()) * (term1 + term2)
-        } else {
-            // General case
-            let num = (PI * t / t_symbol * (1.0 - alpha)).sin()
-                + 4.0 * alpha * t / t_symbol * (PI * t / t_symbol * (1.0 + alpha)).cos();
-            let den = PI * t / t_symbol * (1.0 - (4.0 * alpha * t / t_symbol).powi(2));
-            num / den / t_symbol
-        };
-
-        coeffs.push(h);
-    }
-
-    // Normalize filter for unit energy
-    let energy: f64 = coeffs.iter().map(|x| x * x).sum();
-    let norm = energy.sqrt();
-    for c in &mut coeffs {
-        *c /= norm;
-    }
-
-    coeffs
+    minutemodem_dsp::rrc_coefficients(samples_per_symbol, alpha, span)
 }
 
 #[cfg(test)]
@@ -0,0 +1,313 @@
+//! Constellation scope rasterizer
+//!
+//! Histograms I/Q points into a density image with optional exponential
+//! persistence. The frame buffer lives in the scope so successive calls
+//! blend against the previous frame:
+//!
+//! ```text
+//! frame[n] = persistence * frame[n-1] + hits[n]
+//! ```
+//!
+//! Pixel mapping puts (-full_scale, +full_scale) at the top-left corner and
+//! (+full_scale, -full_scale) at the bottom-right, i.e. I grows to the right
+//! and Q grows upward like a normal scope.
+
+use minutemodem_dsp::flush_denormal_f32;
+
+use crate::modem::ConstellationType;
+
+/// Output pixel format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colormap {
+    /// One byte per pixel, 0 = empty, 255 = densest
+    Gray,
+    /// RGBA, green phosphor on black
+    Green,
+    /// RGBA, black → red → yellow → white
+    Heat,
+}
+
+impl Colormap {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            Self::Gray => 1,
+            Self::Green | Self::Heat => 4,
+        }
+    }
+}
+
+/// Summary of one rasterization call
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScopeStats {
+    /// Points that landed inside the image
+    pub points: usize,
+    /// Points dropped for falling outside ±full_scale (or being NaN)
+    pub out_of_range: usize,
+    /// Highest pixel density in the blended frame
+    pub peak: f32,
+    /// RMS EVM against the nearest ideal point (fraction, not percent)
+    pub evm_rms: Option<f64>,
+    /// RMS EVM in dB
+    pub evm_db: Option<f64>,
+}
+
+/// Density-image constellation scope with persistence
+#[derive(Debug, Clone)]
+pub struct ConstellationScope {
+    width: usize,
+    height: usize,
+    full_scale: f64,
+    frame: Vec<f32>,
+}
+
+impl ConstellationScope {
+    /// Create a scope with the default full scale (±1.5)
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::with_full_scale(width, height, super::DEFAULT_FULL_SCALE)
+    }
+
+    /// Create a scope mapping ±full_scale to the image edges
+    pub fn with_full_scale(width: usize, height: usize, full_scale: f64) -> Self {
+        Self {
+            width,
+            height,
+            full_scale,
+            frame: vec![0.0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn full_scale(&self) -> f64 {
+        self.full_scale
+    }
+
+    /// Bytes held by the persistence buffer
+    pub fn memory_bytes(&self) -> usize {
+        self.frame.capacity() * std::mem::size_of::<f32>()
+    }
+
+    /// Raw blended density buffer (row-major, top row first)
+    pub fn frame(&self) -> &[f32] {
+        &self.frame
+    }
+
+    /// Clear the persistence buffer
+    pub fn clear(&mut self) {
+        for x in &mut self.frame {
+            *x = 0.0;
+        }
+    }
+
+    /// Map an I/Q point to a row-major pixel index, or None if off-screen
+    pub fn pixel_index(&self, i: f64, q: f64) -> Option<usize> {
+        let span = 2.0 * self.full_scale;
+        let x = (i + self.full_scale) / span * self.width as f64;
+        let y = (self.full_scale - q) / span * self.height as f64;
+
+        // NaN fails both comparisons and is dropped here too
+        if !(x >= 0.0 && x < self.width as f64 && y >= 0.0 && y < self.height as f64) {
+            return None;
+        }
+
+        Some(y as usize * self.width + x as usize)
+    }
+
+    /// Decay the previous frame by `persistence` and add the new points
+    ///
+    /// `persistence` must be in [0, 1]; 0 shows only the current points.
+    pub fn accumulate(&mut self, points: &[(f64, f64)], persistence: f32) -> ScopeStats {
+        let persistence = persistence.clamp(0.0, 1.0);
+        for x in &mut self.frame {
+            // A pixel no longer hit fades toward the subnormals
+            *x = flush_denormal_f32(*x * persistence);
+        }
+
+        let mut inside = 0;
+        let mut out_of_range = 0;
+        for &(i, q) in points {
+            match self.pixel_index(i, q) {
+                Some(idx) => {
+                    self.frame[idx] += 1.0;
+                    inside += 1;
+                }
+                None => out_of_range += 1,
+            }
+        }
+
+        ScopeStats {
+            points: inside,
+            out_of_range,
+            peak: self.frame.iter().cloned().fold(0.0, f32::max),
+            evm_rms: None,
+            evm_db: None,
+        }
+    }
+
+    /// Render the blended frame, normalized so the peak maps to 255
+    pub fn render(&self, colormap: Colormap) -> Vec<u8> {
+        let peak = self.frame.iter().cloned().fold(0.0, f32::max);
+        let scale = if peak > 0.0 { 255.0 / peak } else { 0.0 };
+
+        let mut out = Vec::with_capacity(self.frame.len() * colormap.bytes_per_pixel());
+        for &d in &self.frame {
+            let v = (d * scale).round().clamp(0.0, 255.0) as u8;
+            match colormap {
+                Colormap::Gray => out.push(v),
+                Colormap::Green => out.extend_from_slice(&[0, v, 0, 255]),
+                Colormap::Heat => out.extend_from_slice(&heat(v)),
+            }
+        }
+        out
+    }
+}
+
+/// Three-segment heat ramp: black → red → yellow → white
+fn heat(v: u8) -> [u8; 4] {
+    let v = v as u16 * 3;
+    let r = v.min(255) as u8;
+    let g = v.saturating_sub(255).min(255) as u8;
+    let b = v.saturating_sub(510).min(255) as u8;
+    [r, g, b, 255]
+}
+
+/// RMS error vector magnitude against the nearest ideal constellation point
+///
+/// Normalized by the constellation's mean symbol energy. Returns None for
+/// an empty point set.
+pub fn evm_rms(points: &[(f64, f64)], constellation: ConstellationType) -> Option<f64> {
+    if points.is_empty() {
+        return None;
+    }
+
+    let order = constellation.order();
+    let ref_power = (0..order)
+        .map(|s| {
+            let (i, q) = constellation.symbol_to_iq(s as u8);
+            i * i + q * q
+        })
+        .sum::<f64>()
+        / order as f64;
+
+    let err_power = points
+        .iter()
+        .map(|&(i, q)| {
+            let sym = constellation.iq_to_symbol(i, q);
+            let (ii, iq) = constellation.symbol_to_iq(sym);
+            (i - ii).powi(2) + (q - iq).powi(2)
+        })
+        .sum::<f64>()
+        / points.len() as f64;
+
+    Some((err_power / ref_power).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixel_mapping_corners() {
+        let scope = ConstellationScope::with_full_scale(10, 10, 1.0);
+
+        // Top-left pixel is (-1, +1)
+        assert_eq!(scope.pixel_index(-0.99, 0.99), Some(0));
+        // Bottom-right pixel is (+1, -1)
+        assert_eq!(scope.pixel_index(0.99, -0.99), Some(99));
+        // Origin lands on the pixel just right/below center
+        assert_eq!(scope.pixel_index(0.0, 0.0), Some(5 * 10 + 5));
+        // I grows to the right within a row
+        assert_eq!(scope.pixel_index(0.25, 0.0), Some(5 * 10 + 6));
+    }
+
+    #[test]
+    fn test_known_points_histogram() {
+        let mut scope = ConstellationScope::with_full_scale(4, 4, 1.0);
+        let points = [(0.5, 0.5), (0.55, 0.45), (-0.5, -0.5)];
+        let stats = scope.accumulate(&points, 0.0);
+
+        assert_eq!(stats.points, 3);
+        assert_eq!(stats.out_of_range, 0);
+        // (0.5, 0.5) and (0.55, 0.45) share pixel row 1 col 3
+        assert_eq!(scope.frame()[4 + 3], 2.0);
+        assert_eq!(scope.frame()[3 * 4 + 1], 1.0);
+        assert_eq!(stats.peak, 2.0);
+        assert_eq!(scope.frame().iter().sum::<f32>(), 3.0);
+    }
+
+    #[test]
+    fn test_out_of_range_points_dropped() {
+        let mut scope = ConstellationScope::with_full_scale(8, 8, 1.0);
+        let points = [(1.0, 0.0), (0.0, -1.5), (f64::NAN, 0.0), (0.0, 0.0)];
+        let stats = scope.accumulate(&points, 0.0);
+
+        // x == width is off-screen, so +full_scale exactly is dropped
+        assert_eq!(stats.points, 1);
+        assert_eq!(stats.out_of_range, 3);
+        assert_eq!(scope.frame().iter().sum::<f32>(), 1.0);
+    }
+
+    #[test]
+    fn test_persistence_decay() {
+        let mut scope = ConstellationScope::with_full_scale(4, 4, 1.0);
+        let idx = scope.pixel_index(0.5, 0.5).unwrap();
+
+        scope.accumulate(&[(0.5, 0.5)], 0.5);
+        assert_eq!(scope.frame()[idx], 1.0);
+
+        // No new points: frame halves each call
+        scope.accumulate(&[], 0.5);
+        assert!((scope.frame()[idx] - 0.5).abs() < 1e-6);
+        scope.accumulate(&[], 0.5);
+        assert!((scope.frame()[idx] - 0.25).abs() < 1e-6);
+
+        // Zero persistence wipes history
+        scope.accumulate(&[], 0.0);
+        assert_eq!(scope.frame()[idx], 0.0);
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let mut scope = ConstellationScope::with_full_scale(4, 4, 1.0);
+        scope.accumulate(&[(0.5, 0.5), (0.5, 0.5), (-0.5, -0.5)], 0.0);
+
+        let gray = scope.render(Colormap::Gray);
+        assert_eq!(gray.len(), 16);
+        assert_eq!(gray[4 + 3], 255);
+        assert_eq!(gray[3 * 4 + 1], 128);
+        assert_eq!(gray[0], 0);
+
+        let rgba = scope.render(Colormap::Green);
+        assert_eq!(rgba.len(), 64);
+        assert_eq!(&rgba[(4 + 3) * 4..(4 + 3) * 4 + 4], &[0, 255, 0, 255]);
+
+        let heat = scope.render(Colormap::Heat);
+        assert_eq!(&heat[(4 + 3) * 4..(4 + 3) * 4 + 4], &[255, 255, 255, 255]);
+        assert_eq!(&heat[0..4], &[0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_render_empty_frame() {
+        let scope = ConstellationScope::new(3, 2);
+        assert_eq!(scope.render(Colormap::Gray), vec![0; 6]);
+    }
+
+    #[test]
+    fn test_evm_ideal_and_offset() {
+        let ct = ConstellationType::Psk8;
+        let ideal: Vec<_> = (0..8).map(|s| ct.symbol_to_iq(s)).collect();
+        assert!(evm_rms(&ideal, ct).unwrap() < 1e-12);
+
+        // Offset every point by 0.1 in I: EVM = 0.1 for unit-energy PSK
+        let offset: Vec<_> = ideal.iter().map(|&(i, q)| (i + 0.1, q)).collect();
+        assert!((evm_rms(&offset, ct).unwrap() - 0.1).abs() < 1e-9);
+
+        assert!(evm_rms(&[], ct).is_none());
+    }
+}
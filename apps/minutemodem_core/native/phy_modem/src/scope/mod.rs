@@ -0,0 +1,11 @@
+//! Display-side helpers for the LiveView dashboard
+//!
+//! Rasterizes demodulator output into compact images so the browser
+//! doesn't have to receive raw I/Q every frame.
+
+mod constellation;
+
+pub use constellation::{evm_rms, Colormap, ConstellationScope, ScopeStats};
+
+/// Default full-scale I/Q magnitude mapped to the image edges
+pub const DEFAULT_FULL_SCALE: f64 = 1.5;
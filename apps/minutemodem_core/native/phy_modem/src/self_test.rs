@@ -0,0 +1,227 @@
+//! Built-in sanity check for the compiled library
+//!
+//! A build can load fine and still compute garbage (a cross-compile with
+//! fast-math, a broken libm). run() exercises the parts everything else
+//! rests on in well under a second, so the application can refuse to
+//! start on such a build:
+//!
+//! - every constellation table against its embedded fingerprint, and the
+//!   slicer against the table
+//! - the RRC taps: the matched filter cascade must meet the Nyquist ISI
+//!   criterion
+//! - a short 8-PSK modulate → demodulate loopback
+
+use std::time::{Duration, Instant};
+
+use minutemodem_dsp::rrc_coefficients_shared;
+
+use crate::modem::{ConstellationType, UnifiedDemodulator, UnifiedModulator};
+
+/// Every constellation, in the order the fingerprints are listed
+const CONSTELLATIONS: [ConstellationType; 6] = [
+    ConstellationType::Bpsk,
+    ConstellationType::Qpsk,
+    ConstellationType::Psk8,
+    ConstellationType::Qam16,
+    ConstellationType::Qam32,
+    ConstellationType::Qam64,
+];
+
+/// fingerprint() of each table, from a known-good x86_64 build
+const CONSTELLATION_FINGERPRINTS: [u64; 6] = [
+    0x8bda_a7e0_01a1_ae53,
+    0x8a61_022a_f9c7_d9a5,
+    0xd439_fba6_0468_b65d,
+    0xd918_a560_adee_0a7d,
+    0x1fd4_b0cd_fe2a_00dd,
+    0x58ff_12f0_dab1_a499,
+];
+
+/// Largest cascade sample at a nonzero symbol offset, relative to the peak
+const MAX_RRC_ISI: f64 = 0.01;
+
+/// Loopback data symbols, after a 20-symbol preamble
+const LOOPBACK_SYMBOLS: usize = 200;
+
+/// Symbol errors the clean loopback may make
+const MAX_LOOPBACK_ERRORS: usize = 0;
+
+/// Outcome of one check: a short summary, or what went wrong
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub result: Result<String, String>,
+}
+
+/// Every check run, in order, and how long they took together
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub checks: Vec<Check>,
+    pub elapsed: Duration,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.result.is_ok())
+    }
+
+    /// (name, detail) of each failed check
+    pub fn failures(&self) -> Vec<(&'static str, String)> {
+        self.checks
+            .iter()
+            .filter_map(|c| c.result.as_ref().err().map(|e| (c.name, e.clone())))
+            .collect()
+    }
+}
+
+/// Run every check
+pub fn run() -> Report {
+    let start = Instant::now();
+    let checks = vec![
+        Check { name: "constellations", result: check_constellations(|ct| ct.points()) },
+        Check { name: "rrc_nyquist", result: check_rrc_nyquist() },
+        Check { name: "psk8_loopback", result: check_loopback() },
+    ];
+    Report { checks, elapsed: start.elapsed() }
+}
+
+/// FNV-1a over the points, each coordinate rounded to 1e-9
+fn fingerprint(points: &[(f64, f64)]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &(i, q) in points {
+        for x in [i, q] {
+            for byte in ((x * 1e9).round() as i64).to_le_bytes() {
+                hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+    }
+    hash
+}
+
+/// Check the tables `points` gives against their fingerprints, and that
+/// the slicer maps every point back to its (canonical) symbol
+///
+/// `points` is ConstellationType::points outside tests, which substitute
+/// a corrupted table.
+fn check_constellations(points: impl Fn(ConstellationType) -> Vec<(f64, f64)>) -> Result<String, String> {
+    for (ct, &expected) in CONSTELLATIONS.iter().zip(&CONSTELLATION_FINGERPRINTS) {
+        let table = points(*ct);
+        if table.len() != ct.order() {
+            return Err(format!("{:?}: {} points, expected {}", ct, table.len(), ct.order()));
+        }
+        let got = fingerprint(&table);
+        if got != expected {
+            return Err(format!("{:?}: table fingerprint {:016x}, expected {:016x}", ct, got, expected));
+        }
+        for (sym, &(i, q)) in table.iter().enumerate() {
+            let sliced = ct.iq_to_symbol(i, q);
+            let canonical = ct.canonical_symbol(sym as u8);
+            if sliced != canonical {
+                return Err(format!("{:?}: symbol {} slices to {}, expected {}", ct, sym, sliced, canonical));
+            }
+        }
+    }
+    Ok(format!("{} tables", CONSTELLATIONS.len()))
+}
+
+/// The RRC taps convolved with themselves: zero crossings every symbol
+fn check_rrc_nyquist() -> Result<String, String> {
+    let sps = 4;
+    let h = rrc_coefficients_shared(sps, 0.35, 6);
+    if h.iter().any(|x| !x.is_finite()) {
+        return Err("non-finite RRC tap".into());
+    }
+    let center = h.len() - 1;
+    let cascade = |n: usize| -> f64 {
+        h.iter()
+            .enumerate()
+            .filter(|&(k, _)| n >= k && n - k < h.len())
+            .map(|(k, &x)| x * h[n - k])
+            .sum()
+    };
+
+    let peak = cascade(center);
+    if !(peak.is_finite() && peak > 0.0) {
+        return Err(format!("cascade peak {}", peak));
+    }
+    let isi = (1..=center / sps)
+        .flat_map(|k| [center - k * sps, center + k * sps])
+        .map(|n| (cascade(n) / peak).abs())
+        .fold(0.0, f64::max);
+    if isi > MAX_RRC_ISI {
+        return Err(format!("cascade ISI {:.2e} above {:.0e}", isi, MAX_RRC_ISI));
+    }
+    Ok(format!("max ISI {:.2e}", isi))
+}
+
+/// 8-PSK at 9600 Hz through the modulator and demodulator, scored at the
+/// best delay and rotation
+fn check_loopback() -> Result<String, String> {
+    let mut x = 0x1955u32;
+    let mut symbols = vec![0u8; 20];
+    symbols.extend((0..LOOPBACK_SYMBOLS).map(|_| {
+        x = x.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (x >> 16) as u8 & 7
+    }));
+
+    let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0);
+    let mut samples = modulator.modulate(&symbols);
+    samples.extend(modulator.flush());
+    let mut demodulator = UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0);
+    let recovered = demodulator.demodulate(&samples);
+
+    let data = &symbols[20..];
+    let errors = (0..=recovered.len().saturating_sub(data.len()))
+        .flat_map(|delay| (0..8u8).map(move |rot| (delay, rot)))
+        .map(|(delay, rot)| {
+            data.iter()
+                .zip(recovered.get(delay..).unwrap_or(&[]))
+                .filter(|&(&s, &r)| r != (s + rot) % 8)
+                .count()
+                + data.len().saturating_sub(recovered.len().saturating_sub(delay))
+        })
+        .min()
+        .unwrap_or(data.len());
+    if errors > MAX_LOOPBACK_ERRORS {
+        return Err(format!("{}/{} symbol errors", errors, data.len()));
+    }
+    Ok(format!("{}/{} symbol errors", errors, data.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes() {
+        let report = run();
+        assert!(report.passed(), "{:?}", report.failures());
+        assert_eq!(report.checks.len(), 3);
+        assert!(report.elapsed < Duration::from_secs(1), "{:?}", report.elapsed);
+    }
+
+    #[test]
+    fn test_corrupted_table_is_caught() {
+        // One QAM16 point nudged by 1e-6: the slicer still agrees, the
+        // fingerprint doesn't
+        let nudged = |ct: ConstellationType| {
+            let mut points = ct.points();
+            if ct == ConstellationType::Qam16 {
+                points[5].0 += 1e-6;
+            }
+            points
+        };
+        let err = check_constellations(nudged).unwrap_err();
+        assert!(err.starts_with("Qam16: table fingerprint"), "{}", err);
+
+        // Two 8-PSK points swapped
+        let swapped = |ct: ConstellationType| {
+            let mut points = ct.points();
+            if ct == ConstellationType::Psk8 {
+                points.swap(1, 2);
+            }
+            points
+        };
+        assert!(check_constellations(swapped).unwrap_err().starts_with("Psk8"));
+    }
+}
@@ -1,16 +1,6 @@
 //! Clamping utilities for audio samples
 
-/// Clamp a floating point value to i16 range with saturation
-#[inline]
-pub fn clamp_i16(val: f64) -> i16 {
-    if val >= 32767.0 {
-        32767
-    } else if val <= -32768.0 {
-        -32768
-    } else {
-        val as i16
-    }
-}
+pub use minutemodem_dsp::convert::clamp_i16;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,536 @@
+//! Walsh-16 orthogonal correlation bank
+//!
+//! The 141D Table G-IX bank: 16 Walsh sequences of 16 chips, each chip
+//! 8-PSK symbol 0 or 4 (0°/180°), sent four times over for a 64-symbol
+//! dwell that carries a quad-bit. Sequence k's chip n is -1 when k & n has
+//! odd parity, which makes the bank the rows of the 16×16 Hadamard matrix
+//! in natural order.
+//!
+//! Correlating a dwell against every sequence one by one is 16 × 64
+//! complex multiply-adds. Here the four repeats are summed first (every
+//! sequence has the same chip at n and n + 16), and the 16 sums go through
+//! a fast Walsh–Hadamard transform: four butterfly stages whose partial
+//! sums every sequence shares, 64 complex additions in all. Output k is
+//! exactly the correlation with sequence k.
+//!
+//! Metrics are noncoherent, |correlation|², so the carrier phase doesn't
+//! need to be known, and normalised by 64 × the dwell's energy: a clean
+//! dwell of sequence k scores 1 at k and 0 elsewhere, and the 16 metrics
+//! of any dwell add up to at most 1. Input is descrambled symbol-rate I/Q.
+//!
+//! ## 75 bps
+//!
+//! The 110D 75 bps modes send each dibit as one of four 4-chip Walsh
+//! sequences (0000, 0404, 0044, 0440 in 8-PSK symbols, rows 0-3 of the
+//! 4×4 Hadamard matrix in natural order) repeated eight times: a 32-chip
+//! symbol. walsh75_chips() expands dibits for the modulator, and
+//! correlate_walsh75() decides a symbol from its chips' soft I/Q. The
+//! correlation is coherent over the symbol, Σ chip × sequence, so all 32
+//! chips add in amplitude before the one magnitude is taken, rather than
+//! each chip being sliced first; that is worth about 2 dB at the low
+//! SNRs these modes run at. Only the magnitude is used, so the 180°
+//! ambiguity of a carrier loop on 0/4 chips doesn't matter.
+
+/// Sequences in the bank
+pub const WALSH_SEQUENCES: usize = 16;
+
+/// Chips per sequence
+pub const WALSH_CHIPS: usize = 16;
+
+/// Symbols per dwell: the sequence sent four times
+pub const WALSH_DWELL: usize = 64;
+
+/// Sequences in the 75 bps bank, one per dibit
+pub const WALSH75_SEQUENCES: usize = 4;
+
+/// Chips per 75 bps symbol: a 4-chip sequence eight times over
+pub const WALSH75_CHIPS: usize = 32;
+
+/// Sequence `index` (0-15) over a whole dwell, as 8-PSK symbols 0/4
+///
+/// # Panics
+/// If `index` is 16 or more.
+pub fn walsh_sequence(index: usize) -> Vec<u8> {
+    assert!(index < WALSH_SEQUENCES, "Walsh-16 index {}", index);
+    (0..WALSH_DWELL)
+        .map(|n| if (index & n).count_ones() & 1 == 0 { 0 } else { 4 })
+        .collect()
+}
+
+/// One dwell's correlation against the whole bank
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalshDwell {
+    /// Sequence with the highest metric (the lowest index on a tie)
+    pub best: usize,
+    /// Its metric
+    pub metric: f64,
+    /// Every sequence's metric, by index
+    pub metrics: [f64; WALSH_SEQUENCES],
+}
+
+impl WalshDwell {
+    /// Metrics from the 16 raw correlations of a dwell of `energy`
+    fn from_correlations(corr: &[(f64, f64); WALSH_SEQUENCES], energy: f64) -> Self {
+        let scale = if energy > 0.0 { 1.0 / (WALSH_DWELL as f64 * energy) } else { 0.0 };
+        let metrics = corr.map(|(i, q)| (i * i + q * q) * scale);
+        let mut best = 0;
+        for (k, &m) in metrics.iter().enumerate() {
+            if m > metrics[best] {
+                best = k;
+            }
+        }
+        Self { best, metric: metrics[best], metrics }
+    }
+}
+
+fn dwell_energy(iq: &[(f64, f64)]) -> f64 {
+    iq.iter().map(|&(i, q)| i * i + q * q).sum()
+}
+
+/// Correlate one 64-symbol dwell against the bank
+///
+/// # Panics
+/// If `iq` isn't WALSH_DWELL symbols long.
+pub fn correlate_dwell(iq: &[(f64, f64)]) -> WalshDwell {
+    assert_eq!(iq.len(), WALSH_DWELL, "a Walsh-16 dwell is {} symbols", WALSH_DWELL);
+
+    // Fold the four repeats
+    let mut x = [(0.0, 0.0); WALSH_CHIPS];
+    for chunk in iq.chunks_exact(WALSH_CHIPS) {
+        for (acc, &(i, q)) in x.iter_mut().zip(chunk) {
+            acc.0 += i;
+            acc.1 += q;
+        }
+    }
+
+    // In-place fast Walsh-Hadamard transform, natural order
+    let mut h = 1;
+    while h < WALSH_CHIPS {
+        for block in (0..WALSH_CHIPS).step_by(2 * h) {
+            for n in block..block + h {
+                let (a, b) = (x[n], x[n + h]);
+                x[n] = (a.0 + b.0, a.1 + b.1);
+                x[n + h] = (a.0 - b.0, a.1 - b.1);
+            }
+        }
+        h *= 2;
+    }
+
+    WalshDwell::from_correlations(&x, dwell_energy(iq))
+}
+
+/// Correlate one dwell sequence by sequence, multiplying by each
+/// sequence's conjugated 8-PSK points
+///
+/// The reference correlate_dwell() is checked and benchmarked against.
+pub fn correlate_dwell_naive(iq: &[(f64, f64)]) -> WalshDwell {
+    assert_eq!(iq.len(), WALSH_DWELL, "a Walsh-16 dwell is {} symbols", WALSH_DWELL);
+    let mut corr = [(0.0, 0.0); WALSH_SEQUENCES];
+    for (k, c) in corr.iter_mut().enumerate() {
+        for (&(i, q), sym) in iq.iter().zip(walsh_sequence(k)) {
+            let (angle_sin, angle_cos) = (sym as f64 * std::f64::consts::FRAC_PI_4).sin_cos();
+            c.0 += i * angle_cos + q * angle_sin;
+            c.1 += q * angle_cos - i * angle_sin;
+        }
+    }
+    WalshDwell::from_correlations(&corr, dwell_energy(iq))
+}
+
+/// Correlate consecutive dwells from the start of `iq`; a partial dwell
+/// at the end is ignored
+pub fn correlate(iq: &[(f64, f64)]) -> Vec<WalshDwell> {
+    iq.chunks_exact(WALSH_DWELL).map(correlate_dwell).collect()
+}
+
+/// The 32 chips of dibit `dibit` (0-3), as 8-PSK symbols 0/4
+///
+/// # Panics
+/// If `dibit` is 4 or more.
+pub fn walsh75_sequence(dibit: u8) -> Vec<u8> {
+    assert!((dibit as usize) < WALSH75_SEQUENCES, "75 bps dibit {}", dibit);
+    (0..WALSH75_CHIPS)
+        .map(|n| if (dibit as usize & n).count_ones() & 1 == 0 { 0 } else { 4 })
+        .collect()
+}
+
+/// Expand dibits to their chips, WALSH75_CHIPS per dibit; only the low
+/// two bits of each are used
+pub fn walsh75_chips(dibits: &[u8]) -> Vec<u8> {
+    let bank: Vec<Vec<u8>> = (0..WALSH75_SEQUENCES as u8).map(walsh75_sequence).collect();
+    dibits.iter().flat_map(|&d| bank[(d & 3) as usize].iter().copied()).collect()
+}
+
+/// One 75 bps symbol's correlation against the bank
+#[derive(Debug, Clone, PartialEq)]
+pub struct Walsh75Symbol {
+    /// Dibit with the largest magnitude (the lowest on a tie)
+    pub dibit: u8,
+    /// Every dibit's correlation magnitude, normalised by √(32 × the
+    /// symbol's energy): 1 for its own clean symbol, 0 for the others
+    pub magnitudes: [f64; WALSH75_SEQUENCES],
+}
+
+/// Correlate one 32-chip symbol of I/Q against the 75 bps bank
+///
+/// # Panics
+/// If `iq` isn't WALSH75_CHIPS chips long.
+pub fn correlate_walsh75(iq: &[(f64, f64)]) -> Walsh75Symbol {
+    assert_eq!(iq.len(), WALSH75_CHIPS, "a 75 bps symbol is {} chips", WALSH75_CHIPS);
+
+    // Fold the eight repeats, then the 4-point Walsh-Hadamard transform
+    let mut x = [(0.0, 0.0); WALSH75_SEQUENCES];
+    for chunk in iq.chunks_exact(WALSH75_SEQUENCES) {
+        for (acc, &(i, q)) in x.iter_mut().zip(chunk) {
+            acc.0 += i;
+            acc.1 += q;
+        }
+    }
+    let add = |a: (f64, f64), b: (f64, f64)| (a.0 + b.0, a.1 + b.1);
+    let sub = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0, a.1 - b.1);
+    let (s01, d01, s23, d23) = (add(x[0], x[1]), sub(x[0], x[1]), add(x[2], x[3]), sub(x[2], x[3]));
+    let corr = [add(s01, s23), add(d01, d23), sub(s01, s23), sub(d01, d23)];
+
+    let energy = dwell_energy(iq);
+    let scale = if energy > 0.0 { 1.0 / (WALSH75_CHIPS as f64 * energy).sqrt() } else { 0.0 };
+    let magnitudes = corr.map(|(i, q)| i.hypot(q) * scale);
+    let mut dibit = 0;
+    for (k, &m) in magnitudes.iter().enumerate() {
+        if m > magnitudes[dibit] {
+            dibit = k;
+        }
+    }
+    Walsh75Symbol { dibit: dibit as u8, magnitudes }
+}
+
+/// correlate() over a stream of I/Q delivered in blocks of any length
+///
+/// Dwells are counted from the first symbol pushed after creation or
+/// reset; the symbols of an unfinished dwell wait for the next push.
+#[derive(Debug, Clone, Default)]
+pub struct WalshCorrelator {
+    pending: Vec<(f64, f64)>,
+}
+
+impl WalshCorrelator {
+    pub fn new() -> Self {
+        Self { pending: Vec::with_capacity(WALSH_DWELL) }
+    }
+
+    /// Take the next symbols; returns the dwells they complete
+    pub fn push(&mut self, iq: &[(f64, f64)]) -> Vec<WalshDwell> {
+        push_blocks(&mut self.pending, iq, WALSH_DWELL, correlate_dwell)
+    }
+
+    /// Symbols waiting for the rest of their dwell
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Drop any unfinished dwell
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Bytes held by the pending dwell buffer
+    pub fn memory_bytes(&self) -> usize {
+        self.pending.capacity() * std::mem::size_of::<(f64, f64)>()
+    }
+}
+
+/// correlate_walsh75() over a stream of chips delivered in blocks of any
+/// length
+///
+/// Symbols are counted from the first chip pushed after creation, reset
+/// or align(); the chips of an unfinished symbol wait for the next push.
+#[derive(Debug, Clone, Default)]
+pub struct Walsh75Correlator {
+    pending: Vec<(f64, f64)>,
+    /// Chips still to drop before the next symbol starts
+    skip: usize,
+}
+
+impl Walsh75Correlator {
+    pub fn new() -> Self {
+        Self { pending: Vec::with_capacity(WALSH75_CHIPS), skip: 0 }
+    }
+
+    /// Take the next chips; returns the symbols they complete
+    pub fn push(&mut self, iq: &[(f64, f64)]) -> Vec<Walsh75Symbol> {
+        let skip = self.skip.min(iq.len());
+        self.skip -= skip;
+        push_blocks(&mut self.pending, &iq[skip..], WALSH75_CHIPS, correlate_walsh75)
+    }
+
+    /// Start the next symbol `chips` chips on (e.g. where a preamble's
+    /// sync put the symbol boundary), dropping any unfinished one
+    pub fn align(&mut self, chips: usize) {
+        self.pending.clear();
+        self.skip = chips;
+    }
+
+    /// Chips waiting for the rest of their symbol
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Those chips
+    pub fn pending_chips(&self) -> &[(f64, f64)] {
+        &self.pending
+    }
+
+    /// Chips still to drop before the next symbol starts
+    pub fn skip(&self) -> usize {
+        self.skip
+    }
+
+    /// Drop any unfinished symbol and alignment
+    pub fn reset(&mut self) {
+        self.align(0);
+    }
+
+    /// Bytes held by the pending symbol buffer
+    pub fn memory_bytes(&self) -> usize {
+        self.pending.capacity() * std::mem::size_of::<(f64, f64)>()
+    }
+}
+
+/// Run `correlate` on each `len`-chip block of `pending` followed by
+/// `iq`, leaving the chips of an unfinished block in `pending`
+fn push_blocks<T>(
+    pending: &mut Vec<(f64, f64)>,
+    iq: &[(f64, f64)],
+    len: usize,
+    correlate: impl Fn(&[(f64, f64)]) -> T,
+) -> Vec<T> {
+    let mut blocks = Vec::with_capacity((pending.len() + iq.len()) / len);
+    let mut rest = iq;
+
+    if !pending.is_empty() {
+        let take = (len - pending.len()).min(rest.len());
+        pending.extend_from_slice(&rest[..take]);
+        rest = &rest[take..];
+        if pending.len() < len {
+            return blocks;
+        }
+        blocks.push(correlate(pending));
+        pending.clear();
+    }
+
+    let whole = rest.len() - rest.len() % len;
+    blocks.extend(rest[..whole].chunks_exact(len).map(correlate));
+    pending.extend_from_slice(&rest[whole..]);
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    /// Deterministic Gaussian noise (xorshift32 + Box-Muller)
+    struct Noise(u32);
+    impl Noise {
+        fn uniform(&mut self) -> f64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            (self.0 as f64 + 1.0) / (u32::MAX as f64 + 2.0)
+        }
+        fn gaussian(&mut self) -> f64 {
+            (-2.0 * self.uniform().ln()).sqrt() * (2.0 * PI * self.uniform()).cos()
+        }
+    }
+
+    /// Sequence `index` as unit-energy I/Q symbols, rotated by `phase`
+    fn dwell_iq(index: usize, phase: f64) -> Vec<(f64, f64)> {
+        walsh_sequence(index)
+            .into_iter()
+            .map(|sym| {
+                let angle = sym as f64 * PI / 4.0 + phase;
+                (angle.cos(), angle.sin())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sequences_match_table_g_ix() {
+        // Rows 0b0001, 0b0110 and 0b1111 of Table G-IX
+        assert_eq!(&walsh_sequence(1)[..16], &[0, 4, 0, 4, 0, 4, 0, 4, 0, 4, 0, 4, 0, 4, 0, 4]);
+        assert_eq!(&walsh_sequence(6)[..16], &[0, 0, 4, 4, 4, 4, 0, 0, 0, 0, 4, 4, 4, 4, 0, 0]);
+        assert_eq!(&walsh_sequence(15)[..16], &[0, 4, 4, 0, 4, 0, 0, 4, 4, 0, 0, 4, 0, 4, 4, 0]);
+        for k in 0..WALSH_SEQUENCES {
+            let seq = walsh_sequence(k);
+            assert_eq!(seq[..16].repeat(4), seq);
+        }
+    }
+
+    #[test]
+    fn test_clean_dwells_are_orthogonal() {
+        for k in 0..WALSH_SEQUENCES {
+            let dwell = correlate_dwell(&dwell_iq(k, 1.0));
+            assert_eq!(dwell.best, k);
+            for (j, &m) in dwell.metrics.iter().enumerate() {
+                let expected = if j == k { 1.0 } else { 0.0 };
+                assert!((m - expected).abs() < 1e-12, "sequence {} metric {} = {}", k, j, m);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fast_matches_naive() {
+        let mut noise = Noise(1971);
+        for k in 0..WALSH_SEQUENCES {
+            let iq: Vec<(f64, f64)> = dwell_iq(k, 0.3 * k as f64)
+                .into_iter()
+                .map(|(i, q)| (i + noise.gaussian(), q + noise.gaussian()))
+                .collect();
+            let fast = correlate_dwell(&iq);
+            let naive = correlate_dwell_naive(&iq);
+            assert_eq!(fast.best, naive.best);
+            for (a, b) in fast.metrics.iter().zip(&naive.metrics) {
+                assert!((a - b).abs() < 1e-12, "{} vs {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_recovers_index_at_0_db_snr() {
+        // Es/N0 = 0 dB: noise power per symbol equals the signal's
+        let sigma = (0.5f64).sqrt();
+        let mut noise = Noise(7);
+        let mut errors = 0;
+        for n in 0..1000 {
+            let k = n % WALSH_SEQUENCES;
+            let phase = 2.0 * PI * noise.uniform();
+            let iq: Vec<(f64, f64)> = dwell_iq(k, phase)
+                .into_iter()
+                .map(|(i, q)| (i + sigma * noise.gaussian(), q + sigma * noise.gaussian()))
+                .collect();
+            if correlate_dwell(&iq).best != k {
+                errors += 1;
+            }
+        }
+        // 18 dB of processing gain leaves next to no errors
+        assert!(errors <= 1, "{} of 1000 dwells wrong", errors);
+    }
+
+    #[test]
+    fn test_soft_metrics_under_fading() {
+        // Two-ray Rayleigh-like fade at 2 Hz Doppler on 2400 Bd symbols:
+        // gain and phase drift within each dwell, with deep nulls
+        let mut noise = Noise(42);
+        let sigma = (0.05f64).sqrt();
+        let (mut correct, mut faded_best, mut clean_best) = (0, Vec::new(), Vec::new());
+        for n in 0..400 {
+            let k = (n * 7) % WALSH_SEQUENCES;
+            let iq: Vec<(f64, f64)> = dwell_iq(k, 0.0)
+                .into_iter()
+                .enumerate()
+                .map(|(s, (i, q))| {
+                    let t = (n * WALSH_DWELL + s) as f64 / 2400.0;
+                    let (g1, g2) = (2.0 * PI * 2.0 * t, -2.0 * PI * 1.3 * t + 0.7);
+                    let (gi, gq) = (0.7 * g1.cos() + 0.7 * g2.cos(), 0.7 * g1.sin() + 0.7 * g2.sin());
+                    (i * gi - q * gq + sigma * noise.gaussian(), i * gq + q * gi + sigma * noise.gaussian())
+                })
+                .collect();
+            let dwell = correlate_dwell(&iq);
+
+            let total: f64 = dwell.metrics.iter().sum();
+            assert!(dwell.metrics.iter().all(|m| (0.0..=1.0).contains(m)));
+            assert!(total <= 1.0 + 1e-12, "metrics sum to {}", total);
+            if dwell.best == k {
+                correct += 1;
+            }
+            let energy = dwell_energy(&iq) / WALSH_DWELL as f64;
+            if energy < 0.2 { faded_best.push(dwell.metric) } else { clean_best.push(dwell.metric) }
+        }
+        assert!(correct >= 380, "{} of 400 dwells right", correct);
+
+        // Faded dwells still decide, with visibly less confidence
+        let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+        assert!(!faded_best.is_empty() && !clean_best.is_empty());
+        assert!(mean(&faded_best) < mean(&clean_best));
+    }
+
+    #[test]
+    fn test_walsh75_bank() {
+        // 00 -> 0000, 01 -> 0404, 10 -> 0044, 11 -> 0440, eight times over
+        for (dibit, chips) in [[0, 0, 0, 0], [0, 4, 0, 4], [0, 0, 4, 4], [0, 4, 4, 0]].iter().enumerate() {
+            assert_eq!(walsh75_sequence(dibit as u8), chips.repeat(8));
+        }
+        assert_eq!(walsh75_chips(&[2, 7]), [walsh75_sequence(2), walsh75_sequence(3)].concat());
+
+        for dibit in 0..WALSH75_SEQUENCES as u8 {
+            let iq: Vec<(f64, f64)> = walsh75_sequence(dibit)
+                .into_iter()
+                .map(|sym| {
+                    let angle = sym as f64 * PI / 4.0 + 2.0;
+                    (0.3 * angle.cos(), 0.3 * angle.sin())
+                })
+                .collect();
+            let symbol = correlate_walsh75(&iq);
+            assert_eq!(symbol.dibit, dibit);
+            for (k, &m) in symbol.magnitudes.iter().enumerate() {
+                let expected = if k == dibit as usize { 1.0 } else { 0.0 };
+                assert!((m - expected).abs() < 1e-12, "dibit {} magnitude {} = {}", dibit, k, m);
+            }
+        }
+        assert_eq!(correlate_walsh75(&[(0.0, 0.0); WALSH75_CHIPS]).magnitudes, [0.0; 4]);
+    }
+
+    #[test]
+    fn test_silence_scores_zero() {
+        let dwell = correlate_dwell(&[(0.0, 0.0); WALSH_DWELL]);
+        assert_eq!((dwell.best, dwell.metric), (0, 0.0));
+    }
+
+    #[test]
+    fn test_streaming_matches_batch() {
+        let iq: Vec<(f64, f64)> = (0..5).flat_map(|k| dwell_iq(k * 3, 0.2)).chain(dwell_iq(1, 0.0).into_iter().take(10)).collect();
+        let batch = correlate(&iq);
+        assert_eq!(batch.len(), 5);
+
+        let mut stream = WalshCorrelator::new();
+        let mut dwells = Vec::new();
+        for block in iq.chunks(23) {
+            dwells.extend(stream.push(block));
+        }
+        assert_eq!(dwells, batch);
+        assert_eq!(stream.pending(), 10);
+
+        stream.reset();
+        assert_eq!(stream.pending(), 0);
+        assert_eq!(stream.push(&iq[..WALSH_DWELL]), batch[..1]);
+    }
+
+    #[test]
+    fn test_walsh75_streaming_and_alignment() {
+        let chips: Vec<(f64, f64)> = walsh75_chips(&[3, 1, 0, 2, 1])
+            .into_iter()
+            .map(|sym| ((sym as f64 * PI / 4.0).cos(), (sym as f64 * PI / 4.0).sin()))
+            .collect();
+        let dibits = |symbols: Vec<Walsh75Symbol>| symbols.into_iter().map(|s| s.dibit).collect::<Vec<_>>();
+
+        let mut stream = Walsh75Correlator::new();
+        let mut symbols = Vec::new();
+        for block in chips[..150].chunks(13) {
+            symbols.extend(stream.push(block));
+        }
+        assert_eq!(dibits(symbols), [3, 1, 0, 2]);
+        assert_eq!(stream.pending(), 150 - 4 * WALSH75_CHIPS);
+        assert_eq!(stream.pending_chips(), &chips[128..150]);
+
+        // Five chips of preamble ahead of the first symbol, skipped
+        let mut late = vec![(0.5, 0.5); 5];
+        late.extend_from_slice(&chips);
+        stream.align(5);
+        assert_eq!(stream.pending(), 0);
+        assert!(stream.push(&late[..3]).is_empty());
+        assert_eq!(stream.skip(), 2);
+        assert_eq!(dibits(stream.push(&late[3..])), [3, 1, 0, 2, 1]);
+
+        stream.push(&chips[..10]);
+        stream.reset();
+        assert_eq!((stream.pending(), stream.skip()), (0, 0));
+    }
+}
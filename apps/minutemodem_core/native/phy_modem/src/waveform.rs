@@ -0,0 +1,355 @@
+//! 110D waveform presets
+//!
+//! One preset per data rate and interleaver at 3 kHz, named
+//! `m110d_<bps>_<interleaver>` (`m110d_2400_short`), holding everything a
+//! matched modulator and demodulator need: the data constellation, the
+//! frame of U data and K probe symbols, the 8-PSK mini-probe and the
+//! demodulator's equalizer and PLL settings. The protocol-level parts
+//! (code rate, constraint length, interleaver geometry) ride along as
+//! metadata for the Elixir codec and interleaver, which apply them.
+//!
+//! The tables mirror Modem110D.Tables (constellation, U and K) and
+//! Modem110D.Waveforms (code rate, interleaver blocks, Table D-LI
+//! increments) for WIDs 1-10; WIDs 11 and 12 need 256-QAM, which the
+//! modem has no constellation for.
+//!
+//! The demodulator trains its DFE on every mini-probe, with the PLL open
+//! (see UnifiedDemodulator::set_probe_training), counting symbols from
+//! the first sample of the initial mini-probe after the preamble.
+
+use crate::modem::{ConstellationType, DFEConfig, ProbeSchedule, Pulse, UnifiedDemodulator, UnifiedModulator};
+use crate::probes;
+
+/// Symbol rate of every 3 kHz 110D waveform
+pub const SYMBOL_RATE: u32 = 2400;
+
+/// Audio carrier, Hz
+pub const CARRIER_FREQ: f64 = 1800.0;
+
+/// Convolutional code constraint length (the Elixir codec's default)
+pub const CONSTRAINT_LENGTH: u32 = 7;
+
+/// Interleaver length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interleaver {
+    UltraShort,
+    Short,
+    Medium,
+    Long,
+}
+
+impl Interleaver {
+    pub const ALL: [Interleaver; 4] = [Interleaver::UltraShort, Interleaver::Short, Interleaver::Medium, Interleaver::Long];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Interleaver::UltraShort => "ultra_short",
+            Interleaver::Short => "short",
+            Interleaver::Medium => "medium",
+            Interleaver::Long => "long",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Interleaver block: frames per block, coded bits in and input bits
+/// (before FEC) it carries, and the Table D-LI increment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterleaverGeometry {
+    pub frames: usize,
+    pub coded_bits: usize,
+    pub input_bits: usize,
+    pub increment: usize,
+}
+
+/// Raw symbol error rate a preset's pair is held to on a CCIR moderate
+/// channel (two equal paths 1 ms apart, 0.5 Hz Doppler spread) at
+/// `snr_db` in 3 kHz: the bound FEC and interleaving have to clean up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SerBudget {
+    pub ser: f64,
+    pub snr_db: f64,
+}
+
+/// Everything modem-relevant about one 110D data rate and interleaver
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaveformPreset {
+    pub wid: u8,
+    pub data_rate_bps: u32,
+    pub interleaver: Interleaver,
+    /// Data symbols' constellation; probes are always 8-PSK
+    pub constellation: ConstellationType,
+    /// Data symbols per frame (U)
+    pub data_symbols: usize,
+    /// Mini-probe symbols closing each frame (K)
+    pub probe_symbols: usize,
+    /// (k, n): k input bits per n coded
+    pub code_rate: (u32, u32),
+    pub geometry: InterleaverGeometry,
+}
+
+/// Per WID 1-10: data rate, constellation, U, K, code rate, the
+/// ultra-short block's frames, coded and input bits (each longer
+/// interleaver is four times the one before) and the four increments
+struct WidRow {
+    data_rate_bps: u32,
+    constellation: ConstellationType,
+    data_symbols: usize,
+    probe_symbols: usize,
+    code_rate: (u32, u32),
+    block: (usize, usize, usize),
+    increments: [usize; 4],
+}
+
+const WIDS: [WidRow; 10] = [
+    WidRow { data_rate_bps: 75, constellation: ConstellationType::Bpsk, data_symbols: 48, probe_symbols: 48, code_rate: (1, 8), block: (4, 192, 24), increments: [25, 97, 385, 1543] },
+    WidRow { data_rate_bps: 150, constellation: ConstellationType::Bpsk, data_symbols: 48, probe_symbols: 48, code_rate: (1, 4), block: (4, 192, 48), increments: [25, 97, 385, 1543] },
+    WidRow { data_rate_bps: 300, constellation: ConstellationType::Bpsk, data_symbols: 96, probe_symbols: 32, code_rate: (1, 2), block: (2, 192, 64), increments: [25, 97, 385, 1549] },
+    WidRow { data_rate_bps: 600, constellation: ConstellationType::Bpsk, data_symbols: 96, probe_symbols: 32, code_rate: (1, 2), block: (2, 192, 128), increments: [25, 97, 385, 1549] },
+    WidRow { data_rate_bps: 1200, constellation: ConstellationType::Qpsk, data_symbols: 256, probe_symbols: 32, code_rate: (1, 2), block: (1, 256, 192), increments: [33, 129, 513, 2081] },
+    WidRow { data_rate_bps: 2400, constellation: ConstellationType::Psk8, data_symbols: 256, probe_symbols: 32, code_rate: (1, 2), block: (1, 512, 384), increments: [65, 257, 1025, 4161] },
+    WidRow { data_rate_bps: 3600, constellation: ConstellationType::Qam16, data_symbols: 256, probe_symbols: 32, code_rate: (3, 4), block: (1, 768, 576), increments: [97, 385, 1537, 6241] },
+    WidRow { data_rate_bps: 4800, constellation: ConstellationType::Qam32, data_symbols: 256, probe_symbols: 32, code_rate: (3, 4), block: (1, 1024, 768), increments: [129, 641, 2049, 8321] },
+    WidRow { data_rate_bps: 6000, constellation: ConstellationType::Qam64, data_symbols: 256, probe_symbols: 32, code_rate: (15, 16), block: (1, 1280, 960), increments: [161, 641, 2561, 10403] },
+    WidRow { data_rate_bps: 7200, constellation: ConstellationType::Qam64, data_symbols: 256, probe_symbols: 32, code_rate: (3, 4), block: (1, 1536, 1152), increments: [193, 769, 3073, 12481] },
+];
+
+impl WaveformPreset {
+    /// The preset for `wid` (1-10) and `interleaver`
+    pub fn new(wid: u8, interleaver: Interleaver) -> Option<Self> {
+        let row = WIDS.get((wid as usize).checked_sub(1)?)?;
+        let scale = 4usize.pow(interleaver.index() as u32);
+        let (frames, coded_bits, input_bits) = row.block;
+        Some(Self {
+            wid,
+            data_rate_bps: row.data_rate_bps,
+            interleaver,
+            constellation: row.constellation,
+            data_symbols: row.data_symbols,
+            probe_symbols: row.probe_symbols,
+            code_rate: row.code_rate,
+            geometry: InterleaverGeometry {
+                frames: frames * scale,
+                coded_bits: coded_bits * scale,
+                input_bits: input_bits * scale,
+                increment: row.increments[interleaver.index()],
+            },
+        })
+    }
+
+    /// Look a preset up by name, e.g. "m110d_2400_short"
+    pub fn from_name(name: &str) -> Option<Self> {
+        all().find(|preset| preset.name() == name)
+    }
+
+    pub fn name(&self) -> String {
+        format!("m110d_{}_{}", self.data_rate_bps, self.interleaver.name())
+    }
+
+    /// Data plus probe symbols per frame
+    pub fn frame_symbols(&self) -> usize {
+        self.data_symbols + self.probe_symbols
+    }
+
+    /// The frame's mini-probe; `boundary_marker` starts it at the
+    /// interleaver-boundary shift
+    pub fn probe(&self, boundary_marker: bool) -> Vec<u8> {
+        probes::mini_probe(self.probe_symbols, boundary_marker).expect("every preset's K is a tabulated mini-probe length")
+    }
+
+    /// The probes' positions in the demodulator's output: the initial
+    /// mini-probe, then one closing every frame, counted at the matched
+    /// filter output from the initial probe's first sample
+    ///
+    /// The boundary-marked probe differs from this one; training on it
+    /// costs one probe's worth of wrong references per interleaver block.
+    pub fn probe_schedule(&self) -> ProbeSchedule {
+        let offset = 2 * Pulse::Rrc.span() as u64;
+        ProbeSchedule::new(self.frame_symbols() as u64, offset, self.probe(false)).expect("a mini-probe fits its frame")
+    }
+
+    pub fn dfe_config(&self) -> DFEConfig {
+        DFEConfig::hf_skywave()
+    }
+
+    /// The demodulator's PLL bandwidth: open, the probe-trained equalizer
+    /// turning the phase
+    pub fn pll_bandwidth_hz(&self) -> f64 {
+        0.0
+    }
+
+    /// The SER the pair holds to on a CCIR moderate channel
+    ///
+    /// Measured with the pair in this module's tests and rounded up;
+    /// most of what is left clusters where the first path fades or the
+    /// two paths notch the band.
+    pub fn ser_budget(&self) -> SerBudget {
+        let (ser, snr_db) = match self.constellation {
+            ConstellationType::Bpsk => (0.01, 20.0),
+            ConstellationType::Qpsk => (0.05, 20.0),
+            ConstellationType::Psk8 => (0.1, 30.0),
+            ConstellationType::Qam16 => (0.25, 30.0),
+            ConstellationType::Qam32 => (0.3, 30.0),
+            ConstellationType::Qam64 => (0.45, 30.0),
+        };
+        SerBudget { ser, snr_db }
+    }
+
+    /// Modulator for the preset at `sample_rate`; send the data symbols
+    /// in the constellation and the probes as 8-PSK (modulate_mixed)
+    pub fn modulator(&self, sample_rate: u32) -> UnifiedModulator {
+        UnifiedModulator::new(self.constellation, sample_rate, SYMBOL_RATE, CARRIER_FREQ)
+    }
+
+    /// Demodulator for the preset at `sample_rate`: the DFE trained on
+    /// every probe, with the PLL open; start it (or reset it) at the
+    /// initial mini-probe
+    pub fn demodulator(&self, sample_rate: u32) -> UnifiedDemodulator {
+        let mut demod = UnifiedDemodulator::with_equalizer(self.constellation, sample_rate, SYMBOL_RATE, CARRIER_FREQ, self.dfe_config());
+        demod.set_probe_training(Some(self.probe_schedule()));
+        demod.set_pll_bandwidth(self.pll_bandwidth_hz());
+        demod
+    }
+}
+
+/// Every preset, by WID and then interleaver
+pub fn all() -> impl Iterator<Item = WaveformPreset> {
+    (1..=WIDS.len() as u8).flat_map(|wid| Interleaver::ALL.into_iter().filter_map(move |il| WaveformPreset::new(wid, il)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    /// xorshift64
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn uniform(&mut self) -> f64 {
+            (self.next() >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        fn gauss(&mut self) -> f64 {
+            let u1 = self.uniform().max(1e-300);
+            (-2.0 * u1.ln()).sqrt() * (2.0 * PI * self.uniform()).cos()
+        }
+    }
+
+    /// CCIR moderate: two equal Rayleigh paths 1 ms apart, each a sum of
+    /// 32 sinusoids with Gaussian Doppler (0.25 Hz sigma, 0.5 Hz two-sided
+    /// spread), applied to the analytic signal, plus AWGN at `snr_db` in
+    /// 3 kHz. The input is halved to leave the fades headroom.
+    fn ccir_moderate(samples: &[i16], fs: f64, snr_db: f64, seed: u64) -> Vec<i16> {
+        const HALF: usize = 64;
+        const SINUSOIDS: usize = 32;
+        let mut rng = Rng(seed);
+        // Blackman-windowed Hilbert transformer
+        let hilbert: Vec<f64> = (0..=2 * HALF)
+            .map(|n| {
+                let k = n as isize - HALF as isize;
+                let x = n as f64 / (2 * HALF) as f64;
+                let window = 0.42 - 0.5 * (2.0 * PI * x).cos() + 0.08 * (4.0 * PI * x).cos();
+                if k % 2 == 0 { 0.0 } else { 2.0 / (PI * k as f64) * window }
+            })
+            .collect();
+        let mut x: Vec<f64> = samples.iter().map(|&s| s as f64 * 0.5).collect();
+        x.resize(x.len() + HALF, 0.0);
+        let analytic: Vec<(f64, f64)> = (0..x.len())
+            .map(|t| {
+                let re = t.checked_sub(HALF).map_or(0.0, |i| x[i]);
+                let im = hilbert.iter().enumerate().filter_map(|(j, c)| t.checked_sub(j).map(|i| c * x[i])).sum();
+                (re, im)
+            })
+            .collect();
+        let paths: Vec<Vec<(f64, f64, f64)>> = (0..2)
+            .map(|_| (0..SINUSOIDS).map(|_| (2.0 * PI * 0.25 * rng.gauss(), 2.0 * PI * rng.uniform(), 2.0 * PI * rng.uniform())).collect())
+            .collect();
+        let gain = |path: usize, t: f64| {
+            let scale = (0.5 / SINUSOIDS as f64).sqrt();
+            paths[path].iter().fold((0.0, 0.0), |(re, im), &(w, pi, pq)| (re + scale * (w * t + pi).cos(), im + scale * (w * t + pq).cos()))
+        };
+        let delay = (1e-3 * fs).round() as usize;
+        let power = x.iter().map(|v| v * v).sum::<f64>() / x.len() as f64;
+        let sigma = (power / 10f64.powf(snr_db / 10.0) * (fs / 2.0) / 3000.0).sqrt();
+        (0..x.len())
+            .map(|t| {
+                let (g1, g2) = (gain(0, t as f64 / fs), gain(1, t as f64 / fs));
+                let a1 = analytic[t];
+                let a2 = t.checked_sub(delay).map_or((0.0, 0.0), |i| analytic[i]);
+                let y = g1.0 * a1.0 - g1.1 * a1.1 + g2.0 * a2.0 - g2.1 * a2.1 + sigma * rng.gauss();
+                y.round().clamp(-32768.0, 32767.0) as i16
+            })
+            .skip(HALF)
+            .collect()
+    }
+
+    /// SER of `frames` frames of random data sent and received with the
+    /// preset's pair through CCIR moderate at its budget's SNR
+    fn loopback_ser(preset: &WaveformPreset, frames: usize) -> f64 {
+        const SAMPLE_RATE: u32 = 9600;
+        let ct = preset.constellation;
+        let probe: Vec<(u8, ConstellationType)> = preset.probe(false).into_iter().map(|s| (s, ConstellationType::Psk8)).collect();
+        let mut rng = Rng(1981);
+        let mut stream = probe.clone();
+        let mut data = Vec::new();
+        for _ in 0..frames {
+            for _ in 0..preset.data_symbols {
+                data.push(stream.len());
+                stream.push(((rng.next() % ct.order() as u64) as u8, ct));
+            }
+            stream.extend(&probe);
+        }
+        let mut modulator = preset.modulator(SAMPLE_RATE);
+        let mut tx = modulator.modulate_mixed(&stream);
+        tx.extend(modulator.flush());
+        let rx = ccir_moderate(&tx, SAMPLE_RATE as f64, preset.ser_budget().snr_db, 1981);
+
+        let out = preset.demodulator(SAMPLE_RATE).demodulate(&rx);
+        // Matched filters, then the equalizer's center tap
+        let delay = 2 * Pulse::Rrc.span() + preset.dfe_config().ff_taps / 2;
+        let point = |s: u8| ct.symbol_to_iq(s);
+        let errors = data.iter().filter(|&&n| point(out[n + delay]) != point(stream[n].0)).count();
+        errors as f64 / data.len() as f64
+    }
+
+    #[test]
+    fn test_preset_tables() {
+        assert_eq!(all().count(), 40);
+        let preset = WaveformPreset::from_name("m110d_2400_short").unwrap();
+        assert_eq!((preset.wid, preset.constellation), (6, ConstellationType::Psk8));
+        assert_eq!((preset.data_symbols, preset.probe_symbols, preset.code_rate), (256, 32, (1, 2)));
+        assert_eq!(preset.geometry, InterleaverGeometry { frames: 4, coded_bits: 2048, input_bits: 1536, increment: 257 });
+
+        let long = WaveformPreset::new(1, Interleaver::Long).unwrap();
+        assert_eq!(long.name(), "m110d_75_long");
+        assert_eq!(long.geometry, InterleaverGeometry { frames: 256, coded_bits: 12288, input_bits: 1536, increment: 1543 });
+
+        for preset in all() {
+            assert_eq!(WaveformPreset::from_name(&preset.name()), Some(preset.clone()));
+            assert_eq!(preset.probe(false).len(), preset.probe_symbols);
+        }
+        assert!(WaveformPreset::from_name("m110d_9600_short").is_none());
+        assert!(WaveformPreset::new(11, Interleaver::Long).is_none());
+    }
+
+    #[test]
+    fn test_preset_pairs_loop_back_through_ccir_moderate() {
+        for name in ["m110d_300_short", "m110d_1200_short", "m110d_2400_short"] {
+            let preset = WaveformPreset::from_name(name).unwrap();
+            let frames = 40 * 256 / preset.data_symbols;
+            let ser = loopback_ser(&preset, frames);
+            assert!(ser <= preset.ser_budget().ser, "{name}: SER {ser}");
+        }
+    }
+}
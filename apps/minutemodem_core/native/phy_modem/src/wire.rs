@@ -0,0 +1,17 @@
+//! The wire formats phy_modem reads and writes
+//!
+//! I/Q, symbol and LLR binaries follow minutemodem_dsp::wire, shared with
+//! channel_physics and Elixir. Whether input may still arrive without a
+//! header is this crate's one choice, made at build time by the
+//! `legacy-wire` feature (on by default until callers frame their input).
+
+pub use minutemodem_dsp::wire::*;
+
+/// Whether headerless input is taken as a bare payload
+pub const LEGACY: Legacy = if cfg!(feature = "legacy-wire") { Legacy::Accept } else { Legacy::Reject };
+
+/// Read I/Q as the NIFs do: IQ_F32_INTERLEAVED_V1, or bare interleaved
+/// f32-le while LEGACY accepts it
+pub fn read_iq(bytes: &[u8]) -> Result<Vec<(f64, f64)>, WireError> {
+    decode_iq_f32(bytes, LEGACY)
+}
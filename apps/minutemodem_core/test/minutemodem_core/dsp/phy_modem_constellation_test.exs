@@ -0,0 +1,57 @@
+defmodule MinuteModemCore.DSP.PhyModemConstellationTest do
+  use ExUnit.Case, async: true
+  use ExUnitProperties
+
+  alias MinuteModemCore.DSP.PhyModem
+
+  @constellations [:bpsk, :qpsk, :psk8, :qam16, :qam32, :qam64]
+
+  defp constellation_and_symbol do
+    gen all(
+          c <- member_of(@constellations),
+          s <- integer(0..(PhyModem.order(c) - 1))
+        ) do
+      {c, s}
+    end
+  end
+
+  defp distance_sq({i, q}, {pi, pq}), do: (i - pi) * (i - pi) + (q - pq) * (q - pq)
+
+  test "order, bits per symbol and points agree" do
+    for c <- @constellations do
+      assert PhyModem.order(c) == Integer.pow(2, PhyModem.bits_per_symbol(c))
+      assert length(PhyModem.constellation_points(c)) == PhyModem.order(c)
+    end
+  end
+
+  property "every symbol round-trips to its canonical symbol" do
+    check all({c, s} <- constellation_and_symbol()) do
+      {i, q} = PhyModem.symbol_to_iq(c, s)
+      canonical = PhyModem.canonical_symbol(c, s)
+
+      assert PhyModem.iq_to_symbol(c, i, q) == canonical
+      assert canonical <= s
+      assert Enum.at(PhyModem.constellation_points(c), canonical) == {i, q}
+    end
+  end
+
+  property "the slicer picks a nearest point" do
+    check all(
+            c <- member_of(@constellations),
+            i <- float(min: -1.5, max: 1.5),
+            q <- float(min: -1.5, max: 1.5)
+          ) do
+      points = PhyModem.constellation_points(c)
+      nearest = points |> Enum.map(&distance_sq({i, q}, &1)) |> Enum.min()
+      sym = PhyModem.iq_to_symbol(c, i, q)
+
+      assert distance_sq({i, q}, Enum.at(points, sym)) <= nearest + 1.0e-12
+      assert PhyModem.canonical_symbol(c, sym) == sym
+    end
+  end
+
+  test "out of range symbols and unknown constellations are rejected" do
+    assert PhyModem.symbol_to_iq(:psk8, 8) == {:error, {:invalid_argument, :symbol}}
+    assert PhyModem.order(:qam128) == {:error, :unsupported_constellation}
+  end
+end
@@ -0,0 +1,63 @@
+defmodule MinuteModemCore.DSP.PhyModemDecodeReportTest do
+  use ExUnit.Case, async: true
+
+  alias MinuteModemCore.DSP.PhyModem
+
+  defp burst(symbols) do
+    PhyModem.unified_mod_new(:psk8, 9600) |> PhyModem.unified_mod_modulate(symbols)
+  end
+
+  test "a report covers acquisition, PLL, symbols and quality" do
+    demodulator = PhyModem.unified_demod_new(:psk8, 9600)
+    symbols = PhyModem.unified_demod_symbols(demodulator, burst(Enum.map(0..299, &rem(&1 * 5, 8))))
+    report = PhyModem.unified_demod_decode_report(demodulator)
+
+    assert %{start_sample: 0, timing_phase: phase, coarse_freq_hz: _} = report.acquisition
+    assert phase in 0..3
+    assert %{updates: updates, mean_abs_error: _, max_abs_error: _, freq_hz: _} = report.pll
+    assert updates > 0
+    assert report.equalizer == nil
+    assert report.symbols.emitted == length(symbols)
+    assert length(report.confidence_histogram) == 10
+    assert report.snr_db > 30.0
+    assert is_float(report.evm_percent)
+    assert report.eot_symbol == nil
+    assert report.warnings == []
+    assert report.fingerprint == PhyModem.unified_demod_config_fingerprint(demodulator)
+  end
+
+  test "an equalizer adds its mode timeline and MSE curve" do
+    demodulator = PhyModem.unified_demod_new_with_eq(:psk8, 9600, 11, 5, 0.02)
+    PhyModem.unified_demod_symbols(demodulator, burst(Enum.map(0..599, &rem(&1 * 3, 8))))
+
+    assert %{mode_timeline: [{0, :cma} | _], mse_curve: [{0, _} | _], mse_stride: 16} =
+             PhyModem.unified_demod_decode_report(demodulator).equalizer
+  end
+
+  test "successive bursts get independent reports" do
+    demodulator = PhyModem.unified_demod_new(:psk8, 9600)
+    samples = burst(Enum.map(0..199, &rem(&1, 8)))
+
+    PhyModem.unified_demod_symbols(demodulator, samples)
+    first = PhyModem.unified_demod_decode_report(demodulator)
+    PhyModem.unified_demod_reset(demodulator)
+    assert PhyModem.unified_demod_decode_report(demodulator).acquisition == nil
+
+    PhyModem.unified_demod_symbols(demodulator, burst(List.duplicate(3, 500)))
+    PhyModem.unified_demod_reset(demodulator)
+    PhyModem.unified_demod_symbols(demodulator, samples)
+    assert PhyModem.unified_demod_decode_report(demodulator) == first
+  end
+
+  test "CBOR encoding" do
+    demodulator = PhyModem.unified_demod_new(:psk8, 9600)
+    PhyModem.unified_demod_symbols(demodulator, burst(List.duplicate(1, 100)))
+
+    # A map of 11 pairs, starting with "acquisition"
+    assert <<0xAB, 0x6B, "acquisition", _::binary>> =
+             PhyModem.unified_demod_decode_report(demodulator, :cbor)
+
+    assert PhyModem.unified_demod_decode_report(demodulator, :json) ==
+             {:error, {:invalid_argument, :format}}
+  end
+end
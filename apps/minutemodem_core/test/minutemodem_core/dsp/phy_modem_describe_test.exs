@@ -0,0 +1,112 @@
+defmodule MinuteModemCore.DSP.PhyModemDescribeTest do
+  use ExUnit.Case, async: true
+
+  alias MinuteModemCore.DSP.PhyModem
+
+  test "generic modulator reports its configuration and counts symbols" do
+    modulator = PhyModem.mod_new(:qpsk, 9600, 2400, 1500.0)
+    d = PhyModem.describe(modulator)
+
+    assert d.resource == :modulator
+    assert d.constellation == :qpsk
+    assert {d.sample_rate, d.symbol_rate, d.carrier_freq, d.sps} == {9600, 2400, 1500.0, 4}
+    assert {d.pulse, d.rrc_alpha, d.span, d.filter_len} == {:rrc, 0.35, 6, 49}
+    assert d.memory_bytes == 3 * 49 * 8
+    assert d.symbols_modulated == 0
+
+    PhyModem.mod_modulate(modulator, [0, 1, 2, 3, 0])
+    PhyModem.mod_reset(modulator)
+    assert PhyModem.describe(modulator).symbols_modulated == 5
+  end
+
+  test "generic demodulator counts demodulated symbols and reports input warnings" do
+    demodulator = PhyModem.demod_new(:psk8, 9600)
+    assert PhyModem.describe(demodulator).symbols_demodulated == 0
+
+    samples = PhyModem.mod_new(:psk8, 9600) |> PhyModem.mod_modulate(List.duplicate(0, 40))
+    symbols = PhyModem.demod_demodulate(demodulator, samples)
+    PhyModem.demod_set_input_warnings(demodulator, false)
+
+    d = PhyModem.describe(demodulator)
+    assert d.resource == :demodulator
+    assert d.symbols_demodulated == length(symbols)
+    assert d.input_warnings == false
+  end
+
+  test "unified modulator reports pulse, ramp and fingerprint" do
+    modulator = PhyModem.unified_mod_new(:qam16, 48000, pulse: :gaussian, bt: 0.5, ramp_ms: 1)
+    d = PhyModem.describe(modulator)
+
+    assert d.resource == :unified_modulator
+    assert d.constellation == :qam16
+    assert d.sps == 20
+    assert d.pulse == {:gaussian, 0.5}
+    assert d.span == 2
+    assert d.filter_len == 2 * 2 * 20 + 1
+    assert d.ramp_samples == 48
+    assert d.fingerprint == PhyModem.unified_mod_config_fingerprint(modulator)
+
+    PhyModem.unified_mod_set_constellation(modulator, :psk8)
+    assert PhyModem.describe(modulator).constellation == :psk8
+  end
+
+  test "unified modulator counts pushed symbols once pulled" do
+    modulator = PhyModem.unified_mod_new(:psk8, 9600)
+    PhyModem.unified_mod_modulate(modulator, [1, 2, 3])
+    PhyModem.unified_mod_push_symbols(modulator, [4, 5])
+
+    d = PhyModem.describe(modulator)
+    assert d.symbols_modulated == 3
+    assert d.queued_symbols == 2
+
+    PhyModem.unified_mod_pull_samples(modulator, 8)
+    PhyModem.unified_mod_reset(modulator)
+    assert PhyModem.describe(modulator).symbols_modulated == 5
+  end
+
+  test "unified demodulator without an equalizer" do
+    demodulator = PhyModem.unified_demod_new(:psk8, 9600)
+    d = PhyModem.describe(demodulator)
+
+    assert d.resource == :unified_demodulator
+    assert d.eq == nil
+    assert d.eq_taps == 0
+    assert d.pll.beta == 0.0
+    assert d.pll.freq_offset_hz == 0.0
+    assert d.gain_db == nil
+    refute d.rx_filter or d.eot or d.stage_timing or d.timing_tracking
+    assert d.fingerprint == PhyModem.unified_demod_config_fingerprint(demodulator)
+  end
+
+  test "unified demodulator with an equalizer and optional stages" do
+    demodulator = PhyModem.unified_demod_new_with_eq(:psk8, 9600, 11, 5, 0.02)
+    without_eq = PhyModem.unified_demod_new(:psk8, 9600) |> PhyModem.describe()
+    PhyModem.unified_demod_set_rx_filter(demodulator, :ssb_2k7)
+    PhyModem.unified_demod_enable_eot(demodulator)
+    PhyModem.unified_demod_enable_stage_timing(demodulator)
+
+    d = PhyModem.describe(demodulator)
+    assert %{mode: :cma, ff_taps: 11, fb_taps: 5, mu: 0.02} = d.eq
+    assert d.eq_taps == 16
+    assert d.memory_bytes - without_eq.memory_bytes == 2 * 16 * 16
+    assert d.rx_filter and d.eot and d.stage_timing
+  end
+
+  test "unified demodulator counts symbols across reset" do
+    samples =
+      PhyModem.unified_mod_new(:psk8, 9600) |> PhyModem.unified_mod_modulate(List.duplicate(0, 100))
+
+    demodulator = PhyModem.unified_demod_new(:psk8, 9600)
+    symbols = PhyModem.unified_demod_symbols(demodulator, samples)
+    iq = PhyModem.unified_demod_iq(demodulator, samples)
+    PhyModem.unified_demod_reset(demodulator)
+
+    d = PhyModem.describe(demodulator)
+    assert d.symbols_demodulated == length(symbols) + length(iq)
+    assert d.samples_consumed == 0
+  end
+
+  test "anything else is rejected" do
+    assert PhyModem.describe(:psk8) == {:error, {:invalid_argument, :resource}}
+  end
+end
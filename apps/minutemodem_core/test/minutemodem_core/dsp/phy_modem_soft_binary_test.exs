@@ -0,0 +1,69 @@
+defmodule MinuteModemCore.DSP.PhyModemSoftBinaryTest do
+  use ExUnit.Case, async: true
+
+  alias MinuteModemCore.DSP.{PhyModem, Wire}
+
+  # 3 s of QPSK at 9600 Hz
+  defp burst do
+    symbols = Enum.map(0..7199, &rem(&1 * 7, 4))
+    PhyModem.unified_mod_new(:qpsk, 9600) |> PhyModem.unified_mod_modulate(symbols)
+  end
+
+  defp soft(opts) do
+    PhyModem.unified_demod_new(:qpsk, 9600) |> PhyModem.unified_demod_symbols(burst(), opts)
+  end
+
+  test "binary fields hold the same values as the separate returns" do
+    {symbols, confidences} = soft(confidence: true)
+    %{symbols: s, confidences: c, iq: iq} = soft(binary: true)
+
+    assert s == Wire.encode_symbols(symbols, 2)
+    assert c == for(x <- confidences, into: <<>>, do: <<x::float-little-64>>)
+    assert {:ok, {:iq, points}} = Wire.decode(iq)
+    assert length(points) == length(symbols)
+
+    # Past the filters' start-up, where points sit clear of the boundaries
+    # even rounded to f32
+    for {{i, q}, symbol} <- Enum.zip(points, symbols) |> Enum.drop(20) do
+      assert PhyModem.iq_to_symbol(:qpsk, i, q) == symbol
+    end
+  end
+
+  test "fields are sub-binaries of one allocation" do
+    %{symbols: s, confidences: c, iq: iq} = soft(binary: true)
+
+    for field <- [s, c, iq] do
+      assert :binary.referenced_byte_size(field) == byte_size(s) + byte_size(c) + byte_size(iq)
+    end
+
+    {symbols, confidences} = soft(confidence: true)
+    lists = :erts_debug.flat_size({symbols, confidences})
+    binaries = :erts_debug.flat_size({s, c, iq})
+    assert binaries * 100 < lists
+  end
+
+  test "a field outlives its garbage collected siblings" do
+    parent = self()
+
+    holder =
+      spawn(fn ->
+        %{symbols: s, confidences: c, iq: iq} = soft(binary: true)
+        expected = :binary.copy(s)
+        send(parent, {:sizes, byte_size(c), byte_size(iq)})
+        # Drop the siblings and collect them before reading what's left
+        :erlang.garbage_collect()
+
+        receive do
+          :check -> send(parent, {:intact, s == expected, :binary.copy(s)})
+        end
+      end)
+
+    assert_receive {:sizes, c_size, iq_size}
+    assert c_size > 0 and iq_size > 0
+    :erlang.garbage_collect()
+    send(holder, :check)
+    assert_receive {:intact, true, symbols}
+    assert {:ok, {:symbols, 2, decoded}} = Wire.decode(symbols)
+    assert length(decoded) * 8 == c_size
+  end
+end
@@ -0,0 +1,43 @@
+defmodule MinuteModemCore.DSP.PhyModemWalshTest do
+  use ExUnit.Case, async: true
+
+  alias MinuteModemCore.ALE.Waveform.Walsh
+  alias MinuteModemCore.DSP.PhyModem
+
+  defp dwell_iq(quadbit, phase) do
+    Walsh.walsh_16(quadbit)
+    |> Enum.map(fn sym ->
+      angle = sym * :math.pi() / 4 + phase
+      {:math.cos(angle), :math.sin(angle)}
+    end)
+  end
+
+  test "recovers every quad-bit of Table G-IX at any carrier phase" do
+    iq = Enum.flat_map(0..15, &dwell_iq(&1, 0.4 * &1))
+    dwells = PhyModem.walsh_correlate(iq)
+
+    assert Enum.map(dwells, & &1.best) == Enum.to_list(0..15)
+
+    for %{metric: metric, metrics: metrics} <- dwells do
+      assert_in_delta metric, 1.0, 1.0e-9
+      assert length(metrics) == 16
+      assert_in_delta Enum.sum(metrics), 1.0, 1.0e-9
+    end
+  end
+
+  test "streaming matches batch across arbitrary block boundaries" do
+    iq = Enum.flat_map([3, 9, 14], &dwell_iq(&1, 1.0)) ++ Enum.take(dwell_iq(5, 0.0), 20)
+    correlator = PhyModem.walsh_correlator_new()
+
+    streamed = iq |> Enum.chunk_every(50) |> Enum.flat_map(&PhyModem.walsh_correlator_push(correlator, &1))
+    assert streamed == PhyModem.walsh_correlate(iq)
+    assert Enum.map(streamed, & &1.best) == [3, 9, 14]
+
+    assert PhyModem.walsh_correlator_reset(correlator) == :ok
+    assert [%{best: 5}] = PhyModem.walsh_correlator_push(correlator, dwell_iq(5, 0.0))
+  end
+
+  test "bad I/Q is rejected" do
+    assert PhyModem.walsh_correlate(:psk8) == {:error, {:invalid_argument, :iq}}
+  end
+end
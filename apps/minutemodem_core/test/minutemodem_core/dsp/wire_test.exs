@@ -0,0 +1,47 @@
+defmodule MinuteModemCore.DSP.WireTest do
+  use ExUnit.Case, async: true
+
+  import Bitwise
+
+  alias MinuteModemCore.DSP.{PhyModem, Wire}
+
+  test "every format round-trips" do
+    for n <- [0, 1, 7, 64] do
+      points = for k <- 1..n//1, do: {k * 0.25, -k * 0.5}
+      assert Wire.decode(Wire.encode_iq(points)) == {:ok, {:iq, points}}
+
+      llrs = for k <- 1..n//1, do: k * -1.5
+      assert Wire.decode(Wire.encode_llrs(llrs)) == {:ok, {:llrs, llrs}}
+
+      for bits <- 1..8 do
+        symbols = for k <- 1..n//1, do: rem(k * 37, 1 <<< bits)
+        assert Wire.decode(Wire.encode_symbols(symbols, bits)) == {:ok, {:symbols, bits, symbols}}
+      end
+    end
+  end
+
+  test "corrupted headers are rejected" do
+    good = Wire.encode_iq([{1.0, 2.0}, {3.0, 4.0}])
+    <<_::binary-12, payload::binary>> = good
+
+    assert Wire.decode(binary_part(good, 0, 11)) == {:error, :too_short}
+    assert Wire.decode(payload <> payload) == {:error, :bad_magic}
+    assert Wire.decode(<<"MMWF", 2, 1, 32, 0, 2::little-32>> <> payload) == {:error, :unsupported_version}
+    assert Wire.decode(<<"MMWF", 1, 9, 32, 0, 2::little-32>> <> payload) == {:error, :unknown_format}
+    assert Wire.decode(<<"MMWF", 1, 1, 16, 0, 2::little-32>> <> payload) == {:error, :bad_element}
+    assert Wire.decode(<<"MMWF", 1, 1, 32, 1, 2::little-32>> <> payload) == {:error, :bad_reserved}
+    assert Wire.decode(<<"MMWF", 1, 1, 32, 0, 3::little-32>> <> payload) == {:error, :length_mismatch}
+  end
+
+  test "the NIFs read and write the same format" do
+    shaper = PhyModem.pulse_shaper_new(4, 0.35, 6)
+    shaped = PhyModem.pulse_shaper_process(shaper, Wire.encode_iq([{1.0, 0.0}, {0.0, -1.0}]))
+    assert {:ok, {:iq, points}} = Wire.decode(shaped)
+    assert length(points) == 8
+
+    llrs = PhyModem.bit_llrs_block(:qpsk, Wire.encode_iq([{0.7, -0.7}]), 0.1)
+    assert {:ok, {:llrs, block}} = Wire.decode(llrs)
+    expected = PhyModem.bit_llrs(:qpsk, 0.7, -0.7, 0.1)
+    assert Enum.zip(block, expected) |> Enum.all?(fn {a, b} -> abs(a - b) <= 1.0e-5 * abs(b) end)
+  end
+end
@@ -29,7 +29,22 @@ defmodule MinutemodemSimnet.Application do
     ]
 
     opts = [strategy: :one_for_one, name: MinutemodemSimnet.Supervisor]
-    Supervisor.start_link(children, opts)
+
+    with :ok <- self_test() do
+      Supervisor.start_link(children, opts)
+    end
+  end
+
+  # Refuse to start on a channel_physics build that computes garbage
+  defp self_test do
+    if Application.get_env(:minutemodem_simnet, :nif_self_test, true) do
+      case MinutemodemSimnet.Physics.Nif.self_test() do
+        {:ok, _report} -> :ok
+        {:error, failures} -> {:error, {:nif_self_test_failed, failures}}
+      end
+    else
+      :ok
+    end
   end
 
   defp topologies do
@@ -12,63 +12,253 @@ defmodule MinutemodemSimnet.Physics.Channel do
   @doc """
   Creates a new Watterson channel with the given parameters.
 
+  A plain map may warm-start the fading with `start_at_time_s: t` and/or
+  `start_in_fade: depth_db` (see `Types.ChannelParams`).
+
   Returns the channel ID (slab handle) on success.
   """
   @spec create(map() | ChannelParams.t(), integer()) ::
           {:ok, non_neg_integer()} | {:error, term()}
-  def create(%ChannelParams{} = params, seed) do
-    # NIF expects the struct directly
-    nif_params = %MinutemodemSimnet.Physics.Types.ChannelParams{
-      sample_rate: params.sample_rate,
-      delay_spread_samples: params.delay_spread_samples,
-      doppler_bandwidth_hz: params.doppler_bandwidth_hz,
-      snr_db: params.snr_db,
-      carrier_freq_hz: params.carrier_freq_hz || 1800.0
-    }
+  def create(params, seed) do
+    Nif.create_channel(nif_params(params), seed)
+  end
 
-    Nif.create_channel(nif_params, seed)
+  @doc """
+  Creates a channel from a scenario in the Watterson JSON exchange
+  format (see `params_from_json/1`).
+  """
+  @spec create_from_json(binary(), integer()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def create_from_json(json, seed) when is_binary(json) do
+    Nif.create_channel_from_json(json, seed)
   end
 
-  def create(%MinutemodemSimnet.Channel.Params{} = params, seed) do
-    # Convert from Channel.Params (has delay_spread_ms) to NIF params (has delay_spread_samples)
-    delay_spread_samples =
-      round((params.delay_spread_ms || 0) * (params.sample_rate || 9600) / 1000)
+  @doc """
+  Reads channel parameters from a scenario in the JSON format partners
+  exchange:
 
-    nif_params = %MinutemodemSimnet.Physics.Types.ChannelParams{
-      sample_rate: params.sample_rate || 9600,
-      delay_spread_samples: delay_spread_samples,
-      doppler_bandwidth_hz: params.doppler_bandwidth_hz || 1.0,
-      snr_db: params.snr_db || 10.0,
-      carrier_freq_hz: params.carrier_freq_hz || 1800.0
-    }
+      {
+        "format": "watterson-channel",
+        "version": 1,
+        "metadata": {"name": "CCIR poor"},
+        "sample_rate_hz": 9600,
+        "snr_db": 10.0,
+        "carrier_hz": 1800.0,
+        "paths": [
+          {"delay_ms": 0.0, "doppler_spread_hz": 1.0, "relative_db": 0.0},
+          {"delay_ms": 2.0, "doppler_spread_hz": 1.0, "relative_db": 0.0}
+        ]
+      }
 
-    Nif.create_channel(nif_params, seed)
+  `sample_rate_hz` and `carrier_hz` are optional. One or two paths: the
+  first at 0 ms, both with the same Doppler spread, as the two-path model
+  has them; each path's `relative_db` becomes its `tap0_gain_db` or
+  `tap1_gain_db`. The second path's delay is rounded to whole samples.
+  Errors are `{:invalid_json, line, column, message}` or
+  `{:schema_error, json_path, message}`.
+  """
+  @spec params_from_json(binary()) :: {:ok, ChannelParams.t()} | {:error, term()}
+  def params_from_json(json) when is_binary(json) do
+    Nif.channel_params_from_json(json)
   end
 
-  def create(params, seed) when is_map(params) do
-    # Convert plain map to proper struct for NIF
-    # Handles both delay_spread_ms and delay_spread_samples
-    sample_rate = Map.get(params, :sample_rate, 9600)
+  @doc """
+  Writes `params` as a JSON scenario. Fields the format has no place for
+  (bulk delay, output stage, seeds, warm start, clock drift) are left out.
+  """
+  @spec params_to_json(map() | ChannelParams.t()) :: {:ok, binary()} | {:error, term()}
+  def params_to_json(params) do
+    Nif.channel_params_to_json(nif_params(params))
+  end
 
-    delay_spread_samples =
-      case Map.get(params, :delay_spread_samples) do
-        nil ->
-          delay_ms = Map.get(params, :delay_spread_ms, 0)
-          round(delay_ms * sample_rate / 1000)
+  @doc """
+  Works a link budget in real-world terms out to the channel's `snr_db`.
 
-        samples ->
-          samples
-      end
+      Channel.link_budget(
+        %{
+          tx_power_dbm: 50.0,
+          path_loss_db: 120.0,
+          noise_figure_db: 10.0,
+          bandwidth_hz: 3000.0,
+          noise_environment: :residential,
+          freq_hz: 10.0e6
+        },
+        :poor
+      )
+      #=> {:ok, 24.43, %ChannelParams{snr_db: 22.39, delay_spread_samples: 19, ...}}
 
-    nif_params = %MinutemodemSimnet.Physics.Types.ChannelParams{
-      sample_rate: sample_rate,
-      delay_spread_samples: delay_spread_samples,
-      doppler_bandwidth_hz: Map.get(params, :doppler_bandwidth_hz, 1.0),
-      snr_db: Map.get(params, :snr_db, 10.0),
-      carrier_freq_hz: Map.get(params, :carrier_freq_hz, 1800.0)
-    }
+  `path_loss_db` covers everything between the transmitter and receiver
+  terminals, antenna gains included. The noise is kT0 in the bandwidth
+  raised by the receiver's `noise_figure_db` and the ITU-R P.372
+  man-made noise of the `noise_environment` (`:city`, `:residential`,
+  `:rural` or `:quiet_rural`) at `freq_hz`.
+
+  Returns the SNR in the bandwidth and parameters at 9600 Hz with a
+  1800 Hz carrier whose `snr_db` gives it to a modem at the reference
+  level that occupies the bandwidth. `preset` sets the fading, after
+  ITU-R F.1487: `:awgn` (none), `:good` (0.5 ms, 0.1 Hz), `:moderate`
+  (1 ms, 0.5 Hz), `:poor` (2 ms, 1 Hz) or `:flutter` (0.5 ms, 10 Hz).
+  """
+  @spec link_budget(map(), atom()) :: {:ok, float(), ChannelParams.t()} | {:error, term()}
+  def link_budget(budget, preset \\ :awgn) when is_map(budget) do
+    Nif.link_budget(budget, preset)
+  end
+
+  @doc """
+  The path loss at which `budget` (see `link_budget/2`) gives `snr_db`
+  in its bandwidth, e.g. how far a link can go for 10 dB. The budget
+  needn't have a `path_loss_db`.
+  """
+  @spec path_loss_for_snr(map(), number()) :: {:ok, float()} | {:error, term()}
+  def path_loss_for_snr(budget, snr_db) when is_map(budget) do
+    Nif.path_loss_for_snr(Map.put_new(budget, :path_loss_db, 0.0), snr_db / 1)
+  end
+
+  @doc """
+  Channel parameters for a named condition, with the delay converted to
+  samples at `sample_rate` and a 1800 Hz carrier:
+
+  | preset                         | delay  | Doppler |
+  |--------------------------------|--------|---------|
+  | `:awgn`                        | -      | -       |
+  | `:ccir_good`                   | 0.5 ms | 0.1 Hz  |
+  | `:ccir_moderate`               | 1 ms   | 0.5 Hz  |
+  | `:ccir_poor`                   | 2 ms   | 1 Hz    |
+  | `:ccir_flutter`                | 0.5 ms | 10 Hz   |
+  | `:low_latitude_quiet`          | 0.5 ms | 0.5 Hz  |
+  | `:low_latitude_moderate`       | 2 ms   | 1.5 Hz  |
+  | `:low_latitude_disturbed`      | 6 ms   | 10 Hz   |
+  | `:mid_latitude_quiet`          | 0.5 ms | 0.1 Hz  |
+  | `:mid_latitude_moderate`       | 1 ms   | 0.5 Hz  |
+  | `:mid_latitude_disturbed`      | 2 ms   | 1 Hz    |
+  | `:mid_latitude_disturbed_nvis` | 7 ms   | 1 Hz    |
+  | `:high_latitude_quiet`         | 1 ms   | 0.5 Hz  |
+  | `:high_latitude_moderate`      | 3 ms   | 10 Hz   |
+  | `:high_latitude_disturbed`     | 7 ms   | 30 Hz   |
+
+  The CCIR channels are Rec. 520's, the rest ITU-R F.1487's.
 
-    Nif.create_channel(nif_params, seed)
+      Channel.preset_params(:ccir_poor, 9600, 10)
+      #=> {:ok, %ChannelParams{delay_spread_samples: 19, doppler_bandwidth_hz: 1.0, ...}}
+  """
+  @spec preset_params(atom(), pos_integer(), number()) :: {:ok, ChannelParams.t()} | {:error, term()}
+  def preset_params(preset, sample_rate, snr_db) when is_atom(preset) do
+    Nif.preset_params(preset, sample_rate, snr_db / 1)
+  end
+
+  @doc """
+  Creates a channel with `preset_params/3`'s parameters.
+  """
+  @spec create_preset(atom(), pos_integer(), number(), integer()) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def create_preset(preset, sample_rate, snr_db, seed) when is_atom(preset) do
+    Nif.create_channel_preset(preset, sample_rate, snr_db / 1, seed)
+  end
+
+  @doc """
+  Creates `n_outputs` channels that see one fading environment through
+  correlated receivers, e.g. the antennas of a diversity receiver.
+
+  `correlation` is the n_outputs x n_outputs envelope correlation matrix:
+  entry `[i][j]` is the correlation coefficient between the fading power
+  of outputs i and j (symmetric, unit diagonal, entries in [0.0, 1.0]).
+  HF spatial diversity typically sees 0.6-0.9. Each output's noise is
+  independent. With one output the channel is exactly the one `create/2`
+  makes from the same params and seed.
+
+  Returns `{:ok, set_id, channel_ids}`. The members are ordinary channels
+  for `get_state/1`, `update_params/2`, `set_fade_alarm/4` and so on, but
+  drive them with `process_set/2`: processing one on its own uses its
+  unmixed fading and leaves it out of step with the rest of the set.
+
+  Returns `{:error, "invalid_correlation"}` for a malformed or impossible
+  matrix; `start_in_fade` isn't supported for sets.
+  """
+  @spec create_correlated_set(map() | ChannelParams.t(), pos_integer(), [[number()]], integer()) ::
+          {:ok, non_neg_integer(), [non_neg_integer()]} | {:error, term()}
+  def create_correlated_set(params, n_outputs, correlation, seed) do
+    correlation = Enum.map(correlation, fn row -> Enum.map(row, &(&1 * 1.0)) end)
+    Nif.create_correlated_set(nif_params(params), n_outputs, correlation, seed)
+  end
+
+  @doc """
+  Runs one block of native-endian f32 input through every output of a
+  correlated set, returning `{:ok, outputs}` with one f32 binary per
+  output in `channel_ids` order.
+  """
+  @spec process_set(non_neg_integer(), binary()) :: {:ok, [binary()]} | {:error, term()}
+  def process_set(set_id, input_samples) when is_binary(input_samples) do
+    Nif.process_set(set_id, input_samples)
+  end
+
+  @doc """
+  Destroys a correlated set and its member channels.
+  """
+  @spec destroy_set(non_neg_integer()) :: :ok
+  def destroy_set(set_id) do
+    Nif.destroy_set(set_id)
+  end
+
+  @doc """
+  Creates a channel group: a patch of ionosphere whose weather every
+  member channel shares.
+
+  The group drifts an SNR offset (dB) and a Doppler spread offset (Hz)
+  as slow Gaussian processes, each with standard deviation
+  `snr_sigma_db` / `doppler_sigma_hz` and decorrelating over
+  `correlation_time_s` seconds of simulation time (exponential
+  autocorrelation). Members add the offsets to their own `snr_db` and
+  `doppler_bandwidth_hz` at the start of each block they process or
+  advance, so channels in one group fade in and out together while
+  different groups vary independently. The offsets depend only on
+  `seed` and the simulation time.
+
+      {:ok, group} =
+        Channel.create_group(%{
+          correlation_time_s: 300.0,
+          snr_sigma_db: 3.0,
+          doppler_sigma_hz: 0.3,
+          seed: 7
+        })
+
+      {:ok, ch} = Channel.create_in_group(params, 1, group)
+  """
+  @spec create_group(map()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def create_group(%{correlation_time_s: t, snr_sigma_db: snr, doppler_sigma_hz: doppler} = params) do
+    Nif.create_group(%{
+      correlation_time_s: t / 1,
+      snr_sigma_db: snr / 1,
+      doppler_sigma_hz: doppler / 1,
+      seed: Map.get(params, :seed, 0)
+    })
+  end
+
+  @doc """
+  Creates a channel as `create/2` does that follows a group's weather
+  (see `create_group/1`).
+  """
+  @spec create_in_group(map() | ChannelParams.t(), integer(), non_neg_integer()) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def create_in_group(params, seed, group_id) do
+    Nif.create_channel_in_group(nif_params(params), seed, group_id)
+  end
+
+  @doc """
+  Returns `{:ok, state}` with the group's current SNR and Doppler
+  offsets (at the furthest time any member has reached) and its live
+  members.
+  """
+  @spec get_group_state(non_neg_integer()) :: {:ok, map()} | {:error, term()}
+  def get_group_state(group_id) do
+    Nif.get_group_state(group_id)
+  end
+
+  @doc """
+  Disbands a group. Its channels carry on at their own `snr_db` and
+  `doppler_bandwidth_hz`.
+  """
+  @spec disband_group(non_neg_integer()) :: :ok
+  def disband_group(group_id) do
+    Nif.disband_group(group_id)
   end
 
   @doc """
@@ -77,7 +267,10 @@ defmodule MinutemodemSimnet.Physics.Channel do
   Applies two-path Watterson fading, delay, and noise.
   Returns the impaired output samples as a binary of f32 values.
 
-  Input and output are native-endian f32 binaries.
+  Input and output are native-endian f32 binaries. The NIF runs on a
+  dirty CPU scheduler, so blocks of any audio-rate length are fine; the
+  most it takes is 1_920_000 samples (10 s at 192 kHz), past which it
+  returns `{:error, {:out_of_range, :input_samples, 1_920_000}}`.
   """
   @spec process_block(non_neg_integer(), binary()) ::
           {:ok, binary()} | {:error, term()}
@@ -86,17 +279,381 @@ defmodule MinutemodemSimnet.Physics.Channel do
     Nif.process_block(channel_id, input_samples)
   end
 
+  @doc """
+  Processes a block whose first sample is `start_sample_index` on the
+  caller's clock, e.g. a capture pipeline's running sample count.
+
+  Returns `{:ok, output, discontinuity}`. When the index matches the
+  channel's (see `time/1`) this is `process_block/2` and
+  `discontinuity` is `:none`. Otherwise:
+
+    * `{:gap, n}` - the block starts `n` samples late (a dropped buffer).
+      The channel runs on through the gap as through silence, like
+      `run_until/3`, instead of joining the blocks and shifting every
+      later sample.
+    * `{:overlap, n}` - the block starts `n` samples early. Those samples
+      were already processed and are cut from its front, so `output` is
+      shorter than the input (empty if the whole block is old).
+  """
+  @spec process_block_at(non_neg_integer(), non_neg_integer(), binary()) ::
+          {:ok, binary(), :none | {:gap | :overlap, pos_integer()}} | {:error, term()}
+  def process_block_at(channel_id, start_sample_index, input_samples)
+      when is_integer(start_sample_index) and start_sample_index >= 0 and
+             is_binary(input_samples) do
+    Nif.process_block_at(channel_id, start_sample_index, input_samples)
+  end
+
+  @doc """
+  Processes a block with explicit sample formats.
+
+  `input_format` and `output_format` are `:f32ne`, `:f64ne` or `:s24le`
+  (packed 3-byte signed little-endian, ±1.0 full scale). Use the f64 or
+  s24 formats for high-dynamic-range captures; the channel computes in
+  f64 internally.
+
+  Between nodes on hosts of different byte order, use `:f32le` or
+  `:f32be` (network order), or `:f32tagged`: f32 in the writer's own
+  order after a 4-byte magic (`"MMF4"` big-endian, `"4FMM"`
+  little-endian) the reader takes the order from. A `:f32tagged` input
+  without the magic is `{:error, "bad_endian_magic"}`.
+  """
+  @spec process_block_fmt(non_neg_integer(), binary(), atom(), atom()) ::
+          {:ok, binary()} | {:error, term()}
+  def process_block_fmt(channel_id, input_samples, input_format \\ :f64ne, output_format \\ :f64ne)
+      when is_binary(input_samples) do
+    Nif.process_block_fmt(channel_id, input_samples, input_format, output_format)
+  end
+
+  @doc """
+  Processes a block of complex baseband samples.
+
+  `input_iq` and the output are IQ_F32_INTERLEAVED_V1 binaries (see
+  `MinuteModemCore.DSP.PhyModem`, "Wire format"), one I/Q pair per
+  sample at the channel's sample rate. The fading, echo, complex AWGN at
+  the configured SNR and any TR switching apply directly to the
+  envelope; the carrier mixing and baseband filters of `process_block/2`
+  are skipped, and so is their group delay.
+
+  The fading, noise and sample count are shared with `process_block/2`,
+  so a channel can be driven through either from one block to the next;
+  a switch moves the timing by the filters' group delay. A channel with
+  a bulk delay, input conditioning, fractional delay, clock drift or
+  output quantization/clipping works on the real sound-card signal and
+  returns `{:error, "unsupported_for_iq"}`.
+  """
+  @spec process_block_iq(non_neg_integer(), binary()) :: {:ok, binary()} | {:error, term()}
+  def process_block_iq(channel_id, input_iq) when is_binary(input_iq) do
+    Nif.process_block_iq(channel_id, input_iq)
+  end
+
+  @doc """
+  Processes a block, returning `{impaired, reference}`.
+
+  `reference` is what the channel would output with the direct path's
+  fading held at unity and no noise: same mix-down/LPF/mix-up path, same
+  bulk and filter delays. It is sample-aligned with `impaired`, so
+  `impaired - reference` is the fading and noise distortion, ready for
+  error-vector measurements.
+
+  Input and both outputs are native-endian f32 binaries.
+  """
+  @spec process_block_with_reference(non_neg_integer(), binary()) ::
+          {:ok, {binary(), binary()}} | {:error, term()}
+  def process_block_with_reference(channel_id, input_samples) when is_binary(input_samples) do
+    Nif.process_block_with_reference(channel_id, input_samples)
+  end
+
   @doc """
   Advances channel state by N samples without processing.
 
   Used when a channel needs to stay synchronized with
-  simulation time but isn't receiving TX blocks.
+  simulation time but isn't receiving TX blocks. However long the
+  advance, it holds the channel for no more than 48_000 samples at a
+  time, so blocks for it from other processes aren't held up behind it.
   """
   @spec advance(non_neg_integer(), non_neg_integer()) :: :ok | {:error, term()}
   def advance(channel_id, num_samples) do
     Nif.advance(channel_id, num_samples)
   end
 
+  @doc """
+  Advances many channels at once, e.g. every idle channel on a tick.
+
+  Equivalent to calling `advance/2` for each `{channel_id, num_samples}`
+  in order, but in one NIF call. Missing channels are reported per entry.
+  """
+  @spec advance_many([{non_neg_integer(), non_neg_integer()}]) ::
+          {:ok, [{non_neg_integer(), :ok | {:error, :channel_not_found}}]} | {:error, term()}
+  def advance_many(requests) when is_list(requests) do
+    Nif.advance_many(requests)
+  end
+
+  @doc """
+  Processes a block on each of many channels at once, e.g. every station
+  of a net on an audio tick.
+
+  Equivalent to calling `process_block/2` for each `{channel_id,
+  input_samples}` in order, outputs and channel state alike, but in one
+  NIF call that runs the channels in parallel. Missing channels are
+  reported per entry.
+  """
+  @spec process_blocks([{non_neg_integer(), binary()}]) ::
+          {:ok, [{non_neg_integer(), binary() | {:error, :channel_not_found}}]} | {:error, term()}
+  def process_blocks(requests) when is_list(requests) do
+    Nif.process_blocks(requests)
+  end
+
+  @doc """
+  Updates a live channel's parameters.
+
+  Only `snr_db`, `bulk_delay_samples`, the input conditioning
+  (`input_dc_block`, `input_tilt_db`), `sideband_inversion`,
+  `frequency_offset_hz`, the output stage fields (`output_bits`,
+  `output_dither`, `clip_knee`) and `bypass` may differ from the values
+  the channel was created with; anything else returns `{:error,
+  "immutable_param_changed"}`. A new bulk delay is reached by slewing at
+  1 sample per 1000 (a path-length change), not a jump.
+
+  Toggling `bypass` drops whatever is in flight, as `reset(channel_id,
+  :preserve)` does: entering bypass passes the input straight through from
+  the next sample, and leaving it starts from an idle channel.
+  """
+  @spec update_params(non_neg_integer(), ChannelParams.t()) :: :ok | {:error, term()}
+  def update_params(channel_id, %ChannelParams{} = params) do
+    Nif.update_params(channel_id, params)
+  end
+
+  @doc """
+  Returns a channel to idle, e.g. when a scenario aborts mid-burst.
+
+  Clears everything in flight: the bulk delay line (settled at its target,
+  no slew pending), the multipath delay line, the baseband filter
+  histories and the carrier phase. Parameters, including any
+  `update_params/2` changes, are kept.
+
+  `rng` controls the random sequences (fading, noise, dither):
+
+    * `:preserve` - they continue from their current position
+    * `:reseed` - they restart from the channel's seed; the channel is then
+      identical to a newly created one
+    * `{:reseed, seed}` - they restart from `seed`
+  """
+  @spec reset(non_neg_integer(), :preserve | :reseed | {:reseed, non_neg_integer()}) ::
+          :ok | {:error, term()}
+  def reset(channel_id, rng \\ :preserve) do
+    Nif.reset_channel(channel_id, rng)
+  end
+
+  @doc """
+  Restarts a channel's AWGN from `noise_seed`.
+
+  Fading, filters, delays and dither carry on untouched; the noise from
+  here on is what a channel created with `noise_seed: noise_seed` produces
+  from its first sample. Use it to re-run one fading realization with
+  fresh noise draws.
+  """
+  @spec reseed_noise(non_neg_integer(), non_neg_integer()) :: :ok | {:error, term()}
+  def reseed_noise(channel_id, noise_seed) do
+    Nif.reseed_noise(channel_id, noise_seed)
+  end
+
+  @doc """
+  Hops the channel's carrier for a frequency-hopping transmitter.
+
+  `hops` is a list of `{at_sample, carrier_hz}`: from input sample
+  `at_sample` on, the channel mixes at `carrier_hz` (a float, strictly
+  between 0 and Nyquist). `at_sample` must strictly increase from the
+  channel's current sample index. The mixer retunes as each sample reaches
+  it after the bulk delay, keeping its phase, and the fading and
+  multipath carry on across hops as the same physical path. A new
+  schedule replaces any hops still pending; `reset/2` keeps them unless it
+  reseeds. Returns `{:error, "invalid_hop_schedule"}` for a bad schedule.
+  """
+  @spec set_hop_schedule(non_neg_integer(), [{non_neg_integer(), float()}]) ::
+          :ok | {:error, term()}
+  def set_hop_schedule(channel_id, hops) when is_list(hops) do
+    Nif.set_hop_schedule(channel_id, hops)
+  end
+
+  @doc """
+  Mutes the channel while the receiving radio transmits, as a half-duplex
+  radio's T/R switch does.
+
+  `schedule` is a list of `{at_sample, :tx | :rx}`: from output sample
+  `at_sample` on, the receiver is transmitting (output, noise included,
+  muted) or listening (full gain), moving between the two along a
+  raised-cosine ramp. The receiver starts out listening; `at_sample` must
+  strictly increase from the channel's current sample index. A new
+  schedule replaces the old one, and `[]` turns muting off; `reset/2`
+  keeps it unless it reseeds. Bypass ignores it. `get_state/1` reports
+  the current `tr_state`. Returns `{:error, "invalid_tr_schedule"}` for a
+  bad schedule or option.
+
+  ## Options
+
+    * `:attenuation_db` - attenuate by this many dB rather than mute
+      (default `nil`, mute)
+    * `:ramp_ms` - length of each switching ramp, 0 to 1000 ms
+      (default `2.0`)
+  """
+  @spec set_tr_schedule(non_neg_integer(), [{non_neg_integer(), :tx | :rx}], keyword()) ::
+          :ok | {:error, term()}
+  def set_tr_schedule(channel_id, schedule, opts \\ []) when is_list(schedule) do
+    Nif.set_tr_schedule(
+      channel_id,
+      schedule,
+      Keyword.get(opts, :attenuation_db),
+      Keyword.get(opts, :ramp_ms, 2.0)
+    )
+  end
+
+  @doc """
+  Asks for a message whenever the channel goes into or out of a deep fade.
+
+  The fade depth is the combined power of the fading taps relative to
+  their long-term mean. When it drops below `threshold_db`, `pid` receives
+
+      {:fade, channel_id, :start, sample_index}
+
+  and once it climbs back above `threshold_db + hysteresis_db`
+
+      {:fade, channel_id, :end, sample_index}
+
+  `sample_index` is the exact channel sample of the crossing. Messages are
+  sent from the `process_block*/2` or `advance/2` call that processed that
+  sample, so no polling is needed. Setting a new alarm replaces the old one;
+  one pid may watch any number of channels. A channel without fading
+  (`doppler_bandwidth_hz: 0.0`) or in bypass never fades.
+
+  Returns `{:error, "invalid_fade_alarm"}` for a negative hysteresis.
+  """
+  @spec set_fade_alarm(non_neg_integer(), float(), float(), pid()) :: :ok | {:error, term()}
+  def set_fade_alarm(channel_id, threshold_db, hysteresis_db \\ 3.0, pid \\ self()) do
+    Nif.set_fade_alarm(channel_id, threshold_db * 1.0, hysteresis_db * 1.0, pid)
+  end
+
+  @doc """
+  Stops fade messages for a channel.
+  """
+  @spec clear_fade_alarm(non_neg_integer()) :: :ok | {:error, term()}
+  def clear_fade_alarm(channel_id) do
+    Nif.clear_fade_alarm(channel_id)
+  end
+
+  @doc """
+  Sends input level warnings to `pid` from now on; `nil` stops them.
+
+  A float input block peaking far above full scale (±1.0), typically
+  unscaled i16 PCM, is still processed, but
+  `{:input_warning, :suspicious_input_level, channel_id, peak}` goes to
+  the logger.
+  """
+  @spec set_warning_logger(pid() | nil) :: :ok | {:error, term()}
+  def set_warning_logger(pid \\ self()) do
+    Nif.set_warning_logger(pid)
+  end
+
+  @doc """
+  Turns a channel's input level warnings on or off (on by default).
+  """
+  @spec set_input_warnings(non_neg_integer(), boolean()) :: :ok | {:error, term()}
+  def set_input_warnings(channel_id, enabled) when is_boolean(enabled) do
+    Nif.set_input_warnings(channel_id, enabled)
+  end
+
+  @doc """
+  Returns the channel's audit log as `{:ok, entries, dropped}`.
+
+  Every change to what the channel does is logged with the sample index
+  it applied from, oldest first:
+
+    * `{sample_index, :created, params, seed}`
+    * `{sample_index, :update_params, old_params, new_params}`
+    * `{sample_index, :reseed_noise, old_noise_seed | nil, new_noise_seed}`
+    * `{sample_index, :reseed, old_seed, new_seed}` (`reset_to_idle` with
+      a new seed; the sample index restarts at 0 after it)
+    * `{sample_index, :hop_schedule, hops}`
+    * `{sample_index, :tr_schedule, schedule, attenuation_db | nil, ramp_ms}`
+
+  The log keeps the newest 256 entries unless `set_audit_cap/2` says
+  otherwise; `dropped` counts the ones it let go.
+
+  ## Options
+
+    * `:clear` - empty the log (and reset `dropped`) after reading it
+      (default `false`)
+  """
+  @spec audit_log(non_neg_integer(), keyword()) ::
+          {:ok, [tuple()], non_neg_integer()} | {:error, term()}
+  def audit_log(channel_id, opts \\ []) do
+    Nif.get_audit_log(channel_id, Keyword.get(opts, :clear, false))
+  end
+
+  @doc """
+  Sets how many entries the channel's audit log keeps, dropping the oldest
+  if it shrinks.
+  """
+  @spec set_audit_cap(non_neg_integer(), pos_integer()) :: :ok | {:error, term()}
+  def set_audit_cap(channel_id, cap) when is_integer(cap) and cap > 0 do
+    Nif.set_audit_cap(channel_id, cap)
+  end
+
+  @doc """
+  Starts logging the carrier phase the channel mixes each output sample
+  up at, including the filters' group-delay compensation, for every
+  `decimation`th sample (those whose sample index is a multiple of it).
+  `decimation` 0 stops logging. Either way, entries held are dropped.
+
+  With the log, `derotate/3` takes output back to the complex baseband
+  the channel faded, for coherent analysis of the fading alone. Between
+  entries the phase is extrapolated, exactly except across a carrier hop;
+  log every sample to follow hops. The log holds at most 1_048_576
+  entries, dropping the oldest. Processing only: `advance/2` and bypass
+  log nothing.
+  """
+  @spec set_phase_log(non_neg_integer(), non_neg_integer()) :: :ok | {:error, term()}
+  def set_phase_log(channel_id, decimation) when is_integer(decimation) and decimation >= 0 do
+    Nif.set_phase_log(channel_id, decimation)
+  end
+
+  @doc """
+  Returns `{:ok, entries, dropped}`: `{sample_index, phase, step}` per
+  logged sample, oldest first, with the phase in radians and the step it
+  moves by per sample.
+
+  ## Options
+
+    * `:clear` - empty the log (and reset `dropped`) after reading it
+      (default `false`)
+  """
+  @spec phase_log(non_neg_integer(), keyword()) ::
+          {:ok, [{non_neg_integer(), float(), float()}], non_neg_integer()} | {:error, term()}
+  def phase_log(channel_id, opts \\ []) do
+    Nif.get_phase_log(channel_id, Keyword.get(opts, :clear, false))
+  end
+
+  @doc """
+  Takes a block of f32 output (as `process_block/2` returns it) whose
+  first sample is at `start_index` back to the channel's complex
+  baseband, using `entries` from `phase_log/2`.
+
+  Returns an IQ_F32_INTERLEAVED_V1 binary: a 12-byte header
+  (`<<"MMWF", 1, 1, 32, 0, count::little-32>>`) then interleaved f32-le
+  I/Q, one pair per output sample.
+  The baseband filter is applied without delay, so the first and last 15
+  pairs see its edges. Not for channels with clock drift, whose output
+  isn't on the channel's sample index.
+
+  ## Options
+
+    * `:sample_rate` - the channel's sample rate (default 9600)
+  """
+  @spec derotate([{non_neg_integer(), float(), float()}], non_neg_integer(), binary(), keyword()) ::
+          {:ok, binary()} | {:error, term()}
+  def derotate(entries, start_index, output_samples, opts \\ []) when is_binary(output_samples) do
+    Nif.derotate_output(entries, start_index, output_samples, Keyword.get(opts, :sample_rate, 9600))
+  end
+
   @doc """
   Destroys a channel and frees its resources.
   """
@@ -105,6 +662,52 @@ defmodule MinutemodemSimnet.Physics.Channel do
     Nif.destroy_channel(channel_id)
   end
 
+  @doc """
+  Returns the channel's simulation time as `{sample_index, seconds}`.
+
+  The NIF never sleeps: a channel's clock is its sample index, moved only
+  by processing, `advance/2` and `run_until/3`, and fade alarm events are
+  stamped with it. A scenario driven through these calls runs as fast as
+  it can make them, e.g. 10 simulated minutes in well under a second;
+  pacing to real time is the caller's job.
+  """
+  @spec time(non_neg_integer()) :: {:ok, {non_neg_integer(), float()}} | {:error, term()}
+  def time(channel_id) do
+    Nif.get_time(channel_id)
+  end
+
+  @doc """
+  Runs the channel forward to `target_sample` on `:silence` (the only
+  input source so far), as if that many zero samples were processed and
+  the output dropped.
+
+  Signal already in flight drains out and fade alarm events are sent as
+  usual, but past the drain the channel is skipped ahead rather than
+  processed, so long gaps cost next to nothing.
+  """
+  @spec run_until(non_neg_integer(), non_neg_integer(), :silence) ::
+          {:ok, {non_neg_integer(), float()}} | {:error, term()}
+  def run_until(channel_id, target_sample, input_source \\ :silence) do
+    Nif.run_until(channel_id, target_sample, input_source)
+  end
+
+  @doc """
+  Runs silence through the channel, `num_samples` of it or with `:auto`
+  its `transient_len`, so a block that follows another isn't colored by
+  the earlier one's tail (the output is dropped).
+
+  Only the tail can be flushed. A new or reset channel holds silence,
+  its filters' steady state, so priming it changes nothing; and the
+  first `transient_len` samples of output after a signal starts are its
+  run-up, the latency and the filters filling, primed or not. Measure a
+  block's level past that point, or over blocks longer than it.
+  """
+  @spec prime(non_neg_integer(), non_neg_integer() | :auto) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def prime(channel_id, num_samples \\ :auto) do
+    Nif.prime_channel(channel_id, num_samples)
+  end
+
   @doc """
   Gets the current channel state for debugging.
   """
@@ -120,4 +723,116 @@ defmodule MinutemodemSimnet.Physics.Channel do
   def count do
     Nif.channel_count()
   end
+
+  @doc """
+  Live, created and destroyed channels, correlated sets and groups, with
+  the bytes the live ones hold (see `Nif.census/0`).
+  """
+  @spec census() :: map()
+  def census do
+    Nif.census()
+  end
+
+  defp nif_params(%ChannelParams{} = params) do
+    # NIF expects the struct directly
+    %MinutemodemSimnet.Physics.Types.ChannelParams{
+      sample_rate: params.sample_rate,
+      delay_spread_samples: params.delay_spread_samples,
+      doppler_bandwidth_hz: params.doppler_bandwidth_hz,
+      snr_db: params.snr_db,
+      carrier_freq_hz: params.carrier_freq_hz || 1800.0,
+      bulk_delay_samples: params.bulk_delay_samples || 0,
+      output_bits: params.output_bits || 0,
+      output_dither: params.output_dither || false,
+      clip_knee: params.clip_knee || 0.0,
+      bypass: params.bypass || false,
+      fading_seed: params.fading_seed,
+      noise_seed: params.noise_seed,
+      start_at_time_s: (params.start_at_time_s || 0.0) * 1.0,
+      start_in_fade_db: params.start_in_fade_db && params.start_in_fade_db * 1.0,
+      sample_rate_offset_ppm: (params.sample_rate_offset_ppm || 0.0) * 1.0,
+      input_dc_block: params.input_dc_block || false,
+      input_tilt_db: (params.input_tilt_db || 0.0) * 1.0,
+      precision: params.precision || :f64,
+      sideband_inversion: params.sideband_inversion || false,
+      fractional_delay_samples: (params.fractional_delay_samples || 0.0) * 1.0,
+      tap0_gain_db: (params.tap0_gain_db || 0.0) * 1.0,
+      tap1_gain_db: (params.tap1_gain_db || 0.0) * 1.0,
+      frequency_offset_hz: (params.frequency_offset_hz || 0.0) * 1.0,
+      noise_bandwidth_hz: (params.noise_bandwidth_hz || 3000.0) * 1.0,
+      impulse_probability: (params.impulse_probability || 0.0) * 1.0,
+      impulse_power_ratio_db: (params.impulse_power_ratio_db || 20.0) * 1.0,
+      impulse_burst_samples: params.impulse_burst_samples || 1
+    }
+  end
+
+  defp nif_params(%MinutemodemSimnet.Channel.Params{} = params) do
+    # Convert from Channel.Params (has delay_spread_ms) to NIF params (has delay_spread_samples)
+    delay_spread_samples =
+      round((params.delay_spread_ms || 0) * (params.sample_rate || 9600) / 1000)
+
+    %MinutemodemSimnet.Physics.Types.ChannelParams{
+      sample_rate: params.sample_rate || 9600,
+      delay_spread_samples: delay_spread_samples,
+      doppler_bandwidth_hz: params.doppler_bandwidth_hz || 1.0,
+      snr_db: params.snr_db || 10.0,
+      carrier_freq_hz: params.carrier_freq_hz || 1800.0
+    }
+  end
+
+  defp nif_params(params) when is_map(params) do
+    # Convert plain map to proper struct for NIF
+    # Handles both delay_spread_ms and delay_spread_samples
+    # (and bulk_delay_ms / bulk_delay_samples likewise)
+    sample_rate = Map.get(params, :sample_rate, 9600)
+
+    delay_spread_samples =
+      case Map.get(params, :delay_spread_samples) do
+        nil ->
+          delay_ms = Map.get(params, :delay_spread_ms, 0)
+          round(delay_ms * sample_rate / 1000)
+
+        samples ->
+          samples
+      end
+
+    bulk_delay_samples =
+      case Map.get(params, :bulk_delay_samples) do
+        nil -> round(Map.get(params, :bulk_delay_ms, 0) * sample_rate / 1000)
+        samples -> samples
+      end
+
+    # start_in_fade: depth_db, or the struct's field name
+    start_in_fade = Map.get(params, :start_in_fade, Map.get(params, :start_in_fade_db))
+
+    %MinutemodemSimnet.Physics.Types.ChannelParams{
+      sample_rate: sample_rate,
+      delay_spread_samples: delay_spread_samples,
+      doppler_bandwidth_hz: Map.get(params, :doppler_bandwidth_hz, 1.0),
+      snr_db: Map.get(params, :snr_db, 10.0),
+      carrier_freq_hz: Map.get(params, :carrier_freq_hz, 1800.0),
+      bulk_delay_samples: bulk_delay_samples,
+      output_bits: Map.get(params, :output_bits, 0),
+      output_dither: Map.get(params, :output_dither, false),
+      clip_knee: Map.get(params, :clip_knee, 0.0),
+      bypass: Map.get(params, :bypass, false),
+      fading_seed: Map.get(params, :fading_seed),
+      noise_seed: Map.get(params, :noise_seed),
+      start_at_time_s: Map.get(params, :start_at_time_s, 0.0) * 1.0,
+      start_in_fade_db: start_in_fade && start_in_fade * 1.0,
+      sample_rate_offset_ppm: Map.get(params, :sample_rate_offset_ppm, 0.0) * 1.0,
+      input_dc_block: Map.get(params, :input_dc_block, false),
+      input_tilt_db: Map.get(params, :input_tilt_db, 0.0) * 1.0,
+      precision: Map.get(params, :precision, :f64),
+      sideband_inversion: Map.get(params, :sideband_inversion, false),
+      fractional_delay_samples: Map.get(params, :fractional_delay_samples, 0.0) * 1.0,
+      tap0_gain_db: Map.get(params, :tap0_gain_db, 0.0) * 1.0,
+      tap1_gain_db: Map.get(params, :tap1_gain_db, 0.0) * 1.0,
+      frequency_offset_hz: Map.get(params, :frequency_offset_hz, 0.0) * 1.0,
+      noise_bandwidth_hz: Map.get(params, :noise_bandwidth_hz, 3000.0) * 1.0,
+      impulse_probability: Map.get(params, :impulse_probability, 0.0) * 1.0,
+      impulse_power_ratio_db: Map.get(params, :impulse_power_ratio_db, 20.0) * 1.0,
+      impulse_burst_samples: Map.get(params, :impulse_burst_samples, 1)
+    }
+  end
 end
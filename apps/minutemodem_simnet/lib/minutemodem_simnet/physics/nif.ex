@@ -13,41 +13,479 @@ defmodule MinutemodemSimnet.Physics.Nif do
 
   @doc """
   Creates a new WattersonChannel and returns its slab handle.
+
+  Returns `{:error, "invalid_output_bits"}` or `{:error, "invalid_clip_knee"}`
+  if the output stage settings are out of range (bits 0 or 2..24, knee in
+  [0.0, 1.0)), and `{:error, "invalid_warm_start"}` for a negative
+  `start_at_time_s` or a `start_in_fade_db` that isn't below 0.
+  `{:error, "invalid_sample_rate_offset"}` means a `sample_rate_offset_ppm`
+  beyond ±1000, and `{:error, "invalid_input_tilt"}` an `input_tilt_db`
+  beyond ±12 or a tilt at a sample rate of 6000 Hz or less.
+  `{:error, "invalid_fractional_delay"}` is a `fractional_delay_samples`
+  outside [0, 1), `{:error, "invalid_tap_gain"}` a `tap0_gain_db` or
+  `tap1_gain_db` beyond ±60, `{:error, "invalid_frequency_offset"}` a
+  `frequency_offset_hz` beyond ±500, `{:error, "invalid_noise_bandwidth"}`
+  a `noise_bandwidth_hz` that isn't a finite number, and
+  `{:error, "invalid_impulse_noise"}` an `impulse_probability` outside
+  [0, 1], an `impulse_power_ratio_db` outside [0, 80] or an
+  `impulse_burst_samples` outside 1..`sample_rate`.
+
+  Fields that size the channel's buffers are bounded, and a value above
+  the bound gives `{:error, {:out_of_range, field, max}}`: `:sample_rate`
+  (192_000), `:delay_spread_samples` (19_200) and `:bulk_delay_samples`
+  (192_000). `update_params/2` and `create_correlated_set/4` check them
+  too.
   """
   @spec create_channel(map(), integer()) :: {:ok, non_neg_integer()} | {:error, term()}
   def create_channel(_params, _seed), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc """
+  Parses a channel scenario in the Watterson JSON exchange format.
+
+  Returns `{:error, {:invalid_json, line, column, message}}` for a
+  document that doesn't parse and `{:error, {:schema_error, path, message}}`, with
+  a JSON path like `"$.paths[1].delay_ms"`, for one that doesn't fit the
+  schema or the two-path model.
+  """
+  @spec channel_params_from_json(binary()) :: {:ok, struct()} | {:error, term()}
+  def channel_params_from_json(_json), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Writes a channel's parameters as a Watterson JSON exchange document.
+  """
+  @spec channel_params_to_json(struct()) :: {:ok, binary()} | {:error, term()}
+  def channel_params_to_json(_params), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Works out a link budget: returns `{:ok, snr_db, params}`, the SNR in
+  the budget's bandwidth and channel parameters at it with `preset`'s
+  fading (see `MinutemodemSimnet.Physics.Channel.link_budget/2`).
+
+  Returns `{:error, "invalid_link_budget"}` for a term that isn't
+  finite, a negative noise figure, a bandwidth that isn't positive or is
+  wider than the channel's Nyquist, or a frequency outside 0.3-250 MHz.
+  """
+  @spec link_budget(map(), atom()) :: {:ok, float(), struct()} | {:error, term()}
+  def link_budget(_budget, _preset), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  The path loss that gives `snr_db` in the budget's bandwidth; the
+  budget's own `path_loss_db` is ignored. Errors as for `link_budget/2`.
+  """
+  @spec path_loss_for_snr(map(), float()) :: {:ok, float()} | {:error, term()}
+  def path_loss_for_snr(_budget, _snr_db), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Builds channel parameters from scenario-style terms, doing the unit
+  conversions in one place. Keys (atoms or strings): `sample_rate`,
+  `carrier_hz` or `carrier_freq_hz`, `snr_db`, `delay_spread_ms` or
+  `delay_spread_samples`, `doppler_spread_hz`, `doppler_bandwidth_hz` or
+  `fade_rate_per_min` (fades below the RMS level per minute, about 55
+  per Hz), and `bulk_delay_ms` or `bulk_delay_samples`. Missing ones take
+  the defaults `create_channel/2`'s wrapper uses.
+
+  Returns `{:ok, params, derived}`, `derived` listing every field set as
+  `{name, value, source}` with `source` one of `:given`, `:converted`
+  or `:default`, for logging. The params have passed `create_channel/2`'s
+  checks. Errors are `{:unknown_key, key}`, `{:invalid_param, key,
+  message}`, `{:ambiguous, key, key}` for two keys for one field that
+  disagree (milliseconds rounding to other than the samples given, or
+  Doppler terms more than 1% apart), or `create_channel/2`'s own.
+  """
+  @spec params_builder(map()) :: {:ok, struct(), [{atom(), float(), atom()}]} | {:error, term()}
+  def params_builder(_terms), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Channel parameters for a named condition at `sample_rate` and `snr_db`
+  (see `MinutemodemSimnet.Physics.Channel.preset_params/3`). Errors are
+  `params_builder/1`'s, e.g. `{:invalid_param, :carrier_hz, message}`
+  for a rate whose Nyquist is below the 1800 Hz carrier.
+  """
+  @spec preset_params(atom(), pos_integer(), float()) :: {:ok, struct()} | {:error, term()}
+  def preset_params(_preset, _sample_rate, _snr_db), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  `create_channel/2` with `preset_params/3`'s parameters.
+  """
+  @spec create_channel_preset(atom(), pos_integer(), float(), integer()) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def create_channel_preset(_preset, _sample_rate, _snr_db, _seed),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  `create_channel/2` with the parameters from a JSON scenario.
+  """
+  @spec create_channel_from_json(binary(), integer()) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def create_channel_from_json(_json, _seed), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc """
   Processes a block of samples through the channel.
 
-  Returns the channel-impaired output samples.
+  Returns the channel-impaired output samples. With a
+  `sample_rate_offset_ppm` the output can be a sample longer or shorter
+  than the input.
+
+  Runs on a dirty CPU scheduler. A block is at most 1_920_000 samples (10
+  s at 192 kHz); a longer one gives `{:error, {:out_of_range,
+  :input_samples, 1_920_000}}`.
   """
   @spec process_block(non_neg_integer(), binary()) :: {:ok, binary()} | {:error, term()}
   def process_block(_channel_id, _input_samples), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc """
+  Processes a block the caller says starts at `start_sample_index`.
+
+  Returns `{:ok, output, discontinuity}` with `discontinuity` one of
+  `:none`, `{:gap, n_samples}` or `{:overlap, n_samples}`.
+  """
+  @spec process_block_at(non_neg_integer(), non_neg_integer(), binary()) ::
+          {:ok, binary(), :none | {:gap | :overlap, pos_integer()}} | {:error, term()}
+  def process_block_at(_channel_id, _start_sample_index, _input_samples),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Processes a block with explicit input and output sample formats.
+
+  Formats: `:f32ne`, `:f64ne`, `:s24le` (packed 3-byte little-endian),
+  `:f32le`, `:f32be` and `:f32tagged` (byte order from a leading magic).
+  The f64 and s24 paths avoid the f32 quantization of `process_block/2`.
+  """
+  @spec process_block_fmt(non_neg_integer(), binary(), atom(), atom()) ::
+          {:ok, binary()} | {:error, term()}
+  def process_block_fmt(_channel_id, _input_samples, _input_format, _output_format),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Processes a block of complex baseband samples.
+
+  Input and output are IQ_F32_INTERLEAVED_V1 binaries of the same length
+  (see `MinuteModemCore.DSP.PhyModem`, "Wire format").
+  """
+  @spec process_block_iq(non_neg_integer(), binary()) :: {:ok, binary()} | {:error, term()}
+  def process_block_iq(_channel_id, _input_iq), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Processes a block and also returns a time-aligned clean reference.
+
+  The reference went through the same filters and delays with unity
+  fading and no noise. Both are native-endian f32 binaries.
+  """
+  @spec process_block_with_reference(non_neg_integer(), binary()) ::
+          {:ok, {binary(), binary()}} | {:error, term()}
+  def process_block_with_reference(_channel_id, _input_samples),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   @doc """
   Advances the channel state without processing samples.
 
-  Used for time synchronization.
+  Used for time synchronization. Runs on a dirty CPU scheduler, taking
+  the channel's lock 48_000 samples at a time, so `process_block/2` on
+  the same channel from another process can get in between.
   """
   @spec advance(non_neg_integer(), non_neg_integer()) :: :ok | {:error, term()}
   def advance(_channel_id, _num_samples), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc """
+  Advances many channels in a single NIF call.
+
+  Takes a list of `{channel_id, num_samples}`. Returns a result per entry,
+  in input order; missing channels are reported without aborting the batch.
+  """
+  @spec advance_many([{non_neg_integer(), non_neg_integer()}]) ::
+          {:ok, [{non_neg_integer(), :ok | {:error, :channel_not_found}}]} | {:error, term()}
+  def advance_many(_requests), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Processes blocks on many channels in a single NIF call, spread across a
+  thread pool on a dirty CPU scheduler.
+
+  Takes a list of `{channel_id, input_binary}`. Returns a result per
+  entry, in input order; missing channels are reported without aborting
+  the batch. A channel listed more than once takes its blocks in order.
+  """
+  @spec process_blocks([{non_neg_integer(), binary()}]) ::
+          {:ok, [{non_neg_integer(), binary() | {:error, :channel_not_found}}]} | {:error, term()}
+  def process_blocks(_requests), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Updates a live channel's SNR, bulk delay (the delay slews), input
+  conditioning, sideband inversion, output stage settings and bypass flag.
+  """
+  @spec update_params(non_neg_integer(), map()) :: :ok | {:error, term()}
+  def update_params(_channel_id, _params), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Returns a channel to idle: clears the bulk and multipath delay lines,
+  the baseband filters and the carrier phase.
+
+  `rng` is `:preserve` (fading, noise and dither continue), `:reseed`
+  (restart from the creation seed, exactly like a new channel) or
+  `{:reseed, seed}`.
+  """
+  @spec reset_channel(non_neg_integer(), :preserve | :reseed | {:reseed, non_neg_integer()}) ::
+          :ok | {:error, term()}
+  def reset_channel(_channel_id, _rng), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Restarts a channel's AWGN from `noise_seed` without touching fading,
+  filter or delay state.
+  """
+  @spec reseed_noise(non_neg_integer(), non_neg_integer()) :: :ok | {:error, term()}
+  def reseed_noise(_channel_id, _noise_seed), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Hops a channel's carrier: from each `{at_sample, carrier_hz}`'s input
+  sample on, the channel mixes at `carrier_hz`. Replaces any hops still
+  pending.
+  """
+  @spec set_hop_schedule(non_neg_integer(), [{non_neg_integer(), float()}]) ::
+          :ok | {:error, term()}
+  def set_hop_schedule(_channel_id, _hops), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Mutes a channel's output (or attenuates it by `attenuation_db`) from
+  each `{at_sample, :tx}` to the next `{at_sample, :rx}`, ramping over
+  `ramp_ms` at each transition. Replaces any earlier schedule.
+  """
+  @spec set_tr_schedule(
+          non_neg_integer(),
+          [{non_neg_integer(), :tx | :rx}],
+          float() | nil,
+          float()
+        ) :: :ok | {:error, term()}
+  def set_tr_schedule(_channel_id, _schedule, _attenuation_db, _ramp_ms),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Sets a channel's fade alarm, replacing any earlier one.
+
+  Whenever the combined fading envelope (relative to its long-term mean)
+  drops below `threshold_db`, or later climbs back above `threshold_db +
+  hysteresis_db`, the process or advance call that crossed it sends
+  `{:fade, channel_id, :start | :end, sample_index}` to `pid`.
+  """
+  @spec set_fade_alarm(non_neg_integer(), float(), float(), pid()) :: :ok | {:error, term()}
+  def set_fade_alarm(_channel_id, _threshold_db, _hysteresis_db, _pid),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Turns a channel's fade alarm off.
+  """
+  @spec clear_fade_alarm(non_neg_integer()) :: :ok | {:error, term()}
+  def clear_fade_alarm(_channel_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Sends `{:input_warning, :suspicious_input_level, channel_id, peak}` to
+  `pid` whenever a float input block peaks far above full scale; `nil`
+  stops the messages. The call itself still succeeds.
+  """
+  @spec set_warning_logger(pid() | nil) :: :ok | {:error, term()}
+  def set_warning_logger(_pid), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Turns a channel's input level warnings on or off (on by default).
+  """
+  @spec set_input_warnings(non_neg_integer(), boolean()) :: :ok | {:error, term()}
+  def set_input_warnings(_channel_id, _enabled), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Returns `{:ok, entries, dropped}`: the channel's parameter changes,
+  oldest first, and how many older ones the cap has dropped. With
+  `clear` true the log is emptied afterwards.
+  """
+  @spec get_audit_log(non_neg_integer(), boolean()) ::
+          {:ok, [tuple()], non_neg_integer()} | {:error, term()}
+  def get_audit_log(_channel_id, _clear), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Sets how many entries the channel's audit log keeps (256 by default).
+
+  At most 65_536; a larger cap gives `{:error, {:out_of_range, :cap, 65536}}`.
+  """
+  @spec set_audit_cap(non_neg_integer(), pos_integer()) :: :ok | {:error, term()}
+  def set_audit_cap(_channel_id, _cap), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Turns a channel's phase log on, recording the carrier phase of every
+  `decimation`th output sample, or off with 0.
+  """
+  @spec set_phase_log(non_neg_integer(), non_neg_integer()) :: :ok | {:error, term()}
+  def set_phase_log(_channel_id, _decimation), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Returns `{:ok, entries, dropped}` with entries
+  `{sample_index, phase, step}`, oldest first. With `clear` true the log
+  is emptied afterwards. `{:error, "phase_log_off"}` if it isn't on.
+  """
+  @spec get_phase_log(non_neg_integer(), boolean()) ::
+          {:ok, [{non_neg_integer(), float(), float()}], non_neg_integer()} | {:error, term()}
+  def get_phase_log(_channel_id, _clear), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Mixes f32 channel output starting at `start_index` back down with
+  logged phases. Returns IQ_F32_INTERLEAVED_V1 (see
+  `MinuteModemCore.DSP.PhyModem`, "Wire format").
+  """
+  @spec derotate_output(
+          [{non_neg_integer(), float(), float()}],
+          non_neg_integer(),
+          binary(),
+          pos_integer()
+        ) :: {:ok, binary()} | {:error, term()}
+  def derotate_output(_entries, _start_index, _output_samples, _sample_rate),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   @doc """
   Destroys a channel and frees its slab slot.
   """
   @spec destroy_channel(non_neg_integer()) :: :ok | {:error, term()}
   def destroy_channel(_channel_id), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc """
+  Creates a set of `n_outputs` channels with correlated fading (see
+  `Physics.Channel.create_correlated_set/4`).
+
+  Returns `{:ok, set_id, channel_ids}`. `n_outputs` is at most 64
+  (`{:error, {:out_of_range, :n_outputs, 64}}` otherwise).
+  """
+  @spec create_correlated_set(map(), pos_integer(), [[float()]], integer()) ::
+          {:ok, non_neg_integer(), [non_neg_integer()]} | {:error, term()}
+  def create_correlated_set(_params, _n_outputs, _correlation, _seed),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Processes one f32 block through every member of a correlated set.
+
+  Returns one output binary per member, in channel_ids order.
+  """
+  @spec process_set(non_neg_integer(), binary()) :: {:ok, [binary()]} | {:error, term()}
+  def process_set(_set_id, _input_samples), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Destroys a correlated set and its member channels.
+  """
+  @spec destroy_set(non_neg_integer()) :: :ok
+  def destroy_set(_set_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Creates a channel group whose members share slowly varying SNR and
+  Doppler spread offsets (see `Physics.Channel.create_group/1`).
+
+  Returns `{:ok, group_id}`, or `{:error, "invalid_group_params"}` for a
+  term that isn't finite, a correlation time that isn't positive or a
+  negative sigma.
+  """
+  @spec create_group(map()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def create_group(_group_params), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  `create_channel/2` for a channel that follows a group's offsets.
+
+  Errors as for `create_channel/2`, or `{:error, "group_not_found"}`.
+  """
+  @spec create_channel_in_group(map(), integer(), non_neg_integer()) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def create_channel_in_group(_params, _seed, _group_id),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Returns `{:ok, %{time_s, snr_offset_db, doppler_offset_hz, members}}`:
+  the group's offsets at the furthest simulation time any live member has
+  reached, and the live members.
+  """
+  @spec get_group_state(non_neg_integer()) :: {:ok, map()} | {:error, term()}
+  def get_group_state(_group_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Disbands a group; its channels carry on at their own parameters.
+  """
+  @spec disband_group(non_neg_integer()) :: :ok
+  def disband_group(_group_id), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc """
   Gets the current state of a channel for debugging/telemetry.
   """
   @spec get_state(non_neg_integer()) :: {:ok, map()} | {:error, term()}
   def get_state(_channel_id), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc """
+  Gets a channel's simulation time as `{sample_index, seconds}`.
+  """
+  @spec get_time(non_neg_integer()) ::
+          {:ok, {non_neg_integer(), float()}} | {:error, term()}
+  def get_time(_channel_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Runs silence through a channel until its sample index reaches
+  `target_sample`, discarding the output, and returns the new time.
+
+  `input_source` must be `:silence`. Returns `{:error, "target_in_past"}`
+  if the channel is already beyond `target_sample`.
+  """
+  @spec run_until(non_neg_integer(), non_neg_integer(), :silence) ::
+          {:ok, {non_neg_integer(), float()}} | {:error, term()}
+  def run_until(_channel_id, _target_sample, _input_source),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Runs `num_samples` of silence through a channel, or with `:auto` its
+  `transient_len` (see `get_state/1`), dropping the output, so the next
+  block starts from an idle channel instead of the tail of the last.
+  Returns `{:ok, samples_run}`. A new or reset channel is idle already.
+  """
+  @spec prime_channel(non_neg_integer(), non_neg_integer() | :auto) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def prime_channel(_channel_id, _num_samples), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc """
   Returns the number of active channels in the slab.
   """
   @spec channel_count() :: non_neg_integer()
   def channel_count(), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Counts channels, correlated sets and groups, for catching leaks over
+  long runs: how many are live, created and destroyed since the library
+  loaded, and the bytes the live ones held (filters, delay lines, logs)
+  as their last call returned. Over a soak run `live` and `bytes` should
+  settle rather than climb. Clearing a phase or audit log frees its space.
+  """
+  @type census_count :: %{
+          live: non_neg_integer(),
+          created: non_neg_integer(),
+          dropped: non_neg_integer(),
+          bytes: non_neg_integer()
+        }
+  @spec census() :: %{
+          channels: census_count(),
+          correlated_sets: census_count(),
+          groups: census_count()
+        }
+  def census(), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Runs the built-in sanity check: a fixed channel's output against a
+  golden vector, and a second channel with the same seed reproducing it
+  bit for bit. Takes a few milliseconds. The summary's `provenance` is
+  `provenance/0`'s, naming the build the checks passed on.
+  """
+  @spec self_test() ::
+          {:ok,
+           %{elapsed_us: non_neg_integer(), checks: %{atom() => String.t()}, provenance: String.t()}}
+          | {:error, [{atom(), String.t()}]}
+  def self_test(), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  The library's "license plate": crate name and version, the build's
+  `git describe` and profile, enabled features and a hash of the fading
+  presets, baseband filter taps and self-test golden vector, e.g.
+
+      "channel_physics/0.1.0 git=v0.4-12-g3f2a9c1 profile=release features=nif tables=5be0c7a41d93f286"
+
+  Exported channel documents carry it as `metadata.provenance`. It's the
+  same string for the life of the VM.
+  """
+  @spec provenance() :: String.t()
+  def provenance(), do: :erlang.nif_error(:nif_not_loaded)
 end
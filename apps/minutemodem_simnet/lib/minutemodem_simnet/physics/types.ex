@@ -11,6 +11,105 @@ defmodule MinutemodemSimnet.Physics.Types do
     Parameters for creating a WattersonChannel in Rust.
 
     Maps to MIL-STD-188-110D Appendix E channel specification.
+
+    `bulk_delay_samples` is the one-way propagation delay applied inside
+    the channel, ahead of the fading section (default 0). It can be changed
+    on a live channel with `Physics.Channel.update_params/2`; the delay then
+    slews at 0.1% (1 sample per 1000) instead of jumping.
+
+    The output fields model the receiving sound card, applied after the
+    noise: `clip_knee` soft-limits above that fraction of full scale (0.0
+    disables it), then the signal is quantized to `output_bits` (0 keeps
+    float output), with TPDF dither if `output_dither` is set. The defaults
+    leave the output untouched.
+
+    `bypass: true` turns the channel into a loop-back cable: the output is
+    the input, bit for bit, with no fading, noise, delay or filtering, while
+    blocks still go through the channel and count towards its sample
+    index. It can be toggled on a live channel.
+
+    `fading_seed` and `noise_seed` seed the fading taps and the AWGN
+    separately (nil, the default, uses the channel seed). A given
+    `fading_seed` gives the same fading as a channel created with that
+    seed, whatever the noise, and likewise for `noise_seed`, which is what
+    common-random-numbers and antithetic Monte-Carlo runs need. Both only
+    apply at creation; see `Physics.Channel.reseed_noise/2`.
+
+    `start_at_time_s` and `start_in_fade_db` warm-start the fading instead
+    of beginning its realization at time zero. The taps jump straight to
+    `start_at_time_s` seconds in (default 0.0); with `start_in_fade_db`
+    (a negative level, dB relative to the mean tap power) they then skip
+    ahead to the first instant the combined envelope is at or below that
+    level, so the channel opens at the edge of a fade instead of waiting
+    minutes of simulated time for one. If the envelope never gets that
+    deep within 4096 Doppler periods the channel starts at the deepest
+    point found. `ChannelState.start_time_s` reports where it started.
+    Both only apply at creation.
+
+    `sample_rate_offset_ppm` models the receiving sound card's clock being
+    off nominal: the output is resampled so a positive offset gives more
+    output samples than input (about 35 extra per minute at +60 ppm and
+    9600 Hz) and a negative one fewer. Blocks then come back longer or
+    shorter than they went in; `ChannelState.output_samples` counts the
+    total. Bounded to ±1000 ppm, and only applies at creation.
+
+    `input_dc_block` and `input_tilt_db` condition the input ahead of the
+    mix-down, as the transmitter's audio chain might: a first-order DC
+    blocker (corner 20 Hz), and a first-order shelf whose gain at 3000 Hz
+    is `input_tilt_db` above its gain at 300 Hz, half up at one edge and
+    half down at the other (±12 dB at most, and a sample rate above
+    6000 Hz). Both are off by default and can be changed on a live
+    channel.
+
+    `precision` is the float type of the fading, mixers and baseband
+    filters: `:f64` (the default) or `:f32`, which runs a fading channel
+    nearly twice as fast at an error around 130 dB below the signal. Only
+    applies at creation.
+
+    `sideband_inversion` conjugates the baseband, mirroring the spectrum
+    about `carrier_freq_hz` as a receiver on the opposite sideband to the
+    transmitter would: a tone 300 Hz below the carrier comes out 300 Hz
+    above it. Off by default; can be changed on a live channel.
+
+    `fractional_delay_samples` delays the output by a fraction of a sample
+    in [0, 1), on top of the whole-sample delays, for checking a
+    demodulator's timing estimate against a known offset. A windowed-sinc
+    filter does it to within 0.001 samples across the voice band, adding
+    16 whole samples of latency besides the fraction;
+    `ChannelState.total_delay_samples` reports the sum. 0.0 (the default)
+    leaves the filter out. Only applies at creation.
+
+    `tap0_gain_db` and `tap1_gain_db` set the mean power of the direct
+    and delayed paths, e.g. 0.0 and -6.0 for a second hop 6 dB down. The
+    pair is scaled to unit total power, so their difference is the exact
+    power ratio between the echoes; both 0.0 (the default) is the usual
+    equal split. Within ±60 dB; ignored on a single path. Only applies at
+    creation.
+
+    `frequency_offset_hz` shifts everything received by that many Hz,
+    echo and all, as a mistuned receiver or a Doppler-shifted path would:
+    +10.0 moves a 1500 Hz tone to 1510 Hz. Within ±500 Hz; 0.0 (the
+    default) leaves the spectrum where it was. Can change in place, with
+    no phase jump.
+
+    `noise_bandwidth_hz` confines the noise to 0..B Hz, shaped by a
+    windowed-sinc lowpass like the channel's own, and scales it so
+    `snr_db` is exactly the SNR within that band. The default 3000.0 is
+    the HF voice channel; 0.0 or less (or anything past Nyquist) leaves
+    the noise white to Nyquist as before, where the same `snr_db` reads
+    about 2 dB better in a 3 kHz receiver at 9600 Hz. Only applies at
+    creation.
+
+    `impulse_probability`, `impulse_power_ratio_db` and
+    `impulse_burst_samples` add impulsive noise (lightning static) on top
+    of the Gaussian: each sample a burst starts with that probability, and
+    for `impulse_burst_samples` samples the noise takes an extra Gaussian
+    component `impulse_power_ratio_db` above the background. E.g. 0.001,
+    30.0 and 10 give a 1 ms crash roughly every 100 ms at 9600 Hz. The
+    bursts are drawn from the noise seed, so runs repeat. The probability
+    is within [0, 1], 0.0 (the default) for Gaussian noise alone; the
+    ratio within [0, 80] dB (default 20.0); the burst 1 sample up to a
+    second (default 1). Only applies at creation.
     """
 
     @type t :: %__MODULE__{
@@ -18,7 +117,29 @@ defmodule MinutemodemSimnet.Physics.Types do
             delay_spread_samples: non_neg_integer(),
             doppler_bandwidth_hz: float(),
             snr_db: float(),
-            carrier_freq_hz: float()
+            carrier_freq_hz: float(),
+            bulk_delay_samples: non_neg_integer(),
+            output_bits: non_neg_integer(),
+            output_dither: boolean(),
+            clip_knee: float(),
+            bypass: boolean(),
+            fading_seed: non_neg_integer() | nil,
+            noise_seed: non_neg_integer() | nil,
+            start_at_time_s: float(),
+            start_in_fade_db: float() | nil,
+            sample_rate_offset_ppm: float(),
+            input_dc_block: boolean(),
+            input_tilt_db: float(),
+            precision: :f64 | :f32,
+            sideband_inversion: boolean(),
+            fractional_delay_samples: float(),
+            tap0_gain_db: float(),
+            tap1_gain_db: float(),
+            frequency_offset_hz: float(),
+            noise_bandwidth_hz: float(),
+            impulse_probability: float(),
+            impulse_power_ratio_db: float(),
+            impulse_burst_samples: pos_integer()
           }
 
     defstruct [
@@ -26,7 +147,29 @@ defmodule MinutemodemSimnet.Physics.Types do
       :delay_spread_samples,
       :doppler_bandwidth_hz,
       :snr_db,
-      :carrier_freq_hz
+      :carrier_freq_hz,
+      bulk_delay_samples: 0,
+      output_bits: 0,
+      output_dither: false,
+      clip_knee: 0.0,
+      bypass: false,
+      fading_seed: nil,
+      noise_seed: nil,
+      start_at_time_s: 0.0,
+      start_in_fade_db: nil,
+      sample_rate_offset_ppm: 0.0,
+      input_dc_block: false,
+      input_tilt_db: 0.0,
+      precision: :f64,
+      sideband_inversion: false,
+      fractional_delay_samples: 0.0,
+      tap0_gain_db: 0.0,
+      tap1_gain_db: 0.0,
+      frequency_offset_hz: 0.0,
+      noise_bandwidth_hz: 3000.0,
+      impulse_probability: 0.0,
+      impulse_power_ratio_db: 20.0,
+      impulse_burst_samples: 1
     ]
 
     @doc """
@@ -54,7 +197,29 @@ defmodule MinutemodemSimnet.Physics.Types do
         delay_spread_samples: params.delay_spread_samples,
         doppler_bandwidth_hz: params.doppler_bandwidth_hz,
         snr_db: params.snr_db,
-        carrier_freq_hz: params.carrier_freq_hz
+        carrier_freq_hz: params.carrier_freq_hz,
+        bulk_delay_samples: params.bulk_delay_samples,
+        output_bits: params.output_bits,
+        output_dither: params.output_dither,
+        clip_knee: params.clip_knee,
+        bypass: params.bypass,
+        fading_seed: params.fading_seed,
+        noise_seed: params.noise_seed,
+        start_at_time_s: params.start_at_time_s,
+        start_in_fade_db: params.start_in_fade_db,
+        sample_rate_offset_ppm: params.sample_rate_offset_ppm,
+        input_dc_block: params.input_dc_block,
+        input_tilt_db: params.input_tilt_db,
+        precision: params.precision,
+        sideband_inversion: params.sideband_inversion,
+        fractional_delay_samples: params.fractional_delay_samples,
+        tap0_gain_db: params.tap0_gain_db,
+        tap1_gain_db: params.tap1_gain_db,
+        frequency_offset_hz: params.frequency_offset_hz,
+        noise_bandwidth_hz: params.noise_bandwidth_hz,
+        impulse_probability: params.impulse_probability,
+        impulse_power_ratio_db: params.impulse_power_ratio_db,
+        impulse_burst_samples: params.impulse_burst_samples
       }
     end
   end
@@ -67,20 +232,66 @@ defmodule MinutemodemSimnet.Physics.Types do
 
     Fields match the Rust ChannelState struct:
     - sample_index: Number of samples processed
-    - tap0_phase: Current phase of tap 0 fading oscillator
-    - tap1_phase: Current phase of tap 1 fading oscillator
+    - tap0_phase: Phase of tap 0's fading coefficient, radians
+    - tap1_phase: Phase of tap 1's fading coefficient, radians
+    - bulk_delay_samples: Bulk delay currently applied (fractional while slewing)
+    - total_delay_samples: Delay from input to output along the direct
+      path: the bulk delay as applied, the baseband filters, the
+      fractional delay and the drift resampler
+    - bypass: Whether the channel is in loop-back bypass
+    - start_time_s: Where the fading started, in seconds into its
+      realization (see `ChannelParams` warm start)
+    - output_samples: Number of samples output; differs from sample_index
+      by the clock drift (see `ChannelParams` sample_rate_offset_ppm)
+    - tr_state: `:tx` while the receiver is muted for its own
+      transmission, else `:rx` (see `Channel.set_tr_schedule/3`)
+    - transient_len: Output samples over which the filters and delay
+      lines fill after a signal starts, or empty after it stops (see
+      `Channel.prime/2`)
+    - tap0_gain, tap1_gain: Each tap's complex fading coefficient as
+      `{i, q}`, unit mean power, for the next sample (tap 1 is unused on
+      a single path)
+    - envelope_db: Combined envelope of the taps in use, dB relative to
+      its long-term mean; what a fade alarm compares its threshold with
+    - snr_db: SNR the noise is set for, including any group's offset
+    - output_power_db: Mean square of the output over roughly the last
+      100 ms, in dB (a full-scale sine is -3 dB); -200.0 for silence
     """
 
     @type t :: %__MODULE__{
             sample_index: non_neg_integer(),
             tap0_phase: float(),
-            tap1_phase: float()
+            tap1_phase: float(),
+            bulk_delay_samples: float(),
+            total_delay_samples: float(),
+            bypass: boolean(),
+            start_time_s: float(),
+            output_samples: non_neg_integer(),
+            tr_state: :rx | :tx,
+            transient_len: non_neg_integer(),
+            tap0_gain: {float(), float()},
+            tap1_gain: {float(), float()},
+            envelope_db: float(),
+            snr_db: float(),
+            output_power_db: float()
           }
 
     defstruct [
       :sample_index,
       :tap0_phase,
-      :tap1_phase
+      :tap1_phase,
+      :bulk_delay_samples,
+      :total_delay_samples,
+      :bypass,
+      :start_time_s,
+      :output_samples,
+      :tr_state,
+      :transient_len,
+      :tap0_gain,
+      :tap1_gain,
+      :envelope_db,
+      :snr_db,
+      :output_power_db
     ]
   end
 end
@@ -0,0 +1,215 @@
+defmodule MinutemodemSimnet.Sim.Nif do
+  @moduledoc """
+  Rustler NIF module for end-to-end modem + channel simulation.
+
+  Runs the PHY modulator and the Appendix E channel together natively, for
+  scenarios that would otherwise round-trip every burst through Elixir.
+
+  A call that panics returns `{:error, {:panic, message}}` (message cut to
+  256 bytes). A scoreboard a panic left mid-update has its window and
+  burst counts put back in order by the next call on it, which then
+  proceeds, so there is no need to recreate it.
+  """
+
+  use Rustler,
+    otp_app: :minutemodem_simnet,
+    crate: :modem_sim
+
+  @type burst ::
+          {start_sample :: non_neg_integer(), symbols :: [non_neg_integer()],
+           constellation :: :bpsk | :qpsk | :psk8 | :qam16 | :qam32 | :qam64,
+           modulator_opts :: map(), channel_params :: struct(), seed :: non_neg_integer(),
+           level_db :: float()}
+
+  @doc """
+  Renders a TDMA frame of `frame_len` samples from a list of bursts.
+
+  Each burst is modulated, passed through its own channel (seeded with its
+  own seed), scaled by `level_db` and summed in at `start_sample`.
+  `modulator_opts` may set `:symbol_rate` (default 2400) and
+  `:carrier_freq` (default: the channel's carrier).
+
+  Returns the frame as native-endian f32 samples plus one placement map
+  per burst: `:start_sample`, `:end_sample`, `:first_symbol_sample`,
+  `:samples_per_symbol` and `:truncated`.
+
+  `frame_len` is at most 28_800_000 (10 minutes at 48 kHz), a burst's
+  samples per symbol at most 512, and its channel params are bounded as
+  in `MinutemodemSimnet.Physics.Nif.create_channel/2`; anything larger
+  gives `{:error, {:out_of_range, param, max}}`.
+  """
+  @spec compose_frame(non_neg_integer(), [burst()]) ::
+          {:ok, binary(), [map()]} | {:error, term()}
+  def compose_frame(_frame_len, _bursts), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Runs a half-duplex turnaround: our own transmission leaking into the
+  co-located receiver, then a distant station's burst.
+
+  `spec` is a map of `:tx_symbols` (ours), `:isolation_db` (attenuation
+  of our signal into the receiver), `:leak_channel` (channel params for
+  that path; its noise is the receiver's floor throughout and its sample
+  rate everyone's), `:turnaround_samples` (from the end of ours to the
+  distant station's first sample), `:preamble` and `:data` (the distant
+  burst), `:remote_channel`, `:remote_level_db` and `:seed`.
+
+  `opts` may set `:symbol_rate` (2400), `:carrier_freq` (the leak
+  channel's carrier), `:block_samples` (480, samples per demodulate
+  call), `:reset_at_rx` (false, reset the demodulator to idle at the
+  switch to receive), `:eot` (`:off`, `:detect` or `:truncate`, the
+  end-of-transmission detector with its default settings) and
+  `:timing_tracking` (false).
+
+  The received symbols are synced on the preamble (90% of it must match,
+  at any PSK phase rotation) and the data after it scored. Returns a map
+  of `:tx_symbols_out` and `:rx_symbols_out` (symbols the demodulator
+  emitted during each half), `:sync` (`{offset, rotation}` or nil),
+  `:symbol_errors`, `:data_symbols` and `:ser`. Channel params and
+  samples per symbol are bounded as for `compose_frame/2`, and the whole
+  timeline to 28_800_000 samples.
+  """
+  @spec run_echo_scenario(atom(), map(), map()) :: {:ok, map()} | {:error, term()}
+  def run_echo_scenario(_constellation, _spec, _opts \\ %{}),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Creates a BER scoreboard for demodulated symbols.
+
+  `reference` is `{:sequence, symbols}` (a binary or list, repeated as
+  needed), `{:generated, seed}` (the stream from `reference_symbols/3`) or
+  `{:prbs9 | :prbs15 | :prbs23, seed}` (the stream from
+  `MinuteModemCore.DSP.PhyModem.prbs_symbols/4`).
+  `opts` may set `:symbol_rate` (2400) and `:window_seconds` (10.0) for the
+  windowed SER, `:burst_gap` (8 correct symbols end an error burst) and
+  `:confidence_threshold` (0.5) for the high/low confidence split.
+
+  For PSK, the first 32 symbols are buffered to resolve the receiver's
+  phase rotation, then everything is scored against the rotated
+  reference.
+  """
+  @spec new_scoreboard(
+          atom(),
+          {:sequence, binary() | [non_neg_integer()]}
+          | {:generated | :prbs9 | :prbs15 | :prbs23, non_neg_integer()},
+          map()
+        ) ::
+          {:ok, reference()} | {:error, term()}
+  def new_scoreboard(_constellation, _reference, _opts \\ %{}),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Scores the next demodulated symbols (a binary, one byte per symbol).
+
+  `confidences`, if given, is a list of slicer confidences of the same
+  length; those symbols are also counted in the high/low confidence stats.
+  """
+  @spec score(reference(), binary(), [float()] | nil) :: :ok | {:error, term()}
+  def score(_scoreboard, _symbols, _confidences \\ nil), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Returns the running statistics: `:symbols`, `:symbol_errors`,
+  `:bit_errors`, `:ser`, `:ber`, `:error_bursts`, `:longest_burst`,
+  `:window_symbols`, `:window_errors`, `:window_ser`, `:high_confidence`
+  and `:low_confidence` (each `%{symbols, errors, ser}`), `:rotation` (nil
+  until resolved) and `:pending` (symbols buffered for rotation).
+  """
+  @spec get_score(reference()) :: {:ok, map()} | {:error, term()}
+  def get_score(_scoreboard), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Generates the first `count` symbols of the scoreboard's reference stream
+  for `seed`, as a binary with one byte per symbol. `count` is at most
+  28_800_000 (`{:error, {:out_of_range, :count, max}}` otherwise).
+  """
+  @spec reference_symbols(atom(), non_neg_integer(), non_neg_integer()) ::
+          {:ok, binary()} | {:error, term()}
+  def reference_symbols(_constellation, _seed, _count), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Theoretical SER and BER curves for plotting next to measured results.
+
+  `constellation` is as for `new_scoreboard/3`, `channel` `:awgn` or
+  `:rayleigh` (flat, with the receiver knowing the fade exactly) and
+  `snr_points` Es/N0 values in dB, at most 4096 of them. Returns one map
+  per point: `:snr_db`, `:ser`, `:ber` and `:point_ser`.
+
+  Uncoded symbols, all equally likely, sliced to the nearest point of
+  the constellation's 110D table. Rather than closed forms (the QAM
+  tables are circular, not square) the error probabilities are
+  integrated numerically over the table's real decision regions, exact
+  but for an angular quadrature good to 1e-5 relative; 8-PSK
+  over AWGN agrees with Craig's exact formula. Errors are counted as the
+  scoreboard counts them, by symbol index, so for 32-QAM and 64-QAM,
+  whose tables give some points to two symbols, SER and BER level off at
+  a floor (64-QAM's SER at 0.25); `:point_ser` counts only decisions on
+  the wrong point.
+  """
+  @spec ber_reference(atom(), :awgn | :rayleigh, [float()]) ::
+          {:ok, [%{snr_db: float(), ser: float(), ber: float(), point_ser: float()}]}
+          | {:error, term()}
+  def ber_reference(_constellation, _channel, _snr_points), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Creates a token that can stop a running `run_sweep/3` (see `abort/1`).
+  """
+  @spec new_abort_token() :: {:ok, reference()}
+  def new_abort_token(), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Stops every sweep running with `token` once its threads finish the
+  trials they are on; those sweeps return `{:error, :aborted}`.
+  """
+  @spec abort(reference()) :: :ok
+  def abort(_token), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Measures SER and BER over a whole grid of channel conditions in one
+  call, on a pool of native threads.
+
+  `spec` is a map of `:constellations` (atoms as for `new_scoreboard/3`),
+  `:snr_db`, `:doppler_hz` and `:delay_samples` (lists; every
+  combination is a grid point, constellation slowest and delay fastest),
+  `:channel` (channel params for every point, their `snr_db`,
+  `doppler_bandwidth_hz` and `delay_spread_samples` replaced by the
+  point's), `:trials` per point, `:symbols` (data symbols per trial) and
+  `:seed`. Each trial sends a 32-symbol preamble then the data, all from
+  the `reference_symbols/3` stream, through its own channel; the output is
+  synced on the preamble as in `run_echo_scenario/3` and the data scored.
+  A trial whose preamble isn't found counts all its data symbols wrong
+  and half their bits.
+
+  `opts` may set `:symbol_rate` (2400), `:carrier_freq` (the channel's
+  carrier), `:max_threads` (the cores available), `:progress_ms` (250)
+  and `:abort` (a token from `new_abort_token/0`).
+
+  Every trial is seeded from `:seed` and its place in the grid alone, so
+  the results don't depend on the thread count. While it runs, `pid` gets
+  `{:sweep_progress, done, total}` (in trials) about every `progress_ms`;
+  at the end, `{:sweep_done, results}`, where `results` holds
+  `<<symbols::little-64, symbol_errors::little-64,
+  bit_errors::little-64, lost::little-64>>` per grid point, in grid
+  order. The grid is at most 65_536 points and 1_048_576 trials in all,
+  and `:symbols` at most 1_048_576; every point's channel is bounded as
+  for `compose_frame/2`.
+
+  Blocks until the sweep is done, so call it from a process of its own.
+  """
+  @spec run_sweep(map(), map(), pid()) :: :ok | {:error, :aborted | term()}
+  def run_sweep(_spec, _opts, _pid), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Counts the scoreboards alive in the VM: `%{scoreboards: count}`, where
+  a count has `:live`, `:created`, `:dropped` and `:bytes` (the buffers
+  held by the live ones as of their last `score/3`). Over a soak run
+  `live` and `bytes` should settle rather than climb.
+  """
+  @spec census() :: %{
+          scoreboards: %{
+            live: non_neg_integer(),
+            created: non_neg_integer(),
+            dropped: non_neg_integer(),
+            bytes: non_neg_integer()
+          }
+        }
+  def census(), do: :erlang.nif_error(:nif_not_loaded)
+end
@@ -0,0 +1,114 @@
+#ifndef CHANNEL_PHYSICS_H
+#define CHANNEL_PHYSICS_H
+
+/* Generated by cbindgen from src/capi.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Bumped whenever a signature or struct layout in this module changes
+ */
+#define CP_ABI_VERSION 1
+
+#define CP_OK 0
+
+/**
+ * A required pointer was NULL
+ */
+#define CP_ERR_NULL -1
+
+/**
+ * A parameter is out of range
+ */
+#define CP_ERR_INVALID_ARG -2
+
+/**
+ * The library panicked; the handle must be freed and not used again
+ */
+#define CP_ERR_PANIC -4
+
+/**
+ * Opaque channel handle
+ */
+typedef struct CpChannel CpChannel;
+
+/**
+ * Channel parameters, as ChannelParams without the separate fading and
+ * noise seeds or the warm start, and with the noise Gaussian in the
+ * default 3 kHz band; start from cp_channel_params_default()
+ */
+typedef struct CpChannelParams {
+  uint32_t sample_rate;
+  uint32_t delay_spread_samples;
+  double doppler_bandwidth_hz;
+  double snr_db;
+  double carrier_freq_hz;
+  uint32_t bulk_delay_samples;
+  uint32_t output_bits;
+  bool output_dither;
+  double clip_knee;
+  bool bypass;
+} CpChannelParams;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Library version, e.g. "0.1.0" (static, NUL-terminated)
+ */
+const char *cp_version(void);
+
+/**
+ * CP_ABI_VERSION of the library actually linked
+ */
+uint32_t cp_abi_version(void);
+
+/**
+ * A clean single-path channel: 9600 Hz, 1800 Hz carrier, no fading,
+ * 100 dB SNR, no delay, float output
+ */
+struct CpChannelParams cp_channel_params_default(void);
+
+/**
+ * Create a channel; on success `*out` holds the new handle
+ *
+ * The same params and seed give the same output as the NIF.
+ *
+ * # Safety
+ * `params` must point to a valid CpChannelParams and `out` to writable
+ * storage for a pointer.
+ */
+int32_t cp_channel_new(const struct CpChannelParams *params, uint64_t seed, struct CpChannel **out);
+
+/**
+ * Free a channel; NULL is ignored
+ *
+ * # Safety
+ * `channel` must come from cp_channel_new() and not be used afterwards.
+ */
+void cp_channel_free(struct CpChannel *channel);
+
+/**
+ * Run `num_samples` float samples through the channel
+ *
+ * Same as the NIF's process_block. `input` and `output` may be the same
+ * buffer.
+ *
+ * # Safety
+ * `input` must point to `num_samples` readable floats and `output` to
+ * `num_samples` writable floats (either may be NULL if `num_samples` is 0).
+ */
+int32_t cp_channel_process(struct CpChannel *channel,
+                           const float *input,
+                           float *output,
+                           uintptr_t num_samples);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CHANNEL_PHYSICS_H */
@@ -0,0 +1,143 @@
+//! Audit log of parameter changes
+//!
+//! Every operation that changes what a channel does to its input is
+//! recorded with the sample index it took effect at, so a test report can
+//! state exactly which conditions applied to which samples. The log keeps
+//! at most `cap` entries; once full, each new entry drops the oldest and
+//! counts it in `dropped`, so a long scenario can't grow it without bound.
+//!
+//! The NIF returns entries as
+//! - `{sample_index, :created, params, seed}`
+//! - `{sample_index, :update_params, old_params, new_params}`
+//! - `{sample_index, :reseed_noise, old_noise_seed | nil, new_noise_seed}`
+//! - `{sample_index, :reseed, old_seed, new_seed}` (reset with a new seed;
+//!   the sample index restarts at 0 after it)
+//! - `{sample_index, :hop_schedule, [{at_sample, carrier_hz}]}`
+//! - `{sample_index, :tr_schedule, [{at_sample, :tx | :rx}],
+//!   attenuation_db | nil, ramp_ms}`
+
+use std::collections::VecDeque;
+
+use crate::channel::ChannelParams;
+use crate::tr_switch::TrState;
+
+/// Entries a channel keeps unless set_cap() says otherwise
+pub const DEFAULT_AUDIT_CAP: usize = 256;
+
+/// One parameter-affecting operation
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuditChange {
+    /// The channel was built with these parameters and seed
+    Created { params: ChannelParams, seed: u64 },
+    /// update_params(), with the parameters before and after
+    Updated { old: ChannelParams, new: ChannelParams },
+    /// reseed_noise(); `old` is None while the noise followed the channel seed
+    NoiseReseeded { old: Option<u64>, new: u64 },
+    /// reset_to_idle(RngReset::Reseed)
+    Reseeded { old: u64, new: u64 },
+    /// set_hop_schedule(), with the hops as given
+    HopSchedule { hops: Vec<(u64, f64)> },
+    /// set_tr_schedule(), as given
+    TrSchedule { schedule: Vec<(u64, TrState)>, attenuation_db: Option<f64>, ramp_ms: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// Channel sample index the change applied from
+    pub sample_index: u64,
+    pub change: AuditChange,
+}
+
+/// Bounded, oldest-first log of AuditEntry
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    entries: VecDeque<AuditEntry>,
+    cap: usize,
+    dropped: u64,
+}
+
+impl AuditLog {
+    pub fn new(cap: usize) -> Result<Self, &'static str> {
+        if cap == 0 {
+            return Err("invalid_audit_cap");
+        }
+        Ok(Self { entries: VecDeque::new(), cap, dropped: 0 })
+    }
+
+    pub fn record(&mut self, sample_index: u64, change: AuditChange) {
+        if self.entries.len() == self.cap {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(AuditEntry { sample_index, change });
+    }
+
+    /// Change the cap, dropping the oldest entries if it shrinks
+    pub fn set_cap(&mut self, cap: usize) -> Result<(), &'static str> {
+        if cap == 0 {
+            return Err("invalid_audit_cap");
+        }
+        while self.entries.len() > cap {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.cap = cap;
+        Ok(())
+    }
+
+    /// Entries still held, oldest first
+    pub fn entries(&self) -> impl Iterator<Item = &AuditEntry> {
+        self.entries.iter()
+    }
+
+    /// Entries dropped to stay within the cap since the last clear()
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Bytes held by the entries (their own schedules aside)
+    pub fn memory_bytes(&self) -> usize {
+        self.entries.capacity() * std::mem::size_of::<AuditEntry>()
+    }
+
+    /// Forget every entry and reset the dropped count, freeing their space
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.entries.shrink_to_fit();
+        self.dropped = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reseed(n: u64) -> AuditChange {
+        AuditChange::NoiseReseeded { old: None, new: n }
+    }
+
+    #[test]
+    fn test_drops_oldest_beyond_cap() {
+        let mut log = AuditLog::new(3).unwrap();
+        for n in 0..5 {
+            log.record(n * 10, reseed(n));
+        }
+        let kept: Vec<u64> = log.entries().map(|e| e.sample_index).collect();
+        assert_eq!(kept, [20, 30, 40]);
+        assert_eq!(log.dropped(), 2);
+
+        log.set_cap(1).unwrap();
+        assert_eq!(log.entries().next().unwrap().change, reseed(4));
+        assert_eq!(log.dropped(), 4);
+
+        log.clear();
+        assert_eq!(log.entries().count(), 0);
+        assert_eq!(log.dropped(), 0);
+    }
+
+    #[test]
+    fn test_zero_cap_is_rejected() {
+        assert_eq!(AuditLog::new(0).unwrap_err(), "invalid_audit_cap");
+        assert_eq!(AuditLog::new(1).unwrap().set_cap(0), Err("invalid_audit_cap"));
+    }
+}
@@ -0,0 +1,355 @@
+//! ChannelParams from scenario-style terms
+//!
+//! ChannelParams wants the delay spread in samples and the Doppler spread
+//! in Hz; scenario files give delays in milliseconds and sometimes fading
+//! as a fade rate per minute. The conversions are done here, once:
+//!
+//! ```text
+//! samples = round(ms * sample_rate / 1000)
+//! doppler_hz = fades_per_min * e / (60 sqrt(2 pi))
+//! ```
+//!
+//! A fade being a downward crossing of the envelope's RMS level: for the
+//! channel's Jakes spectrum with maximum Doppler f_d, the crossing rate at
+//! ρ = 1 is sqrt(2π) f_d e^-1 per second (see fading's level crossing
+//! test), about 55 per minute per Hz.
+//!
+//! Keys and the field each fills:
+//!
+//! | key                                   | field                 | default |
+//! |---------------------------------------|-----------------------|---------|
+//! | sample_rate                           | sample_rate           | 9600    |
+//! | carrier_hz, carrier_freq_hz           | carrier_freq_hz       | 1800    |
+//! | snr_db                                | snr_db                | 10      |
+//! | delay_spread_ms, delay_spread_samples | delay_spread_samples  | 0       |
+//! | doppler_spread_hz, doppler_bandwidth_hz, fade_rate_per_min | doppler_bandwidth_hz | 1.0 |
+//! | bulk_delay_ms, bulk_delay_samples     | bulk_delay_samples    | 0       |
+//!
+//! The defaults are those of create_channel's Elixir wrapper; the fields
+//! not listed take their ChannelParams defaults. A field may be given by
+//! more than one of its keys only if they agree: the same number of
+//! samples once the milliseconds are rounded, or Doppler spreads within
+//! DOPPLER_AGREEMENT of each other. Unknown keys are rejected, so a typo
+//! doesn't silently leave a default in place.
+
+use rustler::{Atom, Encoder, Env, NifUnitEnum, Term};
+
+use crate::channel::ChannelParams;
+use crate::exchange::{DEFAULT_CARRIER_HZ, DEFAULT_SAMPLE_RATE, MAX_DOPPLER_SPREAD_HZ};
+
+/// SNR when none is given, dB
+pub const DEFAULT_SNR_DB: f64 = 10.0;
+
+/// Doppler spread when none is given, Hz
+pub const DEFAULT_DOPPLER_HZ: f64 = 1.0;
+
+/// Relative difference within which two Doppler terms agree
+pub const DOPPLER_AGREEMENT: f64 = 0.01;
+
+/// Fades (downward crossings of the RMS level) per minute per Hz of
+/// maximum Doppler
+pub fn fades_per_min_per_hz() -> f64 {
+    60.0 * (2.0 * std::f64::consts::PI).sqrt() / std::f64::consts::E
+}
+
+/// Where a built field's value came from
+#[derive(NifUnitEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Given,
+    Converted,
+    Default,
+}
+
+/// One field of the result, for logging
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Derived {
+    pub name: &'static str,
+    pub value: f64,
+    pub source: Source,
+}
+
+/// Encoded as {name, value, source}
+impl Encoder for Derived {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        let name = Atom::from_str(env, self.name).expect("short ASCII atom names");
+        (name, self.value, self.source).encode(env)
+    }
+}
+
+/// Why the terms couldn't be turned into ChannelParams
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    UnknownKey(String),
+    /// Not a usable value for the key
+    Invalid { key: &'static str, message: &'static str },
+    /// Two keys for one field disagree
+    Ambiguous(&'static str, &'static str),
+}
+
+impl Encoder for BuildError {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        let atom = |name: &str| Atom::from_str(env, name).expect("short ASCII atom names");
+        match self {
+            BuildError::UnknownKey(key) => (atom("unknown_key"), key.as_str()).encode(env),
+            BuildError::Invalid { key, message } => (atom("invalid_param"), atom(key), *message).encode(env),
+            BuildError::Ambiguous(a, b) => (atom("ambiguous"), atom(a), atom(b)).encode(env),
+        }
+    }
+}
+
+const KEYS: [&str; 11] = [
+    "sample_rate",
+    "carrier_hz",
+    "carrier_freq_hz",
+    "snr_db",
+    "delay_spread_ms",
+    "delay_spread_samples",
+    "doppler_spread_hz",
+    "doppler_bandwidth_hz",
+    "fade_rate_per_min",
+    "bulk_delay_ms",
+    "bulk_delay_samples",
+];
+
+/// The terms given, by key
+struct Terms<'a>(&'a [(String, f64)]);
+
+impl Terms<'_> {
+    fn get(&self, key: &'static str) -> Result<Option<f64>, BuildError> {
+        match self.0.iter().find(|(k, _)| k == key) {
+            Some(&(_, x)) if !x.is_finite() => Err(BuildError::Invalid { key, message: "must be finite" }),
+            Some(&(_, x)) => Ok(Some(x)),
+            None => Ok(None),
+        }
+    }
+
+    /// A value that may be given under either of two keys, which must then
+    /// agree by `same`
+    fn either(
+        &self,
+        a: &'static str,
+        b: &'static str,
+        same: impl Fn(f64, f64) -> bool,
+    ) -> Result<Option<(&'static str, f64)>, BuildError> {
+        match (self.get(a)?, self.get(b)?) {
+            (Some(x), Some(y)) if !same(x, y) => Err(BuildError::Ambiguous(a, b)),
+            (Some(x), _) => Ok(Some((a, x))),
+            (None, Some(y)) => Ok(Some((b, y))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// A delay as whole samples, from `ms_key` or `samples_key`
+fn delay_samples(
+    terms: &Terms,
+    ms_key: &'static str,
+    samples_key: &'static str,
+    sample_rate: u32,
+) -> Result<(u32, Source), BuildError> {
+    let to_samples = |ms: f64| (ms * sample_rate as f64 / 1000.0).round();
+    let ms = terms.get(ms_key)?;
+    let samples = terms.get(samples_key)?;
+    if let Some(ms) = ms {
+        if ms < 0.0 {
+            return Err(BuildError::Invalid { key: ms_key, message: "must not be negative" });
+        }
+    }
+    if let Some(samples) = samples {
+        if samples < 0.0 || samples.fract() != 0.0 {
+            return Err(BuildError::Invalid { key: samples_key, message: "must be a whole number of samples" });
+        }
+    }
+    let (value, source) = match (ms, samples) {
+        (Some(ms), Some(samples)) if to_samples(ms) != samples => return Err(BuildError::Ambiguous(ms_key, samples_key)),
+        (_, Some(samples)) => (samples, Source::Given),
+        (Some(ms), None) => (to_samples(ms), Source::Converted),
+        (None, None) => (0.0, Source::Default),
+    };
+    if value > u32::MAX as f64 {
+        return Err(BuildError::Invalid { key: samples_key, message: "too long" });
+    }
+    Ok((value as u32, source))
+}
+
+/// ChannelParams from `terms` (key, value) pairs, with every field set
+/// from them listed for logging
+///
+/// Only the conversions and the keys' own ranges are checked here; the
+/// result still has to pass create_channel's validation.
+pub fn build(terms: &[(String, f64)]) -> Result<(ChannelParams, Vec<Derived>), BuildError> {
+    if let Some((key, _)) = terms.iter().find(|(k, _)| !KEYS.contains(&k.as_str())) {
+        return Err(BuildError::UnknownKey(key.clone()));
+    }
+    // An atom and a string key of the same name
+    if let Some(&key) = KEYS.iter().find(|&&key| terms.iter().filter(|(k, _)| k == key).count() > 1) {
+        return Err(BuildError::Ambiguous(key, key));
+    }
+    let terms = Terms(terms);
+    let mut derived = Vec::new();
+    let mut note = |name, value, source| derived.push(Derived { name, value, source });
+    let given = |x: Option<f64>, default| x.map_or((default, Source::Default), |x| (x, Source::Given));
+
+    let (sample_rate, source) = given(terms.get("sample_rate")?, DEFAULT_SAMPLE_RATE as f64);
+    if sample_rate < 1.0 || sample_rate.fract() != 0.0 || sample_rate > u32::MAX as f64 {
+        return Err(BuildError::Invalid { key: "sample_rate", message: "must be a whole number of Hz" });
+    }
+    note("sample_rate", sample_rate, source);
+    let sample_rate = sample_rate as u32;
+    let nyquist = sample_rate as f64 / 2.0;
+
+    let carrier = terms.either("carrier_hz", "carrier_freq_hz", |x, y| x == y)?;
+    let (carrier_freq_hz, source) = given(carrier.map(|(_, x)| x), DEFAULT_CARRIER_HZ);
+    if !(carrier_freq_hz > 0.0 && carrier_freq_hz < nyquist) {
+        let key = carrier.map_or("carrier_hz", |(key, _)| key);
+        return Err(BuildError::Invalid { key, message: "must be above 0 Hz and below Nyquist" });
+    }
+    note("carrier_freq_hz", carrier_freq_hz, source);
+
+    let (snr_db, source) = given(terms.get("snr_db")?, DEFAULT_SNR_DB);
+    note("snr_db", snr_db, source);
+
+    let (delay_spread_samples, source) = delay_samples(&terms, "delay_spread_ms", "delay_spread_samples", sample_rate)?;
+    note("delay_spread_samples", delay_spread_samples as f64, source);
+    note("delay_spread_ms", delay_spread_samples as f64 * 1000.0 / sample_rate as f64, Source::Converted);
+
+    let agree = |x: f64, y: f64| (x - y).abs() <= DOPPLER_AGREEMENT * x.abs().max(y.abs());
+    let doppler = terms.either("doppler_spread_hz", "doppler_bandwidth_hz", |x, y| x == y)?;
+    let fade_rate = terms.get("fade_rate_per_min")?;
+    let from_fade_rate = fade_rate.map(|rate| rate / fades_per_min_per_hz());
+    let (doppler_bandwidth_hz, source) = match (doppler, from_fade_rate) {
+        (Some((key, hz)), Some(converted)) if !agree(hz, converted) => {
+            return Err(BuildError::Ambiguous(key, "fade_rate_per_min"))
+        }
+        (Some((_, hz)), _) => (hz, Source::Given),
+        (None, Some(converted)) => (converted, Source::Converted),
+        (None, None) => (DEFAULT_DOPPLER_HZ, Source::Default),
+    };
+    if !(0.0..=MAX_DOPPLER_SPREAD_HZ).contains(&doppler_bandwidth_hz) {
+        let key = match (doppler, fade_rate) {
+            (Some((key, _)), _) => key,
+            (None, _) => "fade_rate_per_min",
+        };
+        return Err(BuildError::Invalid { key, message: "Doppler spread out of range" });
+    }
+    note("doppler_bandwidth_hz", doppler_bandwidth_hz, source);
+    note("fade_rate_per_min", doppler_bandwidth_hz * fades_per_min_per_hz(), Source::Converted);
+
+    let (bulk_delay_samples, source) = delay_samples(&terms, "bulk_delay_ms", "bulk_delay_samples", sample_rate)?;
+    note("bulk_delay_samples", bulk_delay_samples as f64, source);
+    note("bulk_delay_ms", bulk_delay_samples as f64 * 1000.0 / sample_rate as f64, Source::Converted);
+
+    let params = ChannelParams {
+        sample_rate,
+        delay_spread_samples,
+        doppler_bandwidth_hz,
+        snr_db,
+        carrier_freq_hz,
+        bulk_delay_samples,
+        ..ChannelParams::default()
+    };
+    Ok((params, derived))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(pairs: &[(&str, f64)]) -> Vec<(String, f64)> {
+        pairs.iter().map(|&(k, v)| (k.to_string(), v)).collect()
+    }
+
+    fn derived(list: &[Derived], name: &str) -> (f64, Source) {
+        let d = list.iter().find(|d| d.name == name).unwrap();
+        (d.value, d.source)
+    }
+
+    #[test]
+    fn test_defaults() {
+        let (params, list) = build(&[]).unwrap();
+        assert_eq!(params.sample_rate, 9600);
+        assert_eq!(params.carrier_freq_hz, 1800.0);
+        assert_eq!((params.snr_db, params.doppler_bandwidth_hz), (10.0, 1.0));
+        assert_eq!((params.delay_spread_samples, params.bulk_delay_samples), (0, 0));
+        assert_eq!(derived(&list, "snr_db"), (10.0, Source::Default));
+        assert_eq!(derived(&list, "delay_spread_samples"), (0.0, Source::Default));
+    }
+
+    #[test]
+    fn test_delay_conversions() {
+        // 2 ms at 9600 Hz is 19.2 samples; at 8000 Hz exactly 16
+        let (params, list) = build(&terms(&[("delay_spread_ms", 2.0), ("bulk_delay_ms", 10.0)])).unwrap();
+        assert_eq!((params.delay_spread_samples, params.bulk_delay_samples), (19, 96));
+        assert_eq!(derived(&list, "delay_spread_samples"), (19.0, Source::Converted));
+        // What the rounding left
+        let (ms, _) = derived(&list, "delay_spread_ms");
+        assert!((ms - 19.0 / 9.6).abs() < 1e-12);
+
+        let (params, _) = build(&terms(&[("delay_spread_ms", 2.0), ("sample_rate", 8000.0)])).unwrap();
+        assert_eq!(params.delay_spread_samples, 16);
+
+        let (params, list) = build(&terms(&[("delay_spread_samples", 7.0)])).unwrap();
+        assert_eq!(params.delay_spread_samples, 7);
+        assert_eq!(derived(&list, "delay_spread_samples"), (7.0, Source::Given));
+    }
+
+    #[test]
+    fn test_fade_rate_conversion() {
+        // sqrt(2 pi) e^-1 = 0.922 fades a second per Hz, 55.3 a minute
+        assert!((fades_per_min_per_hz() - 55.328_221).abs() < 1e-5);
+        let (params, list) = build(&terms(&[("fade_rate_per_min", 55.328_221)])).unwrap();
+        assert!((params.doppler_bandwidth_hz - 1.0).abs() < 1e-6);
+        assert_eq!(derived(&list, "doppler_bandwidth_hz").1, Source::Converted);
+
+        let (params, list) = build(&terms(&[("doppler_spread_hz", 0.5)])).unwrap();
+        assert_eq!(params.doppler_bandwidth_hz, 0.5);
+        let (rate, source) = derived(&list, "fade_rate_per_min");
+        assert!((rate - 27.664_110).abs() < 1e-5 && source == Source::Converted);
+    }
+
+    #[test]
+    fn test_aliases_and_agreeing_terms() {
+        let (params, _) = build(&terms(&[("carrier_freq_hz", 1500.0), ("doppler_bandwidth_hz", 2.0)])).unwrap();
+        assert_eq!((params.carrier_freq_hz, params.doppler_bandwidth_hz), (1500.0, 2.0));
+
+        // Consistent duplicates are fine, and the exact term wins
+        let (params, _) = build(&terms(&[
+            ("delay_spread_ms", 2.0),
+            ("delay_spread_samples", 19.0),
+            ("doppler_spread_hz", 1.0),
+            ("fade_rate_per_min", 55.0),
+        ]))
+        .unwrap();
+        assert_eq!((params.delay_spread_samples, params.doppler_bandwidth_hz), (19, 1.0));
+    }
+
+    #[test]
+    fn test_ambiguous_terms_rejected() {
+        let cases = [
+            (terms(&[("delay_spread_ms", 2.0), ("delay_spread_samples", 38.0)]), ("delay_spread_ms", "delay_spread_samples")),
+            (terms(&[("bulk_delay_ms", 1.0), ("bulk_delay_samples", 0.0)]), ("bulk_delay_ms", "bulk_delay_samples")),
+            (terms(&[("doppler_spread_hz", 1.0), ("fade_rate_per_min", 110.0)]), ("doppler_spread_hz", "fade_rate_per_min")),
+            (terms(&[("doppler_spread_hz", 1.0), ("doppler_bandwidth_hz", 2.0)]), ("doppler_spread_hz", "doppler_bandwidth_hz")),
+            (terms(&[("carrier_hz", 1800.0), ("carrier_freq_hz", 1500.0)]), ("carrier_hz", "carrier_freq_hz")),
+        ];
+        for (terms, (a, b)) in cases {
+            assert_eq!(build(&terms), Err(BuildError::Ambiguous(a, b)), "{terms:?}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_terms_rejected() {
+        assert_eq!(build(&terms(&[("delay_ms", 2.0)])), Err(BuildError::UnknownKey("delay_ms".into())));
+        assert_eq!(build(&terms(&[("snr_db", 10.0), ("snr_db", 12.0)])), Err(BuildError::Ambiguous("snr_db", "snr_db")));
+        let invalid = |pairs: &[(&str, f64)]| match build(&terms(pairs)) {
+            Err(BuildError::Invalid { key, .. }) => key,
+            other => panic!("{pairs:?} gave {other:?}"),
+        };
+        assert_eq!(invalid(&[("delay_spread_samples", 2.5)]), "delay_spread_samples");
+        assert_eq!(invalid(&[("delay_spread_ms", -1.0)]), "delay_spread_ms");
+        assert_eq!(invalid(&[("snr_db", f64::NAN)]), "snr_db");
+        assert_eq!(invalid(&[("sample_rate", 0.0)]), "sample_rate");
+        assert_eq!(invalid(&[("carrier_freq_hz", 5000.0)]), "carrier_freq_hz");
+        assert_eq!(invalid(&[("fade_rate_per_min", -3.0)]), "fade_rate_per_min");
+    }
+}
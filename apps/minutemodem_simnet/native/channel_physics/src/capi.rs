@@ -0,0 +1,237 @@
+//! C ABI (feature `capi`)
+//!
+//! A small extern "C" surface over WattersonChannel so C and C++ test
+//! tools can link the same channel model the NIF ships. The header is
+//! include/channel_physics.h, generated by cbindgen (see cbindgen.toml and
+//! scripts/capi_smoke.sh at the repo root).
+//!
+//! Handles are opaque pointers owned by the caller: every `cp_*_new` must be
+//! paired with the matching `cp_*_free`. Functions return CP_OK or a
+//! negative CP_ERR_* code, and never unwind into C: a panic is caught and
+//! reported as CP_ERR_PANIC. A handle must not be used from two threads at
+//! once.
+
+use std::ffi::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::channel::{ChannelParams, WattersonChannel};
+use crate::{limits, output};
+
+/// Bumped whenever a signature or struct layout in this module changes
+pub const CP_ABI_VERSION: u32 = 1;
+
+pub const CP_OK: i32 = 0;
+/// A required pointer was NULL
+pub const CP_ERR_NULL: i32 = -1;
+/// A parameter is out of range
+pub const CP_ERR_INVALID_ARG: i32 = -2;
+/// The library panicked; the handle must be freed and not used again
+pub const CP_ERR_PANIC: i32 = -4;
+
+/// Opaque channel handle
+pub struct CpChannel {
+    inner: WattersonChannel,
+}
+
+/// Channel parameters, as ChannelParams without the separate fading and
+/// noise seeds or the warm start, and with the noise Gaussian in the
+/// default 3 kHz band; start from cp_channel_params_default()
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CpChannelParams {
+    pub sample_rate: u32,
+    pub delay_spread_samples: u32,
+    pub doppler_bandwidth_hz: f64,
+    pub snr_db: f64,
+    pub carrier_freq_hz: f64,
+    pub bulk_delay_samples: u32,
+    pub output_bits: u32,
+    pub output_dither: bool,
+    pub clip_knee: f64,
+    pub bypass: bool,
+}
+
+impl From<CpChannelParams> for ChannelParams {
+    fn from(p: CpChannelParams) -> Self {
+        ChannelParams {
+            sample_rate: p.sample_rate,
+            delay_spread_samples: p.delay_spread_samples,
+            doppler_bandwidth_hz: p.doppler_bandwidth_hz,
+            snr_db: p.snr_db,
+            carrier_freq_hz: p.carrier_freq_hz,
+            bulk_delay_samples: p.bulk_delay_samples,
+            output_bits: p.output_bits,
+            output_dither: p.output_dither,
+            clip_knee: p.clip_knee,
+            bypass: p.bypass,
+            ..ChannelParams::default()
+        }
+    }
+}
+
+/// Run `f`, turning a panic into CP_ERR_PANIC
+fn guard(f: impl FnOnce() -> i32) -> i32 {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(CP_ERR_PANIC)
+}
+
+/// Library version, e.g. "0.1.0" (static, NUL-terminated)
+#[no_mangle]
+pub extern "C" fn cp_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// CP_ABI_VERSION of the library actually linked
+#[no_mangle]
+pub extern "C" fn cp_abi_version() -> u32 {
+    CP_ABI_VERSION
+}
+
+/// A clean single-path channel: 9600 Hz, 1800 Hz carrier, no fading,
+/// 100 dB SNR, no delay, float output
+#[no_mangle]
+pub extern "C" fn cp_channel_params_default() -> CpChannelParams {
+    CpChannelParams {
+        sample_rate: 9600,
+        delay_spread_samples: 0,
+        doppler_bandwidth_hz: 0.0,
+        snr_db: 100.0,
+        carrier_freq_hz: 1800.0,
+        bulk_delay_samples: 0,
+        output_bits: 0,
+        output_dither: false,
+        clip_knee: 0.0,
+        bypass: false,
+    }
+}
+
+/// Create a channel; on success `*out` holds the new handle
+///
+/// The same params and seed give the same output as the NIF.
+///
+/// # Safety
+/// `params` must point to a valid CpChannelParams and `out` to writable
+/// storage for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn cp_channel_new(
+    params: *const CpChannelParams,
+    seed: u64,
+    out: *mut *mut CpChannel,
+) -> i32 {
+    guard(|| {
+        if params.is_null() || out.is_null() {
+            return CP_ERR_NULL;
+        }
+        let params = ChannelParams::from(*params);
+        if limits::validate_params(&params).is_err() {
+            return CP_ERR_INVALID_ARG;
+        }
+        if params.sample_rate == 0 || output::validate(params.output_bits, params.clip_knee).is_err() {
+            return CP_ERR_INVALID_ARG;
+        }
+        *out = Box::into_raw(Box::new(CpChannel { inner: WattersonChannel::new(params, seed) }));
+        CP_OK
+    })
+}
+
+/// Free a channel; NULL is ignored
+///
+/// # Safety
+/// `channel` must come from cp_channel_new() and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn cp_channel_free(channel: *mut CpChannel) {
+    if !channel.is_null() {
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(channel))));
+    }
+}
+
+/// Run `num_samples` float samples through the channel
+///
+/// Same as the NIF's process_block. `input` and `output` may be the same
+/// buffer.
+///
+/// # Safety
+/// `input` must point to `num_samples` readable floats and `output` to
+/// `num_samples` writable floats (either may be NULL if `num_samples` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn cp_channel_process(
+    channel: *mut CpChannel,
+    input: *const f32,
+    output: *mut f32,
+    num_samples: usize,
+) -> i32 {
+    guard(|| {
+        let Some(channel) = channel.as_mut() else {
+            return CP_ERR_NULL;
+        };
+        if num_samples == 0 {
+            return CP_OK;
+        }
+        if input.is_null() || output.is_null() {
+            return CP_ERR_NULL;
+        }
+
+        // Copy the input out before writing, so the buffers may alias
+        let processed = channel.inner.process(std::slice::from_raw_parts(input, num_samples));
+        std::ptr::copy_nonoverlapping(processed.as_ptr(), output, num_samples);
+        CP_OK
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::ptr;
+
+    fn new_channel(params: &CpChannelParams, seed: u64) -> Result<*mut CpChannel, i32> {
+        let mut handle = ptr::null_mut();
+        match unsafe { cp_channel_new(params, seed, &mut handle) } {
+            CP_OK => Ok(handle),
+            err => Err(err),
+        }
+    }
+
+    #[test]
+    fn test_versions() {
+        let version = unsafe { CStr::from_ptr(cp_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+        assert_eq!(cp_abi_version(), CP_ABI_VERSION);
+    }
+
+    #[test]
+    fn test_process_matches_rust_api_in_place() {
+        let params = CpChannelParams {
+            delay_spread_samples: 10,
+            doppler_bandwidth_hz: 1.0,
+            snr_db: 20.0,
+            ..cp_channel_params_default()
+        };
+        let input: Vec<f32> = (0..2000).map(|i| (i as f32 * 0.9).sin() * 0.5).collect();
+        let expected = WattersonChannel::new(params.into(), 33).process(&input);
+
+        let channel = new_channel(&params, 33).unwrap();
+        let mut buffer = input.clone();
+        let rc = unsafe { cp_channel_process(channel, buffer.as_ptr(), buffer.as_mut_ptr(), buffer.len()) };
+        unsafe { cp_channel_free(channel) };
+
+        assert_eq!(rc, CP_OK);
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn test_rejects_bad_arguments() {
+        let bad_bits = CpChannelParams { output_bits: 1, ..cp_channel_params_default() };
+        assert_eq!(new_channel(&bad_bits, 0).unwrap_err(), CP_ERR_INVALID_ARG);
+        let bad_rate = CpChannelParams { sample_rate: 0, ..cp_channel_params_default() };
+        assert_eq!(new_channel(&bad_rate, 0).unwrap_err(), CP_ERR_INVALID_ARG);
+        let huge_spread = CpChannelParams { delay_spread_samples: u32::MAX, ..cp_channel_params_default() };
+        assert_eq!(new_channel(&huge_spread, 0).unwrap_err(), CP_ERR_INVALID_ARG);
+        let huge_delay = CpChannelParams { bulk_delay_samples: u32::MAX, ..cp_channel_params_default() };
+        assert_eq!(new_channel(&huge_delay, 0).unwrap_err(), CP_ERR_INVALID_ARG);
+        assert_eq!(unsafe { cp_channel_new(ptr::null(), 0, &mut ptr::null_mut()) }, CP_ERR_NULL);
+
+        let mut out = [0.0f32; 4];
+        assert_eq!(unsafe { cp_channel_process(ptr::null_mut(), out.as_ptr(), out.as_mut_ptr(), 4) }, CP_ERR_NULL);
+        unsafe { cp_channel_free(ptr::null_mut()) };
+    }
+}
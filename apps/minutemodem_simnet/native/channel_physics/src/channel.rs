@@ -7,22 +7,73 @@
 //! - AWGN added at output
 //!
 //! This implementation uses carrier mixing to properly apply complex fading
-//! to real passband audio signals:
+//! to real passband audio signals (after the bulk delay and any input
+//! conditioning, see `conditioning`):
 //! 1. Mix down to baseband I/Q using known carrier frequency
 //! 2. Low-pass filter with linear-phase FIR (constant group delay)
 //! 3. Apply complex fading coefficients
 //! 4. Mix back up to passband (compensating for filter delay)
+//!
+//! After the noise, an optional TR switch mutes the receiver around its
+//! own transmissions (see `tr_switch`), an optional fractional delay
+//! moves the output between samples (see `fractional_delay`), an optional
+//! clock drift stage
+//! resamples onto the receiving sound card's clock (see `drift`), and an
+//! optional output stage models its limiter and ADC (see `output`).
+//!
+//! Steps 1-4 and the fading run in f64 or f32 as the params' precision
+//! says (see `precision`); the rest is always f64.
 
 use rustler::NifStruct;
 use rand_chacha::ChaCha8Rng;
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
 use std::f64::consts::PI;
+use std::sync::Arc;
 
-use super::fading::FadingTap;
+use minutemodem_dsp::lo::quadrant_cos_sin;
+use minutemodem_dsp::{windowed_sinc_lowpass, TrivialLo};
+
+use super::audit::{AuditChange, AuditLog, DEFAULT_AUDIT_CAP};
+use super::bulk_delay::{self, BulkDelay};
+use super::conditioning::InputConditioning;
+use super::drift::{self, ClockDrift};
+use super::fade_alarm::{FadeAlarm, FadeEvent};
+use super::fading::{self, FadingTap};
+use super::fractional_delay::{self, FractionalDelay};
+use super::group::{GroupOffsets, GroupWeather};
 use super::noise::NoiseGenerator;
+use super::output::OutputStage;
+use super::phase_log::{self, PhaseEntry, PhaseLog};
+use super::precision::{Float, Precision};
+use super::tr_switch::{TrState, TrSwitch};
+
+/// Cutoff of the baseband filters: wider than the ~2400 Hz of an ALE
+/// signal, with some margin
+pub(crate) const LPF_CUTOFF_HZ: f64 = 2800.0;
+
+/// Largest tap gain either way, dB
+pub(crate) const MAX_TAP_GAIN_DB: f64 = 60.0;
+
+/// Largest frequency offset either way, Hz
+pub(crate) const MAX_FREQUENCY_OFFSET_HZ: f64 = 500.0;
+
+/// Largest impulse-to-background power ratio, dB
+pub(crate) const MAX_IMPULSE_POWER_RATIO_DB: f64 = 80.0;
+
+/// Longest TR switching ramp, ms
+pub(crate) const MAX_TR_RAMP_MS: f64 = 1000.0;
+
+/// Baseband filter length: good stopband attenuation for a group delay
+/// of (31-1)/2 = 15 samples, about 1.56 ms at 9600 Hz
+pub(crate) const LPF_TAPS: usize = 31;
+
+/// Bandwidth the noise is shaped to and snr_db taken in when none is
+/// given, Hz: the 3 kHz HF modem SNRs are quoted in
+pub const DEFAULT_NOISE_BANDWIDTH_HZ: f64 = 3000.0;
 
 /// Channel parameters from Elixir
-#[derive(NifStruct, Debug, Clone)]
+#[derive(NifStruct, Debug, Clone, PartialEq)]
 #[module = "MinutemodemSimnet.Physics.Types.ChannelParams"]
 pub struct ChannelParams {
     pub sample_rate: u32,
@@ -30,6 +81,103 @@ pub struct ChannelParams {
     pub doppler_bandwidth_hz: f64,
     pub snr_db: f64,
     pub carrier_freq_hz: f64,
+    /// One-way propagation delay ahead of the fading section
+    pub bulk_delay_samples: u32,
+    /// Receiver ADC bit depth (16, 12, 8, ...); 0 keeps float output
+    pub output_bits: u32,
+    /// TPDF dither ahead of the quantizer
+    pub output_dither: bool,
+    /// Soft-clip knee as a fraction of full scale; 0.0 disables limiting
+    pub clip_knee: f64,
+    /// Loop-back cable: output is the input, sample for sample
+    pub bypass: bool,
+    /// Seed for the fading taps alone; None uses the channel seed
+    pub fading_seed: Option<u64>,
+    /// Seed for the AWGN alone; None uses the channel seed
+    pub noise_seed: Option<u64>,
+    /// Warm start: the fading begins this many seconds into its realization
+    pub start_at_time_s: f64,
+    /// Warm start: from start_at_time_s, skip ahead to the first instant the
+    /// combined envelope is at or below this level (dB re its mean, < 0)
+    pub start_in_fade_db: Option<f64>,
+    /// Receiving sound card's clock offset from the transmitter's, ppm:
+    /// positive gives more output samples than input (see drift)
+    pub sample_rate_offset_ppm: f64,
+    /// Block DC at the input (see conditioning)
+    pub input_dc_block: bool,
+    /// Tilt the input's response by this many dB from 300 to 3000 Hz,
+    /// positive lifting the top; 0.0 leaves it flat (see conditioning)
+    pub input_tilt_db: f64,
+    /// Float type of the fading, mixers and baseband filters (see
+    /// precision)
+    pub precision: Precision,
+    /// Conjugate the baseband (Q -> -Q), mirroring the spectrum about the
+    /// carrier as one end on LSB and the other on USB would
+    pub sideband_inversion: bool,
+    /// Delay the output by this fraction of a sample, in [0, 1), on top of
+    /// the whole-sample delays (see fractional_delay)
+    pub fractional_delay_samples: f64,
+    /// Mean power of the direct path, dB, relative to tap1_gain_db
+    pub tap0_gain_db: f64,
+    /// Mean power of the delayed path, dB, relative to tap0_gain_db
+    ///
+    /// The two are scaled together to unit total mean power (see
+    /// tap_split), so their difference is the exact power ratio between
+    /// the echoes: 0.0 and 0.0 is the usual equal split, 0.0 and -6.0 a
+    /// second hop 6 dB down. Ignored on a single path.
+    pub tap1_gain_db: f64,
+    /// Shift the whole received spectrum by this much, Hz, as a mistuned
+    /// receiver or a Doppler shift on the path would (see frequency_offset)
+    pub frequency_offset_hz: f64,
+    /// Low-pass the noise to this bandwidth, Hz, with snr_db its SNR from
+    /// 0 Hz up to there; 0.0 or less leaves it white to Nyquist, with
+    /// snr_db taken over the whole band (see noise)
+    pub noise_bandwidth_hz: f64,
+    /// Chance per sample of a burst of impulsive noise starting; 0.0 for
+    /// Gaussian noise alone (see noise)
+    pub impulse_probability: f64,
+    /// Power of the impulses over the background noise's, dB
+    pub impulse_power_ratio_db: f64,
+    /// Length of each impulse burst, samples
+    pub impulse_burst_samples: u32,
+}
+
+/// The Elixir struct's defaults
+///
+/// The fields it has no default for are a flat, unfaded channel at
+/// 9600 Hz, with the carrier and SNR Channel.Params falls back to.
+impl Default for ChannelParams {
+    fn default() -> Self {
+        Self {
+            sample_rate: 9600,
+            delay_spread_samples: 0,
+            doppler_bandwidth_hz: 0.0,
+            snr_db: 10.0,
+            carrier_freq_hz: 1800.0,
+            bulk_delay_samples: 0,
+            output_bits: 0,
+            output_dither: false,
+            clip_knee: 0.0,
+            bypass: false,
+            fading_seed: None,
+            noise_seed: None,
+            start_at_time_s: 0.0,
+            start_in_fade_db: None,
+            sample_rate_offset_ppm: 0.0,
+            input_dc_block: false,
+            input_tilt_db: 0.0,
+            precision: Precision::F64,
+            sideband_inversion: false,
+            fractional_delay_samples: 0.0,
+            tap0_gain_db: 0.0,
+            tap1_gain_db: 0.0,
+            frequency_offset_hz: 0.0,
+            noise_bandwidth_hz: DEFAULT_NOISE_BANDWIDTH_HZ,
+            impulse_probability: 0.0,
+            impulse_power_ratio_db: 20.0,
+            impulse_burst_samples: 1,
+        }
+    }
 }
 
 /// Channel state for telemetry
@@ -37,324 +185,1671 @@ pub struct ChannelParams {
 #[module = "MinutemodemSimnet.Physics.Types.ChannelState"]
 pub struct ChannelState {
     pub sample_index: u64,
+    /// Phase of tap0_gain, radians
     pub tap0_phase: f64,
+    /// Phase of tap1_gain, radians
     pub tap1_phase: f64,
+    /// Bulk delay currently applied (fractional while slewing)
+    pub bulk_delay_samples: f64,
+    /// Delay from input to output along the direct path: the bulk delay
+    /// as applied, the baseband filters, the fractional delay and the
+    /// drift resampler
+    pub total_delay_samples: f64,
+    pub bypass: bool,
+    /// Where the fading started, in seconds into its realization
+    pub start_time_s: f64,
+    /// Samples output so far: sample_index, plus or minus the clock drift
+    pub output_samples: u64,
+    /// Whether the receiver is listening or muted for its own transmission
+    /// (see tr_switch); :rx without a TR schedule
+    pub tr_state: TrState,
+    /// Outputs over which the channel's filters and delay lines fill or
+    /// empty (see WattersonChannel::transient_len)
+    pub transient_len: u64,
+    /// Direct path's fading coefficient (I, Q) for the next sample, of
+    /// unit mean power
+    pub tap0_gain: (f64, f64),
+    /// Delayed path's, likewise (not used on a single path)
+    pub tap1_gain: (f64, f64),
+    /// Combined envelope of the taps in use, dB re its long-term mean:
+    /// the level the fade alarm watches
+    pub envelope_db: f64,
+    /// SNR the noise is set for, dB, with any group's offset
+    pub snr_db: f64,
+    /// Mean square of the output over about the last 100 ms, dB (see
+    /// PowerMeter)
+    pub output_power_db: f64,
 }
 
 /// Linear-phase FIR low-pass filter
 /// Uses windowed-sinc design for constant group delay
-pub struct FirLowPassFilter {
-    coeffs: Vec<f64>,
-    history: Vec<f64>,
+///
+/// The history is kept twice over, so the last num_taps samples are
+/// always one contiguous slice for Float::dot to run along.
+#[derive(Clone)]
+pub struct FirLowPassFilter<T = f64> {
+    // Oldest-first, to line up with the history
+    coeffs: Vec<T>,
+    history: Vec<T>,
     write_idx: usize,
-    delay: usize, // Group delay in samples = (len-1)/2
 }
 
-impl FirLowPassFilter {
+impl<T: Float> FirLowPassFilter<T> {
     /// Create a FIR LPF using windowed-sinc design
     /// cutoff_hz: cutoff frequency
     /// sample_rate: sample rate in Hz
     /// num_taps: filter length (odd number for symmetric filter)
     fn new(cutoff_hz: f64, sample_rate: f64, num_taps: usize) -> Self {
-        // Ensure odd number of taps for type 1 linear phase
-        let num_taps = if num_taps % 2 == 0 { num_taps + 1 } else { num_taps };
-        let center = (num_taps - 1) / 2;
-        
-        // Normalized cutoff frequency (0 to 0.5)
-        let fc = cutoff_hz / sample_rate;
-        
-        let mut coeffs = vec![0.0; num_taps];
-        
-        for i in 0..num_taps {
-            let n = i as f64 - center as f64;
-            
-            // Sinc function
-            let sinc = if n.abs() < 1e-10 {
-                2.0 * fc
-            } else {
-                (2.0 * PI * fc * n).sin() / (PI * n)
-            };
-            
-            // Hamming window
-            let window = 0.54 - 0.46 * (2.0 * PI * i as f64 / (num_taps - 1) as f64).cos();
-            
-            coeffs[i] = sinc * window;
-        }
-        
-        // Normalize for unity DC gain
-        let sum: f64 = coeffs.iter().sum();
-        for c in &mut coeffs {
-            *c /= sum;
-        }
-        
+        Self::from_taps(windowed_sinc_lowpass(cutoff_hz, sample_rate, num_taps))
+    }
+    
+    /// A FIR with the given taps, newest input first
+    pub(crate) fn from_taps(taps: Vec<f64>) -> Self {
+        let coeffs: Vec<T> = taps.into_iter().rev().map(T::from_f64).collect();
         Self {
+            history: vec![T::default(); 2 * coeffs.len()],
             coeffs,
-            history: vec![0.0; num_taps],
             write_idx: 0,
-            delay: center,
         }
     }
     
     /// Process one sample through the filter
-    fn process(&mut self, x: f64) -> f64 {
-        // Write new sample
-        self.history[self.write_idx] = x;
-        
-        // Compute convolution
-        let mut sum = 0.0;
+    pub(crate) fn process(&mut self, x: T) -> T {
         let len = self.coeffs.len();
-        for i in 0..len {
-            let hist_idx = (self.write_idx + len - i) % len;
-            sum += self.history[hist_idx] * self.coeffs[i];
-        }
-        
-        // Advance write pointer
+        self.history[self.write_idx] = x;
+        self.history[self.write_idx + len] = x;
+        let window = &self.history[self.write_idx + 1..self.write_idx + 1 + len];
         self.write_idx = (self.write_idx + 1) % len;
-        
-        sum
+        T::dot(window, &self.coeffs)
     }
     
     /// Get the group delay in samples
     fn group_delay(&self) -> usize {
-        self.delay
+        (self.coeffs.len() - 1) / 2
+    }
+    
+    /// Bytes held by the taps and history
+    pub(crate) fn memory_bytes(&self) -> usize {
+        (self.coeffs.capacity() + self.history.capacity()) * std::mem::size_of::<T>()
     }
     
     /// Reset filter state
-    #[allow(dead_code)]
+    pub(crate) fn reset(&mut self) {
+        self.history.fill(T::default());
+        self.write_idx = 0;
+    }
+}
+
+/// A mixer's LO phase for one sample
+#[derive(Debug, Clone, Copy)]
+enum LoPhase {
+    /// Quarter turns, on the trig-free path
+    Quadrant(u8),
+    Radians(f64),
+}
+
+impl LoPhase {
+    fn cos_sin<T: Float>(self) -> (T, T) {
+        match self {
+            LoPhase::Quadrant(quadrant) => {
+                let (c, s) = quadrant_cos_sin(quadrant);
+                (T::from_f64(c), T::from_f64(s))
+            }
+            LoPhase::Radians(phase) => T::cos_sin(phase),
+        }
+    }
+}
+
+/// The baseband section at one precision: mix-down, filters, fading,
+/// the delayed path and mix-up
+struct Baseband<T> {
+    // Linear-phase FIR filters for I and Q channels (tap0)
+    lpf_i_0: FirLowPassFilter<T>,
+    lpf_q_0: FirLowPassFilter<T>,
+    
+    // Linear-phase FIR filters for I and Q channels (tap1 - delayed path)
+    lpf_i_1: FirLowPassFilter<T>,
+    lpf_q_1: FirLowPassFilter<T>,
+    
+    // Delay lines for second tap (I and Q separately)
+    delay_line_i: Vec<T>,
+    delay_line_q: Vec<T>,
+    delay_write_idx: usize,
+    
+    // Mix-down gain of Q: -2 for e^{-jωt}, +2 with the sidebands swapped
+    // (the conjugate, mirroring the spectrum about the carrier)
+    q_gain: T,
+}
+
+impl<T: Float> Baseband<T> {
+    fn new(sample_rate: f64, delay_len: usize) -> Self {
+        // FIR LPF (see LPF_CUTOFF_HZ, LPF_TAPS)
+        Self {
+            lpf_i_0: FirLowPassFilter::new(LPF_CUTOFF_HZ, sample_rate, LPF_TAPS),
+            lpf_q_0: FirLowPassFilter::new(LPF_CUTOFF_HZ, sample_rate, LPF_TAPS),
+            lpf_i_1: FirLowPassFilter::new(LPF_CUTOFF_HZ, sample_rate, LPF_TAPS),
+            lpf_q_1: FirLowPassFilter::new(LPF_CUTOFF_HZ, sample_rate, LPF_TAPS),
+            delay_line_i: vec![T::default(); delay_len],
+            delay_line_q: vec![T::default(); delay_len],
+            delay_write_idx: 0,
+            q_gain: T::from_f64(-2.0),
+        }
+    }
+    
+    /// Bytes held by the filters and the delay line
+    fn memory_bytes(&self) -> usize {
+        let filters = [&self.lpf_i_0, &self.lpf_q_0, &self.lpf_i_1, &self.lpf_q_1];
+        filters.iter().map(|f| f.memory_bytes()).sum::<usize>()
+            + (self.delay_line_i.capacity() + self.delay_line_q.capacity()) * std::mem::size_of::<T>()
+    }
+    
     fn reset(&mut self) {
-        for x in &mut self.history {
-            *x = 0.0;
+        self.delay_line_i.fill(T::default());
+        self.delay_line_q.fill(T::default());
+        self.delay_write_idx = 0;
+        self.lpf_i_0.reset();
+        self.lpf_q_0.reset();
+        self.lpf_i_1.reset();
+        self.lpf_q_1.reset();
+    }
+    
+    /// Take one (conditioned) input sample down with the LO at `down`,
+    /// fade it, and back up at `up`
+    ///
+    /// Returns (faded passband, unfaded direct-path reference).
+    fn process(&mut self, x: f64, down: LoPhase, up: LoPhase, gains: TapGains, split: Option<(f64, f64)>) -> (f64, f64) {
+        let x = T::from_f64(x);
+        let ((h0_i, h0_q), (h1_i, h1_q)) = gains;
+        let (h0_i, h0_q) = (T::from_f64(h0_i), T::from_f64(h0_q));
+        let (h1_i, h1_q) = (T::from_f64(h1_i), T::from_f64(h1_q));
+        let delay_len = self.delay_line_i.len();
+        
+        // === Mix down to baseband ===
+        let (cos_carrier, sin_carrier) = down.cos_sin::<T>();
+        
+        // Multiply by e^{-jωt} = cos(ωt) - j·sin(ωt) to get baseband I/Q
+        // The *2 compensates for mixing loss (we want the baseband component, not half of it)
+        // NOTE: Q uses NEGATIVE sin for proper frequency preservation (not inversion)
+        let two = T::from_f64(2.0);
+        let i_raw = x * cos_carrier * two;
+        let q_raw = x * sin_carrier * self.q_gain;  // Negative for correct e^{-jωt}
+        
+        // Linear-phase FIR filter to remove 2*carrier component, keeping baseband
+        // This introduces a constant group delay
+        let i_bb_0 = self.lpf_i_0.process(i_raw);
+        let q_bb_0 = self.lpf_q_0.process(q_raw);
+        
+        // Also filter for the delayed path
+        let i_bb_1 = self.lpf_i_1.process(i_raw);
+        let q_bb_1 = self.lpf_q_1.process(q_raw);
+        
+        // === Apply fading to tap 0 (direct path) ===
+        // Complex multiply: (i + jq) * (h_i + jh_q) = (i*h_i - q*h_q) + j(i*h_q + q*h_i)
+        let i_faded_0 = i_bb_0 * h0_i - q_bb_0 * h0_q;
+        let q_faded_0 = i_bb_0 * h0_q + q_bb_0 * h0_i;
+        
+        // === Apply fading to tap 1 (delayed path) ===
+        // Read delayed I/Q from delay line
+        let delay_read_idx = (self.delay_write_idx + 1) % delay_len;
+        let i_delayed = self.delay_line_i[delay_read_idx];
+        let q_delayed = self.delay_line_q[delay_read_idx];
+        
+        // Write current baseband I/Q to delay line
+        self.delay_line_i[self.delay_write_idx] = i_bb_1;
+        self.delay_line_q[self.delay_write_idx] = q_bb_1;
+        self.delay_write_idx = (self.delay_write_idx + 1) % delay_len;
+        
+        // Complex multiply for delayed path
+        let i_faded_1 = i_delayed * h1_i - q_delayed * h1_q;
+        let q_faded_1 = i_delayed * h1_q + q_delayed * h1_i;
+        
+        // === Combine taps ===
+        let (i_combined, q_combined) = combine((i_faded_0, q_faded_0), (i_faded_1, q_faded_1), split);
+        
+        // === Mix back up to passband ===
+        // y = I*cos(wt) - Q*sin(wt)
+        let (cos_delayed, sin_delayed) = up.cos_sin::<T>();
+        let y = i_combined * cos_delayed - q_combined * sin_delayed;
+        let reference = i_bb_0 * cos_delayed - q_bb_0 * sin_delayed;
+        (y.to_f64(), reference.to_f64())
+    }
+    
+    /// Fade one complex baseband sample and combine it with the delayed
+    /// path, with no mixing or filtering (see WattersonChannel::process_iq)
+    fn process_iq(&mut self, x: (f64, f64), gains: TapGains, split: Option<(f64, f64)>) -> (f64, f64) {
+        let ((h0_i, h0_q), (h1_i, h1_q)) = gains;
+        let (h0_i, h0_q) = (T::from_f64(h0_i), T::from_f64(h0_q));
+        let (h1_i, h1_q) = (T::from_f64(h1_i), T::from_f64(h1_q));
+        let delay_len = self.delay_line_i.len();
+        
+        // Swapped sidebands are the conjugate at baseband
+        let i = T::from_f64(x.0);
+        let q = T::from_f64(if self.q_gain.to_f64() > 0.0 { -x.1 } else { x.1 });
+        
+        let i_faded_0 = i * h0_i - q * h0_q;
+        let q_faded_0 = i * h0_q + q * h0_i;
+        
+        // The same delay line as the passband path, so the echo carries
+        // across a switch between the two
+        let delay_read_idx = (self.delay_write_idx + 1) % delay_len;
+        let i_delayed = self.delay_line_i[delay_read_idx];
+        let q_delayed = self.delay_line_q[delay_read_idx];
+        self.delay_line_i[self.delay_write_idx] = i;
+        self.delay_line_q[self.delay_write_idx] = q;
+        self.delay_write_idx = (self.delay_write_idx + 1) % delay_len;
+        
+        let i_faded_1 = i_delayed * h1_i - q_delayed * h1_q;
+        let q_faded_1 = i_delayed * h1_q + q_delayed * h1_i;
+        
+        let (i_combined, q_combined) = combine((i_faded_0, q_faded_0), (i_faded_1, q_faded_1), split);
+        (i_combined.to_f64(), q_combined.to_f64())
+    }
+}
+
+/// Sum the faded taps at the amplitudes of `split` (see tap_split), or
+/// pass the direct path through alone on a single path
+fn combine<T: Float>(tap0: (T, T), tap1: (T, T), split: Option<(f64, f64)>) -> (T, T) {
+    match split {
+        // The equal split as one scale of the sum, as it always was
+        Some((a0, a1)) if a0 == a1 => {
+            let scale = T::from_f64(a0);
+            ((tap0.0 + tap1.0) * scale, (tap0.1 + tap1.1) * scale)
         }
-        self.write_idx = 0;
+        Some((a0, a1)) => {
+            let (a0, a1) = (T::from_f64(a0), T::from_f64(a1));
+            (tap0.0 * a0 + tap1.0 * a1, tap0.1 * a0 + tap1.1 * a1)
+        }
+        None => tap0,
+    }
+}
+
+/// The baseband section at the channel's precision
+enum BasebandPath {
+    F64(Baseband<f64>),
+    F32(Baseband<f32>),
+}
+
+impl BasebandPath {
+    fn new(precision: Precision, sample_rate: f64, delay_len: usize) -> Self {
+        match precision {
+            Precision::F64 => BasebandPath::F64(Baseband::new(sample_rate, delay_len)),
+            Precision::F32 => BasebandPath::F32(Baseband::new(sample_rate, delay_len)),
+        }
+    }
+    
+    /// Group delay of the baseband filters, samples
+    fn group_delay(&self) -> usize {
+        match self {
+            BasebandPath::F64(baseband) => baseband.lpf_i_0.group_delay(),
+            BasebandPath::F32(baseband) => baseband.lpf_i_0.group_delay(),
+        }
+    }
+    
+    /// Length of the delayed path's delay line
+    fn delay_len(&self) -> usize {
+        match self {
+            BasebandPath::F64(baseband) => baseband.delay_line_i.len(),
+            BasebandPath::F32(baseband) => baseband.delay_line_i.len(),
+        }
+    }
+    
+    fn memory_bytes(&self) -> usize {
+        match self {
+            BasebandPath::F64(baseband) => baseband.memory_bytes(),
+            BasebandPath::F32(baseband) => baseband.memory_bytes(),
+        }
+    }
+    
+    fn reset(&mut self) {
+        match self {
+            BasebandPath::F64(baseband) => baseband.reset(),
+            BasebandPath::F32(baseband) => baseband.reset(),
+        }
+    }
+    
+    /// Conjugate the baseband from the next sample on (see
+    /// ChannelParams::sideband_inversion)
+    fn set_conjugate(&mut self, conjugate: bool) {
+        let q_gain = if conjugate { 2.0 } else { -2.0 };
+        match self {
+            BasebandPath::F64(baseband) => baseband.q_gain = q_gain,
+            BasebandPath::F32(baseband) => baseband.q_gain = q_gain as f32,
+        }
+    }
+    
+    fn process(&mut self, x: f64, down: LoPhase, up: LoPhase, gains: TapGains, split: Option<(f64, f64)>) -> (f64, f64) {
+        match self {
+            BasebandPath::F64(baseband) => baseband.process(x, down, up, gains, split),
+            BasebandPath::F32(baseband) => baseband.process(x, down, up, gains, split),
+        }
+    }
+    
+    fn process_iq(&mut self, x: (f64, f64), gains: TapGains, split: Option<(f64, f64)>) -> (f64, f64) {
+        match self {
+            BasebandPath::F64(baseband) => baseband.process_iq(x, gains, split),
+            BasebandPath::F32(baseband) => baseband.process_iq(x, gains, split),
+        }
+    }
+}
+
+
+
+/// AWGN power for a given SNR
+/// SNR = signal_power / noise_power
+/// Reference signal: sinusoid with amplitude 0.5 has power = 0.5² / 2 = 0.125
+fn noise_power_for_snr(snr_db: f64) -> f64 {
+    let reference_signal_power = 0.125;
+    reference_signal_power * 10.0_f64.powf(-snr_db / 10.0)
+}
+
+/// The channel seed's RNG after the first `skip` draws
+///
+/// WattersonChannel::new draws the tap0, tap1, noise and output stage
+/// seeds from one RNG, in that order. Starting a separate fading or noise
+/// seed at the same draw means fading_seed (or noise_seed) = s gives the
+/// same fading (or noise) as a channel created with seed s.
+fn seed_stream(seed: u64, skip: usize) -> ChaCha8Rng {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    for _ in 0..skip {
+        let _: u64 = rng.gen();
+    }
+    rng
+}
+
+const NOISE_DRAW: usize = 2;
+const OUTPUT_DRAW: usize = 3;
+
+/// Block size run_until() drains the channel with
+const DRAIN_BLOCK: usize = 4096;
+
+/// How far the start_in_fade search looks: 4096 Doppler periods
+const FADE_SEARCH_STEPS: u64 = 16 * 4096;
+
+/// Time constant of the output power meter, seconds
+const POWER_METER_WINDOW_S: f64 = 0.1;
+
+/// Lowest level ChannelState reports, dB: a power of zero reads as this
+/// rather than -inf, which doesn't cross into Elixir
+const TELEMETRY_FLOOR_DB: f64 = -200.0;
+
+/// `power` in dB, no lower than TELEMETRY_FLOOR_DB
+fn telemetry_db(power: f64) -> f64 {
+    (10.0 * power.log10()).max(TELEMETRY_FLOOR_DB)
+}
+
+/// Running mean square of the channel's output, exponentially weighted
+/// over about POWER_METER_WINDOW_S
+#[derive(Debug, Clone)]
+struct PowerMeter {
+    alpha: f64,
+    power: f64,
+}
+
+impl PowerMeter {
+    fn new(sample_rate: u32) -> Self {
+        Self { alpha: 1.0 / (POWER_METER_WINDOW_S * sample_rate as f64).max(1.0), power: 0.0 }
+    }
+    
+    fn update(&mut self, power: f64) {
+        self.power += self.alpha * (power - self.power);
+    }
+    
+    /// `n` samples all at `power`, in closed form
+    fn settle(&mut self, n: usize, power: f64) {
+        let kept = (1.0 - self.alpha).powf(n as f64);
+        self.power = power + (self.power - power) * kept;
+    }
+}
+
+/// Check the warm-start settings before building a channel
+pub fn validate_warm_start(params: &ChannelParams) -> Result<(), &'static str> {
+    if !(0.0..fading::MAX_TIME).contains(&params.start_at_time_s) {
+        return Err("invalid_warm_start");
+    }
+    match params.start_in_fade_db {
+        Some(depth_db) if !(depth_db.is_finite() && depth_db < 0.0) => Err("invalid_warm_start"),
+        _ => Ok(()),
+    }
+}
+
+/// Check the tap gains before building a channel
+pub fn validate_tap_gains(params: &ChannelParams) -> Result<(), &'static str> {
+    let in_range = |db: f64| db.is_finite() && db.abs() <= MAX_TAP_GAIN_DB;
+    if !(in_range(params.tap0_gain_db) && in_range(params.tap1_gain_db)) {
+        return Err("invalid_tap_gain");
+    }
+    Ok(())
+}
+
+/// Check the frequency offset before building a channel
+pub fn validate_frequency_offset(params: &ChannelParams) -> Result<(), &'static str> {
+    let hz = params.frequency_offset_hz;
+    if !(hz.is_finite() && hz.abs() <= MAX_FREQUENCY_OFFSET_HZ) {
+        return Err("invalid_frequency_offset");
+    }
+    Ok(())
+}
+
+/// Check the noise bandwidth before building a channel: any finite value
+/// will do, those outside (0, Nyquist) meaning the full band
+pub fn validate_noise_bandwidth(params: &ChannelParams) -> Result<(), &'static str> {
+    if !params.noise_bandwidth_hz.is_finite() {
+        return Err("invalid_noise_bandwidth");
     }
+    Ok(())
 }
 
+/// Check the impulsive noise settings before building a channel: a
+/// probability in [0, 1], a power ratio in [0, 80] dB and a burst of 1
+/// sample to a second
+pub fn validate_impulse_noise(params: &ChannelParams) -> Result<(), &'static str> {
+    if !((0.0..=1.0).contains(&params.impulse_probability)
+        && (0.0..=MAX_IMPULSE_POWER_RATIO_DB).contains(&params.impulse_power_ratio_db)
+        && (1..=params.sample_rate).contains(&params.impulse_burst_samples))
+    {
+        return Err("invalid_impulse_noise");
+    }
+    Ok(())
+}
+
+/// Amplitude scale of each tap, None on a single path
+///
+/// The linear gains of tap0_gain_db and tap1_gain_db, scaled so their
+/// powers sum to one. Equal gains are exactly 1/√2 each, the split a
+/// two-path channel has always had.
+pub(crate) fn tap_split(params: &ChannelParams) -> Option<(f64, f64)> {
+    if params.delay_spread_samples == 0 {
+        return None;
+    }
+    if params.tap0_gain_db == params.tap1_gain_db {
+        return Some((std::f64::consts::FRAC_1_SQRT_2, std::f64::consts::FRAC_1_SQRT_2));
+    }
+    let a0 = 10.0_f64.powf(params.tap0_gain_db / 20.0);
+    let a1 = 10.0_f64.powf(params.tap1_gain_db / 20.0);
+    let norm = (a0 * a0 + a1 * a1).sqrt();
+    Some((a0 / norm, a1 / norm))
+}
+
+/// Power of tap1 relative to tap0 in the sum, None on a single path
+/// (exactly 1.0 for the equal split)
+fn tap1_weight(split: Option<(f64, f64)>) -> Option<f64> {
+    split.map(|(a0, a1)| (a1 / a0).powi(2))
+}
 
+/// What WattersonChannel::reset_to_idle() does with the random sequences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RngReset {
+    /// Fading, noise and dither carry on from where they are
+    Preserve,
+    /// Restart every sequence from this seed, as a new channel would
+    Reseed(u64),
+}
+
+/// Where a block given to process_at() starts, relative to the channel's
+/// sample index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Discontinuity {
+    /// Exactly where the last block ended
+    None,
+    /// This many samples after it (dropped input)
+    Gap(u64),
+    /// This many samples before it (input already processed)
+    Overlap(u64),
+}
+
+/// Fading coefficients of the direct and delayed taps for one sample
+pub type TapGains = ((f64, f64), (f64, f64));
 
 /// Watterson two-path channel model with carrier mixing
 pub struct WattersonChannel {
     params: ChannelParams,
+    seed: u64,
     sample_index: u64,
     
     // Two independent fading taps
     tap0: FadingTap,
     tap1: FadingTap,
     
-    // Delay lines for second tap (I and Q separately)
-    delay_line_i: Vec<f64>,
-    delay_line_q: Vec<f64>,
-    delay_write_idx: usize,
-    
     // Carrier NCO
     carrier_phase: f64,
     carrier_phase_inc: f64,
     
-    // Linear-phase FIR filters for I and Q channels (tap0)
-    lpf_i_0: FirLowPassFilter,
-    lpf_q_0: FirLowPassFilter,
+    // Quarter/half-rate carrier: the phase is kept as a count of quarter
+    // turns instead, and mixing needs no trig
+    trivial_lo: Option<TrivialLo>,
+    carrier_quadrant: u8,
     
-    // Linear-phase FIR filters for I and Q channels (tap1 - delayed path)
-    lpf_i_1: FirLowPassFilter,
-    lpf_q_1: FirLowPassFilter,
+    // Frequency hopping: retunes still to come as (sample_index, carrier
+    // Hz), and the last one's sample index and previous phase increment
+    // (None until the first), which the mix-up works from once hopping
+    hops: VecDeque<(u64, f64)>,
+    last_hop: Option<(u64, f64)>,
+    
+    // Baseband filters and the delayed path's delay line
+    baseband: BasebandPath,
     
     // FIR filter group delay for carrier phase compensation
     fir_group_delay: usize,
     
     // AWGN generator
     noise: NoiseGenerator,
+    
+    // Propagation delay ahead of the fading section
+    bulk_delay: BulkDelay,
+    
+    // DC blocker and tilt filter ahead of the mix-down
+    conditioning: InputConditioning,
+    
+    // Sub-sample delay ahead of the receiving sound card
+    fractional_delay: Option<FractionalDelay>,
+    
+    // Receiving sound card: clock drift, soft limiter and quantizer
+    drift: Option<ClockDrift>,
+    output: OutputStage,
+    
+    // Amplitude of each tap in the sum (see tap_split)
+    tap_split: Option<(f64, f64)>,
+    
+    // Frequency offset: the sample index and rotation it counts from
+    // (see frequency_offset)
+    offset_origin: (u64, f64),
+    
+    // Optional deep-fade detector and the taps' long-term mean power
+    fade_alarm: Option<FadeAlarm>,
+    fade_mean_power: f64,
+    
+    // Recent output power, for telemetry
+    output_power: PowerMeter,
+    
+    // Warm-start offset the taps began at
+    start_time_s: f64,
+    
+    // Optional record of the mix-up phase (see phase_log)
+    phase_log: Option<PhaseLog>,
+    
+    // Optional receiver muting around the far end's own transmissions
+    tr_switch: Option<TrSwitch>,
+    
+    // Parameter changes, for reproducibility reports
+    audit: AuditLog,
+    
+    // The group whose weather this channel follows (see group), and the
+    // offsets last sampled from it
+    group: Option<Arc<GroupWeather>>,
+    group_offsets: GroupOffsets,
 }
 
 impl WattersonChannel {
     pub fn new(params: ChannelParams, seed: u64) -> Self {
-        let mut rng = ChaCha8Rng::seed_from_u64(seed);
-        
         // Create two independent fading taps with different seeds
+        let mut fading_rng = seed_stream(params.fading_seed.unwrap_or(seed), 0);
         let tap0 = FadingTap::new(
             params.sample_rate as f64,
             params.doppler_bandwidth_hz,
-            &mut rng,
-        );
+            &mut fading_rng,
+        )
+        .with_precision(params.precision);
         
         let tap1 = FadingTap::new(
             params.sample_rate as f64,
             params.doppler_bandwidth_hz,
-            &mut rng,
-        );
+            &mut fading_rng,
+        )
+        .with_precision(params.precision);
         
-        // Initialize delay lines for tap1 (I and Q)
+        // Delay lines for tap1 (I and Q)
         let delay_samples = params.delay_spread_samples as usize;
         let delay_len = delay_samples.max(1);
-        let delay_line_i = vec![0.0; delay_len];
-        let delay_line_q = vec![0.0; delay_len];
         
         // Carrier NCO setup
         let carrier_phase_inc = 2.0 * PI * params.carrier_freq_hz / params.sample_rate as f64;
         
-        // FIR LPF parameters
-        // Cutoff should be slightly wider than signal bandwidth
-        // ALE uses ~2400 Hz bandwidth, so 2800 Hz cutoff gives some margin
-        let lpf_cutoff = 2800.0;
-        let sample_rate = params.sample_rate as f64;
+        let mut baseband = BasebandPath::new(params.precision, params.sample_rate as f64, delay_len);
+        baseband.set_conjugate(params.sideband_inversion);
         
-        // Use 31 taps for good stopband attenuation while keeping delay reasonable
-        // Group delay = (31-1)/2 = 15 samples ≈ 1.56ms at 9600 Hz
-        let num_taps = 31;
+        // Store FIR group delay for carrier phase compensation
+        let fir_group_delay = baseband.group_delay();
         
-        let lpf_i_0 = FirLowPassFilter::new(lpf_cutoff, sample_rate, num_taps);
-        let lpf_q_0 = FirLowPassFilter::new(lpf_cutoff, sample_rate, num_taps);
-        let lpf_i_1 = FirLowPassFilter::new(lpf_cutoff, sample_rate, num_taps);
-        let lpf_q_1 = FirLowPassFilter::new(lpf_cutoff, sample_rate, num_taps);
+        // Calculate noise power from SNR, in the noise bandwidth
+        let mut noise_rng = seed_stream(params.noise_seed.unwrap_or(seed), NOISE_DRAW);
+        let noise = NoiseGenerator::new(noise_power_for_snr(params.snr_db), &mut noise_rng)
+            .with_bandwidth(params.noise_bandwidth_hz, params.sample_rate as f64)
+            .with_impulses(params.impulse_probability, params.impulse_power_ratio_db, params.impulse_burst_samples);
         
-        // Store FIR group delay for carrier phase compensation
-        let fir_group_delay = lpf_i_0.group_delay();
+        // Dither always follows the channel seed
+        let mut output_rng = seed_stream(seed, OUTPUT_DRAW);
+        let output = OutputStage::new(params.output_bits, params.output_dither, params.clip_knee, &mut output_rng);
         
-        // Calculate noise power from SNR
-        // SNR = signal_power / noise_power
-        // Reference signal: sinusoid with amplitude 0.5 has power = 0.5² / 2 = 0.125
-        let reference_signal_power = 0.125;
-        let noise_power = reference_signal_power * 10.0_f64.powf(-params.snr_db / 10.0);
-        let noise = NoiseGenerator::new(noise_power, &mut rng);
+        let tap_split = tap_split(&params);
+        let fade_mean_power = match tap1_weight(tap_split) {
+            None => tap0.mean_power(),
+            Some(weight) => tap0.mean_power() + weight * tap1.mean_power(),
+        };
         
         Self {
             params: params.clone(),
+            seed,
             sample_index: 0,
             tap0,
             tap1,
-            delay_line_i,
-            delay_line_q,
-            delay_write_idx: 0,
             carrier_phase: 0.0,
             carrier_phase_inc,
-            lpf_i_0,
-            lpf_q_0,
-            lpf_i_1,
-            lpf_q_1,
+            trivial_lo: TrivialLo::detect(params.carrier_freq_hz, params.sample_rate as f64),
+            carrier_quadrant: 0,
+            hops: VecDeque::new(),
+            last_hop: None,
+            baseband,
             fir_group_delay,
             noise,
+            bulk_delay: BulkDelay::new(params.bulk_delay_samples),
+            conditioning: InputConditioning::new(params.sample_rate, params.input_dc_block, params.input_tilt_db),
+            fractional_delay: (params.fractional_delay_samples != 0.0)
+                .then(|| FractionalDelay::new(params.fractional_delay_samples)),
+            drift: (params.sample_rate_offset_ppm != 0.0).then(|| ClockDrift::new(params.sample_rate_offset_ppm)),
+            output,
+            fade_alarm: None,
+            tap_split,
+            offset_origin: (0, 0.0),
+            fade_mean_power,
+            output_power: PowerMeter::new(params.sample_rate),
+            start_time_s: 0.0,
+            phase_log: None,
+            tr_switch: None,
+            audit: AuditLog::new(DEFAULT_AUDIT_CAP).expect("nonzero default cap"),
+            group: None,
+            group_offsets: GroupOffsets::default(),
+        }
+        .warm_started()
+        .audited(seed)
+    }
+    
+    /// Start the audit log with the creation parameters
+    fn audited(mut self, seed: u64) -> Self {
+        let params = self.params.clone();
+        self.audit.record(0, AuditChange::Created { params, seed });
+        self
+    }
+    
+    /// Move both taps to the warm-start instant (see ChannelParams)
+    fn warm_started(mut self) -> Self {
+        let t0 = self.params.start_at_time_s;
+        let offset = match self.params.start_in_fade_db {
+            Some(depth_db) if self.params.doppler_bandwidth_hz != 0.0 => self.samples_to_fade(t0, depth_db),
+            _ => 0,
+        };
+        let t = t0 + offset as f64 / self.params.sample_rate as f64;
+        self.tap0.seek(t);
+        self.tap1.seek(t);
+        self.start_time_s = self.tap0.time();
+        self
+    }
+    
+    /// Samples from `t0` to the first one whose envelope is at or below
+    /// `depth_db`
+    ///
+    /// Steps 1/16 of a Doppler period at a time, then narrows the first
+    /// step that gets there down to the sample, so the channel starts right
+    /// on the edge of the fade. If nothing gets there within
+    /// FADE_SEARCH_STEPS steps, the deepest point seen is used instead.
+    fn samples_to_fade(&self, t0: f64, depth_db: f64) -> u64 {
+        let level = 10f64.powf(depth_db / 10.0);
+        let dt = 1.0 / self.params.sample_rate as f64;
+        let envelope = |n: u64| {
+            let t = t0 + n as f64 * dt;
+            self.relative_power(self.tap0.gain_at(t), self.tap1.gain_at(t))
+        };
+        let step = (self.params.sample_rate as f64 / (16.0 * self.params.doppler_bandwidth_hz.abs())) as u64;
+        let step = step.max(1);
+        
+        let mut deepest = (f64::INFINITY, 0);
+        let mut prev = 0;
+        for k in 0..=FADE_SEARCH_STEPS {
+            let n = k * step;
+            let power = envelope(n);
+            if power <= level {
+                // The crossing is in (prev, n]
+                let (mut lo, mut hi) = (prev, n);
+                while hi - lo > 1 {
+                    let mid = lo + (hi - lo) / 2;
+                    if envelope(mid) <= level {
+                        hi = mid;
+                    } else {
+                        lo = mid;
+                    }
+                }
+                return hi;
+            }
+            if power < deepest.0 {
+                deepest = (power, n);
+            }
+            prev = n;
         }
+        deepest.1
     }
     
     /// Process a block of samples through the channel
     /// Uses carrier mixing to properly apply complex fading to real audio
     pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
-        let mut output = Vec::with_capacity(input.len());
-        let delay_len = self.delay_line_i.len();
-        
-        for &sample in input {
-            let x = sample as f64;
-            
-            // === Mix down to baseband ===
-            let cos_carrier = self.carrier_phase.cos();
-            let sin_carrier = self.carrier_phase.sin();
-            
-            // Multiply by e^{-jωt} = cos(ωt) - j·sin(ωt) to get baseband I/Q
-            // The *2 compensates for mixing loss (we want the baseband component, not half of it)
-            // NOTE: Q uses NEGATIVE sin for proper frequency preservation (not inversion)
-            let i_raw = x * cos_carrier * 2.0;
-            let q_raw = -x * sin_carrier * 2.0;  // Negative for correct e^{-jωt}
-            
-            // Linear-phase FIR filter to remove 2*carrier component, keeping baseband
-            // This introduces a constant group delay
-            let i_bb_0 = self.lpf_i_0.process(i_raw);
-            let q_bb_0 = self.lpf_q_0.process(q_raw);
-            
-            // Also filter for the delayed path
-            let i_bb_1 = self.lpf_i_1.process(i_raw);
-            let q_bb_1 = self.lpf_q_1.process(q_raw);
-            
-            // === Apply fading to tap 0 (direct path) ===
-            let (h0_i, h0_q) = self.tap0.next_sample_complex();
-            let h0_i = h0_i as f64;
-            let h0_q = h0_q as f64;
-            
-            // Complex multiply: (i + jq) * (h_i + jh_q) = (i*h_i - q*h_q) + j(i*h_q + q*h_i)
-            let i_faded_0 = i_bb_0 * h0_i - q_bb_0 * h0_q;
-            let q_faded_0 = i_bb_0 * h0_q + q_bb_0 * h0_i;
-            
-            // === Apply fading to tap 1 (delayed path) ===
-            let (h1_i, h1_q) = self.tap1.next_sample_complex();
-            let h1_i = h1_i as f64;
-            let h1_q = h1_q as f64;
-            
-            // Read delayed I/Q from delay line
-            let delay_read_idx = (self.delay_write_idx + 1) % delay_len;
-            let i_delayed = self.delay_line_i[delay_read_idx];
-            let q_delayed = self.delay_line_q[delay_read_idx];
-            
-            // Write current baseband I/Q to delay line
-            self.delay_line_i[self.delay_write_idx] = i_bb_1;
-            self.delay_line_q[self.delay_write_idx] = q_bb_1;
-            self.delay_write_idx = (self.delay_write_idx + 1) % delay_len;
-            
-            // Complex multiply for delayed path
-            let i_faded_1 = i_delayed * h1_i - q_delayed * h1_q;
-            let q_faded_1 = i_delayed * h1_q + q_delayed * h1_i;
-            
-            // === Combine taps ===
-            let (i_combined, q_combined) = if self.params.delay_spread_samples == 0 {
-                // Single-path channel - only tap0, no scaling needed
-                (i_faded_0, q_faded_0)
-            } else {
-                // Two-path channel - equal power split
-                // Each tap contributes 1/sqrt(2) to maintain unit average power
-                let scale = std::f64::consts::FRAC_1_SQRT_2;
-                ((i_faded_0 + i_faded_1) * scale, (q_faded_0 + q_faded_1) * scale)
-            };
-            
-            // === Mix back up to passband ===
-            // Compute DELAYED carrier phase to compensate for FIR filter group delay
-            // The baseband I/Q at this instant corresponds to input from (group_delay) samples ago
-            let delay_samples = self.fir_group_delay + 1;
-            let phase_delay = delay_samples as f64 * self.carrier_phase_inc;
-            let delayed_phase = self.carrier_phase - phase_delay;
-            let cos_delayed = delayed_phase.cos();
-            let sin_delayed = delayed_phase.sin();
-            
-            // y = I*cos(wt) - Q*sin(wt)
-            let y = i_combined * cos_delayed - q_combined * sin_delayed;
-            
-            // Advance carrier phase
-            self.carrier_phase += self.carrier_phase_inc;
-            if self.carrier_phase > 2.0 * PI {
-                self.carrier_phase -= 2.0 * PI;
-            }
-            
-            // Add AWGN
-            let noisy = y + self.noise.next_sample();
-            
-            output.push(noisy as f32);
-            self.sample_index += 1;
+        let input: Vec<f64> = input.iter().map(|&x| x as f64).collect();
+        self.process_f64(&input).into_iter().map(|y| y as f32).collect()
+    }
+
+    /// Process a block the caller says starts at sample index `start`
+    ///
+    /// A block starting later than the channel's sample index is preceded
+    /// by run_until(start), so the channel moves on through the missing
+    /// input as it would through silence (the output for the gap is
+    /// dropped) rather than joining the two blocks. A block starting
+    /// earlier has the samples already processed cut from its front, so
+    /// the output is shorter than the input (empty if the whole block is
+    /// old). A block starting exactly there is process().
+    pub fn process_at(&mut self, start: u64, input: &[f32]) -> (Vec<f32>, Discontinuity) {
+        let now = self.sample_index;
+        if start < now {
+            let skip = (now - start).min(input.len() as u64) as usize;
+            return (self.process(&input[skip..]), Discontinuity::Overlap(now - start));
         }
         
-        output
+        let discontinuity = if start > now {
+            self.run_until(start).expect("start is after the sample index");
+            Discontinuity::Gap(start - now)
+        } else {
+            Discontinuity::None
+        };
+        (self.process(input), discontinuity)
     }
 
-    /// Advance channel state without processing samples
-    /// Used for time synchronization
-    pub fn advance(&mut self, num_samples: usize) {
-        for _ in 0..num_samples {
-            // Advance fading taps
-            self.tap0.next_sample_complex();
-            self.tap1.next_sample_complex();
-            
-            // Advance carrier phase
-            self.carrier_phase += self.carrier_phase_inc;
-            if self.carrier_phase > 2.0 * PI {
-                self.carrier_phase -= 2.0 * PI;
-            }
-            
-            // Advance noise generator
-            self.noise.next_sample();
-            self.sample_index += 1;
+    /// Process a block at full f64 precision (no f32 quantization at I/O)
+    ///
+    /// The output is as long as the input unless the receiver's clock
+    /// drifts (see drift), when it has the samples the receiving sound
+    /// card would take in the same time.
+    pub fn process_f64(&mut self, input: &[f64]) -> Vec<f64> {
+        self.follow_group();
+        if self.params.bypass {
+            self.sample_index += input.len() as u64;
+            self.measure(input);
+            return input.to_vec();
         }
+        let analog = if self.bulk_delay.is_bypassed() {
+            input.iter().map(|&x| self.process_sample(x).0).collect()
+        } else {
+            let delayed = self.bulk_delay.process(input);
+            delayed.into_iter().map(|x| self.process_sample(x).0).collect()
+        };
+        self.digitize(analog)
     }
-    
-    /// Get current channel state for telemetry
-    pub fn get_state(&self) -> ChannelState {
-        ChannelState {
-            sample_index: self.sample_index,
-            tap0_phase: self.tap0.get_phase(),
+
+    /// Process a block, also returning a time-aligned clean reference
+    ///
+    /// The reference is the direct path with its fading coefficient held
+    /// at (1, 0) and no noise: same mix-down, LPF, mix-up and bulk delay as
+    /// the impaired output, computed in the same pass so the two can be
+    /// subtracted sample for sample.
+    pub fn process_with_reference(&mut self, input: &[f32]) -> (Vec<f32>, Vec<f32>) {
+        let input: Vec<f64> = input.iter().map(|&x| x as f64).collect();
+        let (impaired, reference) = self.process_f64_with_reference(&input);
+        (
+            impaired.into_iter().map(|y| y as f32).collect(),
+            reference.into_iter().map(|y| y as f32).collect(),
+        )
+    }
+
+    /// f64 variant of process_with_reference()
+    pub fn process_f64_with_reference(&mut self, input: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        self.follow_group();
+        if self.params.bypass {
+            self.sample_index += input.len() as u64;
+            self.measure(input);
+            return (input.to_vec(), input.to_vec());
+        }
+        let (analog, reference): (Vec<f64>, Vec<f64>) = if self.bulk_delay.is_bypassed() {
+            input.iter().map(|&x| self.process_sample(x)).unzip()
+        } else {
+            let delayed = self.bulk_delay.process(input);
+            delayed.into_iter().map(|x| self.process_sample(x)).unzip()
+        };
+        // The reference is delayed and goes onto the receiver's clock
+        // alongside
+        let reference = match &self.fractional_delay {
+            Some(delay) => delay.clone().process(&reference),
+            None => reference,
+        };
+        let reference = match &self.drift {
+            Some(drift) => drift.clone().process(&reference),
+            None => reference,
+        };
+        (self.digitize(analog), reference)
+    }
+
+    /// Process a block of complex baseband samples, (I, Q) per sample
+    ///
+    /// The fading, echo delay line, AWGN and TR switch are applied
+    /// directly to the complex envelope: no carrier mixing and no
+    /// baseband filters, so none of their group delay either. The fading
+    /// taps, delay line, noise sequence and sample index are the ones
+    /// process() uses, so a channel can be driven through either from
+    /// block to block; a switch moves the timing by the filters' group
+    /// delay. The carrier NCO still steps (and hops), staying where
+    /// process() expects it.
+    ///
+    /// The noise is complex at the SNR the passband path sees: the
+    /// reference sinusoid of power 0.125 is a complex envelope of power
+    /// 0.25, and each of I and Q takes a draw of the passband's noise
+    /// power. It stays white whatever noise_bandwidth_hz is, that band
+    /// being the passband's, but shaping raises its power by Nyquist /
+    /// noise_bandwidth_hz, as it does the passband noise's density inside
+    /// the band (see noise); an impulse lands on I and Q together.
+    /// The stages that work on the real sound-card signal (bulk delay,
+    /// input conditioning, fractional delay, clock drift and the output
+    /// stage) have no complex equivalent here; a channel using any of
+    /// them is refused with "unsupported_for_iq".
+    pub fn process_iq(&mut self, input: &[(f64, f64)]) -> Result<Vec<(f64, f64)>, &'static str> {
+        self.follow_group();
+        if self.params.bypass {
+            self.sample_index += input.len() as u64;
+            for &(i, q) in input {
+                self.output_power.update((i * i + q * q) / 2.0);
+            }
+            return Ok(input.to_vec());
+        }
+        if !self.bulk_delay.is_bypassed()
+            || !self.conditioning.is_bypassed()
+            || self.fractional_delay.is_some()
+            || self.drift.is_some()
+            || !self.output.is_bypassed()
+        {
+            return Err("unsupported_for_iq");
+        }
+        let split = self.tap_split;
+        let start = self.sample_index;
+        let output = input
+            .iter()
+            .map(|&x| {
+                let gains = self.next_gains();
+                self.track_fade(gains.0, gains.1);
+                let gains = self.frequency_offset(gains);
+                let (i, q) = self.baseband.process_iq(x, gains, split);
+                let (noise_i, noise_q) = self.noise.next_white_pair();
+                let (mut i, mut q) = (i + noise_i, q + noise_q);
+                if let Some(tr) = &mut self.tr_switch {
+                    let gain = tr.gain(self.sample_index);
+                    i *= gain;
+                    q *= gain;
+                }
+                // Half the envelope's power, as the passband it stands for
+                self.output_power.update((i * i + q * q) / 2.0);
+                self.sample_index += 1;
+                (i, q)
+            })
+            .collect();
+        self.advance_carrier_from(start, input.len());
+        Ok(output)
+    }
+
+    /// Draw the next `n` samples of this channel's own fading, as
+    /// process() would use it (none in bypass, which doesn't fade)
+    ///
+    /// A correlated set (see correlated) mixes these across its members
+    /// and hands them back through process_f64_with_gains().
+    pub(crate) fn next_tap_gains(&mut self, n: usize) -> Vec<TapGains> {
+        if self.params.bypass {
+            return vec![((1.0, 0.0), (1.0, 0.0)); n];
+        }
+        (0..n).map(|_| self.next_gains()).collect()
+    }
+
+    /// process_f64() with the fading coefficients supplied, one per
+    /// input sample; the channel's own taps don't move
+    pub(crate) fn process_f64_with_gains(&mut self, input: &[f64], gains: &[TapGains]) -> Vec<f64> {
+        assert_eq!(input.len(), gains.len(), "one set of gains per sample");
+        if self.params.bypass {
+            self.sample_index += input.len() as u64;
+            self.measure(input);
+            return input.to_vec();
+        }
+        let analog = if self.bulk_delay.is_bypassed() {
+            input.iter().zip(gains).map(|(&x, &g)| self.process_sample_with(x, g).0).collect()
+        } else {
+            let delayed = self.bulk_delay.process(input);
+            delayed.into_iter().zip(gains).map(|(x, &g)| self.process_sample_with(x, g).0).collect()
+        };
+        self.digitize(analog)
+    }
+
+    /// Long-term mean of the combined tap power the fade alarm measures
+    /// against
+    pub(crate) fn fade_mean_power(&self) -> f64 {
+        self.fade_mean_power
+    }
+
+    pub(crate) fn set_fade_mean_power(&mut self, mean_power: f64) {
+        self.fade_mean_power = mean_power;
+    }
+
+    /// Run one sample through mix-down, fading, delay, mix-up and AWGN
+    ///
+    /// Returns (impaired output, unfaded noiseless direct-path reference),
+    /// both still to be digitized.
+    fn process_sample(&mut self, x: f64) -> (f64, f64) {
+        let gains = self.next_gains();
+        self.process_sample_with(x, gains)
+    }
+    
+    /// Both taps' next fading coefficients
+    fn next_gains(&mut self) -> TapGains {
+        let (h0_i, h0_q) = self.tap0.next_sample_complex();
+        let (h1_i, h1_q) = self.tap1.next_sample_complex();
+        ((h0_i as f64, h0_q as f64), (h1_i as f64, h1_q as f64))
+    }
+    
+    /// process_sample() with the fading coefficients supplied
+    fn process_sample_with(&mut self, x: f64, gains: TapGains) -> (f64, f64) {
+        while let Some(&(_, hz)) = self.hops.front().filter(|&&(at, _)| at <= self.sample_index) {
+            self.hops.pop_front();
+            self.retune(self.sample_index, hz);
+        }
+
+        let x = self.conditioning.process(x);
+        self.track_fade(gains.0, gains.1);
+        let gains = self.frequency_offset(gains);
+        
+        let down = match self.trivial_lo {
+            Some(_) => LoPhase::Quadrant(self.carrier_quadrant),
+            None => LoPhase::Radians(self.carrier_phase),
+        };
+        let up = self.mix_up_phase();
+        let (y, reference) = self.baseband.process(x, down, up, gains, self.tap_split);
+        
+        // Advance carrier phase
+        self.advance_carrier(1);
+        
+        // Add AWGN; the receiving sound card digitizes it later
+        let noisy = self.add_noise(y);
+        
+        self.sample_index += 1;
+        (noisy, reference)
+    }
+    
+    /// Rotate both taps' coefficients by the frequency offset's phase at
+    /// the current sample
+    ///
+    /// The taps are summed linearly, so this is the combined baseband I/Q
+    /// turning at frequency_offset_hz ahead of the mix-up: the whole
+    /// output, echo and all, moves up by the offset (down if negative),
+    /// while the fading and the delayed path see no difference. The
+    /// phase is worked out from the sample index rather than stepped, so
+    /// advance() and run_until() carry it along however far they skip.
+    /// The clean reference stays unshifted.
+    fn frequency_offset(&self, gains: TapGains) -> TapGains {
+        let hz = self.params.frequency_offset_hz;
+        if hz == 0.0 && self.offset_origin.1 == 0.0 {
+            return gains;
+        }
+        let (origin, origin_phase) = self.offset_origin;
+        let (sin, cos) = self.offset_phase_at(origin, origin_phase, hz).sin_cos();
+        let rotate = |(i, q): (f64, f64)| (i * cos - q * sin, i * sin + q * cos);
+        (rotate(gains.0), rotate(gains.1))
+    }
+    
+    /// The frequency offset's rotation at the current sample, counting
+    /// `hz` on from `phase` at sample `origin`
+    fn offset_phase_at(&self, origin: u64, phase: f64, hz: f64) -> f64 {
+        // Whole cycles dropped before scaling, so the phase keeps its
+        // precision however long the channel runs
+        let cycles = hz * (self.sample_index - origin) as f64 / self.params.sample_rate as f64;
+        phase + 2.0 * PI * cycles.fract()
+    }
+    
+    /// The mix-up's LO phase for the current sample, logged if a phase
+    /// log wants it
+    ///
+    /// The carrier phase taken back over the FIR group delay: the baseband
+    /// I/Q at this instant corresponds to input from that many samples ago.
+    fn mix_up_phase(&mut self) -> LoPhase {
+        let delay_samples = self.fir_group_delay + 1;
+        match self.trivial_lo {
+            Some(lo) => {
+                let delay_quadrants = (delay_samples * lo.step() as usize % 4) as u8;
+                let quadrant = self.carrier_quadrant.wrapping_sub(delay_quadrants);
+                self.log_phase(phase_log::quadrant_phase(quadrant), phase_log::quadrant_phase(lo.step()));
+                LoPhase::Quadrant(quadrant)
+            }
+            None => {
+                // Once hopping, the mixer phase is taken back over the group
+                // delay, part of which may have run at the old carrier. The
+                // extra sample stays at the configured carrier's step, so
+                // the fixed rotation the channel applies doesn't jump at a
+                // hop.
+                let (phase_delay, step) = match self.last_hop {
+                    Some((at, old_inc)) => {
+                        let group_delay = self.fir_group_delay as f64;
+                        let since = ((self.sample_index - at) as f64).min(group_delay);
+                        // Still inside the group delay of the hop, the
+                        // delayed phase moves at the old carrier's step
+                        let step = if since < group_delay { old_inc } else { self.carrier_phase_inc };
+                        let delay = since * self.carrier_phase_inc
+                            + (group_delay - since) * old_inc
+                            + self.carrier_phase_inc_configured();
+                        (delay, step)
+                    }
+                    None => (delay_samples as f64 * self.carrier_phase_inc, self.carrier_phase_inc),
+                };
+                let delayed_phase = self.carrier_phase - phase_delay;
+                self.log_phase(delayed_phase, step);
+                LoPhase::Radians(delayed_phase)
+            }
+        }
+    }
+    
+    /// The noise path: AWGN, then the TR switch's mute
+    ///
+    /// Runs a sample at a time with all of its state in the struct, none
+    /// in per-call locals, so the noise doesn't depend on how the input is
+    /// split into blocks (see properties::test_output_independent_of_block_size).
+    /// A noise stage that filters or measures power keeps its filter and
+    /// running measurement next to the generator's RNG.
+    fn add_noise(&mut self, y: f64) -> f64 {
+        let mut noisy = y + self.noise.next_sample();
+        if let Some(tr) = &mut self.tr_switch {
+            noisy *= tr.gain(self.sample_index);
+        }
+        noisy
+    }
+    
+    /// Record the current sample's mix-up phase if a phase log wants it
+    fn log_phase(&mut self, phase: f64, step: f64) {
+        let sample_index = self.sample_index;
+        if let Some(log) = self.phase_log.as_mut().filter(|log| log.wants(sample_index)) {
+            log.record(PhaseEntry { sample_index, phase, step });
+        }
+    }
+    
+    /// Delay a block by the fractional delay (if any) and sample it onto
+    /// the receiver's clock (if it drifts), then limit and quantize as the
+    /// receiving sound card would
+    fn digitize(&mut self, mut analog: Vec<f64>) -> Vec<f64> {
+        if let Some(delay) = &mut self.fractional_delay {
+            analog = delay.process(&analog);
+        }
+        if let Some(drift) = &mut self.drift {
+            analog = drift.process(&analog);
+        }
+        for x in &mut analog {
+            *x = self.output.process(*x);
+        }
+        self.measure(&analog);
+        analog
+    }
+    
+    /// Feed a block of output to the power meter
+    fn measure(&mut self, output: &[f64]) {
+        for &y in output {
+            self.output_power.update(y * y);
+        }
+    }
+
+    /// Retune the carrier NCO to `hz` from sample `at` on, keeping its
+    /// phase
+    ///
+    /// A quarter-rate carrier leaves the trig-free path for good: the
+    /// phase it hands over is then general.
+    fn retune(&mut self, at: u64, hz: f64) {
+        if self.trivial_lo.take().is_some() {
+            self.carrier_phase = self.carrier_quadrant as f64 * PI / 2.0;
+        }
+        self.last_hop = Some((at, self.carrier_phase_inc));
+        self.carrier_phase_inc = 2.0 * PI * hz / self.params.sample_rate as f64;
+    }
+    
+    /// Phase step of the carrier the channel was created with
+    fn carrier_phase_inc_configured(&self) -> f64 {
+        2.0 * PI * self.params.carrier_freq_hz / self.params.sample_rate as f64
+    }
+    
+    /// advance_carrier() over samples from `start`, retuning at any hops
+    /// among them
+    fn advance_carrier_from(&mut self, start: u64, num_samples: usize) {
+        let end = start + num_samples as u64;
+        let mut at = start;
+        while let Some(&(hop, hz)) = self.hops.front().filter(|&&(hop, _)| hop < end) {
+            self.hops.pop_front();
+            let hop = hop.max(at);
+            self.advance_carrier((hop - at) as usize);
+            self.retune(hop, hz);
+            at = hop;
+        }
+        self.advance_carrier((end - at) as usize);
+    }
+    
+    /// Step the carrier NCO `num_samples` samples
+    fn advance_carrier(&mut self, num_samples: usize) {
+        if let Some(lo) = self.trivial_lo {
+            let quadrants = (num_samples % 4) * lo.step() as usize;
+            self.carrier_quadrant = ((self.carrier_quadrant as usize + quadrants) % 4) as u8;
+            return;
+        }
+        for _ in 0..num_samples {
+            self.carrier_phase += self.carrier_phase_inc;
+            if self.carrier_phase > 2.0 * PI {
+                self.carrier_phase -= 2.0 * PI;
+            }
+        }
+    }
+    
+    /// Advance channel state without processing samples
+    /// Used for time synchronization
+    ///
+    /// Leaves the channel as process() on that many zeros would. The
+    /// baseband filters, echo delay line, fractional delay and clock drift
+    /// interpolator only remember their last few samples between them, so just those
+    /// are processed (output dropped, not phase logged); the input
+    /// conditioning filters decay in closed form. The rest never
+    /// evaluates the fading unless a fade alarm is watching it, and skips
+    /// the noise and dither sequences in place, so a long advance costs
+    /// little more than counting the samples.
+    pub fn advance(&mut self, num_samples: usize) {
+        self.follow_group();
+        if self.params.bypass {
+            self.sample_index += num_samples as u64;
+            self.output_power.settle(num_samples, 0.0);
+            return;
+        }
+        let memory = LPF_TAPS
+            + self.baseband.delay_len()
+            + if self.fractional_delay.is_some() { fractional_delay::TAPS } else { 0 }
+            + if self.drift.is_some() { drift::TAPS } else { 0 };
+        let flushed = num_samples.min(memory);
+        self.skip(num_samples - flushed);
+        // The flush runs after the skip, so its tail would be the last
+        // thing the power meter saw; keep the meter out of it
+        let phase_log = self.phase_log.take();
+        let output_power = self.output_power.clone();
+        self.process_f64(&vec![0.0; flushed]);
+        self.phase_log = phase_log;
+        self.output_power = output_power;
+        self.output_power.settle(flushed, self.noise.power());
+    }
+    
+    /// advance() without running anything through the baseband filters
+    /// or echo delay line
+    fn skip(&mut self, num_samples: usize) {
+        if self.bulk_delay.is_bypassed() {
+            self.conditioning.skip(num_samples);
+        } else if self.conditioning.is_bypassed() {
+            self.bulk_delay.advance(num_samples);
+        } else {
+            // The conditioning filters never forget, so they have to see
+            // what the bulk delay still had in flight
+            let in_flight = self.bulk_drain_samples().min(num_samples);
+            for x in self.bulk_delay.process(&vec![0.0; in_flight]) {
+                self.conditioning.process(x);
+            }
+            self.bulk_delay.advance(num_samples - in_flight);
+            self.conditioning.skip(num_samples - in_flight);
+        }
+        
+        let start = self.sample_index;
+        let end = start + num_samples as u64;
+        if self.fade_alarm.is_some() {
+            // The alarm needs every sample's envelope
+            for _ in 0..num_samples {
+                let (h0, h1) = self.next_gains();
+                self.track_fade(h0, h1);
+                self.sample_index += 1;
+            }
+        } else {
+            self.tap0.skip(num_samples);
+            self.tap1.skip(num_samples);
+        }
+        self.sample_index = end;
+        
+        self.advance_carrier_from(start, num_samples);
+        self.noise.skip(num_samples);
+        // The silence skipped carries only the noise to the output
+        self.output_power.settle(num_samples, self.noise.power());
+        let outputs = match &mut self.drift {
+            Some(drift) => drift.advance(num_samples),
+            None => num_samples,
+        };
+        self.output.advance(outputs);
+    }
+    
+    /// Simulation time: samples processed or advanced so far, and that in
+    /// seconds at the channel's sample rate
+    ///
+    /// Starts at zero whatever the warm start, which only moves the fading
+    /// realization (see ChannelState::start_time_s).
+    pub fn time(&self) -> (u64, f64) {
+        (self.sample_index, self.sample_index as f64 / self.params.sample_rate as f64)
+    }
+    
+    /// Run silence through the channel until its sample index reaches
+    /// `target`
+    ///
+    /// The channel ends up as if process() had been given that many zeros
+    /// and the output dropped: anything still in flight drains out, fade
+    /// crossings are reported as usual. Only the drain is actually
+    /// processed; the rest is skipped with advance(), so reaching a target
+    /// minutes ahead costs milliseconds. Returns the samples run; a target
+    /// already passed is "target_in_past".
+    pub fn run_until(&mut self, target: u64) -> Result<u64, &'static str> {
+        if target < self.sample_index {
+            return Err("target_in_past");
+        }
+        let remaining = target - self.sample_index;
+        let drain = (self.drain_samples() as u64).min(remaining);
+        let silence = vec![0.0; DRAIN_BLOCK];
+        let mut left = drain as usize;
+        while left > 0 {
+            let n = left.min(DRAIN_BLOCK);
+            self.process_f64(&silence[..n]);
+            left -= n;
+        }
+        self.advance((remaining - drain) as usize);
+        Ok(remaining)
+    }
+    
+    /// Output samples over which a change in the input is smeared by the
+    /// filters and delay lines: zero in bypass
+    ///
+    /// A new or reset channel holds silence, which is also their steady
+    /// state on silence, so nothing stale reaches its output; but the
+    /// first transient_len outputs after the input starts still hold the
+    /// run-up (the latency, then the filters filling), and the first that
+    /// many after a burst ends hold its tail. A block shorter than this
+    /// is mostly edge: its level is only right once a signal has been
+    /// going for transient_len samples, whatever came before. Under clock
+    /// drift the count is of input samples.
+    pub fn transient_len(&self) -> usize {
+        if self.params.bypass {
+            return 0;
+        }
+        self.drain_samples()
+    }
+    
+    /// Run transient_len() samples of silence (or `num_samples`) through,
+    /// dropping the output, so the next block starts from an idle channel
+    /// rather than the tail of whatever went before
+    ///
+    /// run_until() that many samples on: the sample index, fading and
+    /// noise move on and fade crossings are reported as usual. A new or
+    /// reset channel is idle already, so priming it changes nothing but
+    /// the time; it can't take the run-up out of the next block either
+    /// (see transient_len()). Returns the samples run.
+    pub fn prime(&mut self, num_samples: Option<usize>) -> u64 {
+        let num_samples = num_samples.unwrap_or_else(|| self.transient_len()) as u64;
+        self.run_until(self.sample_index + num_samples).expect("target is ahead")
+    }
+    
+    /// Samples of silence after which nothing already fed in can still
+    /// reach the output
+    ///
+    /// The bulk delay, allowing for it to slew further out meanwhile, then
+    /// the delayed path, the baseband filters, the fractional delay and the
+    /// drift resampler.
+    fn drain_samples(&self) -> usize {
+        let fractional = if self.fractional_delay.is_some() { fractional_delay::TAPS } else { 0 };
+        let drift = if self.drift.is_some() { 2 * drift::HALF_TAPS } else { 0 };
+        self.bulk_drain_samples() + self.baseband.delay_len() + 2 * self.fir_group_delay + fractional + drift + 2
+    }
+    
+    /// Samples of silence after which the bulk delay has nothing left in
+    /// flight, allowing for it to slew further out meanwhile
+    fn bulk_drain_samples(&self) -> usize {
+        let bulk = self.bulk_delay.current_delay().max(self.bulk_delay.target_delay() as f64);
+        (bulk / (1.0 - bulk_delay::SLEW_PER_SAMPLE)).ceil() as usize + 2
+    }
+    
+    /// Apply new parameters to a live channel
+    ///
+    /// Only snr_db, bulk_delay_samples, the input conditioning, the
+    /// sideband inversion, the frequency offset and the output stage
+    /// settings can change in place. The noise level, inversion and output
+    /// stage switch immediately, as does the conditioning (from empty
+    /// filters, if it changed); the frequency offset changes without a
+    /// phase jump, and the bulk delay slews to its new value
+    /// (see bulk_delay). Anything else needs a new channel.
+    ///
+    /// fading_seed, noise_seed and the warm start only apply at creation
+    /// and are kept as they are (see reseed_noise).
+    ///
+    /// bypass can also be toggled. Either way the in-flight signal is
+    /// dropped as by reset_to_idle(RngReset::Preserve), so leaving bypass
+    /// starts from an idle channel rather than replaying what was queued
+    /// before it was entered.
+    pub fn update_params(&mut self, params: &ChannelParams) -> Result<(), &'static str> {
+        if params.sample_rate != self.params.sample_rate
+            || params.delay_spread_samples != self.params.delay_spread_samples
+            || params.doppler_bandwidth_hz != self.params.doppler_bandwidth_hz
+            || params.carrier_freq_hz != self.params.carrier_freq_hz
+            || params.sample_rate_offset_ppm != self.params.sample_rate_offset_ppm
+            || params.fractional_delay_samples != self.params.fractional_delay_samples
+            || params.tap0_gain_db != self.params.tap0_gain_db
+            || params.tap1_gain_db != self.params.tap1_gain_db
+            || params.precision != self.params.precision
+            || params.noise_bandwidth_hz != self.params.noise_bandwidth_hz
+            || params.impulse_probability != self.params.impulse_probability
+            || params.impulse_power_ratio_db != self.params.impulse_power_ratio_db
+            || params.impulse_burst_samples != self.params.impulse_burst_samples
+        {
+            return Err("immutable_param_changed");
+        }
+        
+        self.noise.set_noise_power(noise_power_for_snr(params.snr_db + self.group_offsets.snr_db));
+        self.bulk_delay.set_target(params.bulk_delay_samples);
+        self.output.configure(params.output_bits, params.output_dither, params.clip_knee);
+        self.baseband.set_conjugate(params.sideband_inversion);
+        if params.frequency_offset_hz != self.params.frequency_offset_hz {
+            let (origin, phase) = self.offset_origin;
+            let phase = self.offset_phase_at(origin, phase, self.params.frequency_offset_hz);
+            self.offset_origin = (self.sample_index, phase % (2.0 * PI));
+        }
+        if params.input_dc_block != self.params.input_dc_block || params.input_tilt_db != self.params.input_tilt_db {
+            self.conditioning = InputConditioning::new(params.sample_rate, params.input_dc_block, params.input_tilt_db);
+        }
+        let bypass_changed = params.bypass != self.params.bypass;
+        let new = ChannelParams {
+            fading_seed: self.params.fading_seed,
+            noise_seed: self.params.noise_seed,
+            start_at_time_s: self.params.start_at_time_s,
+            start_in_fade_db: self.params.start_in_fade_db,
+            ..params.clone()
+        };
+        let old = std::mem::replace(&mut self.params, new.clone());
+        self.audit.record(self.sample_index, AuditChange::Updated { old, new });
+        if bypass_changed {
+            self.reset_to_idle(RngReset::Preserve);
+        }
+        Ok(())
+    }
+    
+    /// Restart the AWGN from `noise_seed`, leaving fading, filters, delays
+    /// and dither exactly where they are
+    ///
+    /// The noise that follows is what a channel created with noise_seed
+    /// (or seed) = `noise_seed` produces from its first sample.
+    pub fn reseed_noise(&mut self, noise_seed: u64) {
+        self.noise.reseed(&mut seed_stream(noise_seed, NOISE_DRAW));
+        let old = self.params.noise_seed.replace(noise_seed);
+        self.audit.record(self.sample_index, AuditChange::NoiseReseeded { old, new: noise_seed });
+    }
+    
+    /// Hop the carrier: from input sample `at_sample` of each (at_sample,
+    /// carrier_hz) on, the channel mixes at that carrier, for a transmitter
+    /// hopping on the same schedule
+    ///
+    /// Replaces any hops still pending. The mixer retunes when the sample
+    /// reaches it, after the bulk delay (its target, if slewing). The NCO
+    /// keeps its phase and the mix-up follows the carrier each sample was
+    /// mixed down with, so the direct path crosses a hop without a glitch;
+    /// the fading and the delayed path's line carry on as the same physical
+    /// path, the echo of the last samples before a hop coming out on the
+    /// new carrier. at_sample must strictly increase from the current
+    /// sample index, and the carriers lie strictly between 0 and Nyquist.
+    /// reset_to_idle() keeps the carrier and pending hops, unless it
+    /// reseeds: a new channel starts on carrier_freq_hz with none.
+    pub fn set_hop_schedule(&mut self, hops: &[(u64, f64)]) -> Result<(), &'static str> {
+        let nyquist = self.params.sample_rate as f64 / 2.0;
+        let increasing = hops.windows(2).all(|w| w[0].0 < w[1].0);
+        let ahead = hops.first().is_none_or(|&(at, _)| at >= self.sample_index);
+        let in_band = hops.iter().all(|&(_, hz)| hz > 0.0 && hz < nyquist);
+        if !(increasing && ahead && in_band) {
+            return Err("invalid_hop_schedule");
+        }
+        let delay = self.bulk_delay.target_delay() as u64;
+        self.hops = hops.iter().map(|&(at, hz)| (at + delay, hz)).collect();
+        self.audit.record(self.sample_index, AuditChange::HopSchedule { hops: hops.to_vec() });
+        Ok(())
+    }
+    
+    /// Mute the receiver around its own transmissions: from the output
+    /// sample `at_sample` of each (at_sample, state) on, the output is at
+    /// full gain for TrState::Rx and attenuated by `attenuation_db` (muted
+    /// with None) for TrState::Tx, ramping between the two over `ramp_ms`
+    /// (see tr_switch)
+    ///
+    /// Replaces any earlier schedule, the receiver starting from :rx at
+    /// full gain; an empty one turns the switch off. at_sample must
+    /// strictly increase from the current sample index. Bypass passes its
+    /// input through untouched, switch or not. reset_to_idle() keeps the
+    /// schedule, unless it reseeds.
+    pub fn set_tr_schedule(
+        &mut self,
+        schedule: &[(u64, TrState)],
+        attenuation_db: Option<f64>,
+        ramp_ms: f64,
+    ) -> Result<(), &'static str> {
+        if !(0.0..=MAX_TR_RAMP_MS).contains(&ramp_ms) {
+            return Err("invalid_tr_schedule");
+        }
+        let ramp_samples = (ramp_ms * self.params.sample_rate as f64 / 1000.0).round() as u64;
+        let tr = TrSwitch::new(schedule, attenuation_db, ramp_samples, self.sample_index)?;
+        self.tr_switch = (!schedule.is_empty()).then_some(tr);
+        self.audit.record(
+            self.sample_index,
+            AuditChange::TrSchedule { schedule: schedule.to_vec(), attenuation_db, ramp_ms },
+        );
+        Ok(())
+    }
+    
+    /// Carrier the channel mixes at now, Hz
+    pub fn carrier_freq_hz(&self) -> f64 {
+        self.carrier_phase_inc * self.params.sample_rate as f64 / (2.0 * PI)
+    }
+    
+    /// Watch the fading for deep fades (None turns the alarm off)
+    ///
+    /// Crossings are collected by sample index during process() and
+    /// advance(); take_fade_events() hands them over.
+    pub fn set_fade_alarm(&mut self, alarm: Option<FadeAlarm>) {
+        self.fade_alarm = alarm;
+    }
+    
+    /// Fade crossings since the last call, oldest first
+    pub fn take_fade_events(&mut self) -> Vec<FadeEvent> {
+        self.fade_alarm.as_mut().map(FadeAlarm::take_events).unwrap_or_default()
+    }
+    
+    /// Feed this sample's tap power to the fade alarm, if there is one
+    ///
+    /// The combined envelope is the power of the taps in use, relative to
+    /// their long-term mean: |h0|² on a single-path channel, |h0|² + |h1|²
+    /// on two paths (the average power gain across the band), with |h1|²
+    /// weighted by the taps' power ratio when they're unequal.
+    fn track_fade(&mut self, h0: (f64, f64), h1: (f64, f64)) {
+        let power = self.relative_power(h0, h1);
+        if let Some(alarm) = &mut self.fade_alarm {
+            alarm.update(power, self.sample_index);
+        }
+    }
+    
+    /// Combined tap power relative to its long-term mean
+    fn relative_power(&self, h0: (f64, f64), h1: (f64, f64)) -> f64 {
+        let mut power = h0.0 * h0.0 + h0.1 * h0.1;
+        if let Some(weight) = tap1_weight(self.tap_split) {
+            power += weight * (h1.0 * h1.0 + h1.1 * h1.1);
+        }
+        power / self.fade_mean_power
+    }
+    
+    /// Drop everything in flight, e.g. when a scenario aborts mid-burst
+    ///
+    /// Clears the bulk delay line (settling at its target delay, with no
+    /// slew pending), the input conditioning filters, the delayed-path
+    /// line, the baseband FIR histories and the carrier and frequency
+    /// offset phases, so no tail of an earlier burst reaches the
+    /// output. Parameters, including any update_params() changes, the fade
+    /// alarm, the phase log and the audit log stay, as do the carrier and
+    /// hops still pending from set_hop_schedule() and any TR schedule.
+    ///
+    /// With `RngReset::Preserve` the fading taps, noise and dither keep
+    /// their sequence positions (and sample_index keeps counting). With
+    /// `RngReset::Reseed(seed)` the channel is exactly a new channel with
+    /// its current parameters and that seed, back on its configured
+    /// carrier with no hops and no TR schedule.
+    pub fn reset_to_idle(&mut self, rng: RngReset) {
+        if let RngReset::Reseed(seed) = rng {
+            let mut fresh = Self::new(self.params.clone(), seed);
+            fresh.fade_alarm = self.fade_alarm.take();
+            fresh.phase_log = self.phase_log.take();
+            std::mem::swap(&mut fresh.audit, &mut self.audit);
+            fresh.audit.record(self.sample_index, AuditChange::Reseeded { old: self.seed, new: seed });
+            fresh.group = self.group.take();
+            *self = fresh;
+            self.follow_group();
+            return;
+        }
+
+        self.baseband.reset();
+        self.carrier_phase = 0.0;
+        self.carrier_quadrant = 0;
+        self.offset_origin = (self.sample_index, 0.0);
+        self.output_power = PowerMeter::new(self.params.sample_rate);
+        self.conditioning.clear();
+        self.bulk_delay = BulkDelay::new(self.bulk_delay.target_delay());
+        if let Some(delay) = &mut self.fractional_delay {
+            delay.clear();
+        }
+        if let Some(drift) = &mut self.drift {
+            drift.clear();
+        }
+    }
+    
+    /// Turn the phase log on, logging every `decimation`th sample, or off
+    /// with None; either way any entries held are dropped
+    pub fn set_phase_log(&mut self, log: Option<PhaseLog>) {
+        self.phase_log = log;
+    }
+    
+    /// The phase log, if on (see phase_log)
+    pub fn phase_log_mut(&mut self) -> Option<&mut PhaseLog> {
+        self.phase_log.as_mut()
+    }
+    
+    /// Parameter changes so far (see audit)
+    pub fn audit_log(&self) -> &AuditLog {
+        &self.audit
+    }
+    
+    /// Mutable access to the audit log, to clear it or change its cap
+    pub fn audit_log_mut(&mut self) -> &mut AuditLog {
+        &mut self.audit
+    }
+    
+    /// Estimate of the heap memory held: filters, delay lines, the hop
+    /// and T/R schedules and the logs (for the resource census)
+    pub fn memory_bytes(&self) -> usize {
+        self.baseband.memory_bytes()
+            + self.bulk_delay.memory_bytes()
+            + self.hops.capacity() * std::mem::size_of::<(u64, f64)>()
+            + self.fractional_delay.as_ref().map_or(0, FractionalDelay::memory_bytes)
+            + self.drift.as_ref().map_or(0, ClockDrift::memory_bytes)
+            + self.fade_alarm.as_ref().map_or(0, FadeAlarm::memory_bytes)
+            + self.phase_log.as_ref().map_or(0, PhaseLog::memory_bytes)
+            + self.tr_switch.as_ref().map_or(0, TrSwitch::memory_bytes)
+            + self.audit.memory_bytes()
+            + self.noise.memory_bytes()
+    }
+    
+    /// Seed the channel was created (or last reseeded) with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    
+    /// Delay from input to output along the direct path, in whole samples
+    ///
+    /// Bulk propagation delay (its target, if slewing) plus the group
+    /// delay of the baseband filters, the fractional delay's whole samples
+    /// and the drift resampler; zero in bypass. Under clock drift this is
+    /// at the start: the receiver's clock then stretches or shrinks it by
+    /// the offset.
+    pub fn latency_samples(&self) -> usize {
+        if self.params.bypass {
+            return 0;
+        }
+        let fractional = if self.fractional_delay.is_some() { fractional_delay::HALF_TAPS } else { 0 };
+        let drift = if self.drift.is_some() { drift::HALF_TAPS } else { 0 };
+        self.bulk_delay.target_delay() as usize + self.fir_group_delay + fractional + drift
+    }
+    
+    /// Delay from input to output along the direct path, samples
+    ///
+    /// latency_samples() with the bulk delay as currently applied (between
+    /// samples while slewing) and the fractional delay's fraction.
+    pub fn total_delay_samples(&self) -> f64 {
+        if self.params.bypass {
+            return 0.0;
+        }
+        let fractional = self.fractional_delay.as_ref().map_or(0.0, FractionalDelay::delay);
+        let drift = if self.drift.is_some() { drift::HALF_TAPS } else { 0 };
+        self.bulk_delay.current_delay() + (self.fir_group_delay + drift) as f64 + fractional
+    }
+    
+    /// Follow a group's weather from now on (see group), replacing any
+    /// group followed before
+    pub fn join_group(&mut self, group: Arc<GroupWeather>) {
+        self.group = Some(group);
+        self.follow_group();
+    }
+    
+    /// Stop following a group and go back to snr_db and
+    /// doppler_bandwidth_hz as they are
+    pub fn leave_group(&mut self) {
+        self.group = None;
+        self.apply_group_offsets(GroupOffsets::default());
+    }
+    
+    /// What the group added at the start of the last block (zero outside
+    /// a group)
+    pub fn group_offsets(&self) -> GroupOffsets {
+        self.group_offsets
+    }
+    
+    /// Sample the group's offsets at the current time, if in one
+    fn follow_group(&mut self) {
+        if let Some(group) = &self.group {
+            let offsets = group.offsets_at(self.time().1);
+            self.apply_group_offsets(offsets);
+        }
+    }
+    
+    /// Set the noise level and fading rate for snr_db and
+    /// doppler_bandwidth_hz plus `offsets`
+    fn apply_group_offsets(&mut self, offsets: GroupOffsets) {
+        self.group_offsets = offsets;
+        self.noise.set_noise_power(noise_power_for_snr(self.params.snr_db + offsets.snr_db));
+        let doppler = self.params.doppler_bandwidth_hz.abs();
+        if doppler != 0.0 {
+            let scale = ((doppler + offsets.doppler_hz) / doppler).max(0.0);
+            self.tap0.set_doppler_scale(scale);
+            self.tap1.set_doppler_scale(scale);
+        }
+    }
+    
+    /// Get current channel state for telemetry
+    ///
+    /// Reading it moves nothing on. The tap gains are the channel's own
+    /// taps at the next sample; a correlated set's members are faded by a
+    /// mix of their members' taps instead (see correlated).
+    pub fn get_state(&self) -> ChannelState {
+        let (tap0_gain, tap1_gain) = (self.tap0.current_gain(), self.tap1.current_gain());
+        ChannelState {
+            sample_index: self.sample_index,
+            tap0_phase: self.tap0.get_phase(),
             tap1_phase: self.tap1.get_phase(),
+            bulk_delay_samples: self.bulk_delay.current_delay(),
+            total_delay_samples: self.total_delay_samples(),
+            bypass: self.params.bypass,
+            start_time_s: self.start_time_s,
+            output_samples: (self.sample_index as i64 + self.drift.as_ref().map_or(0, ClockDrift::slip_samples)) as u64,
+            tr_state: self.tr_switch.as_ref().map_or(TrState::Rx, |tr| tr.state_at(self.sample_index)),
+            transient_len: self.transient_len() as u64,
+            tap0_gain,
+            tap1_gain,
+            envelope_db: telemetry_db(self.relative_power(tap0_gain, tap1_gain)),
+            snr_db: self.params.snr_db + self.group_offsets.snr_db,
+            output_power_db: telemetry_db(self.output_power.power),
         }
     }
 }
@@ -362,7 +1857,9 @@ impl WattersonChannel {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::f64::consts::PI;
+    use crate::audit::AuditEntry;
+    use crate::fade_alarm::FadeEdge;
+    use std::f64::consts::{FRAC_1_SQRT_2, PI};
 
     // ========================================================================
     // TEST UTILITIES
@@ -393,6 +1890,28 @@ mod tests {
         2.0 * ((sum_cos / n).powi(2) + (sum_sin / n).powi(2)).sqrt()
     }
 
+    /// SNR of the tone at `freq_hz` in `signal` counting only the noise
+    /// below `bandwidth_hz`: the tone is fitted coherently (any phase, so
+    /// the channel's delay doesn't matter) and the residual's power taken
+    /// through a long windowed-sinc lowpass
+    fn measure_in_band_snr_db(signal: &[f32], freq_hz: f64, sample_rate: f64, bandwidth_hz: f64) -> f64 {
+        let phase = |i: usize| 2.0 * PI * freq_hz * i as f64 / sample_rate;
+        let n = signal.len() as f64;
+        let a = 2.0 * signal.iter().enumerate().map(|(i, &s)| s as f64 * phase(i).cos()).sum::<f64>() / n;
+        let b = 2.0 * signal.iter().enumerate().map(|(i, &s)| s as f64 * phase(i).sin()).sum::<f64>() / n;
+
+        let num_taps = 511;
+        let mut lpf = FirLowPassFilter::<f64>::new(bandwidth_hz, sample_rate, num_taps);
+        let in_band: Vec<f64> = signal
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| lpf.process(s as f64 - a * phase(i).cos() - b * phase(i).sin()))
+            .skip(num_taps)
+            .collect();
+        let noise_power = in_band.iter().map(|x| x * x).sum::<f64>() / in_band.len() as f64;
+        10.0 * ((a * a + b * b) / 2.0 / noise_power).log10()
+    }
+
     fn measure_rms(signal: &[f32]) -> f64 {
         let sum_sq: f64 = signal.iter().map(|&x| (x as f64).powi(2)).sum();
         (sum_sq / signal.len() as f64).sqrt()
@@ -400,41 +1919,35 @@ mod tests {
 
     fn make_awgn_only_params(snr_db: f64) -> ChannelParams {
         ChannelParams {
-            sample_rate: 9600,
-            delay_spread_samples: 0,
-            doppler_bandwidth_hz: 0.0,
             snr_db,
-            carrier_freq_hz: 1800.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
         }
     }
 
     fn make_fading_only_params(doppler_hz: f64) -> ChannelParams {
         ChannelParams {
-            sample_rate: 9600,
-            delay_spread_samples: 0,
             doppler_bandwidth_hz: doppler_hz,
             snr_db: 80.0, // Effectively no noise
-            carrier_freq_hz: 1800.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
         }
     }
 
     fn make_multipath_only_params(delay_samples: u32) -> ChannelParams {
         ChannelParams {
-            sample_rate: 9600,
             delay_spread_samples: delay_samples,
-            doppler_bandwidth_hz: 0.0,
             snr_db: 80.0,
-            carrier_freq_hz: 1800.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
         }
     }
 
     fn make_clean_channel_params() -> ChannelParams {
         ChannelParams {
-            sample_rate: 9600,
-            delay_spread_samples: 0,
-            doppler_bandwidth_hz: 0.0,
             snr_db: 80.0,
-            carrier_freq_hz: 1800.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
         }
     }
 
@@ -444,7 +1957,7 @@ mod tests {
 
     #[test]
     fn test_fir_dc_gain_unity() {
-        let mut lpf = FirLowPassFilter::new(2800.0, 9600.0, 31);
+        let mut lpf: FirLowPassFilter = FirLowPassFilter::new(2800.0, 9600.0, 31);
         
         let mut output = 0.0;
         for _ in 0..100 {
@@ -484,7 +1997,7 @@ mod tests {
 
     #[test]
     fn test_fir_impulse_response_symmetric() {
-        let mut lpf = FirLowPassFilter::new(2800.0, 9600.0, 31);
+        let mut lpf: FirLowPassFilter = FirLowPassFilter::new(2800.0, 9600.0, 31);
         
         let mut impulse_response = Vec::new();
         for i in 0..31 {
@@ -653,14 +2166,68 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tap_gains_set_echo_power_ratio() {
+        // A carrier burst through an unfaded two-path channel: the energy
+        // of each echo, well apart, is its tap's power
+        let mut input = vec![0.0_f32; 300];
+        for (n, x) in input[50..58].iter_mut().enumerate() {
+            *x = (0.5 * (2.0 * PI * 1800.0 * n as f64 / 9600.0).cos()) as f32;
+        }
+        for (tap0_gain_db, tap1_gain_db) in [(0.0, 0.0), (0.0, -6.0), (0.0, -12.0), (-3.0, 0.0), (4.0, 1.0)] {
+            let params = ChannelParams { snr_db: 100.0, tap0_gain_db, tap1_gain_db, ..make_multipath_only_params(60) };
+            let mut channel = WattersonChannel::new(params, 42);
+            let output = channel.process(&input);
+            let energy = |range: std::ops::Range<usize>| output[range].iter().map(|&y| (y as f64).powi(2)).sum::<f64>();
+            // The direct path arrives over 50..88, the echo 59 samples on
+            let ratio_db = 10.0 * (energy(99..160) / energy(40..99)).log10();
+            let expected = tap1_gain_db - tap0_gain_db;
+            assert!((ratio_db - expected).abs() < 0.5, "{tap0_gain_db}/{tap1_gain_db} dB: echo ratio {ratio_db:.2} dB");
+        }
+    }
+
+    #[test]
+    fn test_tap_gains_under_fading() {
+        // Through Rayleigh fading the mean power of each echo keeps the
+        // configured ratio, and the pair keeps unit total power
+        let params = ChannelParams { tap1_gain_db: -6.0, ..make_fading_only_params(10.0) };
+        let params = ChannelParams { delay_spread_samples: 20, ..params };
+        let mut channel = WattersonChannel::new(params, 7);
+        const PERIOD: usize = 64;
+        let mut input = vec![(0.0, 0.0); 9600 * 60];
+        for x in input.iter_mut().step_by(PERIOD) {
+            *x = (1.0, 0.0);
+        }
+        let output = channel.process_iq(&input).unwrap();
+        let power = |offset: usize| {
+            let echoes: Vec<f64> = output[offset..].iter().step_by(PERIOD).map(|&(i, q)| i * i + q * q).collect();
+            echoes.iter().sum::<f64>() / echoes.len() as f64
+        };
+        // The delay line's spacing (see test_process_iq_echo_and_refusals)
+        let (direct, echo) = (power(0), power(19));
+        let ratio_db = 10.0 * (echo / direct).log10();
+        assert!((ratio_db + 6.0).abs() < 0.5, "echo ratio {ratio_db:.2} dB");
+        assert!((direct + echo - 1.0).abs() < 0.15, "total power {:.3}", direct + echo);
+
+        assert_eq!(validate_tap_gains(&params_with_gains(0.0, -6.0)), Ok(()));
+        assert_eq!(validate_tap_gains(&params_with_gains(0.0, -61.0)), Err("invalid_tap_gain"));
+        assert_eq!(validate_tap_gains(&params_with_gains(f64::NAN, 0.0)), Err("invalid_tap_gain"));
+        let mut channel = WattersonChannel::new(params_with_gains(0.0, -6.0), 1);
+        assert_eq!(channel.update_params(&params_with_gains(0.0, -3.0)), Err("immutable_param_changed"));
+    }
+
+    fn params_with_gains(tap0_gain_db: f64, tap1_gain_db: f64) -> ChannelParams {
+        ChannelParams { tap0_gain_db, tap1_gain_db, ..make_multipath_only_params(10) }
+    }
+
     // ========================================================================
     // SNR CALIBRATION TESTS
     // ========================================================================
 
     #[test]
     fn test_snr_calibration() {
-        for target_snr in [10.0, 20.0, 30.0] {
-            let params = make_awgn_only_params(target_snr);
+        for (target_snr, precision) in [(10.0, Precision::F64), (20.0, Precision::F64), (30.0, Precision::F64), (30.0, Precision::F32)] {
+            let params = ChannelParams { precision, ..make_awgn_only_params(target_snr) };
             let mut channel = WattersonChannel::new(params.clone(), 42);
             
             let num_samples = 50000;
@@ -680,9 +2247,47 @@ mod tests {
             let error = (measured_snr - target_snr).abs();
             
             assert!(error < 2.0,
-                "Target SNR = {} dB, measured = {:.1} dB, error = {:.1} dB",
-                target_snr, measured_snr, error);
+                "Target SNR = {} dB ({:?}), measured = {:.1} dB, error = {:.1} dB",
+                target_snr, precision, measured_snr, error);
+        }
+
+        // Band-limited noise: snr_db holds in the 3 kHz band exactly,
+        // where white noise of the same power would leave 10 log10(2B/fs)
+        // of it outside and read about 2 dB better
+        let input = generate_tone(1800.0, 9600.0, 100_000, 0.5);
+        for (noise_bandwidth_hz, expected_offset_db) in [(DEFAULT_NOISE_BANDWIDTH_HZ, 0.0), (0.0, 2.04)] {
+            for target_snr in [10.0, 20.0] {
+                let params = ChannelParams { noise_bandwidth_hz, ..make_awgn_only_params(target_snr) };
+                let output = WattersonChannel::new(params, 42).process(&input);
+                let measured_snr = measure_in_band_snr_db(&output, 1800.0, 9600.0, 3000.0);
+                assert!((measured_snr - target_snr - expected_offset_db).abs() < 0.3,
+                    "noise bandwidth {} Hz: target {} dB, measured in band {:.2} dB",
+                    noise_bandwidth_hz, target_snr, measured_snr);
+            }
         }
+
+        // Anything finite goes, NaN doesn't
+        for hz in [-1.0, 0.0, 3000.0, 1e6] {
+            let params = ChannelParams { noise_bandwidth_hz: hz, ..make_awgn_only_params(10.0) };
+            assert_eq!(validate_noise_bandwidth(&params), Ok(()));
+        }
+        let params = ChannelParams { noise_bandwidth_hz: f64::NAN, ..make_awgn_only_params(10.0) };
+        assert_eq!(validate_noise_bandwidth(&params), Err("invalid_noise_bandwidth"));
+    }
+
+    #[test]
+    fn test_default_params() {
+        // As the Elixir struct: noise in 3 kHz, no impulses, and snr_db
+        // holding in that band
+        let params = ChannelParams::default();
+        assert_eq!(params.noise_bandwidth_hz, 3000.0);
+        assert_eq!(params.impulse_probability, 0.0);
+        assert_eq!(crate::limits::validate_params(&params), Ok(()));
+
+        let input = generate_tone(1800.0, 9600.0, 100_000, 0.5);
+        let output = WattersonChannel::new(params, 42).process(&input);
+        let measured_snr = measure_in_band_snr_db(&output, 1800.0, 9600.0, 3000.0);
+        assert!((measured_snr - 10.0).abs() < 0.3, "measured in band {measured_snr:.2} dB");
     }
 
     #[test]
@@ -728,6 +2333,47 @@ mod tests {
             "Output power {} should exceed input power {}", output_power, input_power);
     }
 
+    #[test]
+    fn test_impulse_noise() {
+        let impulsive = |probability| ChannelParams {
+            impulse_probability: probability,
+            impulse_power_ratio_db: 30.0,
+            impulse_burst_samples: 10,
+            ..make_awgn_only_params(20.0)
+        };
+        let silence = vec![0.0; 100_000];
+        let plain = WattersonChannel::new(make_awgn_only_params(20.0), 42).process(&silence);
+
+        // The background is the Gaussian channel's, with the bursts on top
+        let output = WattersonChannel::new(impulsive(0.005), 42).process(&silence);
+        let in_burst = plain.iter().zip(&output).filter(|(a, b)| a != b).count() as f64 / silence.len() as f64;
+        let duty = 1.0 - 0.995_f64.powi(10);
+        assert!((in_burst / duty - 1.0).abs() < 0.15, "{in_burst} of samples in a burst, expected {duty}");
+        assert_eq!(WattersonChannel::new(impulsive(0.0), 42).process(&silence), plain);
+
+        // Complex too, on I and Q at once
+        let mut channel = WattersonChannel::new(impulsive(0.001), 42);
+        let iq = channel.process_iq(&vec![(0.0, 0.0); 10_000]).unwrap();
+        let background = 0.125 * 10f64.powf(-20.0 / 10.0);
+        assert!(iq.iter().any(|(i, q)| i.abs().min(q.abs()) > 6.0 * background.sqrt()));
+
+        let mut channel = WattersonChannel::new(impulsive(0.001), 42);
+        assert_eq!(channel.update_params(&impulsive(0.002)), Err("immutable_param_changed"));
+        assert_eq!(channel.update_params(&ChannelParams { snr_db: 10.0, ..impulsive(0.001) }), Ok(()));
+
+        assert_eq!(validate_impulse_noise(&impulsive(0.001)), Ok(()));
+        for params in [
+            impulsive(-0.1),
+            impulsive(1.5),
+            impulsive(f64::NAN),
+            ChannelParams { impulse_power_ratio_db: 81.0, ..impulsive(0.001) },
+            ChannelParams { impulse_burst_samples: 0, ..impulsive(0.001) },
+            ChannelParams { impulse_burst_samples: 9601, ..impulsive(0.001) },
+        ] {
+            assert_eq!(validate_impulse_noise(&params), Err("invalid_impulse_noise"));
+        }
+    }
+
     // ========================================================================
     // CARRIER PHASE / GROUP DELAY TESTS
     // ========================================================================
@@ -843,11 +2489,11 @@ mod tests {
     #[test]
     fn test_numerical_stability_long_run() {
         let params = ChannelParams {
-            sample_rate: 9600,
             delay_spread_samples: 5,
             doppler_bandwidth_hz: 1.0,
             snr_db: 20.0,
-            carrier_freq_hz: 1800.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
         };
         
         let mut channel = WattersonChannel::new(params, 42);
@@ -870,11 +2516,11 @@ mod tests {
     #[test]
     fn test_deterministic_same_seed() {
         let params = ChannelParams {
-            sample_rate: 9600,
             delay_spread_samples: 5,
             doppler_bandwidth_hz: 1.0,
             snr_db: 20.0,
-            carrier_freq_hz: 1800.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
         };
         
         let input = generate_tone(1800.0, 9600.0, 1000, 0.5);
@@ -894,11 +2540,11 @@ mod tests {
     #[test]
     fn test_different_seeds_differ() {
         let params = ChannelParams {
-            sample_rate: 9600,
             delay_spread_samples: 5,
             doppler_bandwidth_hz: 1.0,
             snr_db: 20.0,
-            carrier_freq_hz: 1800.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
         };
         
         let input = generate_tone(1800.0, 9600.0, 1000, 0.5);
@@ -1103,11 +2749,10 @@ mod tests {
         // Test with multiple seeds to average over fading realizations
         for seed in [42u64, 123, 456, 789, 1011] {
             let params = ChannelParams {
-                sample_rate: 9600,
-                delay_spread_samples: 0,
                 doppler_bandwidth_hz: 0.5,
                 snr_db: 30.0,
-                carrier_freq_hz: 1800.0,
+                noise_bandwidth_hz: 0.0,
+                ..ChannelParams::default()
             };
             
             let mut channel = WattersonChannel::new(params, seed);
@@ -1185,4 +2830,1669 @@ mod tests {
             "Preamble detected at {}, expected at {}, error = {} samples",
             best_pos, expected_pos, timing_error);
     }
-}
\ No newline at end of file
+
+    // ========================================================================
+    // BULK PROPAGATION DELAY TESTS
+    // ========================================================================
+
+    fn make_bulk_delay_params(bulk_delay_samples: u32) -> ChannelParams {
+        ChannelParams {
+            bulk_delay_samples,
+            ..make_clean_channel_params()
+        }
+    }
+
+    /// Deterministic broadband test signal
+    fn pseudo_noise(num_samples: usize, seed: u32) -> Vec<f32> {
+        let mut state = seed;
+        (0..num_samples)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+            })
+            .collect()
+    }
+
+    fn process_in_blocks(channel: &mut WattersonChannel, input: &[f32], block: usize) -> Vec<f32> {
+        input.chunks(block).flat_map(|c| channel.process(c)).collect()
+    }
+
+    #[test]
+    fn test_bulk_delay_exact_by_cross_correlation() {
+        // 300 ms at 9600 Hz, far longer than one 20 ms block
+        let delay = 2880;
+        let input = pseudo_noise(8000, 7);
+
+        let mut reference = WattersonChannel::new(make_bulk_delay_params(0), 42);
+        let mut delayed = WattersonChannel::new(make_bulk_delay_params(delay as u32), 42);
+        let out_ref = process_in_blocks(&mut reference, &input, 192);
+        let out_del = process_in_blocks(&mut delayed, &input, 192);
+
+        let xcorr = |lag: usize| -> f64 {
+            (lag..out_del.len())
+                .map(|n| out_del[n] as f64 * out_ref[n - lag] as f64)
+                .sum()
+        };
+        let peak_lag = (0..4000).max_by(|&a, &b| xcorr(a).total_cmp(&xcorr(b))).unwrap();
+        assert_eq!(peak_lag, delay);
+
+        // Static channel: delayed output is the reference shifted
+        for n in delay..out_del.len() {
+            assert!((out_del[n] - out_ref[n - delay]).abs() < 1e-3, "sample {}", n);
+        }
+        assert!(out_del[..delay].iter().all(|x| x.abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_bulk_delay_advance_consistent_with_process() {
+        let input = pseudo_noise(3000, 3);
+        let mut advanced = WattersonChannel::new(make_bulk_delay_params(500), 9);
+        let mut processed = WattersonChannel::new(make_bulk_delay_params(500), 9);
+
+        advanced.process(&input[..1000]);
+        processed.process(&input[..1000]);
+        advanced.advance(300);
+        processed.process(&[0.0; 300]);
+
+        // Remaining input arrives after the gap; the delayed tail of the
+        // first block must still come out first
+        let a = advanced.process(&input[1000..]);
+        let b = processed.process(&input[1000..]);
+        for (x, y) in a.iter().zip(&b) {
+            assert!((x - y).abs() < 1e-3);
+        }
+        assert!(a[..200].iter().any(|x| x.abs() > 0.01), "delayed tail lost across advance");
+        assert_eq!(advanced.get_state().sample_index, processed.get_state().sample_index);
+    }
+
+    #[test]
+    fn test_bulk_delay_ping_rtt() {
+        // Station A pings, station B replies a fixed turnaround after it
+        // hears the ping, both talking over channels with the same one-way
+        // delay, in 10 ms ticks like the SimNet scheduler
+        const TICK: usize = 96;
+        const BURST: usize = 480;
+        const TURNAROUND: usize = 192;
+        const THRESHOLD: f32 = 0.1;
+
+        let burst_sample = |n: usize| -> f32 {
+            (0.5 * (2.0 * PI * 1800.0 * n as f64 / 9600.0).cos()) as f32
+        };
+
+        let measure_rtt = |one_way: u32| -> usize {
+            let mut a_to_b = WattersonChannel::new(make_bulk_delay_params(one_way), 1);
+            let mut b_to_a = WattersonChannel::new(make_bulk_delay_params(one_way), 2);
+            let mut b_reply_at: Option<usize> = None;
+
+            for tick in 0..2000 {
+                let t0 = tick * TICK;
+                let a_tx: Vec<f32> = (t0..t0 + TICK)
+                    .map(|n| if n < BURST { burst_sample(n) } else { 0.0 })
+                    .collect();
+                let b_rx = a_to_b.process(&a_tx);
+                if b_reply_at.is_none() {
+                    if let Some(i) = b_rx.iter().position(|x| x.abs() > THRESHOLD) {
+                        b_reply_at = Some(t0 + i + TURNAROUND);
+                    }
+                }
+
+                let b_tx: Vec<f32> = (t0..t0 + TICK)
+                    .map(|n| match b_reply_at {
+                        Some(start) if n >= start && n < start + BURST => burst_sample(n - start),
+                        _ => 0.0,
+                    })
+                    .collect();
+                let a_rx = b_to_a.process(&b_tx);
+                if let Some(i) = a_rx.iter().position(|x| x.abs() > THRESHOLD) {
+                    return t0 + i - TURNAROUND;
+                }
+            }
+            panic!("no reply within the simulated window");
+        };
+
+        // Filtering adds a small fixed latency; subtract the zero-delay loop
+        let baseline = measure_rtt(0);
+        for one_way in [96u32, 1234, 4800] {
+            assert_eq!(measure_rtt(one_way) - baseline, 2 * one_way as usize, "one-way {}", one_way);
+        }
+    }
+
+    #[test]
+    fn test_bulk_delay_update_params_slews() {
+        let mut channel = WattersonChannel::new(make_bulk_delay_params(1000), 5);
+        assert_eq!(channel.get_state().bulk_delay_samples, 1000.0);
+
+        let mut params = make_bulk_delay_params(1010);
+        params.snr_db = 30.0;
+        channel.update_params(&params).unwrap();
+
+        // Slews rather than jumping
+        channel.process(&[0.0; 5000]);
+        assert!((channel.get_state().bulk_delay_samples - 1005.0).abs() < 1e-6);
+        channel.advance(5100);
+        assert_eq!(channel.get_state().bulk_delay_samples, 1010.0);
+
+        // Fading/carrier parameters can't change on a live channel
+        params.carrier_freq_hz = 1500.0;
+        assert_eq!(channel.update_params(&params), Err("immutable_param_changed"));
+    }
+
+    // ========================================================================
+    // FRACTIONAL DELAY TESTS
+    // ========================================================================
+
+    /// Tones every 50 Hz across 300-3000 Hz at random phases: broadband
+    /// within the voice band, nothing near Nyquist
+    fn voice_band_noise(num_samples: usize, seed: u64) -> Vec<f64> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let tones: Vec<(f64, f64)> = (6..=60).map(|k| (50.0 * k as f64, rng.gen_range(0.0..2.0 * PI))).collect();
+        (0..num_samples)
+            .map(|n| {
+                let t = n as f64 / 9600.0;
+                tones.iter().map(|&(hz, phase)| (2.0 * PI * hz * t + phase).cos()).sum::<f64>() * 0.02
+            })
+            .collect()
+    }
+
+    /// Lag at which `output` correlates best with `input`, to a fraction
+    /// of a sample: the whole-sample peak, then the cross-correlation
+    /// sinc-interpolated between its neighbours
+    fn delay_by_interpolated_xcorr(input: &[f64], output: &[f64], max_lag: usize) -> f64 {
+        const SPAN: isize = 32;
+        let xcorr = |lag: isize| -> f64 {
+            let lag = lag.max(0) as usize;
+            output.iter().skip(lag).zip(input).map(|(y, x)| y * x).sum()
+        };
+        let peak = (0..=max_lag as isize).max_by(|&a, &b| xcorr(a).total_cmp(&xcorr(b))).unwrap();
+        let lags: Vec<(f64, f64)> = (peak - SPAN..=peak + SPAN).map(|l| (l as f64, xcorr(l))).collect();
+        let interpolated = |t: f64| -> f64 {
+            lags.iter()
+                .map(|&(l, r)| {
+                    let x = t - l;
+                    let sinc = if x.abs() < 1e-12 { 1.0 } else { (PI * x).sin() / (PI * x) };
+                    r * sinc * (0.5 + 0.5 * (PI * x / (SPAN + 1) as f64).cos())
+                })
+                .sum()
+        };
+        (-1000..=1000)
+            .map(|k| peak as f64 + k as f64 / 1000.0)
+            .max_by(|&a, &b| interpolated(a).total_cmp(&interpolated(b)))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_fractional_delay_by_interpolated_cross_correlation() {
+        // Measured against the same channel without it: the mix-up's fixed
+        // carrier rotation puts even that one's peak between samples
+        let input = voice_band_noise(9600, 3);
+        let mut plain = WattersonChannel::new(make_bulk_delay_params(0), 42);
+        let undelayed = plain.process_f64(&input);
+        for (bulk, fraction) in [(0, 0.1), (0, 0.25), (0, 0.5), (0, 0.75), (0, 0.9), (37, 0.25)] {
+            let params = ChannelParams { fractional_delay_samples: fraction, ..make_bulk_delay_params(bulk) };
+            let mut channel = WattersonChannel::new(params, 42);
+            let output = channel.process_f64(&input);
+            let measured = delay_by_interpolated_xcorr(&undelayed, &output, 200);
+            let reported = channel.get_state().total_delay_samples - plain.get_state().total_delay_samples;
+            let configured = (bulk as usize + fractional_delay::HALF_TAPS) as f64 + fraction;
+            assert_eq!(reported, configured);
+            assert!((measured - configured).abs() < 0.02, "{bulk} + {fraction}: measured {measured}");
+        }
+    }
+
+    #[test]
+    fn test_fractional_delay_composes_with_whole_sample_delays() {
+        let params = ChannelParams { fractional_delay_samples: 0.25, ..make_bulk_delay_params(100) };
+        let mut channel = WattersonChannel::new(params.clone(), 1);
+        let plain = WattersonChannel::new(make_bulk_delay_params(100), 1);
+        let extra = fractional_delay::HALF_TAPS as f64 + 0.25;
+        assert_eq!(channel.get_state().total_delay_samples, plain.get_state().total_delay_samples + extra);
+        assert_eq!(channel.latency_samples(), plain.latency_samples() + fractional_delay::HALF_TAPS);
+
+        // The bulk delay slews as usual under it
+        channel.update_params(&ChannelParams { bulk_delay_samples: 110, ..params.clone() }).unwrap();
+        channel.process(&[0.0; 5000]);
+        assert!((channel.get_state().total_delay_samples - (plain.get_state().total_delay_samples + 5.0 + extra)).abs() < 1e-6);
+
+        // Fixed for the channel's life
+        let moved = ChannelParams { fractional_delay_samples: 0.5, ..params };
+        assert_eq!(channel.update_params(&moved), Err("immutable_param_changed"));
+    }
+
+    // ========================================================================
+    // CLEAN REFERENCE OUTPUT TESTS
+    // ========================================================================
+
+    fn power(signal: &[f32]) -> f64 {
+        measure_rms(signal).powi(2)
+    }
+
+    fn difference(a: &[f32], b: &[f32]) -> Vec<f32> {
+        a.iter().zip(b).map(|(x, y)| x - y).collect()
+    }
+
+    #[test]
+    fn test_reference_matches_clean_channel() {
+        let impaired_params = ChannelParams {
+            delay_spread_samples: 20,
+            doppler_bandwidth_hz: 1.0,
+            snr_db: 10.0,
+            bulk_delay_samples: 150,
+            ..make_clean_channel_params()
+        };
+        let clean_params = ChannelParams { bulk_delay_samples: 150, ..make_clean_channel_params() };
+
+        let input = pseudo_noise(4000, 1924);
+        let (_, reference) = WattersonChannel::new(impaired_params, 7).process_with_reference(&input);
+        let clean = WattersonChannel::new(clean_params, 42).process(&input);
+
+        // Only the clean channel's 80 dB noise floor separates them
+        let max_err = reference.iter().zip(&clean).map(|(r, c)| (r - c).abs()).fold(0.0, f32::max);
+        assert!(max_err < 5e-4, "reference differs from clean channel by {}", max_err);
+    }
+
+    #[test]
+    fn test_reference_leaves_impaired_output_unchanged() {
+        let params = ChannelParams {
+            delay_spread_samples: 10,
+            doppler_bandwidth_hz: 2.0,
+            snr_db: 15.0,
+            ..make_clean_channel_params()
+        };
+        let input = pseudo_noise(2000, 5);
+        let plain = WattersonChannel::new(params.clone(), 99).process(&input);
+        let (impaired, _) = WattersonChannel::new(params, 99).process_with_reference(&input);
+        assert_eq!(plain, impaired);
+    }
+
+    #[test]
+    fn test_reference_difference_is_noise_on_awgn_channel() {
+        let snr_db = 20.0;
+        let mut channel = WattersonChannel::new(make_awgn_only_params(snr_db), 11);
+        let input = generate_tone(1800.0, 9600.0, 20000, 0.5);
+        let (impaired, reference) = channel.process_with_reference(&input);
+
+        let measured = power(&difference(&impaired, &reference));
+        let expected = noise_power_for_snr(snr_db);
+        assert!((measured / expected - 1.0).abs() < 0.05,
+            "difference power {:.3e}, expected noise power {:.3e}", measured, expected);
+    }
+
+    #[test]
+    fn test_reference_difference_includes_fading_distortion() {
+        // Rayleigh h with E|h|^2 = 1 and E[h] = 0 gives E|h - 1|^2 = 2,
+        // so the distortion is twice the signal power, plus the noise
+        let snr_db = 20.0;
+        let params = ChannelParams { snr_db, ..make_fading_only_params(2.0) };
+        let mut channel = WattersonChannel::new(params, 3);
+        let input = generate_tone(1800.0, 9600.0, 96000, 0.5);
+        let (impaired, reference) = channel.process_with_reference(&input);
+
+        let skip = 100;
+        let measured = power(&difference(&impaired[skip..], &reference[skip..]));
+        let expected = 2.0 * power(&reference[skip..]) + noise_power_for_snr(snr_db);
+        println!("Fading distortion power {:.4}, expected {:.4}", measured, expected);
+        assert!((measured / expected - 1.0).abs() < 0.3,
+            "difference power {:.4}, expected {:.4}", measured, expected);
+    }
+
+    // ========================================================================
+    // OUTPUT CONVERSION TESTS
+    // ========================================================================
+
+    fn make_output_params(output_bits: u32, output_dither: bool, clip_knee: f64) -> ChannelParams {
+        ChannelParams { output_bits, output_dither, clip_knee, ..make_clean_channel_params() }
+    }
+
+    /// SNR of a near-full-scale tone at the output, against the clean reference
+    fn measured_output_snr_db(params: ChannelParams) -> f64 {
+        let input: Vec<f64> = generate_tone(1700.0, 9600.0, 48000, 0.95).iter().map(|&x| x as f64).collect();
+        let (output, reference) = WattersonChannel::new(params, 26).process_f64_with_reference(&input);
+
+        let skip = 100;
+        let signal: f64 = reference[skip..].iter().map(|r| r * r).sum();
+        let error: f64 = output[skip..].iter().zip(&reference[skip..]).map(|(y, r)| (y - r).powi(2)).sum();
+        10.0 * (signal / error).log10()
+    }
+
+    #[test]
+    fn test_8bit_output_caps_snr_near_50_db() {
+        // 6.02 * 8 + 1.76 = 49.9 dB for a full-scale sine
+        for snr_db in [60.0, 80.0, 120.0] {
+            let float = measured_output_snr_db(make_awgn_only_params(snr_db));
+            let quantized = measured_output_snr_db(ChannelParams { snr_db, ..make_output_params(8, false, 0.0) });
+            println!("snr_db {}: float {:.1} dB, 8-bit {:.1} dB", snr_db, float, quantized);
+            assert!(float > snr_db, "float output SNR {:.1} dB", float);
+            assert!((quantized - 50.0).abs() < 1.5, "8-bit output SNR {:.1} dB", quantized);
+        }
+    }
+
+    #[test]
+    fn test_dither_removes_quantization_harmonics() {
+        // A 2.3 LSB tone at 8 bits: plain rounding turns the error into odd
+        // harmonics of the tone, TPDF dither turns it into flat noise
+        let step = 2.0 / 256.0;
+        let tone_hz = 600.0;
+        let skip = 200;
+        // Whole seconds, so each harmonic falls on a bin; four of them push
+        // the dithered noise floor per bin well below the harmonics
+        let input = generate_tone(tone_hz, 9600.0, 4 * 9600 + skip, 2.3 * step);
+
+        let worst_harmonic_dbc = |dither| {
+            let params = ChannelParams { snr_db: 200.0, ..make_output_params(8, dither, 0.0) };
+            let output = WattersonChannel::new(params, 3).process(&input);
+            let output = &output[skip..];
+            let fundamental = measure_sinusoid_amplitude(output, tone_hz, 9600.0);
+            [3.0, 5.0, 7.0]
+                .iter()
+                .map(|h| 20.0 * (measure_sinusoid_amplitude(output, h * tone_hz, 9600.0) / fundamental).log10())
+                .fold(f64::MIN, f64::max)
+        };
+
+        let plain = worst_harmonic_dbc(false);
+        let dithered = worst_harmonic_dbc(true);
+        println!("Worst odd harmonic: plain {:.1} dBc, dithered {:.1} dBc", plain, dithered);
+        assert!(plain > -30.0, "undithered harmonic only {:.1} dBc", plain);
+        assert!(dithered < -44.0, "dithered harmonic still {:.1} dBc", dithered);
+    }
+
+    #[test]
+    fn test_soft_clip_compression_curve() {
+        // Sweep 0.1 to 2.0 of full scale over one second
+        let knee = 0.6;
+        let n = 9600;
+        let input: Vec<f64> = (0..n)
+            .map(|i| {
+                let amplitude = 0.1 + 1.9 * i as f64 / n as f64;
+                amplitude * (2.0 * PI * 1700.0 * i as f64 / 9600.0).sin()
+            })
+            .collect();
+        let params = ChannelParams { snr_db: 200.0, ..make_output_params(0, false, knee) };
+        let (output, reference) = WattersonChannel::new(params, 5).process_f64_with_reference(&input);
+
+        // Sample for sample, the output is the limiter curve of the clean signal
+        for (k, (&y, &r)) in output.iter().zip(&reference).enumerate() {
+            assert!((y - crate::output::soft_clip(r, knee)).abs() < 1e-9, "sample {}: {} from {}", k, y, r);
+        }
+
+        // Per-window peak gain: unity below the knee, falling above it
+        let peak = |x: &[f64]| x.iter().fold(0.0, |m: f64, v| m.max(v.abs()));
+        let gains: Vec<(f64, f64)> = output
+            .chunks(480)
+            .zip(reference.chunks(480))
+            .skip(1)
+            .map(|(y, r)| (peak(r), peak(y) / peak(r)))
+            .collect();
+        for w in gains.windows(2) {
+            assert!(w[1].1 <= w[0].1 + 1e-9, "gain rose from {:.3} to {:.3}", w[0].1, w[1].1);
+        }
+        for &(level, gain) in &gains {
+            if level <= knee {
+                assert!((gain - 1.0).abs() < 1e-9, "gain {:.3} at {:.2} FS", gain, level);
+            }
+        }
+        let (top_level, top_gain) = *gains.last().unwrap();
+        assert!(top_level > 1.8 && top_gain < 0.56, "gain {:.3} at {:.2} FS", top_gain, top_level);
+    }
+
+    // ========================================================================
+    // RESET TESTS
+    // ========================================================================
+
+    /// Bulk delay, multipath and a loud burst to abort partway through
+    fn make_reset_params(doppler_hz: f64, snr_db: f64) -> ChannelParams {
+        ChannelParams {
+            delay_spread_samples: 10,
+            doppler_bandwidth_hz: doppler_hz,
+            snr_db,
+            bulk_delay_samples: 300,
+            ..make_clean_channel_params()
+        }
+    }
+
+    #[test]
+    fn test_reset_reseed_matches_new_channel() {
+        let params = ChannelParams { output_bits: 16, output_dither: true, ..make_reset_params(1.0, 20.0) };
+        let burst = generate_tone(1500.0, 9600.0, 4000, 0.5);
+        let expected = WattersonChannel::new(params.clone(), 77).process(&burst);
+
+        let mut channel = WattersonChannel::new(params, 5);
+        channel.process(&burst[..1234]);
+        channel.reset_to_idle(RngReset::Reseed(77));
+
+        assert_eq!(channel.seed(), 77);
+        assert_eq!(channel.get_state().sample_index, 0);
+        assert_eq!(channel.process(&burst), expected);
+    }
+
+    #[test]
+    fn test_reset_preserve_drops_burst_in_flight() {
+        let params = make_reset_params(0.0, 80.0);
+        let burst = generate_tone(1500.0, 9600.0, 4000, 0.5);
+        let silence = vec![0.0f32; 400];
+
+        // Without a reset, the aborted burst is still coming out of the delays
+        let mut channel = WattersonChannel::new(params.clone(), 8);
+        channel.process(&burst[..1000]);
+        assert!(measure_rms(&channel.process(&silence)) > 0.1);
+
+        let mut channel = WattersonChannel::new(params.clone(), 8);
+        channel.process(&burst[..1000]);
+        channel.reset_to_idle(RngReset::Preserve);
+        assert_eq!(channel.get_state().sample_index, 1000);
+        assert!(measure_rms(&channel.process(&silence)) < 1e-3);
+
+        // Next burst matches a new channel up to the (different) noise
+        channel.reset_to_idle(RngReset::Preserve);
+        let output = channel.process(&burst);
+        let expected = WattersonChannel::new(params, 8).process(&burst);
+        let max_diff = output.iter().zip(&expected).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+        assert!(max_diff < 1e-3, "max diff {}", max_diff);
+    }
+
+    #[test]
+    fn test_reset_preserve_keeps_random_sequences() {
+        // Fading and noise continue as if the channel had only been advanced
+        let params = ChannelParams { delay_spread_samples: 0, bulk_delay_samples: 0, ..make_reset_params(2.0, 10.0) };
+        let mut reset = WattersonChannel::new(params.clone(), 9);
+        let mut advanced = WattersonChannel::new(params, 9);
+        reset.process(&vec![0.0; 700]);
+        advanced.advance(700);
+
+        // The carrier phase restarts, but mix-down and mix-up cancel it
+        reset.reset_to_idle(RngReset::Preserve);
+        let tone = generate_tone(1500.0, 9600.0, 2000, 0.5);
+        let (a, b) = (reset.process(&tone), advanced.process(&tone));
+        let max_diff = a.iter().zip(&b).map(|(x, y)| (x - y).abs()).fold(0.0, f32::max);
+        assert!(max_diff < 1e-5, "max diff {}", max_diff);
+    }
+
+    // ========================================================================
+    // BYPASS TESTS
+    // ========================================================================
+
+    /// Every impairment switched on, so any leak into bypass output shows
+    fn make_impaired_params(bypass: bool) -> ChannelParams {
+        ChannelParams {
+            output_bits: 12,
+            output_dither: true,
+            clip_knee: 0.5,
+            bypass,
+            ..make_reset_params(2.0, 5.0)
+        }
+    }
+
+    #[test]
+    fn test_bypass_is_bit_exact() {
+        let mut channel = WattersonChannel::new(make_impaired_params(true), 10);
+        let input = pseudo_noise(3000, 10);
+
+        let mut output = Vec::new();
+        for block in input.chunks(700) {
+            output.extend(channel.process(block));
+        }
+        assert!(input.iter().zip(&output).all(|(a, b)| a.to_bits() == b.to_bits()));
+
+        let (impaired, reference) = channel.process_with_reference(&input[..100]);
+        assert_eq!(impaired, &input[..100]);
+        assert_eq!(reference, &input[..100]);
+
+        let input_f64: Vec<f64> = (0..500).map(|i| (i as f64 * 0.37).sin() * 1e-7).collect();
+        assert_eq!(channel.process_f64(&input_f64), input_f64);
+
+        assert_eq!(channel.latency_samples(), 0);
+    }
+
+    #[test]
+    fn test_bypass_keeps_sample_bookkeeping() {
+        let mut channel = WattersonChannel::new(make_impaired_params(true), 11);
+        channel.process(&vec![0.25; 480]);
+        channel.advance(1000);
+        channel.process_f64(&[0.0; 20]);
+
+        let state = channel.get_state();
+        assert_eq!(state.sample_index, 1500);
+        assert!(state.bypass);
+    }
+
+    #[test]
+    fn test_bypass_toggle_on_live_channel() {
+        let tone = generate_tone(1500.0, 9600.0, 6000, 0.5);
+        let (before, during, after) = (&tone[..2000], &tone[2000..4000], &tone[4000..]);
+
+        let mut toggled = WattersonChannel::new(make_impaired_params(false), 12);
+        let mut twin = WattersonChannel::new(make_impaired_params(false), 12);
+        assert_eq!(toggled.process(before), twin.process(before));
+
+        // Into bypass: the very first sample is already the input, with
+        // nothing queued in the delays leaking out
+        toggled.update_params(&make_impaired_params(true)).unwrap();
+        assert_eq!(toggled.process(during), during);
+
+        // Back out: an idle channel whose random sequences stood still
+        toggled.update_params(&make_impaired_params(false)).unwrap();
+        twin.reset_to_idle(RngReset::Preserve);
+        assert_eq!(toggled.process(after), twin.process(after));
+        assert_eq!(toggled.get_state().sample_index, 6000);
+        assert!(!toggled.get_state().bypass);
+    }
+
+    // ========================================================================
+    // FADE ALARM TESTS
+    // ========================================================================
+
+    const FADE_THRESHOLD_DB: f64 = -10.0;
+    const FADE_HYSTERESIS_DB: f64 = 3.0;
+
+    fn make_fade_params(delay_spread_samples: u32) -> ChannelParams {
+        ChannelParams { delay_spread_samples, ..make_fading_only_params(2.0) }
+    }
+
+    fn with_fade_alarm(params: ChannelParams, seed: u64) -> WattersonChannel {
+        let mut channel = WattersonChannel::new(params, seed);
+        channel.set_fade_alarm(Some(FadeAlarm::new(FADE_THRESHOLD_DB, FADE_HYSTERESIS_DB).unwrap()));
+        channel
+    }
+
+    /// Fade edges found straight from the taps of a same-seed twin
+    fn expected_fade_edges(params: ChannelParams, seed: u64, num_samples: usize) -> Vec<(FadeEdge, u64)> {
+        let two_path = params.delay_spread_samples != 0;
+        let mut twin = WattersonChannel::new(params, seed);
+        let mean = twin.tap0.mean_power() + if two_path { twin.tap1.mean_power() } else { 0.0 };
+
+        let mut edges = Vec::new();
+        let mut in_fade = false;
+        for n in 0..num_samples {
+            let (a, b) = twin.tap0.next_sample_complex();
+            let (c, d) = twin.tap1.next_sample_complex();
+            let mut power = (a * a + b * b) as f64;
+            if two_path {
+                power += (c * c + d * d) as f64;
+            }
+            let envelope_db = 10.0 * (power / mean).log10();
+            if !in_fade && envelope_db < FADE_THRESHOLD_DB {
+                in_fade = true;
+                edges.push((FadeEdge::Start, n as u64));
+            } else if in_fade && envelope_db > FADE_THRESHOLD_DB + FADE_HYSTERESIS_DB {
+                in_fade = false;
+                edges.push((FadeEdge::End, n as u64));
+            }
+        }
+        edges
+    }
+
+    fn edges(events: Vec<FadeEvent>) -> Vec<(FadeEdge, u64)> {
+        events.into_iter().map(|e| (e.edge, e.sample_index)).collect()
+    }
+
+    #[test]
+    fn test_fade_alarm_tracks_known_envelope() {
+        let num_samples = 9600 * 20;
+        for delay_spread in [0, 10] {
+            let params = make_fade_params(delay_spread);
+            let expected = expected_fade_edges(params.clone(), 34, num_samples);
+            assert!(expected.len() >= 4, "only {} fade edges in 20 s", expected.len());
+
+            let mut channel = with_fade_alarm(params, 34);
+            let mut events = Vec::new();
+            for block in vec![0.0f32; num_samples].chunks(997) {
+                channel.process(block);
+                events.extend(channel.take_fade_events());
+            }
+            let found = edges(events);
+
+            assert_eq!(found.len(), expected.len(), "delay spread {}", delay_spread);
+            for (f, e) in found.iter().zip(&expected) {
+                assert_eq!(f.0, e.0);
+                assert!(f.1.abs_diff(e.1) <= 2, "edge at {} expected near {}", f.1, e.1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fade_alarm_same_through_advance() {
+        let num_samples = 9600 * 10;
+        let mut processed = with_fade_alarm(make_fade_params(10), 35);
+        let mut advanced = with_fade_alarm(make_fade_params(10), 35);
+        processed.process(&vec![0.0; num_samples]);
+        advanced.advance(num_samples);
+
+        let events = processed.take_fade_events();
+        assert!(!events.is_empty());
+        assert_eq!(events, advanced.take_fade_events());
+        assert!(processed.take_fade_events().is_empty());
+    }
+
+    #[test]
+    fn test_fade_alarm_cleared_or_bypassed_is_silent() {
+        let mut channel = with_fade_alarm(make_fade_params(0), 36);
+        channel.set_fade_alarm(None);
+        channel.advance(9600 * 10);
+        assert!(channel.take_fade_events().is_empty());
+
+        let mut bypassed = with_fade_alarm(ChannelParams { bypass: true, ..make_fade_params(0) }, 36);
+        bypassed.process(&vec![0.0; 9600 * 10]);
+        assert!(bypassed.take_fade_events().is_empty());
+    }
+
+    // ========================================================================
+    // SEED DECOUPLING TESTS
+    // ========================================================================
+
+    fn make_seeded_params(fading_seed: Option<u64>, noise_seed: Option<u64>) -> ChannelParams {
+        ChannelParams { fading_seed, noise_seed, snr_db: 10.0, ..make_fade_params(10) }
+    }
+
+    /// Fade log and pure-noise output (zero input) of a fresh channel
+    fn fades_and_noise(params: ChannelParams, seed: u64) -> (Vec<FadeEvent>, Vec<f32>) {
+        let mut channel = with_fade_alarm(params, seed);
+        let noise = channel.process(&vec![0.0; 9600 * 10]);
+        (channel.take_fade_events(), noise)
+    }
+
+    fn correlation(a: &[f32], b: &[f32]) -> f64 {
+        let dot: f64 = a.iter().zip(b).map(|(&x, &y)| x as f64 * y as f64).sum();
+        dot / (power(a) * power(b)).sqrt() / a.len() as f64
+    }
+
+    #[test]
+    fn test_absent_seeds_fall_back_to_channel_seed() {
+        let tone = generate_tone(1500.0, 9600.0, 2000, 0.5);
+        let default = WattersonChannel::new(make_seeded_params(None, None), 7).process(&tone);
+        let explicit = WattersonChannel::new(make_seeded_params(Some(7), Some(7)), 7).process(&tone);
+        assert_eq!(default, explicit);
+    }
+
+    #[test]
+    fn test_same_fading_seed_different_noise() {
+        let (fades_a, noise_a) = fades_and_noise(make_seeded_params(Some(1), Some(10)), 100);
+        let (fades_b, noise_b) = fades_and_noise(make_seeded_params(Some(1), Some(20)), 200);
+
+        assert!(!fades_a.is_empty());
+        assert_eq!(fades_a, fades_b);
+        let r = correlation(&noise_a, &noise_b);
+        assert!(r.abs() < 0.02, "noise correlation {}", r);
+
+        // Swapping the noise seeds swaps the noise, sample for sample
+        let (fades_c, noise_c) = fades_and_noise(make_seeded_params(Some(1), Some(20)), 100);
+        let (_, noise_d) = fades_and_noise(make_seeded_params(Some(1), Some(10)), 200);
+        assert_eq!(fades_c, fades_a);
+        assert_eq!(noise_c, noise_b);
+        assert_eq!(noise_d, noise_a);
+    }
+
+    #[test]
+    fn test_same_noise_seed_different_fading() {
+        let (fades_a, noise_a) = fades_and_noise(make_seeded_params(Some(1), Some(10)), 100);
+        let (fades_b, noise_b) = fades_and_noise(make_seeded_params(Some(2), Some(10)), 100);
+        assert_ne!(fades_a, fades_b);
+        assert_eq!(noise_a, noise_b);
+    }
+
+    #[test]
+    fn test_reseed_noise_leaves_fading_alone() {
+        let params = make_seeded_params(None, None);
+        let mut reseeded = with_fade_alarm(params.clone(), 3);
+        let mut untouched = with_fade_alarm(params.clone(), 3);
+        let zeros = vec![0.0f32; 9600 * 5];
+        reseeded.process(&zeros);
+        untouched.process(&zeros);
+
+        // From here on the noise is a fresh noise_seed = 42 stream
+        reseeded.reseed_noise(42);
+        let noise = reseeded.process(&zeros);
+        untouched.process(&zeros);
+        let (_, expected) = fades_and_noise(ChannelParams { noise_seed: Some(42), ..params }, 99);
+        assert_eq!(noise, &expected[..zeros.len()]);
+
+        let fades = untouched.take_fade_events();
+        assert!(!fades.is_empty());
+        assert_eq!(reseeded.take_fade_events(), fades);
+        assert_eq!(reseeded.get_state().sample_index, untouched.get_state().sample_index);
+    }
+
+    // ========================================================================
+    // WARM START TESTS
+    // ========================================================================
+
+    /// Envelope (dB re mean) of the first sample the channel will fade with
+    fn initial_envelope_db(channel: &WattersonChannel) -> f64 {
+        let t = channel.tap0.time();
+        let power = channel.relative_power(channel.tap0.gain_at(t), channel.tap1.gain_at(t));
+        10.0 * power.log10()
+    }
+
+    #[test]
+    fn test_start_in_fade_reaches_depth() {
+        for (delay_spread_samples, depth_db) in [(0, -10.0), (0, -20.0), (10, -10.0)] {
+            let params = ChannelParams {
+                delay_spread_samples,
+                start_in_fade_db: Some(depth_db),
+                ..make_fading_only_params(0.1)
+            };
+            let channel = WattersonChannel::new(params, 5);
+            let envelope_db = initial_envelope_db(&channel);
+            assert!(
+                envelope_db <= depth_db && envelope_db > depth_db - 1.0,
+                "delay {}: asked for {} dB, started at {:.2} dB",
+                delay_spread_samples, depth_db, envelope_db
+            );
+            assert!(channel.get_state().start_time_s > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_fade_search_starts_at_start_time() {
+        let params = ChannelParams { start_at_time_s: 120.0, ..make_fading_only_params(0.1) };
+        let channel = WattersonChannel::new(params.clone(), 5);
+        assert_eq!(channel.get_state().start_time_s, 120.0);
+
+        let searched = WattersonChannel::new(ChannelParams { start_in_fade_db: Some(-10.0), ..params }, 5);
+        assert!(searched.get_state().start_time_s >= 120.0);
+        assert!(initial_envelope_db(&searched) <= -10.0);
+    }
+
+    #[test]
+    fn test_warm_start_is_deterministic() {
+        let params = ChannelParams { start_in_fade_db: Some(-15.0), snr_db: 20.0, ..make_fade_params(10) };
+        let tone = generate_tone(1500.0, 9600.0, 4800, 0.5);
+        let mut a = WattersonChannel::new(params.clone(), 11);
+        let mut b = WattersonChannel::new(params.clone(), 11);
+        assert_eq!(a.get_state().start_time_s, b.get_state().start_time_s);
+        assert_eq!(a.process(&tone), b.process(&tone));
+
+        // Reseeding to the same seed lands on the same fade again
+        a.reset_to_idle(RngReset::Reseed(11));
+        let mut c = WattersonChannel::new(params, 11);
+        assert_eq!(a.process(&tone), c.process(&tone));
+    }
+
+    #[test]
+    fn test_unreachable_depth_starts_at_deepest_point() {
+        let params = ChannelParams { start_in_fade_db: Some(-200.0), ..make_fading_only_params(2.0) };
+        let channel = WattersonChannel::new(params, 5);
+        let start = channel.get_state().start_time_s;
+        // Within the 4096-period search window at 2 Hz
+        assert!((0.0..4096.0 / 2.0).contains(&start));
+        assert!(initial_envelope_db(&channel) < -20.0);
+    }
+
+    #[test]
+    fn test_warm_start_validation() {
+        let ok = make_fading_only_params(1.0);
+        assert!(validate_warm_start(&ok).is_ok());
+        assert!(validate_warm_start(&ChannelParams { start_at_time_s: -1.0, ..ok.clone() }).is_err());
+        assert!(validate_warm_start(&ChannelParams { start_at_time_s: f64::NAN, ..ok.clone() }).is_err());
+        assert!(validate_warm_start(&ChannelParams { start_in_fade_db: Some(3.0), ..ok.clone() }).is_err());
+        assert!(validate_warm_start(&ChannelParams { start_in_fade_db: Some(-12.0), ..ok }).is_ok());
+    }
+
+    #[test]
+    fn test_update_params_keeps_warm_start() {
+        let params = ChannelParams { start_in_fade_db: Some(-10.0), ..make_fading_only_params(1.0) };
+        let mut channel = WattersonChannel::new(params.clone(), 5);
+        let start = channel.get_state().start_time_s;
+        channel
+            .update_params(&ChannelParams { start_in_fade_db: None, snr_db: 30.0, ..params })
+            .unwrap();
+        channel.reset_to_idle(RngReset::Reseed(5));
+        assert_eq!(channel.get_state().start_time_s, start);
+    }
+
+    // ========================================================================
+    // SIMULATION TIME TESTS
+    // ========================================================================
+
+    fn make_busy_params() -> ChannelParams {
+        ChannelParams {
+            delay_spread_samples: 10,
+            snr_db: 20.0,
+            bulk_delay_samples: 500,
+            output_bits: 12,
+            output_dither: true,
+            ..make_fading_only_params(1.0)
+        }
+    }
+
+    #[test]
+    fn test_run_until_matches_processing_silence() {
+        let input = pseudo_noise(3000, 5);
+        for target in [3100, 3000 + 9600 * 30] {
+            let mut run = WattersonChannel::new(make_busy_params(), 12);
+            let mut processed = WattersonChannel::new(make_busy_params(), 12);
+            // Mid-slew, so the drain has to allow for a growing delay
+            for channel in [&mut run, &mut processed] {
+                channel.update_params(&ChannelParams { bulk_delay_samples: 800, ..make_busy_params() }).unwrap();
+                channel.process(&input);
+            }
+
+            assert_eq!(run.run_until(target), Ok(target - 3000));
+            processed.process(&vec![0.0; (target - 3000) as usize]);
+            assert_eq!(run.time(), processed.time());
+
+            let next = pseudo_noise(2000, 6);
+            assert_eq!(run.process(&next), processed.process(&next), "target {}", target);
+            assert_eq!(run.get_state().bulk_delay_samples, processed.get_state().bulk_delay_samples);
+        }
+    }
+
+    #[test]
+    fn test_run_until_reports_fades() {
+        let mut run = with_fade_alarm(make_fade_params(10), 37);
+        let mut processed = with_fade_alarm(make_fade_params(10), 37);
+        run.run_until(9600 * 10).unwrap();
+        processed.process(&vec![0.0; 9600 * 10]);
+
+        let events = run.take_fade_events();
+        assert!(!events.is_empty());
+        assert_eq!(events, processed.take_fade_events());
+    }
+
+    #[test]
+    fn test_time_and_past_targets() {
+        let mut channel = WattersonChannel::new(
+            ChannelParams { start_at_time_s: 100.0, ..make_busy_params() },
+            13,
+        );
+        assert_eq!(channel.time(), (0, 0.0));
+        channel.process(&vec![0.0; 4800]);
+        assert_eq!(channel.time(), (4800, 0.5));
+        assert_eq!(channel.run_until(4800), Ok(0));
+        assert_eq!(channel.run_until(4799), Err("target_in_past"));
+        assert_eq!(channel.run_until(9600 * 2), Ok(9600 * 2 - 4800));
+        assert_eq!(channel.time(), (19_200, 2.0));
+    }
+
+    #[test]
+    fn test_transient_len_covers_a_burst_tail() {
+        // Quiet enough that only the signal's tail is above the floor
+        let quiet = |params: ChannelParams| ChannelParams { snr_db: 300.0, output_bits: 0, ..params };
+        let cases = [
+            make_awgn_only_params(300.0),
+            quiet(make_busy_params()),
+            quiet(ChannelParams { sample_rate_offset_ppm: 100.0, ..make_busy_params() }),
+        ];
+        for params in cases {
+            let mut channel = WattersonChannel::new(params.clone(), 3);
+            let len = channel.transient_len();
+            assert_eq!(channel.get_state().transient_len, len as u64);
+            channel.process(&pseudo_noise(2000, 4));
+            let tail = channel.process(&vec![0.0; 2 * len]);
+            let last = tail.iter().rposition(|y| y.abs() > 1e-9).unwrap();
+            assert!(last < len, "tail runs to {last} of {len} ({params:?})");
+            assert!(last > len / 2, "tail ends at {last} of {len} ({params:?})");
+        }
+        let bypass = WattersonChannel::new(ChannelParams { bypass: true, ..make_busy_params() }, 3);
+        assert_eq!(bypass.transient_len(), 0);
+    }
+
+    #[test]
+    fn test_prime_flushes_an_earlier_burst() {
+        let params = make_awgn_only_params(300.0);
+        let block = generate_tone(1500.0, 9600.0, 64, 0.5);
+        let burst = generate_tone(1000.0, 9600.0, 4800, 0.9);
+        let power_db = |x: &[f32]| 20.0 * measure_rms(x).log10();
+        let fresh = WattersonChannel::new(params.clone(), 5).process(&block);
+
+        // Straight after the burst, its tail colors the block
+        let mut stale = WattersonChannel::new(params.clone(), 5);
+        stale.process(&burst);
+        let colored = stale.process(&block);
+        assert!((power_db(&colored) - power_db(&fresh)).abs() > 1.0);
+
+        let mut primed = WattersonChannel::new(params.clone(), 5);
+        primed.process(&burst);
+        let len = primed.transient_len();
+        assert_eq!(primed.prime(None), len as u64);
+        assert_eq!(primed.time().0, (4800 + len) as u64);
+        let clean = primed.process(&block);
+        assert!((power_db(&clean) - power_db(&fresh)).abs() < 0.01);
+        assert!(clean.iter().zip(&fresh).all(|(a, b)| (a - b).abs() < 1e-5));
+
+        // Only the run-up is left: the tone is at its steady level from
+        // transient_len in
+        let steady = WattersonChannel::new(params.clone(), 5).process(&generate_tone(1500.0, 9600.0, 9600, 0.5));
+        let steady = measure_sinusoid_amplitude(&steady[4800..], 1500.0, 9600.0);
+        let level = measure_sinusoid_amplitude(&clean[len..], 1500.0, 9600.0);
+        assert!((20.0 * (level / steady).log10()).abs() < 0.1, "{level} against {steady}");
+
+        // A new channel is already idle
+        let mut new = WattersonChannel::new(params, 5);
+        assert_eq!(new.prime(Some(100)), 100);
+        assert!(new.process(&block).iter().zip(&fresh).all(|(a, b)| (a - b).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_ten_minute_scenario_faster_than_real_time() {
+        // Scripted changes, (seconds, snr_db, bulk_delay_samples), with a
+        // one-second burst after each
+        let script = [(0, 20.0, 500), (45, 10.0, 500), (90, 10.0, 900), (240, 3.0, 900), (420, 25.0, 200), (599, 25.0, 200)];
+        let burst = pseudo_noise(9600, 8);
+        let started = std::time::Instant::now();
+
+        let mut channel = WattersonChannel::new(make_busy_params(), 14);
+        let mut fired = Vec::new();
+        for &(at_s, snr_db, bulk_delay_samples) in &script {
+            channel.run_until(at_s * 9600).unwrap();
+            fired.push(channel.time());
+            channel
+                .update_params(&ChannelParams { snr_db, bulk_delay_samples, ..make_busy_params() })
+                .unwrap();
+            channel.process(&burst);
+        }
+
+        let elapsed = started.elapsed();
+        let expected: Vec<_> = script.iter().map(|&(s, _, _)| (s * 9600, s as f64)).collect();
+        assert_eq!(fired, expected);
+        assert_eq!(channel.time(), (600 * 9600, 600.0));
+        assert!(elapsed < std::time::Duration::from_secs(1), "10 simulated minutes took {:?}", elapsed);
+    }
+
+    // ========================================================================
+    // GOLDEN OUTPUT (guards refactors that must not change behavior)
+    // ========================================================================
+
+    /// FNV-1a over the f32 bit patterns of a block
+    fn fingerprint(signal: &[f32]) -> u64 {
+        signal.iter().fold(0xcbf2_9ce4_8422_2325_u64, |h, &x| {
+            x.to_bits().to_le_bytes().iter().fold(h, |h, &b| {
+                (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+        })
+    }
+
+    #[test]
+    fn test_golden_output_unchanged() {
+        let params = ChannelParams {
+            delay_spread_samples: 10,
+            doppler_bandwidth_hz: 1.0,
+            snr_db: 20.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
+        };
+        let mut channel = WattersonChannel::new(params.clone(), 1917);
+        let input = generate_tone(1500.0, 9600.0, 2048, 0.5);
+        let output = channel.process(&input);
+
+        assert_eq!(fingerprint(&output), GOLDEN_FINGERPRINT);
+
+        // Impulse settings with no chance of a burst change nothing
+        let params = ChannelParams { impulse_power_ratio_db: 40.0, impulse_burst_samples: 20, ..params };
+        let output = WattersonChannel::new(params, 1917).process(&input);
+        assert_eq!(fingerprint(&output), GOLDEN_FINGERPRINT);
+    }
+
+    const GOLDEN_FINGERPRINT: u64 = 0x1c4a_cb0b_60b5_844e;
+
+    #[test]
+    fn test_quarter_rate_carrier_matches_general_path() {
+        for (carrier, sample_rate) in [(2400.0, 9600), (12000.0, 48000), (4800.0, 9600)] {
+            let params = ChannelParams {
+                sample_rate,
+                carrier_freq_hz: carrier,
+                doppler_bandwidth_hz: 1.0,
+                snr_db: 20.0,
+                ..make_multipath_only_params(5)
+            };
+            let mut fast = WattersonChannel::new(params.clone(), 42);
+            assert!(fast.trivial_lo.is_some());
+            let mut general = WattersonChannel::new(params, 42);
+            general.trivial_lo = None;
+            
+            let input: Vec<f64> = (0..4000).map(|n| 0.5 * (n as f64 * 0.37).sin()).collect();
+            let mut outputs = Vec::new();
+            for channel in [&mut fast, &mut general] {
+                let mut output = channel.process_f64(&input[..1500]);
+                channel.advance(123);
+                output.extend(channel.process_f64(&input[1500..]));
+                outputs.push(output);
+            }
+            for (n, (a, b)) in outputs[0].iter().zip(&outputs[1]).enumerate() {
+                assert!((a - b).abs() < 1e-12, "{carrier} Hz sample {n}: {a} vs {b}");
+            }
+        }
+    }
+    
+    #[test]
+    fn test_process_at_bridges_gaps_and_overlaps() {
+        let block: Vec<f32> = (0..960).map(|n| (n as f32 * 0.37).sin() * 0.5).collect();
+        let mut channel = WattersonChannel::new(make_busy_params(), 21);
+        let mut reference = WattersonChannel::new(make_busy_params(), 21);
+
+        assert_eq!(channel.process_at(0, &block), (reference.process(&block), Discontinuity::None));
+
+        // A dropped 20 ms buffer: the channel runs on through it
+        reference.run_until(960 + 192).unwrap();
+        let expected = reference.process(&block);
+        assert_eq!(channel.process_at(960 + 192, &block), (expected, Discontinuity::Gap(192)));
+        assert_eq!(channel.time(), reference.time());
+
+        // A repeated tail: only the new samples are processed
+        let (output, discontinuity) = channel.process_at(2 * 960 + 192 - 100, &block);
+        assert_eq!(discontinuity, Discontinuity::Overlap(100));
+        assert_eq!(output, reference.process(&block[100..]));
+
+        let (output, discontinuity) = channel.process_at(0, &block);
+        assert_eq!(discontinuity, Discontinuity::Overlap(3 * 960 + 92));
+        assert!(output.is_empty());
+        assert_eq!(channel.time(), reference.time());
+    }
+
+    #[test]
+    fn test_audit_log_records_scripted_changes() {
+        let params = make_busy_params();
+        let quieter = ChannelParams { snr_db: 10.0, ..params.clone() };
+        let mut channel = WattersonChannel::new(params.clone(), 5);
+
+        channel.process(&vec![0.0; 1000]);
+        channel.update_params(&quieter).unwrap();
+        channel.advance(500);
+        channel.reseed_noise(77);
+        channel.reseed_noise(78);
+        channel.run_until(4000).unwrap();
+        channel.reset_to_idle(RngReset::Reseed(9));
+        channel.process(&[0.0; 10]);
+        channel.update_params(&params).unwrap();
+
+        let log: Vec<_> = channel.audit_log().entries().cloned().collect();
+        let noise_78 = ChannelParams { noise_seed: Some(78), ..quieter.clone() };
+        let restored = ChannelParams { noise_seed: Some(78), ..params.clone() };
+        assert_eq!(
+            log,
+            [
+                AuditEntry { sample_index: 0, change: AuditChange::Created { params, seed: 5 } },
+                AuditEntry {
+                    sample_index: 1000,
+                    change: AuditChange::Updated { old: make_busy_params(), new: quieter.clone() },
+                },
+                AuditEntry { sample_index: 1500, change: AuditChange::NoiseReseeded { old: None, new: 77 } },
+                AuditEntry { sample_index: 1500, change: AuditChange::NoiseReseeded { old: Some(77), new: 78 } },
+                AuditEntry { sample_index: 4000, change: AuditChange::Reseeded { old: 5, new: 9 } },
+                AuditEntry { sample_index: 10, change: AuditChange::Updated { old: noise_78, new: restored } },
+            ]
+        );
+        assert_eq!(channel.audit_log().dropped(), 0);
+
+        channel.audit_log_mut().set_cap(2).unwrap();
+        channel.reseed_noise(1);
+        let kept: Vec<_> = channel.audit_log().entries().map(|e| e.sample_index).collect();
+        assert_eq!(kept, [10, 10]);
+        assert_eq!(channel.audit_log().dropped(), 5);
+        assert!(matches!(
+            channel.audit_log().entries().last().unwrap().change,
+            AuditChange::NoiseReseeded { old: Some(78), new: 1 }
+        ));
+    }
+
+    // ========================================================================
+    // CARRIER HOP TESTS
+    // ========================================================================
+
+    /// A tone that follows `hops` from 1800 Hz, continuous in phase
+    fn hopping_tone(hops: &[(u64, f64)], num_samples: usize) -> Vec<f32> {
+        let mut freq = 1800.0;
+        let mut phase = 0.0_f64;
+        (0..num_samples as u64)
+            .map(|n| {
+                if let Some(&(_, hz)) = hops.iter().find(|&&(at, _)| at == n) {
+                    freq = hz;
+                }
+                let s = (0.5 * phase.cos()) as f32;
+                phase += 2.0 * PI * freq / 9600.0;
+                s
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hop_schedule_follows_hopping_tone() {
+        // The echo lands 4 samples behind the direct path: in phase at the
+        // carrier, cancelling 1200 Hz off it
+        let params = make_multipath_only_params(5);
+        let hops = [(2000, 3000.0), (4000, 1800.0)];
+        let input = hopping_tone(&hops, 6000);
+
+        let mut hopped = WattersonChannel::new(params.clone(), 42);
+        hopped.set_hop_schedule(&hops).unwrap();
+        let hopped_out = hopped.process(&input);
+        assert_eq!(hopped.carrier_freq_hz(), 1800.0);
+        let mut fixed = WattersonChannel::new(params, 42);
+        let fixed_out = fixed.process(&input);
+
+        let amplitude = |out: &[f32], range: std::ops::Range<usize>, hz| {
+            measure_sinusoid_amplitude(&out[range], hz, 9600.0)
+        };
+        for out in [&hopped_out, &fixed_out] {
+            assert!((amplitude(out, 500..1900, 1800.0) - FRAC_1_SQRT_2).abs() < 0.01);
+            assert!((amplitude(out, 4100..6000, 1800.0) - FRAC_1_SQRT_2).abs() < 0.01);
+        }
+        // Following the hop, the channel carries the 3000 Hz stretch as it
+        // did the 1800 Hz one; left at 1800 Hz, the echo cancels it
+        assert!((amplitude(&hopped_out, 2100..3900, 3000.0) - FRAC_1_SQRT_2).abs() < 0.01);
+        assert!(amplitude(&fixed_out, 2100..3900, 3000.0) < 0.01);
+    }
+
+    #[test]
+    fn test_sideband_inversion_mirrors_about_carrier() {
+        // 300 Hz below the 1800 Hz carrier comes out 300 Hz above it
+        let input: Vec<f32> = (0..4800)
+            .map(|n| (0.5 * (2.0 * PI * 1500.0 * n as f64 / 9600.0).cos()) as f32)
+            .collect();
+        let inverted_params = ChannelParams { sideband_inversion: true, ..make_clean_channel_params() };
+
+        let mut normal = WattersonChannel::new(make_clean_channel_params(), 42);
+        let normal_out = normal.process(&input);
+        let mut inverted = WattersonChannel::new(inverted_params.clone(), 42);
+        let inverted_out = inverted.process(&input);
+
+        let through = measure_sinusoid_amplitude(&normal_out[500..], 1500.0, 9600.0);
+        assert!(measure_sinusoid_amplitude(&normal_out[500..], 2100.0, 9600.0) < 0.01);
+        assert!((measure_sinusoid_amplitude(&inverted_out[500..], 2100.0, 9600.0) - through).abs() < 0.01);
+        assert!(measure_sinusoid_amplitude(&inverted_out[500..], 1500.0, 9600.0) < 0.01);
+
+        // Switching it back in place restores the upright spectrum
+        inverted.update_params(&make_clean_channel_params()).unwrap();
+        let restored_out = inverted.process(&input);
+        assert!((measure_sinusoid_amplitude(&restored_out[500..], 1500.0, 9600.0) - through).abs() < 0.01);
+        assert!(measure_sinusoid_amplitude(&restored_out[500..], 2100.0, 9600.0) < 0.01);
+    }
+
+    #[test]
+    fn test_state_reports_fading_and_power() {
+        let params = ChannelParams { delay_spread_samples: 10, snr_db: 30.0, ..make_fading_only_params(1.0) };
+        let mut channel = WattersonChannel::new(params, 11);
+        let input = generate_tone(1800.0, 9600.0, 9600, 0.5);
+        channel.process(&input);
+
+        // The gains are the ones the next sample takes, and looking
+        // doesn't move them on
+        let state = channel.get_state();
+        assert_eq!(channel.get_state().tap0_gain, state.tap0_gain);
+        let (h0, h1) = channel.next_tap_gains(1)[0];
+        for (reported, used) in [(state.tap0_gain, h0), (state.tap1_gain, h1)] {
+            assert!((reported.0 - used.0).abs() < 1e-6 && (reported.1 - used.1).abs() < 1e-6);
+        }
+        assert_eq!(state.tap0_phase, state.tap0_gain.1.atan2(state.tap0_gain.0));
+        assert_eq!(state.tap1_phase, state.tap1_gain.1.atan2(state.tap1_gain.0));
+        let envelope = channel.relative_power(state.tap0_gain, state.tap1_gain);
+        assert!((state.envelope_db - 10.0 * envelope.log10()).abs() < 1e-9);
+        assert_eq!(state.snr_db, 30.0);
+
+        // A steady tone through a clean channel reads at its own power,
+        // and a long silence at the noise floor
+        let mut clean = WattersonChannel::new(make_clean_channel_params(), 11);
+        assert_eq!(clean.get_state().output_power_db, TELEMETRY_FLOOR_DB);
+        clean.process(&input);
+        let tone_db = 10.0 * 0.125_f64.log10();
+        assert!((clean.get_state().output_power_db - tone_db).abs() < 0.2, "{}", clean.get_state().output_power_db);
+        clean.advance(9600 * 5);
+        let floor_db = 10.0 * noise_power_for_snr(80.0).log10();
+        assert!((clean.get_state().output_power_db - floor_db).abs() < 1.0, "{}", clean.get_state().output_power_db);
+    }
+
+    #[test]
+    fn test_frequency_offset_shifts_output() {
+        // A 1500 Hz tone through a +10 Hz channel: the phase of the output
+        // against 1510 Hz, block by block, drifts by the residual offset
+        let params = ChannelParams { frequency_offset_hz: 10.0, ..make_clean_channel_params() };
+        let mut channel = WattersonChannel::new(params.clone(), 42);
+        let input = generate_tone(1500.0, 9600.0, 9600 * 10, 0.5);
+        let output = channel.process(&input);
+        assert!(measure_sinusoid_amplitude(&output[500..], 1500.0, 9600.0) < 0.01);
+        let residual = offset_from(&output, 1510.0);
+        assert!(residual.abs() < 0.1, "residual {residual} Hz");
+
+        // Skipping ahead carries the rotation along with the sample index
+        let mut advanced = WattersonChannel::new(params.clone(), 42);
+        let mut processed = WattersonChannel::new(params, 42);
+        advanced.advance(12_345);
+        processed.process(&vec![0.0; 12_345]);
+        let (a, b) = (advanced.process(&input[..4800]), processed.process(&input[..4800]));
+        for (n, (a, b)) in a.iter().zip(&b).enumerate() {
+            assert!((a - b).abs() < 1e-5, "sample {n}: {a} vs {b}");
+        }
+
+        // Retuned in place without a phase jump
+        processed.update_params(&ChannelParams { frequency_offset_hz: -10.0, ..make_clean_channel_params() }).unwrap();
+        let retuned = processed.process(&input);
+        assert!(offset_from(&retuned, 1490.0).abs() < 0.1);
+        assert!(measure_sinusoid_amplitude(&retuned[500..], 1510.0, 9600.0) < 0.01);
+
+        assert_eq!(validate_frequency_offset(&make_clean_channel_params()), Ok(()));
+        for hz in [-501.0, 501.0, f64::NAN] {
+            let params = ChannelParams { frequency_offset_hz: hz, ..make_clean_channel_params() };
+            assert_eq!(validate_frequency_offset(&params), Err("invalid_frequency_offset"));
+        }
+    }
+
+    /// Frequency of the tone in `signal` relative to `freq_hz`, from the
+    /// drift of its phase between half-second blocks
+    fn offset_from(signal: &[f32], freq_hz: f64) -> f64 {
+        const BLOCK: usize = 4800;
+        let phases: Vec<f64> = signal[500..]
+            .chunks_exact(BLOCK)
+            .enumerate()
+            .map(|(k, block)| {
+                let (mut re, mut im) = (0.0, 0.0);
+                for (i, &y) in block.iter().enumerate() {
+                    let phase = 2.0 * PI * freq_hz * (k * BLOCK + i) as f64 / 9600.0;
+                    re += y as f64 * phase.cos();
+                    im -= y as f64 * phase.sin();
+                }
+                im.atan2(re)
+            })
+            .collect();
+        let drift: f64 = phases
+            .windows(2)
+            .map(|w| (w[1] - w[0] + PI).rem_euclid(2.0 * PI) - PI)
+            .sum::<f64>()
+            / (phases.len() - 1) as f64;
+        drift * 9600.0 / (2.0 * PI * BLOCK as f64)
+    }
+
+    #[test]
+    fn test_hop_schedule_same_through_advance() {
+        let params = ChannelParams { bulk_delay_samples: 40, ..make_busy_params() };
+        let hops = [(700, 2400.0), (1500, 1500.0)];
+        let tail: Vec<f32> = (0..2000).map(|n| (n as f32 * 0.37).sin() * 0.5).collect();
+
+        let mut processed = WattersonChannel::new(params.clone(), 8);
+        let mut advanced = WattersonChannel::new(params, 8);
+        for channel in [&mut processed, &mut advanced] {
+            channel.set_hop_schedule(&hops).unwrap();
+        }
+        processed.process(&vec![0.0; 1200]);
+        advanced.advance(1200);
+        assert_eq!(processed.carrier_freq_hz(), advanced.carrier_freq_hz());
+
+        let expected = processed.process(&tail);
+        let output = advanced.process(&tail);
+        for (n, (a, b)) in output.iter().zip(&expected).enumerate() {
+            assert!((a - b).abs() < 1e-4, "sample {n}: {a} vs {b}");
+        }
+        assert_eq!(processed.carrier_freq_hz(), 1500.0);
+    }
+
+    #[test]
+    fn test_hop_schedule_validation() {
+        let mut channel = WattersonChannel::new(make_clean_channel_params(), 1);
+        channel.process(&[0.0; 100]);
+
+        for bad in [
+            &[(200, 2000.0), (200, 2400.0)][..],
+            &[(50, 2000.0)],
+            &[(200, 0.0)],
+            &[(200, 4800.0)],
+        ] {
+            assert_eq!(channel.set_hop_schedule(bad), Err("invalid_hop_schedule"));
+        }
+        assert!(channel.set_hop_schedule(&[(100, 2000.0), (300, 2400.0)]).is_ok());
+        assert!(channel.set_hop_schedule(&[]).is_ok());
+        assert!(matches!(
+            channel.audit_log().entries().last().unwrap().change,
+            AuditChange::HopSchedule { ref hops } if hops.is_empty()
+        ));
+    }
+
+    // ========================================================================
+    // TR SWITCH TESTS
+    // ========================================================================
+
+    #[test]
+    fn test_tr_schedule_mute_depth() {
+        let input = generate_tone(1800.0, 9600.0, 6000, 1.0);
+        let amplitude = |out: &[f32], range: std::ops::Range<usize>| {
+            measure_sinusoid_amplitude(&out[range], 1800.0, 9600.0)
+        };
+
+        let mut attenuated = WattersonChannel::new(make_clean_channel_params(), 3);
+        attenuated.set_tr_schedule(&[(2000, TrState::Tx)], Some(40.0), 2.0).unwrap();
+        let out = attenuated.process(&input);
+        let depth_db = 20.0 * (amplitude(&out, 2100..6000) / amplitude(&out, 500..1900)).log10();
+        assert!((depth_db + 40.0).abs() < 0.1, "depth {depth_db} dB");
+
+        let mut muted = WattersonChannel::new(make_clean_channel_params(), 3);
+        muted.set_tr_schedule(&[(2000, TrState::Tx)], None, 2.0).unwrap();
+        let out = muted.process(&input);
+        assert!(out[2100..].iter().all(|&x| x == 0.0));
+    }
+
+    #[test]
+    fn test_tr_schedule_ramp_duration() {
+        // 5 ms at 9600 Hz: 48 samples down from 1000, 48 back up from 3000
+        let input = pseudo_noise(4000, 9);
+        let mut reference = WattersonChannel::new(make_clean_channel_params(), 4);
+        let expected = reference.process(&input);
+        let mut channel = WattersonChannel::new(make_clean_channel_params(), 4);
+        channel.set_tr_schedule(&[(1000, TrState::Tx), (3000, TrState::Rx)], None, 5.0).unwrap();
+        let out = channel.process(&input);
+
+        assert_eq!(out[..1000], expected[..1000]);
+        assert!(out[1000..1048].iter().any(|&x| x != 0.0));
+        assert!(out[1048..3000].iter().all(|&x| x == 0.0));
+        // Back up through the ramp, partway there until its last sample
+        for n in 3001..3048 {
+            assert!(out[n].abs() < expected[n].abs() || expected[n] == 0.0, "sample {n}");
+        }
+        assert_eq!(out[3048..], expected[3048..]);
+    }
+
+    #[test]
+    fn test_tr_schedule_leaves_rx_burst_alone() {
+        // Transmitting either side of a burst the receiver is listening for
+        let mut input = vec![0.0; 8000];
+        input[3000..5000].copy_from_slice(&pseudo_noise(2000, 11));
+        let schedule = [(0, TrState::Tx), (1000, TrState::Rx), (7000, TrState::Tx)];
+
+        let mut reference = WattersonChannel::new(make_busy_params(), 21);
+        let expected = reference.process(&input);
+        let mut channel = WattersonChannel::new(make_busy_params(), 21);
+        channel.set_tr_schedule(&schedule, None, 3.0).unwrap();
+        let out = channel.process(&input);
+
+        assert_eq!(out[1029..7000], expected[1029..7000]);
+        // Muted ahead of it once the first ramp is done, bar the sound
+        // card's dither
+        let energy = |x: &[f32]| x.iter().map(|&v| v as f64 * v as f64).sum::<f64>();
+        assert!(energy(&out[29..1000]) < 1e-3 * energy(&expected[29..1000]));
+    }
+
+    #[test]
+    fn test_tr_schedule_state_and_validation() {
+        let mut channel = WattersonChannel::new(make_clean_channel_params(), 1);
+        assert_eq!(channel.get_state().tr_state, TrState::Rx);
+        channel.set_tr_schedule(&[(100, TrState::Tx), (300, TrState::Rx)], None, 2.0).unwrap();
+        channel.process(&[0.0; 100]);
+        assert_eq!(channel.get_state().tr_state, TrState::Tx);
+        channel.advance(200);
+        assert_eq!(channel.get_state().tr_state, TrState::Rx);
+
+        assert_eq!(channel.set_tr_schedule(&[(100, TrState::Tx)], None, 2.0), Err("invalid_tr_schedule"));
+        for ramp_ms in [-1.0, f64::NAN, 2000.0] {
+            assert_eq!(channel.set_tr_schedule(&[], None, ramp_ms), Err("invalid_tr_schedule"));
+        }
+        assert!(channel.set_tr_schedule(&[], None, 0.0).is_ok());
+        assert!(matches!(
+            channel.audit_log().entries().last().unwrap().change,
+            AuditChange::TrSchedule { ref schedule, .. } if schedule.is_empty()
+        ));
+    }
+
+    // ========================================================================
+    // INPUT CONDITIONING TESTS
+    // ========================================================================
+
+    #[test]
+    fn test_dc_block_removes_offset_spur() {
+        // A 1 kHz tone riding on a 0.2 DC offset, over whole seconds so
+        // each line falls on a bin
+        let tone_hz = 1000.0;
+        let skip = 2000;
+        let input: Vec<f32> = generate_tone(tone_hz, 9600.0, 4 * 9600 + skip, 0.3).iter().map(|x| x + 0.2).collect();
+
+        let spurs_dbc = |input_dc_block| {
+            let params = ChannelParams { input_dc_block, ..make_clean_channel_params() };
+            let output = WattersonChannel::new(params, 5).process(&input);
+            let output = &output[skip..];
+            let tone = measure_sinusoid_amplitude(output, tone_hz, 9600.0);
+            let dc = output.iter().map(|&x| x as f64).sum::<f64>().abs() / output.len() as f64;
+            let carrier = measure_sinusoid_amplitude(output, 1800.0, 9600.0);
+            (20.0 * (dc / tone).log10(), 20.0 * (carrier / tone).log10())
+        };
+
+        let (dc_off, carrier_off) = spurs_dbc(false);
+        let (dc_on, carrier_on) = spurs_dbc(true);
+        println!("DC {:.1} / {:.1} dBc, carrier {:.1} / {:.1} dBc", dc_off, dc_on, carrier_off, carrier_on);
+        assert!(dc_off > -20.0, "offset only {:.1} dBc without the blocker", dc_off);
+        assert!(dc_on < -60.0, "offset still {:.1} dBc", dc_on);
+        assert!(carrier_on < -60.0, "carrier spur {:.1} dBc", carrier_on);
+    }
+
+    #[test]
+    fn test_tilt_changes_band_edges() {
+        let edge_gain_db = |input_tilt_db, hz| {
+            let input = generate_tone(hz, 9600.0, 9600, 0.3);
+            let params = ChannelParams { input_tilt_db, ..make_clean_channel_params() };
+            let output = WattersonChannel::new(params, 6).process(&input);
+            20.0 * measure_sinusoid_amplitude(&output[500..], hz, 9600.0).log10()
+        };
+        for tilt_db in [-6.0, 4.0] {
+            let low = edge_gain_db(tilt_db, 300.0) - edge_gain_db(0.0, 300.0);
+            let high = edge_gain_db(tilt_db, 3000.0) - edge_gain_db(0.0, 3000.0);
+            println!("{} dB tilt: {:.3} dB at 300 Hz, {:.3} dB at 3000 Hz", tilt_db, low, high);
+            assert!((high - low - tilt_db).abs() < 0.05);
+            assert!((low + tilt_db / 2.0).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_conditioning_same_through_advance() {
+        // Bulk delay still holding the offset when the advance starts
+        let params = ChannelParams { input_dc_block: true, input_tilt_db: 5.0, ..make_busy_params() };
+        let input: Vec<f32> = pseudo_noise(3000, 12).iter().map(|x| x + 0.3).collect();
+
+        let mut advanced = WattersonChannel::new(params.clone(), 8);
+        let mut processed = WattersonChannel::new(params, 8);
+        advanced.process(&input);
+        processed.process(&input);
+        advanced.advance(5000);
+        processed.process(&vec![0.0; 5000]);
+        assert_eq!(advanced.process(&input), processed.process(&input));
+    }
+
+    // ========================================================================
+    // PHASE LOG TESTS
+    // ========================================================================
+
+    /// Worst distance between a tone 100 Hz above the carrier, through the
+    /// clean channel and derotated with the phase log, and the baseband
+    /// the channel had for it: unit fading, so the mixed-down input,
+    /// delayed by the filters. The edges the derotation's filter sees
+    /// are left out.
+    fn clean_tone_derotation_error(carrier_hz: f64, decimation: u64) -> f64 {
+        let params = ChannelParams { carrier_freq_hz: carrier_hz, ..make_clean_channel_params() };
+        let mut channel = WattersonChannel::new(params, 5);
+        channel.set_phase_log(Some(PhaseLog::new(decimation).unwrap()));
+
+        let (offset_hz, amplitude, theta) = (100.0, 0.5, 0.7);
+        let w = |n: usize| 2.0 * PI * n as f64 / 9600.0;
+        let input: Vec<f32> =
+            (0..4800).map(|n| (amplitude * (w(n) * (carrier_hz + offset_hz) + theta).cos()) as f32).collect();
+        // Skip a block so the log doesn't start at sample 0
+        channel.process(&input[..1000]);
+        let output: Vec<f64> = channel.process(&input[1000..]).into_iter().map(f64::from).collect();
+
+        let entries: Vec<PhaseEntry> = channel.phase_log_mut().unwrap().entries().copied().collect();
+        assert_eq!(entries.len(), 4800usize.div_ceil(decimation as usize));
+        let iq = phase_log::derotate(&entries, 1000, &output, 9600.0).unwrap();
+        iq.iter()
+            .enumerate()
+            .take(iq.len() - LPF_TAPS)
+            .skip(LPF_TAPS)
+            .map(|(n, &(i, q))| {
+                let phase = w(1000 + n - LPF_TAPS / 2) * offset_hz + theta;
+                (i - amplitude * phase.cos()).hypot(q - amplitude * phase.sin())
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_phase_log_derotates_clean_output_to_baseband() {
+        for (carrier_hz, decimation) in [(1800.0, 1), (1800.0, 16), (2400.0, 7)] {
+            let error = clean_tone_derotation_error(carrier_hz, decimation);
+            assert!(error < 0.01, "{carrier_hz} Hz, decimation {decimation}: off by {error}");
+        }
+    }
+
+    #[test]
+    fn test_phase_log_off_by_default_and_not_in_bypass() {
+        let mut channel = WattersonChannel::new(make_clean_channel_params(), 1);
+        channel.process(&[0.1; 64]);
+        assert!(channel.phase_log_mut().is_none());
+
+        channel.set_phase_log(Some(PhaseLog::new(1).unwrap()));
+        channel.advance(100);
+        assert_eq!(channel.phase_log_mut().unwrap().entries().count(), 0);
+        channel.process(&[0.1; 10]);
+        let first = channel.phase_log_mut().unwrap().entries().next().unwrap().sample_index;
+        assert_eq!(first, 164);
+
+        let mut bypassed = WattersonChannel::new(make_impaired_params(true), 1);
+        bypassed.set_phase_log(Some(PhaseLog::new(1).unwrap()));
+        bypassed.process(&[0.1; 10]);
+        assert_eq!(bypassed.phase_log_mut().unwrap().entries().count(), 0);
+    }
+
+    /// Error of the f32 channel against the f64 one, dB relative to the
+    /// f64 output's power, over 10 s of broadband noise
+    ///
+    /// The noise draws are the same in both, so they cancel in the
+    /// difference; the SNR is high so they don't make up the reference.
+    fn f32_error_dbc(params: ChannelParams) -> f64 {
+        let input: Vec<f64> = pseudo_noise(96_000, 7).into_iter().map(|x| x as f64).collect();
+        let run = |precision| {
+            WattersonChannel::new(ChannelParams { precision, ..params.clone() }, 1980).process_f64(&input)
+        };
+        let (exact, single) = (run(Precision::F64), run(Precision::F32));
+        let error: f64 = exact.iter().zip(&single).map(|(a, b)| (a - b).powi(2)).sum();
+        let carrier: f64 = exact.iter().map(|a| a * a).sum();
+        10.0 * (error / carrier).log10()
+    }
+
+    #[test]
+    fn test_f32_tracks_f64() {
+        let cases = [
+            ("AWGN", make_awgn_only_params(40.0)),
+            ("good", ChannelParams { delay_spread_samples: 5, ..make_fading_only_params(0.1) }),
+            ("poor", ChannelParams { delay_spread_samples: 19, ..make_fading_only_params(1.0) }),
+            ("flutter", ChannelParams { delay_spread_samples: 5, ..make_fading_only_params(10.0) }),
+            ("quarter-rate carrier", ChannelParams { carrier_freq_hz: 2400.0, ..make_multipath_only_params(10) }),
+            // A day into the realization, where the oscillator phases are
+            // far out
+            ("warm start", ChannelParams { start_at_time_s: 86_400.0, ..make_busy_params() }),
+        ];
+        for (name, params) in cases {
+            let params = ChannelParams { snr_db: 40.0, output_bits: 0, ..params };
+            let error_dbc = f32_error_dbc(params);
+            assert!(error_dbc < -80.0, "{name}: f32 error {error_dbc:.1} dBc");
+        }
+    }
+
+    #[test]
+    fn test_precision_is_immutable() {
+        let params = make_busy_params();
+        let mut channel = WattersonChannel::new(params.clone(), 1);
+        let single = ChannelParams { precision: Precision::F32, ..params };
+        assert_eq!(channel.update_params(&single), Err("immutable_param_changed"));
+    }
+
+    #[test]
+    fn test_process_iq_noise_at_configured_snr() {
+        // A 0.5 envelope through an AWGN channel: the signal comes through
+        // unchanged, with half the complex noise power in each of I and Q
+        let mut channel = WattersonChannel::new(make_awgn_only_params(10.0), 42);
+        let input = vec![(0.5, 0.0); 48000];
+        let output = channel.process_iq(&input).unwrap();
+        assert_eq!(output.len(), input.len());
+        assert_eq!(channel.sample_index, 48000);
+
+        let n = output.len() as f64;
+        let mean_i = output.iter().map(|&(i, _)| i).sum::<f64>() / n;
+        let mean_q = output.iter().map(|&(_, q)| q).sum::<f64>() / n;
+        assert!((mean_i - 0.5).abs() < 0.005 && mean_q.abs() < 0.005, "({mean_i}, {mean_q})");
+        let noise_i = output.iter().map(|&(i, _)| (i - 0.5).powi(2)).sum::<f64>() / n;
+        let noise_q = output.iter().map(|&(_, q)| q * q).sum::<f64>() / n;
+        let snr_db = 10.0 * (0.25 / (noise_i + noise_q)).log10();
+        assert!((snr_db - 10.0).abs() < 0.2, "SNR {snr_db:.2} dB");
+        assert!((noise_i / noise_q - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_process_iq_noise_follows_noise_bandwidth() {
+        // Shaped to 3 kHz, the passband noise is 4.8 / 3 times as dense
+        // inside the band as white noise of the same power, and the
+        // complex noise takes that much more power to match: the same
+        // draws, scaled
+        let noise_power = |noise_bandwidth_hz| {
+            let params = ChannelParams { noise_bandwidth_hz, ..make_awgn_only_params(10.0) };
+            let mut channel = WattersonChannel::new(params, 42);
+            let output = channel.process_iq(&vec![(0.0, 0.0); 48000]).unwrap();
+            output.iter().map(|&(i, q)| i * i + q * q).sum::<f64>() / output.len() as f64
+        };
+        let white = noise_power(0.0);
+        assert!((white / (2.0 * noise_power_for_snr(10.0)) - 1.0).abs() < 0.03, "{white}");
+        let ratio = noise_power(3000.0) / white;
+        assert!((ratio - 1.6).abs() < 1e-9, "shaped over white {ratio}");
+    }
+
+    #[test]
+    fn test_process_iq_shares_fading_with_passband() {
+        // Whichever path the first block goes through, the second sees
+        // the same fading
+        let params = make_fading_only_params(2.0);
+        let tone: Vec<f32> = generate_tone(1800.0, 9600.0, 4800, 0.5);
+        let probe = vec![(1.0, 0.0); 4800];
+
+        let mut passband_first = WattersonChannel::new(params.clone(), 11);
+        passband_first.process(&tone);
+        let after_passband = passband_first.process_iq(&probe).unwrap();
+        let mut iq_first = WattersonChannel::new(params.clone(), 11);
+        iq_first.process_iq(&probe).unwrap();
+        let after_iq = iq_first.process_iq(&probe).unwrap();
+        for (a, b) in after_passband.iter().zip(&after_iq) {
+            assert!((a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3, "{a:?} vs {b:?}");
+        }
+
+        // A flat single-path channel hands the tap through: its power
+        // averages to one, as on the passband path
+        let mut channel = WattersonChannel::new(params, 12);
+        let faded = channel.process_iq(&vec![(1.0, 0.0); 96000]).unwrap();
+        let power = faded.iter().map(|&(i, q)| i * i + q * q).sum::<f64>() / faded.len() as f64;
+        assert!((power - 1.0).abs() < 0.3, "mean tap power {power:.3}");
+
+        // The carrier kept stepping, so the passband path picks up where
+        // a passband-only channel would be
+        let mut passband_only = WattersonChannel::new(make_clean_channel_params(), 5);
+        let mut switched = WattersonChannel::new(make_clean_channel_params(), 5);
+        passband_only.process(&vec![0.0; 1001]);
+        switched.process_iq(&vec![(0.0, 0.0); 1001]).unwrap();
+        assert_eq!(passband_only.carrier_phase.to_bits(), switched.carrier_phase.to_bits());
+    }
+
+    #[test]
+    fn test_process_iq_echo_and_refusals() {
+        // An impulse comes out on both paths, as far apart as on the
+        // passband path (the delay line reads the slot after the one it
+        // writes: delay_spread_samples - 1)
+        let params = ChannelParams { snr_db: 100.0, ..make_multipath_only_params(10) };
+        let mut channel = WattersonChannel::new(params, 3);
+        let mut input = vec![(0.0, 0.0); 32];
+        input[0] = (1.0, 0.0);
+        let output = channel.process_iq(&input).unwrap();
+        let magnitude: Vec<f64> = output.iter().map(|&(i, q)| (i * i + q * q).sqrt()).collect();
+        assert!((magnitude[0] - FRAC_1_SQRT_2).abs() < 1e-3, "{magnitude:?}");
+        assert!((magnitude[9] - FRAC_1_SQRT_2).abs() < 1e-3, "{magnitude:?}");
+        assert!(magnitude.iter().enumerate().all(|(n, &m)| n == 0 || n == 9 || m < 1e-3));
+
+        // Swapped sidebands conjugate the envelope
+        let inverted = ChannelParams { sideband_inversion: true, ..make_clean_channel_params() };
+        let mut channel = WattersonChannel::new(inverted, 3);
+        let output = channel.process_iq(&[(0.3, 0.4)]).unwrap();
+        assert!((output[0].0 - 0.3).abs() < 1e-3 && (output[0].1 + 0.4).abs() < 1e-3, "{output:?}");
+
+        for params in [
+            ChannelParams { bulk_delay_samples: 40, ..make_clean_channel_params() },
+            ChannelParams { input_dc_block: true, ..make_clean_channel_params() },
+            ChannelParams { fractional_delay_samples: 0.25, ..make_clean_channel_params() },
+            ChannelParams { sample_rate_offset_ppm: 50.0, ..make_clean_channel_params() },
+            ChannelParams { output_bits: 16, ..make_clean_channel_params() },
+        ] {
+            let mut channel = WattersonChannel::new(params, 3);
+            assert_eq!(channel.process_iq(&[(0.5, 0.0)]), Err("unsupported_for_iq"));
+        }
+
+        // Bypass hands the block straight back
+        let bypassed = ChannelParams { bypass: true, ..make_busy_params() };
+        let mut channel = WattersonChannel::new(bypassed, 3);
+        assert_eq!(channel.process_iq(&[(0.5, -0.25)]), Ok(vec![(0.5, -0.25)]));
+    }
+}
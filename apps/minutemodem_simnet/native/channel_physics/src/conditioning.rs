@@ -0,0 +1,260 @@
+//! Transmit-side input conditioning
+//!
+//! Optional clean-up of the audio the channel is fed, ahead of the
+//! mix-down: a DC blocker for sound cards and modems that sit off zero,
+//! and a tilt filter to take out (or put in) a slope across the voice band,
+//! the way a transmitter's audio chain might. Both are off by default.
+//!
+//! The DC blocker is the usual first-order high-pass,
+//! y[n] = x[n] - x[n-1] + R y[n-1], cornered at DC_BLOCK_HZ. The tilt filter is a first-order
+//! shelf pivoting on the geometric middle of TILT_LOW_HZ..TILT_HIGH_HZ
+//! (about 950 Hz): `tilt_db` is how much higher its gain is at 3000 Hz
+//! than at 300 Hz, split evenly either side of 0 dB, so a positive tilt
+//! lifts the top of the band and cuts the bottom by half of it each. A
+//! first-order slope can't exceed 20 dB over the decade; MAX_TILT_DB
+//! stays well short of that.
+
+use std::f64::consts::PI;
+
+/// Corner of the DC blocker, Hz: -0.02 dB at 300 Hz
+pub const DC_BLOCK_HZ: f64 = 20.0;
+
+/// Band edges the tilt is specified across, Hz
+pub const TILT_LOW_HZ: f64 = 300.0;
+pub const TILT_HIGH_HZ: f64 = 3000.0;
+
+/// Largest tilt either way, dB across TILT_LOW_HZ..TILT_HIGH_HZ
+pub const MAX_TILT_DB: f64 = 12.0;
+
+/// Check input_tilt_db from ChannelParams against the sample rate
+///
+/// A tilt needs TILT_HIGH_HZ below Nyquist.
+pub fn validate(sample_rate: u32, tilt_db: f64) -> Result<(), &'static str> {
+    let in_range = tilt_db.is_finite() && tilt_db.abs() <= MAX_TILT_DB;
+    if !in_range || (tilt_db != 0.0 && sample_rate as f64 <= 2.0 * TILT_HIGH_HZ) {
+        return Err("invalid_input_tilt");
+    }
+    Ok(())
+}
+
+/// First-order IIR section: y[n] = b0 x[n] + b1 x[n-1] - a1 y[n-1]
+#[derive(Debug, Clone)]
+struct FirstOrder {
+    b0: f64,
+    b1: f64,
+    a1: f64,
+    x1: f64,
+    y1: f64,
+}
+
+impl FirstOrder {
+    fn new(b0: f64, b1: f64, a1: f64) -> Self {
+        Self { b0, b1, a1, x1: 0.0, y1: 0.0 }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.b1 * self.x1 - self.a1 * self.y1;
+        self.x1 = x;
+        self.y1 = y;
+        y
+    }
+
+    /// First output for a zero input, after which it decays by -a1 a
+    /// sample
+    fn zero_input_start(&self) -> f64 {
+        self.b1 * self.x1 - self.a1 * self.y1
+    }
+
+    /// process() on `num_samples` of the input c·r^k, k = 0, 1, ..., in
+    /// closed form (c = 0 for zeros)
+    ///
+    /// From the second sample on the output is A p^k + B r^k, p = -a1
+    /// being the section's own pole and B r^k the part that follows the
+    /// input, B (r - p) = c (b0 r + b1). That needs r ≠ p, which holds for
+    /// a DC blocker feeding a shelf: their poles are an octave or more
+    /// apart.
+    fn skip_geometric(&mut self, num_samples: usize, c: f64, r: f64) {
+        if num_samples == 0 {
+            return;
+        }
+        let p = -self.a1;
+        let b = if c == 0.0 { 0.0 } else { c * (self.b0 * r + self.b1) / (r - p) };
+        let a = self.b0 * c + self.zero_input_start() - b;
+        let last = (num_samples - 1).min(i32::MAX as usize) as i32;
+        self.y1 = a * p.powi(last) + b * r.powi(last);
+        self.x1 = c * r.powi(last);
+    }
+
+    fn clear(&mut self) {
+        self.x1 = 0.0;
+        self.y1 = 0.0;
+    }
+
+    /// Magnitude response at `hz`
+    fn gain_at(&self, hz: f64, sample_rate: f64) -> f64 {
+        let w = 2.0 * PI * hz / sample_rate;
+        let (c, s) = (w.cos(), w.sin());
+        // (b0 + b1 e^-jw) / (1 + a1 e^-jw)
+        let num = (self.b0 + self.b1 * c).hypot(self.b1 * s);
+        let den = (1.0 + self.a1 * c).hypot(self.a1 * s);
+        num / den
+    }
+}
+
+/// First-order high-pass cornered at DC_BLOCK_HZ
+fn dc_blocker(sample_rate: f64) -> FirstOrder {
+    let r = 1.0 - 2.0 * PI * DC_BLOCK_HZ / sample_rate;
+    FirstOrder::new(1.0, -1.0, -r)
+}
+
+/// Shelf with zero and pole `ratio` apart around the pivot, gains at the
+/// band edges balanced about 0 dB
+///
+/// Bilinear transform of (s + w0/√ratio) / (s + w0·√ratio), prewarped at
+/// the pivot w0; a ratio above 1 rises with frequency.
+fn shelf(ratio: f64, sample_rate: f64) -> FirstOrder {
+    let f0 = (TILT_LOW_HZ * TILT_HIGH_HZ).sqrt();
+    let w0 = 2.0 * PI * f0;
+    let k = w0 / (PI * f0 / sample_rate).tan();
+    let (zero, pole) = (w0 / ratio.sqrt(), w0 * ratio.sqrt());
+    let a0 = k + pole;
+    let mut section = FirstOrder::new((k + zero) / a0, (zero - k) / a0, (pole - k) / a0);
+    let g = 1.0 / (section.gain_at(TILT_LOW_HZ, sample_rate) * section.gain_at(TILT_HIGH_HZ, sample_rate)).sqrt();
+    section.b0 *= g;
+    section.b1 *= g;
+    section
+}
+
+/// Shelf whose gain at TILT_HIGH_HZ is `tilt_db` over that at TILT_LOW_HZ
+///
+/// The tilt rises monotonically with the shelf's ratio, so the ratio is
+/// found by bisection on its log.
+fn tilt_filter(tilt_db: f64, sample_rate: f64) -> FirstOrder {
+    let tilt_of = |section: &FirstOrder| {
+        20.0 * (section.gain_at(TILT_HIGH_HZ, sample_rate) / section.gain_at(TILT_LOW_HZ, sample_rate)).log10()
+    };
+    let (mut lo, mut hi) = (-20.0_f64, 20.0_f64);
+    for _ in 0..60 {
+        let mid = 0.5 * (lo + hi);
+        if tilt_of(&shelf(mid.exp(), sample_rate)) < tilt_db {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    shelf((0.5 * (lo + hi)).exp(), sample_rate)
+}
+
+/// DC blocker then tilt filter, either or both of which may be off
+#[derive(Debug, Clone)]
+pub struct InputConditioning {
+    dc_block: Option<FirstOrder>,
+    tilt: Option<FirstOrder>,
+}
+
+impl InputConditioning {
+    /// Conditioning for ChannelParams' input_dc_block and input_tilt_db
+    /// (0.0 for no tilt; see validate)
+    pub fn new(sample_rate: u32, dc_block: bool, tilt_db: f64) -> Self {
+        let sample_rate = sample_rate as f64;
+        Self {
+            dc_block: dc_block.then(|| dc_blocker(sample_rate)),
+            tilt: (tilt_db != 0.0).then(|| tilt_filter(tilt_db, sample_rate)),
+        }
+    }
+
+    pub fn is_bypassed(&self) -> bool {
+        self.dc_block.is_none() && self.tilt.is_none()
+    }
+
+    /// Condition one input sample
+    pub fn process(&mut self, x: f64) -> f64 {
+        let x = match &mut self.dc_block {
+            Some(section) => section.process(x),
+            None => x,
+        };
+        match &mut self.tilt {
+            Some(section) => section.process(x),
+            None => x,
+        }
+    }
+
+    /// Leave the filters as process() on `num_samples` zeros would
+    pub fn skip(&mut self, num_samples: usize) {
+        // What the tilt filter sees: the DC blocker's decaying tail
+        let (c, r) = match &mut self.dc_block {
+            Some(section) => {
+                let tail = (section.zero_input_start(), -section.a1);
+                section.skip_geometric(num_samples, 0.0, 0.0);
+                tail
+            }
+            None => (0.0, 0.0),
+        };
+        if let Some(section) = &mut self.tilt {
+            section.skip_geometric(num_samples, c, r);
+        }
+    }
+
+    /// Forget the input so far
+    pub fn clear(&mut self) {
+        for section in self.dc_block.iter_mut().chain(self.tilt.iter_mut()) {
+            section.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tilt_across_band_edges() {
+        for sample_rate in [8000.0, 9600.0, 48_000.0] {
+            for tilt_db in [-12.0, -3.0, 1.5, 6.0, 12.0] {
+                let section = tilt_filter(tilt_db, sample_rate);
+                let low = 20.0 * section.gain_at(TILT_LOW_HZ, sample_rate).log10();
+                let high = 20.0 * section.gain_at(TILT_HIGH_HZ, sample_rate).log10();
+                assert!((high - low - tilt_db).abs() < 1e-6, "{} Hz, {} dB: {} to {}", sample_rate, tilt_db, low, high);
+                assert!((high + low).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dc_blocker_response() {
+        let section = dc_blocker(9600.0);
+        assert!(section.gain_at(0.0, 9600.0) < 1e-12);
+        assert!(20.0 * section.gain_at(300.0, 9600.0).log10() > -0.05);
+    }
+
+    #[test]
+    fn test_skip_matches_processing_zeros() {
+        let mut primed = InputConditioning::new(9600, true, 6.0);
+        for n in 0..50 {
+            primed.process((n as f64 * 0.7).sin() + 0.3);
+        }
+        for gap in [1, 2, 200] {
+            let mut skipped = primed.clone();
+            let mut processed = primed.clone();
+            skipped.skip(gap);
+            for _ in 0..gap {
+                processed.process(0.0);
+            }
+            for n in 0..20 {
+                let x = (n as f64 * 1.3).cos();
+                let (a, b) = (skipped.process(x), processed.process(x));
+                assert!((a - b).abs() < 1e-12, "after {} zeros, sample {}: {} vs {}", gap, n, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(9600, 0.0).is_ok());
+        assert!(validate(8000, -MAX_TILT_DB).is_ok());
+        assert!(validate(6000, 0.0).is_ok());
+        assert_eq!(validate(6000, 3.0), Err("invalid_input_tilt"));
+        assert_eq!(validate(9600, MAX_TILT_DB + 0.5), Err("invalid_input_tilt"));
+        assert_eq!(validate(9600, f64::NAN), Err("invalid_input_tilt"));
+    }
+}
@@ -0,0 +1,293 @@
+//! Correlated channel sets: N receiver outputs of one fading environment
+//!
+//! Models spatially separated antennas that see correlated but not
+//! identical fading. Each member is an ordinary WattersonChannel with its
+//! own seed; its taps supply one independent complex Gaussian process per
+//! tap. Member i then fades with
+//!
+//!   h_i = Σ_j L_ij · z_j        (separately for tap0 and tap1)
+//!
+//! where z_j is member j's own fading and L is the lower Cholesky factor
+//! of the Gaussian correlation matrix. Because the members have
+//! independent seeds, their noise is independent too.
+//!
+//! The requested matrix is the *envelope* correlation: the correlation
+//! coefficient between members' fading power |h|². For complex Gaussian
+//! fading that is |ρ|², so the Gaussian processes are mixed with
+//! ρ_ij = √c_ij.
+//!
+//! With one member L = [1] and the output is exactly that of a lone
+//! channel built from the same parameters and seed.
+
+use crate::channel::{self, ChannelParams, TapGains, WattersonChannel};
+use crate::conditioning;
+use crate::drift;
+
+/// A set's members, by slab id in output order, and their mixing matrix
+pub struct CorrelatedSet {
+    pub members: Vec<u64>,
+    /// Lower-triangular Cholesky factor of the Gaussian correlation
+    pub mixing: Vec<Vec<f64>>,
+}
+
+impl CorrelatedSet {
+    /// Bytes held by the member list and the mixing matrix
+    pub fn memory_bytes(&self) -> usize {
+        self.members.capacity() * std::mem::size_of::<u64>()
+            + self.mixing.capacity() * std::mem::size_of::<Vec<f64>>()
+            + self.mixing.iter().map(|row| row.capacity() * std::mem::size_of::<f64>()).sum::<usize>()
+    }
+}
+
+/// Lower Cholesky factor of the Gaussian correlation behind an envelope
+/// correlation matrix
+///
+/// The matrix must be square and symmetric with a unit diagonal and
+/// entries in [0, 1], and √c_ij must be positive semi-definite (fully
+/// correlated members are allowed).
+pub fn mixing_matrix(envelope_correlation: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, &'static str> {
+    let n = envelope_correlation.len();
+    if n == 0 {
+        return Err("invalid_correlation");
+    }
+    for (i, row) in envelope_correlation.iter().enumerate() {
+        if row.len() != n || row[i] != 1.0 {
+            return Err("invalid_correlation");
+        }
+        for (j, &c) in row.iter().enumerate() {
+            if !(0.0..=1.0).contains(&c) || c != envelope_correlation[j][i] {
+                return Err("invalid_correlation");
+            }
+        }
+    }
+
+    let rho = |i: usize, j: usize| envelope_correlation[i][j].sqrt();
+    let mut l = vec![vec![0.0; n]; n];
+    for j in 0..n {
+        let d = rho(j, j) - (0..j).map(|k| l[j][k] * l[j][k]).sum::<f64>();
+        if d < -1e-9 {
+            return Err("invalid_correlation");
+        }
+        l[j][j] = d.max(0.0).sqrt();
+        for i in j + 1..n {
+            let s = rho(i, j) - (0..j).map(|k| l[i][k] * l[j][k]).sum::<f64>();
+            // A zero pivot means member j copies earlier ones; nothing of
+            // it is left for the rest to share
+            l[i][j] = if l[j][j] > 1e-12 { s / l[j][j] } else { 0.0 };
+        }
+    }
+
+    // A zero pivot only works out if the rest of the matrix agrees with it
+    for i in 0..n {
+        for j in 0..=i {
+            let r: f64 = (0..=j).map(|k| l[i][k] * l[j][k]).sum();
+            if (r - rho(i, j)).abs() > 1e-6 {
+                return Err("invalid_correlation");
+            }
+        }
+    }
+    Ok(l)
+}
+
+/// Parameters and seed for member `index`
+///
+/// Member 0 is the channel the set's parameters and seed describe. The
+/// others get seeds (and fading and noise seeds, where given) derived
+/// from those, so every member fades and adds noise independently before
+/// mixing.
+pub fn member_params(params: &ChannelParams, seed: u64, index: usize) -> (ChannelParams, u64) {
+    let derive = |s: u64| s.wrapping_add((index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    let params = ChannelParams {
+        fading_seed: params.fading_seed.map(derive),
+        noise_seed: params.noise_seed.map(derive),
+        ..params.clone()
+    };
+    (params, derive(seed))
+}
+
+/// Build the member channels for a set
+///
+/// start_in_fade_db isn't supported: it would pick the start from one
+/// member's unmixed fading.
+pub fn create_members(
+    params: &ChannelParams,
+    envelope_correlation: &[Vec<f64>],
+    seed: u64,
+) -> Result<(Vec<WattersonChannel>, Vec<Vec<f64>>), &'static str> {
+    if params.start_in_fade_db.is_some() {
+        return Err("invalid_warm_start");
+    }
+    channel::validate_warm_start(params)?;
+    channel::validate_tap_gains(params)?;
+    channel::validate_frequency_offset(params)?;
+    channel::validate_noise_bandwidth(params)?;
+    channel::validate_impulse_noise(params)?;
+    drift::validate(params.sample_rate_offset_ppm)?;
+    conditioning::validate(params.sample_rate, params.input_tilt_db)?;
+    let mixing = mixing_matrix(envelope_correlation)?;
+
+    let mut members: Vec<WattersonChannel> = (0..mixing.len())
+        .map(|i| {
+            let (params, seed) = member_params(params, seed, i);
+            WattersonChannel::new(params, seed)
+        })
+        .collect();
+
+    // Mixed fading power averages Σ_j L_ij² times member j's mean
+    let own_means: Vec<f64> = members.iter().map(WattersonChannel::fade_mean_power).collect();
+    for (member, row) in members.iter_mut().zip(&mixing) {
+        let mean = row.iter().zip(&own_means).map(|(l, m)| l * l * m).sum();
+        member.set_fade_mean_power(mean);
+    }
+    Ok((members, mixing))
+}
+
+/// Mix the members' own fading (`own[j]`, one TapGains per sample) into
+/// the fading each member applies
+pub fn mix(mixing: &[Vec<f64>], own: &[Vec<TapGains>]) -> Vec<Vec<TapGains>> {
+    let len = own.first().map_or(0, Vec::len);
+    mixing
+        .iter()
+        .map(|row| {
+            (0..len)
+                .map(|n| {
+                    let mut h0 = (0.0, 0.0);
+                    let mut h1 = (0.0, 0.0);
+                    for (&l, z) in row.iter().zip(own) {
+                        if l == 0.0 {
+                            continue;
+                        }
+                        let ((a_i, a_q), (b_i, b_q)) = z[n];
+                        h0 = (h0.0 + l * a_i, h0.1 + l * a_q);
+                        h1 = (h1.0 + l * b_i, h1.1 + l * b_q);
+                    }
+                    (h0, h1)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Run one input block through every member (in mixing order), returning
+/// an output per member
+pub fn process(members: &mut [WattersonChannel], mixing: &[Vec<f64>], input: &[f64]) -> Vec<Vec<f64>> {
+    let own: Vec<Vec<TapGains>> = members.iter_mut().map(|m| m.next_tap_gains(input.len())).collect();
+    members
+        .iter_mut()
+        .zip(mix(mixing, &own))
+        .map(|(member, gains)| member.process_f64_with_gains(input, &gains))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn set_params(doppler_bandwidth_hz: f64) -> ChannelParams {
+        ChannelParams {
+            doppler_bandwidth_hz,
+            snr_db: 30.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
+        }
+    }
+
+    fn tone(num_samples: usize) -> Vec<f64> {
+        (0..num_samples).map(|n| 0.5 * (2.0 * PI * 1500.0 * n as f64 / 9600.0).sin()).collect()
+    }
+
+    fn pearson(a: &[f64], b: &[f64]) -> f64 {
+        let n = a.len() as f64;
+        let (ma, mb) = (a.iter().sum::<f64>() / n, b.iter().sum::<f64>() / n);
+        let cov: f64 = a.iter().zip(b).map(|(x, y)| (x - ma) * (y - mb)).sum();
+        let va: f64 = a.iter().map(|x| (x - ma).powi(2)).sum();
+        let vb: f64 = b.iter().map(|y| (y - mb).powi(2)).sum();
+        cov / (va * vb).sqrt()
+    }
+
+    #[test]
+    fn test_single_member_matches_lone_channel() {
+        for params in [set_params(1.0), ChannelParams { delay_spread_samples: 10, bulk_delay_samples: 40, ..set_params(2.0) }] {
+            let (mut members, mixing) = create_members(&params, &[vec![1.0]], 77).unwrap();
+            let mut lone = WattersonChannel::new(params, 77);
+
+            let input = tone(4800);
+            for _ in 0..3 {
+                let out = process(&mut members, &mixing, &input);
+                assert_eq!(out.len(), 1);
+                assert_eq!(out[0], lone.process_f64(&input));
+            }
+            assert_eq!(members[0].get_state().sample_index, lone.get_state().sample_index);
+        }
+    }
+
+    #[test]
+    fn test_envelope_correlation_matches_request() {
+        let requested = vec![
+            vec![1.0, 0.8, 0.6],
+            vec![0.8, 1.0, 0.7],
+            vec![0.6, 0.7, 1.0],
+        ];
+        // Many Doppler periods, sampled well apart, for a tight estimate
+        let params = set_params(20.0);
+        let mut power = vec![Vec::new(); 3];
+        for seed in 0..4 {
+            let (mut members, mixing) = create_members(&params, &requested, seed).unwrap();
+            let own: Vec<Vec<TapGains>> = members.iter_mut().map(|m| m.next_tap_gains(9600 * 20)).collect();
+            for (i, gains) in mix(&mixing, &own).into_iter().enumerate() {
+                power[i].extend(gains.iter().step_by(48).map(|((h_i, h_q), _)| h_i * h_i + h_q * h_q));
+            }
+        }
+
+        for i in 0..3 {
+            for j in i + 1..3 {
+                let r = pearson(&power[i], &power[j]);
+                assert!(
+                    (r - requested[i][j]).abs() < 0.05,
+                    "members {} and {}: envelope correlation {:.3}, asked for {}",
+                    i, j, r, requested[i][j]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_member_noise_is_independent() {
+        // Fully correlated fading, so any output correlation is the noise's
+        let params = ChannelParams { snr_db: 0.0, ..set_params(0.0) };
+        let (mut members, mixing) = create_members(&params, &[vec![1.0, 1.0], vec![1.0, 1.0]], 3).unwrap();
+        let out = process(&mut members, &mixing, &vec![0.0; 48_000]);
+        let r = pearson(&out[0], &out[1]);
+        assert!(r.abs() < 0.02, "noise correlation {}", r);
+    }
+
+    #[test]
+    fn test_fully_correlated_members_fade_together() {
+        let (mut members, mixing) = create_members(&set_params(1.0), &[vec![1.0, 1.0], vec![1.0, 1.0]], 9).unwrap();
+        let own: Vec<Vec<TapGains>> = members.iter_mut().map(|m| m.next_tap_gains(1000)).collect();
+        let mixed = mix(&mixing, &own);
+        assert_eq!(mixed[0], mixed[1]);
+        assert_eq!(mixed[0], own[0]);
+    }
+
+    #[test]
+    fn test_rejects_bad_matrices() {
+        let bad = [
+            vec![],
+            vec![vec![1.0, 0.5]],
+            vec![vec![1.0, 0.5], vec![0.4, 1.0]],
+            vec![vec![0.9, 0.5], vec![0.5, 1.0]],
+            vec![vec![1.0, 1.5], vec![1.5, 1.0]],
+            vec![vec![1.0, f64::NAN], vec![f64::NAN, 1.0]],
+            // Pairwise fine, jointly impossible
+            vec![vec![1.0, 1.0, 0.0], vec![1.0, 1.0, 1.0], vec![0.0, 1.0, 1.0]],
+        ];
+        for m in &bad {
+            assert_eq!(mixing_matrix(m).err(), Some("invalid_correlation"), "{:?}", m);
+        }
+
+        let warm = ChannelParams { start_in_fade_db: Some(-10.0), ..set_params(1.0) };
+        assert_eq!(create_members(&warm, &[vec![1.0]], 1).err(), Some("invalid_warm_start"));
+    }
+}
@@ -0,0 +1,242 @@
+//! Sound card clock drift
+//!
+//! The transmitting and receiving sound cards run from different crystals,
+//! typically within ±50 ppm of nominal each, so the receiver takes slightly
+//! more or fewer samples per second of signal than the transmitter made.
+//! Over a minute at 9600 Hz, 60 ppm is 35 samples: the receiver gains or
+//! loses whole symbols however well it tracks the timing within one.
+//!
+//! ClockDrift resamples the channel's output onto the receiver's clock.
+//! With the receiver fast by `ppm`, output sample k is the input
+//! interpolated at k / (1 + ppm * 1e-6), so a positive offset gives more
+//! output samples than input and a negative one fewer. Positions are
+//! computed from k directly rather than accumulated, so the count after N
+//! inputs is exact and doesn't depend on how the input was blocked.
+//!
+//! The interpolator is a Hann-windowed sinc, TAPS long, tabulated at
+//! PHASES fractional positions with linear interpolation between them.
+//! Across the 300-3000 Hz voice band at 9600 Hz it is flat to well under
+//! 0.01 dB; it adds HALF_TAPS samples of delay.
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::sync::OnceLock;
+
+/// Interpolator length, input samples
+pub const TAPS: usize = 32;
+
+/// Delay the interpolator adds, samples
+pub const HALF_TAPS: usize = TAPS / 2;
+
+/// Fractional positions in the coefficient table
+const PHASES: usize = 256;
+
+/// Largest clock offset accepted, ppm
+pub const MAX_OFFSET_PPM: f64 = 1000.0;
+
+/// Check a sample_rate_offset_ppm before building a channel
+pub fn validate(offset_ppm: f64) -> Result<(), &'static str> {
+    if !(offset_ppm.is_finite() && offset_ppm.abs() <= MAX_OFFSET_PPM) {
+        return Err("invalid_sample_rate_offset");
+    }
+    Ok(())
+}
+
+/// Resampler from the transmitter's sample clock to the receiver's
+#[derive(Clone)]
+pub struct ClockDrift {
+    /// Input samples per output sample
+    step: f64,
+    /// coeffs[p * TAPS + j]: weight of tap j at fractional position p / PHASES
+    coeffs: &'static [f64],
+    /// The last TAPS input samples, oldest first
+    history: VecDeque<f64>,
+    /// Input samples taken so far
+    consumed: u64,
+    /// Output samples produced so far
+    produced: u64,
+}
+
+impl ClockDrift {
+    pub fn new(offset_ppm: f64) -> Self {
+        Self {
+            step: 1.0 / (1.0 + offset_ppm * 1e-6),
+            coeffs: table(),
+            history: std::iter::repeat_n(0.0, TAPS).collect(),
+            consumed: 0,
+            produced: 0,
+        }
+    }
+
+    /// Output samples produced so far
+    pub fn produced(&self) -> u64 {
+        self.produced
+    }
+
+    /// Bytes held by the input history (the table is shared)
+    pub fn memory_bytes(&self) -> usize {
+        self.history.capacity() * std::mem::size_of::<f64>()
+    }
+
+    /// Output minus input samples so far
+    pub fn slip_samples(&self) -> i64 {
+        self.produced as i64 - self.consumed as i64
+    }
+
+    /// Forget the input history (as after a long silence), keeping the
+    /// clock position
+    pub fn clear(&mut self) {
+        self.history.iter_mut().for_each(|x| *x = 0.0);
+    }
+
+    /// Input position of output `k`, before the interpolator's delay
+    #[inline]
+    fn position(&self, k: u64) -> f64 {
+        k as f64 * self.step
+    }
+
+    /// Resample a block; the output is as long as the receiver clock makes it
+    pub fn process(&mut self, input: &[f64]) -> Vec<f64> {
+        let mut output = Vec::with_capacity(input.len() + input.len() / 1000 + 2);
+        for &x in input {
+            self.history.pop_front();
+            self.history.push_back(x);
+            self.consumed += 1;
+            // Output k is ready once the input at floor(position) has
+            // arrived: it then sits at history tap HALF_TAPS - 1 of the
+            // HALF_TAPS-delayed stream
+            while self.position(self.produced) < self.consumed as f64 {
+                output.push(self.interpolate(self.position(self.produced)));
+                self.produced += 1;
+            }
+        }
+        output
+    }
+
+    /// Advance by `num_samples` of silence, discarding the output
+    ///
+    /// Leaves the resampler as process() on that many zeros would, and
+    /// returns how many output samples that would have been.
+    pub fn advance(&mut self, num_samples: usize) -> usize {
+        for _ in 0..num_samples.min(TAPS) {
+            self.history.pop_front();
+            self.history.push_back(0.0);
+        }
+        self.consumed += num_samples as u64;
+        let start = self.produced;
+        while self.position(self.produced) < self.consumed as f64 {
+            self.produced += 1;
+        }
+        (self.produced - start) as usize
+    }
+
+    /// Interpolate the history at `position`, whose whole part is the
+    /// newest input
+    fn interpolate(&self, position: f64) -> f64 {
+        let p = (position - position.floor()) * PHASES as f64;
+        let p0 = (p as usize).min(PHASES - 1);
+        let t = p - p0 as f64;
+        let row0 = &self.coeffs[p0 * TAPS..(p0 + 1) * TAPS];
+        let row1 = &self.coeffs[(p0 + 1) * TAPS..(p0 + 2) * TAPS];
+
+        self.history
+            .iter()
+            .zip(row0.iter().zip(row1))
+            .map(|(x, (c0, c1))| x * (c0 + (c1 - c0) * t))
+            .sum()
+    }
+}
+
+/// The coefficient table, designed on first use
+fn table() -> &'static [f64] {
+    static TABLE: OnceLock<Vec<f64>> = OnceLock::new();
+    TABLE.get_or_init(design)
+}
+
+/// Coefficient table: PHASES + 1 rows of TAPS, each normalized to unity
+/// DC gain
+///
+/// Row p interpolates at p / PHASES past tap HALF_TAPS - 1; with the
+/// newest input at tap TAPS - 1 that is HALF_TAPS samples back.
+fn design() -> Vec<f64> {
+    let mut coeffs = Vec::with_capacity((PHASES + 1) * TAPS);
+    for p in 0..=PHASES {
+        let frac = p as f64 / PHASES as f64;
+        let row: Vec<f64> = (0..TAPS)
+            .map(|j| {
+                let x = j as f64 - (HALF_TAPS as f64 - 1.0) - frac;
+                let sinc = if x.abs() < 1e-12 { 1.0 } else { (PI * x).sin() / (PI * x) };
+                let window = 0.5 + 0.5 * (PI * x / HALF_TAPS as f64).cos();
+                sinc * window
+            })
+            .collect();
+        let sum: f64 = row.iter().sum();
+        coeffs.extend(row.iter().map(|c| c / sum));
+    }
+    coeffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(freq: f64, n: usize) -> Vec<f64> {
+        (0..n).map(|i| (2.0 * PI * freq * i as f64 / 9600.0).sin()).collect()
+    }
+
+    #[test]
+    fn test_output_count_is_exact_and_blocking_independent() {
+        let input = tone(1000.0, 96_000);
+        for ppm in [60.0, -60.0, 1000.0, -1000.0] {
+            let whole = ClockDrift::new(ppm).process(&input);
+            let expected = (input.len() as f64 * (1.0 + ppm * 1e-6)).ceil() as usize;
+            assert!(whole.len().abs_diff(expected) <= 1, "{} ppm: {} samples", ppm, whole.len());
+
+            let mut drift = ClockDrift::new(ppm);
+            let blocked: Vec<f64> = input.chunks(731).flat_map(|c| drift.process(c)).collect();
+            assert_eq!(blocked, whole);
+            assert_eq!(drift.produced(), whole.len() as u64);
+            assert_eq!(drift.slip_samples(), whole.len() as i64 - input.len() as i64);
+        }
+    }
+
+    #[test]
+    fn test_interpolates_voice_band_accurately() {
+        let ppm = 1000.0;
+        let step = 1.0 / (1.0 + ppm * 1e-6);
+        for freq in [300.0, 1800.0, 3000.0] {
+            let output = ClockDrift::new(ppm).process(&tone(freq, 20_000));
+            // Past the start-up, output k is the tone at k * step, delayed
+            let worst = (TAPS..output.len())
+                .map(|k| {
+                    let t = k as f64 * step - HALF_TAPS as f64;
+                    (output[k] - (2.0 * PI * freq * t / 9600.0).sin()).abs()
+                })
+                .fold(0.0, f64::max);
+            assert!(worst < 1e-3, "{} Hz: error {}", freq, worst);
+        }
+    }
+
+    #[test]
+    fn test_advance_matches_processing_silence() {
+        let mut a = ClockDrift::new(-250.0);
+        let mut b = ClockDrift::new(-250.0);
+        let first = tone(700.0, 5000);
+        a.process(&first);
+        b.process(&first);
+
+        let skipped = a.advance(20_001);
+        assert_eq!(skipped, b.process(&vec![0.0; 20_001]).len());
+
+        let next = tone(1300.0, 3000);
+        assert_eq!(a.process(&next), b.process(&next));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(0.0).is_ok());
+        assert!(validate(-MAX_OFFSET_PPM).is_ok());
+        assert_eq!(validate(MAX_OFFSET_PPM + 1.0), Err("invalid_sample_rate_offset"));
+        assert_eq!(validate(f64::NAN), Err("invalid_sample_rate_offset"));
+    }
+}
@@ -0,0 +1,440 @@
+//! Channel scenarios in the Watterson JSON exchange format
+//!
+//! Partners describe a channel as a JSON document:
+//!
+//! ```json
+//! {
+//!   "format": "watterson-channel",
+//!   "version": 1,
+//!   "metadata": { "name": "CCIR poor" },
+//!   "sample_rate_hz": 9600,
+//!   "snr_db": 10.0,
+//!   "carrier_hz": 1800.0,
+//!   "paths": [
+//!     { "delay_ms": 0.0, "doppler_spread_hz": 1.0, "relative_db": 0.0 },
+//!     { "delay_ms": 2.0, "doppler_spread_hz": 1.0, "relative_db": 0.0 }
+//!   ]
+//! }
+//! ```
+//!
+//! `metadata` is free-form and ignored (an export's holds the generator
+//! and its provenance string, see crate::provenance); `sample_rate_hz` (default 9600)
+//! and `carrier_hz` (default 1800) are optional. The channel here is the
+//! two-path Appendix E model, so a document may give one path or two: the
+//! first at 0 ms, the second delayed by at least one sample, both with
+//! the same Doppler spread. Each path's `relative_db` (default 0) is its
+//! tap gain (ChannelParams::tap0_gain_db, tap1_gain_db), within ±60 dB.
+//! Anything it can't represent is rejected rather than approximated.
+//!
+//! Fields of ChannelParams the schema has no place for (bulk delay,
+//! output stage, seeds, warm start, clock drift, frequency offset, noise
+//! bandwidth, impulsive noise) take their defaults on import and are left out on export.
+//!
+//! Documents are read with serde_json into a `serde_json::Value` and
+//! checked here, so a schema error can name the JSON path it's about. A
+//! member given twice takes its last value, as serde_json does.
+//!
+//! Errors come back as `{:invalid_json, line, column, message}` for a
+//! document that doesn't parse, or `{:schema_error, path, message}` with
+//! the JSON path of the offending value, e.g. `"$.paths[1].delay_ms"`.
+
+use rustler::{Atom, Encoder, Env, Term};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::channel::{ChannelParams, MAX_TAP_GAIN_DB};
+use crate::limits::{MAX_DELAY_SPREAD_SAMPLES, MAX_SAMPLE_RATE};
+
+/// Value of the "format" member
+pub const FORMAT: &str = "watterson-channel";
+
+/// Schema version written and accepted
+pub const VERSION: u32 = 1;
+
+/// Sample rate when the document doesn't give one
+pub const DEFAULT_SAMPLE_RATE: u32 = 9600;
+
+/// Carrier when the document doesn't give one
+pub const DEFAULT_CARRIER_HZ: f64 = 1800.0;
+
+/// Highest Doppler spread accepted
+pub const MAX_DOPPLER_SPREAD_HZ: f64 = 100.0;
+
+/// SNR range accepted
+const SNR_RANGE_DB: std::ops::RangeInclusive<f64> = -50.0..=100.0;
+
+const TOP_LEVEL_KEYS: [&str; 7] =
+    ["format", "version", "metadata", "sample_rate_hz", "snr_db", "carrier_hz", "paths"];
+
+const PATH_KEYS: [&str; 3] = ["delay_ms", "doppler_spread_hz", "relative_db"];
+
+/// Why a document couldn't be turned into ChannelParams
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExchangeError {
+    /// Not JSON; `line` and `column` are 1-based
+    Syntax { line: usize, column: usize, message: String },
+    Schema { path: String, message: String },
+}
+
+impl From<serde_json::Error> for ExchangeError {
+    fn from(e: serde_json::Error) -> Self {
+        let (line, column) = (e.line(), e.column());
+        // Display appends the position, which the term carries separately
+        let full = e.to_string();
+        let message = full.strip_suffix(&format!(" at line {line} column {column}")).unwrap_or(&full);
+        ExchangeError::Syntax { line, column, message: message.to_string() }
+    }
+}
+
+impl Encoder for ExchangeError {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        let atom = |name: &str| Atom::from_str(env, name).expect("short ASCII atom names");
+        match self {
+            ExchangeError::Syntax { line, column, message } => {
+                (atom("invalid_json"), *line, *column, message.as_str()).encode(env)
+            }
+            ExchangeError::Schema { path, message } => {
+                (atom("schema_error"), path.as_str(), message.as_str()).encode(env)
+            }
+        }
+    }
+}
+
+fn schema(path: impl Into<String>, message: impl Into<String>) -> ExchangeError {
+    ExchangeError::Schema { path: path.into(), message: message.into() }
+}
+
+/// Parse a scenario document into ChannelParams
+pub fn params_from_json(text: &[u8]) -> Result<ChannelParams, ExchangeError> {
+    let doc: Value = serde_json::from_slice(text)?;
+    params_from_value(&doc)
+}
+
+/// Translate a parsed scenario document into ChannelParams
+pub fn params_from_value(doc: &Value) -> Result<ChannelParams, ExchangeError> {
+    check_keys(doc, "$", &TOP_LEVEL_KEYS)?;
+
+    match required(doc, "$", "format")? {
+        Value::String(s) if s == FORMAT => {}
+        Value::String(s) => return Err(schema("$.format", format!("expected \"{FORMAT}\", got \"{s}\""))),
+        other => return Err(type_error("$.format", "string", other)),
+    }
+    let version = number(required(doc, "$", "version")?, "$.version")?;
+    if version != VERSION as f64 {
+        return Err(schema("$.version", format!("unsupported version {version}")));
+    }
+    if let Some(metadata) = doc.get("metadata") {
+        if !matches!(metadata, Value::Object(_)) {
+            return Err(type_error("$.metadata", "object", metadata));
+        }
+    }
+
+    let sample_rate = match doc.get("sample_rate_hz") {
+        None => DEFAULT_SAMPLE_RATE,
+        Some(v) => {
+            let rate = number(v, "$.sample_rate_hz")?;
+            if rate.fract() != 0.0 || !(1.0..=MAX_SAMPLE_RATE as f64).contains(&rate) {
+                return Err(schema(
+                    "$.sample_rate_hz",
+                    format!("must be a whole number of Hz from 1 to {MAX_SAMPLE_RATE}"),
+                ));
+            }
+            rate as u32
+        }
+    };
+    let nyquist = sample_rate as f64 / 2.0;
+
+    let snr_db = number(required(doc, "$", "snr_db")?, "$.snr_db")?;
+    if !SNR_RANGE_DB.contains(&snr_db) {
+        return Err(schema(
+            "$.snr_db",
+            format!("must be from {} to {} dB", SNR_RANGE_DB.start(), SNR_RANGE_DB.end()),
+        ));
+    }
+
+    let carrier_freq_hz = match doc.get("carrier_hz") {
+        None => DEFAULT_CARRIER_HZ,
+        Some(v) => number(v, "$.carrier_hz")?,
+    };
+    if !(carrier_freq_hz > 0.0 && carrier_freq_hz < nyquist) {
+        return Err(schema("$.carrier_hz", format!("must be above 0 and below {nyquist} Hz")));
+    }
+
+    let paths = match required(doc, "$", "paths")? {
+        Value::Array(paths) => paths,
+        other => return Err(type_error("$.paths", "array", other)),
+    };
+    if !(1..=2).contains(&paths.len()) {
+        return Err(schema("$.paths", format!("two-path model takes 1 or 2 paths, got {}", paths.len())));
+    }
+    let mut doppler = 0.0;
+    let mut delay_spread_samples = 0;
+    let mut gains_db = [0.0; 2];
+    for (n, path) in paths.iter().enumerate() {
+        let at = format!("$.paths[{n}]");
+        check_keys(path, &at, &PATH_KEYS)?;
+
+        let delay_ms = number(required(path, &at, "delay_ms")?, &format!("{at}.delay_ms"))?;
+        let spread = number(required(path, &at, "doppler_spread_hz")?, &format!("{at}.doppler_spread_hz"))?;
+        let relative_db = match path.get("relative_db") {
+            None => 0.0,
+            Some(v) => number(v, &format!("{at}.relative_db"))?,
+        };
+
+        if !(0.0..=MAX_DOPPLER_SPREAD_HZ).contains(&spread) {
+            return Err(schema(
+                format!("{at}.doppler_spread_hz"),
+                format!("must be from 0 to {MAX_DOPPLER_SPREAD_HZ} Hz"),
+            ));
+        }
+        if !(-MAX_TAP_GAIN_DB..=MAX_TAP_GAIN_DB).contains(&relative_db) {
+            return Err(schema(
+                format!("{at}.relative_db"),
+                format!("must be from -{MAX_TAP_GAIN_DB} to {MAX_TAP_GAIN_DB} dB"),
+            ));
+        }
+        gains_db[n] = relative_db;
+        if n == 0 {
+            if delay_ms != 0.0 {
+                return Err(schema(format!("{at}.delay_ms"), "first path must be at 0 ms"));
+            }
+            doppler = spread;
+            continue;
+        }
+        if spread != doppler {
+            return Err(schema(
+                format!("{at}.doppler_spread_hz"),
+                "both paths share one Doppler spread; must equal $.paths[0]'s",
+            ));
+        }
+        let samples = (delay_ms * sample_rate as f64 / 1000.0).round();
+        if !(1.0..=MAX_DELAY_SPREAD_SAMPLES as f64).contains(&samples) {
+            return Err(schema(
+                format!("{at}.delay_ms"),
+                format!("must round to 1 to {MAX_DELAY_SPREAD_SAMPLES} samples at {sample_rate} Hz"),
+            ));
+        }
+        delay_spread_samples = samples as u32;
+    }
+
+    Ok(ChannelParams {
+        sample_rate,
+        delay_spread_samples,
+        doppler_bandwidth_hz: doppler,
+        snr_db,
+        carrier_freq_hz,
+        tap0_gain_db: gains_db[0],
+        tap1_gain_db: gains_db[1],
+        ..ChannelParams::default()
+    })
+}
+
+/// An exported scenario document, members in schema order
+#[derive(Debug, Serialize)]
+pub struct Scenario {
+    pub format: &'static str,
+    pub version: u32,
+    pub metadata: Metadata,
+    pub sample_rate_hz: u32,
+    pub snr_db: f64,
+    pub carrier_hz: f64,
+    pub paths: Vec<ScenarioPath>,
+}
+
+/// What wrote an exported document
+#[derive(Debug, Serialize)]
+pub struct Metadata {
+    pub generator: &'static str,
+    pub provenance: &'static str,
+}
+
+/// One path of an exported document
+#[derive(Debug, Serialize)]
+pub struct ScenarioPath {
+    pub delay_ms: f64,
+    pub doppler_spread_hz: f64,
+    pub relative_db: f64,
+}
+
+/// Write the schema's part of `params` as a scenario document, with
+/// two-space indentation and a trailing newline
+pub fn params_to_json(params: &ChannelParams) -> String {
+    let mut text = serde_json::to_string_pretty(&scenario(params)).expect("scenario serializes");
+    text.push('\n');
+    text
+}
+
+/// The scenario document for `params`
+pub fn scenario(params: &ChannelParams) -> Scenario {
+    let path = |delay_ms: f64, relative_db: f64| ScenarioPath {
+        delay_ms,
+        doppler_spread_hz: params.doppler_bandwidth_hz,
+        relative_db,
+    };
+    let mut paths = vec![path(0.0, params.tap0_gain_db)];
+    if params.delay_spread_samples != 0 {
+        let delay_ms = params.delay_spread_samples as f64 * 1000.0 / params.sample_rate as f64;
+        paths.push(path(delay_ms, params.tap1_gain_db));
+    }
+    Scenario {
+        format: FORMAT,
+        version: VERSION,
+        metadata: Metadata { generator: "minutemodem", provenance: crate::provenance::provenance() },
+        sample_rate_hz: params.sample_rate,
+        snr_db: params.snr_db,
+        carrier_hz: params.carrier_freq_hz,
+        paths,
+    }
+}
+
+/// Reject non-objects and members not in `allowed`
+fn check_keys(value: &Value, at: &str, allowed: &[&str]) -> Result<(), ExchangeError> {
+    let Value::Object(members) = value else {
+        return Err(type_error(at, "object", value));
+    };
+    match members.keys().find(|k| !allowed.contains(&k.as_str())) {
+        Some(key) => Err(schema(format!("{at}.{key}"), "unknown field")),
+        None => Ok(()),
+    }
+}
+
+fn required<'v>(value: &'v Value, at: &str, key: &str) -> Result<&'v Value, ExchangeError> {
+    value.get(key).ok_or_else(|| schema(format!("{at}.{key}"), "missing required field"))
+}
+
+fn number(value: &Value, at: &str) -> Result<f64, ExchangeError> {
+    value.as_f64().ok_or_else(|| type_error(at, "number", value))
+}
+
+fn type_error(at: &str, expected: &str, got: &Value) -> ExchangeError {
+    let got = match got {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+    schema(at, format!("expected {expected}, got {got}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOOD: &str = include_str!("../testdata/ccir_good.json");
+    const MODERATE: &str = include_str!("../testdata/ccir_moderate.json");
+    const POOR: &str = include_str!("../testdata/ccir_poor.json");
+
+    fn schema_path(text: &str) -> String {
+        match params_from_json(text.as_bytes()) {
+            Err(ExchangeError::Schema { path, .. }) => path,
+            other => panic!("expected a schema error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_partner_poor_scenario_at_9600() {
+        let params = params_from_json(POOR.as_bytes()).unwrap();
+        assert_eq!(params.sample_rate, 9600);
+        // 2 ms at 9600 Hz is 19.2 samples
+        assert_eq!(params.delay_spread_samples, 19);
+        assert_eq!(params.doppler_bandwidth_hz, 1.0);
+        assert_eq!(params.snr_db, 10.0);
+        assert_eq!(params.carrier_freq_hz, 1800.0);
+        assert_eq!(params.bulk_delay_samples, 0);
+        assert!(params.fading_seed.is_none());
+    }
+
+    #[test]
+    fn test_sample_files_round_trip() {
+        for text in [GOOD, MODERATE, POOR] {
+            let params = params_from_json(text.as_bytes()).unwrap();
+            let exported = params_to_json(&params);
+            let again = params_from_json(exported.as_bytes()).unwrap();
+            assert_eq!(again.sample_rate, params.sample_rate);
+            assert_eq!(again.delay_spread_samples, params.delay_spread_samples);
+            assert_eq!(again.doppler_bandwidth_hz, params.doppler_bandwidth_hz);
+            assert_eq!(again.snr_db, params.snr_db);
+            assert_eq!(again.carrier_freq_hz, params.carrier_freq_hz);
+            // Export is a fixed point after one pass
+            assert_eq!(params_to_json(&again), exported);
+        }
+    }
+
+    #[test]
+    fn test_unequal_paths_round_trip() {
+        let second_down = POOR.replace(
+            r#""delay_ms": 2.0, "doppler_spread_hz": 1.0, "relative_db": 0.0"#,
+            r#""delay_ms": 2.0, "doppler_spread_hz": 1.0, "relative_db": -6.0"#,
+        );
+        let params = params_from_json(second_down.as_bytes()).unwrap();
+        assert_eq!((params.tap0_gain_db, params.tap1_gain_db), (0.0, -6.0));
+        let again = params_from_json(params_to_json(&params).as_bytes()).unwrap();
+        assert_eq!((again.tap0_gain_db, again.tap1_gain_db), (0.0, -6.0));
+    }
+
+    #[test]
+    fn test_export_carries_provenance() {
+        let params = params_from_json(POOR.as_bytes()).unwrap();
+        let doc: Value = serde_json::from_str(&params_to_json(&params)).unwrap();
+        assert_eq!(doc["metadata"]["provenance"], crate::provenance::provenance());
+        assert_eq!(doc["sample_rate_hz"], 9600);
+    }
+
+    #[test]
+    fn test_single_path_is_flat_fading() {
+        let text = r#"{"format": "watterson-channel", "version": 1, "snr_db": 20,
+            "paths": [{"delay_ms": 0, "doppler_spread_hz": 0.5}]}"#;
+        let params = params_from_json(text.as_bytes()).unwrap();
+        assert_eq!(params.delay_spread_samples, 0);
+        assert_eq!(params.sample_rate, DEFAULT_SAMPLE_RATE);
+        assert_eq!(scenario(&params).paths.len(), 1);
+    }
+
+    #[test]
+    fn test_malformed_documents_are_rejected() {
+        match params_from_json(b"{\"format\": ") {
+            Err(ExchangeError::Syntax { line, column, message }) => {
+                assert_eq!((line, column), (1, 11));
+                assert_eq!(message, "EOF while parsing a value");
+            }
+            other => panic!("{other:?}"),
+        }
+        match params_from_json(b"{\"format\": \"watterson-channel\",\n  \"version\": 1,}") {
+            Err(ExchangeError::Syntax { line, column, message }) => {
+                assert_eq!((line, column), (2, 16));
+                assert_eq!(message, "trailing comma");
+            }
+            other => panic!("{other:?}"),
+        }
+        assert_eq!(schema_path("[]"), "$");
+        assert_eq!(schema_path(&POOR.replace("\"snr_db\"", "\"snr\"")), "$.snr");
+        assert_eq!(schema_path(&POOR.replace("watterson-channel", "itu-channel")), "$.format");
+        assert_eq!(schema_path(&POOR.replace("\"version\": 1", "\"version\": 2")), "$.version");
+        assert_eq!(schema_path(&POOR.replace("\"snr_db\": 10.0", "\"snr_db\": \"10\"")), "$.snr_db");
+        assert_eq!(schema_path(&POOR.replace("\"delay_ms\": 2.0", "\"delay_ms\": 0.01")), "$.paths[1].delay_ms");
+        assert_eq!(schema_path(&POOR.replace("\"delay_ms\": 2.0", "\"delay_ms\": -2")), "$.paths[1].delay_ms");
+        assert_eq!(schema_path(&POOR.replace("\"carrier_hz\": 1800.0", "\"carrier_hz\": 5000")), "$.carrier_hz");
+        assert_eq!(schema_path(&POOR.replace("\"sample_rate_hz\": 9600", "\"sample_rate_hz\": 9600.5")), "$.sample_rate_hz");
+    }
+
+    #[test]
+    fn test_paths_the_model_cannot_represent() {
+        let doc = |paths: &str| {
+            format!(r#"{{"format": "watterson-channel", "version": 1, "snr_db": 10, "paths": {paths}}}"#)
+        };
+        assert_eq!(schema_path(&doc("[]")), "$.paths");
+        let three = r#"[{"delay_ms": 0, "doppler_spread_hz": 1}, {"delay_ms": 1, "doppler_spread_hz": 1},
+            {"delay_ms": 2, "doppler_spread_hz": 1}]"#;
+        assert_eq!(schema_path(&doc(three)), "$.paths");
+        let late = r#"[{"delay_ms": 0.5, "doppler_spread_hz": 1}]"#;
+        assert_eq!(schema_path(&doc(late)), "$.paths[0].delay_ms");
+        let split = r#"[{"delay_ms": 0, "doppler_spread_hz": 1}, {"delay_ms": 1, "doppler_spread_hz": 2}]"#;
+        assert_eq!(schema_path(&doc(split)), "$.paths[1].doppler_spread_hz");
+        let faint = r#"[{"delay_ms": 0, "doppler_spread_hz": 1}, {"delay_ms": 1, "doppler_spread_hz": 1, "relative_db": -90}]"#;
+        assert_eq!(schema_path(&doc(faint)), "$.paths[1].relative_db");
+        let extra = r#"[{"delay_ms": 0, "doppler_spread_hz": 1, "phase": 0}]"#;
+        assert_eq!(schema_path(&doc(extra)), "$.paths[0].phase");
+    }
+}
@@ -0,0 +1,124 @@
+//! Fade alarm: reports when the fading drops into (and climbs out of) a
+//! deep fade
+//!
+//! The channel feeds the alarm the combined power of its fading taps
+//! relative to their long-term mean, once per sample. A fade starts when
+//! that drops below `threshold_db` and ends once it climbs back above
+//! `threshold_db + hysteresis_db`, so ripple around the threshold doesn't
+//! produce a burst of events.
+//!
+//! The NIF forwards each event to the registered pid as
+//! `{:fade, channel_id, :start | :end, sample_index}`:
+//!
+//! ```
+//! use channel_physics::fade_alarm::{FadeAlarm, FadeEdge, FadeEvent};
+//!
+//! let mut alarm = FadeAlarm::new(-10.0, 3.0).unwrap();
+//! alarm.update(0.05, 100); // -13 dB: {:fade, id, :start, 100}
+//! alarm.update(0.15, 101); // -8.2 dB: still within the hysteresis
+//! alarm.update(1.0, 102);  // 0 dB: {:fade, id, :end, 102}
+//!
+//! assert_eq!(
+//!     alarm.take_events(),
+//!     [
+//!         FadeEvent { edge: FadeEdge::Start, sample_index: 100 },
+//!         FadeEvent { edge: FadeEdge::End, sample_index: 102 },
+//!     ]
+//! );
+//! ```
+
+/// Which way the fade threshold was crossed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FadeEdge {
+    Start,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FadeEvent {
+    pub edge: FadeEdge,
+    /// Channel sample index at which the crossing happened
+    pub sample_index: u64,
+}
+
+pub struct FadeAlarm {
+    threshold_db: f64,
+    hysteresis_db: f64,
+    in_fade: bool,
+    events: Vec<FadeEvent>,
+}
+
+impl FadeAlarm {
+    pub fn new(threshold_db: f64, hysteresis_db: f64) -> Result<Self, &'static str> {
+        if !threshold_db.is_finite() || !hysteresis_db.is_finite() || hysteresis_db < 0.0 {
+            return Err("invalid_fade_alarm");
+        }
+        Ok(Self { threshold_db, hysteresis_db, in_fade: false, events: Vec::new() })
+    }
+
+    /// Feed one sample's tap power relative to its long-term mean (linear)
+    pub fn update(&mut self, relative_power: f64, sample_index: u64) {
+        let power_db = 10.0 * relative_power.max(1e-30).log10();
+        let edge = if self.in_fade {
+            (power_db > self.threshold_db + self.hysteresis_db).then_some(FadeEdge::End)
+        } else {
+            (power_db < self.threshold_db).then_some(FadeEdge::Start)
+        };
+        if let Some(edge) = edge {
+            self.in_fade = edge == FadeEdge::Start;
+            self.events.push(FadeEvent { edge, sample_index });
+        }
+    }
+
+    pub fn in_fade(&self) -> bool {
+        self.in_fade
+    }
+
+    /// Bytes held by events not yet taken
+    pub fn memory_bytes(&self) -> usize {
+        self.events.capacity() * std::mem::size_of::<FadeEvent>()
+    }
+
+    /// Events since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<FadeEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db(power_db: f64) -> f64 {
+        10.0_f64.powf(power_db / 10.0)
+    }
+
+    #[test]
+    fn test_rejects_bad_settings() {
+        assert!(FadeAlarm::new(-10.0, -1.0).is_err());
+        assert!(FadeAlarm::new(f64::NAN, 1.0).is_err());
+        assert!(FadeAlarm::new(-10.0, 0.0).is_ok());
+    }
+
+    #[test]
+    fn test_hysteresis_suppresses_ripple() {
+        let mut alarm = FadeAlarm::new(-10.0, 2.0).unwrap();
+        // Dither around the threshold, never clearing threshold + hysteresis
+        for (i, p) in [-9.0, -10.5, -9.5, -10.5, -8.5, -11.0, -8.1].iter().enumerate() {
+            alarm.update(db(*p), i as u64);
+        }
+        assert_eq!(alarm.take_events(), [FadeEvent { edge: FadeEdge::Start, sample_index: 1 }]);
+        assert!(alarm.in_fade());
+
+        alarm.update(db(-7.9), 7);
+        assert_eq!(alarm.take_events(), [FadeEvent { edge: FadeEdge::End, sample_index: 7 }]);
+        assert!(alarm.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_zero_power_is_a_fade() {
+        let mut alarm = FadeAlarm::new(-40.0, 0.0).unwrap();
+        alarm.update(0.0, 5);
+        assert!(alarm.in_fade());
+    }
+}
@@ -18,14 +18,22 @@
 //! - Rayleigh magnitude, uniform phase
 //! - Correct Jakes/Clarke Doppler spectrum
 //! - Autocorrelation following J₀(2πfdτ)
+//!
+//! The sum is taken at the tap's precision (see precision); time and the
+//! oscillator phases are always f64.
 
 use rand::Rng;
 use rand_chacha::ChaCha8Rng;
 use rand::SeedableRng;
 use std::f64::consts::PI;
 
+use crate::precision::{Float, Precision};
+
 const NUM_SINUSOIDS: usize = 64;
 
+/// Tap time wraps to zero past this many seconds
+pub const MAX_TIME: f64 = 1e6;
+
 /// Single Rayleigh fading tap using Gaussian-weighted sum of sinusoids
 pub struct FadingTap {
     sample_rate: f64,
@@ -42,6 +50,9 @@ pub struct FadingTap {
     time: f64,
     dt: f64,
     scale: f64,
+    
+    // What next_sample_complex() sums in
+    precision: Precision,
 }
 
 impl FadingTap {
@@ -94,6 +105,7 @@ impl FadingTap {
             time: 0.0,
             dt: 1.0 / sample_rate,
             scale,
+            precision: Precision::F64,
         }
     }
     
@@ -109,9 +121,18 @@ impl FadingTap {
             time: 0.0,
             dt: 1.0 / sample_rate,
             scale: 1.0,
+            precision: Precision::F64,
         }
     }
     
+    /// Sum the oscillators for next_sample_complex() at `precision`
+    ///
+    /// The realization is the same either way; only the rounding differs.
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+    
     pub fn next_sample(&mut self) -> f32 {
         let (i, q) = self.next_sample_complex();
         (i * i + q * q).sqrt()
@@ -126,32 +147,114 @@ impl FadingTap {
         self.time += self.dt;
         
         // Prevent unbounded growth
-        if self.time > 1e6 {
+        if self.time > MAX_TIME {
             self.time = 0.0;
         }
         
-        let mut x = 0.0;  // Real part (I)
-        let mut y = 0.0;  // Imag part (Q)
+        match self.precision {
+            Precision::F64 => {
+                let (x, y) = self.gain_at(t);
+                (x as f32, y as f32)
+            }
+            Precision::F32 => self.sum_at(t),
+        }
+    }
+    
+    /// Complex gain `t` seconds into this realization, without advancing
+    ///
+    /// The sum of sinusoids can be evaluated at any instant directly, so
+    /// looking (or jumping, see seek) far ahead costs no more than one
+    /// sample.
+    pub fn gain_at(&self, t: f64) -> (f64, f64) {
+        if self.doppler_hz == 0.0 {
+            return (1.0, 0.0);
+        }
+        self.sum_at(t)
+    }
+    
+    /// The sum of sinusoids at `t`, in T
+    fn sum_at<T: Float>(&self, t: f64) -> (T, T) {
+        // The oscillators first, apart from the sum so the trig has no
+        // loop-carried dependency
+        let mut oscillators = [(T::default(), T::default()); NUM_SINUSOIDS];
+        for (n, osc) in oscillators.iter_mut().enumerate() {
+            *osc = T::cos_sin(2.0 * PI * self.freq[n] * t + self.phase[n]);
+        }
+        
+        let mut x = T::default();  // Real part (I)
+        let mut y = T::default();  // Imag part (Q)
         
-        for n in 0..NUM_SINUSOIDS {
-            let psi = 2.0 * PI * self.freq[n] * t + self.phase[n];
-            let cos_psi = psi.cos();
-            let sin_psi = psi.sin();
+        for (n, &(cos_psi, sin_psi)) in oscillators.iter().enumerate() {
+            let (a, b) = (T::from_f64(self.amp_real[n]), T::from_f64(self.amp_imag[n]));
             
             // Complex multiplication: (a + jb) · (cos ψ + j sin ψ)
             // Real: a·cos - b·sin
             // Imag: a·sin + b·cos
-            x += self.amp_real[n] * cos_psi - self.amp_imag[n] * sin_psi;
-            y += self.amp_real[n] * sin_psi + self.amp_imag[n] * cos_psi;
+            x += a * cos_psi - b * sin_psi;
+            y += a * sin_psi + b * cos_psi;
         }
         
-        x *= self.scale;
-        y *= self.scale;
-        
-        (x as f32, y as f32)
+        let scale = T::from_f64(self.scale);
+        (x * scale, y * scale)
+    }
+    
+    /// Jump to `t` seconds into the realization; the next sample is h(t)
+    pub fn seek(&mut self, t: f64) {
+        self.time = if (0.0..=MAX_TIME).contains(&t) { t } else { 0.0 };
+    }
+    
+    /// Move on `n` samples without evaluating them
+    ///
+    /// Lands on exactly the time `n` next_sample_complex() calls would.
+    pub fn skip(&mut self, n: usize) {
+        if self.doppler_hz == 0.0 {
+            return;
+        }
+        for _ in 0..n {
+            self.time += self.dt;
+            if self.time > MAX_TIME {
+                self.time = 0.0;
+            }
+        }
     }
     
-    pub fn get_phase(&self) -> f64 { 0.0 }
+    /// Run the realization's clock `scale` times as fast from the next
+    /// sample on, for a Doppler spread `scale` times the tap's own
+    ///
+    /// The sum only ever sees time, so the fading carries on from where
+    /// it was with no phase jump. 1.0 is the tap as created.
+    pub fn set_doppler_scale(&mut self, scale: f64) {
+        self.dt = scale / self.sample_rate;
+    }
+    
+    /// Time of the next sample, in seconds into the realization
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+    
+    /// The coefficient the next sample takes, without advancing
+    pub fn current_gain(&self) -> (f64, f64) {
+        self.gain_at(self.time)
+    }
+    
+    /// Phase of current_gain(), radians in (-π, π]
+    pub fn get_phase(&self) -> f64 {
+        let (i, q) = self.current_gain();
+        q.atan2(i)
+    }
+    
+    /// Long-term mean of |h|² for this tap's realization
+    ///
+    /// E[|h|²] is 1 over all seeds, but one set of 64 Gaussian amplitudes
+    /// lands a little off. The oscillator cross terms average out over
+    /// time, leaving scale² · Σ|A_n|².
+    pub fn mean_power(&self) -> f64 {
+        if self.doppler_hz == 0.0 {
+            return 1.0;
+        }
+        let sum: f64 = self.amp_real.iter().zip(&self.amp_imag).map(|(a, b)| a * a + b * b).sum();
+        self.scale * self.scale * sum
+    }
 }
 
 #[cfg(test)]
@@ -159,6 +262,9 @@ mod tests {
     use super::*;
     use rand::SeedableRng;
     use std::f64::consts::PI;
+    
+    /// Both precisions, for the statistical tests
+    const PRECISIONS: [Precision; 2] = [Precision::F64, Precision::F32];
 
     fn chi_squared_gof(observed: &[usize], expected: &[f64]) -> (f64, usize) {
         let chi_sq: f64 = observed.iter().zip(expected.iter())
@@ -446,6 +552,66 @@ mod tests {
         assert!(mean_power > 0.9 && mean_power < 1.1, "Mean fading power {} should be ~1.0", mean_power);
     }
 
+    #[test]
+    fn test_mean_power_matches_time_average() {
+        // 20 Hz Doppler over 60 s: ~1200 fades, enough to average out
+        let mut rng = ChaCha8Rng::seed_from_u64(1934);
+        let mut tap = FadingTap::new(9600.0, 20.0, &mut rng);
+        let num_samples = 9600 * 60;
+        let average: f64 = (0..num_samples)
+            .map(|_| { let (i, q) = tap.next_sample_complex(); (i * i + q * q) as f64 })
+            .sum::<f64>() / num_samples as f64;
+        let expected = tap.mean_power();
+        assert!((average / expected - 1.0).abs() < 0.05, "time average {} vs mean_power {}", average, expected);
+    }
+
+    #[test]
+    fn test_seek_matches_stepping() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut stepped = FadingTap::new(9600.0, 1.0, &mut rng);
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut seeked = FadingTap::new(9600.0, 1.0, &mut rng);
+
+        for _ in 0..48_000 {
+            stepped.next_sample_complex();
+        }
+        seeked.seek(48_000.0 / 9600.0);
+        for _ in 0..100 {
+            let (ai, aq) = stepped.next_sample_complex();
+            let (bi, bq) = seeked.next_sample_complex();
+            assert!((ai - bi).abs() < 1e-5 && (aq - bq).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_skip_matches_stepping() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut stepped = FadingTap::new(9600.0, 1.0, &mut rng);
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut skipped = FadingTap::new(9600.0, 1.0, &mut rng);
+
+        for _ in 0..48_001 {
+            stepped.next_sample_complex();
+        }
+        skipped.skip(48_001);
+        assert_eq!(skipped.time(), stepped.time());
+        assert_eq!(skipped.next_sample_complex(), stepped.next_sample_complex());
+    }
+
+    #[test]
+    fn test_doppler_scale_runs_clock_from_where_it_was() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut tap = FadingTap::new(9600.0, 1.0, &mut rng);
+        tap.skip(9600);
+        tap.set_doppler_scale(2.5);
+        tap.skip(9600);
+        assert!((tap.time() - 3.5).abs() < 1e-9);
+
+        tap.set_doppler_scale(0.0);
+        let held = tap.next_sample_complex();
+        assert_eq!(tap.next_sample_complex(), held);
+    }
+
     #[test]
     fn test_fading_numerical_stability() {
         let mut rng = ChaCha8Rng::seed_from_u64(42);
@@ -488,231 +654,249 @@ mod tests {
 
     #[test]
     fn test_fading_magnitude_pdf_rayleigh_chisq() {
-        // Use INDEPENDENT taps for i.i.d. samples (consecutive samples are correlated)
-        let num_samples = 50_000usize;
-        let num_bins = 20usize;
-        let max_r = 3.0;
-        let bin_width = max_r / num_bins as f64;
-        
-        let mut magnitudes = Vec::with_capacity(num_samples);
-        for seed in 0..num_samples {
-            let mut rng = ChaCha8Rng::seed_from_u64(seed as u64);
-            let mut tap = FadingTap::new(9600.0, 10.0, &mut rng);
-            // Skip some samples to get past transient
-            for _ in 0..100 { tap.next_sample(); }
-            let (i, q) = tap.next_sample_complex();
-            magnitudes.push(((i*i + q*q) as f64).sqrt());
-        }
-        
-        let mean_power: f64 = magnitudes.iter().map(|r| r*r).sum::<f64>() / num_samples as f64;
-        let sigma_sq = mean_power / 2.0;
+        for precision in PRECISIONS {
+            println!("\nPrecision: {:?}", precision);
+            // Use INDEPENDENT taps for i.i.d. samples (consecutive samples are correlated)
+            let num_samples = 50_000usize;
+            let num_bins = 20usize;
+            let max_r = 3.0;
+            let bin_width = max_r / num_bins as f64;
+        
+            let mut magnitudes = Vec::with_capacity(num_samples);
+            for seed in 0..num_samples {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed as u64);
+                let mut tap = FadingTap::new(9600.0, 10.0, &mut rng).with_precision(precision);
+                // Skip some samples to get past transient
+                for _ in 0..100 { tap.next_sample(); }
+                let (i, q) = tap.next_sample_complex();
+                magnitudes.push(((i*i + q*q) as f64).sqrt());
+            }
         
-        println!("\n========== Rayleigh PDF Chi-Squared Test ==========");
-        println!("Using {} independent taps (i.i.d. samples)", num_samples);
-        println!("Estimated σ² = {:.4} (expect ~0.5)", sigma_sq);
+            let mean_power: f64 = magnitudes.iter().map(|r| r*r).sum::<f64>() / num_samples as f64;
+            let sigma_sq = mean_power / 2.0;
         
-        let mut observed = vec![0usize; num_bins];
-        for &r in &magnitudes { observed[((r / bin_width) as usize).min(num_bins - 1)] += 1; }
+            println!("\n========== Rayleigh PDF Chi-Squared Test ==========");
+            println!("Using {} independent taps (i.i.d. samples)", num_samples);
+            println!("Estimated σ² = {:.4} (expect ~0.5)", sigma_sq);
         
-        let mut expected = vec![0.0f64; num_bins];
-        for i in 0..num_bins {
-            let r_low = i as f64 * bin_width;
-            let r_high = (i + 1) as f64 * bin_width;
-            expected[i] = (rayleigh_cdf(r_high, sigma_sq) - rayleigh_cdf(r_low, sigma_sq)) * num_samples as f64;
-        }
-        let (chi_sq, df) = chi_squared_gof(&observed, &expected);
-        println!("Chi-squared: {:.2}, df: {}", chi_sq, df);
+            let mut observed = vec![0usize; num_bins];
+            for &r in &magnitudes { observed[((r / bin_width) as usize).min(num_bins - 1)] += 1; }
         
-        println!("\nBin         Observed   Expected   Diff%");
-        for i in 0..num_bins.min(12) {
-            let diff_pct = if expected[i] > 0.0 { 100.0 * (observed[i] as f64 - expected[i]) / expected[i] } else { 0.0 };
-            println!("[{:.2},{:.2})   {:6}     {:6.0}    {:+5.1}%",
-                i as f64 * bin_width, (i+1) as f64 * bin_width, observed[i], expected[i], diff_pct);
+            let mut expected = vec![0.0f64; num_bins];
+            for i in 0..num_bins {
+                let r_low = i as f64 * bin_width;
+                let r_high = (i + 1) as f64 * bin_width;
+                expected[i] = (rayleigh_cdf(r_high, sigma_sq) - rayleigh_cdf(r_low, sigma_sq)) * num_samples as f64;
+            }
+            let (chi_sq, df) = chi_squared_gof(&observed, &expected);
+            println!("Chi-squared: {:.2}, df: {}", chi_sq, df);
+        
+            println!("\nBin         Observed   Expected   Diff%");
+            for i in 0..num_bins.min(12) {
+                let diff_pct = if expected[i] > 0.0 { 100.0 * (observed[i] as f64 - expected[i]) / expected[i] } else { 0.0 };
+                println!("[{:.2},{:.2})   {:6}     {:6.0}    {:+5.1}%",
+                    i as f64 * bin_width, (i+1) as f64 * bin_width, observed[i], expected[i], diff_pct);
+            }
+            assert!(chi_sq < 50.0, "Chi-squared {} too high", chi_sq);
         }
-        assert!(chi_sq < 50.0, "Chi-squared {} too high", chi_sq);
     }
 
     #[test]
     fn test_fading_phase_pdf_uniform_chisq() {
-        // Use INDEPENDENT taps for i.i.d. samples
-        let num_samples = 50_000usize;
-        let num_bins = 16usize;
-        
-        let mut observed = vec![0usize; num_bins];
-        for seed in 0..num_samples {
-            let mut rng = ChaCha8Rng::seed_from_u64(1_000_000 + seed as u64);
-            let mut tap = FadingTap::new(9600.0, 10.0, &mut rng);
-            for _ in 0..100 { tap.next_sample(); }
-            let (i, q) = tap.next_sample_complex();
-            let phase = (q as f64).atan2(i as f64);
-            let normalized = (phase + PI) / (2.0 * PI);
-            observed[((normalized * num_bins as f64) as usize).min(num_bins - 1)] += 1;
-        }
-        let expected_per_bin = num_samples as f64 / num_bins as f64;
-        let expected: Vec<f64> = vec![expected_per_bin; num_bins];
-        let (chi_sq, df) = chi_squared_gof(&observed, &expected);
-        
-        println!("\n========== Uniform Phase Chi-Squared Test ==========");
-        println!("Using {} independent taps (i.i.d. samples)", num_samples);
-        println!("Chi-squared: {:.2}, df: {}", chi_sq, df);
-        println!("\nBin (degrees)    Observed   Expected   Diff%");
-        for i in 0..num_bins {
-            let angle_start = -180.0 + i as f64 * 360.0 / num_bins as f64;
-            let diff_pct = 100.0 * (observed[i] as f64 - expected_per_bin) / expected_per_bin;
-            println!("[{:+6.1}°,{:+6.1}°)  {:6}     {:6.0}    {:+5.1}%",
-                angle_start, angle_start + 360.0/num_bins as f64, observed[i], expected_per_bin, diff_pct);
+        for precision in PRECISIONS {
+            println!("\nPrecision: {:?}", precision);
+            // Use INDEPENDENT taps for i.i.d. samples
+            let num_samples = 50_000usize;
+            let num_bins = 16usize;
+        
+            let mut observed = vec![0usize; num_bins];
+            for seed in 0..num_samples {
+                let mut rng = ChaCha8Rng::seed_from_u64(1_000_000 + seed as u64);
+                let mut tap = FadingTap::new(9600.0, 10.0, &mut rng).with_precision(precision);
+                for _ in 0..100 { tap.next_sample(); }
+                let (i, q) = tap.next_sample_complex();
+                let phase = (q as f64).atan2(i as f64);
+                let normalized = (phase + PI) / (2.0 * PI);
+                observed[((normalized * num_bins as f64) as usize).min(num_bins - 1)] += 1;
+            }
+            let expected_per_bin = num_samples as f64 / num_bins as f64;
+            let expected: Vec<f64> = vec![expected_per_bin; num_bins];
+            let (chi_sq, df) = chi_squared_gof(&observed, &expected);
+        
+            println!("\n========== Uniform Phase Chi-Squared Test ==========");
+            println!("Using {} independent taps (i.i.d. samples)", num_samples);
+            println!("Chi-squared: {:.2}, df: {}", chi_sq, df);
+            println!("\nBin (degrees)    Observed   Expected   Diff%");
+            for i in 0..num_bins {
+                let angle_start = -180.0 + i as f64 * 360.0 / num_bins as f64;
+                let diff_pct = 100.0 * (observed[i] as f64 - expected_per_bin) / expected_per_bin;
+                println!("[{:+6.1}°,{:+6.1}°)  {:6}     {:6.0}    {:+5.1}%",
+                    angle_start, angle_start + 360.0/num_bins as f64, observed[i], expected_per_bin, diff_pct);
+            }
+            assert!(chi_sq < 40.0, "Chi-squared {} too high", chi_sq);
         }
-        assert!(chi_sq < 40.0, "Chi-squared {} too high", chi_sq);
     }
 
     #[test]
     fn test_level_crossing_rate() {
-        let mut rng = ChaCha8Rng::seed_from_u64(42);
-        let doppler_hz = 10.0;
-        let sample_rate = 9600.0;
-        let mut tap = FadingTap::new(sample_rate, doppler_hz, &mut rng);
-        let duration_sec = 100.0;
-        let num_samples = (duration_sec * sample_rate) as usize;
-        
-        let magnitudes: Vec<f64> = (0..num_samples).map(|_| tap.next_sample() as f64).collect();
-        let rms = (magnitudes.iter().map(|&m| m*m).sum::<f64>() / num_samples as f64).sqrt();
-        
-        println!("\n========== Level Crossing Rate Test ==========");
-        println!("Doppler: {} Hz, Duration: {} sec, RMS: {:.4}", doppler_hz, duration_sec, rms);
-        println!("\nρ (thresh/rms)  Measured LCR   Theoretical LCR   Error%");
-        
-        for &rho in &[0.5, 0.707, 1.0, 1.414, 2.0] {
-            let threshold = rho * rms;
-            let crossings = (1..num_samples).filter(|&i| magnitudes[i-1] < threshold && magnitudes[i] >= threshold).count();
-            let measured = crossings as f64 / duration_sec;
-            let theoretical = theoretical_lcr(rho, doppler_hz);
-            let error_pct = 100.0 * (measured - theoretical).abs() / theoretical;
-            println!("ρ = {:.3}         {:8.2}       {:8.2}          {:5.1}%", rho, measured, theoretical, error_pct);
-            assert!(error_pct < 30.0, "LCR at ρ={} error {}% too high", rho, error_pct);
+        for precision in PRECISIONS {
+            println!("\nPrecision: {:?}", precision);
+            let mut rng = ChaCha8Rng::seed_from_u64(42);
+            let doppler_hz = 10.0;
+            let sample_rate = 9600.0;
+            let mut tap = FadingTap::new(sample_rate, doppler_hz, &mut rng).with_precision(precision);
+            let duration_sec = 100.0;
+            let num_samples = (duration_sec * sample_rate) as usize;
+        
+            let magnitudes: Vec<f64> = (0..num_samples).map(|_| tap.next_sample() as f64).collect();
+            let rms = (magnitudes.iter().map(|&m| m*m).sum::<f64>() / num_samples as f64).sqrt();
+        
+            println!("\n========== Level Crossing Rate Test ==========");
+            println!("Doppler: {} Hz, Duration: {} sec, RMS: {:.4}", doppler_hz, duration_sec, rms);
+            println!("\nρ (thresh/rms)  Measured LCR   Theoretical LCR   Error%");
+        
+            for &rho in &[0.5, 0.707, 1.0, 1.414, 2.0] {
+                let threshold = rho * rms;
+                let crossings = (1..num_samples).filter(|&i| magnitudes[i-1] < threshold && magnitudes[i] >= threshold).count();
+                let measured = crossings as f64 / duration_sec;
+                let theoretical = theoretical_lcr(rho, doppler_hz);
+                let error_pct = 100.0 * (measured - theoretical).abs() / theoretical;
+                println!("ρ = {:.3}         {:8.2}       {:8.2}          {:5.1}%", rho, measured, theoretical, error_pct);
+                assert!(error_pct < 30.0, "LCR at ρ={} error {}% too high", rho, error_pct);
+            }
         }
     }
 
     #[test]
     fn test_average_fade_duration() {
-        let mut rng = ChaCha8Rng::seed_from_u64(42);
-        let doppler_hz = 10.0;
-        let sample_rate = 9600.0;
-        let mut tap = FadingTap::new(sample_rate, doppler_hz, &mut rng);
-        let duration_sec = 200.0;
-        let num_samples = (duration_sec * sample_rate) as usize;
-        
-        let magnitudes: Vec<f64> = (0..num_samples).map(|_| tap.next_sample() as f64).collect();
-        let rms = (magnitudes.iter().map(|&m| m*m).sum::<f64>() / num_samples as f64).sqrt();
-        
-        println!("\n========== Average Fade Duration Test ==========");
-        println!("Doppler: {} Hz, Duration: {} sec, RMS: {:.4}", doppler_hz, duration_sec, rms);
-        println!("\nρ (thresh/rms)  Measured AFD(ms)  Theoretical AFD(ms)  Error%");
-        
-        for &rho in &[0.5, 0.707, 1.0] {
-            let threshold = rho * rms;
-            let mut fade_durations: Vec<f64> = Vec::new();
-            let mut in_fade = false;
-            let mut fade_start = 0usize;
-            for i in 0..num_samples {
-                if magnitudes[i] < threshold {
-                    if !in_fade { in_fade = true; fade_start = i; }
-                } else if in_fade {
-                    fade_durations.push((i - fade_start) as f64 / sample_rate);
-                    in_fade = false;
+        for precision in PRECISIONS {
+            println!("\nPrecision: {:?}", precision);
+            let mut rng = ChaCha8Rng::seed_from_u64(42);
+            let doppler_hz = 10.0;
+            let sample_rate = 9600.0;
+            let mut tap = FadingTap::new(sample_rate, doppler_hz, &mut rng).with_precision(precision);
+            let duration_sec = 200.0;
+            let num_samples = (duration_sec * sample_rate) as usize;
+        
+            let magnitudes: Vec<f64> = (0..num_samples).map(|_| tap.next_sample() as f64).collect();
+            let rms = (magnitudes.iter().map(|&m| m*m).sum::<f64>() / num_samples as f64).sqrt();
+        
+            println!("\n========== Average Fade Duration Test ==========");
+            println!("Doppler: {} Hz, Duration: {} sec, RMS: {:.4}", doppler_hz, duration_sec, rms);
+            println!("\nρ (thresh/rms)  Measured AFD(ms)  Theoretical AFD(ms)  Error%");
+        
+            for &rho in &[0.5, 0.707, 1.0] {
+                let threshold = rho * rms;
+                let mut fade_durations: Vec<f64> = Vec::new();
+                let mut in_fade = false;
+                let mut fade_start = 0usize;
+                for i in 0..num_samples {
+                    if magnitudes[i] < threshold {
+                        if !in_fade { in_fade = true; fade_start = i; }
+                    } else if in_fade {
+                        fade_durations.push((i - fade_start) as f64 / sample_rate);
+                        in_fade = false;
+                    }
                 }
+                if fade_durations.is_empty() { continue; }
+                let measured = fade_durations.iter().sum::<f64>() / fade_durations.len() as f64;
+                let theoretical = theoretical_afd(rho, doppler_hz);
+                let error_pct = 100.0 * (measured - theoretical).abs() / theoretical;
+                println!("ρ = {:.3}         {:8.2}          {:8.2}             {:5.1}%",
+                    rho, measured * 1000.0, theoretical * 1000.0, error_pct);
+                assert!(error_pct < 40.0, "AFD at ρ={} error {}% too high", rho, error_pct);
             }
-            if fade_durations.is_empty() { continue; }
-            let measured = fade_durations.iter().sum::<f64>() / fade_durations.len() as f64;
-            let theoretical = theoretical_afd(rho, doppler_hz);
-            let error_pct = 100.0 * (measured - theoretical).abs() / theoretical;
-            println!("ρ = {:.3}         {:8.2}          {:8.2}             {:5.1}%",
-                rho, measured * 1000.0, theoretical * 1000.0, error_pct);
-            assert!(error_pct < 40.0, "AFD at ρ={} error {}% too high", rho, error_pct);
         }
     }
 
     #[test]
     fn test_fading_autocorrelation_bessel() {
-        let mut rng = ChaCha8Rng::seed_from_u64(42);
-        let doppler_hz = 10.0;
-        let sample_rate = 9600.0;
-        let mut tap = FadingTap::new(sample_rate, doppler_hz, &mut rng);
-        let num_samples = 96000usize;
-        
-        let mut i_samples = Vec::with_capacity(num_samples);
-        let mut q_samples = Vec::with_capacity(num_samples);
-        for _ in 0..num_samples {
-            let (i, q) = tap.next_sample_complex();
-            i_samples.push(i as f64);
-            q_samples.push(q as f64);
-        }
-        let i_mean: f64 = i_samples.iter().sum::<f64>() / num_samples as f64;
-        let q_mean: f64 = q_samples.iter().sum::<f64>() / num_samples as f64;
-        let i_var: f64 = i_samples.iter().map(|&x| (x-i_mean).powi(2)).sum::<f64>() / num_samples as f64;
-        let q_var: f64 = q_samples.iter().map(|&x| (x-q_mean).powi(2)).sum::<f64>() / num_samples as f64;
-        let total_var = i_var + q_var;
-        
-        println!("\n========== Autocorrelation vs Bessel J₀ Test ==========");
-        println!("Doppler: {} Hz, Sample rate: {} Hz", doppler_hz, sample_rate);
-        println!("\nLag (ms)   τ*fd    Measured ρ   J₀(2πfdτ)   Error");
-        
-        for &lag_samples in &[0usize, 24, 48, 96, 192, 480, 960, 2400, 4800] {
-            let tau = lag_samples as f64 / sample_rate;
-            let n = num_samples - lag_samples;
-            let mut sum = 0.0;
-            for i in 0..n {
-                sum += (i_samples[i] - i_mean) * (i_samples[i + lag_samples] - i_mean);
-                sum += (q_samples[i] - q_mean) * (q_samples[i + lag_samples] - q_mean);
+        for precision in PRECISIONS {
+            println!("\nPrecision: {:?}", precision);
+            let mut rng = ChaCha8Rng::seed_from_u64(42);
+            let doppler_hz = 10.0;
+            let sample_rate = 9600.0;
+            let mut tap = FadingTap::new(sample_rate, doppler_hz, &mut rng).with_precision(precision);
+            let num_samples = 96000usize;
+        
+            let mut i_samples = Vec::with_capacity(num_samples);
+            let mut q_samples = Vec::with_capacity(num_samples);
+            for _ in 0..num_samples {
+                let (i, q) = tap.next_sample_complex();
+                i_samples.push(i as f64);
+                q_samples.push(q as f64);
+            }
+            let i_mean: f64 = i_samples.iter().sum::<f64>() / num_samples as f64;
+            let q_mean: f64 = q_samples.iter().sum::<f64>() / num_samples as f64;
+            let i_var: f64 = i_samples.iter().map(|&x| (x-i_mean).powi(2)).sum::<f64>() / num_samples as f64;
+            let q_var: f64 = q_samples.iter().map(|&x| (x-q_mean).powi(2)).sum::<f64>() / num_samples as f64;
+            let total_var = i_var + q_var;
+        
+            println!("\n========== Autocorrelation vs Bessel J₀ Test ==========");
+            println!("Doppler: {} Hz, Sample rate: {} Hz", doppler_hz, sample_rate);
+            println!("\nLag (ms)   τ*fd    Measured ρ   J₀(2πfdτ)   Error");
+        
+            for &lag_samples in &[0usize, 24, 48, 96, 192, 480, 960, 2400, 4800] {
+                let tau = lag_samples as f64 / sample_rate;
+                let n = num_samples - lag_samples;
+                let mut sum = 0.0;
+                for i in 0..n {
+                    sum += (i_samples[i] - i_mean) * (i_samples[i + lag_samples] - i_mean);
+                    sum += (q_samples[i] - q_mean) * (q_samples[i + lag_samples] - q_mean);
+                }
+                let measured = sum / (n as f64 * total_var);
+                let theoretical = bessel_j0(2.0 * PI * doppler_hz * tau);
+                let error = (measured - theoretical).abs();
+                println!("{:6.1}     {:.3}      {:+.4}       {:+.4}      {:.4}",
+                    tau * 1000.0, tau * doppler_hz, measured, theoretical, error);
+                let tolerance = if lag_samples < 100 { 0.15 } else { 0.25 };
+                assert!(error < tolerance, "Autocorr at lag {}: error {} > {}", lag_samples, error, tolerance);
             }
-            let measured = sum / (n as f64 * total_var);
-            let theoretical = bessel_j0(2.0 * PI * doppler_hz * tau);
-            let error = (measured - theoretical).abs();
-            println!("{:6.1}     {:.3}      {:+.4}       {:+.4}      {:.4}",
-                tau * 1000.0, tau * doppler_hz, measured, theoretical, error);
-            let tolerance = if lag_samples < 100 { 0.15 } else { 0.25 };
-            assert!(error < tolerance, "Autocorr at lag {}: error {} > {}", lag_samples, error, tolerance);
         }
     }
 
     #[test]
     fn test_coherence_time() {
-        let mut rng = ChaCha8Rng::seed_from_u64(42);
-        let doppler_hz = 10.0;
-        let sample_rate = 9600.0;
-        let mut tap = FadingTap::new(sample_rate, doppler_hz, &mut rng);
-        let num_samples = 96000usize;
-        
-        let mut i_samples = Vec::with_capacity(num_samples);
-        let mut q_samples = Vec::with_capacity(num_samples);
-        for _ in 0..num_samples {
-            let (i, q) = tap.next_sample_complex();
-            i_samples.push(i as f64);
-            q_samples.push(q as f64);
-        }
-        let i_mean: f64 = i_samples.iter().sum::<f64>() / num_samples as f64;
-        let q_mean: f64 = q_samples.iter().sum::<f64>() / num_samples as f64;
-        let i_var: f64 = i_samples.iter().map(|&x| (x-i_mean).powi(2)).sum::<f64>() / num_samples as f64;
-        let q_var: f64 = q_samples.iter().map(|&x| (x-q_mean).powi(2)).sum::<f64>() / num_samples as f64;
-        let total_var = i_var + q_var;
-        
-        let mut coherence_samples = 0usize;
-        for lag in 1..4800 {
-            let n = num_samples - lag;
-            let mut sum = 0.0;
-            for i in 0..n {
-                sum += (i_samples[i] - i_mean) * (i_samples[i + lag] - i_mean);
-                sum += (q_samples[i] - q_mean) * (q_samples[i + lag] - q_mean);
+        for precision in PRECISIONS {
+            println!("\nPrecision: {:?}", precision);
+            let mut rng = ChaCha8Rng::seed_from_u64(42);
+            let doppler_hz = 10.0;
+            let sample_rate = 9600.0;
+            let mut tap = FadingTap::new(sample_rate, doppler_hz, &mut rng).with_precision(precision);
+            let num_samples = 96000usize;
+        
+            let mut i_samples = Vec::with_capacity(num_samples);
+            let mut q_samples = Vec::with_capacity(num_samples);
+            for _ in 0..num_samples {
+                let (i, q) = tap.next_sample_complex();
+                i_samples.push(i as f64);
+                q_samples.push(q as f64);
             }
-            if sum / (n as f64 * total_var) < 0.5 { coherence_samples = lag; break; }
-        }
-        let measured_tc = coherence_samples as f64 / sample_rate;
-        let theoretical_tc = 0.242 / doppler_hz;
-        let error_pct = 100.0 * (measured_tc - theoretical_tc).abs() / theoretical_tc;
+            let i_mean: f64 = i_samples.iter().sum::<f64>() / num_samples as f64;
+            let q_mean: f64 = q_samples.iter().sum::<f64>() / num_samples as f64;
+            let i_var: f64 = i_samples.iter().map(|&x| (x-i_mean).powi(2)).sum::<f64>() / num_samples as f64;
+            let q_var: f64 = q_samples.iter().map(|&x| (x-q_mean).powi(2)).sum::<f64>() / num_samples as f64;
+            let total_var = i_var + q_var;
+        
+            let mut coherence_samples = 0usize;
+            for lag in 1..4800 {
+                let n = num_samples - lag;
+                let mut sum = 0.0;
+                for i in 0..n {
+                    sum += (i_samples[i] - i_mean) * (i_samples[i + lag] - i_mean);
+                    sum += (q_samples[i] - q_mean) * (q_samples[i + lag] - q_mean);
+                }
+                if sum / (n as f64 * total_var) < 0.5 { coherence_samples = lag; break; }
+            }
+            let measured_tc = coherence_samples as f64 / sample_rate;
+            let theoretical_tc = 0.242 / doppler_hz;
+            let error_pct = 100.0 * (measured_tc - theoretical_tc).abs() / theoretical_tc;
         
-        println!("\n========== Coherence Time Test ==========");
-        println!("Measured Tc: {:.2} ms, Theoretical: {:.2} ms, Error: {:.1}%",
-            measured_tc * 1000.0, theoretical_tc * 1000.0, error_pct);
-        assert!(error_pct < 25.0, "Coherence time error {}% too high", error_pct);
+            println!("\n========== Coherence Time Test ==========");
+            println!("Measured Tc: {:.2} ms, Theoretical: {:.2} ms, Error: {:.1}%",
+                measured_tc * 1000.0, theoretical_tc * 1000.0, error_pct);
+            assert!(error_pct < 25.0, "Coherence time error {}% too high", error_pct);
+        }
     }
 
     #[test]
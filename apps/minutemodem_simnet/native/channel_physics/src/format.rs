@@ -0,0 +1,351 @@
+//! Sample formats accepted by process_block_fmt
+//!
+//! The channel computes in f64 internally. Plain process_block goes through
+//! f32 at both ends; these formats let high-dynamic-range captures keep
+//! their low-order bits:
+//! - `:f32ne` - native-endian f32 (same as process_block)
+//! - `:f64ne` - native-endian f64
+//! - `:s24le` - packed 3-byte little-endian signed 24-bit PCM, ±1.0 full scale
+//!
+//! and, for nodes federated across hosts of different byte order, f32 in
+//! an explicit order:
+//! - `:f32le` - little-endian f32
+//! - `:f32be` - big-endian (network order) f32
+//! - `:f32tagged` - f32 in the writer's native order, after a 4-byte
+//!   ENDIAN_MAGIC in the same order. The reader takes the order from the
+//!   magic, so neither side needs to know the other's; a binary without
+//!   the magic in either order is rejected rather than guessed at.
+//!
+//! On a host of the same order as the binary these decode and encode
+//! with the native fast path; otherwise each sample is byte-swapped.
+
+use minutemodem_dsp::convert;
+use rustler::Atom;
+
+mod atoms {
+    rustler::atoms! {
+        f32ne,
+        f64ne,
+        s24le,
+        f32le,
+        f32be,
+        f32tagged,
+    }
+}
+
+/// First 4 bytes of an `:f32tagged` binary, as a u32 in the writer's
+/// order: "MMF4" from a big-endian host, "4FMM" from a little-endian one
+pub const ENDIAN_MAGIC: u32 = u32::from_be_bytes(*b"MMF4");
+
+/// Bytes before the samples of an `:f32tagged` binary
+const TAG_LEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    F32Ne,
+    F64Ne,
+    S24Le,
+    F32Le,
+    F32Be,
+    F32Tagged,
+}
+
+/// Byte order of an f32 binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    pub const NATIVE: Self = if cfg!(target_endian = "big") { Self::Big } else { Self::Little };
+
+    /// The order an `:f32tagged` binary's magic was written in
+    pub fn detect(tag: [u8; TAG_LEN]) -> Option<Self> {
+        if u32::from_le_bytes(tag) == ENDIAN_MAGIC {
+            Some(Self::Little)
+        } else if u32::from_be_bytes(tag) == ENDIAN_MAGIC {
+            Some(Self::Big)
+        } else {
+            None
+        }
+    }
+
+    fn tag(self) -> [u8; TAG_LEN] {
+        match self {
+            Self::Little => ENDIAN_MAGIC.to_le_bytes(),
+            Self::Big => ENDIAN_MAGIC.to_be_bytes(),
+        }
+    }
+
+    fn decode_f32(self, bytes: &[u8]) -> Option<Vec<f64>> {
+        let samples = match self {
+            Self::Little => convert::f32s_from_le_bytes(bytes),
+            Self::Big => convert::f32s_from_be_bytes(bytes),
+        };
+        samples.map(|v| v.into_iter().map(|x| x as f64).collect())
+    }
+
+    fn encode_f32(self, samples: &[f64], out: &mut [u8]) {
+        let narrowed: Vec<f32> = samples.iter().map(|&x| x as f32).collect();
+        match self {
+            Self::Little => convert::f32s_to_le_bytes(&narrowed, out),
+            Self::Big => convert::f32s_to_be_bytes(&narrowed, out),
+        }
+    }
+}
+
+/// Why a binary couldn't be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Not a whole number of samples
+    SampleSize,
+    /// An `:f32tagged` binary without ENDIAN_MAGIC in either order
+    EndianMagic,
+}
+
+impl DecodeError {
+    /// The error as a snake_case reason, for NIF errors
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SampleSize => "invalid_sample_size",
+            Self::EndianMagic => "bad_endian_magic",
+        }
+    }
+}
+
+impl SampleFormat {
+    pub fn from_atom(atom: Atom) -> Option<Self> {
+        if atom == atoms::f32ne() {
+            Some(Self::F32Ne)
+        } else if atom == atoms::f64ne() {
+            Some(Self::F64Ne)
+        } else if atom == atoms::s24le() {
+            Some(Self::S24Le)
+        } else if atom == atoms::f32le() {
+            Some(Self::F32Le)
+        } else if atom == atoms::f32be() {
+            Some(Self::F32Be)
+        } else if atom == atoms::f32tagged() {
+            Some(Self::F32Tagged)
+        } else {
+            None
+        }
+    }
+
+    pub fn bytes_per_sample(&self) -> usize {
+        match self {
+            Self::F32Ne | Self::F32Le | Self::F32Be | Self::F32Tagged => 4,
+            Self::F64Ne => 8,
+            Self::S24Le => 3,
+        }
+    }
+
+    /// Bytes `samples` samples encode to, with any leading tag
+    pub fn encoded_len(&self, samples: usize) -> usize {
+        let tag = if *self == Self::F32Tagged { TAG_LEN } else { 0 };
+        tag + samples * self.bytes_per_sample()
+    }
+
+    /// Decode a binary to f64 samples
+    pub fn decode(&self, bytes: &[u8]) -> Result<Vec<f64>, DecodeError> {
+        let samples = match self {
+            Self::F32Ne => Endian::NATIVE.decode_f32(bytes),
+            Self::F64Ne => convert::f64s_from_ne_bytes(bytes),
+            Self::S24Le => convert::s24le_to_f64s(bytes),
+            Self::F32Le => Endian::Little.decode_f32(bytes),
+            Self::F32Be => Endian::Big.decode_f32(bytes),
+            Self::F32Tagged => {
+                let tag = bytes.first_chunk::<TAG_LEN>().ok_or(DecodeError::EndianMagic)?;
+                let endian = Endian::detect(*tag).ok_or(DecodeError::EndianMagic)?;
+                endian.decode_f32(&bytes[TAG_LEN..])
+            }
+        };
+        samples.ok_or(DecodeError::SampleSize)
+    }
+
+    /// Encode f64 samples into `out` (at least `encoded_len(samples.len())` bytes)
+    pub fn encode(&self, samples: &[f64], out: &mut [u8]) {
+        match self {
+            Self::F32Ne => Endian::NATIVE.encode_f32(samples, out),
+            Self::F64Ne => convert::f64s_to_ne_bytes(samples, out),
+            Self::S24Le => convert::f64s_to_s24le(samples, out),
+            Self::F32Le => Endian::Little.encode_f32(samples, out),
+            Self::F32Be => Endian::Big.encode_f32(samples, out),
+            Self::F32Tagged => encode_tagged(Endian::NATIVE, samples, out),
+        }
+    }
+}
+
+/// `:f32tagged` as a host of byte order `endian` writes it
+fn encode_tagged(endian: Endian, samples: &[f64], out: &mut [u8]) {
+    out[..TAG_LEN].copy_from_slice(&endian.tag());
+    endian.encode_f32(samples, &mut out[TAG_LEN..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::{ChannelParams, WattersonChannel};
+    use std::f64::consts::PI;
+
+    const ALL_FORMATS: [SampleFormat; 6] = [
+        SampleFormat::F32Ne,
+        SampleFormat::F64Ne,
+        SampleFormat::S24Le,
+        SampleFormat::F32Le,
+        SampleFormat::F32Be,
+        SampleFormat::F32Tagged,
+    ];
+
+    #[test]
+    fn test_roundtrip_each_format() {
+        let samples = [0.0, 0.5, -0.25, -1.0];
+        for fmt in ALL_FORMATS {
+            let mut bytes = vec![0u8; fmt.encoded_len(samples.len())];
+            fmt.encode(&samples, &mut bytes);
+            assert_eq!(fmt.decode(&bytes).unwrap(), samples, "{:?}", fmt);
+            assert!(fmt.decode(&bytes[..bytes.len() - 1]).is_err(), "{:?} accepted a partial sample", fmt);
+        }
+    }
+
+    #[test]
+    fn test_f32_byte_order_fixtures() {
+        let samples = [1.0, -0.5];
+        let le = [0x00, 0x00, 0x80, 0x3f, 0x00, 0x00, 0x00, 0xbf];
+        let be = [0x3f, 0x80, 0x00, 0x00, 0xbf, 0x00, 0x00, 0x00];
+        for (fmt, fixture) in [(SampleFormat::F32Le, le), (SampleFormat::F32Be, be)] {
+            let mut bytes = [0u8; 8];
+            fmt.encode(&samples, &mut bytes);
+            assert_eq!(bytes, fixture, "{fmt:?}");
+            assert_eq!(fmt.decode(&fixture).unwrap(), samples, "{fmt:?}");
+        }
+
+        // Tagged, as each host writes it
+        let mut from_le_host = [0u8; 12];
+        encode_tagged(Endian::Little, &samples, &mut from_le_host);
+        assert_eq!(from_le_host, [&b"4FMM"[..], &le].concat()[..]);
+        let mut from_be_host = [0u8; 12];
+        encode_tagged(Endian::Big, &samples, &mut from_be_host);
+        assert_eq!(from_be_host, [&b"MMF4"[..], &be].concat()[..]);
+    }
+
+    #[test]
+    fn test_tagged_round_trip_between_hosts() {
+        let samples: Vec<f64> = (0..100).map(|i| (i as f64 * 0.37).sin() as f32 as f64).collect();
+        for writer in [Endian::Little, Endian::Big] {
+            let mut bytes = vec![0u8; SampleFormat::F32Tagged.encoded_len(samples.len())];
+            encode_tagged(writer, &samples, &mut bytes);
+            assert_eq!(Endian::detect(bytes[..4].try_into().unwrap()), Some(writer));
+            // Whatever order this host is, it reads either writer's stream
+            assert_eq!(SampleFormat::F32Tagged.decode(&bytes).unwrap(), samples, "{writer:?}");
+        }
+        // What this host writes carries its own order
+        let mut bytes = vec![0u8; SampleFormat::F32Tagged.encoded_len(samples.len())];
+        SampleFormat::F32Tagged.encode(&samples, &mut bytes);
+        assert_eq!(Endian::detect(bytes[..4].try_into().unwrap()), Some(Endian::NATIVE));
+    }
+
+    #[test]
+    fn test_tagged_without_magic_rejected() {
+        let fmt = SampleFormat::F32Tagged;
+        assert_eq!(fmt.decode(&[]), Err(DecodeError::EndianMagic));
+        assert_eq!(fmt.decode(b"MMF"), Err(DecodeError::EndianMagic));
+        // Untagged samples, as a writer that forgot the magic would send
+        let mut bare = [0u8; 8];
+        SampleFormat::F32Be.encode(&[1.0, -0.5], &mut bare);
+        assert_eq!(fmt.decode(&bare), Err(DecodeError::EndianMagic));
+        assert_eq!(fmt.decode(b"MMF4\0\0"), Err(DecodeError::SampleSize));
+        assert_eq!(fmt.decode(b"MMF4"), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_s24_fixture_through_decode() {
+        let bytes = [0x00, 0x00, 0x40, 0xff, 0xff, 0xff];
+        assert_eq!(
+            SampleFormat::S24Le.decode(&bytes).unwrap(),
+            vec![0.5, -1.0 / convert::I24_FULL_SCALE]
+        );
+    }
+
+    #[test]
+    fn test_bypass_is_bit_exact_in_each_format() {
+        let params = ChannelParams {
+            delay_spread_samples: 10,
+            doppler_bandwidth_hz: 1.0,
+            bulk_delay_samples: 100,
+            output_bits: 8,
+            output_dither: true,
+            clip_knee: 0.5,
+            bypass: true,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
+        };
+        let samples: Vec<f64> = (0..1000).map(|i| 0.9 * (i as f64 * 0.013).sin() + 1e-9 * i as f64).collect();
+        for fmt in ALL_FORMATS {
+            let mut input = vec![0u8; fmt.encoded_len(samples.len())];
+            fmt.encode(&samples, &mut input);
+
+            // Same path as process_block_fmt
+            let decoded = fmt.decode(&input).unwrap();
+            let output = WattersonChannel::new(params.clone(), 1932).process_f64(&decoded);
+            let mut encoded = vec![0u8; fmt.encoded_len(output.len())];
+            fmt.encode(&output, &mut encoded);
+
+            assert_eq!(encoded, input, "{:?}", fmt);
+        }
+    }
+
+    /// Recover a -120 dBFS tone riding under a full-scale carrier
+    ///
+    /// The channel is linear apart from additive noise, so with identical
+    /// seeds out(big + small) - out(big) should equal out(small) - out(0).
+    /// Returns the residual relative to the small tone's response (rms).
+    fn small_tone_residual(fmt: SampleFormat) -> f64 {
+        let params = ChannelParams {
+            delay_spread_samples: 10,
+            doppler_bandwidth_hz: 0.5,
+            snr_db: 200.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
+        };
+        let n = 4096;
+        let small_amp = 1e-6; // -120 dBFS
+        let big: Vec<f64> = (0..n).map(|i| 0.9 * (2.0 * PI * 1800.0 * i as f64 / 9600.0).sin()).collect();
+        let small: Vec<f64> = (0..n).map(|i| small_amp * (2.0 * PI * 1500.0 * i as f64 / 9600.0).sin()).collect();
+        let mixed: Vec<f64> = big.iter().zip(&small).map(|(a, b)| a + b).collect();
+        let zeros = vec![0.0; n];
+
+        // Encode → channel → encode again, as process_block_fmt does
+        let run = |input: &[f64]| -> Vec<f64> {
+            let mut bytes = vec![0u8; fmt.encoded_len(n)];
+            fmt.encode(input, &mut bytes);
+            let decoded = fmt.decode(&bytes).unwrap();
+            let out = WattersonChannel::new(params.clone(), 1919).process_f64(&decoded);
+            fmt.encode(&out, &mut bytes);
+            fmt.decode(&bytes).unwrap()
+        };
+
+        let (out_mixed, out_big, out_small, out_zero) = (run(&mixed), run(&big), run(&small), run(&zeros));
+
+        let mut err = 0.0;
+        let mut sig = 0.0;
+        // Skip the filter warm-up
+        for i in 100..n {
+            let extracted = out_mixed[i] - out_big[i];
+            let reference = out_small[i] - out_zero[i];
+            err += (extracted - reference).powi(2);
+            sig += reference.powi(2);
+        }
+        (err / sig).sqrt()
+    }
+
+    #[test]
+    fn test_low_level_tone_survives_f64_but_not_f32() {
+        let f64_residual = small_tone_residual(SampleFormat::F64Ne);
+        let f32_residual = small_tone_residual(SampleFormat::F32Ne);
+
+        assert!(f64_residual < 0.01, "f64 path residual {:.2e}", f64_residual);
+        assert!(f32_residual > 0.01, "f32 path residual {:.2e}", f32_residual);
+    }
+}
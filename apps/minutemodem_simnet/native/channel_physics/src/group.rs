@@ -0,0 +1,260 @@
+//! Channel groups: shared Doppler and SNR weather across many channels
+//!
+//! Links through the same part of the ionosphere don't drift
+//! independently: a disturbance spreads everyone's Doppler and drops
+//! everyone's SNR together. A group models that as two slow Gaussian
+//! processes, an SNR offset (dB) and a Doppler spread offset (Hz), which
+//! every member channel adds to its own snr_db and doppler_bandwidth_hz.
+//!
+//! Each process is a sum of sinusoids, like the fading taps, but with
+//! frequencies drawn from a Cauchy distribution of scale 1/(2πT):
+//!
+//!   x(t) = σ √(2/N) Σ cos(2π f_n t + φ_n)
+//!
+//! whose autocorrelation is σ² E[cos(2π f τ)] = σ² exp(-|τ|/T), that of a
+//! Gauss-Markov process with correlation time T. Being a closed form in
+//! t, the offsets at any simulation time depend only on the group's seed,
+//! not on which member gets there first or in what blocks.
+//!
+//! A member samples the offsets at the start of each block it processes
+//! or advances, at its own simulation time in seconds. The SNR offset
+//! sets its noise level; the Doppler offset runs its fading taps' clock
+//! faster or slower (by (|D| + offset) / |D|, never below standstill), so
+//! the realization carries on without a phase jump. A channel without
+//! fading has no Doppler to scale and only follows the SNR.
+
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rustler::NifMap;
+
+/// Sinusoids per offset process
+const GROUP_SINUSOIDS: usize = 32;
+
+/// How a group's weather varies
+#[derive(NifMap, Debug, Clone, PartialEq)]
+pub struct GroupParams {
+    /// Time for the offsets to decorrelate to 1/e, seconds
+    pub correlation_time_s: f64,
+    /// Standard deviation of the SNR offset, dB
+    pub snr_sigma_db: f64,
+    /// Standard deviation of the Doppler spread offset, Hz
+    pub doppler_sigma_hz: f64,
+    pub seed: u64,
+}
+
+impl GroupParams {
+    /// Check the params are usable: all finite, a positive correlation
+    /// time and sigmas of at least zero
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let terms = [self.correlation_time_s, self.snr_sigma_db, self.doppler_sigma_hz];
+        if terms.iter().any(|x| !x.is_finite())
+            || self.correlation_time_s <= 0.0
+            || self.snr_sigma_db < 0.0
+            || self.doppler_sigma_hz < 0.0
+        {
+            return Err("invalid_group_params");
+        }
+        Ok(())
+    }
+}
+
+/// What a group adds to its members' parameters
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GroupOffsets {
+    pub snr_db: f64,
+    pub doppler_hz: f64,
+}
+
+/// A group's state for telemetry
+#[derive(NifMap, Debug, Clone, PartialEq)]
+pub struct GroupState {
+    /// Furthest simulation time any member has reached, seconds
+    pub time_s: f64,
+    /// Offsets at time_s
+    pub snr_offset_db: f64,
+    pub doppler_offset_hz: f64,
+    /// Live member channels, in the order they joined
+    pub members: Vec<u64>,
+}
+
+/// One zero-mean Gaussian process with exponential autocorrelation
+struct OffsetProcess {
+    freq: [f64; GROUP_SINUSOIDS],
+    phase: [f64; GROUP_SINUSOIDS],
+    scale: f64,
+}
+
+impl OffsetProcess {
+    fn new(sigma: f64, correlation_time_s: f64, rng: &mut ChaCha8Rng) -> Self {
+        let gamma = 1.0 / (2.0 * PI * correlation_time_s);
+        let mut freq = [0.0; GROUP_SINUSOIDS];
+        let mut phase = [0.0; GROUP_SINUSOIDS];
+        for n in 0..GROUP_SINUSOIDS {
+            // Cauchy by inverse CDF
+            freq[n] = gamma * (PI * (rng.gen::<f64>() - 0.5)).tan();
+            phase[n] = rng.gen::<f64>() * 2.0 * PI;
+        }
+        Self {
+            freq,
+            phase,
+            scale: sigma * (2.0 / GROUP_SINUSOIDS as f64).sqrt(),
+        }
+    }
+
+    fn at(&self, t: f64) -> f64 {
+        let sum: f64 = self.freq.iter().zip(&self.phase).map(|(f, p)| (2.0 * PI * f * t + p).cos()).sum();
+        self.scale * sum
+    }
+}
+
+/// A group's offsets as a function of simulation time
+///
+/// Immutable once built, so members share it behind an Arc and sample it
+/// without any locking.
+pub struct GroupWeather {
+    snr: OffsetProcess,
+    doppler: OffsetProcess,
+}
+
+impl GroupWeather {
+    pub fn new(params: &GroupParams) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(params.seed);
+        let snr = OffsetProcess::new(params.snr_sigma_db, params.correlation_time_s, &mut rng);
+        let doppler = OffsetProcess::new(params.doppler_sigma_hz, params.correlation_time_s, &mut rng);
+        Self { snr, doppler }
+    }
+
+    /// The offsets `t` seconds into the simulation
+    pub fn offsets_at(&self, t: f64) -> GroupOffsets {
+        GroupOffsets {
+            snr_db: self.snr.at(t),
+            doppler_hz: self.doppler.at(t),
+        }
+    }
+
+    /// State at `time_s` with these members
+    pub fn state(&self, time_s: f64, members: Vec<u64>) -> GroupState {
+        let offsets = self.offsets_at(time_s);
+        GroupState {
+            time_s,
+            snr_offset_db: offsets.snr_db,
+            doppler_offset_hz: offsets.doppler_hz,
+            members,
+        }
+    }
+}
+
+/// A group and its members, by slab id in joining order
+///
+/// Members that have since been destroyed stay listed until the group is
+/// disbanded; lookups just skip them.
+pub struct ChannelGroup {
+    pub weather: Arc<GroupWeather>,
+    pub members: Vec<u64>,
+}
+
+impl ChannelGroup {
+    /// Bytes held by the member list and the weather (which members
+    /// share, so it's counted here only)
+    pub fn memory_bytes(&self) -> usize {
+        self.members.capacity() * std::mem::size_of::<u64>() + std::mem::size_of::<GroupWeather>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::{ChannelParams, WattersonChannel};
+
+    fn awgn_params(sample_rate: u32, snr_db: f64) -> ChannelParams {
+        ChannelParams {
+            sample_rate,
+            snr_db,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
+        }
+    }
+
+    fn weather(seed: u64) -> Arc<GroupWeather> {
+        let params = GroupParams {
+            correlation_time_s: 120.0,
+            snr_sigma_db: 4.0,
+            doppler_sigma_hz: 0.5,
+            seed,
+        };
+        params.validate().unwrap();
+        Arc::new(GroupWeather::new(&params))
+    }
+
+    /// Noise power out of silence, dB, over a short block
+    fn noise_db(channel: &mut WattersonChannel) -> f64 {
+        let output = channel.process(&[0.0; 2000]);
+        let power = output.iter().map(|&y| (y as f64).powi(2)).sum::<f64>() / output.len() as f64;
+        10.0 * power.log10()
+    }
+
+    /// Noise power measured every 30 s over a fast-forwarded hour
+    fn hourly_noise(channel: &mut WattersonChannel, sample_rate: usize) -> Vec<f64> {
+        (0..120)
+            .map(|_| {
+                channel.advance(30 * sample_rate - 2000);
+                noise_db(channel)
+            })
+            .collect()
+    }
+
+    fn correlation(a: &[f64], b: &[f64]) -> f64 {
+        let mean = |x: &[f64]| x.iter().sum::<f64>() / x.len() as f64;
+        let (ma, mb) = (mean(a), mean(b));
+        let cov: f64 = a.iter().zip(b).map(|(x, y)| (x - ma) * (y - mb)).sum();
+        let var = |x: &[f64], m: f64| x.iter().map(|v| (v - m).powi(2)).sum::<f64>();
+        cov / (var(a, ma) * var(b, mb)).sqrt()
+    }
+
+    #[test]
+    fn test_members_share_snr_weather_and_groups_differ() {
+        let (north, south) = (weather(1), weather(2));
+        let mut a = WattersonChannel::new(awgn_params(8000, 20.0), 10);
+        let mut b = WattersonChannel::new(awgn_params(9600, 10.0), 11);
+        let mut c = WattersonChannel::new(awgn_params(8000, 20.0), 12);
+        a.join_group(north.clone());
+        b.join_group(north);
+        c.join_group(south);
+
+        let na = hourly_noise(&mut a, 8000);
+        let nb = hourly_noise(&mut b, 9600);
+        let nc = hourly_noise(&mut c, 8000);
+
+        // Each varies by several dB over the hour
+        let spread = |x: &[f64]| x.iter().cloned().fold(f64::MIN, f64::max) - x.iter().cloned().fold(f64::MAX, f64::min);
+        for noise in [&na, &nb, &nc] {
+            assert!(spread(noise) > 6.0, "spread {}", spread(noise));
+        }
+        let same = correlation(&na, &nb);
+        let different = correlation(&na, &nc);
+        assert!(same > 0.95, "same group {}", same);
+        assert!(different.abs() < 0.5, "different groups {}", different);
+    }
+
+    #[test]
+    fn test_leaving_group_restores_base_params() {
+        let mut grouped = WattersonChannel::new(awgn_params(8000, 20.0), 3);
+        let mut base = WattersonChannel::new(awgn_params(8000, 20.0), 3);
+        grouped.join_group(weather(5));
+        grouped.advance(8000 * 600);
+        assert_ne!(grouped.group_offsets(), GroupOffsets::default());
+
+        grouped.leave_group();
+        assert_eq!(grouped.group_offsets(), GroupOffsets::default());
+        base.advance(8000 * 600);
+        for _ in 0..10 {
+            grouped.advance(8000 * 60);
+            base.advance(8000 * 60);
+            assert_eq!(grouped.group_offsets(), GroupOffsets::default());
+            assert!((noise_db(&mut grouped) - noise_db(&mut base)).abs() < 0.5);
+        }
+    }
+}
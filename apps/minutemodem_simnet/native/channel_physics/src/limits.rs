@@ -0,0 +1,102 @@
+//! Upper bounds on arguments that size allocations
+//!
+//! A channel allocates its echo delay line from `delay_spread_samples`
+//! and buffers up to `bulk_delay_samples` of input, so both are checked
+//! here (with the rest of ChannelParams that scales memory) before a
+//! channel is built or updated. A value over its maximum is reported as
+//! `{:error, {:out_of_range, param, max}}`.
+
+pub use minutemodem_dsp::limits::{check_max, OutOfRange};
+use rustler::{Atom, Encoder, Env, Term};
+
+use crate::channel::ChannelParams;
+
+/// Highest sample rate, Hz
+pub const MAX_SAMPLE_RATE: u32 = 192_000;
+
+/// Longest echo path delay (100 ms at 192 kHz; Appendix E goes to 10 ms)
+pub const MAX_DELAY_SPREAD_SAMPLES: u32 = 19_200;
+
+/// Longest bulk propagation delay (1 s at 192 kHz)
+pub const MAX_BULK_DELAY_SAMPLES: u32 = 192_000;
+
+/// Most entries a channel's audit log may keep
+pub const MAX_AUDIT_CAP: usize = 65_536;
+
+/// Most members of a correlated set
+pub const MAX_CORRELATED_OUTPUTS: usize = 64;
+
+/// Longest block process_block takes (10 s at 192 kHz, 200 s at 9600 Hz)
+///
+/// The call runs on a dirty CPU scheduler, so its length doesn't upset
+/// the BEAM, but it holds its channel's lock and two copies of the block
+/// throughout: at around a microsecond a sample this is a couple of
+/// seconds and 15 MB. Audio-rate blocks (200 ms is 1920 samples at 9600
+/// Hz) are three orders of magnitude inside it.
+pub const MAX_BLOCK_SAMPLES: usize = 1_920_000;
+
+/// `{:out_of_range, param, max}`: OutOfRange as the NIFs return it
+struct RangeError(OutOfRange);
+
+impl Encoder for RangeError {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        let atom = |name: &str| Atom::from_str(env, name).expect("short ASCII atom names");
+        (atom("out_of_range"), atom(self.0.param), self.0.max).encode(env)
+    }
+}
+
+/// The `{:error, {:out_of_range, param, max}}` a NIF returns for `e`
+pub fn range_error(e: OutOfRange) -> rustler::Error {
+    rustler::Error::Term(Box::new(RangeError(e)))
+}
+
+/// Check the ChannelParams fields that size a channel's buffers
+pub fn validate_params(params: &ChannelParams) -> Result<(), OutOfRange> {
+    check_max("sample_rate", params.sample_rate.into(), MAX_SAMPLE_RATE.into())?;
+    check_max("delay_spread_samples", params.delay_spread_samples.into(), MAX_DELAY_SPREAD_SAMPLES.into())?;
+    check_max("bulk_delay_samples", params.bulk_delay_samples.into(), MAX_BULK_DELAY_SAMPLES.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> ChannelParams {
+        ChannelParams {
+            delay_spread_samples: 10,
+            doppler_bandwidth_hz: 1.0,
+            snr_db: 20.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_params() {
+        assert_eq!(validate_params(&params()), Ok(()));
+        let at_max = ChannelParams {
+            sample_rate: MAX_SAMPLE_RATE,
+            delay_spread_samples: MAX_DELAY_SPREAD_SAMPLES,
+            bulk_delay_samples: MAX_BULK_DELAY_SAMPLES,
+            ..params()
+        };
+        assert_eq!(validate_params(&at_max), Ok(()));
+
+        let cases = [
+            (ChannelParams { sample_rate: u32::MAX, ..params() }, "sample_rate", MAX_SAMPLE_RATE),
+            (
+                ChannelParams { delay_spread_samples: 10_000_000, ..params() },
+                "delay_spread_samples",
+                MAX_DELAY_SPREAD_SAMPLES,
+            ),
+            (
+                ChannelParams { bulk_delay_samples: u32::MAX, ..params() },
+                "bulk_delay_samples",
+                MAX_BULK_DELAY_SAMPLES,
+            ),
+        ];
+        for (p, param, max) in cases {
+            assert_eq!(validate_params(&p), Err(OutOfRange { param, max: max.into() }));
+        }
+    }
+}
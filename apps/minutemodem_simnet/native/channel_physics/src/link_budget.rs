@@ -0,0 +1,258 @@
+//! Link budget: the channel's snr_db from real-world terms
+//!
+//! Scenario authors think in transmit power, path loss and noise figure;
+//! the channel's snr_db is the power of a 0.5-amplitude sinusoid over its
+//! AWGN across the whole band up to Nyquist. The budget is worked out the
+//! usual way, in the receiver's bandwidth B:
+//!
+//! ```text
+//! S = P_tx - L                                      dBm
+//! N = -174 + 10 log10(B) + 10 log10(f_a + f_r - 1)  dBm
+//! ```
+//!
+//! -174 dBm/Hz being kT0 at 290 K, f_a the antenna's external noise factor
+//! from the ITU-R P.372 man-made noise curve for the environment, and f_r
+//! the receiver's, from its noise figure (a lossless antenna is assumed).
+//!
+//! SNR = S - N then goes onto the channel assuming the modem drives it at
+//! the reference level: its signal power is the sinusoid's, and its
+//! bandwidth is B. The channel's noise is left white to Nyquist, fs/2
+//! (noise_bandwidth_hz 0), of which B sees 2B/fs, so
+//! snr_db = SNR + 10 log10(2B/fs).
+
+use rustler::{NifMap, NifUnitEnum};
+
+use crate::channel::ChannelParams;
+use crate::exchange::{DEFAULT_CARRIER_HZ, DEFAULT_SAMPLE_RATE};
+
+/// Thermal noise density at 290 K, dBm/Hz
+pub const KT0_DBM_PER_HZ: f64 = -174.0;
+
+/// Frequencies the P.372 man-made noise curves hold over, Hz
+pub const P372_RANGE_HZ: std::ops::RangeInclusive<f64> = 0.3e6..=250e6;
+
+/// Man-made noise environment, as ITU-R P.372 categorizes it
+#[derive(NifUnitEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseEnvironment {
+    City,
+    Residential,
+    Rural,
+    QuietRural,
+}
+
+impl NoiseEnvironment {
+    /// Median external noise figure F_am at `freq_hz`, dB above kT0
+    ///
+    /// P.372's curves are straight in log frequency: F_am = c - d log10(f),
+    /// f in MHz.
+    pub fn noise_figure_db(self, freq_hz: f64) -> f64 {
+        let (c, d) = match self {
+            NoiseEnvironment::City => (76.8, 27.7),
+            NoiseEnvironment::Residential => (72.5, 27.7),
+            NoiseEnvironment::Rural => (67.2, 27.7),
+            NoiseEnvironment::QuietRural => (53.6, 28.6),
+        };
+        c - d * (freq_hz / 1e6).log10()
+    }
+}
+
+/// Fading conditions for the two-path model, after ITU-R F.1487
+#[derive(NifUnitEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FadingPreset {
+    /// One unfaded path
+    Awgn,
+    Good,
+    Moderate,
+    Poor,
+    Flutter,
+}
+
+impl FadingPreset {
+    /// Differential delay (ms) and Doppler spread (Hz)
+    pub fn delay_and_doppler(self) -> (f64, f64) {
+        match self {
+            FadingPreset::Awgn => (0.0, 0.0),
+            FadingPreset::Good => (0.5, 0.1),
+            FadingPreset::Moderate => (1.0, 0.5),
+            FadingPreset::Poor => (2.0, 1.0),
+            FadingPreset::Flutter => (0.5, 10.0),
+        }
+    }
+}
+
+/// One link's budget terms
+#[derive(NifMap, Debug, Clone, PartialEq)]
+pub struct LinkBudget {
+    pub tx_power_dbm: f64,
+    /// Everything between the transmitter and receiver terminals,
+    /// antenna gains included
+    pub path_loss_db: f64,
+    pub noise_figure_db: f64,
+    /// Receiver bandwidth the SNR is taken in, Hz
+    pub bandwidth_hz: f64,
+    pub noise_environment: NoiseEnvironment,
+    /// RF frequency, for the external noise
+    pub freq_hz: f64,
+}
+
+impl LinkBudget {
+    /// Check the terms are usable: all finite, a positive bandwidth and a
+    /// frequency the P.372 curves cover
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let terms = [self.tx_power_dbm, self.path_loss_db, self.noise_figure_db, self.bandwidth_hz, self.freq_hz];
+        if terms.iter().any(|x| !x.is_finite())
+            || self.noise_figure_db < 0.0
+            || self.bandwidth_hz <= 0.0
+            || !P372_RANGE_HZ.contains(&self.freq_hz)
+        {
+            return Err("invalid_link_budget");
+        }
+        Ok(())
+    }
+
+    /// Noise power in the bandwidth, dBm: external and receiver noise
+    pub fn noise_power_dbm(&self) -> f64 {
+        let external = db_to_ratio(self.noise_environment.noise_figure_db(self.freq_hz));
+        let receiver = db_to_ratio(self.noise_figure_db);
+        KT0_DBM_PER_HZ + ratio_to_db(self.bandwidth_hz) + ratio_to_db(external + receiver - 1.0)
+    }
+
+    /// SNR in the bandwidth, dB
+    pub fn snr_db(&self) -> f64 {
+        self.tx_power_dbm - self.path_loss_db - self.noise_power_dbm()
+    }
+
+    /// The path loss giving `snr_db` in the bandwidth; path_loss_db is
+    /// ignored
+    pub fn path_loss_for_snr(&self, snr_db: f64) -> f64 {
+        self.tx_power_dbm - self.noise_power_dbm() - snr_db
+    }
+}
+
+/// The channel's snr_db for an SNR of `snr_db` in `bandwidth_hz`
+///
+/// The bandwidth must be within Nyquist.
+pub fn channel_snr_db(snr_db: f64, bandwidth_hz: f64, sample_rate: u32) -> f64 {
+    snr_db + ratio_to_db(2.0 * bandwidth_hz / sample_rate as f64)
+}
+
+/// ChannelParams at the budget's SNR with `preset`'s fading, at the
+/// exchange format's default sample rate and carrier
+///
+/// Returns the SNR in the bandwidth with them. Fails with
+/// "invalid_link_budget" if the terms are unusable or the bandwidth is
+/// wider than the channel's Nyquist.
+pub fn channel_params(budget: &LinkBudget, preset: FadingPreset) -> Result<(f64, ChannelParams), &'static str> {
+    budget.validate()?;
+    let sample_rate = DEFAULT_SAMPLE_RATE;
+    if budget.bandwidth_hz > sample_rate as f64 / 2.0 {
+        return Err("invalid_link_budget");
+    }
+    let snr_db = budget.snr_db();
+    let (delay_ms, doppler_hz) = preset.delay_and_doppler();
+    let params = ChannelParams {
+        sample_rate,
+        delay_spread_samples: (delay_ms * sample_rate as f64 / 1000.0).round() as u32,
+        doppler_bandwidth_hz: doppler_hz,
+        snr_db: channel_snr_db(snr_db, budget.bandwidth_hz, sample_rate),
+        carrier_freq_hz: DEFAULT_CARRIER_HZ,
+        noise_bandwidth_hz: 0.0,
+        ..ChannelParams::default()
+    };
+    Ok((snr_db, params))
+}
+
+fn db_to_ratio(db: f64) -> f64 {
+    10f64.powf(db / 10.0)
+}
+
+fn ratio_to_db(ratio: f64) -> f64 {
+    10.0 * ratio.log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget() -> LinkBudget {
+        LinkBudget {
+            tx_power_dbm: 50.0,
+            path_loss_db: 120.0,
+            noise_figure_db: 10.0,
+            bandwidth_hz: 3000.0,
+            noise_environment: NoiseEnvironment::Residential,
+            freq_hz: 10e6,
+        }
+    }
+
+    #[test]
+    fn test_p372_curves() {
+        // c - d log10(f_MHz), by hand
+        let cases = [
+            (NoiseEnvironment::City, 10e6, 49.1),
+            (NoiseEnvironment::Residential, 10e6, 44.8),
+            (NoiseEnvironment::Rural, 1e6, 67.2),
+            (NoiseEnvironment::QuietRural, 100e6, -3.6),
+            (NoiseEnvironment::Residential, 3e6, 59.283_741_244),
+        ];
+        for (environment, freq_hz, expected) in cases {
+            let got = environment.noise_figure_db(freq_hz);
+            assert!((got - expected).abs() < 1e-9, "{environment:?} at {freq_hz} Hz: {got}");
+        }
+    }
+
+    #[test]
+    fn test_budget_arithmetic() {
+        // Residential at 10 MHz: F_a = 44.8 dB swamps the 10 dB receiver,
+        // 10 log10(10^4.48 + 10 - 1) = 44.801 dB; B = 3 kHz adds 34.771
+        let budget = budget();
+        let noise = -174.0 + 34.771_212_547 + 44.801_294_083;
+        assert!((budget.noise_power_dbm() - noise).abs() < 1e-6, "{}", budget.noise_power_dbm());
+        assert!((budget.snr_db() - (50.0 - 120.0 - noise)).abs() < 1e-6);
+
+        // Quiet rural at 30 MHz leaves the receiver's noise to count:
+        // F_a = 53.6 - 28.6 log10(30) = 11.355 dB
+        let quiet = LinkBudget { noise_environment: NoiseEnvironment::QuietRural, freq_hz: 30e6, ..budget };
+        let f_a = 53.6 - 28.6 * 30f64.log10();
+        let combined = 10.0 * (10f64.powf(f_a / 10.0) + 10.0 - 1.0).log10();
+        assert!((quiet.noise_power_dbm() - (-174.0 + 34.771_212_547 + combined)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_path_loss_for_snr_inverts() {
+        let budget = budget();
+        let loss = budget.path_loss_for_snr(10.0);
+        let at_loss = LinkBudget { path_loss_db: loss, ..budget };
+        assert!((at_loss.snr_db() - 10.0).abs() < 1e-9);
+        // 50 dBm over a -94.43 dBm noise floor is 144.43 dB, less 10
+        assert!((loss - 134.427_493_370).abs() < 1e-6, "{loss}");
+    }
+
+    #[test]
+    fn test_channel_params_from_budget() {
+        let budget = budget();
+        let (snr_db, params) = channel_params(&budget, FadingPreset::Poor).unwrap();
+        assert_eq!(snr_db, budget.snr_db());
+        // 3 kHz of the 4.8 kHz to Nyquist: 10 log10(0.625) = -2.041 dB
+        assert!((params.snr_db - (snr_db - 2.041_199_827)).abs() < 1e-6);
+        assert_eq!(params.delay_spread_samples, 19);
+        assert_eq!(params.doppler_bandwidth_hz, 1.0);
+
+        let (_, awgn) = channel_params(&budget, FadingPreset::Awgn).unwrap();
+        assert_eq!((awgn.delay_spread_samples, awgn.doppler_bandwidth_hz), (0, 0.0));
+    }
+
+    #[test]
+    fn test_invalid_budgets() {
+        let budget = budget();
+        for bad in [
+            LinkBudget { bandwidth_hz: 0.0, ..budget.clone() },
+            LinkBudget { bandwidth_hz: 6000.0, ..budget.clone() },
+            LinkBudget { freq_hz: 100e3, ..budget.clone() },
+            LinkBudget { tx_power_dbm: f64::NAN, ..budget.clone() },
+            LinkBudget { noise_figure_db: -1.0, ..budget.clone() },
+        ] {
+            assert_eq!(channel_params(&bad, FadingPreset::Good), Err("invalid_link_budget"), "{bad:?}");
+        }
+    }
+}
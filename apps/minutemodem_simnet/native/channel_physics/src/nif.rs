@@ -0,0 +1,1554 @@
+//! NIF entry points
+//!
+//! Channels live in a global slab and are addressed by id from Elixir.
+//!
+//! ## Simulation time
+//!
+//! No NIF ever sleeps or reads the wall clock. A channel's only clock is
+//! its sample index, which moves exactly as far as process*/advance/
+//! run_until move it, and everything time-based (fade alarm events, the
+//! warm start's fading offset) is defined against it. A scenario runs as
+//! fast as it can make those calls: pacing to real time is up to the
+//! caller.
+//!
+//! ## Input level warnings
+//!
+//! Float inputs are full scale at ±1.0. A block peaking far above that
+//! was almost certainly i16 PCM passed through without scaling (see
+//! minutemodem_dsp::level). The call still succeeds, but
+//! {:input_warning, :suspicious_input_level, channel_id, peak} goes to
+//! the pid registered with set_warning_logger/1, unless warnings were
+//! turned off for that channel with set_input_warnings/2.
+//!
+//! ## Panics
+//!
+//! A NIF that reaches the slab runs inside guarded(), so a panic (a bug)
+//! comes back as {:error, {:panic, message}} rather than raising. A
+//! channel whose closure panicked is reset to idle by the next call that
+//! reaches it (see slab::Recover), keeping its parameters and RNG
+//! positions, and carries on from there.
+
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+use minutemodem_dsp::census::{Census, CensusCount};
+use minutemodem_dsp::{convert, guard, level, wire};
+use rustler::{Atom, Binary, Encoder, Env, LocalPid, MapIterator, NifResult, OwnedBinary, Term};
+
+use crate::audit::{AuditChange, AuditEntry};
+use crate::builder::{self, Derived};
+use crate::tr_switch::TrState;
+use crate::channel::{self, ChannelParams, Discontinuity, RngReset, WattersonChannel};
+use crate::correlated::{self, CorrelatedSet};
+use crate::conditioning;
+use crate::drift;
+use crate::exchange;
+use crate::fade_alarm::{FadeAlarm, FadeEdge, FadeEvent};
+use crate::fractional_delay;
+use crate::format::SampleFormat;
+use crate::group::{ChannelGroup, GroupParams, GroupState, GroupWeather};
+use crate::limits::{self, MAX_AUDIT_CAP, MAX_BLOCK_SAMPLES, MAX_CORRELATED_OUTPUTS};
+use crate::link_budget::{self, FadingPreset, LinkBudget};
+use crate::output;
+use crate::phase_log::{self, PhaseEntry, PhaseLog};
+use crate::presets::{self, ChannelPreset};
+use crate::slab::{ChannelSlab, Recover};
+
+// Global slab for channel storage - now with per-channel locking
+// What each slab holds, for census/0 (see minutemodem_dsp::census)
+static CHANNEL_CENSUS: Census = Census::new("channels");
+static SET_CENSUS: Census = Census::new("correlated_sets");
+static GROUP_CENSUS: Census = Census::new("groups");
+
+lazy_static::lazy_static! {
+    static ref CHANNELS: ChannelSlab<WattersonChannel> =
+        ChannelSlab::new(1024).with_census(&CHANNEL_CENSUS, WattersonChannel::memory_bytes);
+    // Correlated sets; their members live in CHANNELS
+    static ref SETS: ChannelSlab<CorrelatedSet> =
+        ChannelSlab::new(256).with_census(&SET_CENSUS, CorrelatedSet::memory_bytes);
+    // Channel groups; their members live in CHANNELS
+    static ref GROUPS: ChannelSlab<ChannelGroup> =
+        ChannelSlab::new(256).with_census(&GROUP_CENSUS, ChannelGroup::memory_bytes);
+    // Where each channel's fade events go (set_fade_alarm)
+    static ref FADE_SUBSCRIBERS: Mutex<HashMap<u64, LocalPid>> = Mutex::new(HashMap::new());
+    // Where input level warnings go (set_warning_logger)
+    static ref WARNING_LOGGER: Mutex<Option<LocalPid>> = Mutex::new(None);
+    // Channels whose input level warnings are off
+    static ref WARNINGS_OFF: Mutex<HashSet<u64>> = Mutex::new(HashSet::new());
+}
+
+mod atoms {
+    rustler::atoms! {
+        ok,
+        error,
+        channel_not_found,
+        preserve,
+        reseed,
+        silence,
+        fade,
+        start,
+        end,
+        input_warning,
+        suspicious_input_level,
+        created,
+        update_params,
+        reseed_noise,
+        hop_schedule,
+        tr_schedule,
+        none,
+        gap,
+        overlap,
+        auto,
+    }
+}
+
+/// A channel a panic left mid-block drops what it had in flight, as
+/// reset_channel(:preserve) would
+impl Recover for WattersonChannel {
+    fn recover(&mut self) {
+        self.reset_to_idle(RngReset::Preserve);
+    }
+}
+
+/// Membership and mixing never change after creation
+impl Recover for CorrelatedSet {
+    fn recover(&mut self) {}
+}
+
+/// A member is only listed once it is in CHANNELS, and the weather never
+/// changes
+impl Recover for ChannelGroup {
+    fn recover(&mut self) {}
+}
+
+/// Samples advance/2 runs under one hold of the channel's lock (5 s at
+/// 9600 Hz); see advance_chunked
+const ADVANCE_CHUNK_SAMPLES: usize = 48_000;
+
+/// A caught panic's message, encoded as {:panic, message}
+struct Panicked(String);
+
+impl Encoder for Panicked {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        let panic = Atom::from_str(env, "panic").expect("a short ASCII atom");
+        (panic, self.0.as_str()).encode(env)
+    }
+}
+
+/// Run a NIF body, turning a panic into {:error, {:panic, message}}
+fn guarded<T>(body: impl FnOnce() -> NifResult<T>) -> NifResult<T> {
+    guard::catch(body).unwrap_or_else(|message| Err(rustler::Error::Term(Box::new(Panicked(message)))))
+}
+
+/// Send {:fade, channel_id, :start | :end, sample_index} for each event
+fn notify_fades(env: Env, channel_id: u64, events: Vec<FadeEvent>) {
+    if events.is_empty() {
+        return;
+    }
+    let Some(pid) = FADE_SUBSCRIBERS.lock().unwrap_or_else(PoisonError::into_inner).get(&channel_id).copied() else {
+        return;
+    };
+    for event in events {
+        let edge = match event.edge {
+            FadeEdge::Start => atoms::start(),
+            FadeEdge::End => atoms::end(),
+        };
+        // A dead subscriber just misses the message
+        let _ = env.send(&pid, (atoms::fade(), channel_id, edge, event.sample_index));
+    }
+}
+
+/// Peak of `samples` if they look i16-scaled and the channel's warnings are on
+fn loud_peak<T: Copy + Into<f64>>(channel_id: u64, samples: &[T]) -> Option<f64> {
+    let off = WARNINGS_OFF.lock().unwrap_or_else(PoisonError::into_inner).contains(&channel_id);
+    if off {
+        return None;
+    }
+    level::loud_float_peak(samples)
+}
+
+/// Send {:input_warning, :suspicious_input_level, channel_id, peak} if
+/// the input looks mis-scaled and a logger is registered
+fn check_input_level<T: Copy + Into<f64>>(env: Env, channel_id: u64, samples: &[T]) {
+    let Some(pid) = *WARNING_LOGGER.lock().unwrap_or_else(PoisonError::into_inner) else {
+        return;
+    };
+    if let Some(peak) = loud_peak(channel_id, samples) {
+        // A dead logger just misses the message
+        let _ = env.send(&pid, (atoms::input_warning(), atoms::suspicious_input_level(), channel_id, peak));
+    }
+}
+
+/// Creates a new WattersonChannel and returns its slab handle.
+#[rustler::nif]
+fn create_channel(params: ChannelParams, seed: u64) -> NifResult<(rustler::Atom, u64)> {
+    guarded(|| {
+        insert_channel(params, seed)
+    })
+}
+
+/// Validates `params` and puts a new channel in the slab
+fn insert_channel(params: ChannelParams, seed: u64) -> NifResult<(rustler::Atom, u64)> {
+    let channel = new_channel(params, seed)?;
+
+    match CHANNELS.insert(channel) {
+        Some(id) => Ok((atoms::ok(), id)),
+        None => Err(rustler::Error::Term(Box::new("slab_full"))),
+    }
+}
+
+/// Validates `params` and builds a channel from them
+fn new_channel(params: ChannelParams, seed: u64) -> NifResult<WattersonChannel> {
+    validate_params(&params)?;
+    Ok(WattersonChannel::new(params, seed))
+}
+
+/// Everything create_channel checks of `params`
+fn validate_params(params: &ChannelParams) -> NifResult<()> {
+    limits::validate_params(params).map_err(limits::range_error)?;
+    output::validate(params.output_bits, params.clip_knee)
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    channel::validate_warm_start(params).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    channel::validate_tap_gains(params).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    channel::validate_frequency_offset(params).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    channel::validate_noise_bandwidth(params).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    channel::validate_impulse_noise(params).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    drift::validate(params.sample_rate_offset_ppm).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    conditioning::validate(params.sample_rate, params.input_tilt_db).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    fractional_delay::validate(params.fractional_delay_samples).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    Ok(())
+}
+
+/// Parses a scenario in the Watterson JSON exchange format (see exchange).
+#[rustler::nif]
+fn channel_params_from_json(json: Binary) -> NifResult<(rustler::Atom, ChannelParams)> {
+    let params = exchange::params_from_json(json.as_slice()).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    Ok((atoms::ok(), params))
+}
+
+/// Writes the exchange format's part of `params` as a JSON document.
+#[rustler::nif]
+fn channel_params_to_json(params: ChannelParams) -> NifResult<(rustler::Atom, String)> {
+    Ok((atoms::ok(), exchange::params_to_json(&params)))
+}
+
+/// SNR in the budget's bandwidth, and ChannelParams at it with `preset`'s
+/// fading (see link_budget).
+#[rustler::nif]
+fn link_budget(budget: LinkBudget, preset: FadingPreset) -> NifResult<(rustler::Atom, f64, ChannelParams)> {
+    let (snr_db, params) = link_budget::channel_params(&budget, preset).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    Ok((atoms::ok(), snr_db, params))
+}
+
+/// ChannelParams from a map of scenario-style terms, converted and
+/// defaulted (see builder), and the fields set as {name, value, source}.
+///
+/// Keys may be atoms or strings, values integers or floats. The result
+/// has passed create_channel's validation.
+#[rustler::nif]
+fn params_builder(terms: Term) -> NifResult<(rustler::Atom, ChannelParams, Vec<Derived>)> {
+    let iter = MapIterator::new(terms).ok_or(rustler::Error::BadArg)?;
+    let terms = iter
+        .map(|(key, value)| {
+            let key = key.atom_to_string().or_else(|_| key.decode::<String>())?;
+            let value = value.decode::<f64>().or_else(|_| value.decode::<i64>().map(|x| x as f64))?;
+            Ok((key, value))
+        })
+        .collect::<NifResult<Vec<_>>>()?;
+    let (params, derived) = builder::build(&terms).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    validate_params(&params)?;
+    Ok((atoms::ok(), params, derived))
+}
+
+/// Path loss that gives `snr_db` in the budget's bandwidth (its own
+/// path_loss_db is ignored).
+#[rustler::nif]
+fn path_loss_for_snr(budget: LinkBudget, snr_db: f64) -> NifResult<(rustler::Atom, f64)> {
+    budget.validate().map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    if !snr_db.is_finite() {
+        return Err(rustler::Error::Term(Box::new("invalid_link_budget")));
+    }
+    Ok((atoms::ok(), budget.path_loss_for_snr(snr_db)))
+}
+
+/// ChannelParams for a named condition (see presets) at `sample_rate`
+/// and `snr_db`. The result has passed create_channel's validation.
+#[rustler::nif]
+fn preset_params(preset: ChannelPreset, sample_rate: u32, snr_db: f64) -> NifResult<(rustler::Atom, ChannelParams)> {
+    let params = presets::channel_params(preset, sample_rate, snr_db).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    validate_params(&params)?;
+    Ok((atoms::ok(), params))
+}
+
+/// create_channel/2 with the parameters of a named condition.
+#[rustler::nif]
+fn create_channel_preset(preset: ChannelPreset, sample_rate: u32, snr_db: f64, seed: u64) -> NifResult<(rustler::Atom, u64)> {
+    guarded(|| {
+        let params = presets::channel_params(preset, sample_rate, snr_db).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        insert_channel(params, seed)
+    })
+}
+
+/// create_channel/2 with the parameters from a JSON scenario.
+#[rustler::nif]
+fn create_channel_from_json(json: Binary, seed: u64) -> NifResult<(rustler::Atom, u64)> {
+    guarded(|| {
+        let params = exchange::params_from_json(json.as_slice()).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        insert_channel(params, seed)
+    })
+}
+
+/// Processes a block of samples through the channel.
+/// Input: f32 samples as binary (native endian), at most
+/// MAX_BLOCK_SAMPLES
+/// Output: f32 samples as binary (native endian, same length unless the
+/// receiver's clock drifts, when its length is the exact sample count;
+/// see ChannelParams.sample_rate_offset_ppm)
+///
+/// Runs on a dirty CPU scheduler: a 200 ms block through the filters and
+/// fading takes well over the 1 ms a normal scheduler allows.
+#[rustler::nif(schedule = "DirtyCpu")]
+fn process_block<'a>(
+    env: Env<'a>,
+    channel_id: u64,
+    input: Binary,
+) -> NifResult<(rustler::Atom, Binary<'a>)> {
+    guarded(|| {
+        // Convert input binary to f32 samples
+        let samples = convert::f32s_from_ne_bytes(input.as_slice())
+            .ok_or_else(|| rustler::Error::Term(Box::new("invalid_sample_size")))?;
+        limits::check_max("input_samples", samples.len() as u64, MAX_BLOCK_SAMPLES as u64)
+            .map_err(limits::range_error)?;
+        check_input_level(env, channel_id, &samples);
+
+        // Lock only this channel and process
+        let (output, fades) = CHANNELS
+            .with_channel_mut(channel_id, |channel| (channel.process(&samples), channel.take_fade_events()))
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+        notify_fades(env, channel_id, fades);
+
+        // Allocate output binary on BEAM heap
+        let output_byte_len = output.len() * 4;
+        let mut owned = OwnedBinary::new(output_byte_len)
+            .ok_or_else(|| rustler::Error::Term(Box::new("binary_alloc_failed")))?;
+
+        // Copy f32 samples as native-endian bytes into the binary
+        convert::f32s_to_ne_bytes(&output, owned.as_mut_slice());
+
+        // Release ownership to BEAM garbage collector
+        Ok((atoms::ok(), owned.release(env)))
+    })
+}
+
+/// :none, {:gap, n_samples} or {:overlap, n_samples}
+fn encode_discontinuity<'a>(env: Env<'a>, discontinuity: Discontinuity) -> Term<'a> {
+    match discontinuity {
+        Discontinuity::None => atoms::none().encode(env),
+        Discontinuity::Gap(n) => (atoms::gap(), n).encode(env),
+        Discontinuity::Overlap(n) => (atoms::overlap(), n).encode(env),
+    }
+}
+
+/// Processes a block the caller says starts at start_sample_index.
+/// Input/output as process_block; returns {:ok, output, discontinuity}.
+/// A gap is run through as silence before the block; an overlap is cut
+/// from the front of the input, shortening the output (see
+/// WattersonChannel::process_at).
+#[rustler::nif]
+fn process_block_at<'a>(
+    env: Env<'a>,
+    channel_id: u64,
+    start_sample_index: u64,
+    input: Binary,
+) -> NifResult<(rustler::Atom, Binary<'a>, Term<'a>)> {
+    guarded(|| {
+        let samples = convert::f32s_from_ne_bytes(input.as_slice())
+            .ok_or_else(|| rustler::Error::Term(Box::new("invalid_sample_size")))?;
+        check_input_level(env, channel_id, &samples);
+
+        let ((output, discontinuity), fades) = CHANNELS
+            .with_channel_mut(channel_id, |channel| {
+                (channel.process_at(start_sample_index, &samples), channel.take_fade_events())
+            })
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+        notify_fades(env, channel_id, fades);
+
+        let mut owned = OwnedBinary::new(output.len() * 4)
+            .ok_or_else(|| rustler::Error::Term(Box::new("binary_alloc_failed")))?;
+        convert::f32s_to_ne_bytes(&output, owned.as_mut_slice());
+
+        Ok((atoms::ok(), owned.release(env), encode_discontinuity(env, discontinuity)))
+    })
+}
+
+/// Processes a block with explicit input/output sample formats.
+/// Formats: :f32ne, :f64ne, :s24le (packed 3-byte, little endian),
+/// :f32le, :f32be and :f32tagged (byte order from a leading magic, see
+/// format). The f64 and s24 paths skip the f32 quantization of process_block.
+#[rustler::nif]
+fn process_block_fmt<'a>(
+    env: Env<'a>,
+    channel_id: u64,
+    input: Binary,
+    input_format: rustler::Atom,
+    output_format: rustler::Atom,
+) -> NifResult<(rustler::Atom, Binary<'a>)> {
+    guarded(|| {
+        let in_fmt = SampleFormat::from_atom(input_format)
+            .ok_or_else(|| rustler::Error::Term(Box::new("unsupported_format")))?;
+        let out_fmt = SampleFormat::from_atom(output_format)
+            .ok_or_else(|| rustler::Error::Term(Box::new("unsupported_format")))?;
+
+        let samples = in_fmt
+            .decode(input.as_slice())
+            .map_err(|e| rustler::Error::Term(Box::new(e.name())))?;
+        check_input_level(env, channel_id, &samples);
+
+        let (output, fades) = CHANNELS
+            .with_channel_mut(channel_id, |channel| (channel.process_f64(&samples), channel.take_fade_events()))
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+        notify_fades(env, channel_id, fades);
+
+        let mut owned = OwnedBinary::new(out_fmt.encoded_len(output.len()))
+            .ok_or_else(|| rustler::Error::Term(Box::new("binary_alloc_failed")))?;
+        out_fmt.encode(&output, owned.as_mut_slice());
+
+        Ok((atoms::ok(), owned.release(env)))
+    })
+}
+
+/// Processes a block of complex baseband samples, with no carrier mixing
+/// or baseband filters (see WattersonChannel::process_iq).
+/// Input: IQ_F32_INTERLEAVED_V1 (see minutemodem_dsp::wire)
+/// Output: IQ_F32_INTERLEAVED_V1 of the same length
+#[rustler::nif]
+fn process_block_iq<'a>(
+    env: Env<'a>,
+    channel_id: u64,
+    input: Binary,
+) -> NifResult<(rustler::Atom, Binary<'a>)> {
+    guarded(|| {
+        let iq = wire::decode_iq_f32(input.as_slice(), wire::Legacy::Reject)
+            .map_err(|e| rustler::Error::Term(Box::new(e.name())))?;
+        let flat: Vec<f64> = iq.iter().flat_map(|&(i, q)| [i, q]).collect();
+        check_input_level(env, channel_id, &flat);
+
+        let (output, fades) = CHANNELS
+            .with_channel_mut(channel_id, |channel| (channel.process_iq(&iq), channel.take_fade_events()))
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+        notify_fades(env, channel_id, fades);
+        let output = output.map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        let mut owned = OwnedBinary::new(wire::Format::IqF32.frame_len(output.len()))
+            .ok_or_else(|| rustler::Error::Term(Box::new("binary_alloc_failed")))?;
+        wire::write_iq_f32(&output, owned.as_mut_slice());
+
+        Ok((atoms::ok(), owned.release(env)))
+    })
+}
+
+/// Processes a block and also returns a clean reference for error vectors.
+/// Input: f32 samples as binary (native endian)
+/// Output: {impaired, reference}, both f32 binaries of the output length
+/// (as process_block).
+/// The reference shares the filter, carrier and bulk delays of the
+/// impaired output but has unity fading and no noise.
+#[rustler::nif]
+fn process_block_with_reference<'a>(
+    env: Env<'a>,
+    channel_id: u64,
+    input: Binary,
+) -> NifResult<(rustler::Atom, (Binary<'a>, Binary<'a>))> {
+    guarded(|| {
+        let samples = convert::f32s_from_ne_bytes(input.as_slice())
+            .ok_or_else(|| rustler::Error::Term(Box::new("invalid_sample_size")))?;
+        check_input_level(env, channel_id, &samples);
+
+        let ((impaired, reference), fades) = CHANNELS
+            .with_channel_mut(channel_id, |channel| {
+                (channel.process_with_reference(&samples), channel.take_fade_events())
+            })
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+        notify_fades(env, channel_id, fades);
+
+        let encode = |samples: &[f32]| -> NifResult<Binary<'a>> {
+            let mut owned = OwnedBinary::new(samples.len() * 4)
+                .ok_or_else(|| rustler::Error::Term(Box::new("binary_alloc_failed")))?;
+            convert::f32s_to_ne_bytes(samples, owned.as_mut_slice());
+            Ok(owned.release(env))
+        };
+
+        Ok((atoms::ok(), (encode(&impaired)?, encode(&reference)?)))
+    })
+}
+
+/// Advances channel state by N samples without processing.
+///
+/// Runs on a dirty CPU scheduler, in chunks that each take the channel's
+/// lock afresh (see advance_chunked), so a long advance doesn't keep
+/// process_block waiting on the same channel until it's done.
+#[rustler::nif(schedule = "DirtyCpu")]
+fn advance(env: Env, channel_id: u64, num_samples: u64) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        let fades = advance_chunked(&CHANNELS, channel_id, num_samples)
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+        notify_fades(env, channel_id, fades);
+
+        Ok(atoms::ok())
+    })
+}
+
+/// Advance a channel ADVANCE_CHUNK_SAMPLES at a time, releasing its lock
+/// and yielding between chunks
+///
+/// A call on the channel from another thread can get in between two
+/// chunks, and then sees it part way through the advance: its block
+/// follows the samples advanced so far and the rest of the advance comes
+/// after it. The state at the end is that of the advances and blocks in
+/// the order they took the lock; the advance itself matches one of the
+/// whole length as advance() matches processing silence. Gives the fade
+/// events of the whole advance, or None if the channel doesn't exist (or
+/// is destroyed part way through).
+fn advance_chunked(slab: &ChannelSlab<WattersonChannel>, channel_id: u64, num_samples: u64) -> Option<Vec<FadeEvent>> {
+    let mut fades = Vec::new();
+    let mut left = num_samples;
+    loop {
+        let chunk = left.min(ADVANCE_CHUNK_SAMPLES as u64);
+        let events = slab.with_channel_mut(channel_id, |channel| {
+            channel.advance(chunk as usize);
+            channel.take_fade_events()
+        })?;
+        fades.extend(events);
+        left -= chunk;
+        if left == 0 {
+            return Some(fades);
+        }
+        thread::yield_now();
+    }
+}
+
+/// Advances several channels in one NIF call.
+///
+/// Input: list of {channel_id, num_samples}
+/// Output: {:ok, [{channel_id, :ok | {:error, :channel_not_found}}]} in input order
+///
+/// Each entry is the same as an individual advance/2 call, so per-channel
+/// state ends up identical. A missing channel is reported in its entry and
+/// does not stop the rest of the batch.
+#[rustler::nif]
+fn advance_many<'a>(
+    env: Env<'a>,
+    requests: Vec<(u64, u64)>,
+) -> NifResult<(rustler::Atom, Vec<(u64, Term<'a>)>)> {
+    guarded(|| {
+        let results = advance_batch(&CHANNELS, &requests)
+            .into_iter()
+            .zip(&requests)
+            .map(|(found, &(channel_id, _))| {
+                let status = match found {
+                    Some(fades) => {
+                        notify_fades(env, channel_id, fades);
+                        atoms::ok().encode(env)
+                    }
+                    None => (atoms::error(), atoms::channel_not_found()).encode(env),
+                };
+                (channel_id, status)
+            })
+            .collect();
+
+        Ok((atoms::ok(), results))
+    })
+}
+
+/// Advance each (channel_id, num_samples) in order
+///
+/// Gives the fade events of each advance, or None where the channel
+/// doesn't exist.
+fn advance_batch(slab: &ChannelSlab<WattersonChannel>, requests: &[(u64, u64)]) -> Vec<Option<Vec<FadeEvent>>> {
+    requests
+        .iter()
+        .map(|&(channel_id, num_samples)| {
+            slab.with_channel_mut(channel_id, |channel| {
+                channel.advance(num_samples as usize);
+                channel.take_fade_events()
+            })
+        })
+        .collect()
+}
+
+/// Processes blocks on many channels in one NIF call, in parallel.
+///
+/// Input: list of {channel_id, input_binary}, each as process_block's
+/// Output: {:ok, [{channel_id, output_binary | {:error, :channel_not_found}}]}
+/// in input order
+///
+/// Each entry is the same as an individual process_block/2 call, so
+/// per-channel state ends up identical (see process_batch). A missing
+/// channel is reported in its entry and does not stop the rest of the
+/// batch; an input that isn't whole f32 samples, or is longer than
+/// MAX_BLOCK_SAMPLES, fails the whole call before anything runs.
+#[rustler::nif(schedule = "DirtyCpu")]
+fn process_blocks<'a>(
+    env: Env<'a>,
+    requests: Vec<(u64, Binary<'a>)>,
+) -> NifResult<(rustler::Atom, Vec<(u64, Term<'a>)>)> {
+    guarded(|| {
+        let inputs = requests
+            .iter()
+            .map(|(channel_id, input)| {
+                let samples = convert::f32s_from_ne_bytes(input.as_slice())
+                    .ok_or_else(|| rustler::Error::Term(Box::new("invalid_sample_size")))?;
+                limits::check_max("input_samples", samples.len() as u64, MAX_BLOCK_SAMPLES as u64)
+                    .map_err(limits::range_error)?;
+                check_input_level(env, *channel_id, &samples);
+                Ok((*channel_id, samples))
+            })
+            .collect::<NifResult<Vec<_>>>()?;
+
+        let results = process_batch(&CHANNELS, &inputs)
+            .into_iter()
+            .zip(&inputs)
+            .map(|(found, &(channel_id, _))| {
+                let output = match found {
+                    Some((output, fades)) => {
+                        notify_fades(env, channel_id, fades);
+                        let mut owned = OwnedBinary::new(output.len() * 4)
+                            .ok_or_else(|| rustler::Error::Term(Box::new("binary_alloc_failed")))?;
+                        convert::f32s_to_ne_bytes(&output, owned.as_mut_slice());
+                        owned.release(env).encode(env)
+                    }
+                    None => (atoms::error(), atoms::channel_not_found()).encode(env),
+                };
+                Ok((channel_id, output))
+            })
+            .collect::<NifResult<Vec<_>>>()?;
+
+        Ok((atoms::ok(), results))
+    })
+}
+
+/// Process each (channel_id, samples) on a pool of scoped threads
+///
+/// Gives the output and fade events of each entry in input order, or
+/// None where the channel doesn't exist. Entries are grouped by channel
+/// and a group runs start to finish on one thread, in input order, so a
+/// channel listed twice takes its blocks in sequence and every output is
+/// what processing the entries one by one gives. Each block holds only
+/// its own channel's lock, as process_block does.
+fn process_batch(
+    slab: &ChannelSlab<WattersonChannel>,
+    requests: &[(u64, Vec<f32>)],
+) -> Vec<Option<(Vec<f32>, Vec<FadeEvent>)>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of: HashMap<u64, usize> = HashMap::new();
+    for (n, &(channel_id, _)) in requests.iter().enumerate() {
+        let group = *group_of.entry(channel_id).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(n);
+    }
+
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get).min(groups.len());
+    let next_group = AtomicUsize::new(0);
+    let done: Vec<Vec<(usize, Option<(Vec<f32>, Vec<FadeEvent>)>)>> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    while let Some(group) = groups.get(next_group.fetch_add(1, Ordering::Relaxed)) {
+                        for &n in group {
+                            let (channel_id, samples) = &requests[n];
+                            let result = slab.with_channel_mut(*channel_id, |channel| {
+                                (channel.process(samples), channel.take_fade_events())
+                            });
+                            done.push((n, result));
+                        }
+                    }
+                    done
+                })
+            })
+            .collect();
+        // A worker's panic goes on to guarded() like any other
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap_or_else(|payload| panic::resume_unwind(payload)))
+            .collect()
+    });
+
+    let mut results: Vec<Option<(Vec<f32>, Vec<FadeEvent>)>> = requests.iter().map(|_| None).collect();
+    for (n, result) in done.into_iter().flatten() {
+        results[n] = result;
+    }
+    results
+}
+
+/// Updates a live channel's parameters.
+/// Only snr_db, bulk_delay_samples, the input conditioning and the output
+/// stage settings may differ from creation; the bulk delay slews to the
+/// new value (see bulk_delay) rather than jumping.
+#[rustler::nif]
+fn update_params(channel_id: u64, params: ChannelParams) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        limits::validate_params(&params).map_err(limits::range_error)?;
+        output::validate(params.output_bits, params.clip_knee)
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        conditioning::validate(params.sample_rate, params.input_tilt_db)
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        channel::validate_frequency_offset(&params).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        CHANNELS
+            .with_channel_mut(channel_id, |channel| channel.update_params(&params))
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        Ok(atoms::ok())
+    })
+}
+
+/// reset_channel's rng argument; a bare :reseed reuses the channel's seed
+enum RngArg {
+    Preserve,
+    Reseed(Option<u64>),
+}
+
+fn decode_rng_arg(term: Term) -> NifResult<RngArg> {
+    if let Ok(atom) = term.decode::<Atom>() {
+        if atom == atoms::preserve() {
+            return Ok(RngArg::Preserve);
+        }
+        if atom == atoms::reseed() {
+            return Ok(RngArg::Reseed(None));
+        }
+    } else if let Ok((atom, seed)) = term.decode::<(Atom, u64)>() {
+        if atom == atoms::reseed() {
+            return Ok(RngArg::Reseed(Some(seed)));
+        }
+    }
+    Err(rustler::Error::Term(Box::new("invalid_rng_mode")))
+}
+
+/// Returns a channel to idle (see WattersonChannel::reset_to_idle).
+/// rng: :preserve, :reseed (restart from the channel's seed) or
+/// {:reseed, seed}.
+#[rustler::nif]
+fn reset_channel(channel_id: u64, rng: Term) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        let rng = decode_rng_arg(rng)?;
+        CHANNELS
+            .with_channel_mut(channel_id, |channel| {
+                let mode = match rng {
+                    RngArg::Preserve => RngReset::Preserve,
+                    RngArg::Reseed(seed) => RngReset::Reseed(seed.unwrap_or(channel.seed())),
+                };
+                channel.reset_to_idle(mode);
+            })
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+
+        Ok(atoms::ok())
+    })
+}
+
+/// Restarts a channel's AWGN from noise_seed; fading, filters and delays
+/// are untouched (see WattersonChannel::reseed_noise).
+#[rustler::nif]
+fn reseed_noise(channel_id: u64, noise_seed: u64) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        CHANNELS
+            .with_channel_mut(channel_id, |channel| channel.reseed_noise(noise_seed))
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+
+        Ok(atoms::ok())
+    })
+}
+
+/// Hops a channel's carrier on [{at_sample, carrier_hz}], replacing any
+/// hops still pending (see WattersonChannel::set_hop_schedule).
+#[rustler::nif]
+fn set_hop_schedule(channel_id: u64, hops: Vec<(u64, f64)>) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        CHANNELS
+            .with_channel_mut(channel_id, |channel| channel.set_hop_schedule(&hops))
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        Ok(atoms::ok())
+    })
+}
+
+/// Mutes (or attenuates by attenuation_db) a channel's output while the
+/// receiver transmits, on [{at_sample, :tx | :rx}], replacing any earlier
+/// schedule (see WattersonChannel::set_tr_schedule).
+#[rustler::nif]
+fn set_tr_schedule(
+    channel_id: u64,
+    schedule: Vec<(u64, TrState)>,
+    attenuation_db: Option<f64>,
+    ramp_ms: f64,
+) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        CHANNELS
+            .with_channel_mut(channel_id, |channel| channel.set_tr_schedule(&schedule, attenuation_db, ramp_ms))
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        Ok(atoms::ok())
+    })
+}
+
+/// Sets a channel's fade alarm (see fade_alarm), replacing any earlier one.
+/// Each crossing during process/advance sends
+/// {:fade, channel_id, :start | :end, sample_index} to pid.
+#[rustler::nif]
+fn set_fade_alarm(channel_id: u64, threshold_db: f64, hysteresis_db: f64, pid: LocalPid) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        let alarm = FadeAlarm::new(threshold_db, hysteresis_db)
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        let mut subscribers = FADE_SUBSCRIBERS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        CHANNELS
+            .with_channel_mut(channel_id, |channel| channel.set_fade_alarm(Some(alarm)))
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+        subscribers.insert(channel_id, pid);
+
+        Ok(atoms::ok())
+    })
+}
+
+/// Turns a channel's fade alarm off; events not yet sent are dropped.
+#[rustler::nif]
+fn clear_fade_alarm(channel_id: u64) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        let mut subscribers = FADE_SUBSCRIBERS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        CHANNELS
+            .with_channel_mut(channel_id, |channel| channel.set_fade_alarm(None))
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+        subscribers.remove(&channel_id);
+
+        Ok(atoms::ok())
+    })
+}
+
+/// Sends input level warnings to pid from now on (nil stops them).
+#[rustler::nif]
+fn set_warning_logger(pid: Option<LocalPid>) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        let mut logger = WARNING_LOGGER
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *logger = pid;
+        Ok(atoms::ok())
+    })
+}
+
+/// Turns a channel's input level warnings on or off (on by default).
+#[rustler::nif]
+fn set_input_warnings(channel_id: u64, enabled: bool) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        if CHANNELS.with_channel(channel_id, |_| ()).is_none() {
+            return Err(rustler::Error::Term(Box::new("channel_not_found")));
+        }
+        let mut off = WARNINGS_OFF
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if enabled {
+            off.remove(&channel_id);
+        } else {
+            off.insert(channel_id);
+        }
+        Ok(atoms::ok())
+    })
+}
+
+/// {sample_index, kind, old | params, new | seed} (see audit)
+fn encode_audit_entry<'a>(env: Env<'a>, entry: &AuditEntry) -> Term<'a> {
+    let at = entry.sample_index;
+    match &entry.change {
+        AuditChange::Created { params, seed } => (at, atoms::created(), params, seed).encode(env),
+        AuditChange::Updated { old, new } => (at, atoms::update_params(), old, new).encode(env),
+        AuditChange::NoiseReseeded { old, new } => (at, atoms::reseed_noise(), old, new).encode(env),
+        AuditChange::Reseeded { old, new } => (at, atoms::reseed(), old, new).encode(env),
+        AuditChange::HopSchedule { hops } => (at, atoms::hop_schedule(), hops).encode(env),
+        AuditChange::TrSchedule { schedule, attenuation_db, ramp_ms } => {
+            (at, atoms::tr_schedule(), schedule, attenuation_db, ramp_ms).encode(env)
+        }
+    }
+}
+
+/// Gets a channel's audit log as {:ok, entries, dropped}, oldest first,
+/// optionally clearing it.
+#[rustler::nif]
+fn get_audit_log(env: Env, channel_id: u64, clear: bool) -> NifResult<(rustler::Atom, Vec<Term>, u64)> {
+    guarded(|| {
+        let (entries, dropped) = CHANNELS
+            .with_channel_mut(channel_id, |channel| {
+                let log = channel.audit_log_mut();
+                let entries: Vec<Term> = log.entries().map(|e| encode_audit_entry(env, e)).collect();
+                let dropped = log.dropped();
+                if clear {
+                    log.clear();
+                }
+                (entries, dropped)
+            })
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+
+        Ok((atoms::ok(), entries, dropped))
+    })
+}
+
+/// Sets how many entries a channel's audit log keeps (256 by default).
+#[rustler::nif]
+fn set_audit_cap(channel_id: u64, cap: usize) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        limits::check_max("cap", cap as u64, MAX_AUDIT_CAP as u64).map_err(limits::range_error)?;
+        CHANNELS
+            .with_channel_mut(channel_id, |channel| channel.audit_log_mut().set_cap(cap))
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        Ok(atoms::ok())
+    })
+}
+
+/// A phase log entry as Elixir sees it: {sample_index, phase, step}
+type PhaseTuple = (u64, f64, f64);
+
+/// Turns a channel's phase log on, logging every `decimation`th sample,
+/// or off with 0. Entries already held are dropped either way.
+#[rustler::nif]
+fn set_phase_log(channel_id: u64, decimation: u64) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        let log = match decimation {
+            0 => None,
+            n => Some(PhaseLog::new(n).map_err(|e| rustler::Error::Term(Box::new(e)))?),
+        };
+        CHANNELS
+            .with_channel_mut(channel_id, |channel| channel.set_phase_log(log))
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+
+        Ok(atoms::ok())
+    })
+}
+
+/// Gets a channel's phase log as {:ok, [{sample_index, phase, step}],
+/// dropped}, oldest first, optionally clearing it.
+#[rustler::nif]
+fn get_phase_log(channel_id: u64, clear: bool) -> NifResult<(rustler::Atom, Vec<PhaseTuple>, u64)> {
+    guarded(|| {
+        let (entries, dropped) = CHANNELS
+            .with_channel_mut(channel_id, |channel| {
+                let log = channel.phase_log_mut()?;
+                let entries: Vec<PhaseTuple> = log.entries().map(|e| (e.sample_index, e.phase, e.step)).collect();
+                let dropped = log.dropped();
+                if clear {
+                    log.clear();
+                }
+                Some((entries, dropped))
+            })
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?
+            .ok_or_else(|| rustler::Error::Term(Box::new("phase_log_off")))?;
+
+        Ok((atoms::ok(), entries, dropped))
+    })
+}
+
+/// Puts a block of channel output back at complex baseband with logged
+/// phases (see phase_log::derotate).
+/// Input: f32 output samples as binary (native endian), the first at
+/// channel sample `start_index`
+/// Output: IQ_F32_INTERLEAVED_V1 (see minutemodem_dsp::wire)
+#[rustler::nif]
+fn derotate_output<'a>(
+    env: Env<'a>,
+    entries: Vec<PhaseTuple>,
+    start_index: u64,
+    output: Binary,
+    sample_rate: u32,
+) -> NifResult<(rustler::Atom, Binary<'a>)> {
+    let samples = convert::f32s_from_ne_bytes(output.as_slice())
+        .ok_or_else(|| rustler::Error::Term(Box::new("invalid_sample_size")))?;
+    let samples: Vec<f64> = samples.into_iter().map(f64::from).collect();
+    let entries: Vec<PhaseEntry> = entries
+        .into_iter()
+        .map(|(sample_index, phase, step)| PhaseEntry { sample_index, phase, step })
+        .collect();
+    let bytes = phase_log::derotate_wire(&entries, start_index, &samples, sample_rate as f64)
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let mut owned = OwnedBinary::new(bytes.len())
+        .ok_or_else(|| rustler::Error::Term(Box::new("binary_alloc_failed")))?;
+    owned.as_mut_slice().copy_from_slice(&bytes);
+    Ok((atoms::ok(), owned.release(env)))
+}
+
+/// Destroys a channel and frees its slab slot.
+#[rustler::nif]
+fn destroy_channel(channel_id: u64) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        // Ids are never reused; without this the entry would just leak
+        FADE_SUBSCRIBERS.lock().unwrap_or_else(PoisonError::into_inner).remove(&channel_id);
+        WARNINGS_OFF.lock().unwrap_or_else(PoisonError::into_inner).remove(&channel_id);
+        CHANNELS.remove(channel_id);
+        Ok(atoms::ok())
+    })
+}
+
+/// Gets the current state of a channel for debugging/telemetry.
+#[rustler::nif]
+fn get_state(channel_id: u64) -> NifResult<(rustler::Atom, channel::ChannelState)> {
+    guarded(|| {
+        let state = CHANNELS
+            .with_channel(channel_id, |channel| channel.get_state())
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+
+        Ok((atoms::ok(), state))
+    })
+}
+
+/// Gets a channel's simulation time as {sample_index, seconds}.
+#[rustler::nif]
+fn get_time(channel_id: u64) -> NifResult<(rustler::Atom, (u64, f64))> {
+    guarded(|| {
+        let time = CHANNELS
+            .with_channel(channel_id, |channel| channel.time())
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+
+        Ok((atoms::ok(), time))
+    })
+}
+
+/// Runs a channel forward to sample index target_sample, fed from
+/// input_source (only :silence), discarding the output.
+/// Returns {:ok, {sample_index, seconds}}; fade events go out as usual.
+#[rustler::nif]
+fn run_until(
+    env: Env,
+    channel_id: u64,
+    target_sample: u64,
+    input_source: Atom,
+) -> NifResult<(rustler::Atom, (u64, f64))> {
+    guarded(|| {
+        if input_source != atoms::silence() {
+            return Err(rustler::Error::Term(Box::new("unsupported_input_source")));
+        }
+        let (result, fades) = CHANNELS
+            .with_channel_mut(channel_id, |channel| {
+                let result = channel.run_until(target_sample).map(|_| channel.time());
+                (result, channel.take_fade_events())
+            })
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+        notify_fades(env, channel_id, fades);
+
+        let time = result.map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        Ok((atoms::ok(), time))
+    })
+}
+
+/// Runs num_samples (or, with :auto, transient_len) samples of silence
+/// through a channel, dropping the output, so the next block doesn't carry
+/// the tail of the last (see WattersonChannel::prime).
+/// Returns {:ok, samples_run}; fade events go out as usual.
+#[rustler::nif]
+fn prime_channel(env: Env, channel_id: u64, num_samples: Term) -> NifResult<(rustler::Atom, u64)> {
+    guarded(|| {
+        let num_samples = match num_samples.decode::<Atom>() {
+            Ok(atom) if atom == atoms::auto() => None,
+            Ok(_) => return Err(rustler::Error::BadArg),
+            Err(_) => Some(num_samples.decode::<usize>()?),
+        };
+        let (run, fades) = CHANNELS
+            .with_channel_mut(channel_id, |channel| (channel.prime(num_samples), channel.take_fade_events()))
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+        notify_fades(env, channel_id, fades);
+        Ok((atoms::ok(), run))
+    })
+}
+
+/// Creates a correlated set of n_outputs channels (see correlated).
+/// Returns {:ok, set_id, channel_ids}; the members are ordinary channels
+/// for get_state, update_params, set_fade_alarm and so on, but should be
+/// driven through process_set, which keeps their fading in step.
+#[rustler::nif]
+fn create_correlated_set(
+    params: ChannelParams,
+    n_outputs: usize,
+    correlation: Vec<Vec<f64>>,
+    seed: u64,
+) -> NifResult<(rustler::Atom, u64, Vec<u64>)> {
+    guarded(|| {
+        limits::check_max("n_outputs", n_outputs as u64, MAX_CORRELATED_OUTPUTS as u64)
+            .map_err(limits::range_error)?;
+        limits::validate_params(&params).map_err(limits::range_error)?;
+        if correlation.len() != n_outputs {
+            return Err(rustler::Error::Term(Box::new("invalid_correlation")));
+        }
+        output::validate(params.output_bits, params.clip_knee)
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        let (members, mixing) = correlated::create_members(&params, &correlation, seed)
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        let mut ids = Vec::with_capacity(members.len());
+        for member in members {
+            match CHANNELS.insert(member) {
+                Some(id) => ids.push(id),
+                None => {
+                    for id in ids {
+                        CHANNELS.remove(id);
+                    }
+                    return Err(rustler::Error::Term(Box::new("slab_full")));
+                }
+            }
+        }
+        match SETS.insert(CorrelatedSet { members: ids.clone(), mixing }) {
+            Some(set_id) => Ok((atoms::ok(), set_id, ids)),
+            None => {
+                for id in ids {
+                    CHANNELS.remove(id);
+                }
+                Err(rustler::Error::Term(Box::new("slab_full")))
+            }
+        }
+    })
+}
+
+/// Runs one f32 input block through every member of a set.
+/// Returns {:ok, outputs}: one f32 binary per member, in channel_ids order.
+#[rustler::nif]
+fn process_set<'a>(env: Env<'a>, set_id: u64, input: Binary) -> NifResult<(rustler::Atom, Vec<Binary<'a>>)> {
+    guarded(|| {
+        let samples: Vec<f64> = convert::f32s_from_ne_bytes(input.as_slice())
+            .ok_or_else(|| rustler::Error::Term(Box::new("invalid_sample_size")))?
+            .into_iter()
+            .map(f64::from)
+            .collect();
+
+        // The set stays locked throughout, so concurrent calls can't interleave
+        let outputs = SETS
+            .with_channel_mut(set_id, |set| {
+                let own = set
+                    .members
+                    .iter()
+                    .map(|&id| CHANNELS.with_channel_mut(id, |c| c.next_tap_gains(samples.len())))
+                    .collect::<Option<Vec<_>>>()?;
+                correlated::mix(&set.mixing, &own)
+                    .into_iter()
+                    .zip(&set.members)
+                    .map(|(gains, &id)| {
+                        CHANNELS.with_channel_mut(id, |c| {
+                            (id, c.process_f64_with_gains(&samples, &gains), c.take_fade_events())
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| rustler::Error::Term(Box::new("set_not_found")))?
+            .ok_or_else(|| rustler::Error::Term(Box::new("channel_not_found")))?;
+
+        // One input feeds every member; report it against each
+        for &(id, _, _) in &outputs {
+            check_input_level(env, id, &samples);
+        }
+
+        let mut binaries = Vec::with_capacity(outputs.len());
+        for (id, output, fades) in outputs {
+            notify_fades(env, id, fades);
+            let output: Vec<f32> = output.into_iter().map(|y| y as f32).collect();
+            let mut owned = OwnedBinary::new(output.len() * 4)
+                .ok_or_else(|| rustler::Error::Term(Box::new("binary_alloc_failed")))?;
+            convert::f32s_to_ne_bytes(&output, owned.as_mut_slice());
+            binaries.push(owned.release(env));
+        }
+        Ok((atoms::ok(), binaries))
+    })
+}
+
+/// Destroys a correlated set and its member channels.
+#[rustler::nif]
+fn destroy_set(set_id: u64) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        let members = SETS.remove(set_id).map(|set| set.members).unwrap_or_default();
+        let mut subscribers = FADE_SUBSCRIBERS.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut off = WARNINGS_OFF.lock().unwrap_or_else(PoisonError::into_inner);
+        for id in &members {
+            subscribers.remove(id);
+            off.remove(id);
+        }
+        drop((subscribers, off));
+        for id in members {
+            CHANNELS.remove(id);
+        }
+        Ok(atoms::ok())
+    })
+}
+
+/// Creates a channel group (see group) and returns its id.
+#[rustler::nif]
+fn create_group(params: GroupParams) -> NifResult<(rustler::Atom, u64)> {
+    guarded(|| {
+        params.validate().map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        let group = ChannelGroup {
+            weather: Arc::new(GroupWeather::new(&params)),
+            members: Vec::new(),
+        };
+        match GROUPS.insert(group) {
+            Some(id) => Ok((atoms::ok(), id)),
+            None => Err(rustler::Error::Term(Box::new("slab_full"))),
+        }
+    })
+}
+
+/// create_channel/2 for a channel that follows a group's weather: its
+/// SNR and Doppler spread are params' plus the group's offsets, sampled
+/// block by block.
+#[rustler::nif]
+fn create_channel_in_group(params: ChannelParams, seed: u64, group_id: u64) -> NifResult<(rustler::Atom, u64)> {
+    guarded(|| {
+        let mut channel = new_channel(params, seed)?;
+        GROUPS
+            .with_channel_mut(group_id, |group| {
+                channel.join_group(group.weather.clone());
+                let id = CHANNELS.insert(channel).ok_or_else(|| rustler::Error::Term(Box::new("slab_full")))?;
+                group.members.push(id);
+                Ok((atoms::ok(), id))
+            })
+            .ok_or_else(|| rustler::Error::Term(Box::new("group_not_found")))?
+    })
+}
+
+/// Gets a group's offsets at the furthest time any live member has
+/// reached (0.0 with none), and the live members.
+#[rustler::nif]
+fn get_group_state(group_id: u64) -> NifResult<(rustler::Atom, GroupState)> {
+    guarded(|| {
+        let state = GROUPS
+            .with_channel(group_id, |group| {
+                let times: Vec<(u64, f64)> = group
+                    .members
+                    .iter()
+                    .filter_map(|&id| CHANNELS.with_channel(id, |c| (id, c.time().1)))
+                    .collect();
+                let time_s = times.iter().map(|&(_, t)| t).fold(0.0, f64::max);
+                group.weather.state(time_s, times.into_iter().map(|(id, _)| id).collect())
+            })
+            .ok_or_else(|| rustler::Error::Term(Box::new("group_not_found")))?;
+        Ok((atoms::ok(), state))
+    })
+}
+
+/// Disbands a group. Its channels carry on at their own parameters.
+#[rustler::nif]
+fn disband_group(group_id: u64) -> NifResult<rustler::Atom> {
+    guarded(|| {
+        let members = GROUPS.remove(group_id).map(|group| group.members).unwrap_or_default();
+        for id in members {
+            CHANNELS.with_channel_mut(id, WattersonChannel::leave_group);
+        }
+        Ok(atoms::ok())
+    })
+}
+
+/// Returns the number of active channels in the slab.
+#[rustler::nif]
+fn channel_count() -> NifResult<u64> {
+    guarded(|| {
+        Ok(CHANNELS.count() as u64)
+    })
+}
+
+/// `%{live: n, created: n, dropped: n, bytes: n}` for one kind
+fn census_term(env: Env<'_>, count: CensusCount) -> NifResult<Term<'_>> {
+    let key = |name: &str| Atom::from_str(env, name).map(|a| a.encode(env));
+    Term::map_from_pairs(
+        env,
+        &[
+            (key("live")?, count.live.encode(env)),
+            (key("created")?, count.created.encode(env)),
+            (key("dropped")?, count.dropped.encode(env)),
+            (key("bytes")?, count.bytes.encode(env)),
+        ],
+    )
+}
+
+/// Counts channels, correlated sets and groups: live, created and
+/// destroyed since load, and the bytes the live ones held after their
+/// last call, as %{channels: counts, correlated_sets: counts, groups: counts}.
+#[rustler::nif]
+fn census(env: Env<'_>) -> NifResult<Term<'_>> {
+    let mut map = Term::map_new(env);
+    for census in [&CHANNEL_CENSUS, &SET_CENSUS, &GROUP_CENSUS] {
+        map = map.map_put(Atom::from_str(env, census.name())?, census_term(env, census.count())?)?;
+    }
+    Ok(map)
+}
+
+/// Runs the built-in sanity check (see self_test).
+///
+/// Returns {:ok, %{elapsed_us: n, checks: %{name => summary}, provenance: s}}, or
+/// {:error, [{name, detail}]} listing the checks that failed.
+#[rustler::nif(schedule = "DirtyCpu")]
+fn self_test(env: Env<'_>) -> NifResult<Term<'_>> {
+    let report = crate::self_test::run();
+    let name = |name: &str| Atom::from_str(env, name);
+
+    if !report.passed() {
+        let failures = report
+            .failures()
+            .into_iter()
+            .map(|(check, detail)| Ok((name(check)?, detail)))
+            .collect::<NifResult<Vec<_>>>()?;
+        return Ok((atoms::error(), failures).encode(env));
+    }
+
+    let mut checks = Term::map_new(env);
+    for check in &report.checks {
+        let summary = check.result.as_deref().unwrap_or_default();
+        checks = checks.map_put(name(check.name)?, summary)?;
+    }
+    let summary = Term::map_from_pairs(
+        env,
+        &[
+            (name("elapsed_us")?.encode(env), (report.elapsed.as_micros() as u64).encode(env)),
+            (name("checks")?.encode(env), checks),
+            (name("provenance")?.encode(env), crate::provenance::provenance().encode(env)),
+        ],
+    )?;
+    Ok((atoms::ok(), summary).encode(env))
+}
+
+/// The library's provenance string (see provenance)
+#[rustler::nif]
+fn provenance() -> &'static str {
+    crate::provenance::provenance()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_params() -> ChannelParams {
+        ChannelParams {
+            delay_spread_samples: 10,
+            doppler_bandwidth_hz: 1.0,
+            snr_db: 20.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
+        }
+    }
+
+    #[test]
+    fn test_advance_batch_matches_individual_advance() {
+        let advances = [(0u64, 192u64), (1, 0), (2, 1000), (0, 37)];
+        let input: Vec<f32> = (0..256).map(|i| ((i * 7) % 13) as f32 / 13.0 - 0.5).collect();
+
+        let batched = ChannelSlab::new(8);
+        let single = ChannelSlab::new(8);
+        for seed in 0..3 {
+            batched.insert(WattersonChannel::new(test_params(), seed)).unwrap();
+            single.insert(WattersonChannel::new(test_params(), seed)).unwrap();
+        }
+
+        assert!(advance_batch(&batched, &advances).iter().all(Option::is_some));
+        for &(id, n) in &advances {
+            single.with_channel_mut(id, |c| c.advance(n as usize)).unwrap();
+        }
+
+        for id in 0..3 {
+            let a = batched.with_channel_mut(id, |c| c.process(&input)).unwrap();
+            let b = single.with_channel_mut(id, |c| c.process(&input)).unwrap();
+            assert_eq!(a, b, "channel {} diverged", id);
+        }
+    }
+
+    #[test]
+    fn test_process_batch_matches_sequential_processing() {
+        // A net's worth of channels, a block each
+        const CHANNELS_IN_NET: u64 = 64;
+        const BLOCK: usize = 4096;
+        let batched = ChannelSlab::new(CHANNELS_IN_NET as usize);
+        let single = ChannelSlab::new(CHANNELS_IN_NET as usize);
+        for seed in 0..CHANNELS_IN_NET {
+            let params = ChannelParams { doppler_bandwidth_hz: 0.5 + seed as f64 * 0.1, ..test_params() };
+            batched.insert(WattersonChannel::new(params.clone(), seed)).unwrap();
+            single.insert(WattersonChannel::new(params, seed)).unwrap();
+        }
+        let requests: Vec<(u64, Vec<f32>)> = (0..CHANNELS_IN_NET)
+            .map(|id| (id, (0..BLOCK).map(|i| ((i as f32 + id as f32) * 0.37).sin() * 0.5).collect()))
+            .collect();
+
+        for _ in 0..2 {
+            let results = process_batch(&batched, &requests);
+            for ((id, input), result) in requests.iter().zip(results) {
+                let (output, _) = result.unwrap();
+                let expected = single.with_channel_mut(*id, |c| c.process(input)).unwrap();
+                assert_eq!(output, expected, "channel {} diverged", id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_process_batch_takes_repeats_in_order() {
+        let batched = ChannelSlab::new(4);
+        let single = ChannelSlab::new(4);
+        for seed in 0..2 {
+            batched.insert(WattersonChannel::new(test_params(), seed)).unwrap();
+            single.insert(WattersonChannel::new(test_params(), seed)).unwrap();
+        }
+        let block = |k: usize| -> Vec<f32> { (0..300).map(|i| ((i * k) % 17) as f32 / 17.0 - 0.5).collect() };
+        let requests = [(0, block(1)), (1, block(2)), (99, block(3)), (0, block(4)), (0, block(5)), (1, block(6))];
+
+        let results = process_batch(&batched, &requests);
+        assert!(results[2].is_none());
+        for ((id, input), result) in requests.iter().zip(results) {
+            let expected = single.with_channel_mut(*id, |c| c.process(input));
+            assert_eq!(result.map(|(output, _)| output), expected, "channel {}", id);
+        }
+        assert!(process_batch(&batched, &[]).is_empty());
+    }
+
+    /// Thousands of channels through a counted slab, with their logs
+    /// filled and drained on the way
+    #[test]
+    fn test_channel_census_returns_to_zero() {
+        static COUNTED: Census = Census::new("channels");
+        let slab = ChannelSlab::new(500).with_census(&COUNTED, WattersonChannel::memory_bytes);
+        let input = [0.1f32; 480];
+
+        for round in 0..4 {
+            let ids: Vec<u64> = (0..500).map(|seed| slab.insert(WattersonChannel::new(test_params(), seed)).unwrap()).collect();
+            let idle = COUNTED.this_thread().bytes;
+            assert_eq!(COUNTED.this_thread().live, 500);
+            assert!(idle > 0);
+
+            for &id in &ids[..50] {
+                slab.with_channel_mut(id, |c| {
+                    c.set_phase_log(Some(PhaseLog::new(1).unwrap()));
+                    c.process(&input);
+                });
+            }
+            assert!(COUNTED.this_thread().bytes >= idle + 50 * 480 * std::mem::size_of::<PhaseEntry>() as i64);
+            for &id in &ids[..50] {
+                slab.with_channel_mut(id, |c| c.phase_log_mut().unwrap().clear());
+            }
+            assert!(COUNTED.this_thread().bytes <= idle, "round {}: cleared logs still held", round);
+
+            for id in ids {
+                slab.remove(id);
+            }
+            assert_eq!(COUNTED.this_thread().live, 0);
+            assert_eq!(COUNTED.this_thread().bytes, 0);
+        }
+    }
+
+    #[test]
+    fn test_long_advance_lets_blocks_through() {
+        // A 10 s advance against a stream of blocks on the same channel
+        // from another thread: both finish, and the channel ends up at the
+        // sum of the two
+        const ADVANCE: u64 = 9600 * 10;
+        const BLOCKS: usize = 50;
+        let slab = ChannelSlab::new(2);
+        let mut channel = WattersonChannel::new(ChannelParams { doppler_bandwidth_hz: 2.0, ..test_params() }, 3);
+        // The alarm needs every sample's fading, so the advance does real work
+        channel.set_fade_alarm(Some(FadeAlarm::new(-10.0, 3.0).unwrap()));
+        let id = slab.insert(channel).unwrap();
+        let block: Vec<f32> = (0..480).map(|i| (i as f32 * 0.37).sin() * 0.5).collect();
+
+        let seen = thread::scope(|scope| {
+            let blocks = scope.spawn(|| {
+                (0..BLOCKS)
+                    .map(|_| slab.with_channel_mut(id, |c| {
+                        let at = c.get_state().sample_index;
+                        assert_eq!(c.process(&block).len(), block.len());
+                        at
+                    }).unwrap())
+                    .collect::<Vec<u64>>()
+            });
+            let fades = advance_chunked(&slab, id, ADVANCE).unwrap();
+            assert!(!fades.is_empty());
+            blocks.join().unwrap()
+        });
+
+        let state = slab.with_channel(id, |c| c.get_state()).unwrap();
+        assert_eq!(state.sample_index, ADVANCE + (BLOCKS * block.len()) as u64);
+        // Every block ran on whole chunks of the advance, never mid-chunk
+        for (k, &at) in seen.iter().enumerate() {
+            let advanced = at - (k * block.len()) as u64;
+            assert!(advanced % ADVANCE_CHUNK_SAMPLES as u64 == 0 || advanced == ADVANCE, "block {k} at {at}");
+        }
+        assert_eq!(advance_chunked(&slab, id + 1, ADVANCE), None);
+    }
+
+    #[test]
+    fn test_advance_batch_reports_missing_channels() {
+        let slab = ChannelSlab::new(4);
+        let id = slab.insert(WattersonChannel::new(test_params(), 1)).unwrap();
+
+        let results = advance_batch(&slab, &[(99, 10), (id, 10), (id + 1, 10)]);
+        assert_eq!(results, vec![None, Some(vec![]), None]);
+
+        let state = slab.with_channel(id, |c| c.get_state()).unwrap();
+        assert_eq!(state.sample_index, 10);
+    }
+
+    #[test]
+    fn test_advance_batch_returns_fade_events() {
+        let slab = ChannelSlab::new(4);
+        let mut channel = WattersonChannel::new(ChannelParams { doppler_bandwidth_hz: 2.0, ..test_params() }, 2);
+        channel.set_fade_alarm(Some(FadeAlarm::new(-10.0, 3.0).unwrap()));
+        let id = slab.insert(channel).unwrap();
+
+        let results = advance_batch(&slab, &[(id, 9600 * 10), (id, 0)]);
+        let fades = results[0].as_ref().unwrap();
+        assert!(!fades.is_empty());
+        assert_eq!(fades[0].edge, FadeEdge::Start);
+        assert!(fades.windows(2).all(|w| w[0].edge != w[1].edge && w[0].sample_index < w[1].sample_index));
+        assert_eq!(results[1], Some(vec![]));
+    }
+
+    #[test]
+    fn test_built_params_create_a_channel() {
+        let terms: Vec<(String, f64)> = [("delay_spread_ms", 2.0), ("fade_rate_per_min", 30.0), ("snr_db", 15.0), ("bulk_delay_ms", 5.0)]
+            .iter()
+            .map(|&(k, v)| (k.to_string(), v))
+            .collect();
+        let (params, _) = builder::build(&terms).unwrap();
+        assert!(validate_params(&params).is_ok());
+
+        // Same channel as from the hand-converted struct
+        let by_hand = ChannelParams {
+            delay_spread_samples: 19,
+            doppler_bandwidth_hz: 30.0 / builder::fades_per_min_per_hz(),
+            snr_db: 15.0,
+            bulk_delay_samples: 48,
+            ..test_params()
+        };
+        let input: Vec<f32> = (0..4800).map(|n| (n as f32 * 0.7).sin() * 0.5).collect();
+        let mut built = new_channel(params, 7).unwrap();
+        let mut expected = new_channel(by_hand, 7).unwrap();
+        assert_eq!(built.process(&input), expected.process(&input));
+
+        // A result over the limits fails as create_channel would
+        let (long, _) = builder::build(&[("delay_spread_ms".to_string(), 3000.0)]).unwrap();
+        assert!(validate_params(&long).is_err());
+    }
+
+    #[test]
+    fn test_input_level_warnings() {
+        // Ids no slab hands out, so other tests can't collide
+        let (a, b) = (u64::MAX - 1, u64::MAX - 2);
+        let unscaled: Vec<f32> = (0..4800).map(|n| (n as f32 * 0.3).sin() * 16000.0).collect();
+        let scaled: Vec<f32> = unscaled.iter().map(|x| x / 32768.0).collect();
+
+        assert!(loud_peak(a, &unscaled).is_some());
+        assert_eq!(loud_peak(a, &scaled), None);
+
+        WARNINGS_OFF.lock().unwrap().insert(b);
+        assert_eq!(loud_peak(b, &unscaled), None);
+        assert!(loud_peak(a, &unscaled).is_some());
+        WARNINGS_OFF.lock().unwrap().remove(&b);
+    }
+}
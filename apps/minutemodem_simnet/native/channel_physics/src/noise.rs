@@ -1,12 +1,171 @@
 //! Additive White Gaussian Noise generator
 //!
 //! Uses Box-Muller transform for Gaussian samples.
+//!
+//! Samples are drawn one at a time, with the Box-Muller spare kept here
+//! between calls, so the sequence is the same however the channel's input
+//! is blocked.
+//!
+//! The noise can be shaped to a bandwidth B, as HF modem SNRs are quoted
+//! in 3 kHz: the draws go through a windowed-sinc low-pass at B (the
+//! design the channel's baseband filters use), scaled so the power from
+//! 0 to B is exactly the configured noise power. What leaks past B on the
+//! filter's skirt comes on top, so the total is a little more. Complex
+//! noise for the I/Q path stays white, but the shaping puts the same
+//! power into B as white noise spreads to Nyquist, raising the density
+//! there by Nyquist / B; the complex draws are scaled up by that much
+//! too, so a signal inside B sees the same change of SNR on either path.
+//!
+//! Impulsive noise (lightning static) can go on top of the Gaussian
+//! background, Bernoulli-Gaussian style: each sample a burst starts with
+//! a given probability, and for the burst's length every sample takes an
+//! extra Gaussian draw at a given power ratio over the background. A
+//! start inside a burst restarts it rather than adding to it. The bursts
+//! come from their own stream of the background's ChaCha8Rng seed, each
+//! sample drawing the same three f64s burst or not, so the background
+//! sequence is untouched by them and skip() stays a jump.
 
 use rand::Rng;
 use rand_chacha::ChaCha8Rng;
 use rand::SeedableRng;
 use std::f64::consts::PI;
 
+use minutemodem_dsp::windowed_sinc_lowpass;
+
+use crate::channel::FirLowPassFilter;
+
+/// Shaping filter length: the skirt is about 4/63 of the sample rate
+/// wide, ±300 Hz around B at 9600 Hz
+pub(crate) const SHAPING_TAPS: usize = 63;
+
+/// Frequencies the in-band power of the shaping filter is summed over
+const BAND_POINTS: usize = 512;
+
+/// ChaCha stream the impulses are drawn from; the background is on 0
+const IMPULSE_STREAM: u64 = 1;
+
+/// ChaCha words each sample of impulses draws: three f64s, the burst
+/// start's uniform and a Box-Muller pair
+const IMPULSE_WORDS_PER_SAMPLE: u128 = 6;
+
+/// The low-pass a shaped generator's draws go through
+struct Shaping {
+    filter: FirLowPassFilter<f64>,
+    /// Total output power per unit of power from 0 to B
+    power_gain: f64,
+    /// Deviation scale of the unshaped complex draws: the square root
+    /// of Nyquist / B
+    white_scale: f64,
+}
+
+impl Shaping {
+    fn new(bandwidth_hz: f64, sample_rate: f64) -> Self {
+        let taps = windowed_sinc_lowpass(bandwidth_hz, sample_rate, SHAPING_TAPS);
+        let scale = in_band_power(&taps, bandwidth_hz / sample_rate).sqrt().recip();
+        let taps: Vec<f64> = taps.iter().map(|h| h * scale).collect();
+        Self {
+            power_gain: taps.iter().map(|h| h * h).sum(),
+            white_scale: (sample_rate / 2.0 / bandwidth_hz).sqrt(),
+            filter: FirLowPassFilter::from_taps(taps),
+        }
+    }
+}
+
+/// Bursts of impulsive noise, in units of the background's deviation
+struct Impulses {
+    rng: ChaCha8Rng,
+    /// Chance a burst starts on any one sample
+    probability: f64,
+    /// Impulse deviation over the background's
+    scale: f64,
+    burst_samples: u32,
+    /// Samples left in the current burst
+    remaining: u32,
+}
+
+impl Impulses {
+    fn new(seed: [u8; 32], probability: f64, power_ratio_db: f64, burst_samples: u32) -> Self {
+        Self {
+            rng: Self::stream(seed),
+            probability,
+            scale: 10.0_f64.powf(power_ratio_db / 20.0),
+            burst_samples: burst_samples.max(1),
+            remaining: 0,
+        }
+    }
+
+    /// The impulse stream of the background's seed
+    fn stream(seed: [u8; 32]) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::from_seed(seed);
+        rng.set_stream(IMPULSE_STREAM);
+        rng
+    }
+
+    /// Restart from the background's new seed, outside a burst
+    fn reseed(&mut self, seed: [u8; 32]) {
+        self.rng = Self::stream(seed);
+        self.remaining = 0;
+    }
+
+    /// Fraction of samples inside a burst
+    fn duty(&self) -> f64 {
+        1.0 - (1.0 - self.probability).powi(self.burst_samples as i32)
+    }
+
+    /// The next sample's impulse for I and Q (a real signal takes I),
+    /// (0, 0) outside a burst
+    fn next_pair(&mut self) -> (f64, f64) {
+        let start: f64 = self.rng.gen();
+        let u1: f64 = self.rng.gen();
+        let u2: f64 = self.rng.gen();
+        if start < self.probability {
+            self.remaining = self.burst_samples;
+        }
+        if self.remaining == 0 {
+            return (0.0, 0.0);
+        }
+        self.remaining -= 1;
+        let r = (-2.0 * u1.max(1e-10).ln()).sqrt() * self.scale;
+        let (sin, cos) = (2.0 * PI * u2).sin_cos();
+        (r * cos, r * sin)
+    }
+
+    /// Move on `n` samples: only a start in the last burst_samples of
+    /// them can leave a burst running, so the rest are jumped
+    fn skip(&mut self, n: usize) {
+        let burst = self.burst_samples as usize;
+        let stepped = if n >= burst {
+            let pos = self.rng.get_word_pos();
+            self.rng.set_word_pos(pos + IMPULSE_WORDS_PER_SAMPLE * (n - burst) as u128);
+            self.remaining = 0;
+            burst
+        } else {
+            n
+        };
+        for _ in 0..stepped {
+            self.next_pair();
+        }
+    }
+}
+
+/// Power from 0 to `band` (a fraction of the sample rate) of unit white
+/// noise through `taps`: (1/π) ∫ |H(ω)|² dω over 0 to 2π·band, by the
+/// midpoint rule
+fn in_band_power(taps: &[f64], band: f64) -> f64 {
+    let step = 2.0 * PI * band / BAND_POINTS as f64;
+    let sum: f64 = (0..BAND_POINTS)
+        .map(|k| {
+            let w = (k as f64 + 0.5) * step;
+            let (re, im) = taps.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, h)| {
+                let (sin, cos) = (w * n as f64).sin_cos();
+                (re + h * cos, im - h * sin)
+            });
+            re * re + im * im
+        })
+        .sum();
+    sum * step / PI
+}
+
 /// AWGN generator with configurable power
 pub struct NoiseGenerator {
     /// Standard deviation (sqrt of noise power)
@@ -17,6 +176,12 @@ pub struct NoiseGenerator {
     
     /// Cached second sample from Box-Muller
     cached: Option<f64>,
+    
+    /// Band-limiting, None for white noise to Nyquist
+    shaping: Option<Shaping>,
+    
+    /// Impulsive bursts on top, None for Gaussian noise alone
+    impulses: Option<Impulses>,
 }
 
 impl NoiseGenerator {
@@ -31,14 +196,117 @@ impl NoiseGenerator {
             std_dev,
             rng,
             cached: None,
+            shaping: None,
+            impulses: None,
         }
     }
     
-    /// Generate next Gaussian noise sample using Box-Muller transform
+    /// Shape the noise to `bandwidth_hz`, its power from 0 to there
+    /// staying the one given to new(); zero or negative, or Nyquist and
+    /// up, leaves it white
+    pub fn with_bandwidth(mut self, bandwidth_hz: f64, sample_rate: f64) -> Self {
+        self.shaping = (bandwidth_hz > 0.0 && bandwidth_hz < sample_rate / 2.0)
+            .then(|| Shaping::new(bandwidth_hz, sample_rate));
+        self
+    }
+    
+    /// Add bursts of impulsive noise: each sample a burst of
+    /// `burst_samples` starts with chance `probability`, its samples
+    /// `power_ratio_db` above the background power; a zero probability
+    /// leaves the noise Gaussian
+    pub fn with_impulses(mut self, probability: f64, power_ratio_db: f64, burst_samples: u32) -> Self {
+        self.impulses = (probability > 0.0)
+            .then(|| Impulses::new(self.rng.get_seed(), probability, power_ratio_db, burst_samples));
+        self
+    }
+    
+    /// Noise power per sample, including any shaped noise's skirt and
+    /// the impulses' average
+    pub fn power(&self) -> f64 {
+        let gain = self.shaping.as_ref().map_or(1.0, |shaping| shaping.power_gain);
+        let impulsive = self.impulses.as_ref().map_or(0.0, |impulses| impulses.duty() * impulses.scale.powi(2));
+        self.std_dev * self.std_dev * gain * (1.0 + impulsive)
+    }
+    
+    /// Bytes held by the shaping filter
+    pub fn memory_bytes(&self) -> usize {
+        self.shaping.as_ref().map_or(0, |shaping| shaping.filter.memory_bytes())
+    }
+    
+    /// Change the noise power without disturbing the random sequence
+    pub fn set_noise_power(&mut self, noise_power: f64) {
+        self.std_dev = noise_power.sqrt();
+    }
+    
+    /// Restart the random sequence from a seed drawn from `seed_rng`
+    ///
+    /// Same sequence as NoiseGenerator::new with that RNG; the power,
+    /// bandwidth and impulse settings stay.
+    pub fn reseed(&mut self, seed_rng: &mut ChaCha8Rng) {
+        let seed: u64 = seed_rng.gen();
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self.cached = None;
+        if let Some(shaping) = &mut self.shaping {
+            shaping.filter.reset();
+        }
+        if let Some(impulses) = &mut self.impulses {
+            impulses.reseed(self.rng.get_seed());
+        }
+    }
+    
+    /// Move on `n` samples without generating them
+    ///
+    /// Each Box-Muller pair draws two f64s (four 32-bit words of ChaCha
+    /// output), so whole pairs are skipped by moving the stream position;
+    /// leaves the generator exactly where `n` next_sample() calls would.
+    /// Shaped noise runs its last SHAPING_TAPS draws through the filter,
+    /// which remembers no more than that.
+    pub fn skip(&mut self, n: usize) {
+        let filled = if self.shaping.is_some() { n.min(SHAPING_TAPS) } else { 0 };
+        let mut n = n - filled;
+        if let Some(impulses) = &mut self.impulses {
+            impulses.skip(n);
+        }
+        if n > 0 && self.cached.take().is_some() {
+            n -= 1;
+        }
+        let pos = self.rng.get_word_pos();
+        self.rng.set_word_pos(pos + 4 * (n / 2) as u128);
+        if n % 2 == 1 {
+            self.next_unit();
+        }
+        for _ in 0..filled {
+            self.next_sample();
+        }
+    }
+    
+    /// Next sample, with any impulse, shaped if the generator is
     pub fn next_sample(&mut self) -> f64 {
+        let mut z = self.next_unit();
+        if let Some(impulses) = &mut self.impulses {
+            z += impulses.next_pair().0;
+        }
+        match &mut self.shaping {
+            Some(shaping) => shaping.filter.process(z) * self.std_dev,
+            None => z * self.std_dev,
+        }
+    }
+    
+    /// Next I and Q samples left white and sharing any impulse, for
+    /// complex noise: each at the power given to new(), times Nyquist / B
+    /// if shaped (see the module docs)
+    pub fn next_white_pair(&mut self) -> (f64, f64) {
+        let (i, q) = (self.next_unit(), self.next_unit());
+        let (impulse_i, impulse_q) = self.impulses.as_mut().map_or((0.0, 0.0), Impulses::next_pair);
+        let std_dev = self.std_dev * self.shaping.as_ref().map_or(1.0, |shaping| shaping.white_scale);
+        ((i + impulse_i) * std_dev, (q + impulse_q) * std_dev)
+    }
+    
+    /// Next unit-variance Gaussian draw, by the Box-Muller transform
+    fn next_unit(&mut self) -> f64 {
         // Return cached value if available
         if let Some(cached) = self.cached.take() {
-            return cached * self.std_dev;
+            return cached;
         }
         
         // Box-Muller transform generates two independent Gaussian samples
@@ -57,7 +325,7 @@ impl NoiseGenerator {
         // Cache second sample
         self.cached = Some(z1);
         
-        z0 * self.std_dev
+        z0
     }
 }
 
@@ -180,4 +448,208 @@ mod tests {
         assert_eq!(nan_count, 0, "Found {} NaN values", nan_count);
         assert_eq!(inf_count, 0, "Found {} Inf values", inf_count);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_skip_matches_stepping() {
+        for (offset, n) in [(0, 0), (0, 1), (0, 1001), (1, 1000), (1, 1), (3, 7)] {
+            let mut stepped = NoiseGenerator::new(0.1, &mut ChaCha8Rng::seed_from_u64(9));
+            let mut skipped = NoiseGenerator::new(0.1, &mut ChaCha8Rng::seed_from_u64(9));
+            for _ in 0..offset {
+                stepped.next_sample();
+                skipped.next_sample();
+            }
+
+            for _ in 0..n {
+                stepped.next_sample();
+            }
+            skipped.skip(n);
+            for _ in 0..5 {
+                assert_eq!(stepped.next_sample(), skipped.next_sample(), "offset {} skip {}", offset, n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shaped_noise_power_is_in_band() {
+        let mut noise = NoiseGenerator::new(0.5, &mut ChaCha8Rng::seed_from_u64(3)).with_bandwidth(3000.0, 9600.0);
+        let samples: Vec<f64> = (0..200_000).map(|_| noise.next_sample()).collect();
+        let total = samples.iter().map(|x| x * x).sum::<f64>() / samples.len() as f64;
+
+        // A much sharper low-pass at 3 kHz stands in for a brick wall
+        let mut band = FirLowPassFilter::<f64>::from_taps(windowed_sinc_lowpass(3000.0, 9600.0, 511));
+        let in_band: Vec<f64> = samples.iter().map(|&x| band.process(x)).collect();
+        let in_band = in_band[511..].iter().map(|x| x * x).sum::<f64>() / (in_band.len() - 511) as f64;
+
+        assert!((in_band / 0.5 - 1.0).abs() < 0.03, "in-band power {in_band}");
+        assert!((total / noise.power() - 1.0).abs() < 0.03, "total {total} vs {}", noise.power());
+        assert!(noise.power() > 0.5 && noise.power() < 0.55, "skirt adds {}", noise.power() - 0.5);
+
+        // No bandwidth, or all of it, is white
+        for bandwidth_hz in [0.0, -1.0, 4800.0] {
+            let noise = NoiseGenerator::new(0.5, &mut ChaCha8Rng::seed_from_u64(3)).with_bandwidth(bandwidth_hz, 9600.0);
+            assert!(noise.shaping.is_none());
+            assert!((noise.power() - 0.5).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_white_pair_density_follows_bandwidth() {
+        let pair_power = |bandwidth_hz| {
+            let mut noise = NoiseGenerator::new(0.5, &mut ChaCha8Rng::seed_from_u64(3)).with_bandwidth(bandwidth_hz, 9600.0);
+            let pairs: Vec<(f64, f64)> = (0..100_000).map(|_| noise.next_white_pair()).collect();
+            let power = |part: fn(&(f64, f64)) -> f64| pairs.iter().map(|p| part(p).powi(2)).sum::<f64>() / pairs.len() as f64;
+            // Still white: successive draws are uncorrelated
+            let lag = pairs.windows(2).map(|w| w[0].0 * w[1].0).sum::<f64>() / pairs.len() as f64;
+            assert!(lag.abs() < 0.02 * power(|p| p.0), "lag-1 correlation {lag}");
+            (power(|p| p.0), power(|p| p.1))
+        };
+
+        let (i, q) = pair_power(0.0);
+        assert!((i / 0.5 - 1.0).abs() < 0.02 && (q / 0.5 - 1.0).abs() < 0.02, "({i}, {q})");
+        // 3 kHz of 4.8 kHz to Nyquist: 1.6 times the power
+        let (i, q) = pair_power(3000.0);
+        assert!((i / 0.8 - 1.0).abs() < 0.02 && (q / 0.8 - 1.0).abs() < 0.02, "({i}, {q})");
+    }
+
+    #[test]
+    fn test_shaped_skip_matches_stepping() {
+        let shaped = || NoiseGenerator::new(0.1, &mut ChaCha8Rng::seed_from_u64(9)).with_bandwidth(3000.0, 9600.0);
+        for (offset, n) in [(0, 1), (1, 10), (0, 1001), (3, SHAPING_TAPS), (1, SHAPING_TAPS + 2)] {
+            let (mut stepped, mut skipped) = (shaped(), shaped());
+            for _ in 0..offset {
+                stepped.next_sample();
+                skipped.next_sample();
+            }
+
+            for _ in 0..n {
+                stepped.next_sample();
+            }
+            skipped.skip(n);
+            for _ in 0..5 {
+                assert_eq!(stepped.next_sample(), skipped.next_sample(), "offset {} skip {}", offset, n);
+            }
+        }
+    }
+
+    /// P(|z| > t) for a unit Gaussian, by Simpson's rule on the density
+    fn gaussian_tail(t: f64) -> f64 {
+        let steps = 1000;
+        let h = t / steps as f64;
+        let pdf = |x: f64| (-x * x / 2.0).exp() / (2.0 * PI).sqrt();
+        let weight = |k: usize| if k == 0 || k == steps { 1.0 } else if k % 2 == 1 { 4.0 } else { 2.0 };
+        let integral = (0..=steps).map(|k| weight(k) * pdf(k as f64 * h)).sum::<f64>() * h / 3.0;
+        1.0 - 2.0 * integral
+    }
+
+    #[test]
+    fn test_impulse_rate() {
+        // The background is drawn as without impulses, so the samples that
+        // differ are exactly those inside a burst
+        for (probability, burst_samples) in [(0.002, 1), (0.002, 5), (0.0005, 40)] {
+            let mut plain = NoiseGenerator::new(1.0, &mut ChaCha8Rng::seed_from_u64(5));
+            let mut impulsive = NoiseGenerator::new(1.0, &mut ChaCha8Rng::seed_from_u64(5))
+                .with_impulses(probability, 20.0, burst_samples);
+            let n = 1_000_000;
+            let in_burst: Vec<bool> = (0..n).map(|_| plain.next_sample() != impulsive.next_sample()).collect();
+
+            let duty = 1.0 - (1.0 - probability).powi(burst_samples as i32);
+            let measured = in_burst.iter().filter(|&&b| b).count() as f64 / n as f64;
+            assert!((measured / duty - 1.0).abs() < 0.1, "p {probability} x {burst_samples}: duty {measured} vs {duty}");
+
+            // Bursts come whole: none shorter than burst_samples, bar one
+            // cut off at the end
+            let runs: Vec<usize> = in_burst
+                .split(|&b| !b)
+                .map(<[bool]>::len)
+                .filter(|&len| len > 0)
+                .collect();
+            let short = runs.iter().filter(|&&len| len < burst_samples as usize).count();
+            assert!(short <= 1, "{short} of {} bursts short of {burst_samples}", runs.len());
+        }
+    }
+
+    #[test]
+    fn test_impulse_amplitude_tails() {
+        // A mixture: the background alone, and 1% of the time the
+        // background plus an impulse 20 dB up
+        let (probability, ratio) = (0.01, 100.0_f64);
+        let mut noise = NoiseGenerator::new(1.0, &mut ChaCha8Rng::seed_from_u64(6)).with_impulses(probability, 20.0, 1);
+        let samples: Vec<f64> = (0..1_000_000).map(|_| noise.next_sample()).collect();
+
+        for t in [3.0, 5.0, 8.0, 15.0] {
+            let measured = samples.iter().filter(|x| x.abs() > t).count() as f64 / samples.len() as f64;
+            let expected = (1.0 - probability) * gaussian_tail(t) + probability * gaussian_tail(t / (1.0 + ratio).sqrt());
+            assert!((measured / expected - 1.0).abs() < 0.1, "P(|x| > {t}) = {measured}, expected {expected}");
+        }
+        // Far past anything the background alone reaches
+        assert!(gaussian_tail(8.0) < 1e-14);
+
+        let power = samples.iter().map(|x| x * x).sum::<f64>() / samples.len() as f64;
+        assert!((noise.power() - 2.0).abs() < 1e-12);
+        assert!((power / noise.power() - 1.0).abs() < 0.05, "power {power} vs {}", noise.power());
+    }
+
+    #[test]
+    fn test_zero_impulse_probability_is_gaussian() {
+        let make = || NoiseGenerator::new(0.3, &mut ChaCha8Rng::seed_from_u64(8)).with_bandwidth(3000.0, 9600.0);
+        let mut plain = make();
+        let mut zero = make().with_impulses(0.0, 40.0, 10);
+        assert!(zero.impulses.is_none());
+        assert_eq!(zero.power(), plain.power());
+        for _ in 0..1000 {
+            assert_eq!(plain.next_sample(), zero.next_sample());
+            assert_eq!(plain.next_white_pair(), zero.next_white_pair());
+        }
+    }
+
+    #[test]
+    fn test_impulse_skip_matches_stepping() {
+        // Bursts often enough that a skip lands in one
+        let make = |bandwidth_hz| {
+            NoiseGenerator::new(0.1, &mut ChaCha8Rng::seed_from_u64(9))
+                .with_bandwidth(bandwidth_hz, 9600.0)
+                .with_impulses(0.05, 30.0, 7)
+        };
+        for bandwidth_hz in [0.0, 3000.0] {
+            let make = || make(bandwidth_hz);
+            for (offset, n) in [(0, 1), (3, 6), (3, 7), (2, 8), (1, 1001), (5, SHAPING_TAPS + 7)] {
+                let (mut stepped, mut skipped) = (make(), make());
+                for _ in 0..offset {
+                    stepped.next_sample();
+                    skipped.next_sample();
+                }
+
+                for _ in 0..n {
+                    stepped.next_sample();
+                }
+                skipped.skip(n);
+                for _ in 0..20 {
+                    assert_eq!(stepped.next_sample(), skipped.next_sample(), "offset {} skip {}", offset, n);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_reseed_matches_new() {
+        let mut used = NoiseGenerator::new(0.1, &mut ChaCha8Rng::seed_from_u64(1));
+        used.next_sample();
+        used.reseed(&mut ChaCha8Rng::seed_from_u64(2));
+        let mut fresh = NoiseGenerator::new(0.1, &mut ChaCha8Rng::seed_from_u64(2));
+
+        for _ in 0..100 {
+            assert_eq!(used.next_sample(), fresh.next_sample());
+        }
+
+        let impulsive = |seed| NoiseGenerator::new(0.1, &mut ChaCha8Rng::seed_from_u64(seed)).with_impulses(0.1, 20.0, 3);
+        let mut used = impulsive(1);
+        for _ in 0..10 {
+            used.next_sample();
+        }
+        used.reseed(&mut ChaCha8Rng::seed_from_u64(2));
+        let mut fresh = impulsive(2);
+        for _ in 0..100 {
+            assert_eq!(used.next_sample(), fresh.next_sample());
+        }
+    }
+}
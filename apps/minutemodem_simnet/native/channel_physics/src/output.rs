@@ -0,0 +1,194 @@
+//! Receive-side output conversion
+//!
+//! Models the sound card at the receiving end: an optional soft limiter
+//! (the analog AGC / codec driver running into the rails) followed by
+//! quantization to a fixed bit depth, with optional TPDF dither. The
+//! channel applies it after the noise, so the returned samples are what the
+//! ADC would have delivered.
+//!
+//! Full scale is ±1.0. With the defaults (0 bits, no dither, knee 0) the
+//! stage is bypassed and the float output is untouched.
+
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use rand::SeedableRng;
+
+/// Largest supported bit depth
+pub const MAX_OUTPUT_BITS: u32 = 24;
+
+/// Check output_bits and clip_knee from ChannelParams
+pub fn validate(output_bits: u32, clip_knee: f64) -> Result<(), &'static str> {
+    if output_bits == 1 || output_bits > MAX_OUTPUT_BITS {
+        return Err("invalid_output_bits");
+    }
+    if !(0.0..1.0).contains(&clip_knee) {
+        return Err("invalid_clip_knee");
+    }
+    Ok(())
+}
+
+/// Soft limiter: linear up to `knee`, then a tanh shoulder approaching ±1.0
+///
+/// Continuous with unit slope at the knee, so small signals and the onset
+/// of compression show no kink.
+pub fn soft_clip(x: f64, knee: f64) -> f64 {
+    let mag = x.abs();
+    if mag <= knee {
+        return x;
+    }
+    let headroom = 1.0 - knee;
+    (knee + headroom * ((mag - knee) / headroom).tanh()).copysign(x)
+}
+
+/// Soft limiter + quantizer with its own dither RNG
+pub struct OutputStage {
+    /// Quantizer step (2 / 2^bits); None for float output
+    step: Option<f64>,
+    dither: bool,
+    /// Soft-clip knee; None for no limiting
+    knee: Option<f64>,
+    rng: ChaCha8Rng,
+}
+
+impl OutputStage {
+    pub fn new(output_bits: u32, dither: bool, clip_knee: f64, seed_rng: &mut ChaCha8Rng) -> Self {
+        let seed: u64 = seed_rng.gen();
+        let mut stage = Self {
+            step: None,
+            dither: false,
+            knee: None,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        };
+        stage.configure(output_bits, dither, clip_knee);
+        stage
+    }
+
+    /// Change the settings without disturbing the dither sequence
+    pub fn configure(&mut self, output_bits: u32, dither: bool, clip_knee: f64) {
+        self.step = (output_bits > 0).then(|| 2.0 / (1u64 << output_bits) as f64);
+        self.dither = dither;
+        self.knee = (clip_knee > 0.0).then_some(clip_knee);
+    }
+
+    pub fn is_bypassed(&self) -> bool {
+        self.step.is_none() && self.knee.is_none()
+    }
+
+    /// Convert one sample
+    pub fn process(&mut self, x: f64) -> f64 {
+        let x = match self.knee {
+            Some(knee) => soft_clip(x, knee),
+            None => x,
+        };
+        let Some(step) = self.step else {
+            return x;
+        };
+
+        // TPDF dither spanning ±1 LSB
+        let x = if self.dither {
+            let (r1, r2): (f64, f64) = (self.rng.gen(), self.rng.gen());
+            x + (r1 - r2) * step
+        } else {
+            x
+        };
+        ((x / step).round() * step).clamp(-1.0, 1.0 - step)
+    }
+
+    /// Keep the dither sequence in step with skipped samples
+    pub fn advance(&mut self, num_samples: usize) {
+        if self.step.is_some() && self.dither {
+            // Two f64 draws, four 32-bit words of ChaCha output, per sample
+            let pos = self.rng.get_word_pos();
+            self.rng.set_word_pos(pos + 4 * num_samples as u128);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(bits: u32, dither: bool, knee: f64) -> OutputStage {
+        OutputStage::new(bits, dither, knee, &mut ChaCha8Rng::seed_from_u64(1))
+    }
+
+    #[test]
+    fn test_defaults_are_bypassed() {
+        let mut s = stage(0, false, 0.0);
+        assert!(s.is_bypassed());
+        for x in [0.123456789, -1.7, 3.0] {
+            assert_eq!(s.process(x), x);
+        }
+        // Dither alone does nothing without a bit depth
+        assert!(stage(0, true, 0.0).is_bypassed());
+    }
+
+    #[test]
+    fn test_quantizer_levels_and_rails() {
+        let mut s = stage(8, false, 0.0);
+        let step = 2.0 / 256.0;
+        assert_eq!(s.process(0.3 * step), 0.0);
+        assert_eq!(s.process(0.7 * step), step);
+        assert_eq!(s.process(-2.6 * step), -3.0 * step);
+        // Two's complement range: -1.0 ..= 1.0 - 1 LSB
+        assert_eq!(s.process(5.0), 1.0 - step);
+        assert_eq!(s.process(-5.0), -1.0);
+    }
+
+    #[test]
+    fn test_dither_is_unbiased_and_bounded() {
+        // A constant a third of an LSB up averages back to itself
+        let mut s = stage(12, true, 0.0);
+        let step = 2.0 / 4096.0;
+        let x = step / 3.0;
+        let out: Vec<f64> = (0..100_000).map(|_| s.process(x)).collect();
+        let mean = out.iter().sum::<f64>() / out.len() as f64;
+        assert!((mean - x).abs() < 0.01 * step, "mean {:.4} LSB", mean / step);
+        assert!(out.iter().all(|&y| (y - x).abs() <= 1.5 * step));
+    }
+
+    #[test]
+    fn test_advance_matches_processing() {
+        let mut processed = stage(12, true, 0.0);
+        let mut advanced = stage(12, true, 0.0);
+        for _ in 0..1001 {
+            processed.process(0.1);
+        }
+        advanced.advance(1001);
+        for _ in 0..10 {
+            assert_eq!(processed.process(0.1), advanced.process(0.1));
+        }
+    }
+
+    #[test]
+    fn test_soft_clip_curve() {
+        let knee = 0.5;
+        // Linear below the knee
+        assert_eq!(soft_clip(0.4, knee), 0.4);
+        assert_eq!(soft_clip(-0.5, knee), -0.5);
+        // Continuous slope at the knee
+        let slope = (soft_clip(knee + 1e-6, knee) - knee) / 1e-6;
+        assert!((slope - 1.0).abs() < 1e-3);
+        // Odd, monotonic, bounded by full scale
+        let mut last = 0.0;
+        for i in 1..=400 {
+            let x = i as f64 * 0.01;
+            let y = soft_clip(x, knee);
+            assert!(y > last && y < 1.0, "x {} y {}", x, y);
+            assert_eq!(soft_clip(-x, knee), -y);
+            last = y;
+        }
+        // tanh(1): one headroom past the knee gives 76% of the headroom
+        assert!((soft_clip(1.0, knee) - (0.5 + 0.5 * 1f64.tanh())).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(0, 0.0).is_ok());
+        assert!(validate(16, 0.9).is_ok());
+        assert_eq!(validate(1, 0.0), Err("invalid_output_bits"));
+        assert_eq!(validate(25, 0.0), Err("invalid_output_bits"));
+        assert_eq!(validate(16, 1.0), Err("invalid_clip_knee"));
+        assert_eq!(validate(16, -0.1), Err("invalid_clip_knee"));
+    }
+}
@@ -0,0 +1,214 @@
+//! Carrier phase log for coherent analysis
+//!
+//! The channel mixes its faded baseband back up at the carrier phase of
+//! the input the baseband came from: the NCO phase taken back over the
+//! filters' group delay. With a phase log on, that phase is recorded for
+//! every `decimation`th sample (those whose sample index is a multiple of
+//! it), with the step to the next sample, so analysis can put the output
+//! back at baseband with derotate() and see the fading alone.
+//!
+//! Between logged samples the phase is extrapolated from the last entry
+//! at its step, which is exact away from carrier hops; log every sample
+//! (decimation 1) to follow a hop exactly. Only processed samples are
+//! logged: advance() and bypass mix nothing. The log keeps at most
+//! PHASE_LOG_CAP entries, dropping the oldest and counting them, like the
+//! audit log.
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use minutemodem_dsp::{windowed_sinc_lowpass, wire};
+
+use crate::channel::{LPF_CUTOFF_HZ, LPF_TAPS};
+
+/// Most entries a phase log keeps
+pub const PHASE_LOG_CAP: usize = 1 << 20;
+
+/// The mix-up phase of one output sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhaseEntry {
+    pub sample_index: u64,
+    /// Radians, group-delay compensated, not wrapped to any range
+    pub phase: f64,
+    /// Radians the phase moves by to the next sample
+    pub step: f64,
+}
+
+/// Bounded, oldest-first log of PhaseEntry
+#[derive(Debug, Clone)]
+pub struct PhaseLog {
+    decimation: u64,
+    entries: VecDeque<PhaseEntry>,
+    dropped: u64,
+}
+
+impl PhaseLog {
+    pub fn new(decimation: u64) -> Result<Self, &'static str> {
+        if decimation == 0 {
+            return Err("invalid_decimation");
+        }
+        Ok(Self { decimation, entries: VecDeque::new(), dropped: 0 })
+    }
+
+    /// Whether the sample at `sample_index` is one to log
+    pub fn wants(&self, sample_index: u64) -> bool {
+        sample_index.is_multiple_of(self.decimation)
+    }
+
+    pub fn record(&mut self, entry: PhaseEntry) {
+        if self.entries.len() == PHASE_LOG_CAP {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Entries still held, oldest first
+    pub fn entries(&self) -> impl Iterator<Item = &PhaseEntry> {
+        self.entries.iter()
+    }
+
+    /// Entries dropped to stay within the cap since the last clear()
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Bytes held by the entries
+    pub fn memory_bytes(&self) -> usize {
+        self.entries.capacity() * std::mem::size_of::<PhaseEntry>()
+    }
+
+    /// Forget every entry and reset the dropped count
+    ///
+    /// The space goes too: a log can reach PHASE_LOG_CAP entries (24 MiB).
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.entries.shrink_to_fit();
+        self.dropped = 0;
+    }
+}
+
+/// The complex baseband a block of channel output was mixed up from
+///
+/// `output` starts at channel sample `start_index` (a channel with clock
+/// drift has no such index). Each sample is mixed down at its phase from
+/// `entries` (extrapolated from the nearest earlier entry, or the first
+/// if none is earlier) and the result low-passed by the channel's own
+/// baseband filter, applied forwards and centred so it adds no delay.
+/// The block's first and last LPF_TAPS / 2 samples see the filter's
+/// zero padding.
+pub fn derotate(
+    entries: &[PhaseEntry],
+    start_index: u64,
+    output: &[f64],
+    sample_rate: f64,
+) -> Result<Vec<(f64, f64)>, &'static str> {
+    if entries.is_empty() {
+        return Err("empty_phase_log");
+    }
+    if sample_rate.is_nan() || sample_rate <= 0.0 {
+        return Err("invalid_sample_rate");
+    }
+    let mut sorted = entries.to_vec();
+    sorted.sort_by_key(|e| e.sample_index);
+
+    let mut k = 0;
+    let mixed: Vec<(f64, f64)> = output
+        .iter()
+        .enumerate()
+        .map(|(n, &y)| {
+            let index = start_index + n as u64;
+            while k + 1 < sorted.len() && sorted[k + 1].sample_index <= index {
+                k += 1;
+            }
+            let e = sorted[k];
+            let phase = e.phase + (index as f64 - e.sample_index as f64) * e.step;
+            // 2·y·e^{-jφ}: the baseband plus an image at twice the carrier
+            (2.0 * y * phase.cos(), -2.0 * y * phase.sin())
+        })
+        .collect();
+
+    let taps = windowed_sinc_lowpass(LPF_CUTOFF_HZ, sample_rate, LPF_TAPS);
+    let half = taps.len() / 2;
+    Ok((0..mixed.len())
+        .map(|n| {
+            let mut acc = (0.0, 0.0);
+            for (j, &h) in taps.iter().enumerate() {
+                if let Some(&(i, q)) = (n + j).checked_sub(half).and_then(|m| mixed.get(m)) {
+                    acc.0 += h * i;
+                    acc.1 += h * q;
+                }
+            }
+            acc
+        })
+        .collect())
+}
+
+/// derotate(), as the IQ_F32_INTERLEAVED_V1 binary derotate_output
+/// returns (see minutemodem_dsp::wire)
+pub fn derotate_wire(
+    entries: &[PhaseEntry],
+    start_index: u64,
+    output: &[f64],
+    sample_rate: f64,
+) -> Result<Vec<u8>, &'static str> {
+    Ok(wire::encode_iq_f32(&derotate(entries, start_index, output, sample_rate)?))
+}
+
+/// Phase of `quadrant` quarter turns, for the quarter/half-rate carrier
+pub(crate) fn quadrant_phase(quadrant: u8) -> f64 {
+    (quadrant % 4) as f64 * PI / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(sample_index: u64) -> PhaseEntry {
+        PhaseEntry { sample_index, phase: 0.0, step: 0.1 }
+    }
+
+    #[test]
+    fn test_decimation_and_clear() {
+        let mut log = PhaseLog::new(4).unwrap();
+        for n in 0..10 {
+            if log.wants(n) {
+                log.record(entry(n));
+            }
+        }
+        let kept: Vec<u64> = log.entries().map(|e| e.sample_index).collect();
+        assert_eq!(kept, [0, 4, 8]);
+        log.clear();
+        assert_eq!(log.entries().count(), 0);
+        assert_eq!(PhaseLog::new(0).unwrap_err(), "invalid_decimation");
+    }
+
+    #[test]
+    fn test_derotate_recovers_a_tone_from_one_entry() {
+        // A 100 Hz baseband tone on a 1800 Hz carrier, mixed up exactly
+        let (fs, step) = (9600.0, 2.0 * PI * 1800.0 / 9600.0);
+        let start = 1000;
+        let bb = |n: usize| {
+            let t = 2.0 * PI * 100.0 * n as f64 / fs;
+            (0.5 * t.cos(), 0.5 * t.sin())
+        };
+        let phase0 = 0.3;
+        let output: Vec<f64> = (0..960)
+            .map(|n| {
+                let (i, q) = bb(n);
+                let phase = phase0 + (start + n) as f64 * step;
+                i * phase.cos() - q * phase.sin()
+            })
+            .collect();
+
+        // One entry well before the block; the rest is extrapolated
+        let entries = [PhaseEntry { sample_index: 0, phase: phase0, step }];
+        let iq = derotate(&entries, start as u64, &output, fs).unwrap();
+        for (n, &got) in iq.iter().enumerate().take(output.len() - LPF_TAPS).skip(LPF_TAPS) {
+            let (i, q) = bb(n);
+            assert!((got.0 - i).abs() < 0.01 && (got.1 - q).abs() < 0.01, "sample {n}: {got:?} vs {:?}", (i, q));
+        }
+        assert_eq!(derotate(&[], 0, &output, fs), Err("empty_phase_log"));
+        assert_eq!(derotate(&entries, 0, &output, 0.0), Err("invalid_sample_rate"));
+    }
+}
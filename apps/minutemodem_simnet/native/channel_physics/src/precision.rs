@@ -0,0 +1,162 @@
+//! Internal arithmetic precision of the channel
+//!
+//! The fading taps, mixers, baseband filters and echo delay line are where
+//! the channel spends its time, and they run in whichever float type
+//! ChannelParams' precision selects. In f32 the taps' sin/cos are a short
+//! polynomial rather than libm's, and the filters sum in SIMD lanes rather
+//! than strictly in order: a fading channel runs about 1.75x as fast on
+//! x86-64 and should come close to 2x on a Cortex-A53 (see
+//! benches/precision.rs). The output stays within -130 dBc or so of the
+//! f64 channel's, far below the noise at any SNR the model is used at (see
+//! test_f32_tracks_f64 in channel).
+//!
+//! Everything cheap or sequence-defining stays f64 either way: the input
+//! conditioning, the AWGN, clock drift and output stage, and all phases
+//! and times. Those are accumulated in f64 and reduced to a quarter turn
+//! before f32 sin/cos see them, so an f32 channel doesn't drift off its carrier
+//! or its fading realization over a long run.
+
+use rustler::NifUnitEnum;
+use std::f64::consts::{FRAC_2_PI, FRAC_PI_2};
+use std::ops::{Add, AddAssign, Mul, Neg, Sub};
+
+/// Float type the channel's signal path computes in
+#[derive(NifUnitEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Precision {
+    #[default]
+    F64,
+    F32,
+}
+
+/// Arithmetic the signal path needs, for f32 and f64
+pub trait Float:
+    Copy
+    + Default
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+{
+    fn from_f64(x: f64) -> Self;
+
+    fn to_f64(self) -> f64;
+
+    /// cos and sin of `phase` radians, which may be many turns out
+    fn cos_sin(phase: f64) -> (Self, Self);
+
+    /// Σ a[k]·b[k] over two equal-length slices
+    fn dot(a: &[Self], b: &[Self]) -> Self;
+}
+
+impl Float for f64 {
+    #[inline]
+    fn from_f64(x: f64) -> Self {
+        x
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    #[inline]
+    fn cos_sin(phase: f64) -> (Self, Self) {
+        (phase.cos(), phase.sin())
+    }
+
+    /// Summed from the end back, one term at a time: the order
+    /// minutemodem_dsp's RingFir sums in, newest sample first, so the
+    /// channel's f64 output is what it always was
+    #[inline]
+    fn dot(a: &[Self], b: &[Self]) -> Self {
+        a.iter().zip(b).rev().fold(0.0, |sum, (x, c)| sum + x * c)
+    }
+}
+
+impl Float for f32 {
+    #[inline]
+    fn from_f64(x: f64) -> Self {
+        x as f32
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    /// Reduced to the nearest quarter turn in f64 first (an f32 phase a
+    /// few thousand turns out has lost its fraction of a turn), then
+    /// Cephes' single-precision polynomials, good to a few 1e-8 over ±π/4
+    ///
+    /// Branch-free, so the fading taps' 64 oscillators vectorize.
+    #[inline]
+    fn cos_sin(phase: f64) -> (Self, Self) {
+        let quarters = round_to_even(phase * FRAC_2_PI);
+        let x = (phase - quarters * FRAC_PI_2) as f32;
+        let z = x * x;
+        let sin = ((-1.951_529_6e-4 * z + 8.332_161e-3) * z - 1.666_665_5e-1) * z * x + x;
+        let cos = ((2.443_315_7e-5 * z - 1.388_731_6e-3) * z + 4.166_664_6e-2) * z * z - 0.5 * z + 1.0;
+        // Quarter turns mod 4, still in f64 so a phase any number of turns
+        // out converts
+        let quadrant = (quarters - 4.0 * round_to_even(quarters * 0.25)) as i32;
+        let (c, s) = if quadrant & 1 == 0 { (cos, sin) } else { (-sin, cos) };
+        if quadrant & 2 == 0 { (c, s) } else { (-c, -s) }
+    }
+
+    /// DOT_LANES partial sums, which vectorize
+    #[inline]
+    fn dot(a: &[Self], b: &[Self]) -> Self {
+        let mut lanes = [0.0; DOT_LANES];
+        let (a_chunks, b_chunks) = (a.chunks_exact(DOT_LANES), b.chunks_exact(DOT_LANES));
+        let tail: f32 = a_chunks.remainder().iter().zip(b_chunks.remainder()).map(|(x, c)| x * c).sum();
+        for (x, c) in a_chunks.zip(b_chunks) {
+            for lane in 0..DOT_LANES {
+                lanes[lane] += x[lane] * c[lane];
+            }
+        }
+        lanes.iter().sum::<f32>() + tail
+    }
+}
+
+/// Partial sums in an f32 dot product: two SSE or NEON registers' worth
+const DOT_LANES: usize = 8;
+
+/// Nearest integer, ties to even, for |x| < 2^51
+///
+/// Adding and taking away 1.5·2^52 leaves no fraction bits: one add
+/// each way where f64::round() is a libm call on baseline x86-64.
+#[inline]
+fn round_to_even(x: f64) -> f64 {
+    const SHIFTER: f64 = 6_755_399_441_055_744.0;
+    (x + SHIFTER) - SHIFTER
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_f32_cos_sin_far_out() {
+        // Every quadrant, 1e6 s of a 20 Hz oscillator, and past i32 quarter
+        // turns; the reduction loses about 1e-16 of the phase
+        let near = (0..1000).map(|n| -7.0 + n as f64 * 0.014_159);
+        let far = [2.0 * PI * 20.0 * 1e6 + 0.3, -1e7, 2e10 + 0.25, 3.0 * FRAC_PI_2, -FRAC_PI_2 / 2.0];
+        for phase in near.chain(far) {
+            let (c, s) = f32::cos_sin(phase);
+            let tolerance = 2e-7 + 1e-16 * phase.abs();
+            assert!((c as f64 - phase.cos()).abs() < tolerance, "cos {phase}: {c}");
+            assert!((s as f64 - phase.sin()).abs() < tolerance, "sin {phase}: {s}");
+        }
+    }
+
+    #[test]
+    fn test_dot_products_agree() {
+        let a: Vec<f64> = (0..31).map(|n| (n as f64 * 0.37).sin()).collect();
+        let b: Vec<f64> = (0..31).map(|n| (n as f64 * 0.11).cos()).collect();
+        let exact = f64::dot(&a, &b);
+        let single = f32::dot(&a.iter().map(|&x| x as f32).collect::<Vec<_>>(), &b.iter().map(|&x| x as f32).collect::<Vec<_>>());
+        assert!((single as f64 - exact).abs() < 1e-5, "{single} vs {exact}");
+    }
+}
@@ -0,0 +1,177 @@
+//! Named channel conditions as ChannelParams
+//!
+//! The CCIR channels of Rec. 520 (now ITU-R F.520) and the latitude and
+//! condition table of ITU-R F.1487, each a differential delay between the
+//! two paths and a Doppler spread:
+//!
+//! | preset                       | delay, ms | Doppler, Hz |
+//! |------------------------------|-----------|-------------|
+//! | ccir_good                    | 0.5       | 0.1         |
+//! | ccir_moderate                | 1         | 0.5         |
+//! | ccir_poor                    | 2         | 1           |
+//! | ccir_flutter                 | 0.5       | 10          |
+//! | low_latitude_quiet           | 0.5       | 0.5         |
+//! | low_latitude_moderate        | 2         | 1.5         |
+//! | low_latitude_disturbed       | 6         | 10          |
+//! | mid_latitude_quiet           | 0.5       | 0.1         |
+//! | mid_latitude_moderate        | 1         | 0.5         |
+//! | mid_latitude_disturbed       | 2         | 1           |
+//! | mid_latitude_disturbed_nvis  | 7         | 1           |
+//! | high_latitude_quiet          | 1         | 0.5         |
+//! | high_latitude_moderate       | 3         | 10          |
+//! | high_latitude_disturbed      | 7         | 30          |
+//!
+//! Plus awgn, one unfaded path. The rest of the parameters go through
+//! builder with the sample rate and SNR given, so the milliseconds become
+//! samples there as for any scenario, and the carrier is its default.
+
+use rustler::NifUnitEnum;
+
+use crate::builder::{self, BuildError};
+use crate::channel::ChannelParams;
+
+/// A named channel condition
+#[derive(NifUnitEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelPreset {
+    /// One unfaded path
+    Awgn,
+    CcirGood,
+    CcirModerate,
+    CcirPoor,
+    CcirFlutter,
+    LowLatitudeQuiet,
+    LowLatitudeModerate,
+    LowLatitudeDisturbed,
+    MidLatitudeQuiet,
+    MidLatitudeModerate,
+    MidLatitudeDisturbed,
+    /// Near-vertical incidence, where the two magnetoionic rays part
+    /// furthest
+    MidLatitudeDisturbedNvis,
+    HighLatitudeQuiet,
+    HighLatitudeModerate,
+    HighLatitudeDisturbed,
+}
+
+impl ChannelPreset {
+    /// Differential delay (ms) and Doppler spread (Hz)
+    pub fn delay_and_doppler(self) -> (f64, f64) {
+        match self {
+            ChannelPreset::Awgn => (0.0, 0.0),
+            ChannelPreset::CcirGood => (0.5, 0.1),
+            ChannelPreset::CcirModerate => (1.0, 0.5),
+            ChannelPreset::CcirPoor => (2.0, 1.0),
+            ChannelPreset::CcirFlutter => (0.5, 10.0),
+            ChannelPreset::LowLatitudeQuiet => (0.5, 0.5),
+            ChannelPreset::LowLatitudeModerate => (2.0, 1.5),
+            ChannelPreset::LowLatitudeDisturbed => (6.0, 10.0),
+            ChannelPreset::MidLatitudeQuiet => (0.5, 0.1),
+            ChannelPreset::MidLatitudeModerate => (1.0, 0.5),
+            ChannelPreset::MidLatitudeDisturbed => (2.0, 1.0),
+            ChannelPreset::MidLatitudeDisturbedNvis => (7.0, 1.0),
+            ChannelPreset::HighLatitudeQuiet => (1.0, 0.5),
+            ChannelPreset::HighLatitudeModerate => (3.0, 10.0),
+            ChannelPreset::HighLatitudeDisturbed => (7.0, 30.0),
+        }
+    }
+}
+
+/// ChannelParams for `preset` at `sample_rate` and `snr_db`
+///
+/// Fails as builder::build does for a sample rate or SNR it won't take,
+/// including one whose Nyquist is below the default carrier.
+pub fn channel_params(preset: ChannelPreset, sample_rate: u32, snr_db: f64) -> Result<ChannelParams, BuildError> {
+    let (delay_ms, doppler_hz) = preset.delay_and_doppler();
+    let terms = [
+        ("sample_rate", sample_rate as f64),
+        ("snr_db", snr_db),
+        ("delay_spread_ms", delay_ms),
+        ("doppler_spread_hz", doppler_hz),
+    ]
+    .map(|(key, value)| (key.to_string(), value));
+    builder::build(&terms).map(|(params, _)| params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::WattersonChannel;
+
+    #[test]
+    fn test_ccir_presets_match_rec_520() {
+        // Rec. 520: good 0.5 ms / 0.1 Hz, moderate 1 ms / 0.5 Hz, poor
+        // 2 ms / 1 Hz; 2 ms at 9600 Hz is 19.2 samples
+        let cases = [
+            (ChannelPreset::CcirGood, 9600, 5, 0.1),
+            (ChannelPreset::CcirModerate, 9600, 10, 0.5),
+            (ChannelPreset::CcirPoor, 9600, 19, 1.0),
+            (ChannelPreset::CcirGood, 8000, 4, 0.1),
+            (ChannelPreset::CcirModerate, 8000, 8, 0.5),
+            (ChannelPreset::CcirPoor, 8000, 16, 1.0),
+            (ChannelPreset::CcirPoor, 48000, 96, 1.0),
+        ];
+        for (preset, sample_rate, delay_samples, doppler_hz) in cases {
+            let params = channel_params(preset, sample_rate, 12.0).unwrap();
+            assert_eq!(params.sample_rate, sample_rate);
+            assert_eq!(params.delay_spread_samples, delay_samples, "{preset:?} at {sample_rate} Hz");
+            assert_eq!(params.doppler_bandwidth_hz, doppler_hz, "{preset:?}");
+            assert_eq!(params.snr_db, 12.0);
+        }
+    }
+
+    #[test]
+    fn test_latitude_presets() {
+        // The mid-latitude rows are the CCIR channels again
+        let pairs = [
+            (ChannelPreset::MidLatitudeQuiet, ChannelPreset::CcirGood),
+            (ChannelPreset::MidLatitudeModerate, ChannelPreset::CcirModerate),
+            (ChannelPreset::MidLatitudeDisturbed, ChannelPreset::CcirPoor),
+        ];
+        for (a, b) in pairs {
+            assert_eq!(a.delay_and_doppler(), b.delay_and_doppler());
+        }
+        let params = channel_params(ChannelPreset::HighLatitudeDisturbed, 9600, 0.0).unwrap();
+        assert_eq!((params.delay_spread_samples, params.doppler_bandwidth_hz), (67, 30.0));
+        let params = channel_params(ChannelPreset::Awgn, 9600, 0.0).unwrap();
+        assert_eq!((params.delay_spread_samples, params.doppler_bandwidth_hz), (0, 0.0));
+    }
+
+    #[test]
+    fn test_every_preset_makes_a_channel() {
+        let presets = [
+            ChannelPreset::Awgn,
+            ChannelPreset::CcirFlutter,
+            ChannelPreset::LowLatitudeQuiet,
+            ChannelPreset::LowLatitudeModerate,
+            ChannelPreset::LowLatitudeDisturbed,
+            ChannelPreset::MidLatitudeDisturbedNvis,
+            ChannelPreset::HighLatitudeQuiet,
+            ChannelPreset::HighLatitudeModerate,
+            ChannelPreset::HighLatitudeDisturbed,
+        ];
+        for preset in presets {
+            let params = channel_params(preset, 9600, 20.0).unwrap();
+            assert!(crate::limits::validate_params(&params).is_ok(), "{preset:?}");
+            let mut channel = WattersonChannel::new(params, 1);
+            let output = channel.process(&[0.5; 960]);
+            assert!(output.iter().all(|x| x.is_finite()), "{preset:?}");
+        }
+    }
+
+    #[test]
+    fn test_unusable_rates_rejected() {
+        // Nyquist below the 1800 Hz carrier
+        assert!(matches!(
+            channel_params(ChannelPreset::CcirPoor, 3000, 10.0),
+            Err(BuildError::Invalid { key: "carrier_hz", .. })
+        ));
+        assert!(matches!(
+            channel_params(ChannelPreset::CcirPoor, 0, 10.0),
+            Err(BuildError::Invalid { key: "sample_rate", .. })
+        ));
+        assert!(matches!(
+            channel_params(ChannelPreset::CcirPoor, 9600, f64::NAN),
+            Err(BuildError::Invalid { key: "snr_db", .. })
+        ));
+    }
+}
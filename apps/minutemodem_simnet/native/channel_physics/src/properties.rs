@@ -0,0 +1,160 @@
+//! Property tests: physical invariants of the channel over random valid
+//! ChannelParams
+//!
+//! Each property runs CASES cases drawn by proptest from
+//! test_support::valid_params(), with a channel seed and an input seed.
+//! proptest shrinks a failing case towards the plainest channel that
+//! still fails and saves its seed under proptest-regressions/, where it is
+//! replayed first from then on.
+
+use proptest::prelude::*;
+
+use crate::channel::{ChannelParams, WattersonChannel, LPF_TAPS};
+use crate::fractional_delay;
+use crate::test_support::{delay_by_xcorr, power, pseudo_noise, valid_params};
+
+/// Cases per property
+const CASES: u32 = 40;
+
+/// Input block length
+const BLOCK: usize = 4800;
+
+/// Headroom over input plus noise power the output may have: a block can
+/// land on a fading peak, and a Rayleigh tap's power exceeds its mean by
+/// 10 dB with probability e^-10
+const POWER_MARGIN_DB: f64 = 12.0;
+
+proptest! {
+    #![proptest_config(ProptestConfig { cases: CASES, ..ProptestConfig::default() })]
+
+    #[test]
+    fn test_output_is_finite(params in valid_params(), seed in any::<u64>(), input_seed in any::<u64>()) {
+        let mut channel = WattersonChannel::new(params, seed);
+        let input = pseudo_noise(BLOCK, input_seed);
+        let mut output = channel.process_f64(&input);
+        channel.advance(BLOCK);
+        output.extend(channel.process_f64(&input));
+        if let Some(n) = output.iter().position(|y| !y.is_finite()) {
+            prop_assert!(false, "output[{}] = {}", n, output[n]);
+        }
+    }
+
+    #[test]
+    fn test_output_power_bounded(params in valid_params(), seed in any::<u64>(), input_seed in any::<u64>()) {
+        // Impulses add their average power on top of the background's
+        let duty = 1.0 - (1.0 - params.impulse_probability).powi(params.impulse_burst_samples as i32);
+        let impulsive = 1.0 + duty * 10f64.powf(params.impulse_power_ratio_db / 10.0);
+        let noise = if params.bypass { 0.0 } else { 0.125 * 10f64.powf(-params.snr_db / 10.0) * impulsive };
+        let mut channel = WattersonChannel::new(params, seed);
+        let input = pseudo_noise(BLOCK, input_seed);
+        let output = channel.process_f64(&input);
+        let bound = (power(&input) + noise) * 10f64.powf(POWER_MARGIN_DB / 10.0);
+        let got = power(&output);
+        prop_assert!(got <= bound, "output power {:.3e} over bound {:.3e}", got, bound);
+    }
+
+    #[test]
+    fn test_deterministic_per_seed(params in valid_params(), seed in any::<u64>(), input_seed in any::<u64>()) {
+        let input = pseudo_noise(BLOCK, input_seed);
+        let run = || {
+            let mut channel = WattersonChannel::new(params.clone(), seed);
+            let mut output = channel.process_f64(&input[..BLOCK / 2]);
+            output.extend(channel.process_f64(&input[BLOCK / 2..]));
+            output.into_iter().map(f64::to_bits).collect::<Vec<_>>()
+        };
+        prop_assert!(run() == run(), "two channels with the same seed differ");
+    }
+
+    #[test]
+    fn test_advance_matches_processing_silence(
+        params in valid_params(),
+        seed in any::<u64>(),
+        input_seed in any::<u64>(),
+        gap in 1usize..3000,
+    ) {
+        let input = pseudo_noise(BLOCK, input_seed);
+        let (before, after) = input.split_at(BLOCK / 2);
+
+        let mut advanced = WattersonChannel::new(params.clone(), seed);
+        let mut processed = WattersonChannel::new(params, seed);
+        advanced.process_f64(before);
+        processed.process_f64(before);
+        advanced.advance(gap);
+        processed.process_f64(&vec![0.0; gap]);
+
+        let a = advanced.process_f64(after);
+        let b = processed.process_f64(after);
+        prop_assert_eq!(a.len(), b.len(), "samples after advance({}) and after silence", gap);
+        if let Some(n) = a.iter().zip(&b).position(|(x, y)| (x - y).abs() > 1e-9) {
+            prop_assert!(false, "after advance({}), sample {}: {} vs {}", gap, n, a[n], b[n]);
+        }
+    }
+
+    #[test]
+    fn test_output_independent_of_block_size(params in valid_params(), seed in any::<u64>(), input_seed in any::<u64>()) {
+        // The gate for the noise path: a noise stage must carry its state
+        // across calls, not build it per block (see WattersonChannel::add_noise)
+        let input = pseudo_noise(BLOCK, input_seed);
+        let run = |block: usize| -> Vec<u64> {
+            let mut channel = WattersonChannel::new(params.clone(), seed);
+            input.chunks(block).flat_map(|x| channel.process_f64(x)).map(f64::to_bits).collect()
+        };
+        let whole = run(4096);
+        for block in [1, 7] {
+            let output = run(block);
+            if let Some(n) = output.iter().zip(&whole).position(|(a, b)| a != b) {
+                prop_assert!(false, "{}-sample blocks differ from 4096 at sample {}", block, n);
+            }
+            prop_assert_eq!(output.len(), whole.len(), "samples in {}-sample blocks and in 4096", block);
+        }
+    }
+
+    #[test]
+    fn test_reference_delay_matches_configured(params in valid_params(), seed in any::<u64>(), input_seed in any::<u64>()) {
+        // A drifting clock moves the delay along the block, and a mirrored
+        // spectrum doesn't correlate with the input at any delay
+        prop_assume!(params.sample_rate_offset_ppm == 0.0 && !params.sideband_inversion);
+        // The baseband filter adds its group delay to the bulk delay, and
+        // the fractional delay its whole samples (the fraction is within
+        // the tolerance)
+        let fractional = match params.fractional_delay_samples {
+            0.0 => 0,
+            _ => fractional_delay::HALF_TAPS,
+        };
+        let expected = match params.bypass {
+            true => 0,
+            false => params.bulk_delay_samples as usize + LPF_TAPS / 2 + fractional,
+        };
+        let mut channel = WattersonChannel::new(params, seed);
+        let input = pseudo_noise(BLOCK, input_seed);
+        let (_, reference) = channel.process_f64_with_reference(&input);
+        let measured = delay_by_xcorr(&input, &reference, expected + 64);
+        prop_assert!(measured.abs_diff(expected) <= 2, "delay {}, configured {}", measured, expected);
+    }
+}
+
+#[test]
+fn test_regression_advance_flushes_filters() {
+    // The shrunk failures against the advance() that skipped the baseband
+    // filters, and the drift interpolator after them (their seeds are in
+    // proptest-regressions/properties.txt)
+    let plain = ChannelParams {
+        sample_rate: 48_000,
+        doppler_bandwidth_hz: 0.156,
+        snr_db: 60.0,
+        carrier_freq_hz: 13_778.0,
+        noise_bandwidth_hz: 0.0,
+        ..ChannelParams::default()
+    };
+    let drifting = ChannelParams { carrier_freq_hz: 6754.0, sample_rate_offset_ppm: -88.6, ..plain.clone() };
+    for params in [plain, drifting] {
+        let input = pseudo_noise(BLOCK, 5);
+        let mut advanced = WattersonChannel::new(params.clone(), 5);
+        let mut processed = WattersonChannel::new(params.clone(), 5);
+        advanced.process_f64(&input);
+        processed.process_f64(&input);
+        advanced.advance(2880);
+        processed.process_f64(&[0.0; 2880]);
+        assert_eq!(advanced.process_f64(&input), processed.process_f64(&input), "{params:?}");
+    }
+}
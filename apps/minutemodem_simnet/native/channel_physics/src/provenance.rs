@@ -0,0 +1,82 @@
+//! This crate's provenance string (see minutemodem_dsp::provenance)
+//!
+//! Carried in the metadata of every exported channel document and the
+//! self test's summary, and returned by the provenance NIF. The tables
+//! hashed are the fading presets, the baseband filter taps and the self
+//! test's golden vector.
+
+use std::sync::OnceLock;
+
+use minutemodem_dsp::{windowed_sinc_lowpass, Provenance};
+
+use crate::channel::{LPF_CUTOFF_HZ, LPF_TAPS};
+use crate::link_budget::FadingPreset;
+use crate::self_test::GOLDEN;
+
+/// Sample rate the baseband filter taps are hashed at
+const TABLE_SAMPLE_RATE: f64 = 9600.0;
+
+/// The algorithm tables the plate hashes, by name
+fn tables() -> Vec<(&'static str, Vec<f64>)> {
+    let presets = [
+        FadingPreset::Awgn,
+        FadingPreset::Good,
+        FadingPreset::Moderate,
+        FadingPreset::Poor,
+        FadingPreset::Flutter,
+    ]
+    .into_iter()
+    .flat_map(|p| {
+        let (delay_ms, doppler_hz) = p.delay_and_doppler();
+        [delay_ms, doppler_hz]
+    })
+    .collect();
+    vec![
+        ("fading_presets", presets),
+        ("baseband_lpf", windowed_sinc_lowpass(LPF_CUTOFF_HZ, TABLE_SAMPLE_RATE, LPF_TAPS)),
+        ("self_test_golden", GOLDEN.to_vec()),
+    ]
+}
+
+/// The plate with `tables` hashed in
+fn plate(tables: &[(&str, Vec<f64>)]) -> String {
+    let build = Provenance::new(
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        env!("MINUTEMODEM_GIT_DESCRIBE"),
+        env!("MINUTEMODEM_BUILD_PROFILE"),
+    )
+    .feature("nif", cfg!(feature = "nif"))
+    .feature("capi", cfg!(feature = "capi"));
+    tables.iter().fold(build, |p, (name, values)| p.table(name, values)).build()
+}
+
+/// The provenance string, built on first use
+pub fn provenance() -> &'static str {
+    static PLATE: OnceLock<String> = OnceLock::new();
+    PLATE.get_or_init(|| plate(&tables()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plate_is_stable_within_a_process() {
+        let plate = provenance();
+        assert!(plate.starts_with(concat!("channel_physics/", env!("CARGO_PKG_VERSION"), " git=")), "{plate}");
+        assert_eq!(provenance(), plate);
+        assert_eq!(super::plate(&tables()), plate);
+    }
+
+    #[test]
+    fn test_table_edit_changes_plate() {
+        let mut edited = tables();
+        // Moderate's Doppler spread
+        edited[0].1[5] = 0.6;
+        assert_ne!(plate(&edited), provenance());
+        let mut edited = tables();
+        edited[1].1[0] += 1e-12;
+        assert_ne!(plate(&edited), provenance());
+    }
+}
@@ -0,0 +1,146 @@
+//! Built-in sanity check for the compiled library
+//!
+//! A build can load fine and still compute garbage (a cross-compile with
+//! fast-math, a broken libm). run() drives a short block through a fixed
+//! channel and checks the result against a golden vector captured from a
+//! known-good build, and that a second channel with the same seed
+//! reproduces it bit for bit.
+
+use std::f64::consts::PI;
+use std::time::{Duration, Instant};
+
+use crate::channel::{ChannelParams, WattersonChannel};
+
+/// Channel seed for both checks
+const SEED: u64 = 1955;
+
+/// Input block length: 100 ms at 9600 Hz
+const BLOCK: usize = 960;
+
+/// Output samples the golden vector holds: every 96th, from sample 48
+const GOLDEN_STRIDE: usize = 96;
+const GOLDEN_START: usize = 48;
+
+/// Output at GOLDEN_START, GOLDEN_START + GOLDEN_STRIDE, ... from a
+/// known-good x86_64 build
+pub(crate) const GOLDEN: [f64; 10] = [
+    9.35156268860828e-1,
+    9.733389438979136e-1,
+    9.521985048877344e-1,
+    9.495073723196702e-1,
+    9.137923787614316e-1,
+    8.992057337826233e-1,
+    8.865603709773789e-1,
+    8.676413230293998e-1,
+    8.210749035336496e-1,
+    8.348871584024645e-1,
+];
+
+/// Largest difference from GOLDEN allowed: well above libm's last-bit
+/// differences between platforms, far below any real fault
+const GOLDEN_TOLERANCE: f64 = 1e-9;
+
+/// Outcome of one check: a short summary, or what went wrong
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub result: Result<String, String>,
+}
+
+/// Every check run, in order, and how long they took together
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub checks: Vec<Check>,
+    pub elapsed: Duration,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.result.is_ok())
+    }
+
+    /// (name, detail) of each failed check
+    pub fn failures(&self) -> Vec<(&'static str, String)> {
+        self.checks
+            .iter()
+            .filter_map(|c| c.result.as_ref().err().map(|e| (c.name, e.clone())))
+            .collect()
+    }
+}
+
+/// Two-path fading channel with noise, exercising every stage
+fn params() -> ChannelParams {
+    ChannelParams {
+        delay_spread_samples: 10,
+        doppler_bandwidth_hz: 1.0,
+        snr_db: 30.0,
+        bulk_delay_samples: 20,
+        noise_bandwidth_hz: 0.0,
+        ..ChannelParams::default()
+    }
+}
+
+/// A 1500 Hz tone at half scale through a fresh channel
+fn render() -> Vec<f64> {
+    let input: Vec<f64> = (0..BLOCK)
+        .map(|n| 0.5 * (2.0 * PI * 1500.0 * n as f64 / 9600.0).sin())
+        .collect();
+    WattersonChannel::new(params(), SEED).process_f64(&input)
+}
+
+/// Run every check
+pub fn run() -> Report {
+    let start = Instant::now();
+    let first = render();
+    let second = render();
+    let checks = vec![
+        Check { name: "determinism", result: check_determinism(&first, &second) },
+        Check { name: "golden_vector", result: check_golden(&first, &GOLDEN) },
+    ];
+    Report { checks, elapsed: start.elapsed() }
+}
+
+fn check_determinism(first: &[f64], second: &[f64]) -> Result<String, String> {
+    match first.iter().zip(second).position(|(a, b)| a.to_bits() != b.to_bits()) {
+        Some(n) => Err(format!("same seed differs at sample {}: {} vs {}", n, first[n], second[n])),
+        None if first.len() != second.len() => Err(format!("lengths {} and {}", first.len(), second.len())),
+        None => Ok(format!("{} samples identical", first.len())),
+    }
+}
+
+fn check_golden(output: &[f64], golden: &[f64]) -> Result<String, String> {
+    let mut worst = 0.0f64;
+    for (k, &expected) in golden.iter().enumerate() {
+        let n = GOLDEN_START + k * GOLDEN_STRIDE;
+        let got = output.get(n).copied().unwrap_or(f64::NAN);
+        let diff = (got - expected).abs();
+        if diff.is_nan() || diff > GOLDEN_TOLERANCE {
+            return Err(format!("sample {}: {:e}, expected {:e}", n, got, expected));
+        }
+        worst = worst.max(diff);
+    }
+    Ok(format!("max deviation {:.1e}", worst))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes() {
+        let report = run();
+        assert!(report.passed(), "{:?}", report.failures());
+        assert!(report.elapsed < Duration::from_secs(1), "{:?}", report.elapsed);
+    }
+
+    #[test]
+    fn test_corrupted_output_is_caught() {
+        let mut output = render();
+        output[GOLDEN_START + 3 * GOLDEN_STRIDE] += 1e-6;
+        let err = check_golden(&output, &GOLDEN).unwrap_err();
+        assert!(err.starts_with("sample 336"), "{}", err);
+
+        let copy = render();
+        assert!(check_determinism(&output, &copy).is_err());
+    }
+}
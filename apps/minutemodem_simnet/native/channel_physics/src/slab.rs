@@ -2,26 +2,110 @@
 //!
 //! Provides O(1) insert/lookup/remove with stable IDs.
 //! Uses per-slot locks for concurrent access to different channels.
+//!
+//! ## Thread-safety contract
+//!
+//! - Lock order is always metadata, released, then one slot. No path holds
+//!   the metadata lock while waiting on a slot, so a closure running on
+//!   one channel never stalls lookups of the others, and remove() of a
+//!   busy channel only blocks its own caller.
+//! - A with_channel*() closure runs with its slot locked; it must not call
+//!   back into the same slab (remove() or another with_channel*() on the
+//!   same id would deadlock on that slot).
+//! - Each slot records the id it holds, checked under the slot lock. A
+//!   lookup that resolved an id to a slot just before that id was removed
+//!   (and the slot handed to a new item) finds a different id there and
+//!   returns None instead of touching the new item.
+//! - remove() of an id whose closure is in flight waits for the closure to
+//!   return, then takes the item. Only after that does the slot go back on
+//!   the free list, so it is never reused while still occupied.
+//! - A closure that panics poisons its slot. The next lookup of that id
+//!   clears the poison and calls the item's Recover::recover() before
+//!   running its closure, so the channel carries on; remove() frees a
+//!   poisoned slot as usual.
+//!
+//! A slab made with_census() holds a Tally for each item (see census),
+//! brought up to date after every with_channel_mut() closure and dropped
+//! when the item is removed.
+//!
+//! Test builds with `--cfg loom` swap in loom's locks and atomics, for the
+//! model test of insert/remove/with_channel_mut interleavings:
+//! `RUSTFLAGS="--cfg loom" cargo test --release --lib slab::tests::loom`
 
-use std::sync::{Mutex, RwLock};
+use std::sync::PoisonError;
+
+#[cfg(all(test, loom))]
+use loom::sync::atomic::{AtomicU64, Ordering};
+#[cfg(all(test, loom))]
+use loom::sync::{Mutex, MutexGuard, RwLock};
+#[cfg(not(all(test, loom)))]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(not(all(test, loom)))]
+use std::sync::{Mutex, MutexGuard, RwLock};
+
+use minutemodem_dsp::census::{Census, Tally};
+
+/// Slot owner value while the slot is empty (ids count up from 0)
+const NO_OWNER: u64 = u64::MAX;
+
+/// Mutex::clear_poison(); loom's Mutex never poisons and has none
+#[cfg(not(all(test, loom)))]
+fn clear_poison<T>(mutex: &Mutex<T>) {
+    mutex.clear_poison();
+}
+
+#[cfg(all(test, loom))]
+fn clear_poison<T>(_: &Mutex<T>) {}
+
+/// Putting an item back in working order after a closure panicked on it
+/// part way through
+pub trait Recover {
+    fn recover(&mut self);
+}
+
+/// The census a slab counts its items in, and how to measure one
+type Counting<T> = (&'static Census, fn(&T) -> usize);
 
 /// Slot containing a channel with its own lock
 pub struct ChannelSlot<T> {
     /// The channel data, protected by its own mutex
     pub data: Mutex<Option<T>>,
+    /// Id of the item in `data`; only changed with `data` locked
+    owner: AtomicU64,
+    /// The item's census entry, if the slab keeps one; only changed with
+    /// `data` locked
+    tally: Mutex<Option<Tally>>,
 }
 
-impl<T> ChannelSlot<T> {
+impl<T: Recover> ChannelSlot<T> {
     fn new() -> Self {
         Self {
             data: Mutex::new(None),
+            owner: AtomicU64::new(NO_OWNER),
+            tally: Mutex::new(None),
         }
     }
     
-    fn new_with(item: T) -> Self {
-        Self {
-            data: Mutex::new(Some(item)),
+    fn tally(&self) -> MutexGuard<'_, Option<Tally>> {
+        self.tally.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    
+    /// Lock the slot if it still holds `id`, recovering its item if a
+    /// closure panicked on it
+    fn lock_for(&self, id: u64) -> Option<MutexGuard<'_, Option<T>>> {
+        let data = self.data.lock().unwrap_or_else(|poisoned| {
+            let mut data = poisoned.into_inner();
+            if let Some(item) = data.as_mut() {
+                item.recover();
+            }
+            clear_poison(&self.data);
+            data
+        });
+        if self.owner.load(Ordering::Relaxed) != id {
+            return None;
         }
+        debug_assert!(data.is_some(), "slot owned by {} but empty", id);
+        Some(data)
     }
 }
 
@@ -36,6 +120,9 @@ pub struct ChannelSlab<T> {
     /// Metadata protected by RwLock
     /// (free list, id mapping, next_id)
     meta: RwLock<SlabMeta>,
+    
+    /// Where items are counted, and how their bytes are measured
+    census: Option<Counting<T>>,
 }
 
 struct SlabMeta {
@@ -49,7 +136,15 @@ struct SlabMeta {
     id_to_slot: std::collections::HashMap<u64, usize>,
 }
 
-impl<T> ChannelSlab<T> {
+impl SlabMeta {
+    /// Every slot is mapped, free, or between the two inside remove()
+    fn check(&self, capacity: usize) {
+        debug_assert!(self.id_to_slot.len() + self.free.len() <= capacity);
+        debug_assert!(self.free.iter().all(|&idx| idx < capacity));
+    }
+}
+
+impl<T: Recover> ChannelSlab<T> {
     pub fn new(capacity: usize) -> Self {
         let mut slots = Vec::with_capacity(capacity);
         for _ in 0..capacity {
@@ -64,9 +159,16 @@ impl<T> ChannelSlab<T> {
                 next_id: 0,
                 id_to_slot: std::collections::HashMap::new(),
             }),
+            census: None,
         }
     }
     
+    /// Count the items in `census`, measuring their bytes with `bytes`
+    pub fn with_census(mut self, census: &'static Census, bytes: fn(&T) -> usize) -> Self {
+        self.census = Some((census, bytes));
+        self
+    }
+    
     /// Insert an item, returns its ID or None if full
     /// Requires write lock on metadata
     pub fn insert(&self, item: T) -> Option<u64> {
@@ -77,12 +179,23 @@ impl<T> ChannelSlab<T> {
         let id = meta.next_id;
         meta.next_id += 1;
         
-        // Lock the specific slot and insert
-        let mut slot_data = self.slots[slot_idx].data.lock().ok()?;
+        // A free slot has no owner and nobody waiting on it (lookups only
+        // reach a slot through id_to_slot), so this doesn't block
+        let slot = &self.slots[slot_idx];
+        let mut slot_data = slot.data.lock().unwrap_or_else(PoisonError::into_inner);
+        debug_assert!(slot_data.is_none(), "free slot {} is occupied", slot_idx);
+        debug_assert_eq!(slot.owner.load(Ordering::Relaxed), NO_OWNER);
+        if let Some((census, bytes)) = self.census {
+            *slot.tally() = Some(Tally::new(census, bytes(&item)));
+        }
         *slot_data = Some(item);
+        slot.owner.store(id, Ordering::Relaxed);
         drop(slot_data);
+        // Clear poison left by a closure that panicked on the previous item
+        clear_poison(&slot.data);
         
         meta.id_to_slot.insert(id, slot_idx);
+        meta.check(self.slots.len());
         
         Some(id)
     }
@@ -100,9 +213,23 @@ impl<T> ChannelSlab<T> {
         F: FnOnce(&mut T) -> R,
     {
         let slot_idx = self.get_slot_idx(id)?;
-        let mut slot_data = self.slots[slot_idx].data.lock().ok()?;
+        self.with_slot_mut(slot_idx, id, f)
+    }
+    
+    /// with_channel_mut() once the id has been resolved to a slot, which by
+    /// now may hold something else
+    fn with_slot_mut<F, R>(&self, slot_idx: usize, id: u64, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        let slot = &self.slots[slot_idx];
+        let mut slot_data = slot.lock_for(id)?;
         let channel = slot_data.as_mut()?;
-        Some(f(channel))
+        let result = f(channel);
+        if let (Some((_, bytes)), Some(tally)) = (self.census, slot.tally().as_ref()) {
+            tally.set_bytes(bytes(channel));
+        }
+        Some(result)
     }
     
     /// Execute a function with read access to a channel
@@ -111,25 +238,36 @@ impl<T> ChannelSlab<T> {
         F: FnOnce(&T) -> R,
     {
         let slot_idx = self.get_slot_idx(id)?;
-        let slot_data = self.slots[slot_idx].data.lock().ok()?;
+        let slot_data = self.slots[slot_idx].lock_for(id)?;
         let channel = slot_data.as_ref()?;
         Some(f(channel))
     }
     
     /// Remove an item by ID
-    /// Requires write lock on metadata
+    ///
+    /// Unmaps the id first, so new lookups miss it; then waits for any
+    /// closure already running on it, takes the item and frees the slot.
     pub fn remove(&self, id: u64) -> Option<T> {
-        let mut meta = self.meta.write().ok()?;
-        
-        let slot_idx = meta.id_to_slot.remove(&id)?;
+        let slot_idx = {
+            let mut meta = self.meta.write().ok()?;
+            meta.id_to_slot.remove(&id)?
+        };
         
-        // Lock the specific slot and remove
-        let mut slot_data = self.slots[slot_idx].data.lock().ok()?;
-        let item = slot_data.take()?;
+        // Only this call can take the item now: the id is unmapped, and a
+        // slot isn't reused until it's back on the free list
+        let slot = &self.slots[slot_idx];
+        let mut slot_data = slot.data.lock().unwrap_or_else(PoisonError::into_inner);
+        debug_assert_eq!(slot.owner.load(Ordering::Relaxed), id, "slot {} changed hands", slot_idx);
+        let item = slot_data.take();
+        slot.owner.store(NO_OWNER, Ordering::Relaxed);
+        slot.tally().take();
         drop(slot_data);
         
+        let mut meta = self.meta.write().unwrap_or_else(PoisonError::into_inner);
+        debug_assert!(!meta.free.contains(&slot_idx), "slot {} freed twice", slot_idx);
         meta.free.push(slot_idx);
-        Some(item)
+        meta.check(self.slots.len());
+        item
     }
     
     /// Get the number of active items
@@ -138,9 +276,6 @@ impl<T> ChannelSlab<T> {
     }
 }
 
-// Make ChannelSlab safe to share across threads
-unsafe impl<T: Send> Sync for ChannelSlab<T> {}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -218,4 +353,288 @@ mod tests {
             assert_eq!(val, i as i32 + 1000);
         }
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_census_counts_items_in_and_out() {
+        static COUNTED: Census = Census::new("counted");
+        let slab: ChannelSlab<i32> = ChannelSlab::new(1000).with_census(&COUNTED, |v| *v as usize);
+        
+        for _ in 0..5 {
+            let ids: Vec<u64> = (0..1000).map(|i| slab.insert(i).unwrap()).collect();
+            assert_eq!(COUNTED.this_thread().live, 1000);
+            assert_eq!(COUNTED.this_thread().bytes, (0..1000).sum::<i64>());
+            
+            // A closure that changes the item's size is followed
+            slab.with_channel_mut(ids[0], |v| *v = 5000);
+            assert_eq!(COUNTED.this_thread().bytes, (0..1000).sum::<i64>() + 5000);
+            
+            for id in ids {
+                slab.remove(id);
+            }
+            assert_eq!(COUNTED.this_thread().live, 0);
+            assert_eq!(COUNTED.this_thread().bytes, 0);
+        }
+        assert_eq!(COUNTED.count().created, 5000);
+        assert_eq!(COUNTED.count().dropped, 5000);
+    }
+    
+    #[test]
+    fn test_stale_slot_lookup_misses() {
+        let slab: ChannelSlab<i32> = ChannelSlab::new(1);
+        
+        // A lookup resolves the id, then loses the race to remove + insert,
+        // which hands the same slot to a new item
+        let old = slab.insert(1).unwrap();
+        let slot_idx = slab.get_slot_idx(old).unwrap();
+        assert_eq!(slab.remove(old), Some(1));
+        let new = slab.insert(2).unwrap();
+        assert_eq!(slab.get_slot_idx(new), Some(slot_idx));
+        
+        assert_eq!(slab.with_slot_mut(slot_idx, old, |v| *v += 100), None);
+        assert_eq!(slab.with_channel(new, |v| *v), Some(2));
+    }
+    
+    #[test]
+    fn test_remove_waits_for_closure() {
+        use std::sync::mpsc;
+        use std::sync::Arc;
+        use std::thread;
+        use std::time::Duration;
+        
+        let slab: Arc<ChannelSlab<i32>> = Arc::new(ChannelSlab::new(2));
+        let busy = slab.insert(1).unwrap();
+        let other = slab.insert(2).unwrap();
+        
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (go_tx, go_rx) = mpsc::channel::<()>();
+        let worker = {
+            let slab = Arc::clone(&slab);
+            thread::spawn(move || {
+                slab.with_channel_mut(busy, |v| {
+                    entered_tx.send(()).unwrap();
+                    go_rx.recv().unwrap();
+                    *v += 10;
+                })
+            })
+        };
+        entered_rx.recv().unwrap();
+        
+        let remover = {
+            let slab = Arc::clone(&slab);
+            thread::spawn(move || slab.remove(busy))
+        };
+        // The id is unmapped straight away...
+        while slab.count() != 1 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(slab.with_channel(busy, |v| *v).is_none());
+        // ...the rest of the slab stays usable...
+        assert_eq!(slab.with_channel_mut(other, |v| { *v += 1; *v }), Some(3));
+        // ...but the busy slot isn't reused while the closure runs
+        assert!(slab.insert(3).is_none());
+        
+        go_tx.send(()).unwrap();
+        assert_eq!(worker.join().unwrap(), Some(()));
+        assert_eq!(remover.join().unwrap(), Some(11));
+        assert!(slab.insert(3).is_some());
+    }
+    
+    /// Test items recover to zero
+    impl Recover for i32 {
+        fn recover(&mut self) {
+            *self = 0;
+        }
+    }
+    
+    #[test]
+    fn test_panicked_slot_recovers() {
+        let slab: ChannelSlab<i32> = ChannelSlab::new(1);
+        let id = slab.insert(1).unwrap();
+        
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            slab.with_channel_mut(id, |v| {
+                *v += 1;
+                panic!("closure failed");
+            });
+        }));
+        assert!(result.is_err());
+        
+        // The next lookup recovers the item and carries on
+        assert_eq!(slab.with_channel(id, |v| *v), Some(0));
+        assert_eq!(slab.with_channel_mut(id, |v| { *v += 7; *v }), Some(7));
+        assert_eq!(slab.with_channel(id, |v| *v), Some(7));
+    }
+    
+    #[test]
+    fn test_remove_frees_poisoned_slot() {
+        let slab: ChannelSlab<i32> = ChannelSlab::new(1);
+        let id = slab.insert(1).unwrap();
+        
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            slab.with_channel_mut(id, |_| panic!("closure failed"));
+        }));
+        assert!(result.is_err());
+        
+        assert_eq!(slab.remove(id), Some(1));
+        assert_eq!(slab.count(), 0);
+        let id = slab.insert(5).unwrap();
+        assert_eq!(slab.with_channel(id, |v| *v), Some(5));
+    }
+    
+    /// Item for the stress test: knows its own id once stamped
+    struct Tagged {
+        id: u64,
+        count: u64,
+    }
+    
+    impl Recover for Tagged {
+        fn recover(&mut self) {}
+    }
+    
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+    
+    #[test]
+    fn test_stress_64_channels() {
+        use std::sync::atomic::AtomicBool;
+        use std::sync::Arc;
+        use std::thread;
+        use std::time::{Duration, Instant};
+        
+        const CHANNELS: usize = 64;
+        const OWNERS: usize = 8;
+        const UNSTAMPED: u64 = u64::MAX;
+        
+        let slab: Arc<ChannelSlab<Tagged>> = Arc::new(ChannelSlab::new(CHANNELS));
+        let stop = Arc::new(AtomicBool::new(false));
+        let highest_id = Arc::new(AtomicU64::new(0));
+        
+        // Owners each churn CHANNELS / OWNERS channels of their own, so the
+        // slab stays near full and slots are constantly reused
+        let owners: Vec<_> = (0..OWNERS as u64).map(|t| {
+            let slab = Arc::clone(&slab);
+            let stop = Arc::clone(&stop);
+            let highest_id = Arc::clone(&highest_id);
+            thread::spawn(move || {
+                let mut rng = 0x2545_f491_4f6c_dd1d ^ (t + 1);
+                let mut lanes: Vec<Option<(u64, u64)>> = vec![None; CHANNELS / OWNERS];
+                let mut ops = 0u64;
+                while !stop.load(Ordering::Relaxed) {
+                    let r = xorshift(&mut rng);
+                    let lane = &mut lanes[(r % (CHANNELS / OWNERS) as u64) as usize];
+                    match *lane {
+                        None => {
+                            let id = slab.insert(Tagged { id: UNSTAMPED, count: 0 }).expect("owner lane has a slot");
+                            assert_eq!(slab.with_channel_mut(id, |item| item.id = id), Some(()));
+                            highest_id.fetch_max(id, Ordering::Relaxed);
+                            *lane = Some((id, 0));
+                        }
+                        Some((id, count)) if r >> 60 == 0 => {
+                            let item = slab.remove(id).expect("owner's channel vanished");
+                            assert_eq!((item.id, item.count), (id, count));
+                            *lane = None;
+                        }
+                        Some((id, count)) => {
+                            let seen = slab.with_channel_mut(id, |item| {
+                                assert_eq!(item.id, id);
+                                item.count += 1;
+                                item.count
+                            });
+                            assert_eq!(seen, Some(count + 1));
+                            *lane = Some((id, count + 1));
+                        }
+                    }
+                    ops += 1;
+                }
+                for (id, count) in lanes.into_iter().flatten() {
+                    let item = slab.remove(id).unwrap();
+                    assert_eq!((item.id, item.count), (id, count));
+                }
+                ops
+            })
+        }).collect();
+        
+        // Readers look up arbitrary (often stale) ids; whatever they reach
+        // must be that id's item
+        let readers: Vec<_> = (0..4u64).map(|t| {
+            let slab = Arc::clone(&slab);
+            let stop = Arc::clone(&stop);
+            let highest_id = Arc::clone(&highest_id);
+            thread::spawn(move || {
+                let mut rng = 0x9e37_79b9_7f4a_7c15 ^ (t + 1);
+                let mut hits = 0u64;
+                while !stop.load(Ordering::Relaxed) {
+                    let id = xorshift(&mut rng) % (highest_id.load(Ordering::Relaxed) + 1);
+                    if let Some(tag) = slab.with_channel(id, |item| item.id) {
+                        assert!(tag == id || tag == UNSTAMPED, "id {} reached item {}", id, tag);
+                        hits += 1;
+                    }
+                }
+                hits
+            })
+        }).collect();
+        
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(3) {
+            assert!(slab.count() <= CHANNELS);
+            thread::sleep(Duration::from_millis(10));
+        }
+        stop.store(true, Ordering::Relaxed);
+        
+        let ops: u64 = owners.into_iter().map(|h| h.join().unwrap()).sum();
+        let hits: u64 = readers.into_iter().map(|h| h.join().unwrap()).sum();
+        assert!(ops > 10_000, "only {} owner operations", ops);
+        assert!(hits > 0);
+        
+        // Every slot came back
+        assert_eq!(slab.count(), 0);
+        for _ in 0..CHANNELS {
+            assert!(slab.insert(Tagged { id: UNSTAMPED, count: 0 }).is_some());
+        }
+        assert!(slab.insert(Tagged { id: UNSTAMPED, count: 0 }).is_none());
+    }
+
+    /// Every interleaving of a closure on an item, its removal and an
+    /// insert that may reuse the slot: the closure runs on the item or
+    /// misses it, never on its successor, and remove() hands back what
+    /// the closure left
+    #[cfg(loom)]
+    #[test]
+    fn loom_insert_remove_with_channel_mut() {
+        use loom::sync::Arc;
+        use loom::thread;
+
+        loom::model(|| {
+            let slab: Arc<ChannelSlab<i32>> = Arc::new(ChannelSlab::new(1));
+            let id = slab.insert(1).unwrap();
+
+            let worker = {
+                let slab = slab.clone();
+                thread::spawn(move || slab.with_channel_mut(id, |v| *v += 1))
+            };
+            let remover = {
+                let slab = slab.clone();
+                thread::spawn(move || slab.remove(id))
+            };
+            let inserted = slab.insert(100);
+
+            let ran = worker.join().unwrap().is_some();
+            let removed = remover.join().unwrap();
+            assert_eq!(removed, Some(if ran { 2 } else { 1 }));
+            assert_eq!(slab.with_channel(id, |v| *v), None);
+            match inserted {
+                Some(new_id) => {
+                    assert_ne!(new_id, id);
+                    assert_eq!(slab.with_channel(new_id, |v| *v), Some(100));
+                    assert_eq!(slab.count(), 1);
+                }
+                // The slot was still in use
+                None => assert_eq!(slab.count(), 0),
+            }
+        });
+    }
+}
@@ -0,0 +1,145 @@
+//! Helpers shared by the property tests (see properties)
+//!
+//! A proptest strategy for valid ChannelParams, a broadband test signal,
+//! and the two measurements the invariants are stated in: block power and
+//! delay by cross-correlation.
+
+use proptest::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::channel::{self, ChannelParams};
+use crate::precision::Precision;
+use crate::{conditioning, drift, fractional_delay, limits, output};
+
+/// Whether `params` passes every check create_channel makes
+pub fn is_valid(params: &ChannelParams) -> bool {
+    limits::validate_params(params).is_ok()
+        && output::validate(params.output_bits, params.clip_knee).is_ok()
+        && channel::validate_warm_start(params).is_ok()
+        && channel::validate_tap_gains(params).is_ok()
+        && channel::validate_frequency_offset(params).is_ok()
+        && channel::validate_noise_bandwidth(params).is_ok()
+        && channel::validate_impulse_noise(params).is_ok()
+        && drift::validate(params.sample_rate_offset_ppm).is_ok()
+        && conditioning::validate(params.sample_rate, params.input_tilt_db).is_ok()
+        && fractional_delay::validate(params.fractional_delay_samples).is_ok()
+}
+
+/// Zero, or a draw from `range` (zero being where a failure shrinks to)
+fn zero_or<T>(chance: u32, range: impl Strategy<Value = T> + 'static) -> BoxedStrategy<T>
+where
+    T: Default + Clone + std::fmt::Debug + 'static,
+{
+    prop_oneof![100 - chance => Just(T::default()), chance => range].boxed()
+}
+
+/// Random ChannelParams over the ranges the model is meant for and a
+/// little past them (zero Doppler, carriers near the band edge, echo
+/// paths beyond Appendix E's 10 ms), kept to what create_channel accepts
+///
+/// Every optional feature starts from off, so proptest shrinks a failure
+/// towards the plainest channel that still fails. A new noise mode
+/// belongs here too, so the properties (block size independence in
+/// particular) cover it.
+pub fn valid_params() -> impl Strategy<Value = ChannelParams> {
+    prop_oneof![Just(8000u32), Just(9600), Just(16_000), Just(48_000)]
+        .prop_flat_map(|sample_rate| {
+            let nyquist = sample_rate as f64 / 2.0;
+            let paths = (
+                zero_or(50, 1..=sample_rate / 50),
+                zero_or(90, 0.01..20.0),
+                -10.0..60.0,
+                // Clear of 0 Hz and Nyquist by the baseband filter's cutoff
+                channel::LPF_CUTOFF_HZ..(nyquist - channel::LPF_CUTOFF_HZ).max(3000.0),
+                zero_or(50, 1u32..2000),
+            );
+            let output = (
+                prop_oneof![Just(0u32), Just(8), Just(12), Just(16), Just(24)],
+                any::<bool>(),
+                zero_or(50, 0.3..0.99),
+                prop::bool::weighted(0.05),
+            );
+            let start = (
+                any::<Option<u64>>(),
+                any::<Option<u64>>(),
+                zero_or(50, 0.0..1000.0),
+                prop::option::weighted(0.2, -20.0..-1.0),
+                zero_or(50, -200.0..200.0),
+            );
+            let input = (
+                any::<bool>(),
+                zero_or(20, -6.0..6.0),
+                prop_oneof![Just(Precision::F64), Just(Precision::F32)],
+                prop::bool::weighted(0.2),
+                zero_or(20, 0.0..1.0),
+            );
+            let extras = (
+                zero_or(20, -20.0..6.0),
+                zero_or(20, -50.0..50.0),
+                prop_oneof![Just(0.0), Just(channel::DEFAULT_NOISE_BANDWIDTH_HZ)],
+                zero_or(20, 0.0..0.01),
+                0.0..20.0,
+                1u32..=100,
+            );
+            (Just(sample_rate), paths, output, start, input, extras)
+        })
+        .prop_map(|(sample_rate, paths, output, start, input, extras)| {
+            let (delay_spread_samples, doppler_bandwidth_hz, snr_db, carrier_freq_hz, bulk_delay_samples) = paths;
+            let (output_bits, output_dither, clip_knee, bypass) = output;
+            let (fading_seed, noise_seed, start_at_time_s, start_in_fade_db, sample_rate_offset_ppm) = start;
+            let (input_dc_block, input_tilt_db, precision, sideband_inversion, fractional_delay_samples) = input;
+            let (tap1_gain_db, frequency_offset_hz, noise_bandwidth_hz, impulse_probability, impulse_power_ratio_db, impulse_burst_samples) =
+                extras;
+            ChannelParams {
+                sample_rate,
+                delay_spread_samples,
+                doppler_bandwidth_hz,
+                snr_db,
+                carrier_freq_hz,
+                bulk_delay_samples,
+                output_bits,
+                output_dither,
+                clip_knee,
+                bypass,
+                fading_seed,
+                noise_seed,
+                start_at_time_s,
+                start_in_fade_db,
+                sample_rate_offset_ppm,
+                input_dc_block,
+                input_tilt_db,
+                precision,
+                sideband_inversion,
+                fractional_delay_samples,
+                tap0_gain_db: 0.0,
+                tap1_gain_db,
+                frequency_offset_hz,
+                noise_bandwidth_hz,
+                impulse_probability,
+                impulse_power_ratio_db,
+                impulse_burst_samples,
+            }
+        })
+        .prop_filter("rejected by create_channel's validation", is_valid)
+}
+
+/// Deterministic broadband test signal, uniform on ±0.25
+pub fn pseudo_noise(num_samples: usize, seed: u64) -> Vec<f64> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    (0..num_samples).map(|_| rng.gen_range(-0.25..0.25)).collect()
+}
+
+/// Mean square of a block
+pub fn power(x: &[f64]) -> f64 {
+    x.iter().map(|v| v * v).sum::<f64>() / x.len().max(1) as f64
+}
+
+/// Lag (0..=max_lag) at which `output` correlates best with `input`,
+/// by magnitude
+pub fn delay_by_xcorr(input: &[f64], output: &[f64], max_lag: usize) -> usize {
+    let xcorr = |lag: usize| -> f64 {
+        output.iter().skip(lag).zip(input).map(|(y, x)| y * x).sum::<f64>().abs()
+    };
+    (0..=max_lag).max_by(|&a, &b| xcorr(a).total_cmp(&xcorr(b))).unwrap_or(0)
+}
@@ -0,0 +1,182 @@
+//! Transmit/receive switching of a half-duplex receiver
+//!
+//! A half-duplex radio mutes its receiver while it transmits, and the
+//! T/R relay and AGC take a few ms to get there and back. With a TR
+//! schedule the channel output (signal and noise alike, as the receiver
+//! hears it) follows the receiver through that: from each `(at_sample,
+//! state)` on it is either at full gain (`Rx`) or attenuated by
+//! `attenuation_db` (`Tx`; muted outright when None), moving between the
+//! two along a raised-cosine ramp of `ramp_samples` that starts at the
+//! transition sample. A transition that lands mid-ramp starts the next
+//! ramp from wherever the gain had got to.
+//!
+//! ```
+//! use channel_physics::tr_switch::{TrState, TrSwitch};
+//!
+//! let mut tr = TrSwitch::new(&[(100, TrState::Tx), (500, TrState::Rx)], None, 40, 0).unwrap();
+//! assert_eq!(tr.gain(99), 1.0);
+//! assert!((tr.gain(120) - 0.5).abs() < 1e-12); // halfway down the ramp
+//! assert_eq!(tr.gain(140), 0.0); // muted
+//! assert_eq!(tr.gain(540), 1.0); // back up after the :rx ramp
+//! ```
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use rustler::NifUnitEnum;
+
+/// Whether the receiving radio is listening or transmitting
+#[derive(NifUnitEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrState {
+    Rx,
+    Tx,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrSwitch {
+    /// Transitions still to come, as (sample_index, state)
+    schedule: VecDeque<(u64, TrState)>,
+    state: TrState,
+    /// Amplitude gain while transmitting
+    tx_gain: f64,
+    ramp_samples: u64,
+    /// The ramp under way or last run: (start sample, from gain)
+    ramp: Option<(u64, f64)>,
+}
+
+impl TrSwitch {
+    /// A switch that starts out receiving and follows `schedule` from
+    /// `sample_index` on
+    ///
+    /// Transition samples must strictly increase from `sample_index`;
+    /// `attenuation_db` is None (mute) or a non-negative dB figure.
+    pub fn new(
+        schedule: &[(u64, TrState)],
+        attenuation_db: Option<f64>,
+        ramp_samples: u64,
+        sample_index: u64,
+    ) -> Result<Self, &'static str> {
+        let increasing = schedule.windows(2).all(|w| w[0].0 < w[1].0);
+        let ahead = schedule.first().is_none_or(|&(at, _)| at >= sample_index);
+        let depth_ok = attenuation_db.is_none_or(|db| db >= 0.0 && !db.is_nan());
+        if !(increasing && ahead && depth_ok) {
+            return Err("invalid_tr_schedule");
+        }
+        Ok(Self {
+            schedule: schedule.iter().copied().collect(),
+            state: TrState::Rx,
+            tx_gain: attenuation_db.map_or(0.0, |db| 10f64.powf(-db / 20.0)),
+            ramp_samples,
+            ramp: None,
+        })
+    }
+
+    /// Gain a steady `state` settles at
+    fn steady_gain(&self, state: TrState) -> f64 {
+        match state {
+            TrState::Rx => 1.0,
+            TrState::Tx => self.tx_gain,
+        }
+    }
+
+    /// Gain at `sample_index` without taking up any transitions due by then
+    fn gain_now(&self, sample_index: u64) -> f64 {
+        let target = self.steady_gain(self.state);
+        match self.ramp {
+            Some((start, from)) if sample_index < start + self.ramp_samples => {
+                let t = (sample_index - start) as f64 / self.ramp_samples as f64;
+                from + (target - from) * 0.5 * (1.0 - (PI * t).cos())
+            }
+            _ => target,
+        }
+    }
+
+    /// Bytes held by the transitions still to come
+    pub fn memory_bytes(&self) -> usize {
+        self.schedule.capacity() * std::mem::size_of::<(u64, TrState)>()
+    }
+
+    /// Amplitude gain for the output sample at `sample_index`
+    ///
+    /// Sample indices must not go backwards from one call to the next.
+    pub fn gain(&mut self, sample_index: u64) -> f64 {
+        while let Some(&(at, state)) = self.schedule.front().filter(|&&(at, _)| at <= sample_index) {
+            self.schedule.pop_front();
+            if state != self.state {
+                let from = self.gain_now(at);
+                self.state = state;
+                self.ramp = Some((at, from));
+            }
+        }
+        self.gain_now(sample_index)
+    }
+
+    /// State the receiver is in at `sample_index`
+    pub fn state_at(&self, sample_index: u64) -> TrState {
+        self.schedule
+            .iter()
+            .take_while(|&&(at, _)| at <= sample_index)
+            .last()
+            .map_or(self.state, |&(_, state)| state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ramp_is_raised_cosine() {
+        let mut tr = TrSwitch::new(&[(10, TrState::Tx)], None, 8, 0).unwrap();
+        let gains: Vec<f64> = (0..20).map(|n| tr.gain(n)).collect();
+        assert!(gains[..10].iter().all(|&g| g == 1.0));
+        for (k, &g) in gains[10..18].iter().enumerate() {
+            let expected = 0.5 * (1.0 + (PI * k as f64 / 8.0).cos());
+            assert!((g - expected).abs() < 1e-12, "sample {}: {} vs {}", 10 + k, g, expected);
+        }
+        assert!(gains[18..].iter().all(|&g| g == 0.0));
+    }
+
+    #[test]
+    fn test_attenuation_depth() {
+        let mut tr = TrSwitch::new(&[(0, TrState::Tx)], Some(30.0), 0, 0).unwrap();
+        assert!((20.0 * tr.gain(0).log10() + 30.0).abs() < 1e-9);
+        assert_eq!(tr.state_at(0), TrState::Tx);
+    }
+
+    #[test]
+    fn test_transition_mid_ramp_starts_from_current_gain() {
+        let mut tr = TrSwitch::new(&[(0, TrState::Tx), (5, TrState::Rx)], None, 10, 0).unwrap();
+        let at_switch = tr.gain(4);
+        let next = tr.gain(5);
+        // Still heading down at 4, turning back up from about there at 5
+        assert!(at_switch > 0.5 && at_switch < 1.0);
+        assert!((next - 0.5 * (1.0 + (PI * 0.5).cos())).abs() < 1e-12);
+        assert_eq!(tr.gain(15), 1.0);
+    }
+
+    #[test]
+    fn test_skipped_transitions_are_taken_up_late() {
+        // gain() called only long after both transitions: still back at rx
+        let mut tr = TrSwitch::new(&[(100, TrState::Tx), (200, TrState::Rx)], None, 20, 0).unwrap();
+        assert_eq!(tr.state_at(150), TrState::Tx);
+        assert_eq!(tr.gain(1000), 1.0);
+        assert_eq!(tr.state_at(1000), TrState::Rx);
+    }
+
+    #[test]
+    fn test_validation() {
+        for schedule in [
+            &[(10, TrState::Tx), (10, TrState::Rx)][..],
+            &[(20, TrState::Tx), (10, TrState::Rx)],
+            &[(4, TrState::Tx)],
+        ] {
+            assert_eq!(TrSwitch::new(schedule, None, 0, 5).err(), Some("invalid_tr_schedule"));
+        }
+        for depth in [-3.0, f64::NAN] {
+            assert!(TrSwitch::new(&[(10, TrState::Tx)], Some(depth), 0, 5).is_err());
+        }
+        assert!(TrSwitch::new(&[], None, 0, 5).is_ok());
+        assert!(TrSwitch::new(&[(5, TrState::Tx)], Some(0.0), 0, 5).is_ok());
+    }
+}
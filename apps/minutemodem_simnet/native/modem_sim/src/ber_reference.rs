@@ -0,0 +1,312 @@
+//! Theoretical SER/BER curves, for plotting against measured results
+//!
+//! Uncoded symbols from the constellation's own table, every symbol
+//! equally likely, sliced to the nearest point as phy_modem's slicers do,
+//! over AWGN or flat Rayleigh fading with the receiver knowing the fade
+//! (a perfect gain reference). SNR is Es/N0 in dB, Es the mean symbol
+//! energy over all `order` symbols of the table.
+//!
+//! No closed forms: the 110D QAM tables are circular, not square, and
+//! 32-QAM and 64-QAM give some points to two symbols. Instead each sent
+//! point's error probability is integrated over the real decision
+//! regions. Complex Gaussian noise of power N0 leaves a disc of radius r
+//! with probability exp(-r²/N0), whatever the direction, so walking a ray
+//! out from the sent point through the Voronoi cells it crosses, the
+//! chance of ending in each cell is the difference of that tail between
+//! where the ray enters and leaves it. Averaging over the ray's direction
+//! gives the transition probabilities exactly, up to the angular
+//! quadrature (RAYS midpoint steps, good to 10⁻⁵ relative at the
+//! SNRs plotted). Under Rayleigh fading the tail averaged over the fade
+//! power is 1 / (1 + r²/N0), and the rest is the same.
+//!
+//! Errors are counted as the Scoreboard counts them: a symbol error is a
+//! decision other than the symbol sent, bit errors the differing bits of
+//! the two. A duplicated point always decodes to its lower symbol, so for
+//! 32-QAM and 64-QAM SER and BER level off at a floor however high the
+//! SNR; `point_ser` counts only decisions on the wrong point.
+
+use phy_modem::ConstellationType;
+
+/// Directions the error probability is averaged over
+pub const RAYS: usize = 1440;
+
+/// Most SNR points one call may ask for
+pub const MAX_POINTS: usize = 4096;
+
+/// Fading the reference is computed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fading {
+    Awgn,
+    /// Flat Rayleigh, coherent detection with perfect channel knowledge
+    Rayleigh,
+}
+
+impl Fading {
+    /// Probability that the noise (and fade) carries the received point
+    /// further than `r` from the sent one, for noise power `n0`
+    fn tail(self, r: f64, n0: f64) -> f64 {
+        if r.is_infinite() {
+            return 0.0;
+        }
+        match self {
+            Self::Awgn => (-r * r / n0).exp(),
+            Self::Rayleigh => 1.0 / (1.0 + r * r / n0),
+        }
+    }
+}
+
+/// Theoretical rates at one SNR
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BerPoint {
+    pub snr_db: f64,
+    pub ser: f64,
+    pub ber: f64,
+    /// Decisions on a point other than the one sent
+    pub point_ser: f64,
+}
+
+/// A ray's passage through one decision region: the region's symbol and
+/// the distances from the sent point where the ray enters and leaves it
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    symbol: u8,
+    enter: f64,
+    leave: f64,
+}
+
+/// The decision regions every ray from every distinct point crosses
+struct Geometry {
+    constellation: ConstellationType,
+    /// Per canonical symbol, per ray, the segments in order
+    rays: Vec<Vec<Vec<Segment>>>,
+    /// Mean symbol energy over the whole table
+    es: f64,
+}
+
+impl Geometry {
+    fn new(constellation: ConstellationType) -> Self {
+        let points = constellation.points();
+        let es = points.iter().map(|&(i, q)| i * i + q * q).sum::<f64>() / points.len() as f64;
+        let canonical = constellation.canonical_symbols();
+        let rays = canonical
+            .iter()
+            .map(|&sent| {
+                (0..RAYS)
+                    .map(|k| {
+                        let angle = (k as f64 + 0.5) * std::f64::consts::TAU / RAYS as f64;
+                        walk(&points, canonical, sent, (angle.cos(), angle.sin()))
+                    })
+                    .collect()
+            })
+            .collect();
+        Self { constellation, rays, es }
+    }
+
+    fn at(&self, fading: Fading, snr_db: f64) -> BerPoint {
+        let n0 = self.es / 10f64.powf(snr_db / 10.0);
+        let order = self.constellation.order();
+        let bits = self.constellation.bits_per_symbol() as f64;
+        let canonical = self.constellation.canonical_symbols();
+
+        // Transition probabilities from each distinct point
+        let mut decided = vec![[0.0; 64]; canonical.len()];
+        for (from, rays) in self.rays.iter().enumerate() {
+            for segments in rays {
+                for s in segments {
+                    decided[from][s.symbol as usize] += fading.tail(s.enter, n0) - fading.tail(s.leave, n0);
+                }
+            }
+            decided[from].iter_mut().for_each(|p| *p /= RAYS as f64);
+        }
+
+        let (mut ser, mut ber, mut point_ser) = (0.0, 0.0, 0.0);
+        for sent in 0..order as u8 {
+            let from = canonical.iter().position(|&c| c == self.constellation.canonical_symbol(sent)).unwrap();
+            for &to in canonical {
+                let p = decided[from][to as usize];
+                if to != sent {
+                    ser += p;
+                    ber += p * (to ^ sent).count_ones() as f64 / bits;
+                }
+                if to != canonical[from] {
+                    point_ser += p;
+                }
+            }
+        }
+        let order = order as f64;
+        BerPoint { snr_db, ser: ser / order, ber: ber / order, point_ser: point_ser / order }
+    }
+}
+
+/// The regions a ray from `sent`'s point in direction `u` crosses,
+/// nearest first, out to the unbounded one it leaves by
+fn walk(points: &[(f64, f64)], canonical: &[u8], sent: u8, u: (f64, f64)) -> Vec<Segment> {
+    let origin = points[sent as usize];
+    let mut segments = Vec::new();
+    let (mut cell, mut enter) = (sent, 0.0);
+    loop {
+        // Leave by the nearest bisector ahead: the ray point x = origin + r u
+        // is nearer m than cell once 2<x, m - cell> > |m|² - |cell|²
+        let c = points[cell as usize];
+        let exit = canonical
+            .iter()
+            .filter(|&&m| m != cell)
+            .filter_map(|&m| {
+                let p = points[m as usize];
+                let d = (p.0 - c.0, p.1 - c.1);
+                let rate = 2.0 * (u.0 * d.0 + u.1 * d.1);
+                if rate <= 1e-12 {
+                    return None;
+                }
+                let bound = p.0 * p.0 + p.1 * p.1 - c.0 * c.0 - c.1 * c.1 - 2.0 * (origin.0 * d.0 + origin.1 * d.1);
+                Some((bound / rate, m))
+            })
+            .filter(|&(r, _)| r >= enter - 1e-12)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        match exit {
+            Some((leave, next)) => {
+                segments.push(Segment { symbol: cell, enter, leave });
+                cell = next;
+                enter = leave.max(enter);
+            }
+            None => {
+                segments.push(Segment { symbol: cell, enter, leave: f64::INFINITY });
+                return segments;
+            }
+        }
+    }
+}
+
+/// SER and BER of `constellation` over `fading` at each of `snr_db`
+pub fn ber_reference(constellation: ConstellationType, fading: Fading, snr_db: &[f64]) -> Vec<BerPoint> {
+    let geometry = Geometry::new(constellation);
+    snr_db.iter().map(|&snr| geometry.at(fading, snr)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Standard normal pairs, Box-Muller over an xorshift
+    struct Gaussian(u64);
+
+    impl Gaussian {
+        fn uniform(&mut self) -> f64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            ((self.0 >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+        }
+
+        fn pair(&mut self) -> (f64, f64) {
+            let (u, v) = (self.uniform(), self.uniform());
+            let r = (-2.0 * u.ln()).sqrt();
+            let theta = std::f64::consts::TAU * v;
+            (r * theta.cos(), r * theta.sin())
+        }
+    }
+
+    /// Monte-Carlo SER and BER through the modem's own slicer
+    fn trial(constellation: ConstellationType, fading: Fading, snr_db: f64, symbols: usize) -> (f64, f64) {
+        let points = constellation.points();
+        let es = points.iter().map(|&(i, q)| i * i + q * q).sum::<f64>() / points.len() as f64;
+        let sigma = (es / 10f64.powf(snr_db / 10.0) / 2.0).sqrt();
+        let mut rng = Gaussian(0x9e37_79b9_7f4a_7c15);
+        let (mut symbol_errors, mut bit_errors) = (0, 0);
+        for k in 0..symbols {
+            let sent = (k % constellation.order()) as u8;
+            let (i, q) = points[sent as usize];
+            let (ni, nq) = rng.pair();
+            let (ni, nq) = match fading {
+                Fading::Awgn => (ni * sigma, nq * sigma),
+                Fading::Rayleigh => {
+                    // y = h p + n, divided through by the known h
+                    let (hi, hq) = rng.pair();
+                    let (hi, hq) = (hi / 2f64.sqrt(), hq / 2f64.sqrt());
+                    let power = hi * hi + hq * hq;
+                    ((ni * hi + nq * hq) * sigma / power, (nq * hi - ni * hq) * sigma / power)
+                }
+            };
+            let decided = constellation.iq_to_symbol(i + ni, q + nq);
+            symbol_errors += (decided != sent) as u64;
+            bit_errors += (decided ^ sent).count_ones() as u64;
+        }
+        let bits = (symbols * constellation.bits_per_symbol()) as f64;
+        (symbol_errors as f64 / symbols as f64, bit_errors as f64 / bits)
+    }
+
+    /// Within four standard errors of `symbols` Bernoulli trials
+    fn agrees(theory: f64, measured: f64, trials: f64) -> bool {
+        (theory - measured).abs() < 4.0 * (theory * (1.0 - theory) / trials).sqrt()
+    }
+
+    #[test]
+    fn test_matches_monte_carlo() {
+        let cases = [
+            (ConstellationType::Psk8, Fading::Awgn, 12.0),
+            (ConstellationType::Psk8, Fading::Rayleigh, 20.0),
+            (ConstellationType::Qam16, Fading::Awgn, 16.0),
+            (ConstellationType::Qam16, Fading::Rayleigh, 25.0),
+            (ConstellationType::Qam64, Fading::Awgn, 24.0),
+            (ConstellationType::Qam64, Fading::Rayleigh, 30.0),
+        ];
+        let symbols = 200_000;
+        for (constellation, fading, snr) in cases {
+            let theory = ber_reference(constellation, fading, &[snr])[0];
+            let (ser, ber) = trial(constellation, fading, snr, symbols);
+            let bits = (symbols * constellation.bits_per_symbol()) as f64;
+            assert!(
+                agrees(theory.ser, ser, symbols as f64),
+                "{constellation:?} {fading:?} {snr} dB: SER {} against {ser}",
+                theory.ser
+            );
+            // Bit errors within a symbol aren't independent; the symbol
+            // count is the safer number of trials
+            assert!(
+                agrees(theory.ber, ber, bits.min(symbols as f64)),
+                "{constellation:?} {fading:?} {snr} dB: BER {} against {ber}",
+                theory.ber
+            );
+        }
+    }
+
+    #[test]
+    fn test_psk8_awgn_matches_closed_form() {
+        // Craig's exact 8-PSK SER: (1/π) ∫₀^{7π/8} exp(-γ sin²(π/8) / sin²θ) dθ
+        let exact = |snr_db: f64| {
+            let gamma = 10f64.powf(snr_db / 10.0);
+            let steps = 100_000;
+            let h = 7.0 * std::f64::consts::PI / 8.0 / steps as f64;
+            let sin2 = (std::f64::consts::PI / 8.0).sin().powi(2);
+            (0..steps)
+                .map(|k| {
+                    let theta = (k as f64 + 0.5) * h;
+                    (-gamma * sin2 / theta.sin().powi(2)).exp()
+                })
+                .sum::<f64>()
+                * h
+                / std::f64::consts::PI
+        };
+        for point in ber_reference(ConstellationType::Psk8, Fading::Awgn, &[0.0, 6.0, 12.0, 18.0]) {
+            let expected = exact(point.snr_db);
+            assert!((point.ser - expected).abs() < 1e-5 * expected.max(1e-3), "{point:?} against {expected}");
+            assert_eq!(point.point_ser, point.ser);
+        }
+    }
+
+    #[test]
+    fn test_curves_fall_with_snr_to_the_duplicate_floor() {
+        let snrs: Vec<f64> = (0..=12).map(|k| k as f64 * 5.0).collect();
+        for constellation in [ConstellationType::Psk8, ConstellationType::Qam16, ConstellationType::Qam64] {
+            for fading in [Fading::Awgn, Fading::Rayleigh] {
+                let curve = ber_reference(constellation, fading, &snrs);
+                assert!(curve.windows(2).all(|w| w[1].ser <= w[0].ser && w[1].ber <= w[0].ber), "{constellation:?} {fading:?}");
+            }
+        }
+        // 64-QAM: 16 of 64 symbols share a point with a lower one
+        let floor = ber_reference(ConstellationType::Qam64, Fading::Awgn, &[80.0])[0];
+        assert!((floor.ser - 0.25).abs() < 1e-9, "{floor:?}");
+        assert!(floor.point_ser < 1e-12);
+        assert!(ber_reference(ConstellationType::Qam16, Fading::Awgn, &[60.0])[0].ser < 1e-12);
+    }
+}
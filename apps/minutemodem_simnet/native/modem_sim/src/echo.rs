@@ -0,0 +1,355 @@
+//! Half-duplex self-interference scenarios
+//!
+//! A transceiver's receiver hears its own transmission through the TR
+//! switch, attenuated by the switch's isolation. The receive chain runs
+//! throughout, so its timing and carrier loops have been tracking that echo
+//! by the time the transmitter unkeys and the distant station starts: it
+//! must not carry any of it into the real reception.
+//!
+//! run_echo_scenario() builds that timeline: our own burst, attenuated by
+//! `isolation_db` and sent through a leak channel whose noise is the
+//! receiver's noise floor for the whole run, then after `turnaround_samples`
+//! of that floor the distant station's burst (a known preamble then data)
+//! through its own channel. One demodulator takes it all in blocks, as the
+//! sound card would deliver them, with the echo handling under test
+//! applied at the switch to receive. The decoded symbols are synced on the
+//! preamble, as the link layer would, and the data scored.
+//!
+//! ## Known limitation
+//!
+//! reset_to_idle() at the switch only helps if the distant station starts
+//! within the turnaround the receiver can absorb. After the reset the
+//! demodulator takes symbol timing from its first 500 samples and its
+//! carrier loop starts pulling in at once: with a 100-sample turnaround
+//! both lock onto the distant burst and it decodes clean at any isolation
+//! down to 20 dB, but with 960 samples (100 ms) of noise floor first they
+//! lock onto that, and the burst is lost, timing tracking or not
+//! (test_long_turnaround_loses_burst). Without the reset the burst is
+//! usually lost, even at 200 dB isolation: the chain has run on the floor
+//! for the whole transmission, and the symbol timing it took there suits
+//! the distant burst only by chance. Resetting when the distant carrier is detected,
+//! rather than at the switch, would cover both; nothing does that yet.
+
+use channel_physics::channel::{ChannelParams, WattersonChannel};
+use minutemodem_dsp::convert::{f64_to_i16, i16_to_f64};
+use phy_modem::modem::EotConfig;
+use phy_modem::{ConstellationType, UnifiedDemodulator, UnifiedModulator};
+
+use crate::frame::{self, Burst};
+
+/// Fraction of the preamble that must match for the burst to count as
+/// found
+pub const SYNC_THRESHOLD: f64 = 0.9;
+
+/// What the receive chain does about the echo
+#[derive(Debug, Clone, Default)]
+pub struct EchoHandling {
+    /// reset_to_idle() the demodulator at the switch to receive
+    pub reset_at_rx: bool,
+    /// End-of-transmission detector (set `truncate` to trim the echo's
+    /// tail from the output)
+    pub eot: Option<EotConfig>,
+    pub timing_tracking: bool,
+}
+
+/// One transmit-then-receive turnaround
+#[derive(Debug, Clone)]
+pub struct EchoScenario {
+    pub constellation: ConstellationType,
+    pub symbol_rate: u32,
+    pub carrier_freq: f64,
+    /// Our own transmission
+    pub tx_symbols: Vec<u8>,
+    /// Attenuation of our own signal into the receiver, dB
+    pub isolation_db: f64,
+    /// Path of the echo into the receiver; its noise is the receiver's
+    /// floor throughout, and its sample_rate everyone's
+    pub leak_channel: ChannelParams,
+    /// Receiver input from the end of our transmission to the distant
+    /// station's first sample
+    pub turnaround_samples: usize,
+    /// Known symbols the distant burst starts with
+    pub preamble: Vec<u8>,
+    pub data: Vec<u8>,
+    pub remote_channel: ChannelParams,
+    /// Gain on the distant burst's channel output
+    pub remote_level_db: f64,
+    /// Samples per demodulate call
+    pub block_samples: usize,
+    pub handling: EchoHandling,
+    pub seed: u64,
+}
+
+/// What the demodulator made of the turnaround
+#[derive(Debug, Clone, PartialEq)]
+pub struct EchoOutcome {
+    /// Symbols emitted while we were transmitting
+    pub tx_symbols_out: usize,
+    /// Symbols emitted from the switch to receive on
+    pub rx_symbols_out: usize,
+    /// Where the preamble starts among the received symbols and the
+    /// carrier phase rotation it was found at; None if no offset matched
+    /// SYNC_THRESHOLD of it
+    pub sync: Option<(usize, u8)>,
+    /// Data symbols wrong or missing after the preamble (all of them
+    /// without sync)
+    pub symbol_errors: usize,
+    pub data_symbols: usize,
+}
+
+impl EchoOutcome {
+    /// Symbol error rate over the data
+    pub fn ser(&self) -> f64 {
+        self.symbol_errors as f64 / self.data_symbols.max(1) as f64
+    }
+}
+
+/// Run the scenario (see the module docs)
+pub fn run_echo_scenario(scenario: &EchoScenario) -> Result<EchoOutcome, &'static str> {
+    let sample_rate = scenario.leak_channel.sample_rate;
+    if scenario.remote_channel.sample_rate != sample_rate {
+        return Err("sample_rate_mismatch");
+    }
+    if scenario.block_samples == 0 {
+        return Err("invalid_block_samples");
+    }
+    if scenario.preamble.is_empty() {
+        return Err("empty_preamble");
+    }
+    let constellation = scenario.constellation;
+    let order = constellation.order() as u8;
+    if scenario.tx_symbols.iter().chain(&scenario.preamble).chain(&scenario.data).any(|&s| s >= order) {
+        return Err("invalid_symbol");
+    }
+
+    // Our burst, the first samples of the timeline
+    let mut modulator = UnifiedModulator::new(constellation, sample_rate, scenario.symbol_rate, scenario.carrier_freq);
+    let mut own = modulator.modulate(&scenario.tx_symbols);
+    own.extend(modulator.end_burst());
+    let tx_len = own.len();
+    let sps = modulator.sps();
+
+    // The distant burst after the turnaround, with room for its tail
+    let mut remote_symbols = scenario.preamble.clone();
+    remote_symbols.extend(&scenario.data);
+    let remote = Burst {
+        start_sample: tx_len + scenario.turnaround_samples,
+        symbols: remote_symbols,
+        constellation,
+        symbol_rate: scenario.symbol_rate,
+        carrier_freq: scenario.carrier_freq,
+        channel: scenario.remote_channel.clone(),
+        seed: scenario.seed,
+        level_db: scenario.remote_level_db,
+    };
+    let channel = &scenario.remote_channel;
+    let tail = 32 * sps + channel.bulk_delay_samples as usize + channel.delay_spread_samples as usize + 256;
+    let frame_len = remote.start_sample + remote.symbols.len() * sps + tail;
+    let (mut rx, placements) = frame::compose_frame(frame_len, std::slice::from_ref(&remote))?;
+
+    // The echo and the floor under everything
+    let gain = 10f64.powf(-scenario.isolation_db / 20.0);
+    let mut leak_input: Vec<f64> = own.iter().map(|&x| i16_to_f64(x) * gain).collect();
+    leak_input.resize(frame_len, 0.0);
+    let mut leak = WattersonChannel::new(scenario.leak_channel.clone(), scenario.seed.wrapping_add(1));
+    for (out, x) in rx.iter_mut().zip(leak.process_f64(&leak_input)) {
+        *out += x;
+    }
+    let samples: Vec<i16> = rx.iter().map(|&x| f64_to_i16(x)).collect();
+
+    let handling = &scenario.handling;
+    let mut demod = UnifiedDemodulator::new(constellation, sample_rate, scenario.symbol_rate, scenario.carrier_freq);
+    if handling.timing_tracking {
+        demod.enable_timing_tracking();
+    }
+    demod.set_eot_detector(handling.eot);
+
+    let demodulate = |demod: &mut UnifiedDemodulator, samples: &[i16]| -> Vec<u8> {
+        samples.chunks(scenario.block_samples).flat_map(|block| demod.demodulate(block)).collect()
+    };
+    let tx_out = demodulate(&mut demod, &samples[..tx_len]);
+    if handling.reset_at_rx {
+        demod.reset_to_idle();
+    }
+    let rx_out = demodulate(&mut demod, &samples[tx_len..]);
+
+    // The preamble can be no earlier than the turnaround, and no later
+    // than the channel delays and filter latency allow for
+    let earliest = (placements[0].first_symbol_sample - tx_len) / sps;
+    let search = earliest.saturating_sub(16)..earliest + 64;
+    let sync = find_preamble(&rx_out, &scenario.preamble, search, order, rotations(constellation));
+    let symbol_errors = match sync {
+        Some((offset, rot)) => {
+            let received = rx_out.get(offset + scenario.preamble.len()..).unwrap_or(&[]);
+            let right = scenario.data.iter().zip(received).filter(|&(&s, &r)| (s + rot) % order == r).count();
+            scenario.data.len() - right
+        }
+        None => scenario.data.len(),
+    };
+
+    Ok(EchoOutcome {
+        tx_symbols_out: tx_out.len(),
+        rx_symbols_out: rx_out.len(),
+        sync,
+        symbol_errors,
+        data_symbols: scenario.data.len(),
+    })
+}
+
+/// Carrier phase rotations the demodulator can't tell apart, in symbol
+/// index steps: all of them for PSK, none for QAM (whose labelling isn't a
+/// rotation)
+pub(crate) fn rotations(constellation: ConstellationType) -> u8 {
+    match constellation {
+        ConstellationType::Bpsk | ConstellationType::Qpsk | ConstellationType::Psk8 => constellation.order() as u8,
+        _ => 1,
+    }
+}
+
+/// Best offset in `search` and rotation for the preamble, if it matches
+/// at least SYNC_THRESHOLD of it
+pub(crate) fn find_preamble(
+    received: &[u8],
+    preamble: &[u8],
+    search: std::ops::Range<usize>,
+    order: u8,
+    rotations: u8,
+) -> Option<(usize, u8)> {
+    let (matched, offset, rot) = search
+        .flat_map(|offset| (0..rotations).map(move |rot| (offset, rot)))
+        .map(|(offset, rot)| {
+            let window = received.get(offset..).unwrap_or(&[]);
+            let matched = preamble.iter().zip(window).filter(|&(&s, &r)| (s + rot) % order == r).count();
+            (matched, offset, rot)
+        })
+        .max_by_key(|&(matched, offset, _)| (matched, std::cmp::Reverse(offset)))?;
+    (matched as f64 >= SYNC_THRESHOLD * preamble.len() as f64).then_some((offset, rot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub fn channel(snr_db: f64) -> ChannelParams {
+        ChannelParams {
+            snr_db,
+            carrier_freq_hz: 2400.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
+        }
+    }
+
+    pub fn pattern(len: usize, seed: u32) -> Vec<u8> {
+        let mut x = seed;
+        (0..len)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                (x % 8) as u8
+            })
+            .collect()
+    }
+
+    /// 1 s of our 8PSK, then a distant burst 10 dB down. On a 2400 Hz
+    /// carrier, clear of the channel's low roll-off the unequalized
+    /// receiver can't undo
+    fn scenario(handling: EchoHandling) -> EchoScenario {
+        EchoScenario {
+            constellation: ConstellationType::Psk8,
+            symbol_rate: 2400,
+            carrier_freq: 2400.0,
+            tx_symbols: pattern(2400, 1),
+            isolation_db: 60.0,
+            leak_channel: channel(40.0),
+            turnaround_samples: 960,
+            preamble: pattern(32, 3),
+            data: pattern(1200, 2),
+            remote_channel: ChannelParams { bulk_delay_samples: 37, ..channel(25.0) },
+            remote_level_db: -10.0,
+            block_samples: 480,
+            handling,
+            seed: 5,
+        }
+    }
+
+    fn trim_and_reset() -> EchoHandling {
+        EchoHandling {
+            reset_at_rx: true,
+            eot: Some(EotConfig { truncate: true, ..EotConfig::default() }),
+            timing_tracking: false,
+        }
+    }
+
+    #[test]
+    fn test_reset_decodes_burst_after_echo() {
+        for isolation_db in [60.0, 40.0, 20.0] {
+            let outcome = run_echo_scenario(&EchoScenario {
+                isolation_db,
+                turnaround_samples: 100,
+                ..scenario(trim_and_reset())
+            })
+            .unwrap();
+            assert!(outcome.sync.is_some(), "{} dB: no sync", isolation_db);
+            assert_eq!(outcome.symbol_errors, 0, "{} dB", isolation_db);
+            assert_eq!(outcome.data_symbols, 1200);
+        }
+    }
+
+    /// Whether the burst is found without the reset is down to the timing
+    /// taken on the floor (see the module docs), so over several seeds
+    #[test]
+    fn test_without_reset_burst_is_lost() {
+        let handling = EchoHandling { reset_at_rx: false, ..trim_and_reset() };
+        for isolation_db in [60.0, 200.0] {
+            let lost = (1..=8)
+                .map(|seed| {
+                    run_echo_scenario(&EchoScenario {
+                        isolation_db,
+                        turnaround_samples: 100,
+                        seed,
+                        ..scenario(handling.clone())
+                    })
+                    .unwrap()
+                })
+                .filter(|outcome| outcome.sync.is_none())
+                .inspect(|outcome| assert_eq!(outcome.ser(), 1.0))
+                .count();
+            assert!(lost >= 5, "{} dB: lost {} of 8", isolation_db, lost);
+        }
+    }
+
+    /// Known limitation (see the module docs): the reset reacquires on the
+    /// turnaround's noise floor, timing tracking or not
+    #[test]
+    fn test_long_turnaround_loses_burst() {
+        for timing_tracking in [false, true] {
+            let outcome = run_echo_scenario(&EchoScenario {
+                turnaround_samples: 960,
+                ..scenario(EchoHandling { timing_tracking, ..trim_and_reset() })
+            })
+            .unwrap();
+            assert_eq!(outcome.sync, None, "timing tracking {}", timing_tracking);
+        }
+    }
+
+    #[test]
+    fn test_outcome_counts_both_segments() {
+        let outcome = run_echo_scenario(&EchoScenario { turnaround_samples: 100, ..scenario(trim_and_reset()) }).unwrap();
+        // Our 2400 symbols plus the burst tail, echoed at 60 dB, all decoded
+        assert!(outcome.tx_symbols_out >= 2400);
+        // The burst plus the turnaround and delays ahead of it
+        assert!(outcome.rx_symbols_out >= 32 + 1200 + 25);
+        assert_eq!(run_echo_scenario(&EchoScenario { turnaround_samples: 100, ..scenario(trim_and_reset()) }).unwrap(), outcome);
+    }
+
+    #[test]
+    fn test_invalid_scenarios_rejected() {
+        let base = scenario(trim_and_reset());
+        let rate = ChannelParams { sample_rate: 8000, ..channel(25.0) };
+        assert_eq!(run_echo_scenario(&EchoScenario { remote_channel: rate, ..base.clone() }), Err("sample_rate_mismatch"));
+        assert_eq!(run_echo_scenario(&EchoScenario { block_samples: 0, ..base.clone() }), Err("invalid_block_samples"));
+        assert_eq!(run_echo_scenario(&EchoScenario { preamble: vec![], ..base.clone() }), Err("empty_preamble"));
+        assert_eq!(run_echo_scenario(&EchoScenario { data: vec![8], ..base }), Err("invalid_symbol"));
+    }
+}
@@ -0,0 +1,553 @@
+//! TDMA frame composition
+//!
+//! Renders several stations' bursts into one buffer. Each burst is
+//! modulated, run through its own channel instance and added in at an
+//! exact sample offset. Keeping the length bookkeeping in one place means
+//! slot boundaries stay sample-exact however the filters and delays add up.
+//!
+//! Each burst carries its own channel noise over its own span only; add
+//! background noise separately if the gaps between slots should not be
+//! silent.
+
+use channel_physics::channel::{ChannelParams, WattersonChannel};
+use minutemodem_dsp::convert::i16_to_f64;
+use phy_modem::{ConstellationType, UnifiedModulator};
+
+/// Longest frame compose_frame will render (10 minutes at 48 kHz)
+pub const MAX_FRAME_SAMPLES: usize = 48_000 * 600;
+
+/// One station's transmission within the frame
+#[derive(Debug, Clone)]
+pub struct Burst {
+    /// Frame sample at which the modulator starts
+    pub start_sample: usize,
+    pub symbols: Vec<u8>,
+    pub constellation: ConstellationType,
+    pub symbol_rate: u32,
+    pub carrier_freq: f64,
+    /// Channel for this burst; its sample_rate is also the modulator's
+    pub channel: ChannelParams,
+    pub seed: u64,
+    /// Gain applied to the channel output
+    pub level_db: f64,
+}
+
+/// Where a burst actually landed in the frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BurstPlacement {
+    /// First sample of the waveform, after bulk and filter delays
+    pub start_sample: usize,
+    /// One past the last sample written (clipped to the frame)
+    pub end_sample: usize,
+    /// Sample at which the first symbol's pulse peaks; symbol k peaks at
+    /// first_symbol_sample + k * samples_per_symbol
+    pub first_symbol_sample: usize,
+    pub samples_per_symbol: usize,
+    /// True if the frame ended before the burst did
+    pub truncated: bool,
+}
+
+/// Render `bursts` into a frame of `frame_len` samples (±1.0 full scale)
+///
+/// Deterministic: each burst's channel is seeded with its own seed, so the
+/// result depends only on the inputs, not on burst order.
+pub fn compose_frame(
+    frame_len: usize,
+    bursts: &[Burst],
+) -> Result<(Vec<f64>, Vec<BurstPlacement>), &'static str> {
+    if frame_len > MAX_FRAME_SAMPLES {
+        return Err("frame_too_long");
+    }
+
+    let mut frame = vec![0.0; frame_len];
+    let mut placements = Vec::with_capacity(bursts.len());
+
+    for burst in bursts {
+        let (rendered, placement) = render_burst(burst)?;
+        let start = burst.start_sample.min(frame_len);
+        let end = (burst.start_sample + rendered.len()).min(frame_len);
+        for (out, &x) in frame[start..end].iter_mut().zip(&rendered) {
+            *out += x;
+        }
+        placements.push(BurstPlacement {
+            start_sample: placement.start_sample.min(frame_len),
+            end_sample: placement.end_sample.min(frame_len),
+            truncated: placement.end_sample > frame_len,
+            ..placement
+        });
+    }
+
+    Ok((frame, placements))
+}
+
+/// Modulate and channel one burst, relative to its start sample
+///
+/// Returns the samples to add at burst.start_sample and the unclipped
+/// placement in frame coordinates.
+fn render_burst(burst: &Burst) -> Result<(Vec<f64>, BurstPlacement), &'static str> {
+    let sample_rate = burst.channel.sample_rate;
+    if burst.symbol_rate == 0 || !sample_rate.is_multiple_of(burst.symbol_rate) || sample_rate / burst.symbol_rate < 2 {
+        return Err("invalid_symbol_rate");
+    }
+    channel_physics::output::validate(burst.channel.output_bits, burst.channel.clip_knee)?;
+
+    let mut modulator = UnifiedModulator::new(
+        burst.constellation,
+        sample_rate,
+        burst.symbol_rate,
+        burst.carrier_freq,
+    );
+    let mut waveform = modulator.modulate(&burst.symbols);
+    waveform.extend(modulator.drain());
+    let waveform_len = waveform.len();
+
+    let mut channel = WattersonChannel::new(burst.channel.clone(), burst.seed);
+    let latency = channel.latency_samples();
+
+    // The channel's output is as long as its input (give or take the
+    // clock drift): pad so the delayed tail (and the echo path) comes out
+    // too
+    let pad = latency + burst.channel.delay_spread_samples as usize;
+    let mut input: Vec<f64> = waveform.into_iter().map(i16_to_f64).collect();
+    input.resize(waveform_len + pad, 0.0);
+
+    let gain = 10f64.powf(burst.level_db / 20.0);
+    let mut rendered = channel.process_f64(&input);
+    for x in &mut rendered {
+        *x *= gain;
+    }
+
+    let start = burst.start_sample;
+    let placement = BurstPlacement {
+        start_sample: start + latency,
+        end_sample: start + rendered.len(),
+        first_symbol_sample: start + latency + modulator.latency_samples(),
+        samples_per_symbol: modulator.sps(),
+        truncated: false,
+    };
+    Ok((rendered, placement))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phy_modem::UnifiedDemodulator;
+
+    fn clean_channel() -> ChannelParams {
+        ChannelParams {
+            snr_db: 80.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
+        }
+    }
+
+    fn pattern(len: usize, seed: u32) -> Vec<u8> {
+        let mut x = seed;
+        (0..len)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                (x % 8) as u8
+            })
+            .collect()
+    }
+
+    fn burst(start_sample: usize, symbols: Vec<u8>, seed: u64, level_db: f64) -> Burst {
+        Burst {
+            start_sample,
+            symbols,
+            constellation: ConstellationType::Psk8,
+            symbol_rate: 2400,
+            carrier_freq: 1800.0,
+            channel: clean_channel(),
+            seed,
+            level_db,
+        }
+    }
+
+    fn rms(x: &[f64]) -> f64 {
+        (x.iter().map(|v| v * v).sum::<f64>() / x.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn test_bursts_land_at_exact_offsets() {
+        let a = burst(0, pattern(200, 1), 1, 0.0);
+        let b = burst(537, pattern(200, 2), 2, 0.0);
+
+        let (alone, _) = compose_frame(4000, std::slice::from_ref(&a)).unwrap();
+        let (shifted, placements) = compose_frame(4000, &[Burst { start_sample: 537, ..a.clone() }]).unwrap();
+        assert_eq!(&shifted[537..], &alone[..4000 - 537]);
+        assert!(shifted[..537].iter().all(|&x| x == 0.0));
+        assert_eq!(placements[0].start_sample, 537 + 15);
+
+        // Overlapping bursts sum sample for sample
+        let (both, _) = compose_frame(4000, &[a.clone(), b.clone()]).unwrap();
+        let (only_b, _) = compose_frame(4000, &[b]).unwrap();
+        for k in 0..4000 {
+            assert!((both[k] - (alone[k] + only_b[k])).abs() < 1e-15, "sample {}", k);
+        }
+    }
+
+    #[test]
+    fn test_burst_level_db() {
+        let symbols = pattern(300, 3);
+        let (full, _) = compose_frame(2000, &[burst(0, symbols.clone(), 5, 0.0)]).unwrap();
+        let (quiet, _) = compose_frame(2000, &[burst(0, symbols, 5, -6.0)]).unwrap();
+        let ratio_db = 20.0 * (rms(&quiet) / rms(&full)).log10();
+        assert!((ratio_db + 6.0).abs() < 1e-6, "level {:.3} dB", ratio_db);
+    }
+
+    #[test]
+    fn test_placement_metadata() {
+        let channel = ChannelParams { bulk_delay_samples: 100, ..clean_channel() };
+        let b = Burst { channel, ..burst(1000, pattern(50, 4), 1, 0.0) };
+        let (_, placements) = compose_frame(1300, &[b]).unwrap();
+        let p = &placements[0];
+
+        // Bulk delay + 15-sample LPF group delay, then the modulator's
+        // 2-sample impulse offset + 24-sample RRC group delay
+        assert_eq!(p.start_sample, 1000 + 100 + 15);
+        assert_eq!(p.first_symbol_sample, 1000 + 100 + 15 + 26);
+        assert_eq!(p.samples_per_symbol, 4);
+        assert_eq!(p.end_sample, 1300);
+        assert!(p.truncated);
+    }
+
+    #[test]
+    fn test_deterministic_per_seed() {
+        let faded = |seed| Burst {
+            channel: ChannelParams { doppler_bandwidth_hz: 2.0, delay_spread_samples: 8, snr_db: 15.0, ..clean_channel() },
+            ..burst(10, pattern(200, 6), seed, 0.0)
+        };
+        let (a, _) = compose_frame(1500, &[faded(7)]).unwrap();
+        let (b, _) = compose_frame(1500, &[faded(7)]).unwrap();
+        let (c, _) = compose_frame(1500, &[faded(8)]).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_invalid_bursts_rejected() {
+        let b = Burst { symbol_rate: 2500, ..burst(0, vec![0], 1, 0.0) };
+        assert_eq!(compose_frame(100, &[b]).unwrap_err(), "invalid_symbol_rate");
+        assert_eq!(compose_frame(MAX_FRAME_SAMPLES + 1, &[]).unwrap_err(), "frame_too_long");
+    }
+
+    #[test]
+    fn test_reset_to_idle_chain_matches_fresh() {
+        use channel_physics::channel::RngReset;
+        use minutemodem_dsp::convert::f64_to_i16;
+
+        let params = ChannelParams { delay_spread_samples: 6, doppler_bandwidth_hz: 0.5, snr_db: 25.0, bulk_delay_samples: 200, ..clean_channel() };
+        let probe = phy_modem::probes::capture_probe(32).unwrap();
+        let new_chain = || {
+            let mut demod = UnifiedDemodulator::with_hf_equalizer(ConstellationType::Psk8, 9600, 2400, 1800.0);
+            demod.set_training_symbols(probe.clone());
+            (
+                UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0),
+                WattersonChannel::new(params.clone(), 3),
+                demod,
+            )
+        };
+        let run = |(modulator, channel, demod): &mut (UnifiedModulator, WattersonChannel, UnifiedDemodulator), symbols: &[u8]| {
+            let mut samples = modulator.modulate(symbols);
+            samples.extend(modulator.drain());
+            let input: Vec<f64> = samples.into_iter().map(i16_to_f64).collect();
+            let received: Vec<i16> = channel.process_f64(&input).into_iter().map(f64_to_i16).collect();
+            demod.demodulate_with_confidence(&received)
+        };
+
+        let mut burst = probe.clone();
+        burst.extend(pattern(300, 11));
+        let expected = run(&mut new_chain(), &burst);
+
+        // Abort a scenario halfway through its first burst, then reset
+        let mut chain = new_chain();
+        run(&mut chain, &pattern(150, 12));
+        chain.0.reset_to_idle();
+        chain.1.reset_to_idle(RngReset::Reseed(3));
+        chain.2.reset_to_idle();
+        chain.2.set_training_symbols(probe.clone());
+
+        assert_eq!(run(&mut chain, &burst), expected);
+    }
+
+    /// Symbol error rate over the last 10 s of a 60 s 8PSK transmission
+    /// through a channel whose receiver clock is 60 ppm fast, aligned on
+    /// the first second
+    fn drifting_transmission_ser(timing_tracking: bool) -> f64 {
+        use minutemodem_dsp::convert::f64_to_i16;
+
+        // A 2400 Hz carrier keeps the lower skirt clear of the channel's
+        // roll-off below 1.3 kHz, which an unequalized receiver can't undo
+        let symbols = pattern(60 * 2400, 21);
+        let params = ChannelParams { snr_db: 30.0, sample_rate_offset_ppm: 60.0, ..clean_channel() };
+        let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 2400.0);
+        let mut channel = WattersonChannel::new(params, 5);
+        let mut demod = UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 2400.0);
+        if timing_tracking {
+            demod.enable_timing_tracking();
+        }
+
+        // Half a second at a time, as the sound card delivers it
+        let mut received = Vec::with_capacity(symbols.len() + 100);
+        let mut sent = 0;
+        for block in symbols.chunks(1200) {
+            let input: Vec<f64> = modulator.modulate(block).into_iter().map(i16_to_f64).collect();
+            let output: Vec<i16> = channel.process_f64(&input).into_iter().map(f64_to_i16).collect();
+            sent += input.len();
+            received.extend(demod.demodulate(&output));
+        }
+        // 60 ppm of 576,000 samples is 35 more out than went in
+        assert_eq!(channel.get_state().output_samples, sent as u64 + 35);
+
+        let (delay, rot) = (0..100)
+            .flat_map(|delay| (0..8u8).map(move |rot| (delay, rot)))
+            .max_by_key(|&(delay, rot)| (100..2400).filter(|&k| received[k + delay] == (symbols[k] + rot) % 8).count())
+            .unwrap();
+        let last = symbols.len() - 10 * 2400..symbols.len() - 100;
+        let errors = last.clone().filter(|&k| received.get(k + delay) != Some(&((symbols[k] + rot) % 8))).count();
+        errors as f64 / last.len() as f64
+    }
+
+    #[test]
+    fn test_clock_drift_needs_timing_tracking() {
+        // The demodulator's timing loop absorbs the drift end to end...
+        let tracked = drifting_transmission_ser(true);
+        assert!(tracked < 1e-3, "SER {} with timing tracking", tracked);
+
+        // ...while the fixed acquired phase has slipped several symbols by
+        // the end
+        let fixed = drifting_transmission_ser(false);
+        assert!(fixed > 0.5, "SER {} without timing tracking", fixed);
+    }
+
+    #[test]
+    fn test_hopping_link_decodes_through_channel() {
+        use channel_physics::channel::RngReset;
+        use minutemodem_dsp::convert::f64_to_i16;
+        use phy_modem::modem::HopSchedule;
+
+        // 2400 -> 2700 -> 2550 Hz mid-burst, off symbol boundaries, all
+        // keeping the sidebands clear of the channel's images. The channel
+        // hops on the transmitter's sample clock, the receiver its latency
+        // later
+        let sent: Vec<u8> = pattern(900, 23).iter().map(|s| s % 8).collect();
+        let hops = [(1001, 2700.0), (2403, 2550.0)];
+        let params = ChannelParams { snr_db: 30.0, bulk_delay_samples: 120, carrier_freq_hz: 2400.0, ..clean_channel() };
+        let mut channel = WattersonChannel::new(params, 7);
+        let latency = channel.latency_samples() as u64;
+        let rx_hops: Vec<_> = hops.iter().map(|&(at, hz)| (at + latency, hz)).collect();
+
+        let mut run = |rx_schedule: Option<&[(u64, f64)]>| {
+            let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 2400.0);
+            modulator.set_hop_schedule(HopSchedule::new(hops.to_vec(), 9600).unwrap());
+            let mut demod = UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 2400.0);
+            if let Some(schedule) = rx_schedule {
+                demod.set_hop_schedule(HopSchedule::new(schedule.to_vec(), 9600).unwrap());
+            }
+            channel.reset_to_idle(RngReset::Reseed(7));
+            channel.set_hop_schedule(&hops).unwrap();
+
+            let mut samples = modulator.modulate(&sent);
+            samples.extend(modulator.end_burst());
+            // Run the channel on until the burst's tail is out
+            samples.resize(samples.len() + latency as usize, 0);
+            let input: Vec<f64> = samples.into_iter().map(i16_to_f64).collect();
+            let received: Vec<i16> = channel.process_f64(&input).into_iter().map(f64_to_i16).collect();
+            demod.demodulate(&received)
+        };
+        let errors = |received: &[u8], range: std::ops::Range<usize>| {
+            let (delay, rot) = (0..200)
+                .flat_map(|delay| (0..8u8).map(move |rot| (delay, rot)))
+                .max_by_key(|&(delay, rot)| (50..200).filter(|&k| received.get(k + delay) == Some(&((sent[k] + rot) % 8))).count())
+                .unwrap();
+            range.filter(|&k| received.get(k + delay) != Some(&((sent[k] + rot) % 8))).count()
+        };
+
+        let followed = run(Some(&rx_hops));
+        assert_eq!(errors(&followed, 50..sent.len()), 0);
+
+        // A receiver left on 2400 Hz loses the burst at the first hop
+        let fixed = run(None);
+        assert!(errors(&fixed, 300..sent.len()) > 300);
+    }
+
+    #[test]
+    fn test_swapped_sidebands_need_detection() {
+        use minutemodem_dsp::convert::f64_to_i16;
+        use phy_modem::modem::{BurstReport, BurstWarning, ProbeSchedule, Pulse};
+
+        // 20 frames of 64 data symbols and a 32-symbol mini-probe, over a
+        // channel whose receiver sits on the other sideband
+        let probe = phy_modem::probes::mini_probe(32, false).unwrap();
+        let data = pattern(20 * 64, 25);
+        let sent: Vec<u8> = data.chunks(64).flat_map(|d| d.iter().chain(&probe).copied()).collect();
+        let schedule = ProbeSchedule::new(96, 64 + 2 * Pulse::Rrc.span() as u64, probe).unwrap();
+        let params = ChannelParams { snr_db: 30.0, carrier_freq_hz: 2400.0, sideband_inversion: true, ..clean_channel() };
+        let mut channel = WattersonChannel::new(params, 3);
+        let latency = channel.latency_samples();
+
+        let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 2400.0);
+        let mut samples = modulator.modulate(&sent);
+        samples.extend(modulator.drain());
+        samples.resize(samples.len() + latency, 0);
+        let input: Vec<f64> = samples.into_iter().map(i16_to_f64).collect();
+        // Latency off the front, so the probes fall where the schedule says
+        let received: Vec<i16> = channel.process_f64(&input)[latency..].iter().map(|&x| f64_to_i16(x)).collect();
+
+        let run = |detect: bool| -> (Vec<u8>, BurstReport) {
+            let mut demod = UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 2400.0);
+            demod.set_probe_tracking(Some(schedule.clone()));
+            demod.set_sideband_detection(detect);
+            (demod.demodulate(&received), demod.decode_report())
+        };
+        let errors = |received: &[u8]| {
+            (0..64)
+                .flat_map(|delay| (0..8u8).map(move |rot| (delay, rot)))
+                .map(|(delay, rot)| (100..sent.len()).filter(|&k| received.get(k + delay) != Some(&((sent[k] + rot) % 8))).count())
+                .min()
+                .unwrap()
+        };
+
+        // Mirrored points are no rotation of the sent ones
+        let (plain, report) = run(false);
+        assert!(errors(&plain) > sent.len() / 2);
+        assert!(!report.warnings.contains(&BurstWarning::InvertedSideband));
+
+        let (detected, report) = run(true);
+        assert_eq!(errors(&detected), 0);
+        assert_eq!(report.acquisition.unwrap().sideband_inverted, Some(true));
+        assert!(report.warnings.contains(&BurstWarning::InvertedSideband));
+    }
+
+    #[test]
+    fn test_gardner_timing_follows_fractional_delay() {
+        use minutemodem_dsp::convert::f64_to_i16;
+
+        let sent = pattern(2000, 41);
+        let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0);
+        let mut samples = modulator.modulate(&sent);
+        samples.extend(modulator.drain());
+
+        // Each channel's whole-sample latency off the front, leaving the
+        // fraction
+        let strobe_phase = |fraction: f64| -> f64 {
+            let mut channel = WattersonChannel::new(ChannelParams { fractional_delay_samples: fraction, ..clean_channel() }, 7);
+            let latency = channel.latency_samples();
+            let mut padded = samples.clone();
+            padded.resize(padded.len() + latency, 0);
+            let input: Vec<f64> = padded.into_iter().map(i16_to_f64).collect();
+            let received: Vec<i16> = channel.process_f64(&input)[latency..].iter().map(|&x| f64_to_i16(x)).collect();
+
+            let mut demod = UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 1800.0);
+            demod.enable_timing_tracking();
+            demod.demodulate(&received);
+            demod.timing_strobe_phase().unwrap()
+        };
+
+        let reference = strobe_phase(0.0);
+        for fraction in [0.25, 0.5, 0.75] {
+            let moved = (strobe_phase(fraction) - reference + 2.0).rem_euclid(4.0) - 2.0;
+            assert!((moved - fraction).abs() < 0.05, "{fraction}: strobe moved {moved}");
+        }
+    }
+
+    /// Dibit errors in a 2000-dibit 75 bps burst through the channel at
+    /// `snr_db` for the modem's full-scale output: decided by Walsh
+    /// correlation of the demodulator's I/Q, and by matching its 8-PSK
+    /// decisions chip by chip at the best rotation
+    fn walsh75_errors(snr_db: f64) -> (usize, usize) {
+        use minutemodem_dsp::convert::f64_to_i16;
+        use phy_modem::walsh::{self, WALSH75_CHIPS};
+
+        let dibits: Vec<u8> = pattern(2000, 75).iter().map(|s| s % 4).collect();
+        let mut modulator = UnifiedModulator::new(ConstellationType::Psk8, 9600, 2400, 2400.0);
+        let mut samples = modulator.modulate_walsh(&dibits);
+        samples.extend(modulator.drain());
+
+        // Sent 20 dB down so the noise doesn't clip, the SNR raised to match
+        let params = ChannelParams { snr_db: snr_db + 20.0, carrier_freq_hz: 2400.0, ..clean_channel() };
+        let mut channel = WattersonChannel::new(params, 75);
+        let latency = channel.latency_samples();
+        samples.resize(samples.len() + latency, 0);
+        let input: Vec<f64> = samples.into_iter().map(|x| 0.1 * i16_to_f64(x)).collect();
+        let received: Vec<i16> = channel.process_f64(&input)[latency..].iter().map(|&x| f64_to_i16(x)).collect();
+
+        // The symbol boundary is where the first 10 dibits correlate best
+        let new_demod = || UnifiedDemodulator::new(ConstellationType::Psk8, 9600, 2400, 2400.0);
+        let iq = new_demod().demodulate_iq(&received);
+        let score = |delay: usize| -> f64 {
+            iq[delay..]
+                .chunks_exact(WALSH75_CHIPS)
+                .zip(&dibits[..10])
+                .map(|(chips, &d)| walsh::correlate_walsh75(chips).magnitudes[d as usize])
+                .sum()
+        };
+        let delay = (0..WALSH75_CHIPS).max_by(|&a, &b| score(a).total_cmp(&score(b))).unwrap();
+
+        let mut demod = new_demod();
+        demod.align_walsh(delay);
+        let soft: Vec<u8> = demod.demodulate_walsh(&received).into_iter().map(|s| s.dibit).collect();
+        let hard: Vec<u8> = new_demod().demodulate(&received)[delay..]
+            .chunks_exact(WALSH75_CHIPS)
+            .map(|chips| {
+                (0..4u8)
+                    .max_by_key(|&dibit| {
+                        let sequence = walsh::walsh75_sequence(dibit);
+                        (0..8u8).map(|rot| chips.iter().zip(&sequence).filter(|&(&c, &s)| c == (s + rot) % 8).count()).max()
+                    })
+                    .unwrap()
+            })
+            .collect();
+        assert!(soft.len() >= 1990 && hard.len() >= 1990, "{} and {} symbols", soft.len(), hard.len());
+        let errors = |decided: &[u8]| decided.iter().zip(&dibits).skip(10).filter(|(d, s)| d != s).count();
+        (errors(&soft), errors(&hard))
+    }
+
+    #[test]
+    fn test_walsh75_coherent_gain_through_channel() {
+        let (soft, hard) = walsh75_errors(10.0);
+        assert_eq!((soft, hard), (0, 0));
+
+        // A chip at -3.5 dB Es/N0 is sliced wrong two times in three; the
+        // correlation adds all 32 in amplitude before deciding
+        let (soft, hard) = walsh75_errors(-6.5);
+        assert!(soft * 2 < hard, "{} symbol errors correlated, {} from sliced chips", soft, hard);
+    }
+
+    #[test]
+    fn test_non_overlapped_portion_decodes() {
+        // Station A's 400-symbol burst is overlapped by B from roughly
+        // symbol 300 on. QPSK, since the clean channel still rotates the
+        // carrier by a fixed angle that sits between 8PSK decision regions
+        let data: Vec<u8> = pattern(400, 9).iter().map(|s| s % 4).collect();
+        let qpsk = |b: Burst| Burst { constellation: ConstellationType::Qpsk, ..b };
+        let a = qpsk(burst(0, data.clone(), 1, 0.0));
+        let b = qpsk(burst(1200, pattern(400, 10).iter().map(|s| s % 4).collect(), 2, -3.0));
+        let (frame, placements) = compose_frame(4000, &[a, b]).unwrap();
+
+        let samples: Vec<i16> = frame.iter().map(|&x| minutemodem_dsp::convert::f64_to_i16(x)).collect();
+        let mut demod = UnifiedDemodulator::new(ConstellationType::Qpsk, 9600, 2400, 1800.0);
+        let rx = demod.demodulate(&samples);
+
+        // Symbols of A whose pulses end before B's first sample
+        let sps = placements[0].samples_per_symbol;
+        let clean_symbols = (placements[1].start_sample - placements[0].first_symbol_sample) / sps - 6;
+
+        // Search receiver delay and the carrier loop's 4-fold phase ambiguity
+        let best = (0..40)
+            .flat_map(|delay| (0..4u8).map(move |rot| (delay, rot)))
+            .map(|(delay, rot)| {
+                (0..clean_symbols)
+                    .filter(|&k| k + delay < rx.len())
+                    .filter(|&k| rx[k + delay] == (data[k] + rot) % 4)
+                    .count()
+            })
+            .max()
+            .unwrap();
+        // Allow for the receiver's acquisition transient at the very start
+        assert!(best >= clean_symbols - 20, "{} of {} symbols decoded", best, clean_symbols);
+    }
+}
@@ -0,0 +1,814 @@
+//! Modem simulation NIF for MinuteModem SimNet
+//!
+//! Runs the phy_modem modulator and the channel_physics Watterson channel
+//! together in one native call, for scenarios that would otherwise shuttle
+//! every burst through Elixir and back. Both crates are linked as plain
+//! libraries (their `nif` feature off), so only this crate's NIFs load.
+
+pub mod ber_reference;
+pub mod echo;
+pub mod frame;
+pub mod scoreboard;
+pub mod sweep;
+
+use minutemodem_dsp::census::{Census, CensusCount, Tally};
+use minutemodem_dsp::convert;
+use minutemodem_dsp::guard;
+use phy_modem::prbs::PrbsPolynomial;
+use phy_modem::ConstellationType;
+use rustler::{Atom, Binary, Encoder, Env, LocalPid, NifMap, NifResult, NifTuple, OwnedBinary, ResourceArc, Term};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use channel_physics::channel::ChannelParams;
+use channel_physics::limits::{self as channel_limits, range_error};
+use ber_reference::{BerPoint, Fading};
+use echo::{EchoHandling, EchoOutcome, EchoScenario};
+use frame::{Burst, BurstPlacement};
+use phy_modem::modem::EotConfig;
+use scoreboard::{ConfidenceBin, Reference, Score, Scoreboard, ScoreboardConfig};
+use sweep::SweepSpec;
+
+mod atoms {
+    rustler::atoms! {
+        ok,
+        error,
+        bpsk,
+        qpsk,
+        psk8,
+        qam16,
+        qam32,
+        qam64,
+        symbol_rate,
+        carrier_freq,
+        sequence,
+        generated,
+        prbs9,
+        prbs15,
+        prbs23,
+        window_seconds,
+        burst_gap,
+        confidence_threshold,
+        block_samples,
+        reset_at_rx,
+        eot,
+        timing_tracking,
+        off,
+        detect,
+        truncate,
+        awgn,
+        rayleigh,
+        max_threads,
+        abort,
+        progress_ms,
+        aborted,
+        sweep_progress,
+        sweep_done,
+    }
+}
+
+fn on_load(env: Env, _info: Term) -> bool {
+    let _ = rustler::resource!(ScoreboardResource, env);
+    let _ = rustler::resource!(AbortToken, env);
+    true
+}
+
+rustler::init!("Elixir.MinutemodemSimnet.Sim.Nif", load = on_load);
+
+const DEFAULT_SYMBOL_RATE: u32 = 2400;
+
+/// Most samples per symbol a burst may use, as phy_modem's NIFs allow:
+/// the modulator's pulse filter is 12 * sps + 1 taps
+const MAX_SAMPLES_PER_SYMBOL: u64 = 512;
+
+fn constellation_from_atom(atom: Atom) -> Option<ConstellationType> {
+    if atom == atoms::bpsk() {
+        Some(ConstellationType::Bpsk)
+    } else if atom == atoms::qpsk() {
+        Some(ConstellationType::Qpsk)
+    } else if atom == atoms::psk8() {
+        Some(ConstellationType::Psk8)
+    } else if atom == atoms::qam16() {
+        Some(ConstellationType::Qam16)
+    } else if atom == atoms::qam32() {
+        Some(ConstellationType::Qam32)
+    } else if atom == atoms::qam64() {
+        Some(ConstellationType::Qam64)
+    } else {
+        None
+    }
+}
+
+fn polynomial_from_atom(atom: Atom) -> Option<PrbsPolynomial> {
+    if atom == atoms::prbs9() {
+        Some(PrbsPolynomial::Prbs9)
+    } else if atom == atoms::prbs15() {
+        Some(PrbsPolynomial::Prbs15)
+    } else if atom == atoms::prbs23() {
+        Some(PrbsPolynomial::Prbs23)
+    } else {
+        None
+    }
+}
+
+fn term_error(e: &'static str) -> rustler::Error {
+    rustler::Error::Term(Box::new(e))
+}
+
+/// `{:out_of_range, param, max}` if `value` is above `max`
+fn check_max(param: &'static str, value: u64, max: u64) -> NifResult<()> {
+    channel_limits::check_max(param, value, max).map_err(range_error)
+}
+
+/// A caught panic's message, encoded as {:panic, message}
+struct Panicked(String);
+
+impl Encoder for Panicked {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        let panic = Atom::from_str(env, "panic").expect("a short ASCII atom");
+        (panic, self.0.as_str()).encode(env)
+    }
+}
+
+/// Run a NIF body, turning a panic into {:error, {:panic, message}}
+fn guarded<T>(body: impl FnOnce() -> NifResult<T>) -> NifResult<T> {
+    guard::catch(body).unwrap_or_else(|message| Err(rustler::Error::Term(Box::new(Panicked(message)))))
+}
+
+// ============================================================================
+// TDMA frame composition
+// ============================================================================
+
+/// {start_sample, symbols, constellation, modulator_opts, channel_params, seed, level_db}
+#[derive(NifTuple)]
+struct BurstSpec<'a> {
+    start_sample: u64,
+    symbols: Vec<u8>,
+    constellation: Atom,
+    modulator_opts: Term<'a>,
+    channel_params: ChannelParams,
+    seed: u64,
+    level_db: f64,
+}
+
+#[derive(NifMap)]
+struct BurstPlacementMap {
+    start_sample: u64,
+    end_sample: u64,
+    first_symbol_sample: u64,
+    samples_per_symbol: u64,
+    truncated: bool,
+}
+
+impl From<BurstPlacement> for BurstPlacementMap {
+    fn from(p: BurstPlacement) -> Self {
+        Self {
+            start_sample: p.start_sample as u64,
+            end_sample: p.end_sample as u64,
+            first_symbol_sample: p.first_symbol_sample as u64,
+            samples_per_symbol: p.samples_per_symbol as u64,
+            truncated: p.truncated,
+        }
+    }
+}
+
+impl BurstSpec<'_> {
+    /// Resolve atoms and modulator defaults into a frame::Burst
+    ///
+    /// modulator_opts is a map with optional `symbol_rate` (default 2400)
+    /// and `carrier_freq` (default: the channel's carrier).
+    fn into_burst(self) -> NifResult<Burst> {
+        let constellation = constellation_from_atom(self.constellation)
+            .ok_or_else(|| rustler::Error::Term(Box::new("invalid_constellation")))?;
+
+        let opt = |key: Atom| self.modulator_opts.map_get(key).ok();
+        let symbol_rate = match opt(atoms::symbol_rate()) {
+            Some(term) => term.decode()?,
+            None => DEFAULT_SYMBOL_RATE,
+        };
+        let carrier_freq = match opt(atoms::carrier_freq()) {
+            Some(term) => term.decode()?,
+            None => self.channel_params.carrier_freq_hz,
+        };
+
+        // Before anything is sized from them; a zero symbol rate is left
+        // to render_burst's invalid_symbol_rate
+        channel_limits::validate_params(&self.channel_params).map_err(range_error)?;
+        if let Some(sps) = self.channel_params.sample_rate.checked_div(symbol_rate) {
+            check_max("samples_per_symbol", sps.into(), MAX_SAMPLES_PER_SYMBOL)?;
+        }
+
+        Ok(Burst {
+            start_sample: self.start_sample as usize,
+            symbols: self.symbols,
+            constellation,
+            symbol_rate,
+            carrier_freq,
+            channel: self.channel_params,
+            seed: self.seed,
+            level_db: self.level_db,
+        })
+    }
+}
+
+/// Modulates, channels and sums several bursts into one frame.
+/// Output: f32 samples as binary (native endian, frame_len samples) and
+/// one placement map per burst, in input order.
+#[rustler::nif(schedule = "DirtyCpu")]
+fn compose_frame<'a>(
+    env: Env<'a>,
+    frame_len: u64,
+    bursts: Vec<BurstSpec<'a>>,
+) -> NifResult<(Atom, Binary<'a>, Vec<BurstPlacementMap>)> {
+    guarded(|| {
+        check_max("frame_len", frame_len, frame::MAX_FRAME_SAMPLES as u64)?;
+        let bursts = bursts
+            .into_iter()
+            .map(BurstSpec::into_burst)
+            .collect::<NifResult<Vec<_>>>()?;
+
+        let (samples, placements) = frame::compose_frame(frame_len as usize, &bursts)
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        let narrowed: Vec<f32> = samples.iter().map(|&x| x as f32).collect();
+        let mut owned = OwnedBinary::new(narrowed.len() * 4)
+            .ok_or_else(|| rustler::Error::Term(Box::new("binary_alloc_failed")))?;
+        convert::f32s_to_ne_bytes(&narrowed, owned.as_mut_slice());
+
+        Ok((
+            atoms::ok(),
+            owned.release(env),
+            placements.into_iter().map(Into::into).collect(),
+        ))
+    })
+}
+
+// ============================================================================
+// Half-duplex echo scenarios
+// ============================================================================
+
+const DEFAULT_BLOCK_SAMPLES: usize = 480;
+
+#[derive(NifMap)]
+struct EchoSpec {
+    tx_symbols: Vec<u8>,
+    isolation_db: f64,
+    leak_channel: ChannelParams,
+    turnaround_samples: u64,
+    preamble: Vec<u8>,
+    data: Vec<u8>,
+    remote_channel: ChannelParams,
+    remote_level_db: f64,
+    seed: u64,
+}
+
+#[derive(NifMap)]
+struct EchoOutcomeMap {
+    tx_symbols_out: u64,
+    rx_symbols_out: u64,
+    sync: Option<(u64, u8)>,
+    symbol_errors: u64,
+    data_symbols: u64,
+    ser: f64,
+}
+
+impl From<EchoOutcome> for EchoOutcomeMap {
+    fn from(o: EchoOutcome) -> Self {
+        Self {
+            tx_symbols_out: o.tx_symbols_out as u64,
+            rx_symbols_out: o.rx_symbols_out as u64,
+            sync: o.sync.map(|(offset, rot)| (offset as u64, rot)),
+            symbol_errors: o.symbol_errors as u64,
+            data_symbols: o.data_symbols as u64,
+            ser: o.ser(),
+        }
+    }
+}
+
+/// :off, :detect or :truncate (the EOT detector with its defaults)
+fn decode_eot(term: Term) -> NifResult<Option<EotConfig>> {
+    let mode: Atom = term.decode()?;
+    if mode == atoms::off() {
+        Ok(None)
+    } else if mode == atoms::detect() {
+        Ok(Some(EotConfig::default()))
+    } else if mode == atoms::truncate() {
+        Ok(Some(EotConfig { truncate: true, ..EotConfig::default() }))
+    } else {
+        Err(term_error("invalid_eot"))
+    }
+}
+
+/// Runs one transmit-then-receive turnaround (see echo.rs).
+/// opts map: symbol_rate (2400), carrier_freq (the leak channel's),
+/// block_samples (480), reset_at_rx (false), eot (:off),
+/// timing_tracking (false).
+#[rustler::nif(schedule = "DirtyCpu")]
+fn run_echo_scenario(constellation: Atom, spec: EchoSpec, opts: Term) -> NifResult<(Atom, EchoOutcomeMap)> {
+    guarded(|| {
+        let constellation = constellation_from_atom(constellation)
+            .ok_or_else(|| term_error("invalid_constellation"))?;
+
+        let opt = |key: Atom| opts.map_get(key).ok();
+        let symbol_rate = match opt(atoms::symbol_rate()) {
+            Some(term) => term.decode()?,
+            None => DEFAULT_SYMBOL_RATE,
+        };
+        let carrier_freq = match opt(atoms::carrier_freq()) {
+            Some(term) => term.decode()?,
+            None => spec.leak_channel.carrier_freq_hz,
+        };
+        let block_samples = match opt(atoms::block_samples()) {
+            Some(term) => term.decode()?,
+            None => DEFAULT_BLOCK_SAMPLES,
+        };
+        let mut handling = EchoHandling::default();
+        if let Some(term) = opt(atoms::reset_at_rx()) {
+            handling.reset_at_rx = term.decode()?;
+        }
+        if let Some(term) = opt(atoms::eot()) {
+            handling.eot = decode_eot(term)?;
+        }
+        if let Some(term) = opt(atoms::timing_tracking()) {
+            handling.timing_tracking = term.decode()?;
+        }
+
+        // Bound everything the timeline is sized from before modulating
+        channel_limits::validate_params(&spec.leak_channel).map_err(range_error)?;
+        channel_limits::validate_params(&spec.remote_channel).map_err(range_error)?;
+        let sps = spec.leak_channel.sample_rate.checked_div(symbol_rate).ok_or_else(|| term_error("invalid_symbol_rate"))?;
+        check_max("samples_per_symbol", sps.into(), MAX_SAMPLES_PER_SYMBOL)?;
+        let symbols = spec.tx_symbols.len() + spec.preamble.len() + spec.data.len();
+        let timeline = (symbols as u64).saturating_mul(sps.into()).saturating_add(spec.turnaround_samples);
+        check_max("frame_len", timeline, frame::MAX_FRAME_SAMPLES as u64)?;
+
+        let scenario = EchoScenario {
+            constellation,
+            symbol_rate,
+            carrier_freq,
+            tx_symbols: spec.tx_symbols,
+            isolation_db: spec.isolation_db,
+            leak_channel: spec.leak_channel,
+            turnaround_samples: spec.turnaround_samples as usize,
+            preamble: spec.preamble,
+            data: spec.data,
+            remote_channel: spec.remote_channel,
+            remote_level_db: spec.remote_level_db,
+            block_samples,
+            handling,
+            seed: spec.seed,
+        };
+        let outcome = echo::run_echo_scenario(&scenario).map_err(term_error)?;
+        Ok((atoms::ok(), outcome.into()))
+    })
+}
+
+// ============================================================================
+// BER scoreboard
+// ============================================================================
+
+static SCOREBOARDS: Census = Census::new("scoreboards");
+
+/// State a resource can be put back into after a panic left it half
+/// updated
+trait Recover {
+    fn recover(&mut self);
+}
+
+impl Recover for Scoreboard {
+    fn recover(&mut self) {
+        Scoreboard::recover(self);
+    }
+}
+
+/// Lock a resource, recovering it if an earlier call panicked with it held
+fn lock<T: Recover>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        let mut state = poisoned.into_inner();
+        state.recover();
+        mutex.clear_poison();
+        state
+    })
+}
+
+pub struct ScoreboardResource {
+    inner: Mutex<Scoreboard>,
+    tally: Tally,
+}
+
+impl ScoreboardResource {
+    fn new(scoreboard: Scoreboard) -> Self {
+        let tally = Tally::new(&SCOREBOARDS, scoreboard.memory_bytes());
+        Self { inner: Mutex::new(scoreboard), tally }
+    }
+}
+
+#[derive(NifMap)]
+struct ConfidenceBinMap {
+    symbols: u64,
+    errors: u64,
+    ser: f64,
+}
+
+impl From<ConfidenceBin> for ConfidenceBinMap {
+    fn from(bin: ConfidenceBin) -> Self {
+        Self { symbols: bin.symbols, errors: bin.errors, ser: bin.ser() }
+    }
+}
+
+#[derive(NifMap)]
+struct ScoreMap {
+    symbols: u64,
+    symbol_errors: u64,
+    bit_errors: u64,
+    ser: f64,
+    ber: f64,
+    error_bursts: u64,
+    longest_burst: u64,
+    window_symbols: u64,
+    window_errors: u64,
+    window_ser: f64,
+    high_confidence: ConfidenceBinMap,
+    low_confidence: ConfidenceBinMap,
+    rotation: Option<u8>,
+    pending: u64,
+}
+
+impl From<Score> for ScoreMap {
+    fn from(s: Score) -> Self {
+        Self {
+            symbols: s.symbols,
+            symbol_errors: s.symbol_errors,
+            bit_errors: s.bit_errors,
+            ser: s.ser,
+            ber: s.ber,
+            error_bursts: s.error_bursts,
+            longest_burst: s.longest_burst,
+            window_symbols: s.window_symbols,
+            window_errors: s.window_errors,
+            window_ser: s.window_ser,
+            high_confidence: s.high_confidence.into(),
+            low_confidence: s.low_confidence.into(),
+            rotation: s.rotation,
+            pending: s.pending,
+        }
+    }
+}
+
+/// {:sequence, binary | [symbol]}, {:generated, seed} or
+/// {:prbs9 | :prbs15 | :prbs23, seed}
+fn decode_reference(term: Term) -> NifResult<Reference> {
+    let (kind, value): (Atom, Term) = term.decode()?;
+    if kind == atoms::sequence() {
+        let symbols = match value.decode::<Binary>() {
+            Ok(bin) => bin.as_slice().to_vec(),
+            Err(_) => value.decode::<Vec<u8>>()?,
+        };
+        Ok(Reference::Sequence(symbols))
+    } else if kind == atoms::generated() {
+        Ok(Reference::Generated { seed: value.decode()? })
+    } else if let Some(polynomial) = polynomial_from_atom(kind) {
+        Ok(Reference::Prbs { polynomial, seed: value.decode()? })
+    } else {
+        Err(term_error("invalid_reference"))
+    }
+}
+
+/// Creates a scoreboard.
+/// opts map: symbol_rate (2400), window_seconds (10.0), burst_gap (8),
+/// confidence_threshold (0.5).
+#[rustler::nif]
+fn new_scoreboard(
+    constellation: Atom,
+    reference: Term,
+    opts: Term,
+) -> NifResult<(Atom, ResourceArc<ScoreboardResource>)> {
+    guarded(|| {
+        let constellation = constellation_from_atom(constellation)
+            .ok_or_else(|| term_error("invalid_constellation"))?;
+        let reference = decode_reference(reference)?;
+
+        let mut config = ScoreboardConfig::new(constellation);
+        let opt = |key: Atom| opts.map_get(key).ok();
+        if let Some(term) = opt(atoms::symbol_rate()) {
+            config.symbol_rate = term.decode()?;
+        }
+        if let Some(term) = opt(atoms::window_seconds()) {
+            config.window_seconds = term.decode()?;
+        }
+        if let Some(term) = opt(atoms::burst_gap()) {
+            config.burst_gap = term.decode()?;
+        }
+        if let Some(term) = opt(atoms::confidence_threshold()) {
+            config.confidence_threshold = term.decode()?;
+        }
+
+        let scoreboard = Scoreboard::new(config, reference).map_err(term_error)?;
+        Ok((
+            atoms::ok(),
+            ResourceArc::new(ScoreboardResource::new(scoreboard)),
+        ))
+    })
+}
+
+/// Scores demodulated symbols (one byte each), optionally with their
+/// slicer confidences.
+#[rustler::nif]
+fn score(
+    scoreboard: ResourceArc<ScoreboardResource>,
+    symbols: Binary,
+    confidences: Option<Vec<f64>>,
+) -> NifResult<Atom> {
+    guarded(|| {
+        let mut state = lock(&scoreboard.inner);
+        state
+            .score(symbols.as_slice(), confidences.as_deref())
+            .map_err(term_error)?;
+        scoreboard.tally.set_bytes(state.memory_bytes());
+        Ok(atoms::ok())
+    })
+}
+
+/// Returns the running statistics as a map.
+#[rustler::nif]
+fn get_score(scoreboard: ResourceArc<ScoreboardResource>) -> NifResult<(Atom, ScoreMap)> {
+    guarded(|| {
+        let state = lock(&scoreboard.inner);
+        Ok((atoms::ok(), state.get_score().into()))
+    })
+}
+
+/// First `count` symbols of the scoreboard's generated reference for `seed`,
+/// as one byte per symbol. For the transmitting side of a test.
+#[rustler::nif]
+fn reference_symbols<'a>(
+    env: Env<'a>,
+    constellation: Atom,
+    seed: u64,
+    count: u64,
+) -> NifResult<(Atom, Binary<'a>)> {
+    guarded(|| {
+        let constellation = constellation_from_atom(constellation)
+            .ok_or_else(|| term_error("invalid_constellation"))?;
+        check_max("count", count, frame::MAX_FRAME_SAMPLES as u64)?;
+        let symbols = scoreboard::reference_symbols(constellation, seed, count as usize);
+
+        let mut owned = OwnedBinary::new(symbols.len()).ok_or_else(|| term_error("binary_alloc_failed"))?;
+        owned.as_mut_slice().copy_from_slice(&symbols);
+        Ok((atoms::ok(), owned.release(env)))
+    })
+}
+
+// ============================================================================
+// Theoretical BER reference
+// ============================================================================
+
+#[derive(NifMap)]
+struct BerPointMap {
+    snr_db: f64,
+    ser: f64,
+    ber: f64,
+    point_ser: f64,
+}
+
+impl From<BerPoint> for BerPointMap {
+    fn from(p: BerPoint) -> Self {
+        Self { snr_db: p.snr_db, ser: p.ser, ber: p.ber, point_ser: p.point_ser }
+    }
+}
+
+/// Theoretical SER and BER at each Es/N0 in `snr_points` (see
+/// ber_reference.rs). channel: :awgn or :rayleigh.
+#[rustler::nif(schedule = "DirtyCpu")]
+fn ber_reference(constellation: Atom, channel: Atom, snr_points: Vec<f64>) -> NifResult<(Atom, Vec<BerPointMap>)> {
+    guarded(|| {
+        let constellation = constellation_from_atom(constellation)
+            .ok_or_else(|| term_error("invalid_constellation"))?;
+        let fading = if channel == atoms::awgn() {
+            Fading::Awgn
+        } else if channel == atoms::rayleigh() {
+            Fading::Rayleigh
+        } else {
+            return Err(term_error("invalid_channel"));
+        };
+        check_max("snr_points", snr_points.len() as u64, ber_reference::MAX_POINTS as u64)?;
+        if snr_points.iter().any(|snr| !snr.is_finite()) {
+            return Err(term_error("invalid_snr"));
+        }
+
+        let curve = ber_reference::ber_reference(constellation, fading, &snr_points);
+        Ok((atoms::ok(), curve.into_iter().map(BerPointMap::from).collect()))
+    })
+}
+
+// ============================================================================
+// Batch BER sweeps
+// ============================================================================
+
+const DEFAULT_PROGRESS_MS: u64 = 250;
+
+/// Stops a running sweep when set (see abort/1)
+pub struct AbortToken(AtomicBool);
+
+#[derive(NifMap)]
+struct SweepSpecMap {
+    constellations: Vec<Atom>,
+    snr_db: Vec<f64>,
+    doppler_hz: Vec<f64>,
+    delay_samples: Vec<u32>,
+    channel: ChannelParams,
+    trials: u64,
+    symbols: u64,
+    seed: u64,
+}
+
+/// Creates an abort token for run_sweep's opts.
+#[rustler::nif]
+fn new_abort_token() -> (Atom, ResourceArc<AbortToken>) {
+    (atoms::ok(), ResourceArc::new(AbortToken(AtomicBool::new(false))))
+}
+
+/// Stops every sweep running with `token` after the trials in hand.
+#[rustler::nif]
+fn abort(token: ResourceArc<AbortToken>) -> Atom {
+    token.0.store(true, Ordering::Relaxed);
+    atoms::ok()
+}
+
+/// Runs every trial of a sweep grid (see sweep.rs) on a pool of threads,
+/// sending `{:sweep_progress, done, total}` to `pid` as it goes and
+/// `{:sweep_done, results}` at the end: four little-endian u64s per grid
+/// point, in grid order. opts map: symbol_rate (2400), carrier_freq (the
+/// channel's), max_threads (the cores available), progress_ms (250),
+/// abort (a token from new_abort_token/0).
+#[rustler::nif(schedule = "DirtyCpu")]
+fn run_sweep<'a>(env: Env<'a>, spec: SweepSpecMap, opts: Term<'a>, pid: LocalPid) -> NifResult<Atom> {
+    guarded(|| {
+        let constellations = spec
+            .constellations
+            .iter()
+            .map(|&atom| constellation_from_atom(atom))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| term_error("invalid_constellation"))?;
+
+        let opt = |key: Atom| opts.map_get(key).ok();
+        let symbol_rate = match opt(atoms::symbol_rate()) {
+            Some(term) => term.decode()?,
+            None => DEFAULT_SYMBOL_RATE,
+        };
+        let carrier_freq = match opt(atoms::carrier_freq()) {
+            Some(term) => term.decode()?,
+            None => spec.channel.carrier_freq_hz,
+        };
+        let max_threads = match opt(atoms::max_threads()) {
+            Some(term) => term.decode()?,
+            None => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        };
+        let progress_ms = match opt(atoms::progress_ms()) {
+            Some(term) => term.decode()?,
+            None => DEFAULT_PROGRESS_MS,
+        };
+        let token: Option<ResourceArc<AbortToken>> = match opt(atoms::abort()) {
+            Some(term) => Some(term.decode()?),
+            None => None,
+        };
+
+        let sweep = SweepSpec {
+            constellations,
+            snr_db: spec.snr_db,
+            doppler_hz: spec.doppler_hz,
+            delay_samples: spec.delay_samples,
+            channel: spec.channel,
+            trials: spec.trials as usize,
+            symbols: spec.symbols as usize,
+            symbol_rate,
+            carrier_freq,
+            seed: spec.seed,
+        };
+
+        // Bound the grid, then every point's channel, before any work
+        check_max("points", sweep.len() as u64, sweep::MAX_POINTS as u64)?;
+        check_max("trials", (sweep.len() as u64).saturating_mul(spec.trials), sweep::MAX_UNITS as u64)?;
+        check_max("symbols", spec.symbols, sweep::MAX_TRIAL_SYMBOLS as u64)?;
+        for index in 0..sweep.len() {
+            channel_limits::validate_params(&sweep.channel_at(&sweep.point(index))).map_err(range_error)?;
+        }
+        if let Some(sps) = sweep.channel.sample_rate.checked_div(symbol_rate) {
+            check_max("samples_per_symbol", sps.into(), MAX_SAMPLES_PER_SYMBOL)?;
+        }
+
+        let never = AtomicBool::new(false);
+        let abort = token.as_ref().map_or(&never, |token| &token.0);
+        let interval = Duration::from_millis(progress_ms);
+        let counts = sweep::run_sweep(&sweep, max_threads, abort, interval, |done, total| {
+            // A dead pid just misses the message
+            let _ = env.send(&pid, (atoms::sweep_progress(), done as u64, total as u64));
+        })
+        .map_err(|e| match e {
+            "aborted" => rustler::Error::Term(Box::new(atoms::aborted())),
+            e => term_error(e),
+        })?;
+
+        let mut owned = OwnedBinary::new(counts.len() * sweep::BerCount::PACKED_LEN)
+            .ok_or_else(|| term_error("binary_alloc_failed"))?;
+        for (chunk, count) in owned.as_mut_slice().chunks_exact_mut(sweep::BerCount::PACKED_LEN).zip(&counts) {
+            chunk.copy_from_slice(&count.to_le_bytes());
+        }
+        let results = owned.release(env);
+        let _ = env.send(&pid, (atoms::sweep_done(), results));
+        Ok(atoms::ok())
+    })
+}
+
+// ============================================================================
+// Resource census
+// ============================================================================
+
+#[derive(NifMap)]
+struct CensusMap {
+    live: u64,
+    created: u64,
+    dropped: u64,
+    bytes: u64,
+}
+
+impl From<CensusCount> for CensusMap {
+    fn from(c: CensusCount) -> Self {
+        Self { live: c.live, created: c.created, dropped: c.dropped, bytes: c.bytes }
+    }
+}
+
+#[derive(NifMap)]
+struct SimCensusMap {
+    scoreboards: CensusMap,
+}
+
+/// Live scoreboards and the buffer bytes they hold, for leak checks.
+#[rustler::nif]
+fn census() -> SimCensusMap {
+    SimCensusMap { scoreboards: SCOREBOARDS.count().into() }
+}
+
+#[cfg(test)]
+mod tests {
+    use channel_physics::phase_log::{self, PhaseEntry};
+    use phy_modem::wire;
+
+    /// What channel_physics writes for I/Q, phy_modem reads back, with no
+    /// help from the legacy flag (off here: phy_modem is linked without
+    /// its default features)
+    #[test]
+    fn test_channel_physics_iq_parses_with_phy_modem_reader() {
+        assert_eq!(wire::LEGACY, wire::Legacy::Reject);
+        let entries = [PhaseEntry { sample_index: 100, phase: 0.3, step: 2.0 * std::f64::consts::PI * 1800.0 / 9600.0 }];
+        let output: Vec<f64> = (0..960).map(|n| (0.01 * n as f64).sin() * 0.4).collect();
+
+        let bytes = phase_log::derotate_wire(&entries, 100, &output, 9600.0).unwrap();
+        let expected = phase_log::derotate(&entries, 100, &output, 9600.0).unwrap();
+        let read = wire::read_iq(&bytes).unwrap();
+        assert_eq!(read.len(), output.len());
+        for (&(i, q), &(ei, eq)) in read.iter().zip(&expected) {
+            assert_eq!((i, q), (ei as f32 as f64, eq as f32 as f64));
+        }
+        // Cut short or stripped of its header, it no longer parses
+        assert_eq!(wire::read_iq(&bytes[..bytes.len() - 8]), Err(wire::WireError::LengthMismatch));
+        assert_eq!(wire::read_iq(&bytes[wire::HEADER_LEN..]), Err(wire::WireError::BadMagic));
+    }
+
+    /// Thousands of scoreboards made, fed and dropped leave none behind,
+    /// and the bytes follow their buffers as scoring changes them
+    #[test]
+    fn test_scoreboards_dont_leak() {
+        use super::{Reference, Scoreboard, ScoreboardConfig, ScoreboardResource, SCOREBOARDS};
+        use minutemodem_dsp::census::ThreadCount;
+        use phy_modem::ConstellationType;
+
+        for _ in 0..5 {
+            let boards: Vec<ScoreboardResource> = (0..1000u64)
+                .map(|seed| {
+                    let config = ScoreboardConfig::new(ConstellationType::Qpsk);
+                    let reference = Reference::Sequence(vec![(seed % 4) as u8; 64]);
+                    ScoreboardResource::new(Scoreboard::new(config, reference).unwrap())
+                })
+                .collect();
+            let held = SCOREBOARDS.this_thread();
+            assert_eq!(held.live, 1000);
+
+            let mut bytes = 0;
+            for board in &boards {
+                let mut state = board.inner.lock().unwrap();
+                state.score(&[0; 100], None).unwrap();
+                board.tally.set_bytes(state.memory_bytes());
+                bytes += state.memory_bytes();
+            }
+            assert_ne!(bytes, held.bytes as usize);
+            assert_eq!(SCOREBOARDS.this_thread().bytes, bytes as i64);
+
+            drop(boards);
+            assert_eq!(SCOREBOARDS.this_thread(), ThreadCount::default());
+        }
+    }
+}
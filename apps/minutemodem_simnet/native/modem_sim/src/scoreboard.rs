@@ -0,0 +1,565 @@
+//! Running symbol and bit error statistics
+//!
+//! A Scoreboard compares demodulated symbols with the reference stream as
+//! they arrive, so a soak test can watch SER/BER without shipping every
+//! symbol back to Elixir. The reference is an explicit sequence (repeated
+//! as needed), the seeded generator in reference_symbols() or a PRBS from
+//! phy_modem::prbs, which the transmitting side can run too.
+//!
+//! The first ROTATION_WINDOW symbols resolve the carrier loop's phase
+//! ambiguity for PSK (the received index is the sent one plus a fixed
+//! rotation); after that, symbols are scored as they come. Bit errors count
+//! differing bits of the symbol indices.
+
+use std::collections::VecDeque;
+
+use phy_modem::prbs::{Prbs, PrbsPolynomial};
+use phy_modem::ConstellationType;
+
+/// Symbols buffered to pick the PSK rotation before scoring starts
+pub const ROTATION_WINDOW: usize = 32;
+
+/// Where the expected symbols come from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reference {
+    /// Explicit sequence, repeated as needed
+    Sequence(Vec<u8>),
+    /// reference_symbols() with this seed
+    Generated { seed: u64 },
+    /// phy_modem::prbs::prbs_symbols() with this polynomial and seed
+    Prbs { polynomial: PrbsPolynomial, seed: u64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct ScoreboardConfig {
+    pub constellation: ConstellationType,
+    /// Converts window_seconds to symbols
+    pub symbol_rate: u32,
+    /// Span of the windowed SER
+    pub window_seconds: f64,
+    /// Correct symbols in a row that end an error burst
+    pub burst_gap: usize,
+    /// Confidence at or above which a symbol counts as high confidence
+    pub confidence_threshold: f64,
+}
+
+impl ScoreboardConfig {
+    pub fn new(constellation: ConstellationType) -> Self {
+        Self {
+            constellation,
+            symbol_rate: 2400,
+            window_seconds: 10.0,
+            burst_gap: 8,
+            confidence_threshold: 0.5,
+        }
+    }
+}
+
+/// Seeded uniform symbol generator shared by both ends of a test
+///
+/// SplitMix64, one output per symbol, taking the top bits. Symbol k of a
+/// seed is the same however the stream is chunked.
+pub struct ReferenceGenerator {
+    state: u64,
+    bits: u32,
+}
+
+impl ReferenceGenerator {
+    pub fn new(constellation: ConstellationType, seed: u64) -> Self {
+        Self { state: seed, bits: constellation.bits_per_symbol() as u32 }
+    }
+
+    pub fn next_symbol(&mut self) -> u8 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> (64 - self.bits)) as u8
+    }
+}
+
+/// The first `count` symbols of reference_symbols stream `seed`
+pub fn reference_symbols(constellation: ConstellationType, seed: u64, count: usize) -> Vec<u8> {
+    let mut generator = ReferenceGenerator::new(constellation, seed);
+    (0..count).map(|_| generator.next_symbol()).collect()
+}
+
+enum ReferenceSource {
+    Sequence { symbols: Vec<u8>, position: usize },
+    Generated(ReferenceGenerator),
+    Prbs { prbs: Prbs, bits: usize },
+}
+
+impl ReferenceSource {
+    fn next_symbol(&mut self) -> u8 {
+        match self {
+            Self::Sequence { symbols, position } => {
+                let symbol = symbols[*position];
+                *position = (*position + 1) % symbols.len();
+                symbol
+            }
+            Self::Generated(generator) => generator.next_symbol(),
+            Self::Prbs { prbs, bits } => prbs.next_symbol(*bits),
+        }
+    }
+}
+
+/// Symbol and error counts for one confidence class
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ConfidenceBin {
+    pub symbols: u64,
+    pub errors: u64,
+}
+
+impl ConfidenceBin {
+    pub fn ser(&self) -> f64 {
+        ratio(self.errors, self.symbols)
+    }
+}
+
+/// Snapshot of the running statistics
+#[derive(Debug, Clone, PartialEq)]
+pub struct Score {
+    /// Symbols scored (excludes `pending`)
+    pub symbols: u64,
+    pub symbol_errors: u64,
+    pub bit_errors: u64,
+    pub ser: f64,
+    pub ber: f64,
+    /// Runs of errors separated by fewer than burst_gap correct symbols
+    pub error_bursts: u64,
+    /// Longest burst, first to last error inclusive
+    pub longest_burst: u64,
+    /// Over the last window_seconds of symbols
+    pub window_symbols: u64,
+    pub window_errors: u64,
+    pub window_ser: f64,
+    pub high_confidence: ConfidenceBin,
+    pub low_confidence: ConfidenceBin,
+    /// PSK rotation removed from received symbols; None until resolved
+    pub rotation: Option<u8>,
+    /// Symbols buffered for rotation resolution, not yet scored
+    pub pending: u64,
+}
+
+pub struct Scoreboard {
+    config: ScoreboardConfig,
+    reference: ReferenceSource,
+    rotation: Option<u8>,
+    /// (symbol, confidence) awaiting rotation resolution
+    pending: Vec<(u8, Option<f64>)>,
+
+    symbols: u64,
+    symbol_errors: u64,
+    bit_errors: u64,
+
+    error_bursts: u64,
+    longest_burst: u64,
+    /// Symbol index of the open burst's first error
+    burst_start: Option<u64>,
+    correct_run: usize,
+
+    window: VecDeque<bool>,
+    window_len: usize,
+    window_errors: u64,
+
+    high_confidence: ConfidenceBin,
+    low_confidence: ConfidenceBin,
+}
+
+impl Scoreboard {
+    pub fn new(config: ScoreboardConfig, reference: Reference) -> Result<Self, &'static str> {
+        let order = config.constellation.order();
+        let reference = match reference {
+            Reference::Sequence(symbols) => {
+                if symbols.is_empty() {
+                    return Err("empty_reference");
+                }
+                if symbols.iter().any(|&s| s as usize >= order) {
+                    return Err("symbol_out_of_range");
+                }
+                ReferenceSource::Sequence { symbols, position: 0 }
+            }
+            Reference::Generated { seed } => {
+                ReferenceSource::Generated(ReferenceGenerator::new(config.constellation, seed))
+            }
+            Reference::Prbs { polynomial, seed } => ReferenceSource::Prbs {
+                prbs: Prbs::new(polynomial, seed),
+                bits: config.constellation.bits_per_symbol(),
+            },
+        };
+        if config.symbol_rate == 0 || config.window_seconds.is_nan() || config.window_seconds <= 0.0 {
+            return Err("invalid_window");
+        }
+        if config.burst_gap == 0 {
+            return Err("invalid_burst_gap");
+        }
+
+        let window_len = ((config.window_seconds * config.symbol_rate as f64).round() as usize).max(1);
+        // Only PSK has a pure index rotation to resolve
+        let rotation = match config.constellation {
+            ConstellationType::Bpsk | ConstellationType::Qpsk | ConstellationType::Psk8 => None,
+            _ => Some(0),
+        };
+
+        Ok(Self {
+            config,
+            reference,
+            rotation,
+            pending: Vec::with_capacity(ROTATION_WINDOW),
+            symbols: 0,
+            symbol_errors: 0,
+            bit_errors: 0,
+            error_bursts: 0,
+            longest_burst: 0,
+            burst_start: None,
+            correct_run: 0,
+            window: VecDeque::with_capacity(window_len),
+            window_len,
+            window_errors: 0,
+            high_confidence: ConfidenceBin::default(),
+            low_confidence: ConfidenceBin::default(),
+        })
+    }
+
+    /// Score the next demodulated symbols, with their slicer confidences
+    /// if available
+    pub fn score(&mut self, symbols: &[u8], confidences: Option<&[f64]>) -> Result<(), &'static str> {
+        if confidences.is_some_and(|c| c.len() != symbols.len()) {
+            return Err("confidence_length_mismatch");
+        }
+        let order = self.config.constellation.order();
+        if symbols.iter().any(|&s| s as usize >= order) {
+            return Err("symbol_out_of_range");
+        }
+
+        for (k, &symbol) in symbols.iter().enumerate() {
+            let confidence = confidences.map(|c| c[k]);
+            match self.rotation {
+                Some(rotation) => {
+                    let expected = self.reference.next_symbol();
+                    self.record(symbol, expected, rotation, confidence);
+                }
+                None => {
+                    self.pending.push((symbol, confidence));
+                    if self.pending.len() == ROTATION_WINDOW {
+                        self.resolve_rotation();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Bytes held in the reference, rotation and window buffers
+    pub fn memory_bytes(&self) -> usize {
+        let reference = match &self.reference {
+            ReferenceSource::Sequence { symbols, .. } => symbols.capacity(),
+            _ => 0,
+        };
+        reference
+            + self.pending.capacity() * std::mem::size_of::<(u8, Option<f64>)>()
+            + self.window.capacity() * std::mem::size_of::<bool>()
+    }
+
+    pub fn get_score(&self) -> Score {
+        let bits = self.config.constellation.bits_per_symbol() as u64;
+        Score {
+            symbols: self.symbols,
+            symbol_errors: self.symbol_errors,
+            bit_errors: self.bit_errors,
+            ser: ratio(self.symbol_errors, self.symbols),
+            ber: ratio(self.bit_errors, self.symbols * bits),
+            error_bursts: self.error_bursts,
+            longest_burst: self.longest_burst,
+            window_symbols: self.window.len() as u64,
+            window_errors: self.window_errors,
+            window_ser: ratio(self.window_errors, self.window.len() as u64),
+            high_confidence: self.high_confidence,
+            low_confidence: self.low_confidence,
+            rotation: self.rotation,
+            pending: self.pending.len() as u64,
+        }
+    }
+
+    /// Make the counts agree again after a panic part way through
+    /// score(): the window's error count is taken from the window and any
+    /// open error burst is closed. Symbols already scored stay counted.
+    pub fn recover(&mut self) {
+        while self.window.len() > self.window_len {
+            self.window.pop_front();
+        }
+        self.window_errors = self.window.iter().filter(|&&error| error).count() as u64;
+        self.burst_start = None;
+        self.correct_run = 0;
+    }
+
+    /// Pick the rotation with the fewest errors over the buffered symbols,
+    /// then score them
+    fn resolve_rotation(&mut self) {
+        let order = self.config.constellation.order();
+        let expected: Vec<u8> = (0..self.pending.len()).map(|_| self.reference.next_symbol()).collect();
+        let errors = |rotation: usize| {
+            self.pending
+                .iter()
+                .zip(&expected)
+                .filter(|&(&(rx, _), &tx)| (tx as usize + rotation) % order != rx as usize)
+                .count()
+        };
+        let rotation = (0..order).min_by_key(|&r| errors(r)).unwrap_or(0) as u8;
+
+        self.rotation = Some(rotation);
+        for ((symbol, confidence), expected) in std::mem::take(&mut self.pending).into_iter().zip(expected) {
+            self.record(symbol, expected, rotation, confidence);
+        }
+    }
+
+    fn record(&mut self, received: u8, expected: u8, rotation: u8, confidence: Option<f64>) {
+        let order = self.config.constellation.order();
+        let corrected = ((received as usize + order - rotation as usize) % order) as u8;
+        let error = corrected != expected;
+        let index = self.symbols;
+
+        self.symbols += 1;
+        if error {
+            self.symbol_errors += 1;
+            self.bit_errors += (corrected ^ expected).count_ones() as u64;
+        }
+
+        // Bursts: an error opens one (or extends the open one); burst_gap
+        // correct symbols close it
+        if error {
+            let start = *self.burst_start.get_or_insert_with(|| {
+                self.error_bursts += 1;
+                index
+            });
+            self.longest_burst = self.longest_burst.max(index - start + 1);
+            self.correct_run = 0;
+        } else if self.burst_start.is_some() {
+            self.correct_run += 1;
+            if self.correct_run >= self.config.burst_gap {
+                self.burst_start = None;
+            }
+        }
+
+        if self.window.len() == self.window_len && self.window.pop_front() == Some(true) {
+            self.window_errors -= 1;
+        }
+        self.window.push_back(error);
+        self.window_errors += error as u64;
+
+        if let Some(confidence) = confidence {
+            let bin = if confidence >= self.config.confidence_threshold {
+                &mut self.high_confidence
+            } else {
+                &mut self.low_confidence
+            };
+            bin.symbols += 1;
+            bin.errors += error as u64;
+        }
+    }
+}
+
+fn ratio(n: u64, d: u64) -> f64 {
+    if d == 0 { 0.0 } else { n as f64 / d as f64 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(constellation: ConstellationType, reference: Reference) -> Scoreboard {
+        Scoreboard::new(ScoreboardConfig::new(constellation), reference).unwrap()
+    }
+
+    /// `reference` with errors (index + 1, mod order) at the given positions
+    fn with_errors(reference: &[u8], errors: &[usize], order: u8) -> Vec<u8> {
+        let mut rx = reference.to_vec();
+        for &k in errors {
+            rx[k] = (rx[k] + 1) % order;
+        }
+        rx
+    }
+
+    #[test]
+    fn test_counts_and_rates() {
+        let tx = reference_symbols(ConstellationType::Qam16, 1, 1000);
+        let errors = [40, 41, 500, 999];
+        let mut rx = with_errors(&tx, &errors, 16);
+        // Flip exactly two bits at 500: 0b0101 difference
+        rx[500] = tx[500] ^ 0b0101;
+
+        let mut sb = board(ConstellationType::Qam16, Reference::Generated { seed: 1 });
+        // Chunking must not matter
+        for chunk in rx.chunks(77) {
+            sb.score(chunk, None).unwrap();
+        }
+        let score = sb.get_score();
+
+        let bits: u64 = errors.iter().map(|&k| (rx[k] ^ tx[k]).count_ones() as u64).sum();
+        assert_eq!(score.symbols, 1000);
+        assert_eq!(score.symbol_errors, 4);
+        assert_eq!(score.bit_errors, bits);
+        assert_eq!(score.ser, 0.004);
+        assert_eq!(score.ber, bits as f64 / 4000.0);
+        assert_eq!(score.rotation, Some(0));
+        assert_eq!(score.pending, 0);
+    }
+
+    #[test]
+    fn test_psk_rotation_resolved_once() {
+        let tx = reference_symbols(ConstellationType::Psk8, 2, 300);
+        // Receiver locked 3 × 45° off, with a few errors in the first window
+        let rotated: Vec<u8> = tx.iter().map(|&s| (s + 3) % 8).collect();
+        let rx = with_errors(&rotated, &[5, 17, 200], 8);
+
+        let mut sb = board(ConstellationType::Psk8, Reference::Sequence(tx.clone()));
+        sb.score(&rx[..ROTATION_WINDOW - 1], None).unwrap();
+        let score = sb.get_score();
+        assert_eq!((score.rotation, score.pending, score.symbols), (None, ROTATION_WINDOW as u64 - 1, 0));
+
+        sb.score(&rx[ROTATION_WINDOW - 1..], None).unwrap();
+        let score = sb.get_score();
+        assert_eq!(score.rotation, Some(3));
+        assert_eq!(score.pending, 0);
+        assert_eq!(score.symbols, 300);
+        assert_eq!(score.symbol_errors, 3);
+    }
+
+    #[test]
+    fn test_recover_recounts_the_window() {
+        let tx = reference_symbols(ConstellationType::Qpsk, 4, 200);
+        let mut sb = board(ConstellationType::Qpsk, Reference::Sequence(tx.clone()));
+        sb.score(&with_errors(&tx, &[150, 199], 4), None).unwrap();
+        let before = sb.get_score();
+
+        // As a panic between updates could leave them
+        sb.window_errors += 5;
+        sb.burst_start = Some(sb.symbols + 10);
+        sb.recover();
+        assert_eq!(sb.get_score(), before);
+        assert_eq!(before.window_errors, 2);
+
+        // Scoring carries on from where it was
+        sb.score(&tx[..10], None).unwrap();
+        assert_eq!(sb.get_score().symbols, 210);
+        assert_eq!(sb.get_score().error_bursts, 2);
+    }
+
+    #[test]
+    fn test_sequence_reference_repeats() {
+        let pattern = vec![0, 1, 2, 3];
+        let rx: Vec<u8> = pattern.iter().cycle().take(100).copied().collect();
+        let mut sb = board(ConstellationType::Qpsk, Reference::Sequence(pattern));
+        sb.score(&rx, None).unwrap();
+        let score = sb.get_score();
+        assert_eq!((score.symbols, score.symbol_errors, score.rotation), (100, 0, Some(0)));
+    }
+
+    #[test]
+    fn test_error_bursts() {
+        // burst_gap 8: errors closer than 8 correct symbols join one burst
+        let tx = reference_symbols(ConstellationType::Qam16, 3, 400);
+        let errors = [
+            100,                // burst 1: single
+            200, 203, 210,      // burst 2: gaps of 2 and 6 correct, spans 11
+            219,                // burst 3: 8 correct since 210 closed burst 2
+            300, 301, 302, 303, // burst 4: spans 4
+        ];
+        let rx = with_errors(&tx, &errors, 16);
+        let mut sb = board(ConstellationType::Qam16, Reference::Generated { seed: 3 });
+        sb.score(&rx, None).unwrap();
+        let score = sb.get_score();
+        assert_eq!(score.symbol_errors, 9);
+        assert_eq!(score.error_bursts, 4);
+        assert_eq!(score.longest_burst, 11);
+    }
+
+    #[test]
+    fn test_windowed_ser() {
+        // 1 s window at 100 baud: the last 100 symbols
+        let config = ScoreboardConfig { symbol_rate: 100, window_seconds: 1.0, ..ScoreboardConfig::new(ConstellationType::Qam16) };
+        let mut sb = Scoreboard::new(config, Reference::Generated { seed: 4 }).unwrap();
+        let tx = reference_symbols(ConstellationType::Qam16, 4, 500);
+        // 20 errors early on, 5 in the last 100 symbols
+        let errors: Vec<usize> = (0..20).map(|k| 10 + k * 10).chain([400, 420, 450, 480, 499]).collect();
+        let rx = with_errors(&tx, &errors, 16);
+
+        sb.score(&rx[..150], None).unwrap();
+        let score = sb.get_score();
+        // Symbols 50..150 hold errors at 50, 60, ..., 140
+        assert_eq!((score.window_symbols, score.window_errors), (100, 10));
+
+        sb.score(&rx[150..], None).unwrap();
+        let score = sb.get_score();
+        assert_eq!((score.window_symbols, score.window_errors), (100, 5));
+        assert_eq!(score.window_ser, 0.05);
+        assert_eq!(score.symbol_errors, 25);
+    }
+
+    #[test]
+    fn test_confidence_bins() {
+        let tx = reference_symbols(ConstellationType::Qam16, 5, 200);
+        let errors = [10, 20, 30, 150];
+        let rx = with_errors(&tx, &errors, 16);
+        // Low confidence on symbols 0..100, except error 30 which is high
+        let confidences: Vec<f64> = (0..200).map(|k| if k < 100 && k != 30 { 0.2 } else { 0.9 }).collect();
+
+        let mut sb = board(ConstellationType::Qam16, Reference::Generated { seed: 5 });
+        sb.score(&rx[..50], Some(&confidences[..50])).unwrap();
+        // Symbols without confidences count in totals only
+        sb.score(&rx[50..60], None).unwrap();
+        sb.score(&rx[60..], Some(&confidences[60..])).unwrap();
+        let score = sb.get_score();
+
+        assert_eq!(score.low_confidence, ConfidenceBin { symbols: 89, errors: 2 });
+        assert_eq!(score.high_confidence, ConfidenceBin { symbols: 101, errors: 2 });
+        assert_eq!(score.symbols, 200);
+        assert!((score.high_confidence.ser() - 2.0 / 101.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let config = ScoreboardConfig::new(ConstellationType::Qpsk);
+        assert_eq!(Scoreboard::new(config.clone(), Reference::Sequence(vec![])).err(), Some("empty_reference"));
+        assert_eq!(Scoreboard::new(config.clone(), Reference::Sequence(vec![4])).err(), Some("symbol_out_of_range"));
+        let zero_gap = ScoreboardConfig { burst_gap: 0, ..config };
+        assert_eq!(Scoreboard::new(zero_gap, Reference::Generated { seed: 0 }).err(), Some("invalid_burst_gap"));
+
+        let mut sb = board(ConstellationType::Qpsk, Reference::Generated { seed: 0 });
+        assert_eq!(sb.score(&[0, 1], Some(&[0.5])), Err("confidence_length_mismatch"));
+        assert_eq!(sb.score(&[7], None), Err("symbol_out_of_range"));
+        assert_eq!(sb.get_score().pending, 0);
+    }
+
+    #[test]
+    fn test_reference_generator() {
+        // Chunk-independent, in range, and roughly uniform
+        let all = reference_symbols(ConstellationType::Psk8, 9, 8000);
+        let mut generator = ReferenceGenerator::new(ConstellationType::Psk8, 9);
+        let again: Vec<u8> = (0..8000).map(|_| generator.next_symbol()).collect();
+        assert_eq!(all, again);
+        let mut counts = [0usize; 8];
+        for &s in &all {
+            counts[s as usize] += 1;
+        }
+        assert!(counts.iter().all(|&c| (900..1100).contains(&c)), "{:?}", counts);
+        assert_ne!(all, reference_symbols(ConstellationType::Psk8, 10, 8000));
+    }
+
+    #[test]
+    fn test_prbs_reference() {
+        let polynomial = PrbsPolynomial::Prbs15;
+        let tx = phy_modem::prbs::prbs_symbols(ConstellationType::Qam16, 2000, 7, polynomial);
+        let rx = with_errors(&tx, &[100, 1500], 16);
+        let mut sb = board(ConstellationType::Qam16, Reference::Prbs { polynomial, seed: 7 });
+        for chunk in rx.chunks(333) {
+            sb.score(chunk, None).unwrap();
+        }
+        let score = sb.get_score();
+        assert_eq!(score.symbols, 2000);
+        assert_eq!(score.symbol_errors, 2);
+        assert_eq!(score.rotation, Some(0));
+    }
+}
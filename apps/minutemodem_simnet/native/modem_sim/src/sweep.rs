@@ -0,0 +1,430 @@
+//! Batch BER sweeps over a grid of channel conditions
+//!
+//! A characterization run measures SER and BER at every combination of
+//! constellation, SNR, Doppler spread and multipath delay, several trials
+//! a point. run_sweep() runs the whole grid in one call on a rayon pool,
+//! so Elixir neither shuttles each point through a NIF call nor leaves
+//! cores idle.
+//!
+//! Each trial is one burst through its own Watterson channel: a known
+//! preamble then `symbols` data symbols, all from the scoreboard's
+//! reference generator, demodulated in blocks as the sound card would
+//! deliver them. The output is synced on the preamble as echo.rs does and
+//! the data scored by symbol index, as the Scoreboard scores. A trial
+//! whose preamble isn't found counts every data symbol as wrong and half
+//! its bits, what a receiver guessing would get.
+//!
+//! Trial `t` of grid point `p` is seeded from the sweep seed and its unit
+//! index `p * trials + t` alone, and the counts are kept per unit and
+//! summed per point at the end, so the results are the same whatever the
+//! thread count and whichever worker ran what.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use rayon::prelude::*;
+
+use channel_physics::channel::ChannelParams;
+use minutemodem_dsp::convert::f64_to_i16;
+use phy_modem::{ConstellationType, UnifiedDemodulator};
+
+use crate::echo;
+use crate::frame::{self, Burst};
+use crate::scoreboard;
+
+/// Known symbols ahead of each trial's data
+pub const PREAMBLE_SYMBOLS: usize = 32;
+
+/// Samples per demodulate call
+pub const BLOCK_SAMPLES: usize = 480;
+
+/// Most grid points one sweep may have
+pub const MAX_POINTS: usize = 65_536;
+
+/// Most trials (over all points) one sweep may run
+pub const MAX_UNITS: usize = 1 << 20;
+
+/// Most data symbols per trial
+pub const MAX_TRIAL_SYMBOLS: usize = 1 << 20;
+
+/// The grid and how each point is measured
+#[derive(Debug, Clone)]
+pub struct SweepSpec {
+    pub constellations: Vec<ConstellationType>,
+    /// Channel snr_db at each point
+    pub snr_db: Vec<f64>,
+    /// Channel doppler_bandwidth_hz at each point
+    pub doppler_hz: Vec<f64>,
+    /// Channel delay_spread_samples at each point
+    pub delay_samples: Vec<u32>,
+    /// Channel for every point, but for the three swept fields
+    pub channel: ChannelParams,
+    pub trials: usize,
+    /// Data symbols per trial
+    pub symbols: usize,
+    pub symbol_rate: u32,
+    pub carrier_freq: f64,
+    pub seed: u64,
+}
+
+/// One grid point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepPoint {
+    pub constellation: ConstellationType,
+    pub snr_db: f64,
+    pub doppler_hz: f64,
+    pub delay_samples: u32,
+}
+
+impl SweepSpec {
+    /// Grid points
+    pub fn len(&self) -> usize {
+        self.constellations
+            .len()
+            .saturating_mul(self.snr_db.len())
+            .saturating_mul(self.doppler_hz.len())
+            .saturating_mul(self.delay_samples.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Point `index` of the grid, constellation slowest and delay fastest
+    pub fn point(&self, index: usize) -> SweepPoint {
+        let delay = index % self.delay_samples.len();
+        let index = index / self.delay_samples.len();
+        let doppler = index % self.doppler_hz.len();
+        let index = index / self.doppler_hz.len();
+        let snr = index % self.snr_db.len();
+        SweepPoint {
+            constellation: self.constellations[index / self.snr_db.len()],
+            snr_db: self.snr_db[snr],
+            doppler_hz: self.doppler_hz[doppler],
+            delay_samples: self.delay_samples[delay],
+        }
+    }
+
+    /// The channel at `point`
+    pub fn channel_at(&self, point: &SweepPoint) -> ChannelParams {
+        ChannelParams {
+            snr_db: point.snr_db,
+            doppler_bandwidth_hz: point.doppler_hz,
+            delay_spread_samples: point.delay_samples,
+            ..self.channel.clone()
+        }
+    }
+}
+
+/// Symbol and bit error counts over one or more trials
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BerCount {
+    /// Data symbols sent
+    pub symbols: u64,
+    pub symbol_errors: u64,
+    pub bit_errors: u64,
+    /// Trials whose preamble wasn't found
+    pub lost: u64,
+}
+
+impl BerCount {
+    fn add(&mut self, other: &BerCount) {
+        self.symbols += other.symbols;
+        self.symbol_errors += other.symbol_errors;
+        self.bit_errors += other.bit_errors;
+        self.lost += other.lost;
+    }
+
+    /// Bytes per count in to_le_bytes()
+    pub const PACKED_LEN: usize = 32;
+
+    /// symbols, symbol_errors, bit_errors and lost as little-endian u64s
+    pub fn to_le_bytes(&self) -> [u8; Self::PACKED_LEN] {
+        let mut bytes = [0; Self::PACKED_LEN];
+        for (chunk, value) in bytes.chunks_exact_mut(8).zip([self.symbols, self.symbol_errors, self.bit_errors, self.lost]) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// Seed of the trial with unit index `unit`
+///
+/// SplitMix64 of the sweep seed stepped `unit + 1` times, so every
+/// (point, trial) draws its own stream whichever worker runs it.
+pub fn trial_seed(seed: u64, unit: u64) -> u64 {
+    let mut z = seed.wrapping_add(unit.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Run one trial at `point` (see the module docs)
+pub fn run_ber_trial(spec: &SweepSpec, point: &SweepPoint, seed: u64) -> Result<BerCount, &'static str> {
+    let constellation = point.constellation;
+    let channel = spec.channel_at(point);
+    let symbols = scoreboard::reference_symbols(constellation, seed, PREAMBLE_SYMBOLS + spec.symbols);
+    let (preamble, data) = symbols.split_at(PREAMBLE_SYMBOLS);
+
+    // Room for the filter and channel delays, as echo.rs leaves
+    let sps = (channel.sample_rate / spec.symbol_rate.max(1)).max(1) as usize;
+    let tail = 32 * sps + channel.bulk_delay_samples as usize + channel.delay_spread_samples as usize + 256;
+    let frame_len = symbols.len() * sps + tail;
+    let burst = Burst {
+        start_sample: 0,
+        symbols: symbols.clone(),
+        constellation,
+        symbol_rate: spec.symbol_rate,
+        carrier_freq: spec.carrier_freq,
+        channel,
+        seed,
+        level_db: 0.0,
+    };
+    let (rx, placements) = frame::compose_frame(frame_len, std::slice::from_ref(&burst))?;
+    let samples: Vec<i16> = rx.iter().map(|&x| f64_to_i16(x)).collect();
+
+    let sample_rate = burst.channel.sample_rate;
+    let mut demod = UnifiedDemodulator::new(constellation, sample_rate, spec.symbol_rate, spec.carrier_freq);
+    let received: Vec<u8> = samples.chunks(BLOCK_SAMPLES).flat_map(|block| demod.demodulate(block)).collect();
+
+    let order = constellation.order() as u8;
+    let bits = constellation.bits_per_symbol() as u64;
+    let earliest = placements[0].first_symbol_sample / sps;
+    let search = earliest.saturating_sub(16)..earliest + 64;
+    let mut count = BerCount { symbols: data.len() as u64, ..BerCount::default() };
+    match echo::find_preamble(&received, preamble, search, order, echo::rotations(constellation)) {
+        Some((offset, rot)) => {
+            let rx_data = received.get(offset + PREAMBLE_SYMBOLS..).unwrap_or(&[]);
+            for (k, &sent) in data.iter().enumerate() {
+                let expected = (sent + rot) % order;
+                match rx_data.get(k) {
+                    Some(&r) if r == expected => {}
+                    Some(&r) => {
+                        count.symbol_errors += 1;
+                        count.bit_errors += u64::from((r ^ expected).count_ones());
+                    }
+                    None => {
+                        count.symbol_errors += 1;
+                        count.bit_errors += bits / 2;
+                    }
+                }
+            }
+        }
+        None => {
+            count.symbol_errors = data.len() as u64;
+            count.bit_errors = data.len() as u64 * bits / 2;
+            count.lost = 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Run every trial of the grid on a rayon pool of up to `max_threads`
+/// threads
+///
+/// Calls `progress(done, total)` (in trials) from the calling thread
+/// about every `interval` while the pool runs, and once at the end.
+/// Setting `abort` stops the pool after the trials it is on and gives
+/// Err("aborted"); so does a trial's own error, which is returned.
+/// The counts come back one per grid point, in grid order.
+pub fn run_sweep(
+    spec: &SweepSpec,
+    max_threads: usize,
+    abort: &AtomicBool,
+    interval: Duration,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Vec<BerCount>, &'static str> {
+    if spec.is_empty() || spec.trials == 0 || spec.symbols == 0 {
+        return Err("empty_sweep");
+    }
+    let points = spec.len();
+    let total = points * spec.trials;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(max_threads.clamp(1, total))
+        .build()
+        .map_err(|_| "thread_pool_failed")?;
+    let done = AtomicUsize::new(0);
+    let caller = thread::current();
+
+    // install() blocks until the pool is through, so it runs on a thread
+    // of its own while this one reports progress
+    let counts = thread::scope(|scope| {
+        let sweep = scope.spawn(|| {
+            let counts = pool.install(|| {
+                (0..total)
+                    .into_par_iter()
+                    .map(|unit| {
+                        if abort.load(Ordering::Relaxed) {
+                            return Err("aborted");
+                        }
+                        let point = spec.point(unit / spec.trials);
+                        let count = run_ber_trial(spec, &point, trial_seed(spec.seed, unit as u64))?;
+                        done.fetch_add(1, Ordering::Relaxed);
+                        Ok(count)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            });
+            caller.unpark();
+            counts
+        });
+
+        let mut reported = None;
+        while !sweep.is_finished() {
+            thread::park_timeout(interval);
+            let now = done.load(Ordering::Relaxed);
+            if reported != Some(now) {
+                progress(now, total);
+                reported = Some(now);
+            }
+        }
+        sweep.join().unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    })?;
+
+    if abort.load(Ordering::Relaxed) {
+        return Err("aborted");
+    }
+    progress(total, total);
+
+    let mut results = vec![BerCount::default(); points];
+    for (unit, count) in counts.iter().enumerate() {
+        results[unit / spec.trials].add(count);
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel() -> ChannelParams {
+        ChannelParams {
+            snr_db: 30.0,
+            carrier_freq_hz: 2400.0,
+            noise_bandwidth_hz: 0.0,
+            ..ChannelParams::default()
+        }
+    }
+
+    fn spec() -> SweepSpec {
+        SweepSpec {
+            constellations: vec![ConstellationType::Qpsk, ConstellationType::Psk8],
+            snr_db: vec![4.0, 30.0],
+            doppler_hz: vec![0.0, 1.0],
+            delay_samples: vec![0],
+            channel: channel(),
+            trials: 2,
+            symbols: 300,
+            symbol_rate: 2400,
+            carrier_freq: 2400.0,
+            seed: 11,
+        }
+    }
+
+    fn run(spec: &SweepSpec, threads: usize) -> Vec<BerCount> {
+        run_sweep(spec, threads, &AtomicBool::new(false), Duration::from_millis(1), |_, _| {}).unwrap()
+    }
+
+    #[test]
+    fn test_grid_order() {
+        let spec = SweepSpec { delay_samples: vec![0, 8, 16], ..spec() };
+        assert_eq!(spec.len(), 24);
+        let points: Vec<SweepPoint> = (0..spec.len()).map(|i| spec.point(i)).collect();
+        assert_eq!(
+            points[0],
+            SweepPoint { constellation: ConstellationType::Qpsk, snr_db: 4.0, doppler_hz: 0.0, delay_samples: 0 }
+        );
+        assert_eq!(points[1].delay_samples, 8);
+        assert_eq!(points[3].doppler_hz, 1.0);
+        assert_eq!(points[6].snr_db, 30.0);
+        assert_eq!(
+            points[23],
+            SweepPoint { constellation: ConstellationType::Psk8, snr_db: 30.0, doppler_hz: 1.0, delay_samples: 16 }
+        );
+    }
+
+    #[test]
+    fn test_sweep_is_deterministic_across_threads() {
+        let spec = spec();
+        let serial = run(&spec, 1);
+        assert_eq!(serial.len(), 8);
+        assert_eq!(run(&spec, 1), serial);
+        assert_eq!(run(&spec, 3), serial);
+        assert_eq!(run(&spec, 16), serial);
+
+        // A different sweep seed draws different noise
+        assert_ne!(run(&SweepSpec { seed: 12, ..spec.clone() }, 3), serial);
+
+        for (i, count) in serial.iter().enumerate() {
+            let point = spec.point(i);
+            assert_eq!(count.symbols, 600, "{point:?}");
+            // Static and clean: every trial found, no errors
+            if point.snr_db == 30.0 && point.doppler_hz == 0.0 {
+                assert_eq!(*count, BerCount { symbols: 600, ..BerCount::default() }, "{point:?}");
+            }
+            // 4 dB is well into errors for both
+            if point.snr_db == 4.0 && point.doppler_hz == 0.0 {
+                assert!(count.symbol_errors > 30, "{point:?}: {count:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_counts_are_per_trial_sums() {
+        let spec = SweepSpec { constellations: vec![ConstellationType::Psk8], snr_db: vec![8.0], trials: 3, ..spec() };
+        let swept = run(&spec, 2);
+        for (p, count) in swept.iter().enumerate() {
+            let mut sum = BerCount::default();
+            for t in 0..spec.trials {
+                let unit = (p * spec.trials + t) as u64;
+                sum.add(&run_ber_trial(&spec, &spec.point(p), trial_seed(spec.seed, unit)).unwrap());
+            }
+            assert_eq!(*count, sum);
+        }
+    }
+
+    #[test]
+    fn test_abort() {
+        let spec = SweepSpec { trials: 20, ..spec() };
+        let total = spec.len() * spec.trials;
+
+        // Aborted before it starts, nothing runs
+        let mut reports = Vec::new();
+        let aborted = AtomicBool::new(true);
+        let result = run_sweep(&spec, 2, &aborted, Duration::from_millis(1), |done, total| reports.push((done, total)));
+        assert_eq!(result, Err("aborted"));
+        assert!(reports.iter().all(|&(done, _)| done == 0), "{reports:?}");
+
+        // Aborted from the first progress report, it stops short
+        let abort = AtomicBool::new(false);
+        let mut last = 0;
+        let result = run_sweep(&spec, 1, &abort, Duration::from_millis(1), |done, _| {
+            abort.store(true, Ordering::Relaxed);
+            last = done;
+        });
+        assert_eq!(result, Err("aborted"));
+        assert!(last < total, "{last} of {total}");
+    }
+
+    #[test]
+    fn test_progress_reaches_total() {
+        let spec = SweepSpec { constellations: vec![ConstellationType::Qpsk], ..spec() };
+        let mut reports = Vec::new();
+        let result = run_sweep(&spec, 2, &AtomicBool::new(false), Duration::from_millis(1), |done, total| {
+            reports.push((done, total))
+        });
+        assert!(result.is_ok());
+        assert_eq!(reports.last(), Some(&(8, 8)));
+        assert!(reports.windows(2).all(|w| w[0].0 <= w[1].0), "{reports:?}");
+    }
+
+    #[test]
+    fn test_invalid_sweeps_rejected() {
+        let abort = AtomicBool::new(false);
+        let rejected = |spec: &SweepSpec| run_sweep(spec, 2, &abort, Duration::from_millis(1), |_, _| {}).unwrap_err();
+        assert_eq!(rejected(&SweepSpec { snr_db: vec![], ..spec() }), "empty_sweep");
+        assert_eq!(rejected(&SweepSpec { trials: 0, ..spec() }), "empty_sweep");
+        assert_eq!(rejected(&SweepSpec { symbol_rate: 2500, ..spec() }), "invalid_symbol_rate");
+    }
+}
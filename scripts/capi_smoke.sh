@@ -0,0 +1,42 @@
+#!/bin/bash
+set -euo pipefail
+
+# Build the C ABI of channel_physics and phy_modem (feature `capi`, no NIF),
+# regenerate their headers if cbindgen is installed, then compile and run
+# scripts/capi_smoke.c against the shared libraries.
+# Run from the repo root: ./scripts/capi_smoke.sh
+
+ROOT="$(cd "$(dirname "$0")/.." && pwd)"
+CHANNEL="$ROOT/apps/minutemodem_simnet/native/channel_physics"
+PHY="$ROOT/apps/minutemodem_core/native/phy_modem"
+BUILD_DIR="$ROOT/build/capi"
+CC="${CC:-cc}"
+
+mkdir -p "$BUILD_DIR"
+
+for crate in "$CHANNEL" "$PHY"; do
+  name="$(basename "$crate")"
+  echo "==> Building $name (capi)..."
+  cargo build --release --manifest-path "$crate/Cargo.toml" --no-default-features --features capi
+  # Stage both libraries in one directory for linking
+  cp "$crate/target/release/lib$name.so" "$BUILD_DIR/" 2>/dev/null \
+    || cp "$crate/target/release/lib$name.dylib" "$BUILD_DIR/"
+
+  if command -v cbindgen >/dev/null 2>&1; then
+    echo "==> Regenerating include/$name.h..."
+    (cd "$crate" && cbindgen --config cbindgen.toml --crate "$name" --output "include/$name.h")
+  else
+    echo "==> cbindgen not found, using the checked-in include/$name.h"
+  fi
+done
+
+echo "==> Compiling smoke test..."
+"$CC" -std=c99 -Wall -Wextra -Werror -D_DEFAULT_SOURCE \
+  -I "$CHANNEL/include" -I "$PHY/include" \
+  "$ROOT/scripts/capi_smoke.c" \
+  -L "$BUILD_DIR" -lchannel_physics -lphy_modem -lm \
+  -Wl,-rpath,"$BUILD_DIR" \
+  -o "$BUILD_DIR/capi_smoke"
+
+echo "==> Running smoke test..."
+"$BUILD_DIR/capi_smoke"